@@ -0,0 +1,83 @@
+// acl.ts - Access labels on documents and the metadata filter that scopes queries to them
+import { AccessDeniedError, InvalidInputError } from './errors.js';
+import { DocumentMetadata } from './metadata.js';
+
+// Types
+export interface AclPolicy {
+  enforce: boolean;  // Refuse queries that name no labels instead of searching every document
+  labels?: string[];  // Used when a query passes none, e.g. the labels of the tenant a server runs for
+}
+
+// Metadata key holding the labels of a document, as a string list
+export const ACL_METADATA_KEY = 'acl';
+
+const LABEL_PATTERN = /^[A-Za-z0-9_][A-Za-z0-9_.:@-]*$/;
+
+// Parses "team-a,team-b" (or a list of such values) into unique labels
+export function parseAclLabels(value: string | string[]): string[] {
+  const labels = (Array.isArray(value) ? value : [value])
+    .flatMap(item => item.split(','))
+    .map(label => label.trim())
+    .filter(Boolean);
+  for (const label of labels) {
+    if (!LABEL_PATTERN.test(label)) {
+      throw new InvalidInputError(`Invalid access label "${label}": use letters, digits and _ . : @ -`);
+    }
+  }
+  return [...new Set(labels)];
+}
+
+// Adds the labels as a string list, the only value type the "has" operator matches
+export function withAclLabels(metadata: DocumentMetadata, labels: string[] | undefined): DocumentMetadata {
+  if (!labels || labels.length === 0) return metadata;
+  return { ...metadata, [ACL_METADATA_KEY]: labels };
+}
+
+// The labels a query runs with: its own, else the policy's. Without either the
+// query is unscoped, which ACL mode refuses.
+export function resolveAclLabels(labels: string[] | undefined, policy?: AclPolicy): string[] | undefined {
+  const resolved = labels ?? policy?.labels;
+  if (resolved && resolved.length === 0) {
+    throw new AccessDeniedError('No access labels given; the query would match no documents');
+  }
+  if (!resolved && policy?.enforce) {
+    throw new AccessDeniedError('ACL mode is on: pass the access labels of the caller (--acl-labels or aclLabels)');
+  }
+  return resolved;
+}
+
+// AIP-160 filter matching documents that carry any of the labels
+export function aclFilter(labels: string[]): string {
+  return labels.map(label => `${ACL_METADATA_KEY} : "${label}"`).join(' OR ');
+}
+
+// Combines the label filter with a metadata filter. A filter whose parentheses do
+// not balance could close the label group and is rejected.
+export function scopeFilter(metadataFilter: string | undefined, labels: string[] | undefined): string | undefined {
+  if (!labels) return metadataFilter;
+  if (!metadataFilter) return aclFilter(labels);
+  if (!balanced(metadataFilter)) {
+    throw new InvalidInputError(`Unbalanced parentheses in metadata filter: ${metadataFilter}`);
+  }
+  return `(${aclFilter(labels)}) AND (${metadataFilter})`;
+}
+
+function balanced(filter: string): boolean {
+  let depth = 0;
+  let quoted = false;
+  for (let i = 0; i < filter.length; i++) {
+    const char = filter[i];
+    if (quoted) {
+      if (char === '\\') i++;
+      else if (char === '"') quoted = false;
+    } else if (char === '"') {
+      quoted = true;
+    } else if (char === '(') {
+      depth++;
+    } else if (char === ')' && --depth < 0) {
+      return false;
+    }
+  }
+  return depth === 0 && !quoted;
+}
+
@@ -0,0 +1,92 @@
+// answer-file.ts - Answers streamed into a file that is renamed into place once complete, with a metadata sidecar
+import * as fs from 'fs';
+import * as path from 'path';
+import { AnswerReport } from './output.js';
+import { queryCost } from './cost.js';
+import { writeFileAtomic } from './shutdown.js';
+
+// Types
+// Written next to the answer file, so the citations are kept with the answer
+export interface AnswerSidecar {
+  file: string;  // Name of the answer file, in the same directory
+  question: string;
+  stores: string[];
+  model?: string;
+  citations: AnswerReport['citations'];
+  usage?: AnswerReport['usage'];
+  estimatedCostUsd?: number;
+  confidence?: AnswerReport['confidence'];
+  language?: AnswerReport['language'];
+  latencyMs: number;  // From sending the question to the end of the answer
+  firstTokenMs?: number;  // Until the first text arrived
+  completedAt: string;
+}
+
+export const SIDECAR_SUFFIX = '.meta.json';
+
+// answer.md → answer.meta.json
+export function sidecarPath(filePath: string): string {
+  const extension = path.extname(filePath);
+  return `${extension ? filePath.slice(0, -extension.length) : filePath}${SIDECAR_SUFFIX}`;
+}
+
+export function answerSidecar(filePath: string, report: AnswerReport, timing: { latencyMs: number; firstTokenMs?: number }): AnswerSidecar {
+  return {
+    file: path.basename(filePath),
+    question: report.question,
+    stores: report.stores,
+    model: report.model,
+    citations: report.citations,
+    usage: report.usage,
+    estimatedCostUsd: report.model && report.usage ? queryCost(report.model, report.usage) : undefined,
+    confidence: report.confidence,
+    language: report.language,
+    latencyMs: timing.latencyMs,
+    firstTokenMs: timing.firstTokenMs,
+    completedAt: new Date().toISOString(),
+  };
+}
+
+// The answer is written to "<file>.<pid>.tmp" as it arrives and only replaces the file
+// on commit, so an interrupted or failed run leaves the previous answer untouched
+export class AnswerFileWriter {
+  private readonly fd: number;
+  private written = '';
+  private closed = false;
+
+  private constructor(readonly filePath: string, readonly tempPath: string) {
+    this.fd = fs.openSync(tempPath, 'w');
+  }
+
+  static open(filePath: string): AnswerFileWriter {
+    fs.mkdirSync(path.dirname(filePath), { recursive: true });
+    return new AnswerFileWriter(filePath, `${filePath}.${process.pid}.tmp`);
+  }
+
+  write(text: string): void {
+    fs.writeSync(this.fd, text);
+    this.written += text;
+  }
+
+  // content replaces what was streamed when it differs, e.g. after post-processing.
+  // The answer is renamed into place before the sidecar, which marks it as complete.
+  commit(content: string, sidecar: AnswerSidecar): void {
+    if (content !== this.written) {
+      fs.ftruncateSync(this.fd, 0);
+      fs.writeSync(this.fd, content, 0);
+    }
+    fs.fsyncSync(this.fd);
+    fs.closeSync(this.fd);
+    this.closed = true;
+    fs.renameSync(this.tempPath, this.filePath);
+    writeFileAtomic(sidecarPath(this.filePath), `${JSON.stringify(sidecar, null, 2)}\n`);
+  }
+
+  // Drops the partial answer; safe to call after commit
+  abort(): void {
+    if (this.closed) return;
+    this.closed = true;
+    fs.closeSync(this.fd);
+    fs.rmSync(this.tempPath, { force: true });
+  }
+}
@@ -0,0 +1,138 @@
+// answer-language.ts - Answers in a requested language: the instruction, detection of answers in another language, and translation
+import { FileSearchClient } from './client.js';
+import { Citation } from './citations.js';
+import { InvalidInputError } from './errors.js';
+import { detectLanguage } from './language.js';
+import { Semaphore } from './pipeline.js';
+import { AnswerReport } from './output.js';
+import { TranslateTransform, applyTransforms } from './postprocess.js';
+
+// Types
+export interface AnswerLanguage {
+  code?: string;  // ISO 639-1, when the language is one of ANSWER_LANGUAGES; only these are checked
+  name: string;  // As written in the instruction, e.g. "Japanese"
+}
+
+// How the answer's language compared to the requested one
+export interface AnswerLanguageReport {
+  requested: string;  // Code, or the name of a language that cannot be checked
+  detected?: string;  // Code of the answer as received; undefined when it was too short to tell
+  translated: boolean;  // The answer was in another language and was translated
+  snippetsTranslated?: number;  // Cited snippets translated, with translateSnippets
+}
+
+export interface AnswerLanguageOptions {
+  translateSnippets?: boolean;  // Also translate the text of the cited chunks
+  model?: string;  // For the translations
+}
+
+// The languages detectLanguage tells apart, so an answer in another one can be noticed
+export const ANSWER_LANGUAGES: { [code: string]: string } = {
+  en: 'English',
+  ja: 'Japanese',
+  zh: 'Chinese',
+  ko: 'Korean',
+  de: 'German',
+  fr: 'French',
+  es: 'Spanish',
+  it: 'Italian',
+  pt: 'Portuguese',
+  nl: 'Dutch',
+  sv: 'Swedish',
+  pl: 'Polish',
+  ru: 'Russian',
+  uk: 'Ukrainian',
+  ar: 'Arabic',
+  fa: 'Persian',
+  he: 'Hebrew',
+  el: 'Greek',
+  th: 'Thai',
+  hi: 'Hindi',
+};
+
+const SNIPPET_CONCURRENCY = 4;
+
+// A code ("ja", "ja-JP") or a name ("Japanese"). Names of other languages are kept as
+// written and only instructed; a code that is not known is rejected.
+export function parseAnswerLanguage(value: string): AnswerLanguage {
+  const trimmed = value.trim();
+  if (!trimmed) throw new InvalidInputError('The answer language must not be empty');
+  const code = trimmed.toLowerCase().split(/[-_]/)[0];
+  if (ANSWER_LANGUAGES[code] && /^[a-z]{2}([-_][a-z0-9]{2,4})?$/i.test(trimmed)) return { code, name: ANSWER_LANGUAGES[code] };
+  const named = Object.entries(ANSWER_LANGUAGES).find(([, name]) => name.toLowerCase() === trimmed.toLowerCase());
+  if (named) return { code: named[0], name: named[1] };
+  if (/^[a-z]{2,3}([-_][a-z0-9]{2,4})?$/i.test(trimmed)) {
+    throw new InvalidInputError(`Unknown answer language code "${value}" (expected one of: ${Object.keys(ANSWER_LANGUAGES).join(', ')}, or a language name)`);
+  }
+  return { name: trimmed };
+}
+
+export function answerLanguageInstruction(language: AnswerLanguage): string {
+  return `Always answer in ${language.name}, even when the question or the retrieved documents are in another language. `
+    + `Translate what you take from the documents; keep code, identifiers, file names and quoted error messages unchanged.`;
+}
+
+// Code blocks and inline code are left out, as they are not translated
+function proseOf(answer: string): string {
+  return answer.replace(/^(`{3,}|~{3,})[^\n]*\n[\s\S]*?^\1[ \t]*$/gm, '').replace(/`[^`\n]*`/g, '');
+}
+
+// The language the answer is in when it is not the requested one; undefined when it
+// matches, is too short to tell, or the language cannot be checked
+export function answerLanguageMismatch(answer: string, language: AnswerLanguage): string | undefined {
+  if (!language.code) return undefined;
+  const detected = detectLanguage(proseOf(answer));
+  return detected && detected !== language.code ? detected : undefined;
+}
+
+// Brings a report into the requested language: an answer the model wrote in another
+// language despite the instruction is translated, and with translateSnippets so are
+// the cited chunks (their original text is kept as originalChunkText)
+export async function enforceAnswerLanguage(
+  client: FileSearchClient,
+  report: AnswerReport,
+  language: AnswerLanguage,
+  options: AnswerLanguageOptions = {}
+): Promise<AnswerReport> {
+  const detected = language.code ? detectLanguage(proseOf(report.answer)) : undefined;
+  const mismatch = detected !== undefined && detected !== language.code;
+  let translated = report;
+  if (mismatch) {
+    console.error(`⚠️  The answer is in ${ANSWER_LANGUAGES[detected] || detected}, not ${language.name}: translating it`);
+    const transform = new TranslateTransform(language.code || language.name, language.name);
+    translated = await applyTransforms(report, [transform], { question: report.question, client, model: options.model });
+  }
+  let snippetsTranslated: number | undefined;
+  if (options.translateSnippets) {
+    const citations = await translateCitations(client, translated.citations, language, options.model);
+    snippetsTranslated = citations.filter(citation => citation.originalChunkText !== undefined).length;
+    translated = { ...translated, citations };
+  }
+  return {
+    ...translated,
+    language: {
+      requested: language.code || language.name,
+      ...(detected ? { detected } : {}),
+      translated: mismatch,
+      ...(snippetsTranslated !== undefined ? { snippetsTranslated } : {}),
+    },
+  };
+}
+
+// Chunks already in the language are kept; each distinct text is translated once
+export async function translateCitations(client: FileSearchClient, citations: Citation[], language: AnswerLanguage, model?: string): Promise<Citation[]> {
+  const transform = new TranslateTransform(language.code || language.name, language.name);
+  const semaphore = new Semaphore(SNIPPET_CONCURRENCY);
+  const translations = new Map<string, Promise<string>>();
+  for (const citation of citations) {
+    const text = citation.chunkText;
+    if (!text.trim() || translations.has(text) || (language.code && detectLanguage(text) === language.code)) continue;
+    translations.set(text, semaphore.run(async () => transform.apply(text, { question: '', client, model })));
+  }
+  const results = new Map<string, string>();
+  for (const [text, translation] of translations) results.set(text, await translation);
+  return citations.map(citation => {
+    const translation = results.get(citation.chunkText);
+    return translation !== undefined ? { ...citation, chunkText: translation, originalChunkText: citation.chunkText } : citation;
+  });
+}
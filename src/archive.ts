@@ -0,0 +1,223 @@
+// archive.ts - Export a store to a local archive and restore it into a new store
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { createHash } from 'crypto';
+import { FileSearchClient, UploadResult } from './client.js';
+import { DocumentInfo, toDocumentInfo } from './documents.js';
+import { StoreInfo } from './store.js';
+import { ChunkingPolicy } from './chunking.js';
+import { DirectoryIngester, IngestSummary } from './ingest.js';
+import { SyncManifest } from './sync.js';
+import { InvalidInputError } from './errors.js';
+import { readTarGz, writeTarGz } from './tar.js';
+
+// Types
+export interface ArchivedFile {
+  path: string;  // Relative path, also the display name of its documents
+  sha256: string;
+  size: number;
+}
+
+export interface ArchiveManifest {
+  version: number;
+  exportedAt: string;
+  store: StoreInfo;
+  project?: { [key: string]: unknown };  // The projects.json entry, when the store belongs to a project
+  documents: DocumentInfo[];
+  files: ArchivedFile[];  // Source files included with includeContent
+}
+
+export interface StoreArchive {
+  manifest: ArchiveManifest;
+  content: Map<string, Buffer>;  // Relative path -> file content
+}
+
+export interface ExportOptions {
+  project?: { [key: string]: unknown };
+  rootDir?: string;  // Where the source files of the documents live
+  includeContent?: boolean;
+  chunking?: ChunkingPolicy;
+}
+
+export interface RestoreOptions {
+  displayName?: string;  // Defaults to the display name of the exported store
+  ingester?: DirectoryIngester;  // Applies the MIME, chunking and metadata settings of the project
+  rootDir?: string;  // Local checkout of the project; unchanged files are recorded in its sync manifest
+}
+
+export interface RestoreSummary {
+  storeName: string;
+  upload: IngestSummary;
+  missingDocuments: string[];  // Documents listed in the archive without content to restore them from
+}
+
+export const ARCHIVE_VERSION = 1;
+export const ARCHIVE_MANIFEST = 'archive.json';
+const CONTENT_DIR = 'content';
+
+function sha256(data: Buffer): string {
+  return createHash('sha256').update(data).digest('hex');
+}
+
+// "src/lib.rs#Parser::parse" was uploaded from "src/lib.rs"
+function sourceOf(displayName: string): string {
+  return displayName.split('#')[0];
+}
+
+function isTarGz(archivePath: string): boolean {
+  return /\.(tar\.gz|tgz)$/i.test(archivePath);
+}
+
+// Archived paths come from display names; refuse anything that would escape the target directory
+function safeRelativePath(relativePath: string): string {
+  const normalized = path.posix.normalize(relativePath);
+  if (!normalized || normalized.startsWith('../') || normalized === '..' || path.posix.isAbsolute(normalized)) {
+    throw new InvalidInputError(`Unsafe path in archive: ${relativePath}`);
+  }
+  return normalized;
+}
+
+// The API does not return document content, so content can only be taken from
+// the local source files the documents were uploaded from
+export async function exportStore(client: FileSearchClient, storeName: string, options: ExportOptions = {}): Promise<StoreArchive> {
+  if (options.includeContent && !options.rootDir) {
+    throw new InvalidInputError('includeContent requires the directory the documents were uploaded from');
+  }
+
+  const store = await client.stores.getStore(storeName);
+  const documents = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document, options.chunking));
+  const content = new Map<string, Buffer>();
+  const files: ArchivedFile[] = [];
+
+  if (options.includeContent && options.rootDir) {
+    const sources = [...new Set(documents.map(document => sourceOf(document.displayName)).filter(Boolean))];
+    for (const source of sources) {
+      const relativePath = safeRelativePath(source);
+      try {
+        const data = fs.readFileSync(path.join(options.rootDir, relativePath));
+        content.set(relativePath, data);
+        files.push({ path: relativePath, sha256: sha256(data), size: data.length });
+      } catch (error) {
+        console.error(`⚠️  Not archived (source file not found): ${relativePath}`);
+      }
+    }
+  }
+
+  return {
+    manifest: {
+      version: ARCHIVE_VERSION,
+      exportedAt: new Date().toISOString(),
+      store,
+      project: options.project,
+      documents,
+      files,
+    },
+    content,
+  };
+}
+
+// Writes a .tar.gz/.tgz file, or a directory with archive.json and content/ otherwise
+export function writeArchive(archive: StoreArchive, archivePath: string): void {
+  const manifest = Buffer.from(JSON.stringify(archive.manifest, null, 2));
+  if (isTarGz(archivePath)) {
+    writeTarGz(archivePath, [
+      { name: ARCHIVE_MANIFEST, data: manifest },
+      ...[...archive.content].map(([relativePath, data]) => ({ name: `${CONTENT_DIR}/${relativePath}`, data })),
+    ]);
+    return;
+  }
+
+  fs.mkdirSync(archivePath, { recursive: true });
+  fs.writeFileSync(path.join(archivePath, ARCHIVE_MANIFEST), manifest);
+  for (const [relativePath, data] of archive.content) {
+    const target = path.join(archivePath, CONTENT_DIR, relativePath);
+    fs.mkdirSync(path.dirname(target), { recursive: true });
+    fs.writeFileSync(target, data);
+  }
+}
+
+export function readArchive(archivePath: string): StoreArchive {
+  let manifestData: Buffer | undefined;
+  const raw = new Map<string, Buffer>();
+
+  if (isTarGz(archivePath)) {
+    for (const entry of readTarGz(archivePath)) {
+      if (entry.name === ARCHIVE_MANIFEST) {
+        manifestData = entry.data;
+      } else if (entry.name.startsWith(`${CONTENT_DIR}/`)) {
+        raw.set(entry.name.slice(CONTENT_DIR.length + 1), entry.data);
+      }
+    }
+  } else {
+    const manifestPath = path.join(archivePath, ARCHIVE_MANIFEST);
+    if (fs.existsSync(manifestPath)) manifestData = fs.readFileSync(manifestPath);
+  }
+  if (!manifestData) {
+    throw new InvalidInputError(`Not a store archive (missing ${ARCHIVE_MANIFEST}): ${archivePath}`);
+  }
+
+  const manifest = JSON.parse(manifestData.toString('utf8')) as ArchiveManifest;
+  if (manifest.version !== ARCHIVE_VERSION) {
+    throw new InvalidInputError(`Unsupported archive version ${manifest.version} (expected ${ARCHIVE_VERSION})`);
+  }
+
+  const content = new Map<string, Buffer>();
+  for (const file of manifest.files) {
+    const relativePath = safeRelativePath(file.path);
+    const data = isTarGz(archivePath) ? raw.get(relativePath) : fs.readFileSync(path.join(archivePath, CONTENT_DIR, relativePath));
+    if (!data || sha256(data) !== file.sha256) {
+      throw new InvalidInputError(`Archive content is missing or corrupt: ${relativePath}`);
+    }
+    content.set(relativePath, data);
+  }
+  return { manifest, content };
+}
+
+// Creates a new store and uploads the archived files into it. Documents are
+// re-indexed from the content, so the new store gets new document names.
+export async function restoreArchive(client: FileSearchClient, archive: StoreArchive, options: RestoreOptions = {}): Promise<RestoreSummary> {
+  const { manifest, content } = archive;
+  const missingDocuments = manifest.documents
+    .filter(document => !content.has(sourceOf(document.displayName)))
+    .map(document => document.displayName || document.name);
+
+  const storeName = await client.createStore(options.displayName || manifest.store.displayName || 'restored-store');
+  console.error(`📦 Restoring ${content.size} files into ${storeName}`);
+
+  // Stage the content under its original relative paths so display names are preserved
+  const stagingDir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-restore-'));
+  try {
+    const files: string[] = [];
+    for (const [relativePath, data] of content) {
+      const target = path.join(stagingDir, relativePath);
+      fs.mkdirSync(path.dirname(target), { recursive: true });
+      fs.writeFileSync(target, data);
+      files.push(target);
+    }
+
+    const manifestByPath = new Map(manifest.files.map(file => [file.path, file]));
+    const sync = options.rootDir ? SyncManifest.load(options.rootDir, storeName) : undefined;
+    const onFileUploaded = (filePath: string, result: UploadResult): void => {
+      if (!sync || !options.rootDir) return;
+      const relativePath = path.relative(stagingDir, filePath).split(path.sep).join('/');
+      const localPath = path.join(options.rootDir, relativePath);
+      // Only files identical to the archived version count as in sync
+      if (fs.existsSync(localPath) && sha256(fs.readFileSync(localPath)) === manifestByPath.get(relativePath)?.sha256) {
+        sync.record(options.rootDir, localPath, result);
+      }
+    };
+
+    const upload = await (options.ingester || new DirectoryIngester()).upload(
+      client,
+      storeName,
+      stagingDir,
+      files,
+      undefined,
+      onFileUploaded
+    );
+    return { storeName, upload, missingDocuments };
+  } finally {
+    fs.rmSync(stagingDir, { recursive: true, force: true });
+  }
+}
@@ -0,0 +1,187 @@
+// ask-all.ts - Several related questions answered together, optionally from one shared retrieval pass
+import { GroundingMetadata } from '@google/genai';
+import { FileSearchClient, QueryOptions } from './client.js';
+import { Citation, extractCitations } from './citations.js';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { InvalidInputError } from './errors.js';
+import { Semaphore } from './pipeline.js';
+import { checkGrounding, formatNoGroundedAnswer } from './grounding.js';
+
+// Types
+export interface QuestionAnswer {
+  question: string;
+  answer: string;
+  citations: number[];  // Footnote numbers of the MultiAnswer citations supporting this answer
+  error?: string;
+}
+
+export interface MultiAnswer {
+  answer: string;  // Every answer under a "### <n>. <question>" heading
+  answers: QuestionAnswer[];
+  citations: Citation[];
+  groundingMetadata?: GroundingMetadata;  // Offsets relative to answer; only set with shared retrieval
+  usage?: TokenUsage;
+  requests: number;
+}
+
+export interface AskAllOptions extends QueryOptions {
+  sharedRetrieval?: boolean;  // One query for all questions instead of one per question
+  concurrency?: number;  // Independent queries in flight at once (default: 5)
+  minConfidence?: number;  // For groundedOnly, see checkGrounding
+}
+
+// Long lists dilute the retrieval pass; ask them in several calls instead
+export const MAX_SHARED_QUESTIONS = 20;
+
+function heading(index: number, question: string): string {
+  return `### ${index + 1}. ${question.replace(/\s+/g, ' ').trim()}`;
+}
+
+function sharedPrompt(questions: string[]): string {
+  return [
+    'Answer each of the following related questions from the documents.',
+    'Write the answers in order, each under its own heading of the form "### <number>. <question>", and answer every question, if only to say that the documents do not cover it.',
+    '',
+    ...questions.map((question, i) => `${i + 1}. ${question.replace(/\s+/g, ' ').trim()}`),
+  ].join('\n');
+}
+
+// Character ranges of the answer sections, found by their numbered headings
+function sectionRanges(text: string, count: number): ({ start: number; end: number; body: string } | undefined)[] {
+  const headings = [...text.matchAll(/^#{1,6}\s*\**(\d+)[.):]/gm)]
+    .map(match => ({ number: Number(match[1]), start: match.index || 0, bodyStart: text.indexOf('\n', match.index || 0) }));
+  const ranges: ({ start: number; end: number; body: string } | undefined)[] = new Array(count).fill(undefined);
+  headings.forEach((current, i) => {
+    const end = i + 1 < headings.length ? headings[i + 1].start : text.length;
+    if (current.number < 1 || current.number > count || ranges[current.number - 1]) return;
+    const body = current.bodyStart === -1 || current.bodyStart > end ? '' : text.slice(current.bodyStart, end).trim();
+    ranges[current.number - 1] = { start: current.start, end, body };
+  });
+  return ranges;
+}
+
+// Which citations support each section: a support belongs to the section its segment starts in
+function citationsBySection(
+  text: string,
+  metadata: GroundingMetadata | undefined,
+  ranges: ({ start: number; end: number } | undefined)[]
+): number[][] {
+  const bySection = ranges.map(() => new Set<number>());
+  if (!metadata) return bySection.map(() => []);
+  const numbers = new Map<number, number>();
+  (metadata.groundingChunks || []).forEach((chunk, i) => {
+    if (chunk.retrievedContext?.text) numbers.set(i, numbers.size + 1);
+  });
+  const encoded = Buffer.from(text, 'utf8');
+  for (const support of metadata.groundingSupports || []) {
+    const start = encoded.subarray(0, support.segment?.startIndex || 0).toString('utf8').length;
+    const section = ranges.findIndex(range => range !== undefined && start >= range.start && start < range.end);
+    if (section === -1) continue;
+    for (const chunkIndex of support.groundingChunkIndices || []) {
+      const number = numbers.get(chunkIndex);
+      if (number !== undefined) bySection[section].add(number);
+    }
+  }
+  return bySection.map(section => [...section].sort((a, b) => a - b));
+}
+
+async function askShared(client: FileSearchClient, storeNames: string[], questions: string[], options: AskAllOptions): Promise<MultiAnswer> {
+  const response = await client.query(storeNames, sharedPrompt(questions), options);
+  const text = response.text || '';
+  const groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+  const usage = toTokenUsage(response.usageMetadata);
+
+  const rejected = options.groundedOnly ? checkGrounding(text, groundingMetadata, options.minConfidence) : undefined;
+  if (rejected) {
+    const message = formatNoGroundedAnswer(rejected);
+    return {
+      answer: questions.map((question, i) => `${heading(i, question)}\n\n_${message}_`).join('\n\n'),
+      answers: questions.map(question => ({ question, answer: '', citations: [], error: message })),
+      citations: [],
+      usage,
+      requests: 1,
+    };
+  }
+
+  const ranges = sectionRanges(text, questions.length);
+  const sectionCitations = citationsBySection(text, groundingMetadata, ranges);
+  const answers = questions.map((question, i) => {
+    const range = ranges[i];
+    return range
+      ? { question, answer: range.body, citations: sectionCitations[i] }
+      : { question, answer: '', citations: [], error: 'The answer has no section for this question' };
+  });
+  return { answer: text, answers, citations: extractCitations(groundingMetadata, text), groundingMetadata, usage, requests: 1 };
+}
+
+async function askIndependently(client: FileSearchClient, storeNames: string[], questions: string[], options: AskAllOptions): Promise<MultiAnswer> {
+  const semaphore = new Semaphore(options.concurrency || 5);
+  const responses = await Promise.all(questions.map(async question => {
+    try {
+      return { response: await semaphore.run(() => client.query(storeNames, question, options)) };
+    } catch (error) {
+      return { error: error as Error };
+    }
+  }));
+  if (responses.every(r => r.error)) throw responses[0].error;
+
+  const sections: string[] = [];
+  const answers: QuestionAnswer[] = [];
+  const citations: Citation[] = [];
+  let usage: TokenUsage | undefined;
+  responses.forEach(({ response, error }, i) => {
+    const question = questions[i];
+    if (!response) {
+      const message = error?.message || 'Unknown error';
+      sections.push(`${heading(i, question)}\n\n_Query failed: ${message}_`);
+      answers.push({ question, answer: '', citations: [], error: message });
+      return;
+    }
+    const text = response.text || '';
+    const metadata = response.candidates?.[0]?.groundingMetadata;
+    const questionUsage = toTokenUsage(response.usageMetadata);
+    if (questionUsage) {
+      usage = {
+        promptTokens: (usage?.promptTokens || 0) + questionUsage.promptTokens,
+        responseTokens: (usage?.responseTokens || 0) + questionUsage.responseTokens,
+        totalTokens: (usage?.totalTokens || 0) + questionUsage.totalTokens,
+      };
+    }
+    const rejected = options.groundedOnly ? checkGrounding(text, metadata, options.minConfidence) : undefined;
+    if (rejected) {
+      const message = formatNoGroundedAnswer(rejected);
+      sections.push(`${heading(i, question)}\n\n_${message}_`);
+      answers.push({ question, answer: '', citations: [], error: message });
+      return;
+    }
+    // Numbered across all questions; offsets are dropped since they point into the single answers
+    const numbers = extractCitations(metadata, text).map(citation => {
+      citations.push({ ...citation, index: citations.length + 1, startIndex: undefined, endIndex: undefined });
+      return citations.length;
+    });
+    sections.push(`${heading(i, question)}\n\n${text}`);
+    answers.push({ question, answer: text, citations: numbers });
+  });
+  return { answer: sections.join('\n\n'), answers, citations, usage, requests: questions.length };
+}
+
+// Answers related questions in one go. With sharedRetrieval a single query retrieves
+// the passages once and answers every question from them, which costs one prompt
+// instead of one per question; citations are shared and mapped back to the answers
+// that use them. Otherwise each question is queried on its own, concurrently.
+export async function askAll(
+  client: FileSearchClient,
+  storeNames: string[],
+  questions: string[],
+  options: AskAllOptions = {}
+): Promise<MultiAnswer> {
+  const asked = questions.map(question => question.trim()).filter(Boolean);
+  if (asked.length === 0) throw new InvalidInputError('At least one question is required');
+  if (options.responseSchema) throw new InvalidInputError('A response schema cannot be combined with several questions');
+  if (options.sharedRetrieval && asked.length > MAX_SHARED_QUESTIONS) {
+    throw new InvalidInputError(`Shared retrieval takes up to ${MAX_SHARED_QUESTIONS} questions, got ${asked.length}`);
+  }
+  return options.sharedRetrieval
+    ? askShared(client, storeNames, asked, options)
+    : askIndependently(client, storeNames, asked, options);
+}
@@ -0,0 +1,114 @@
+// attributes.ts - File system attributes (path, size, mtime, owner, depth) as document metadata
+import * as fs from 'fs';
+import { FileSearchClient } from './client.js';
+import { Citation } from './citations.js';
+import { DocumentMetadata, fromCustomMetadata } from './metadata.js';
+import { archiveMemberOf } from './expand.js';
+
+// Types
+export interface FileAttributes {
+  path: string;  // Relative path under the ingested directory
+  size?: number;  // Bytes of the file on disk
+  mtime?: number;  // Last modification, in seconds since the epoch
+  owner?: string;  // User name of the owner, or its uid when it has none
+  depth?: number;  // Directories between the ingested directory and the file
+}
+
+// Each store's documents are listed at most once in this time per client
+const LISTING_TTL_MS = 5 * 60 * 1000;
+
+let userNames: Map<number, string> | undefined;
+const listings = new WeakMap<FileSearchClient, Map<string, { at: number; files: Promise<Map<string, FileAttributes>> }>>();
+
+function userName(uid: number): string {
+  if (!userNames) {
+    userNames = new Map();
+    try {
+      for (const line of fs.readFileSync('/etc/passwd', 'utf8').split('\n')) {
+        const [name, , id] = line.split(':');
+        if (name && id && /^\d+$/.test(id)) userNames.set(Number(id), name);
+      }
+    } catch (error) {
+      // No user database; owners are shown by uid
+    }
+  }
+  return userNames.get(uid) || String(uid);
+}
+
+// Members of archives are extracted to temporary files, so they only get path, size and depth
+export function fileAttributes(filePath: string, relativePath: string): FileAttributes {
+  const attributes: FileAttributes = { path: relativePath, depth: relativePath.split('/').length - 1 };
+  let stats: fs.Stats;
+  try {
+    stats = fs.statSync(filePath);
+  } catch (error) {
+    // Unreadable files are reported by the upload
+    return attributes;
+  }
+  attributes.size = stats.size;
+  if (!archiveMemberOf(filePath)) {
+    attributes.mtime = Math.floor(stats.mtimeMs / 1000);
+    if (process.platform !== 'win32') attributes.owner = userName(stats.uid);
+  }
+  return attributes;
+}
+
+export function fileAttributeMetadata(attributes: FileAttributes): DocumentMetadata {
+  const metadata: DocumentMetadata = {};
+  for (const [key, value] of Object.entries(attributes)) {
+    if (value !== undefined) metadata[key] = value;
+  }
+  return metadata;
+}
+
+export function attributesFromMetadata(metadata: DocumentMetadata): FileAttributes | undefined {
+  if (typeof metadata.path !== 'string') return undefined;
+  const number = (key: string) => typeof metadata[key] === 'number' ? metadata[key] as number : undefined;
+  return {
+    path: metadata.path,
+    size: number('size'),
+    mtime: number('mtime'),
+    owner: typeof metadata.owner === 'string' ? metadata.owner : undefined,
+    depth: number('depth'),
+  };
+}
+
+async function listAttributes(client: FileSearchClient, storeName: string): Promise<Map<string, FileAttributes>> {
+  const files = new Map<string, FileAttributes>();
+  for (const document of await client.listDocuments(storeName)) {
+    const attributes = attributesFromMetadata(fromCustomMetadata(document.customMetadata));
+    if (document.displayName && attributes) files.set(document.displayName, attributes);
+  }
+  return files;
+}
+
+function storeAttributes(client: FileSearchClient, storeName: string): Promise<Map<string, FileAttributes>> {
+  let stores = listings.get(client);
+  if (!stores) {
+    stores = new Map();
+    listings.set(client, stores);
+  }
+  const listing = stores.get(storeName);
+  if (listing && Date.now() - listing.at < LISTING_TTL_MS) return listing.files;
+  const files = listAttributes(client, storeName);
+  stores.set(storeName, { at: Date.now(), files });
+  files.catch(() => stores?.delete(storeName));
+  return files;
+}
+
+// Adds the file attributes of the cited documents, so that files sharing a name (three
+// config.yaml, say) can be told apart; citations of documents without them are kept as
+// they are, and so are all of them when the stores cannot be listed
+export async function annotateCitations(client: FileSearchClient, storeNames: string[], citations: Citation[]): Promise<Citation[]> {
+  if (citations.length === 0) return citations;
+  try {
+    const stores = await Promise.all(storeNames.map(storeName => storeAttributes(client, storeName)));
+    return citations.map(citation => {
+      const file = stores.map(files => files.get(citation.fileName)).find(attributes => attributes);
+      return file ? { ...citation, file } : citation;
+    });
+  } catch (error) {
+    console.error(`⚠️  Could not look up the cited files: ${(error as Error).message}`);
+    return citations;
+  }
+}
@@ -0,0 +1,175 @@
+// auth.ts - Credentials for the Gemini API: API keys, ADC and service accounts
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { GoogleAuth, GoogleAuthOptions } from 'google-auth-library';
+import { ConfigError } from './errors.js';
+import { PASSPHRASE_ENV, loadCredential } from './credentials.js';
+import { ApiKeyEntry, KEYS_ENV, KeyRing } from './keys.js';
+
+// Types
+export type AuthMethod = 'api-key' | 'adc' | 'service-account';
+
+export interface AuthSettings {
+  authMethod?: AuthMethod;  // Default: the credential chain, see createAuthProvider
+  apiKeyEnv: string;
+  apiKeyEnvs?: string[];  // Further keys, used in turn when one runs out of quota, see keys.ts
+  apiKey?: string;  // From --api-key; ends up in shell history, prefer auth login
+  credentialsFile?: string;  // Service account JSON key, required for service-account
+  profile?: string;  // Account of the stored API key
+}
+
+// Supplies credentials to the SDK client and to the raw fetch calls of resumable uploads
+export interface AuthProvider {
+  readonly method: AuthMethod;
+  clientOptions(): { apiKey?: string; googleAuthOptions?: GoogleAuthOptions };
+  requestHeaders(url: string): Promise<Record<string, string>>;
+  describe(): string;
+}
+
+export const AUTH_METHODS: AuthMethod[] = ['api-key', 'adc', 'service-account'];
+
+// cloud-platform covers Vertex AI; generative-language.retriever covers File Search on the Gemini API
+export const AUTH_SCOPES = [
+  'https://www.googleapis.com/auth/cloud-platform',
+  'https://www.googleapis.com/auth/generative-language.retriever',
+];
+
+export class ApiKeyAuthProvider implements AuthProvider {
+  readonly method = 'api-key';
+
+  constructor(private readonly apiKey: string, private readonly source?: string) {}
+
+  clientOptions(): { apiKey: string } {
+    return { apiKey: this.apiKey };
+  }
+
+  async requestHeaders(): Promise<Record<string, string>> {
+    return { 'x-goog-api-key': this.apiKey };
+  }
+
+  describe(): string {
+    return this.source ? `API key from ${this.source}` : 'API key';
+  }
+}
+
+// Several API keys, one at a time. The key ring moves on when a key runs out of quota or
+// is refused, and the client repeats the request with the next one.
+export class KeyRingAuthProvider implements AuthProvider {
+  readonly method = 'api-key';
+
+  constructor(readonly ring: KeyRing) {}
+
+  clientOptions(): { apiKey: string } {
+    return { apiKey: this.ring.current.key };
+  }
+
+  async requestHeaders(): Promise<Record<string, string>> {
+    return { 'x-goog-api-key': this.ring.use() };
+  }
+
+  describe(): string {
+    return `${this.ring.keys.length} API keys (${this.ring.keys.map(entry => entry.source).join(', ')}), starting with ${this.ring.current.source}`;
+  }
+}
+
+// OAuth access tokens from Application Default Credentials or a service account key.
+// google-auth-library caches the token and refreshes it before it expires.
+export class GoogleCredentialsAuthProvider implements AuthProvider {
+  private readonly auth: GoogleAuth;
+
+  constructor(readonly method: 'adc' | 'service-account', private readonly keyFile?: string) {
+    this.auth = new GoogleAuth(this.authOptions());
+  }
+
+  private authOptions(): GoogleAuthOptions {
+    return { scopes: AUTH_SCOPES, keyFile: this.keyFile };
+  }
+
+  clientOptions(): { googleAuthOptions: GoogleAuthOptions } {
+    return { googleAuthOptions: this.authOptions() };
+  }
+
+  async requestHeaders(url: string): Promise<Record<string, string>> {
+    return Object.fromEntries(await this.auth.getRequestHeaders(url));
+  }
+
+  describe(): string {
+    return this.keyFile ? `service account (${this.keyFile})` : 'Application Default Credentials';
+  }
+}
+
+// The API key from the --api-key flag, then the environment, then the OS keychain or
+// the encrypted credentials file written by auth login
+export function resolveApiKey(settings: AuthSettings, env: NodeJS.ProcessEnv = process.env): { apiKey: string; source: string } | undefined {
+  if (settings.apiKey) return { apiKey: settings.apiKey, source: '--api-key' };
+  const fromEnv = env[settings.apiKeyEnv];
+  if (fromEnv) return { apiKey: fromEnv, source: settings.apiKeyEnv };
+  const stored = loadCredential({ account: settings.profile, passphrase: env[PASSPHRASE_ENV] });
+  if (stored) return { apiKey: stored.apiKey, source: stored.backend === 'keychain' ? 'the keychain' : 'the encrypted credentials file' };
+  return undefined;
+}
+
+// Every configured key, in the order they are tried: api_key_env, the comma-separated
+// GEMINI_API_KEYS, then the variables named by api_key_envs. --api-key replaces them all,
+// and the stored key is used when none is set.
+export function resolveApiKeys(settings: AuthSettings, env: NodeJS.ProcessEnv = process.env): ApiKeyEntry[] {
+  if (settings.apiKey) return [{ key: settings.apiKey, source: '--api-key' }];
+  const entries: ApiKeyEntry[] = [];
+  const add = (key: string | undefined, source: string) => {
+    const trimmed = key?.trim();
+    if (trimmed && !entries.some(entry => entry.key === trimmed)) entries.push({ key: trimmed, source });
+  };
+  add(env[settings.apiKeyEnv], settings.apiKeyEnv);
+  (env[KEYS_ENV] || '').split(',').forEach((key, i) => add(key, `${KEYS_ENV} #${i + 1}`));
+  for (const name of settings.apiKeyEnvs || []) add(env[name], name);
+  if (entries.length > 0) return entries;
+  const resolved = resolveApiKey(settings, env);
+  return resolved ? [{ key: resolved.apiKey, source: resolved.source }] : [];
+}
+
+// A key file, the gcloud well-known file, or a Google Cloud runtime with a metadata server
+export function hasApplicationDefaultCredentials(env: NodeJS.ProcessEnv = process.env): boolean {
+  if (env.GOOGLE_APPLICATION_CREDENTIALS) return fs.existsSync(env.GOOGLE_APPLICATION_CREDENTIALS);
+  const gcloudDir = env.CLOUDSDK_CONFIG || (process.platform === 'win32'
+    ? path.join(env.APPDATA || path.join(os.homedir(), 'AppData', 'Roaming'), 'gcloud')
+    : path.join(os.homedir(), '.config', 'gcloud'));
+  return fs.existsSync(path.join(gcloudDir, 'application_default_credentials.json'))
+    || Boolean(env.K_SERVICE || env.FUNCTION_TARGET || env.GCE_METADATA_HOST);
+}
+
+// Without an auth method the credential chain applies: flag, environment, stored key, then ADC.
+// api-key stops before ADC.
+export function createAuthProvider(settings: AuthSettings, env: NodeJS.ProcessEnv = process.env): AuthProvider {
+  const method = settings.authMethod;
+  switch (method) {
+    case undefined:
+    case 'api-key': {
+      const keys = resolveApiKeys(settings, env);
+      if (keys.length > 1) return new KeyRingAuthProvider(new KeyRing(keys));
+      if (keys.length === 1) return new ApiKeyAuthProvider(keys[0].key, keys[0].source);
+      if (method === undefined && hasApplicationDefaultCredentials(env)) {
+        return new GoogleCredentialsAuthProvider('adc');
+      }
+      throw new ConfigError(`No API key found: set ${settings.apiKeyEnv} or ${KEYS_ENV}, or run \`gemini-fs-auth login\`${method === undefined ? ', or set up Application Default Credentials' : ''}`);
+    }
+
+    case 'adc':
+    case 'service-account': {
+      const keyFile = method === 'service-account' ? settings.credentialsFile || env.GOOGLE_APPLICATION_CREDENTIALS : undefined;
+      if (method === 'service-account' && (!keyFile || !fs.existsSync(keyFile))) {
+        throw new ConfigError(keyFile
+          ? `Service account key not found: ${keyFile}`
+          : 'auth = "service-account" requires credentials_file or GOOGLE_APPLICATION_CREDENTIALS');
+      }
+      // The SDK prefers an API key from the environment over OAuth credentials
+      if (env.GEMINI_API_KEY || env.GOOGLE_API_KEY) {
+        console.error(`⚠️  GEMINI_API_KEY/GOOGLE_API_KEY is set and takes precedence over ${method} credentials for SDK calls`);
+      }
+      return new GoogleCredentialsAuthProvider(method, keyFile);
+    }
+
+    default:
+      throw new ConfigError(`auth must be one of: ${AUTH_METHODS.join(', ')} (got "${method}")`);
+  }
+}
@@ -0,0 +1,115 @@
+// backend.ts - Selects the Gemini API or Vertex AI RAG Engine as the retrieval backend, its API version and extra headers
+import { Tool } from '@google/genai';
+import { ConfigError } from './errors.js';
+import { TomlValue } from './toml.js';
+
+// Types
+export type BackendKind = 'gemini' | 'vertex';
+
+export interface BackendConfig {
+  kind: BackendKind;
+  project?: string;  // Vertex AI only
+  location?: string;
+}
+
+export interface ApiOptions {
+  version?: string;  // Path segment of the API version, e.g. "v1" (default: v1beta on the Gemini API, v1 on Vertex AI)
+  headers?: { [name: string]: string };  // Sent with every request, e.g. x-goog-user-project or billing labels
+}
+
+export const BACKENDS: BackendKind[] = ['gemini', 'vertex'];
+export const DEFAULT_VERTEX_LOCATION = 'us-central1';
+export const DEFAULT_API_VERSION = 'v1beta';
+export const DEFAULT_VERTEX_API_VERSION = 'v1';
+
+// Set by the auth provider; a custom header must not replace the credentials
+const RESERVED_HEADERS = ['authorization', 'x-goog-api-key', 'content-type', 'content-length', 'host'];
+
+// Vertex AI takes its project and location from the flags, then the config, then
+// GOOGLE_CLOUD_PROJECT / GOOGLE_CLOUD_LOCATION
+export function resolveBackend(
+  kind: string | undefined,
+  project: string | undefined,
+  location: string | undefined,
+  env: NodeJS.ProcessEnv = process.env
+): BackendConfig {
+  const backend = (kind || 'gemini') as BackendKind;
+  if (!BACKENDS.includes(backend)) {
+    throw new ConfigError(`backend must be one of: ${BACKENDS.join(', ')} (got "${kind}")`);
+  }
+  if (backend === 'gemini') return { kind: 'gemini' };
+
+  const vertexProject = project || env.GOOGLE_CLOUD_PROJECT;
+  if (!vertexProject) {
+    throw new ConfigError('The vertex backend requires vertex_project, --vertex-project or GOOGLE_CLOUD_PROJECT');
+  }
+  return {
+    kind: 'vertex',
+    project: vertexProject,
+    location: location || env.GOOGLE_CLOUD_LOCATION || DEFAULT_VERTEX_LOCATION,
+  };
+}
+
+// Stores are File Search stores on the Gemini API and RAG corpora on Vertex AI
+export function retrievalTool(backend: BackendConfig, storeNames: string[], metadataFilter?: string): Tool {
+  if (backend.kind === 'vertex') {
+    return {
+      retrieval: {
+        vertexRagStore: {
+          ragResources: storeNames.map(ragCorpus => ({ ragCorpus })),
+          ragRetrievalConfig: metadataFilter ? { filter: { metadataFilter } } : undefined,
+        },
+      },
+    };
+  }
+  return {
+    fileSearch: {
+      fileSearchStoreNames: storeNames,
+      metadataFilter,
+    },
+  };
+}
+
+function checkHeader(name: string, context: string): void {
+  if (!/^[A-Za-z0-9!#$%&'*+.^_`|~-]+$/.test(name)) throw new ConfigError(`${context}: invalid header name "${name}"`);
+  if (RESERVED_HEADERS.includes(name.toLowerCase())) throw new ConfigError(`${context}: the ${name} header cannot be set`);
+}
+
+// The [headers] table: header names as keys, string values
+export function parseHeadersConfig(value: TomlValue | undefined): { [name: string]: string } {
+  if (value === undefined) return {};
+  if (typeof value !== 'object' || Array.isArray(value)) throw new ConfigError('headers must be a table of header names and values');
+  const headers: { [name: string]: string } = {};
+  for (const [name, header] of Object.entries(value)) {
+    checkHeader(name, 'headers');
+    if (typeof header !== 'string') throw new ConfigError(`headers.${name} must be a string`);
+    headers[name] = header;
+  }
+  return headers;
+}
+
+// --header takes "Name: value"; repeated flags add headers and replace configured ones
+export function parseHeaderFlags(values: string[]): { [name: string]: string } {
+  const headers: { [name: string]: string } = {};
+  for (const value of values) {
+    const separator = value.indexOf(':');
+    if (separator <= 0) throw new ConfigError(`--header must be "Name: value", got "${value}"`);
+    const name = value.slice(0, separator).trim();
+    checkHeader(name, '--header');
+    headers[name] = value.slice(separator + 1).trim();
+  }
+  return headers;
+}
+
+// Headers of --header flags are added to the [headers] of the config
+export function resolveApiOptions(version: string | undefined, headers: { [name: string]: string } | undefined, headerFlags: string[] = []): ApiOptions {
+  if (version && !/^v\d+((alpha|beta)\d*)?$/.test(version)) {
+    throw new ConfigError(`api_version must look like v1, v1beta or v1beta1 (got "${version}")`);
+  }
+  const merged = { ...headers, ...parseHeaderFlags(headerFlags) };
+  return { version: version || undefined, headers: Object.keys(merged).length > 0 ? merged : undefined };
+}
+
+export function describeBackend(backend: BackendConfig): string {
+  return backend.kind === 'vertex' ? `Vertex AI RAG Engine (${backend.project}, ${backend.location})` : 'Gemini API File Search';
+}
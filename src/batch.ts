@@ -0,0 +1,138 @@
+// batch.ts - JSONL batch queries for evaluation runs
+import * as fs from 'fs';
+import * as readline from 'readline';
+import { once } from 'events';
+import { FileSearchClient } from './client.js';
+import { Citation, extractCitations } from './citations.js';
+import { parseFilterExpression } from './search.js';
+import { Semaphore } from './pipeline.js';
+import { InvalidInputError } from './errors.js';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { GenerationParams } from './generation.js';
+
+// Types
+export interface BatchQuestion {
+  id?: string | number;
+  question: string;
+  filter?: string;  // Metadata filter expression
+}
+
+export interface BatchResult {
+  id: string | number;
+  question: string;
+  answer?: string;
+  citations?: Citation[];
+  usage?: TokenUsage;
+  latencyMs: number;
+  error?: string;
+}
+
+export interface BatchOptions {
+  concurrency?: number;
+  model?: string;
+  generation?: GenerationParams;
+  aclLabels?: string[];  // Access labels every question is scoped to
+  onResult?: (result: BatchResult, completed: number) => void;
+}
+
+export interface BatchSummary {
+  total: number;
+  succeeded: number;
+  failed: number;
+  averageLatencyMs: number;
+  totalTokens: number;
+}
+
+function parseLine(line: string, lineNumber: number): BatchQuestion {
+  const parsed = JSON.parse(line) as BatchQuestion | string;
+  const question = typeof parsed === 'string' ? { question: parsed } : parsed;
+  if (!question.question || typeof question.question !== 'string') {
+    throw new InvalidInputError(`Line ${lineNumber}: missing "question"`);
+  }
+  return question;
+}
+
+// Reads questions line by line and writes one result per line in completion order.
+// At most `concurrency` queries are in flight, so large files are never fully loaded.
+export async function runBatchQueries(
+  client: FileSearchClient,
+  storeNames: string[],
+  inputPath: string,
+  outputPath: string,
+  options: BatchOptions = {}
+): Promise<BatchSummary> {
+  const semaphore = new Semaphore(options.concurrency || 5);
+  const output = fs.createWriteStream(outputPath, { encoding: 'utf8' });
+  const lines = readline.createInterface({ input: fs.createReadStream(inputPath, 'utf8'), crlfDelay: Infinity });
+  const pending: Promise<void>[] = [];
+  let total = 0;
+  let succeeded = 0;
+  let failed = 0;
+  let totalLatency = 0;
+  let totalTokens = 0;
+
+  const write = async (result: BatchResult): Promise<void> => {
+    if (!output.write(`${JSON.stringify(result)}\n`)) {
+      await once(output, 'drain');
+    }
+    totalLatency += result.latencyMs;
+    totalTokens += result.usage?.totalTokens || 0;
+    if (result.error) {
+      failed++;
+    } else {
+      succeeded++;
+    }
+    options.onResult?.(result, succeeded + failed);
+  };
+
+  try {
+    let lineNumber = 0;
+    for await (const line of lines) {
+      lineNumber++;
+      if (!line.trim()) continue;
+      total++;
+
+      await semaphore.acquire();
+      const currentLine = lineNumber;
+      pending.push((async () => {
+        const startedAt = Date.now();
+        let id: string | number = currentLine;
+        let question = '';
+        try {
+          const parsed = parseLine(line, currentLine);
+          id = parsed.id ?? currentLine;
+          question = parsed.question;
+          const metadataFilter = parsed.filter ? parseFilterExpression(parsed.filter) : undefined;
+          const response = await client.query(storeNames, question, { model: options.model, metadataFilter, aclLabels: options.aclLabels, generation: options.generation });
+          const answer = response.text || '';
+          await write({
+            id,
+            question,
+            answer,
+            citations: extractCitations(response.candidates?.[0]?.groundingMetadata, answer),
+            usage: toTokenUsage(response.usageMetadata),
+            latencyMs: Date.now() - startedAt,
+          });
+        } catch (error) {
+          const err = error as Error;
+          console.error(`❌ Batch query error (line ${currentLine}): ${err.message}`);
+          await write({ id, question, latencyMs: Date.now() - startedAt, error: err.message });
+        } finally {
+          semaphore.release();
+        }
+      })());
+    }
+    await Promise.all(pending);
+  } finally {
+    output.end();
+    await once(output, 'finish');
+  }
+
+  return {
+    total,
+    succeeded,
+    failed,
+    averageLatencyMs: total > 0 ? Math.round(totalLatency / total) : 0,
+    totalTokens,
+  };
+}
@@ -0,0 +1,257 @@
+// bench.ts - Upload throughput and query latency measurements for tuning concurrency and chunking
+import * as fs from 'fs';
+import { FileSearchClient } from './client.js';
+import { DirectoryIngester, IngestOptions } from './ingest.js';
+import { ChunkingConfig, ChunkingPolicy, validateChunkingConfig } from './chunking.js';
+import { toTokenUsage } from './cost.js';
+import { Semaphore } from './pipeline.js';
+import { ReportFormat } from './progress.js';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export interface BenchOptions {
+  uploadDir?: string;  // Files uploaded once per concurrency level and chunking, each run into a scratch store
+  concurrency?: number[];  // Upload concurrency levels (default: 1, 5, 10)
+  chunking?: ChunkingConfig[];  // Chunking variants; the ingest settings' chunking when omitted
+  questions?: string[];
+  stores?: string[];  // Queried instead of the scratch stores
+  queryRuns?: number;  // Times each question is asked (default: 10)
+  queryConcurrency?: number;  // Queries in flight at once (default: 1, for undisturbed latencies)
+  warmup?: number;  // Unmeasured queries before the runs (default: 1)
+  model?: string;
+  ingest?: IngestOptions;  // MIME, extraction and rate limit settings of the uploads
+  keepStores?: boolean;  // Leave the scratch stores in place, e.g. to query them again
+  onProgress?: (message: string) => void;
+}
+
+export interface LatencyStats {
+  min: number;  // Milliseconds
+  mean: number;
+  p50: number;
+  p95: number;
+  p99: number;
+  max: number;
+}
+
+export interface UploadBenchRun {
+  concurrency: number;
+  chunking?: ChunkingConfig;
+  storeName: string;
+  files: number;  // Uploaded successfully
+  bytes: number;
+  errors: number;
+  retries: number;
+  durationMs: number;  // Until every document was indexed
+  mbPerSecond: number;
+  filesPerMinute: number;
+}
+
+export interface QueryBench {
+  label: string;  // Stores queried, or the chunking of the scratch store
+  stores: string[];
+  chunking?: ChunkingConfig;
+  runs: number;
+  errors: number;
+  latency?: LatencyStats;  // Absent when every query failed
+  queriesPerMinute: number;
+  averageTokens?: number;
+}
+
+export interface BenchReport {
+  generatedAt: string;  // ISO 8601
+  model: string;
+  upload?: UploadBenchRun[];
+  query?: QueryBench[];
+}
+
+export const DEFAULT_BENCH_CONCURRENCY = [1, 5, 10];
+export const DEFAULT_QUERY_RUNS = 10;
+
+// Nearest-rank percentile of ascending values
+export function percentile(sorted: number[], p: number): number {
+  if (sorted.length === 0) return 0;
+  return sorted[Math.min(sorted.length - 1, Math.max(0, Math.ceil((p / 100) * sorted.length) - 1))];
+}
+
+export function latencyStats(latencies: number[]): LatencyStats | undefined {
+  if (latencies.length === 0) return undefined;
+  const sorted = [...latencies].sort((a, b) => a - b);
+  return {
+    min: sorted[0],
+    mean: Math.round(sorted.reduce((sum, value) => sum + value, 0) / sorted.length),
+    p50: percentile(sorted, 50),
+    p95: percentile(sorted, 95),
+    p99: percentile(sorted, 99),
+    max: sorted[sorted.length - 1],
+  };
+}
+
+// "400/40", "400" or "default"
+export function formatChunking(chunking: ChunkingConfig | undefined): string {
+  if (!chunking || chunking.maxTokensPerChunk === undefined) return 'default';
+  return chunking.maxOverlapTokens !== undefined ? `${chunking.maxTokensPerChunk}/${chunking.maxOverlapTokens}` : `${chunking.maxTokensPerChunk}`;
+}
+
+// Parses the "<maxTokensPerChunk>[/<maxOverlapTokens>]" of --chunking
+export function parseChunkingArgument(value: string): ChunkingConfig {
+  const match = /^(\d+)(?:\/(\d+))?$/.exec(value.trim());
+  if (!match) throw new InvalidInputError(`Chunking must be <maxTokensPerChunk>[/<maxOverlapTokens>], got "${value}"`);
+  const config: ChunkingConfig = { maxTokensPerChunk: Number(match[1]), ...(match[2] !== undefined ? { maxOverlapTokens: Number(match[2]) } : {}) };
+  validateChunkingConfig(config);
+  return config;
+}
+
+function round(value: number, digits: number): number {
+  return Math.round(value * 10 ** digits) / 10 ** digits;
+}
+
+async function benchUpload(
+  client: FileSearchClient,
+  uploadDir: string,
+  concurrency: number,
+  chunking: ChunkingConfig | undefined,
+  options: BenchOptions,
+  scratch: string[]
+): Promise<UploadBenchRun> {
+  const storeName = await client.createStore(`gemini-bench-c${concurrency}-${formatChunking(chunking).replace('/', '-')}-${Date.now()}`);
+  scratch.push(storeName);
+  // Without the index cache every run uploads every file
+  const ingester = new DirectoryIngester({
+    ...options.ingest,
+    cache: undefined,
+    dedup: undefined,
+    concurrency,
+    chunking: chunking ? new ChunkingPolicy({ default: chunking }) : options.ingest?.chunking,
+  });
+  const files = ingester.collect(uploadDir);
+  const summary = await ingester.upload(client, storeName, uploadDir, files);
+  const seconds = summary.durationMs / 1000;
+  return {
+    concurrency,
+    ...(chunking ? { chunking } : {}),
+    storeName,
+    files: summary.successCount,
+    bytes: summary.uploadedBytes,
+    errors: summary.errorCount,
+    retries: summary.retries,
+    durationMs: summary.durationMs,
+    mbPerSecond: seconds > 0 ? round(summary.uploadedBytes / 1024 / 1024 / seconds, 3) : 0,
+    filesPerMinute: seconds > 0 ? round(summary.successCount / (seconds / 60), 1) : 0,
+  };
+}
+
+async function benchQuery(
+  client: FileSearchClient,
+  label: string,
+  stores: string[],
+  chunking: ChunkingConfig | undefined,
+  options: BenchOptions
+): Promise<QueryBench> {
+  const questions = options.questions || [];
+  const model = options.model || client.model;
+  for (const question of questions.slice(0, options.warmup ?? 1)) {
+    await client.query(stores, question, { model }).catch(() => undefined);
+  }
+
+  const runs = questions.flatMap(question => Array.from({ length: options.queryRuns || DEFAULT_QUERY_RUNS }, () => question));
+  const semaphore = new Semaphore(options.queryConcurrency || 1);
+  const latencies: number[] = [];
+  const tokens: number[] = [];
+  let errors = 0;
+  const startedAt = Date.now();
+  await Promise.all(runs.map(question => semaphore.run(async () => {
+    const queryStartedAt = Date.now();
+    try {
+      const response = await client.query(stores, question, { model });
+      latencies.push(Date.now() - queryStartedAt);
+      const usage = toTokenUsage(response.usageMetadata);
+      if (usage) tokens.push(usage.totalTokens);
+    } catch (error) {
+      errors++;
+      options.onProgress?.(`❌ Query failed: ${(error as Error).message}`);
+    }
+  })));
+  const minutes = (Date.now() - startedAt) / 60000;
+  return {
+    label,
+    stores,
+    ...(chunking ? { chunking } : {}),
+    runs: runs.length,
+    errors,
+    latency: latencyStats(latencies),
+    queriesPerMinute: minutes > 0 ? round(latencies.length / minutes, 1) : 0,
+    averageTokens: tokens.length > 0 ? Math.round(tokens.reduce((sum, value) => sum + value, 0) / tokens.length) : undefined,
+  };
+}
+
+// Uploads run one after another, so levels do not compete for the quota. The questions
+// are asked against the given stores, else against one scratch store per chunking.
+export async function runBenchmark(client: FileSearchClient, options: BenchOptions): Promise<BenchReport> {
+  const report: BenchReport = { generatedAt: new Date().toISOString(), model: options.model || client.model };
+  if (!options.uploadDir && !(options.questions?.length && options.stores?.length)) {
+    throw new InvalidInputError('Give a directory to upload, or questions and stores to query');
+  }
+  if (options.uploadDir && !fs.statSync(options.uploadDir).isDirectory()) {
+    throw new InvalidInputError(`Not a directory: ${options.uploadDir}`);
+  }
+
+  const scratch: string[] = [];
+  try {
+    if (options.uploadDir) {
+      report.upload = [];
+      const variants: (ChunkingConfig | undefined)[] = options.chunking && options.chunking.length > 0 ? options.chunking : [undefined];
+      for (const chunking of variants) {
+        for (const concurrency of options.concurrency || DEFAULT_BENCH_CONCURRENCY) {
+          options.onProgress?.(`⏱️  Uploading ${options.uploadDir} at concurrency ${concurrency}, chunking ${formatChunking(chunking)}`);
+          const run = await benchUpload(client, options.uploadDir, concurrency, chunking, options, scratch);
+          report.upload.push(run);
+          options.onProgress?.(`   ${run.files} files in ${(run.durationMs / 1000).toFixed(1)}s: ${run.mbPerSecond} MB/s, ${run.filesPerMinute} files/min`);
+        }
+      }
+    }
+
+    if (options.questions && options.questions.length > 0) {
+      report.query = [];
+      const targets = options.stores && options.stores.length > 0
+        ? [{ label: options.stores.join(', '), stores: options.stores, chunking: undefined }]
+        : [...new Map((report.upload || []).map(run => [formatChunking(run.chunking), run])).values()]
+          .map(run => ({ label: `chunking ${formatChunking(run.chunking)}`, stores: [run.storeName], chunking: run.chunking }));
+      for (const target of targets) {
+        options.onProgress?.(`⏱️  Querying ${target.label}`);
+        const bench = await benchQuery(client, target.label, target.stores, target.chunking, options);
+        report.query.push(bench);
+        if (bench.latency) options.onProgress?.(`   p50 ${bench.latency.p50}ms, p95 ${bench.latency.p95}ms, p99 ${bench.latency.p99}ms`);
+      }
+    }
+  } finally {
+    if (!options.keepStores) {
+      for (const storeName of scratch) {
+        await client.deleteStore(storeName).catch(error => options.onProgress?.(`⚠️  Could not delete scratch store ${storeName}: ${(error as Error).message}`));
+      }
+    }
+  }
+  return report;
+}
+
+export function formatBenchReport(report: BenchReport, format: ReportFormat = 'json'): string {
+  if (format === 'json') return JSON.stringify(report, null, 2);
+  const lines: string[] = [];
+  if (report.upload) {
+    lines.push('Upload throughput:', '  concurrency  chunking   files  errors   seconds      MB/s  files/min');
+    for (const run of report.upload) {
+      lines.push(`  ${String(run.concurrency).padStart(11)}  ${formatChunking(run.chunking).padEnd(8)} ${String(run.files).padStart(6)}  ${String(run.errors).padStart(6)}  `
+        + `${(run.durationMs / 1000).toFixed(1).padStart(8)}  ${run.mbPerSecond.toFixed(3).padStart(8)}  ${run.filesPerMinute.toFixed(1).padStart(9)}`);
+    }
+  }
+  if (report.query) {
+    if (lines.length > 0) lines.push('');
+    lines.push(`Query latency (${report.model}, ms):`);
+    for (const bench of report.query) {
+      const latency = bench.latency
+        ? `p50 ${bench.latency.p50}  p95 ${bench.latency.p95}  p99 ${bench.latency.p99}  (min ${bench.latency.min}, mean ${bench.latency.mean}, max ${bench.latency.max})`
+        : 'every query failed';
+      lines.push(`  ${bench.label}: ${latency}; ${bench.runs} runs, ${bench.errors} errors, ${bench.queriesPerMinute} queries/min`);
+    }
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,151 @@
+#!/usr/bin/env node
+
+// archive-cli.ts - Export a File Search store to an archive and restore it
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { DirectoryIngester } from '../ingest.js';
+import { Redactor } from '../redact.js';
+import { DocumentMetadata } from '../metadata.js';
+import { RowChunkingOptions } from '../tables.js';
+import { Settings } from '../config.js';
+import { Notifier } from '../notify.js';
+import { telemetry } from '../telemetry.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { exportStore, readArchive, restoreArchive, writeArchive } from '../archive.js';
+import { REPORT_FORMATS, ReportFormat, formatIngestReport } from '../progress.js';
+import { CI_REPORT_FORMATS, CiReportWriter, parseCiReportTarget } from '../ci-report.js';
+import { shutdown } from '../shutdown.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings, projectsFile } from './common.js';
+
+interface ProjectEntry {
+  id: string;
+  name: string;
+  storeId: string;
+  path: string;
+  metadata?: DocumentMetadata;
+  codeChunking?: boolean;
+  rowChunking?: RowChunkingOptions;
+}
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run archive -- export (--store <storeName> | --project <projectId>) --out <file.tar.gz|dir> [--include-content] [--source <dir>]',
+    `  npm run archive -- import --from-archive <file.tar.gz|dir> [--name <displayName>] [--path <projectDir>] [--report table|json] [--report-file <file>] [--report ${CI_REPORT_FORMATS.join('|')}:<file> ...]`,
+    `${COMMON_USAGE} [--notify-command <cmd>] [--notify-webhook <url>]`,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function findProject(projectId: string): ProjectEntry | undefined {
+  try {
+    const data = JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: ProjectEntry[] };
+    return data.projects.find(p => p.id === projectId);
+  } catch (error) {
+    // No registered projects
+    return undefined;
+  }
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  if (command !== 'export' && command !== 'import') usage();
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+  const notifier = new Notifier({
+    command: getArgValue('--notify-command') || settings.notifyCommand,
+    webhook: getArgValue('--notify-webhook') || settings.notifyWebhook,
+  });
+  const client = buildClientFromArgs(settings, { notifier });
+
+  if (command === 'export') {
+    const projectId = getArgValue('--project');
+    const project = projectId ? findProject(projectId) : undefined;
+    if (projectId && !project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    const storeName = project?.storeId || getArgValue('--store') || settings.defaultStore;
+    const outputPath = getArgValue('--out');
+    if (!storeName || !outputPath) usage();
+
+    const archive = await exportStore(client, storeName, {
+      project: project ? { ...project } : undefined,
+      rootDir: getArgValue('--source') || project?.path,
+      includeContent: process.argv.includes('--include-content'),
+      chunking: settings.chunking,
+    });
+    writeArchive(archive, path.resolve(outputPath));
+    console.log(`✅ Exported ${storeName} to ${path.resolve(outputPath)} (${archive.manifest.documents.length} documents, ${archive.manifest.files.length} source files)`);
+    return;
+  }
+
+  const archivePath = getArgValue('--from-archive');
+  // "table" or "json" picks the report on stdout, "<format>:<file>" adds a CI report
+  const reportValues = getArgValues('--report');
+  const ciReport = CiReportWriter.of(reportValues.filter(value => value.includes(':')).map(parseCiReportTarget));
+  const reportFormat = (reportValues.find(value => !value.includes(':')) || 'table') as ReportFormat;
+  if (!archivePath || !REPORT_FORMATS.includes(reportFormat)) usage();
+  const archive = readArchive(path.resolve(archivePath));
+  const archivedProject = archive.manifest.project as ProjectEntry | undefined;
+  const rootDir = getArgValue('--path') || archivedProject?.path;
+
+  const summary = await restoreArchive(client, archive, {
+    displayName: getArgValue('--name') || archivedProject?.name,
+    ingester: new DirectoryIngester({
+      metadata: archivedProject?.metadata,
+      codeChunking: archivedProject?.codeChunking,
+      rowChunking: archivedProject?.rowChunking,
+      chunking: settings.chunking,
+      concurrency: settings.concurrency ? Math.floor(settings.concurrency) : undefined,
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      notebooks: { stripOutputs: settings.stripNotebookOutputs },
+      emails: { stripQuotes: settings.stripQuotedReplies },
+      normalizeEncoding: settings.normalizeEncoding,
+      detectLanguage: settings.detectLanguage,
+      fileAttributes: settings.fileAttributes,
+      redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+      transforms: settings.transforms,
+      summaries: settings.withSummaries ? { minBytes: settings.summaryMinSize, model: settings.model } : undefined,
+      partSize: settings.partSize ? Math.floor(settings.partSize) : undefined,
+      dedup: settings.dedup,
+      dedupDistance: settings.dedupDistance,
+      progressBars: true,
+      report: ciReport,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    }),
+    rootDir: rootDir && fs.existsSync(rootDir) ? path.resolve(rootDir) : undefined,
+  });
+  const report = { store: summary.storeName, ...summary.upload };
+  const reportFile = getArgValue('--report-file');
+  if (reportFile) {
+    fs.writeFileSync(reportFile, formatIngestReport(report, 'json') + '\n');
+  }
+  if (reportFormat === 'json') {
+    // The table report is already on stderr; stdout stays machine-readable
+    console.log(formatIngestReport(report, 'json'));
+    if (summary.upload.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+  console.log(`✅ Restored into ${summary.storeName}: ${summary.upload.successCount}/${summary.upload.totalFiles} files uploaded`);
+  if (summary.missingDocuments.length > 0) {
+    console.log(`   ${summary.missingDocuments.length} documents had no content in the archive and were not restored`);
+  }
+  if (archivedProject) {
+    console.log('   Use gemini_import_archive to also register the project with the MCP server');
+  }
+  if (summary.upload.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+}
+
+// Ctrl-C lets the restore finish the files in flight and report what is left
+shutdown.install();
+shutdown.track(main()).then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
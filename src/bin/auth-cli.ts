@@ -0,0 +1,117 @@
+#!/usr/bin/env node
+
+// auth-cli.ts - Store the Gemini API key in the OS keychain or an encrypted file
+import 'dotenv/config';
+import { createAuthProvider, resolveApiKeys } from '../auth.js';
+import { KEYS_ENV, KeyRing, keyUsageFilePath } from '../keys.js';
+import {
+  CREDENTIAL_BACKENDS,
+  CredentialBackend,
+  PASSPHRASE_ENV,
+  credentialsFilePath,
+  deleteCredential,
+  hasStoredCredential,
+  keychainAvailable,
+  promptSecret,
+  saveCredential,
+} from '../credentials.js';
+import { EXIT_CODES, InvalidInputError, exitCodeFor, toFileSearchError } from '../errors.js';
+import { getArgValue, loadSettings } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run auth -- login [--storage keychain|file]   Prompt for the API key and store it (default: keychain when available)',
+    '  npm run auth -- logout   Remove the stored key',
+    '  npm run auth -- status   Show which credentials the tools would use',
+    `  npm run auth -- keys status [--json]   Usage and state of each API key (from api_key_env, ${KEYS_ENV} and api_key_envs)`,
+    '  npm run auth -- keys reset   Make keys that ran out of quota or were refused usable again',
+    'Options: [--config <file>] [--profile <name>]   The key is stored per profile',
+    `The encrypted file is ${credentialsFilePath()}; its passphrase is prompted for, or read from ${PASSPHRASE_ENV}.`,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+async function readPassphrase(): Promise<string> {
+  const fromEnv = process.env[PASSPHRASE_ENV];
+  if (fromEnv) return fromEnv;
+  const passphrase = await promptSecret('Passphrase for the credentials file: ');
+  if (!passphrase) throw new InvalidInputError('A passphrase is required');
+  if (await promptSecret('Repeat the passphrase: ') !== passphrase) {
+    throw new InvalidInputError('The passphrases do not match');
+  }
+  return passphrase;
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  const settings = loadSettings();
+  const account = settings.profile;
+
+  switch (command) {
+    case 'login': {
+      const storage = (getArgValue('--storage') || (keychainAvailable() ? 'keychain' : 'file')) as CredentialBackend;
+      if (!CREDENTIAL_BACKENDS.includes(storage)) usage();
+      // Read from the terminal rather than argv, so the key stays out of shell history
+      const apiKey = (await promptSecret('Gemini API key: ')).trim();
+      if (!apiKey) throw new InvalidInputError('No API key entered');
+      const passphrase = storage === 'file' ? await readPassphrase() : undefined;
+      const location = saveCredential(apiKey, storage, { account, passphrase });
+      console.log(`✅ API key${account ? ` for profile "${account}"` : ''} stored in ${location}`);
+      if (process.env[settings.apiKeyEnv]) {
+        console.log(`   ${settings.apiKeyEnv} is set and still takes precedence; unset it to use the stored key`);
+      }
+      break;
+    }
+
+    case 'logout': {
+      const removed = deleteCredential({ account });
+      console.log(removed.length > 0 ? `✅ Removed the stored API key from: ${removed.join(', ')}` : 'No stored API key');
+      break;
+    }
+
+    case 'status': {
+      const stored = hasStoredCredential({ account });
+      console.log(`Stored API key: ${stored ? (stored === 'keychain' ? 'keychain' : `encrypted file (${credentialsFilePath()})`) : 'none'}`);
+      try {
+        console.log(`Credentials in use: ${createAuthProvider(settings).describe()}`);
+      } catch (error) {
+        console.log(`Credentials in use: none (${(error as Error).message})`);
+      }
+      break;
+    }
+
+    case 'keys': {
+      const keys = resolveApiKeys(settings);
+      if (keys.length === 0) throw new InvalidInputError(`No API keys configured: set ${settings.apiKeyEnv} or ${KEYS_ENV}`);
+      const ring = new KeyRing(keys);
+      if (process.argv[3] === 'reset') {
+        ring.reset();
+        console.log(`✅ All ${keys.length} API key${keys.length === 1 ? '' : 's'} usable again`);
+        break;
+      }
+      if (process.argv[3] !== 'status') usage();
+      const status = ring.status();
+      if (process.argv.includes('--json')) {
+        console.log(JSON.stringify(status, null, 2));
+        break;
+      }
+      console.log(`API keys (usage from ${keyUsageFilePath()}):`);
+      for (const key of status) {
+        const state = key.state === 'cooling' ? `cooling down until ${key.coolingUntil}` : key.state;
+        console.log(`${key.current ? '→' : ' '} ${key.source} [${key.id}]: ${state}, ${key.requests} requests, ${key.quotaErrors} quota errors, ${key.authErrors} auth errors${key.lastUsed ? `, last used ${key.lastUsed}` : ''}`);
+        if (key.state !== 'active' && key.lastError) console.log(`    ${key.lastError}`);
+      }
+      break;
+    }
+
+    default:
+      usage();
+  }
+}
+
+main().catch(error => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,128 @@
+#!/usr/bin/env node
+
+// bench-cli.ts - Measures upload throughput and query latency, for tuning concurrency and chunking
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { MimeRegistry } from '../file-types.js';
+import { Redactor } from '../redact.js';
+import { DEFAULT_BENCH_CONCURRENCY, DEFAULT_QUERY_RUNS, formatBenchReport, parseChunkingArgument, runBenchmark } from '../bench.js';
+import { REPORT_FORMATS, ReportFormat } from '../progress.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings, projectsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage: npm run bench -- [--dir <directory>] [--question <question> ...] [--project <projectId> | --store <storeName> ...]',
+    'Options: [--concurrency <n,...>] [--chunking <maxTokens>[/<overlap>] ...] [--runs <n>] [--query-concurrency <n>] [--warmup <n>] [--model <model>] [--keep-stores] [--format json|table] [--out <report.json>]',
+    COMMON_USAGE,
+    `--dir is uploaded once per concurrency level (default: ${DEFAULT_BENCH_CONCURRENCY.join(',')}) and chunking, each time into a scratch store deleted afterwards.`,
+    `Each --question is asked --runs times (default: ${DEFAULT_QUERY_RUNS}) against the given stores, else against the scratch store of each chunking.`,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function getIntegerArg(flag: string, min: number, max: number): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= min && Number(value) <= max)) {
+    console.error(`Error: ${flag} must be an integer between ${min} and ${max}, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+function resolveStores(): string[] {
+  const stores = getArgValues('--store');
+  const projectId = getArgValue('--project');
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+  }
+  return stores;
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const uploadDir = getArgValue('--dir');
+  const questions = getArgValues('--question');
+  const stores = resolveStores();
+  if (!uploadDir && (questions.length === 0 || stores.length === 0)) usage();
+  if (uploadDir && !fs.existsSync(uploadDir)) {
+    console.error(`Error: Directory not found: ${uploadDir}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const format = getArgValue('--format') || 'json';
+  if (!REPORT_FORMATS.includes(format as ReportFormat)) {
+    console.error(`Error: --format must be one of: ${REPORT_FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const concurrencyArg = getArgValue('--concurrency');
+  const concurrency = concurrencyArg !== undefined ? concurrencyArg.split(',').map(level => Number(level.trim())) : undefined;
+  if (concurrency && !concurrency.every(level => Number.isInteger(level) && level >= 1 && level <= 50)) {
+    console.error(`Error: --concurrency must be a comma-separated list of integers between 1 and 50, got "${concurrencyArg}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const chunking = getArgValues('--chunking').map(parseChunkingArgument);
+  const runs = getIntegerArg('--runs', 1, 1000);
+  const queryConcurrency = getIntegerArg('--query-concurrency', 1, 50);
+  const warmup = getIntegerArg('--warmup', 0, 100);
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const client = buildClientFromArgs(settings);
+
+  const report = await runBenchmark(client, {
+    uploadDir,
+    concurrency,
+    chunking,
+    questions,
+    stores,
+    queryRuns: runs,
+    queryConcurrency,
+    warmup,
+    model: getArgValue('--model') || settings.model,
+    ingest: {
+      mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+      chunking: settings.chunking,
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      normalizeEncoding: settings.normalizeEncoding !== false,
+      detectLanguage: settings.detectLanguage !== false,
+      fileAttributes: settings.fileAttributes !== false,
+      redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+      transforms: settings.transforms,
+    },
+    keepStores: process.argv.includes('--keep-stores'),
+    onProgress: message => console.error(message),
+  });
+
+  const outFile = getArgValue('--out');
+  if (outFile) {
+    fs.writeFileSync(outFile, JSON.stringify(report, null, 2));
+    console.error(`💾 Report written to ${outFile}`);
+  }
+  console.log(formatBenchReport(report, format as ReportFormat));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
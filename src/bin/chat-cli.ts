@@ -0,0 +1,168 @@
+#!/usr/bin/env node
+
+// chat-cli.ts - Interactive chat REPL grounded in a project's File Search store
+import * as fs from 'fs';
+import * as readline from 'readline';
+import 'dotenv/config';
+import { FileSearchClient } from '../client.js';
+import { ChatSession } from '../chat.js';
+import { formatFootnotes } from '../citations.js';
+import { formatUsage } from '../cost.js';
+import { renderMarkdown, supportsRendering } from '../render.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { formatNoGroundedAnswer } from '../grounding.js';
+import { GenerationParams, formatBlockedAnswer, mergeGenerationParams, parseSafetySettings, validateGenerationParams } from '../generation.js';
+import { answerLanguageOf, readSystemOption, withAnswerLanguage } from '../prompts.js';
+import { enforceAnswerLanguage, parseAnswerLanguage } from '../answer-language.js';
+import { MAX_CONTEXT_LINES, addCitationContext, projectRoots } from '../cite-context.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValues, loadSettings, projectsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage: npm run chat -- --project <projectId> | --store <storeName> [--store <storeName> ...] [--model <model>] [--temperature <0-2>] [--top-p <0-1>] [--max-output-tokens <n>] [--safety [<category>=]<threshold> ...] [--grounded-only [--min-confidence <0-1>]] [--system <instruction>|@<file>] [--answer-lang <code> [--translate-snippets]] [--cite-context <lines> [--source-root <dir> ...]] [--render]',
+    COMMON_USAGE,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+// The [generation] settings with the flags of this session on top
+function generationParams(settings: Settings): GenerationParams {
+  const number = (flag: string): number | undefined => {
+    const [value] = getArgValues(flag);
+    return value !== undefined ? Number(value) : undefined;
+  };
+  return mergeGenerationParams(settings.generation, validateGenerationParams({
+    temperature: number('--temperature'),
+    topP: number('--top-p'),
+    maxOutputTokens: number('--max-output-tokens'),
+    safety: parseSafetySettings(getArgValues('--safety')),
+  }));
+}
+
+function resolveStores(settings: Settings): string[] {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  if (projectId) {
+    try {
+      const data = JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] };
+      const project = data.projects.find(p => p.id === projectId || p.name === projectId);
+      if (!project) {
+        console.error(`Error: Project not found: ${projectId}`);
+        process.exit(EXIT_CODES.NOT_FOUND);
+      }
+      stores.push(project.storeId);
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores.length > 0 ? stores : usage();
+}
+
+async function main(): Promise<void> {
+  let settings: Settings;
+  try {
+    settings = loadSettings();
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+  configureRuntime(settings);
+
+  let client: FileSearchClient;
+  try {
+    client = buildClientFromArgs(settings);
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+  const [model] = getArgValues('--model');
+  const [minConfidence] = getArgValues('--min-confidence');
+  if (minConfidence !== undefined && !(Number(minConfidence) >= 0 && Number(minConfidence) <= 1)) {
+    console.error(`Error: --min-confidence must be between 0 and 1, got "${minConfidence}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [system] = getArgValues('--system');
+  const answerLang = getArgValues('--answer-lang')[0] || process.env.GEMINI_ANSWER_LANG;
+  const prompt = answerLang ? withAnswerLanguage(settings.prompt, parseAnswerLanguage(answerLang)) : settings.prompt;
+  const translateSnippets = process.argv.includes('--translate-snippets') || prompt.translateSnippets === true;
+  const [citeContextFlag] = getArgValues('--cite-context');
+  if (citeContextFlag !== undefined && !(Number.isInteger(Number(citeContextFlag)) && Number(citeContextFlag) >= 0 && Number(citeContextFlag) <= MAX_CONTEXT_LINES)) {
+    console.error(`Error: --cite-context must be an integer between 0 and ${MAX_CONTEXT_LINES}, got "${citeContextFlag}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const citeContext = citeContextFlag !== undefined ? Number(citeContextFlag) : settings.citeContext;
+  const session = new ChatSession(client, resolveStores(settings), {
+    model: model || settings.model,
+    groundedOnly: process.argv.includes('--grounded-only') || settings.groundedOnly === true,
+    minConfidence: minConfidence ? Number(minConfidence) : settings.minGroundingConfidence,
+    generation: generationParams(settings),
+    fileAttributes: settings.fileAttributes,
+    prompt,
+    system: system !== undefined ? readSystemOption(system) : undefined,
+  });
+
+  const rl = readline.createInterface({
+    input: process.stdin,
+    output: process.stdout,
+    historySize: 200,
+    prompt: 'you> ',
+  });
+
+  // --render draws the Markdown of replies when stdout is a terminal
+  const render = process.argv.includes('--render') && supportsRendering()
+    ? (markdown: string) => renderMarkdown(markdown, { width: process.stdout.columns })
+    : (markdown: string) => markdown;
+  console.log(`Chatting with ${session.stores.join(', ')}. Type /help for commands, Ctrl+D to exit.\n`);
+  rl.prompt();
+
+  for await (const line of rl) {
+    if (line.trim()) {
+      try {
+        const reply = await session.send(line);
+        if (reply.type === 'command') {
+          console.log(reply.message);
+        } else if (reply.type === 'no_grounded_answer') {
+          console.log(`\n🚫 ${formatNoGroundedAnswer(reply)}`);
+        } else if (reply.type === 'blocked') {
+          console.log(`\n⛔ ${formatBlockedAnswer(reply)}`);
+        } else {
+          if (citeContext !== undefined) {
+            const sourceRoots = [...getArgValues('--source-root'), ...projectRoots(projectsFile(), session.stores), '.'];
+            reply.citations = addCitationContext(reply.citations, { sourceRoots, lines: citeContext });
+          }
+          // Replies in another language are shown translated; the chat history keeps them as received
+          const language = answerLanguageOf(prompt, session.stores);
+          if (language) {
+            const report = await enforceAnswerLanguage(client, { question: line, answer: reply.answer, citations: reply.citations, stores: session.stores }, language, { translateSnippets, model: session.model });
+            reply.answer = report.answer;
+            reply.citations = report.citations;
+          }
+          console.log(`\n${render(reply.answer)}`);
+          if (reply.citations.length > 0) {
+            console.log(`\n${render(formatFootnotes(reply.citations))}`);
+          }
+          if (reply.usage) {
+            console.log(`\n(${formatUsage(session.model, reply.usage)})`);
+          }
+        }
+      } catch (error) {
+        const err = toFileSearchError(error);
+        console.error(`❌ [${err.code}] ${err.message}`);
+      }
+      console.log();
+    }
+    rl.prompt();
+  }
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  console.error('Fatal error:', error);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(error));
+});
@@ -0,0 +1,143 @@
+// common.ts - What the command-line tools share: flag lookup, local state paths and the client of the common options
+import * as path from 'path';
+import { fileURLToPath } from 'url';
+import { FileSearchClient, FileSearchClientBuilder, SESSIONS_FILE } from '../client.js';
+import { Settings, resolveSettings } from '../config.js';
+import { createAuthProvider } from '../auth.js';
+import { resolveApiOptions, resolveBackend } from '../backend.js';
+import { configureTelemetry } from '../telemetry.js';
+import { LogLevel, configureLogging, resolveLogLevel } from '../log.js';
+import { installHttpFixtures } from '../testing.js';
+import { parseAclLabels } from '../acl.js';
+import { Notifier } from '../notify.js';
+import { QUOTA_FILE, QuotaScheduler, overrideQuotaConfig } from '../quota.js';
+import { shutdown } from '../shutdown.js';
+import { SHARD_MAP_FILE } from '../shards.js';
+import { QUERY_HISTORY_FILE } from '../history.js';
+import { QUARANTINE_FILE } from '../quarantine.js';
+import { resolveTenant, statePath, tenantPolicy } from '../tenant.js';
+
+// Types
+export interface ClientArgsOptions {
+  notifier?: Notifier;  // Attached when enabled
+  quota?: QuotaScheduler;  // Instead of the one of the settings and flags, see quotaFromArgs
+}
+
+// Command-line options, e.g. --store <storeName> --header "Name: value" --header ...
+export function getArgValue(flag: string): string | undefined {
+  const index = process.argv.indexOf(flag);
+  return index !== -1 ? process.argv[index + 1] : undefined;
+}
+
+export function getArgValues(flag: string): string[] {
+  const values: string[] = [];
+  process.argv.forEach((arg, i) => {
+    if (arg === flag && process.argv[i + 1]) values.push(process.argv[i + 1]);
+  });
+  return values;
+}
+
+// Flags of the common options that take a value, so their values are not mistaken for positional arguments
+export const COMMON_VALUE_FLAGS = [
+  '--config', '--profile', '--tenant', '--api-key', '--backend', '--vertex-project', '--vertex-location', '--api-version', '--header',
+  '--otlp-endpoint', '--proxy', '--ca-bundle', '--acl-labels', '--requests-per-day', '--tokens-per-day', '--log-file', '--record', '--replay',
+];
+
+export const COMMON_USAGE = 'Common options: [--config <file>] [--profile <name>] [--tenant <name> [--allow-cross-tenant]] [--api-key <key>] [--backend gemini|vertex] [--vertex-project <id>] [--vertex-location <region>] [--api-version <version>] [--header "<name>: <value>" ...] [--otlp-endpoint <url>] [--proxy <url>] [--ca-bundle <file>] [--native-certs] [--acl-mode] [--acl-labels <label,...>] [--requests-per-day <n>] [--tokens-per-day <n>] [--verbose | --quiet] [--log-file <file>] [--record <dir> | --replay <dir>]';
+
+// The package directory: dist/bin/ is two levels down
+export const PACKAGE_DIR = path.join(path.dirname(fileURLToPath(import.meta.url)), '..', '..');
+
+export const TENANT = resolveTenant(getArgValue('--tenant') || process.env.GEMINI_FS_TENANT);
+
+const stateFiles = new Map<string, string>();
+
+// Local state lives in the state home (see tenant.ts), resolved when a tool first uses the
+// file rather than when it is imported, so `--help` touches nothing. Files that earlier
+// versions kept in the package are copied there; the working directory is never looked
+// at, since a projects.json there belongs to whatever project the user is in.
+function stateFile(name: string, tenant: string | undefined = TENANT): string {
+  const key = `${tenant || ''}/${name}`;
+  let file = stateFiles.get(key);
+  if (!file) {
+    file = statePath(name, tenant, tenant ? [] : [path.join(PACKAGE_DIR, name)]);
+    stateFiles.set(key, file);
+  }
+  return file;
+}
+
+export const projectsFile = (): string => stateFile('projects.json');
+export const uploadSessionsFile = (): string => stateFile(SESSIONS_FILE);
+export const indexCacheFile = (): string => stateFile('.gemini-index-cache.json');
+export const pendingOperationsFile = (): string => stateFile('.gemini-pending-operations.json');
+export const queryCacheFile = (): string => stateFile('.gemini-query-cache.json');
+export const shardsFile = (): string => stateFile(SHARD_MAP_FILE);
+export const historyFile = (): string => stateFile(QUERY_HISTORY_FILE);
+export const quarantineListFile = (): string => stateFile(QUARANTINE_FILE);
+// Shared by the tenants: the daily limits belong to the API project
+export const quotaStateFile = (): string => stateFile(QUOTA_FILE, undefined);
+
+// The settings file of --config, else gemini-fs.toml in the first of searchDirs holding one,
+// with the section of profile (default: --profile) applied
+export function loadSettings(searchDirs: string[] = [process.cwd()], profile?: string): Settings {
+  return resolveSettings(
+    getArgValue('--config') || process.env.GEMINI_FS_CONFIG,
+    profile ?? (getArgValue('--profile') || process.env.GEMINI_FS_PROFILE),
+    searchDirs
+  );
+}
+
+// Log level and file, telemetry export and HTTP record/replay; secrets are redacted from the log,
+// and level, when given, replaces the one of --verbose, --quiet and the settings
+export function configureRuntime(settings: Settings, secrets: string[] = [], level?: LogLevel): void {
+  configureLogging({
+    level: level || resolveLogLevel(process.argv.includes('--verbose'), process.argv.includes('--quiet'), process.env.GEMINI_LOG_LEVEL || settings.logLevel),
+    file: getArgValue('--log-file') || process.env.GEMINI_LOG_FILE || settings.logFile,
+    secrets: [process.env[settings.apiKeyEnv], getArgValue('--api-key'), ...secrets],
+  });
+  configureTelemetry(getArgValue('--otlp-endpoint') || settings.otlpEndpoint);
+  installHttpFixtures(getArgValue('--record') || process.env.GEMINI_FS_RECORD, getArgValue('--replay') || process.env.GEMINI_FS_REPLAY);
+}
+
+// The daily budgets of [quota] with --requests-per-day and --tokens-per-day (or their variables) on top, counted
+// in the one quota file of the state home, so every tool and tenant spends the same budget
+export function quotaFromArgs(settings: Settings): QuotaScheduler {
+  return new QuotaScheduler(overrideQuotaConfig(settings.quota, {
+    requestsPerDay: getArgValue('--requests-per-day') || process.env.GEMINI_REQUESTS_PER_DAY,
+    tokensPerDay: getArgValue('--tokens-per-day') || process.env.GEMINI_TOKENS_PER_DAY,
+  }), quotaStateFile(), { signal: shutdown.signal });
+}
+
+// Credentials, backend, API version and headers, retries, timeouts, tenant, network, access
+// labels and daily quota, from the flags over the settings; for tools that set more on the builder
+export function clientBuilderFromArgs(settings: Settings, options: ClientArgsOptions = {}): FileSearchClientBuilder {
+  const aclLabels = getArgValues('--acl-labels');
+  const builder = FileSearchClient.builder()
+    .auth(createAuthProvider({ ...settings, apiKey: getArgValue('--api-key') }))
+    .backend(resolveBackend(
+      getArgValue('--backend') || process.env.GEMINI_BACKEND || settings.backend,
+      getArgValue('--vertex-project') || settings.vertexProject,
+      getArgValue('--vertex-location') || settings.vertexLocation
+    ))
+    .api(resolveApiOptions(getArgValue('--api-version') || process.env.GEMINI_API_VERSION || settings.apiVersion, settings.headers, getArgValues('--header')))
+    .retry({ maxAttempts: settings.maxAttempts ? Math.floor(settings.maxAttempts) : undefined })
+    .timeouts(settings.timeouts)
+    .tenant(tenantPolicy(TENANT, process.argv.includes('--allow-cross-tenant')))
+    .network({
+      proxy: getArgValue('--proxy') || settings.proxy,
+      caBundle: getArgValue('--ca-bundle') || settings.caBundle,
+      nativeCerts: process.argv.includes('--native-certs') || settings.nativeCerts,
+    })
+    .acl({
+      enforce: process.argv.includes('--acl-mode') || settings.aclMode === true,
+      labels: aclLabels.length > 0 ? parseAclLabels(aclLabels) : settings.aclLabels,
+    });
+  if (options.notifier?.enabled) builder.notifier(options.notifier);
+  const quota = options.quota || quotaFromArgs(settings);
+  if (quota.enabled) builder.quota(quota);
+  return builder;
+}
+
+export function buildClientFromArgs(settings: Settings, options: ClientArgsOptions = {}): FileSearchClient {
+  return clientBuilderFromArgs(settings, options).build();
+}
@@ -0,0 +1,92 @@
+#!/usr/bin/env node
+
+// compare-cli.ts - Asks the same questions of two stores or two config profiles and diffs the answers
+import * as fs from 'fs';
+import 'dotenv/config';
+import { telemetry } from '../telemetry.js';
+import { CompareSide, DEFAULT_DIVERGENCE_THRESHOLD, compareAnswers, formatComparison } from '../compare.js';
+import { REPORT_FORMATS, ReportFormat } from '../progress.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage: npm run compare -- --questions <questions.jsonl> [--store-a <storeName> ...] [--store-b <storeName> ...] [--profile-a <name>] [--profile-b <name>]',
+    'Options: [--model-a <model>] [--model-b <model>] [--threshold <0-1>] [--concurrency <n>] [--divergent-only] [--format table|json] [--out <report.json>]',
+    COMMON_USAGE,
+    'Each line of the questions file is {"id": "...", "question": "...", "filter": "..."}, as for npm run eval.',
+    'A side without --store-<side> searches the default_store of its profile; --profile-<side> defaults to --profile.',
+    `Answers diverge when either side fails, their word similarity is below --threshold (default: ${DEFAULT_DIVERGENCE_THRESHOLD}) or they cite different files.`,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+// One side of the comparison, from its own profile, stores and model
+function resolveSide(name: 'a' | 'b', defaultProfile: string | undefined): CompareSide {
+  const profile = getArgValue(`--profile-${name}`) || defaultProfile;
+  const settings = loadSettings([process.cwd()], profile);
+  const stores = getArgValues(`--store-${name}`);
+  if (stores.length === 0 && settings.defaultStore) stores.push(settings.defaultStore);
+  if (stores.length === 0) {
+    console.error(`Error: No store for side ${name.toUpperCase()}; pass --store-${name} or a --profile-${name} with a default_store`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return {
+    label: getArgValue(`--profile-${name}`) ? `${profile}: ${stores.join(', ')}` : stores.join(', '),
+    client: buildClientFromArgs(settings),
+    stores,
+    model: getArgValue(`--model-${name}`) || settings.model,
+  };
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const dataset = getArgValue('--questions');
+  if (!dataset) usage();
+  if (!fs.existsSync(dataset)) {
+    console.error(`Error: Questions file not found: ${dataset}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const format = getArgValue('--format') || 'table';
+  if (!REPORT_FORMATS.includes(format as ReportFormat)) {
+    console.error(`Error: --format must be one of: ${REPORT_FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const threshold = getArgValue('--threshold');
+  if (threshold !== undefined && !(Number(threshold) >= 0 && Number(threshold) <= 1)) {
+    console.error(`Error: --threshold must be between 0 and 1, got "${threshold}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const concurrency = getArgValue('--concurrency');
+  if (concurrency !== undefined && !(Number.isInteger(Number(concurrency)) && Number(concurrency) >= 1 && Number(concurrency) <= 50)) {
+    console.error(`Error: --concurrency must be an integer between 1 and 50, got "${concurrency}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const defaultProfile = getArgValue('--profile') || process.env.GEMINI_FS_PROFILE;
+  const settings = loadSettings();
+  configureRuntime(settings);
+
+  const a = resolveSide('a', defaultProfile);
+  const b = resolveSide('b', defaultProfile);
+  const report = await compareAnswers(a, b, dataset, {
+    concurrency: concurrency ? Number(concurrency) : undefined,
+    threshold: threshold !== undefined ? Number(threshold) : undefined,
+    onResult: (result, completed) => console.error(`${result.a.error || result.b.error ? '❌' : result.diverges ? '≠' : '='} [${completed}] ${result.id}`),
+  });
+
+  const outFile = getArgValue('--out');
+  if (outFile) {
+    fs.writeFileSync(outFile, JSON.stringify(report, null, 2));
+    console.error(`💾 Report written to ${outFile}`);
+  }
+  console.log(formatComparison(report, format as ReportFormat, process.argv.includes('--divergent-only')));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,39 @@
+#!/usr/bin/env node
+
+// completions-cli.ts - Print shell completion scripts and the man page
+import * as fs from 'fs';
+import * as path from 'path';
+import { SHELLS, Shell, generateCompletion, generateManPage } from '../completions.js';
+import { EXIT_CODES } from '../errors.js';
+import { PACKAGE_DIR } from './common.js';
+
+const PACKAGE_FILE = path.join(PACKAGE_DIR, 'package.json');
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    `  npm run --silent completions -- ${SHELLS.join('|')}   Print the completion script for a shell`,
+    '  npm run --silent completions -- man   Print the man page (roff)',
+    'Examples:',
+    '  echo \'source <(gemini-fs-completions bash)\' >> ~/.bashrc',
+    '  gemini-fs-completions zsh > "${fpath[1]}/_gemini-fs"',
+    '  echo \'gemini-fs-completions fish | source\' >> ~/.config/fish/config.fish',
+    '  gemini-fs-completions powershell >> $PROFILE',
+    '  gemini-fs-completions man > ~/.local/share/man/man1/gemini-fs.1',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function main(): void {
+  const target = process.argv[2];
+  if (target === 'man') {
+    const { version } = JSON.parse(fs.readFileSync(PACKAGE_FILE, 'utf8')) as { version: string };
+    process.stdout.write(generateManPage(version));
+  } else if (SHELLS.includes(target as Shell)) {
+    process.stdout.write(generateCompletion(target as Shell));
+  } else {
+    usage();
+  }
+}
+
+main();
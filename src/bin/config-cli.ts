@@ -0,0 +1,53 @@
+#!/usr/bin/env node
+
+// config-cli.ts - Scaffold and inspect gemini-fs.toml settings
+import * as path from 'path';
+import 'dotenv/config';
+import { CONFIG_FILE_NAME, initConfig } from '../config.js';
+import { EXIT_CODES, exitCodeFor } from '../errors.js';
+import { hasStoredCredential } from '../credentials.js';
+import { getArgValue, loadSettings } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    `  npm run config -- init [--path <file>] [--force]   Write a commented ${CONFIG_FILE_NAME}`,
+    '  npm run config -- show [--config <file>] [--profile <name>]   Print the resolved settings',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function main(): void {
+  const command = process.argv[2];
+  switch (command) {
+    case 'init': {
+      const filePath = path.resolve(getArgValue('--path') || CONFIG_FILE_NAME);
+      initConfig(filePath, process.argv.includes('--force'));
+      console.log(`✅ Wrote ${filePath}`);
+      break;
+    }
+
+    case 'show': {
+      const settings = loadSettings();
+      const { chunking, ...rest } = settings;
+      console.log(JSON.stringify({
+        ...rest,
+        apiKeySet: Boolean(process.env[settings.apiKeyEnv]),
+        storedApiKey: hasStoredCredential({ account: settings.profile }) || null,
+        chunking: chunking.rules,
+      }, null, 2));
+      break;
+    }
+
+    default:
+      usage();
+  }
+}
+
+try {
+  main();
+} catch (error) {
+  const err = error as Error;
+  console.error(`Error: ${err.message}`);
+  process.exit(exitCodeFor(error));
+}
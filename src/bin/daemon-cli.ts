@@ -0,0 +1,143 @@
+#!/usr/bin/env node
+
+// daemon-cli.ts - Start, inspect and stop the daemon that shares rate limits and quotas between commands
+import * as fs from 'fs';
+import * as path from 'path';
+import { spawn } from 'child_process';
+import { fileURLToPath } from 'url';
+import 'dotenv/config';
+import { Settings } from '../config.js';
+import { QuotaScheduler, overrideQuotaConfig } from '../quota.js';
+import { DAEMON_LOG, DaemonLink, DaemonServer, daemonSocketPath, formatDaemonStatus } from '../daemon.js';
+import { stateHome } from '../tenant.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { shutdown } from '../shutdown.js';
+import { getArgValue, loadSettings, quotaStateFile } from './common.js';
+
+const __filename = fileURLToPath(import.meta.url);
+
+// How long start --detach waits for the daemon to answer
+const DETACH_TIMEOUT_MS = 10_000;
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run daemon -- start [--detach] [--requests-per-minute <n>] [--requests-per-day <n>] [--tokens-per-day <n>] [--socket <path>]',
+    '  npm run daemon -- status [--json] [--socket <path>]',
+    '  npm run daemon -- stop [--socket <path>]',
+    'Common options: [--config <file>] [--profile <name>]',
+    `While the daemon runs, every command started by the user takes its API calls' turn from it, so bursts of commands share one rate limit,`,
+    `one daily budget and the pause after a 429. The socket is ${daemonSocketPath()} (GEMINI_FS_DAEMON_SOCKET); GEMINI_FS_DAEMON=off opts a command out.`,
+    `Rate limit and budgets default to requests_per_minute and [quota] of the settings; --detach writes the daemon's output to ${path.join(stateHome(), DAEMON_LOG)}.`,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positiveArg(flag: string): number | undefined {
+  const raw = getArgValue(flag);
+  if (raw === undefined) return undefined;
+  const value = Number(raw);
+  if (!(value > 0)) {
+    console.error(`Error: ${flag} must be a positive number, got "${raw}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return value;
+}
+
+// Runs start again without --detach in a process of its own, with its output in the log
+// file, and returns once it answers on the socket
+async function detach(socketPath: string): Promise<void> {
+  const logFile = path.join(stateHome(), DAEMON_LOG);
+  fs.mkdirSync(path.dirname(logFile), { recursive: true });
+  const log = fs.openSync(logFile, 'a');
+  const child = spawn(process.execPath, [__filename, ...process.argv.slice(2).filter(arg => arg !== '--detach')], {
+    detached: true,
+    stdio: ['ignore', log, log],
+    env: { ...process.env, GEMINI_FS_DAEMON_SOCKET: socketPath },
+  });
+  child.unref();
+  fs.closeSync(log);
+
+  const deadline = Date.now() + DETACH_TIMEOUT_MS;
+  while (Date.now() < deadline) {
+    if (child.exitCode !== null) break;
+    const link = new DaemonLink(socketPath);
+    const status = await link.status();
+    link.close();
+    if (status) {
+      console.log(`✅ Daemon ${status.pid} listening on ${socketPath}`);
+      console.log(`   Output goes to ${logFile}; stop it with: npm run daemon -- stop`);
+      return;
+    }
+    await new Promise(resolve => setTimeout(resolve, 200));
+  }
+  console.error(`Error: The daemon did not start; see ${logFile}`);
+  process.exit(EXIT_CODES.OPERATION_FAILED);
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  if (!command || process.argv.includes('--help')) usage();
+  const socketPath = getArgValue('--socket') || daemonSocketPath();
+
+  switch (command) {
+    case 'start': {
+      const settings: Settings = loadSettings();
+      const requestsPerMinute = positiveArg('--requests-per-minute') ?? settings.requestsPerMinute;
+      const quota = new QuotaScheduler(overrideQuotaConfig(settings.quota, {
+        requestsPerDay: getArgValue('--requests-per-day'),
+        tokensPerDay: getArgValue('--tokens-per-day'),
+      }), quotaStateFile(), { signal: shutdown.signal });
+      if (process.argv.includes('--detach')) return detach(socketPath);
+
+      const server = new DaemonServer({ socketPath, quota, requestsPerMinute, signal: shutdown.signal });
+      await server.start();
+      shutdown.onFlush(() => server.stop());
+      const limits = [
+        requestsPerMinute ? `${requestsPerMinute} requests per minute` : undefined,
+        quota.config.requestsPerDay ? `${quota.config.requestsPerDay} requests per day` : undefined,
+        quota.config.tokensPerDay ? `${quota.config.tokensPerDay} tokens per day` : undefined,
+      ].filter(Boolean);
+      console.error(`🛰️  Daemon ${process.pid} listening on ${socketPath} (${limits.length > 0 ? limits.join(', ') : 'shared 429 pauses only'})`);
+      await server.stopped();
+      await server.stop();
+      console.error('👋 Daemon stopped');
+      return;
+    }
+
+    case 'status': {
+      const link = new DaemonLink(socketPath);
+      const status = await link.status();
+      link.close();
+      if (!status) {
+        console.error(`No daemon is running on ${socketPath}; commands schedule their own calls`);
+        process.exit(EXIT_CODES.NOT_FOUND);
+      }
+      console.log(process.argv.includes('--json') ? JSON.stringify(status, null, 2) : formatDaemonStatus(status));
+      return;
+    }
+
+    case 'stop': {
+      const link = new DaemonLink(socketPath);
+      const stopped = await link.stop();
+      link.close();
+      if (!stopped) {
+        console.error(`No daemon is running on ${socketPath}`);
+        process.exit(EXIT_CODES.NOT_FOUND);
+      }
+      console.log(`✅ Stopped the daemon on ${socketPath}`);
+      return;
+    }
+
+    default:
+      usage();
+  }
+}
+
+// Ctrl-C disconnects the commands, which go on with their own scheduling
+shutdown.install();
+main().catch(error => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  process.exit(exitCodeFor(err));
+});
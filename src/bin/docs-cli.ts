@@ -0,0 +1,349 @@
+#!/usr/bin/env node
+
+// docs-cli.ts - List, inspect, delete, copy and verify the documents in a File Search store, and report store usage and statistics
+import * as fs from 'fs';
+import * as path from 'path';
+import * as readline from 'readline';
+import 'dotenv/config';
+import { IndexCache } from '../cache.js';
+import { SyncManifest } from '../sync.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { EXIT_CODES, InvalidInputError, exitCodeFor, toFileSearchError } from '../errors.js';
+import { DocumentInfo, DocumentState, formatDocumentMarkdown, groupDocumentParts, parseAge, selectDocuments, storeOfDocument, toDocumentInfo } from '../documents.js';
+import { Semaphore } from '../pipeline.js';
+import { formatBytes } from '../progress.js';
+import { STORAGE_TIERS, formatUsageMarkdown, isStorageTier, usageReport } from '../capacity.js';
+import { QueryCache } from '../query-cache.js';
+import { DEFAULT_TOP_TERMS, corpusStats, formatCorpusStatsMarkdown } from '../stats.js';
+import { SourceRoot, carriedMetadata, copyDocuments, formatCopyPlan, planCopy } from '../copy.js';
+import { formatIntegrityReport, integrityDrift, verifyIntegrity } from '../integrity.js';
+import { DirectoryIngester } from '../ingest.js';
+import { Redactor } from '../redact.js';
+import { RowChunkingOptions } from '../tables.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, indexCacheFile, loadSettings, projectsFile, queryCacheFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run docs -- list [--store <storeName>] [--state active|pending|failed] [--page-size <n>] [--limit <n>] [--page-token <token>] [--parts] [--json]',
+    '  npm run docs -- info <documentName> [--json]',
+    '  npm run docs -- delete <documentName>',
+    '  npm run docs -- delete [--store <storeName>] [--filter <expression>] [--older-than <age>] [--state active|pending|failed] [--dry-run] [--yes]',
+    '  npm run docs -- copy --from <storeName> --to <storeName> [--filter <expression>] [--older-than <age>] [--state active|pending|failed] [--source-root <dir> ...] [--move [--yes]] [--dry-run] [--json]',
+    `  npm run docs -- usage [--store <storeName>]... [--tier ${STORAGE_TIERS.join('|')}] [--max-store-documents <n>] [--json]`,
+    '  npm run docs -- stats [--store <storeName>] [--top <n>] [--json]',
+    '  npm run docs -- verify [--store <storeName>] [--source-root <dir> ...] [--json]',
+    'copy re-uploads the files the matching documents were indexed from, found in the projects of the source store or under --source-root,',
+    'as long as they are unchanged since; documents without their file are listed and left where they are. --move deletes the copied ones from the source store.',
+    'verify compares the sha256 metadata of every document with its local file and the hash recorded at upload, and exits with 8 on drift',
+    '(files edited since upload, checksums not the uploaded ones, synced files gone from the store), or 4 when files could not be checked.',
+    COMMON_USAGE,
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+interface Project {
+  storeId: string;
+  path: string;
+  codeChunking?: boolean;
+  rowChunking?: RowChunkingOptions;
+}
+
+function loadProjects(): Project[] {
+  try {
+    return (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: Project[] }).projects;
+  } catch (error) {
+    return [];  // No registered projects
+  }
+}
+
+// Same bookkeeping as gemini_delete_document, so the next sync uploads the files again
+function forgetDeleted(storeName: string, documentNames: string[]): string[] {
+  new IndexCache(indexCacheFile()).forgetDocuments(storeName, documentNames);
+  const sourceFiles: string[] = [];
+  for (const project of loadProjects().filter(p => p.storeId === storeName)) {
+    const manifest = SyncManifest.load(project.path, project.storeId);
+    for (const documentName of documentNames) {
+      const sourceFile = manifest.forgetDocument(documentName);
+      if (sourceFile) sourceFiles.push(sourceFile);
+    }
+  }
+  return sourceFiles;
+}
+
+function confirm(question: string): Promise<boolean> {
+  if (!process.stdin.isTTY) {
+    throw new InvalidInputError('Refusing to delete without confirmation; pass --yes to skip the prompt');
+  }
+  const rl = readline.createInterface({ input: process.stdin, output: process.stderr });
+  return new Promise(resolve => rl.question(question, answer => {
+    rl.close();
+    resolve(/^y(es)?$/i.test(answer.trim()));
+  }));
+}
+
+function formatDocumentLine(info: DocumentInfo): string {
+  const parts = info.parts ? ` (${info.parts.length} parts)` : '';
+  return `${info.state.padEnd(8)} ${String(info.sizeBytes).padStart(10)}  ${(info.mimeType || '').padEnd(24)} ${info.displayName || info.name}${parts}  ${info.name}`;
+}
+
+function countArg(flag: string): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= 1)) {
+    console.error(`Error: ${flag} must be a positive integer, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+function documentArg(): string {
+  const documentName = process.argv[3];
+  if (!documentName || !storeOfDocument(documentName)) usage();
+  return documentName;
+}
+
+async function main(): Promise<void> {
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+  const client = buildClientFromArgs(settings);
+  const json = process.argv.includes('--json');
+
+  switch (process.argv[2]) {
+    case 'list': {
+      const storeName = getArgValue('--store') || settings.defaultStore;
+      if (!storeName) usage();
+      const state = getArgValue('--state');
+      const pages = client.paginateDocuments(storeName, {
+        pageSize: countArg('--page-size'),
+        limit: countArg('--limit'),
+        pageToken: getArgValue('--page-token'),
+      });
+      // Text output is printed a page at a time as the pages arrive; the parts of files
+      // uploaded in parts are held back and printed as one document each at the end,
+      // unless --parts lists them one by one
+      const showParts = process.argv.includes('--parts');
+      const documents: DocumentInfo[] = [];
+      const parts: DocumentInfo[] = [];
+      let listed = 0;
+      for await (const page of pages.pages()) {
+        const infos = page
+          .map(document => toDocumentInfo(document, settings.chunking))
+          .filter(info => !state || info.state === state.toUpperCase());
+        if (json) documents.push(...infos);
+        else for (const info of infos) {
+          if (!showParts && info.metadata.part_of !== undefined) parts.push(info);
+          else console.log(formatDocumentLine(info));
+        }
+        listed += infos.length;
+      }
+      for (const info of groupDocumentParts(parts)) console.log(formatDocumentLine(info));
+      if (json) {
+        console.log(JSON.stringify(showParts ? documents : groupDocumentParts(documents), null, 2));
+      } else {
+        console.log(`\n${listed} documents in ${storeName}${pages.pageCount > 1 ? ` (${pages.pageCount} pages)` : ''}`);
+      }
+      if (pages.nextPageToken) console.error(`⏭️  More documents: --page-token ${pages.nextPageToken}`);
+      break;
+    }
+
+    case 'info': {
+      const info = toDocumentInfo(await client.getDocument(documentArg()), settings.chunking);
+      console.log(json ? JSON.stringify(info, null, 2) : formatDocumentMarkdown(info));
+      break;
+    }
+
+    case 'delete': {
+      const named = process.argv[3];
+      if (named && !named.startsWith('--')) {
+        const documentName = documentArg();
+        const storeName = storeOfDocument(documentName) || '';
+        await client.deleteDocument(documentName);
+        console.log(`✅ Deleted ${documentName}`);
+        for (const sourceFile of forgetDeleted(storeName, [documentName])) {
+          console.log(`   Source file: ${sourceFile} (uploaded again on the next sync)`);
+        }
+        break;
+      }
+
+      // Bulk deletion of the documents matching the criteria
+      const storeName = getArgValue('--store') || settings.defaultStore;
+      const filter = getArgValue('--filter');
+      const olderThan = getArgValue('--older-than');
+      const state = getArgValue('--state');
+      if (!storeName || (!filter && !olderThan && !state)) usage();
+      if (state && !['active', 'pending', 'failed'].includes(state)) usage();
+      const documents = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document));
+      const selected = selectDocuments(documents, {
+        filter,
+        olderThanMs: olderThan ? parseAge(olderThan) : undefined,
+        state: state as DocumentState | undefined,
+      });
+      const selectedBytes = selected.reduce((sum, info) => sum + info.sizeBytes, 0);
+      for (const info of selected) console.log(formatDocumentLine(info));
+      if (selected.length === 0) {
+        console.log(`No documents in ${storeName} match`);
+        break;
+      }
+      const summary = `${selected.length} of ${documents.length} documents (${formatBytes(selectedBytes)}) in ${storeName}`;
+      if (process.argv.includes('--dry-run')) {
+        console.log(`\n🔍 Dry run: ${summary} would be deleted`);
+        break;
+      }
+      if (!process.argv.includes('--yes') && !(await confirm(`Delete ${summary}? [y/N] `))) {
+        console.log('Nothing deleted');
+        break;
+      }
+
+      const semaphore = new Semaphore(5);
+      const deleted: string[] = [];
+      const failures: { name: string; error: Error }[] = [];
+      await Promise.all(selected.map(info => semaphore.run(async () => {
+        try {
+          await client.deleteDocument(info.name);
+          deleted.push(info.name);
+        } catch (error) {
+          failures.push({ name: info.name, error: error as Error });
+        }
+      })));
+      const sourceFiles = forgetDeleted(storeName, deleted);
+      console.log(`\n✅ Deleted ${deleted.length} of ${selected.length} documents from ${storeName}`);
+      if (sourceFiles.length > 0) console.log(`   ${sourceFiles.length} source files are uploaded again on the next sync`);
+      if (failures.length > 0) {
+        for (const failure of failures) console.error(`❌ ${failure.name}: ${failure.error.message}`);
+        process.exitCode = exitCodeFor(failures[0].error);
+      }
+      break;
+    }
+
+    case 'copy': {
+      const from = getArgValue('--from');
+      const to = getArgValue('--to');
+      if (!from || !to) usage();
+      const state = getArgValue('--state');
+      if (state && !['active', 'pending', 'failed'].includes(state)) usage();
+      const move = process.argv.includes('--move');
+      await client.stores.getStore(to);  // Fails before anything is uploaded when the store does not exist
+      const documents = (await client.listDocuments(from)).map(document => toDocumentInfo(document));
+      const filter = getArgValue('--filter');
+      const olderThan = getArgValue('--older-than');
+      const selected = filter || olderThan || state
+        ? selectDocuments(documents, { filter, olderThanMs: olderThan ? parseAge(olderThan) : undefined, state: state as DocumentState | undefined })
+        : documents;
+
+      // The projects synced with the source store know what each file was when it was uploaded
+      const projects = loadProjects().filter(p => p.storeId === from && fs.existsSync(p.path));
+      const roots: SourceRoot[] = [
+        ...projects.map(project => ({ rootDir: project.path, manifest: SyncManifest.load(project.path, from) })),
+        ...getArgValues('--source-root').map(rootDir => ({ rootDir: path.resolve(rootDir) })),
+      ];
+      const plan = planCopy(from, to, selected, documents, { roots, cache: new IndexCache(indexCacheFile()) });
+      if (plan.sources.length === 0) {
+        console.log(`No documents in ${from} match`);
+        break;
+      }
+      const ready = plan.sources.filter(source => source.status === 'ready');
+      if (process.argv.includes('--dry-run') || ready.length === 0) {
+        console.log(json ? JSON.stringify(plan, null, 2) : formatCopyPlan(plan, move));
+        if (ready.length === 0) process.exitCode = EXIT_CODES.NOT_FOUND;
+        break;
+      }
+      if (!json) console.log(formatCopyPlan(plan, move));
+      if (move && !process.argv.includes('--yes')
+        && !(await confirm(`Move ${ready.length} files (${ready.reduce((sum, source) => sum + source.documents.length, 0)} documents) from ${from} to ${to}? [y/N] `))) {
+        console.log('Nothing moved');
+        break;
+      }
+
+      const project = projects[0];
+      const ingester = new DirectoryIngester({
+        fileMetadata: carriedMetadata(plan),
+        codeChunking: project?.codeChunking,
+        rowChunking: project?.rowChunking,
+        cache: new IndexCache(indexCacheFile()),
+        chunking: settings.chunking,
+        concurrency: settings.concurrency ? Math.floor(settings.concurrency) : undefined,
+        requestsPerMinute: settings.requestsPerMinute,
+        extractLocally: settings.extractLocally,
+        notebooks: { stripOutputs: settings.stripNotebookOutputs },
+        emails: { stripQuotes: settings.stripQuotedReplies },
+        normalizeEncoding: settings.normalizeEncoding,
+        detectLanguage: settings.detectLanguage,
+        fileAttributes: settings.fileAttributes,
+        redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+        transforms: settings.transforms,
+        partSize: settings.partSize ? Math.floor(settings.partSize) : undefined,
+        progressBars: true,
+      });
+      const summary = await copyDocuments(client, plan, { ingester, move });
+      const sourceFiles = forgetDeleted(from, summary.deleted);
+      if (json) {
+        console.log(JSON.stringify({ from, to, ...summary, flagged: summary.flagged.map(({ path: source, status, documents: docs }) => ({ path: source, status, documents: docs.map(d => d.name) })) }, null, 2));
+      } else {
+        console.log(`\n✅ ${move ? 'Moved' : 'Copied'} ${summary.copied.length} of ${plan.sources.length} files from ${from} to ${to}`);
+        if (move) console.log(`   Deleted ${summary.deleted.length} documents from ${from}${sourceFiles.length > 0 ? `; ${sourceFiles.length} source files are uploaded to it again on its next sync` : ''}`);
+        for (const source of summary.flagged) console.error(`⚠️  Not copied (${source.status === 'missing' ? 'source file not found' : source.status === 'changed' ? 'source file changed since it was uploaded' : 'no record of the uploaded content'}): ${source.path}`);
+      }
+      for (const failure of summary.failures) console.error(`❌ ${failure.path}: ${failure.reason}`);
+      if (summary.failures.length > 0) process.exitCode = EXIT_CODES.OPERATION_FAILED;
+      else if (summary.flagged.length > 0) process.exitCode = EXIT_CODES.NOT_FOUND;
+      break;
+    }
+
+    case 'usage': {
+      const tier = getArgValue('--tier') || settings.storageTier || 'free';
+      if (!isStorageTier(tier)) usage();
+      const maxStoreDocuments = getArgValue('--max-store-documents');
+      if (maxStoreDocuments !== undefined && !(Number(maxStoreDocuments) >= 1)) usage();
+      const report = await usageReport(client, getArgValues('--store'), {
+        tier,
+        maxStoreDocuments: maxStoreDocuments !== undefined ? Math.floor(Number(maxStoreDocuments)) : settings.maxStoreDocuments,
+        indexCache: new IndexCache(indexCacheFile()),
+      });
+      console.log(json ? JSON.stringify(report, null, 2) : formatUsageMarkdown(report));
+      break;
+    }
+
+    case 'stats': {
+      const storeName = getArgValue('--store') || settings.defaultStore;
+      if (!storeName) usage();
+      // Terms are counted in the chunks cited by answers the MCP server cached
+      const stats = await corpusStats(client, storeName, {
+        queryCache: new QueryCache(queryCacheFile()),
+        topTerms: countArg('--top') || DEFAULT_TOP_TERMS,
+      });
+      console.log(json ? JSON.stringify(stats, null, 2) : formatCorpusStatsMarkdown(stats));
+      break;
+    }
+
+    case 'verify': {
+      const storeName = getArgValue('--store') || settings.defaultStore;
+      if (!storeName) usage();
+      // Same roots as copy; without any, the working directory
+      const roots: SourceRoot[] = [
+        ...loadProjects().filter(p => p.storeId === storeName && fs.existsSync(p.path))
+          .map(project => ({ rootDir: project.path, manifest: SyncManifest.load(project.path, storeName) })),
+        ...getArgValues('--source-root').map(rootDir => ({ rootDir: path.resolve(rootDir) })),
+      ];
+      const report = await verifyIntegrity(client, storeName, {
+        roots: roots.length > 0 ? roots : [{ rootDir: process.cwd() }],
+        cache: new IndexCache(indexCacheFile()),
+      });
+      console.log(json ? JSON.stringify(report, null, 2) : formatIntegrityReport(report));
+      if (integrityDrift(report).length > 0) process.exitCode = EXIT_CODES.OPERATION_FAILED;
+      else if ((report.counts.ok || 0) < report.files.length) process.exitCode = EXIT_CODES.NOT_FOUND;
+      break;
+    }
+
+    default:
+      usage();
+  }
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,133 @@
+#!/usr/bin/env node
+
+// embed-cli.ts - Embed the text of a directory with Gemini embeddings into a local vector file
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { DEFAULT_EMBEDDING_MODEL } from '../client.js';
+import { parseMetadataPairs } from '../metadata.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { MimeRegistry } from '../file-types.js';
+import { DirectoryIngester } from '../ingest.js';
+import { isTextMimeType } from '../encoding.js';
+import { Redactor } from '../redact.js';
+import { DEFAULT_PASSAGE_SIZE, EmbeddingRecord, embedTexts, signature, splitPassages, vectorFormat, vectorMetadataPath, writeVectorFile } from '../embeddings.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings } from './common.js';
+
+const VALUE_FLAGS = [
+  '--out', '--include', '--exclude', '--metadata', '--model', '--dimensions', '--passage-size',
+  '--config', '--profile', '--tenant', '--api-key', '--backend', '--vertex-project', '--vertex-location', '--api-version', '--header', '--otlp-endpoint', '--proxy', '--ca-bundle', '--log-file', '--record', '--replay',
+];
+
+function usage(): never {
+  console.error([
+    'Usage: npm run embed -- <dir> --out <file.jsonl|file.fvecs> [--include <glob> ...] [--exclude <glob> ...] [--metadata key=value ...] [--model <model>] [--dimensions <n>] [--passage-size <chars>] [--code-chunking] [--strip-notebook-outputs] [--strip-quoted-replies]',
+    COMMON_USAGE,
+    'Files are prepared as ingest would upload them (conversions, redaction, transforms) and split into passages;',
+    '.jsonl writes one record per line with its vector, .fvecs writes FAISS vectors and their texts to <file>.meta.jsonl.',
+    'Search the file with: npm run local-search -- <file> "<query>"',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(2);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+function integerArgument(flag: string, min: number, max: number): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= min && Number(value) <= max)) {
+    console.error(`Error: ${flag} must be an integer between ${min} and ${max}, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+// The passages of every text document ingest would upload; binary uploads (PDF and
+// the like, unless extract_locally converts them) have no local text and are skipped
+function collectPassages(ingester: DirectoryIngester, rootDir: string, passageSize: number): Omit<EmbeddingRecord, 'vector' | 'model' | 'lsh'>[] {
+  const passages: Omit<EmbeddingRecord, 'vector' | 'model' | 'lsh'>[] = [];
+  for (const filePath of ingester.collect(rootDir)) {
+    const prepared = ingester.prepare(rootDir, filePath);
+    if (prepared.skipped) console.error(`⚠️  Skipped (${prepared.skipped}): ${prepared.path}`);
+    for (const document of prepared.documents) {
+      try {
+        const source = document.options.displayName || prepared.path;
+        if (!isTextMimeType(document.options.mimeType)) {
+          console.error(`⚠️  Skipped (no local text, ${document.options.mimeType || 'unknown type'}): ${source}`);
+          continue;
+        }
+        const texts = splitPassages(fs.readFileSync(document.uploadPath, 'utf8'), { size: passageSize, overlap: Math.floor(passageSize / 10) });
+        texts.forEach((text, passage) => passages.push({ id: `${source}#${passage}`, source, passage, text, metadata: document.options.metadata }));
+      } finally {
+        if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+      }
+    }
+  }
+  return passages;
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const [rootDir] = positionalArgs();
+  const out = getArgValue('--out');
+  if (!rootDir || !out) usage();
+  if (!fs.existsSync(rootDir) || !fs.statSync(rootDir).isDirectory()) {
+    console.error(`Error: Directory not found: ${rootDir}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const dimensions = integerArgument('--dimensions', 1, 3072);
+  const passageSize = integerArgument('--passage-size', 200, 100000) ?? DEFAULT_PASSAGE_SIZE;
+
+  const settings: Settings = loadSettings([path.resolve(rootDir), process.cwd()]);
+  configureRuntime(settings);
+
+  const client = buildClientFromArgs(settings);
+
+  const metadata = parseMetadataPairs(getArgValues('--metadata'));
+  const ingester = new DirectoryIngester({
+    include: getArgValues('--include'),
+    exclude: getArgValues('--exclude'),
+    metadata: Object.keys(metadata).length > 0 ? metadata : undefined,
+    mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+    codeChunking: process.argv.includes('--code-chunking'),
+    extractLocally: settings.extractLocally,
+    notebooks: { stripOutputs: process.argv.includes('--strip-notebook-outputs') || settings.stripNotebookOutputs },
+    emails: { stripQuotes: process.argv.includes('--strip-quoted-replies') || settings.stripQuotedReplies },
+    normalizeEncoding: settings.normalizeEncoding !== false,
+    detectLanguage: settings.detectLanguage !== false,
+    fileAttributes: settings.fileAttributes !== false,
+    redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules || []),
+    transforms: settings.transforms,
+  });
+
+  const passages = collectPassages(ingester, rootDir, passageSize);
+  if (passages.length === 0) {
+    console.error(`Error: No text to embed in ${rootDir}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const model = (getArgValue('--model') || DEFAULT_EMBEDDING_MODEL).replace(/^models\//, '');
+  const vectors = await embedTexts(client, passages.map(passage => passage.text), { model, dimensions }, done => {
+    console.error(`🧮 Embedded ${done}/${passages.length} passages`);
+  });
+  const records: EmbeddingRecord[] = passages.map((passage, i) => ({
+    ...passage,
+    model,
+    lsh: signature(vectors[i]),
+    vector: vectors[i],
+  }));
+  writeVectorFile(out, records);
+  console.log(`✅ Wrote ${records.length} vectors of ${records[0].vector.length} dimensions to ${out}`
+    + (vectorFormat(out) === 'fvecs' ? ` (texts in ${vectorMetadataPath(out)})` : ''));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,104 @@
+#!/usr/bin/env node
+
+// eval-cli.ts - Scores retrieval and answer quality of a store against a labelled dataset
+import * as fs from 'fs';
+import 'dotenv/config';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { ANSWER_MATCHES, AnswerMatch, formatScorecard, runEvaluation } from '../eval.js';
+import { REPORT_FORMATS, ReportFormat } from '../progress.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValues, loadSettings, projectsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage: npm run eval -- <dataset.jsonl> [--project <projectId> | --store <storeName> ...]',
+    'Options: [--match exact|regex|embedding] [--threshold <0-1>] [--concurrency <n>] [--model <model>] [--format table|json] [--out <scorecard.json>]',
+    COMMON_USAGE,
+    'Each dataset line is {"question": "...", "expectedSource": "src/lib.rs", "expectedAnswer": "...", "match": "exact"}.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function resolveStores(settings: Settings): string[] {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores.length > 0 ? stores : usage();
+}
+
+async function main(): Promise<void> {
+  const dataset = process.argv[2];
+  if (!dataset || dataset.startsWith('--')) usage();
+  if (!fs.existsSync(dataset)) {
+    console.error(`Error: Dataset not found: ${dataset}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const [match] = getArgValues('--match');
+  if (match !== undefined && !ANSWER_MATCHES.includes(match as AnswerMatch)) {
+    console.error(`Error: --match must be one of: ${ANSWER_MATCHES.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [format = 'table'] = getArgValues('--format');
+  if (!REPORT_FORMATS.includes(format as ReportFormat)) {
+    console.error(`Error: --format must be one of: ${REPORT_FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [threshold] = getArgValues('--threshold');
+  if (threshold !== undefined && !(Number(threshold) >= 0 && Number(threshold) <= 1)) {
+    console.error(`Error: --threshold must be between 0 and 1, got "${threshold}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [concurrency] = getArgValues('--concurrency');
+  if (concurrency !== undefined && !(Number.isInteger(Number(concurrency)) && Number(concurrency) >= 1 && Number(concurrency) <= 50)) {
+    console.error(`Error: --concurrency must be an integer between 1 and 50, got "${concurrency}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const stores = resolveStores(settings);
+
+  const client = buildClientFromArgs(settings);
+
+  const [model] = getArgValues('--model');
+  const scorecard = await runEvaluation(client, stores, dataset, {
+    concurrency: concurrency ? Number(concurrency) : undefined,
+    model: model || settings.model,
+    match: match as AnswerMatch | undefined,
+    threshold: threshold ? Number(threshold) : undefined,
+    onResult: (result, completed) => console.error(`${result.error ? '❌' : '✅'} [${completed}] ${result.id}`),
+  });
+
+  const [outFile] = getArgValues('--out');
+  if (outFile) {
+    fs.writeFileSync(outFile, JSON.stringify(scorecard, null, 2));
+    console.error(`💾 Scorecard written to ${outFile}`);
+  }
+  console.log(formatScorecard(scorecard, format as ReportFormat));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
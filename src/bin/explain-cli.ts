@@ -0,0 +1,136 @@
+#!/usr/bin/env node
+
+// explain-cli.ts - Explain one file section by section, citing the related documents of its store
+import * as fs from 'fs';
+import 'dotenv/config';
+import { IndexCache } from '../cache.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { parseFilterExpression } from '../search.js';
+import { DirectoryIngester } from '../ingest.js';
+import { Redactor } from '../redact.js';
+import { explainFile, formatExplanationMarkdown } from '../explain.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, COMMON_VALUE_FLAGS, buildClientFromArgs, configureRuntime, getArgValues, indexCacheFile, loadSettings, projectsFile } from './common.js';
+
+// Flags that take a value, so their values are not mistaken for the file
+const VALUE_FLAGS = [
+  '--store', '--project', '--root', '--section-lines', '--max-sections', '--concurrency', '--filter', '--format', '--model',
+  ...COMMON_VALUE_FLAGS,
+];
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run explain -- [--project <projectId> | --store <storeName> ...] <path>',
+    'Options: [--root <dir>] [--no-upload] [--section-lines <n>] [--max-sections <n>] [--concurrency <n>] [--filter <expression>] [--format markdown|json] [--model <model>]',
+    COMMON_USAGE,
+    'The file is uploaded to the first store unless it is already indexed there or --no-upload is given.',
+    'Display names are relative to --root, the project directory with --project, or else the working directory.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(2);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+function positiveInteger(flag: string): number | undefined {
+  const [value] = getArgValues(flag);
+  if (value === undefined) return undefined;
+  if (!/^\d+$/.test(value) || Number(value) < 1) {
+    console.error(`Error: ${flag} must be a positive integer, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+// The stores to search and the directory display names are relative to
+function resolveTarget(settings: Settings): { stores: string[]; rootDir?: string } {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  let rootDir: string | undefined;
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string; path: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: typeof projects }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.unshift(project.storeId);
+    rootDir = project.path;
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores.length > 0 ? { stores, rootDir } : usage();
+}
+
+async function main(): Promise<void> {
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const [format = 'markdown'] = getArgValues('--format');
+  if (format !== 'markdown' && format !== 'json') {
+    console.error('Error: --format must be one of: markdown, json');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [filePath] = positionalArgs();
+  if (!filePath) usage();
+  if (!fs.existsSync(filePath) || !fs.statSync(filePath).isFile()) {
+    console.error(`Error: File not found: ${filePath}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const sectionLines = positiveInteger('--section-lines');
+  const maxSections = positiveInteger('--max-sections');
+  const concurrency = positiveInteger('--concurrency');
+  const [filter] = getArgValues('--filter');
+  const metadataFilter = filter ? parseFilterExpression(filter) : undefined;
+  const target = resolveTarget(settings);
+  const [rootFlag] = getArgValues('--root');
+
+  const client = buildClientFromArgs(settings);
+
+  const [modelFlag] = getArgValues('--model');
+  const indexCache = new IndexCache(indexCacheFile());
+  const ingester = process.argv.includes('--no-upload')
+    ? undefined
+    : new DirectoryIngester({
+      cache: indexCache,
+      extractLocally: settings.extractLocally,
+      notebooks: { stripOutputs: settings.stripNotebookOutputs },
+      emails: { stripQuotes: settings.stripQuotedReplies },
+      normalizeEncoding: settings.normalizeEncoding,
+      detectLanguage: settings.detectLanguage,
+      fileAttributes: settings.fileAttributes,
+      redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+      transforms: settings.transforms,
+    });
+  const explanation = await explainFile(client, target.stores, filePath, {
+    rootDir: rootFlag || target.rootDir || process.cwd(),
+    ingester,
+    indexCache,
+    sectionLines,
+    maxSections,
+    concurrency,
+    model: modelFlag || settings.model || client.model,
+    metadataFilter,
+    generation: settings.generation,
+  });
+  console.error(`💡 ${explanation.sections.length} ${explanation.sections.length === 1 ? 'section' : 'sections'} explained in ${explanation.requests} ${explanation.requests === 1 ? 'request' : 'requests'}, ${explanation.related.length} related documents`);
+  console.log(format === 'json' ? JSON.stringify(explanation, null, 2) : formatExplanationMarkdown(explanation));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,106 @@
+#!/usr/bin/env node
+
+// history-cli.ts - List, show and replay the queries recorded in the local query history
+import 'dotenv/config';
+import { FileSearchClient } from '../client.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { parseAge } from '../documents.js';
+import { QueryHistory, formatHistoryEntry, replayQuery } from '../history.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, historyFile, loadSettings } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run history -- list [--limit <n>] [--store <storeName>] [--since <age>] [--search <text>] [--json]',
+    '  npm run history -- show <id> [--json]',
+    '  npm run history -- replay <id> [--store <storeName> ...] [--model <model>] [--json]',
+    COMMON_USAGE,
+    'Queries are recorded by npm run query, gemini_search_project and POST /query unless query_history = false; --since takes an age such as 7d.',
+    'replay asks the question again with its stores and parameters (or other --store and --model) and reports whether the answer and its sources changed.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function idArg(): number {
+  const id = Number(process.argv[3]);
+  if (!Number.isInteger(id) || id < 1) usage();
+  return id;
+}
+
+function buildClient(settings: Settings): FileSearchClient {
+  return buildClientFromArgs(settings);
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  const json = process.argv.includes('--json');
+  const history = new QueryHistory(historyFile());
+
+  switch (command) {
+    case 'list': {
+      const limit = getArgValue('--limit');
+      if (limit !== undefined && !(Number.isInteger(Number(limit)) && Number(limit) >= 1)) {
+        console.error(`Error: --limit must be a positive integer, got "${limit}"`);
+        process.exit(EXIT_CODES.INVALID_INPUT);
+      }
+      const since = getArgValue('--since');
+      const entries = history.list({
+        limit: limit !== undefined ? Number(limit) : undefined,
+        store: getArgValue('--store'),
+        since: since !== undefined ? new Date(Date.now() - parseAge(since)) : undefined,
+        search: getArgValue('--search'),
+      });
+      if (json) {
+        console.log(JSON.stringify(entries, null, 2));
+      } else if (entries.length === 0) {
+        console.log('No queries recorded yet');
+      } else {
+        for (const entry of entries) {
+          const outcome = entry.error ? `❌ ${entry.error}` : `${entry.sources.length} sources`;
+          console.log(`${String(entry.id).padStart(5)}  ${entry.timestamp}  ${String(entry.latencyMs).padStart(6)}ms  ${entry.source.padEnd(6)}  ${entry.question.replace(/\s+/g, ' ').slice(0, 80)}  (${outcome})`);
+        }
+      }
+      break;
+    }
+
+    case 'show': {
+      const entry = history.get(idArg());
+      console.log(json ? JSON.stringify(entry, null, 2) : formatHistoryEntry(entry));
+      break;
+    }
+
+    case 'replay': {
+      const id = idArg();
+      const settings: Settings = loadSettings();
+      configureRuntime(settings);
+
+      const result = await replayQuery(buildClient(settings), history, id, {
+        stores: getArgValues('--store'),
+        model: getArgValue('--model'),
+      });
+      if (json) {
+        console.log(JSON.stringify(result, null, 2));
+      } else {
+        console.log(formatHistoryEntry(result.replay));
+        console.log('');
+        console.log(`Compared with #${result.original.id}: answer ${result.answerChanged ? 'changed' : 'unchanged'}, latency ${result.latencyDeltaMs >= 0 ? '+' : ''}${result.latencyDeltaMs}ms`);
+        for (const source of result.sourcesAdded) console.log(`   + ${source}`);
+        for (const source of result.sourcesRemoved) console.log(`   - ${source}`);
+      }
+      if (result.replay.error) process.exitCode = EXIT_CODES.API_ERROR;
+      break;
+    }
+
+    default:
+      usage();
+  }
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
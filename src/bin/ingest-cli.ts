@@ -0,0 +1,521 @@
+#!/usr/bin/env node
+
+// ingest-cli.ts - Upload web pages, sitemaps, a git repository at a ref, a Drive folder, a Cloud Storage prefix or a staged directory to a store, wait for pending indexing, roll synced directories back and verify manifests against the store
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { FileSearchClient } from '../client.js';
+import { IndexCache } from '../cache.js';
+import { DocumentMetadata, parseMetadataPairs } from '../metadata.js';
+import { parseAclLabels, withAclLabels } from '../acl.js';
+import { Settings } from '../config.js';
+import { Notifier } from '../notify.js';
+import { telemetry } from '../telemetry.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { parseFailurePolicy } from '../pipeline.js';
+import { collectSitemapUrls, ingestUrls, parseHttpUrl } from '../web.js';
+import { ingestGitRepository } from '../git.js';
+import { DRIVE_SCOPE, DriveSource, GCS_SCOPE, GcsSource, Source, googleSourceAuth, ingestSource } from '../sources.js';
+import { MimeRegistry } from '../file-types.js';
+import { DirectoryIngester, retryQuarantined, waitForPendingUploads } from '../ingest.js';
+import { PendingOperations } from '../operations.js';
+import { Quarantine } from '../quarantine.js';
+import { RowChunkingOptions } from '../tables.js';
+import { RedactionPolicy, Redactor } from '../redact.js';
+import { formatBytes } from '../progress.js';
+import { STAGE_MANIFEST_FILE, pushStaged, readStageManifest, stageDirectory } from '../staging.js';
+import { shutdown } from '../shutdown.js';
+import { MANIFEST_FILE, SyncManifest, reconcileSync, rollbackSync } from '../sync.js';
+import { ManifestVerification, formatManifestVerification, manifestMatches, verifyStageManifest, verifySyncManifest } from '../manifest.js';
+import { SnapshotStore, planRollback } from '../snapshots.js';
+import { CI_REPORT_FORMATS, CiReportWriter, parseCiReportTarget } from '../ci-report.js';
+import { COMMON_USAGE, COMMON_VALUE_FLAGS, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, indexCacheFile, loadSettings, pendingOperationsFile, projectsFile, quarantineListFile } from './common.js';
+
+const VALUE_FLAGS = [
+  ...COMMON_VALUE_FLAGS,
+  '--store', '--project', '--metadata', '--acl', '--concurrency', '--max-pages', '--ref', '--since', '--include', '--exclude', '--notify-command', '--notify-webhook',
+  '--rows-per-document', '--metadata-columns', '--redaction-policy', '--redaction-rules', '--to', '--timeout',
+  '--mime-map', '--mime-type', '--source-credentials', '--report', '--on-error',
+];
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run ingest -- url <url> [<url> ...] (--store <storeName> | --project <projectId>) [--sitemap] [--metadata key=value ...] [--acl <label,...>] [--concurrency <n>] [--max-pages <n>] [--keep-old] [--wait | --no-wait]',
+    '  npm run ingest -- git <repo-or-path> (--store <storeName> | --project <projectId>) [--ref <ref>] [--since <ref>] [--include <glob> ...] [--exclude <glob> ...] [--metadata key=value ...] [--acl <label,...>] [--concurrency <n>] [--code-chunking] [--row-chunking [--rows-per-document <n>] [--metadata-columns <name,...>]] [--with-summaries] [--strip-notebook-outputs] [--strip-quoted-replies] [--no-normalize-encoding] [--no-detect-language] [--no-file-attributes] [--redaction-policy off|block|mask|tag] [--redaction-rules <file> ...] [--wait | --no-wait]',
+    '  npm run ingest -- gdrive <folder-id|folder-url> (--store <storeName> | --project <projectId>) [--source-credentials <key.json>] [--include <glob> ...] [--exclude <glob> ...] [--metadata key=value ...] [--acl <label,...>] [--concurrency <n>] [--code-chunking] [--row-chunking [--rows-per-document <n>] [--metadata-columns <name,...>]] [--with-summaries] [--no-file-attributes] [--redaction-policy off|block|mask|tag] [--redaction-rules <file> ...] [--keep-old] [--wait | --no-wait]',
+    '  npm run ingest -- gcs gs://<bucket>/<prefix> (--store <storeName> | --project <projectId>) [--source-credentials <key.json> | --anonymous] [same options as gdrive]',
+    `${COMMON_USAGE} [--notify-command <cmd>] [--notify-webhook <url>] [--report ${CI_REPORT_FORMATS.join('|')}:<file> ...] [--on-error continue|fail-fast|abort-after:<n>]`,
+    '  npm run ingest -- stage <dir> <stage-dir> [--include <glob> ...] [--exclude <glob> ...] [--metadata key=value ...] [--acl <label,...>] [--code-chunking] [--row-chunking [--rows-per-document <n>] [--metadata-columns <name,...>]] [--strip-notebook-outputs] [--strip-quoted-replies] [--no-normalize-encoding] [--no-detect-language] [--no-file-attributes] [--redaction-policy off|block|mask|tag] [--redaction-rules <file> ...]',
+    '  npm run ingest -- push <stage-dir> (--store <storeName> | --project <projectId>) [--metadata key=value ...] [--acl <label,...>] [--concurrency <n>]',
+    '  npm run ingest -- wait [<operation> ...] [--store <storeName> | --project <projectId>] [--timeout <seconds>] [--concurrency <n>]',
+    '  npm run ingest -- retry-failed [--store <storeName> | --project <projectId>] [--list [--json] | --clear] [--mime-map <file>] [--mime-type <type>] [--extract-locally | --no-extract-locally] [--code-chunking] [--concurrency <n>] [--wait | --no-wait]',
+    '  npm run ingest -- snapshots <dir> [--json]',
+    '  npm run ingest -- rollback <dir> --to <snapshot> [--project <projectId>] [--concurrency <n>] [--dry-run]',
+    '  npm run ingest -- manifest verify <stage-dir|dir> (--store <storeName> | --project <projectId>) [--json]',
+    'With --sitemap each URL is a sitemap.xml (or sitemap index) and every page it lists is uploaded.',
+    'git indexes the files at --ref (default: HEAD) with commit, path, last_author and last_modified metadata; --since only re-indexes files changed since that ref.',
+    'gdrive and gcs download the files of a Drive folder (with its subfolders; Docs, Sheets and Slides exported as Markdown, CSV and text) or below a Cloud Storage prefix and upload them as ingest would, with source_url and last_modified metadata.',
+    'They read with --source-credentials, else credentials_file, else Application Default Credentials, which need the drive.readonly or devstorage.read_only scope; --anonymous reads a public bucket.',
+    '--acl labels the documents for ACL mode: only queries passing one of the labels find them.',
+    '--row-chunking uploads every row of CSV and TSV files (or every --rows-per-document rows) as its own document, with the --metadata-columns (default: the first 10) as metadata.',
+    '--redaction-policy scans the text of every file for secrets and PII and blocks the file, masks the matches or tags the document; --redaction-rules adds TOML rule files.',
+    '--no-wait returns once every upload is accepted instead of polling its indexing (split files and files with a summary still wait); wait polls the pending ones later, at intervals growing from 1s to 30s for up to --timeout (default: timeouts.operation, else 600).',
+    'Files whose upload or indexing fails are quarantined with the error; retry-failed uploads only those again, optionally with another --mime-map, a --mime-type for all of them or local extraction, and --list shows them.',
+    'snapshots lists the snapshots each successful sync of <dir> records; rollback re-uploads the files changed or removed since one and deletes the documents added since. Local files are not changed.',
+    'stage does every local step of an upload (filters, MIME types, extraction, chunking) without API access and writes the documents and a manifest to <stage-dir>; push uploads them later, also from another machine.',
+    '--on-error fail-fast or abort-after:<n> stops git, gdrive, gcs, push, retry-failed and rollback from starting files once one or <n> failed (default: on_error, else continue);',
+    '  files in flight still finish, and the next run of the same command continues with the files left.',
+    '--report writes the status, duration and error class of every file or page uploaded by url, git, gdrive, gcs, push, retry-failed and rollback as JUnit or Checkstyle XML, or JSON, for CI; may be repeated.',
+    'Identical trees stage to identical manifests (with --no-file-attributes, since mtime and owner differ between checkouts); manifest verify checks that the store holds exactly the documents of a stage manifest, or of the sync manifest of <dir>, and exits with 8 if not.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+// --rows-per-document and --metadata-columns imply --row-chunking
+function rowChunkingArgument(): RowChunkingOptions | undefined {
+  const rowsPerDocument = parseCount('--rows-per-document', 10000);
+  const columns = getArgValue('--metadata-columns');
+  if (!process.argv.includes('--row-chunking') && rowsPerDocument === undefined && columns === undefined) return undefined;
+  return {
+    rowsPerDocument,
+    metadataColumns: columns !== undefined ? columns.split(',').map(column => column.trim()).filter(Boolean) : undefined,
+  };
+}
+
+// --redaction-policy replaces redaction_policy, --redaction-rules adds to redaction_rules
+function redactionArgument(settings: Settings): Redactor | undefined {
+  return Redactor.load(
+    (getArgValue('--redaction-policy') || settings.redactionPolicy) as RedactionPolicy | undefined,
+    [...(settings.redactionRules || []), ...getArgValues('--redaction-rules').map(file => path.resolve(file))]
+  );
+}
+
+function aclArgument(): string[] | undefined {
+  const value = getArgValue('--acl');
+  return value !== undefined ? parseAclLabels(value) : undefined;
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(3);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+function findProject(projectId: string): { storeId: string; metadata?: DocumentMetadata; codeChunking?: boolean; rowChunking?: RowChunkingOptions } | undefined {
+  try {
+    const data = JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string; metadata?: DocumentMetadata; codeChunking?: boolean; rowChunking?: RowChunkingOptions }[] };
+    return data.projects.find(p => p.id === projectId || p.name === projectId);
+  } catch (error) {
+    // No registered projects
+    return undefined;
+  }
+}
+
+function parseCount(flag: string, max: number): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= 1 && Number(value) <= max)) {
+    console.error(`Error: ${flag} must be an integer between 1 and ${max}, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+// Needs neither credentials nor network access
+function stage(rootDir: string, stageDir: string, settings: Settings): void {
+  if (!fs.existsSync(rootDir) || !fs.statSync(rootDir).isDirectory()) {
+    console.error(`Error: Directory not found: ${rootDir}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const ingester = new DirectoryIngester({
+    include: getArgValues('--include'),
+    exclude: getArgValues('--exclude'),
+    metadata: withAclLabels(parseMetadataPairs(getArgValues('--metadata')), aclArgument()),
+    mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+    chunking: settings.chunking,
+    codeChunking: process.argv.includes('--code-chunking'),
+    rowChunking: rowChunkingArgument(),
+    extractLocally: settings.extractLocally,
+    notebooks: { stripOutputs: process.argv.includes('--strip-notebook-outputs') || settings.stripNotebookOutputs },
+    emails: { stripQuotes: process.argv.includes('--strip-quoted-replies') || settings.stripQuotedReplies },
+    normalizeEncoding: !process.argv.includes('--no-normalize-encoding') && settings.normalizeEncoding !== false,
+    detectLanguage: !process.argv.includes('--no-detect-language') && settings.detectLanguage !== false,
+    fileAttributes: !process.argv.includes('--no-file-attributes') && settings.fileAttributes !== false,
+    redaction: redactionArgument(settings),
+    transforms: settings.transforms,
+    partSize: settings.partSize ? Math.floor(settings.partSize) : undefined,
+  });
+  const manifest = stageDirectory(ingester, rootDir, ingester.collect(rootDir), stageDir);
+  const skipped = manifest.files.filter(file => file.skipped);
+  for (const file of skipped) console.error(`⚠️  Skipped (${file.skipped}): ${file.path}`);
+  console.log(`📦 Staged ${manifest.files.length - skipped.length} files as ${manifest.documentCount} documents `
+    + `(${formatBytes(manifest.totalBytes)}, ~${manifest.estimatedTokens.toLocaleString('en-US')} tokens) in ${stageDir}`);
+  console.log(`   Upload them with: npm run ingest -- push ${stageDir} --store <storeName>`);
+}
+
+// A stage directory is checked by display names, sizes and metadata; a synced directory by document names
+async function verifyManifest(client: FileSearchClient, storeName: string, dir: string): Promise<void> {
+  let result: ManifestVerification;
+  if (fs.existsSync(path.join(dir, STAGE_MANIFEST_FILE))) {
+    result = await verifyStageManifest(client, storeName, readStageManifest(dir));
+  } else {
+    const manifest = SyncManifest.load(dir, storeName);
+    if (manifest.size === 0) {
+      console.error(`Error: No ${STAGE_MANIFEST_FILE}, and no ${MANIFEST_FILE} of ${storeName}, in ${dir}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    result = await verifySyncManifest(client, storeName, manifest);
+  }
+  console.log(process.argv.includes('--json') ? JSON.stringify(result, null, 2) : formatManifestVerification(result));
+  if (!manifestMatches(result)) process.exitCode = EXIT_CODES.OPERATION_FAILED;
+}
+
+function printPending(pending: PendingOperations, storeName: string): void {
+  const count = pending.list(storeName).length;
+  if (count > 0) console.log(`   ⏳ ${count} uploads still indexing; wait for them with: npm run ingest -- wait --store ${storeName}`);
+}
+
+// Needs neither credentials nor network access
+function listQuarantine(quarantine: Quarantine, storeName: string | undefined): void {
+  if (process.argv.includes('--clear')) {
+    const count = quarantine.clear(storeName);
+    console.log(`🧹 ${count} files dropped from the quarantine`);
+    return;
+  }
+  const files = quarantine.list(storeName);
+  if (process.argv.includes('--json')) {
+    console.log(JSON.stringify(files, null, 2));
+    return;
+  }
+  if (files.length === 0) {
+    console.log('No quarantined files');
+    return;
+  }
+  for (const file of files) {
+    console.log(`❌ ${file.path}  ${file.storeName}  ${file.attempts} failed attempt${file.attempts === 1 ? '' : 's'}, last ${file.lastFailedAt}`);
+    console.log(`   ${file.code ? `[${file.code}] ` : ''}${file.reason}  (in ${file.rootDir})`);
+  }
+}
+
+// Needs neither credentials nor network access
+function listSnapshots(rootDir: string): void {
+  const snapshots = SnapshotStore.open(rootDir).list();
+  if (process.argv.includes('--json')) {
+    console.log(JSON.stringify(snapshots, null, 2));
+    return;
+  }
+  if (snapshots.length === 0) {
+    console.log(`No snapshots in ${rootDir}: one is recorded after each successful upload or sync`);
+    return;
+  }
+  for (const info of snapshots) {
+    console.log(`${String(info.id).padStart(4)}  ${info.createdAt}  ${info.storeName}  ${info.fileCount} files, ${info.documentCount} documents  (${info.reason})`);
+  }
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  if (!['url', 'git', 'gdrive', 'gcs', 'stage', 'push', 'wait', 'retry-failed', 'snapshots', 'rollback', 'manifest'].includes(command)) usage();
+  const targets = positionalArgs();
+  if (command === 'retry-failed' && targets.length > 0) usage();
+  if (command !== 'wait' && command !== 'retry-failed' && (targets.length === 0 || (command !== 'url' && targets.length !== (command === 'stage' || command === 'manifest' ? 2 : 1)))) usage();
+  if (command === 'manifest' && targets[0] !== 'verify') usage();
+  if (command === 'url') targets.forEach(target => parseHttpUrl(target));
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+  // Written after each upload, so the report is there even when a later step fails
+  const report = CiReportWriter.of(getArgValues('--report').map(parseCiReportTarget));
+  const onError = getArgValue('--on-error') || process.env.GEMINI_ON_ERROR;
+  const failurePolicy = onError ? parseFailurePolicy(onError) : settings.onError;
+
+  if (command === 'stage') {
+    stage(targets[0], targets[1], settings);
+    return;
+  }
+  if (command === 'snapshots') {
+    listSnapshots(targets[0]);
+    return;
+  }
+
+  const projectId = getArgValue('--project');
+  const project = projectId ? findProject(projectId) : undefined;
+  if (projectId && !project) {
+    console.error(`Error: Project not found: ${projectId}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const quarantine = new Quarantine(quarantineListFile());
+  if (command === 'retry-failed' && (process.argv.includes('--list') || process.argv.includes('--clear'))) {
+    listQuarantine(quarantine, project?.storeId || getArgValue('--store'));
+    return;
+  }
+  const snapshotId = command === 'rollback' ? parseCount('--to', Number.MAX_SAFE_INTEGER) : undefined;
+  if (command === 'rollback' && snapshotId === undefined) usage();
+  // A rollback goes to the store the snapshot was taken of
+  const snapshot = snapshotId !== undefined ? SnapshotStore.open(targets[0]).get(snapshotId) : undefined;
+  const storeName = snapshot?.storeName || project?.storeId || getArgValue('--store') || settings.defaultStore;
+  if (snapshot && project && project.storeId !== snapshot.storeName) {
+    console.error(`Error: Snapshot ${snapshot.id} is of store ${snapshot.storeName}, not of project ${projectId}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const concurrency = parseCount('--concurrency', 50);
+  const maxPages = parseCount('--max-pages', 10000);
+  const metadata = withAclLabels({ ...project?.metadata, ...parseMetadataPairs(getArgValues('--metadata')) }, aclArgument());
+
+  const notifier = new Notifier({
+    command: getArgValue('--notify-command') || settings.notifyCommand,
+    webhook: getArgValue('--notify-webhook') || settings.notifyWebhook,
+  });
+  const client = buildClientFromArgs(settings, { notifier });
+  const pending = new PendingOperations(pendingOperationsFile());
+  const wait = !process.argv.includes('--no-wait');
+
+  if (command === 'wait') {
+    const timeout = parseCount('--timeout', 86400);
+    // Without names or a store every pending upload is waited on
+    const summary = await waitForPendingUploads(client, pending, {
+      operations: targets,
+      storeName: project?.storeId || getArgValue('--store'),
+      cache: new IndexCache(indexCacheFile()),
+      quarantine,
+      concurrency,
+      poll: { timeout: timeout !== undefined ? timeout * 1000 : undefined, signal: shutdown.signal },
+    });
+    for (const entry of summary.indexed) console.log(`✅ ${entry.displayName} → ${entry.documentName || entry.operation}`);
+    for (const entry of summary.failed) console.log(`❌ ${entry.displayName}: ${entry.reason}`);
+    for (const entry of summary.pending) console.log(`⏳ ${entry.displayName}: still indexing (${entry.operation})`);
+    console.log(`${summary.indexed.length} indexed, ${summary.failed.length} failed, ${summary.pending.length} still pending`);
+    if (summary.failed.length > 0) process.exitCode = EXIT_CODES.OPERATION_FAILED;
+    else if (summary.pending.length > 0) process.exitCode = EXIT_CODES.TIMEOUT;
+    return;
+  }
+  if (command === 'retry-failed') {
+    const retryStore = project?.storeId || getArgValue('--store');
+    const queued = quarantine.list(retryStore);
+    if (queued.length === 0) {
+      console.log(retryStore ? `No quarantined files for ${retryStore}` : 'No quarantined files');
+      return;
+    }
+    const mimeType = getArgValue('--mime-type');
+    const mimeMap = getArgValue('--mime-map') || settings.mimeMap;
+    const summary = await retryQuarantined(client, quarantine, {
+      metadata: Object.keys(metadata).length > 0 ? metadata : undefined,
+      // --mime-type applies to the quarantined files only, by their names
+      mimeRegistry: mimeType
+        ? new MimeRegistry({ filenames: Object.fromEntries(queued.map(file => [path.posix.basename(file.path), mimeType])) })
+        : mimeMap ? MimeRegistry.fromTomlFile(path.resolve(mimeMap)) : undefined,
+      chunking: settings.chunking,
+      codeChunking: process.argv.includes('--code-chunking') || project?.codeChunking,
+      rowChunking: project?.rowChunking,
+      concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: process.argv.includes('--extract-locally') || (!process.argv.includes('--no-extract-locally') && settings.extractLocally),
+      notebooks: { stripOutputs: settings.stripNotebookOutputs },
+      emails: { stripQuotes: settings.stripQuotedReplies },
+      normalizeEncoding: settings.normalizeEncoding !== false,
+      detectLanguage: settings.detectLanguage !== false,
+      fileAttributes: settings.fileAttributes !== false,
+      redaction: redactionArgument(settings),
+      transforms: settings.transforms,
+      cache: new IndexCache(indexCacheFile()),
+      progressBars: true,
+      wait,
+      pending,
+      report,
+      failurePolicy,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    }, retryStore);
+    for (const file of summary.missing) console.log(`   🗑️  ${file.path}: no longer in ${file.rootDir}, dropped`);
+    console.log(`✅ ${summary.uploaded}/${summary.retried} quarantined files uploaded, ${summary.failed} still failing`
+      + (summary.missing.length > 0 ? `, ${summary.missing.length} missing` : ''));
+    if (summary.failed > 0) console.log('   See why with: npm run ingest -- retry-failed --list');
+    if (!wait && retryStore) printPending(pending, retryStore);
+    if (summary.failed > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+  if (!storeName) usage();
+
+  if (command === 'manifest') {
+    await verifyManifest(client, storeName, targets[1]);
+    return;
+  }
+
+  if (command === 'rollback' && snapshotId !== undefined) {
+    const rootDir = targets[0];
+    const snapshots = SnapshotStore.open(rootDir);
+    const manifest = SyncManifest.load(rootDir, storeName);
+    const plan = planRollback(manifest, snapshots.get(snapshotId), snapshots);
+    for (const key of plan.missing) console.error(`⚠️  Not restorable, its content was not kept: ${key}`);
+    if (process.argv.includes('--dry-run')) {
+      console.log(`Rollback of ${rootDir} to snapshot ${snapshotId} (dry run): ${plan.restored.length} to restore, ${plan.deleted.length} to delete, ${plan.unchanged.length} unchanged`);
+      for (const key of plan.restored) console.log(`   ⏪ ${key}`);
+      for (const key of plan.deleted) console.log(`   🗑️  ${key}`);
+      return;
+    }
+    const ingester = new DirectoryIngester({
+      metadata: Object.keys(metadata).length > 0 ? metadata : undefined,
+      mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+      chunking: settings.chunking,
+      codeChunking: process.argv.includes('--code-chunking') || project?.codeChunking,
+      rowChunking: rowChunkingArgument() || project?.rowChunking,
+      concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      redaction: redactionArgument(settings),
+      transforms: settings.transforms,
+      cache: new IndexCache(indexCacheFile()),
+      report,
+      failurePolicy,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    });
+    await reconcileSync(client, storeName, rootDir, manifest, ingester);
+    const summary = await rollbackSync(client, storeName, rootDir, manifest, ingester, snapshotId);
+    console.log(`✅ Rolled ${storeName} back to snapshot ${snapshotId}: ${summary.restored.length} restored, ${summary.deleted.length} deleted`
+      + (summary.created !== undefined ? ` (now snapshot ${summary.created})` : ''));
+    for (const failure of summary.failures) console.log(`   ❌ ${failure.path}: ${failure.reason}`);
+    console.log('   Local files are not changed: the next sync uploads those that differ from the snapshot again');
+    if (summary.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+
+  if (command === 'push') {
+    const summary = await pushStaged(client, storeName, targets[0], {
+      cache: new IndexCache(indexCacheFile()),
+      concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+      requestsPerMinute: settings.requestsPerMinute,
+      metadata: Object.keys(metadata).length > 0 ? metadata : undefined,
+      report,
+      failurePolicy,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    });
+    console.log(`✅ ${summary.successCount}/${summary.totalFiles} staged files uploaded to ${storeName}`);
+    if (summary.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+
+  if (command === 'git') {
+    const summary = await ingestGitRepository(client, storeName, targets[0], {
+      ref: getArgValue('--ref'),
+      since: getArgValue('--since'),
+      include: getArgValues('--include'),
+      exclude: getArgValues('--exclude'),
+      metadata,
+      mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+      chunking: settings.chunking,
+      codeChunking: process.argv.includes('--code-chunking') || project?.codeChunking,
+      rowChunking: rowChunkingArgument() || project?.rowChunking,
+      concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      notebooks: { stripOutputs: process.argv.includes('--strip-notebook-outputs') || settings.stripNotebookOutputs },
+      emails: { stripQuotes: process.argv.includes('--strip-quoted-replies') || settings.stripQuotedReplies },
+      normalizeEncoding: !process.argv.includes('--no-normalize-encoding') && settings.normalizeEncoding !== false,
+      detectLanguage: !process.argv.includes('--no-detect-language') && settings.detectLanguage !== false,
+      fileAttributes: !process.argv.includes('--no-file-attributes') && settings.fileAttributes !== false,
+      redaction: redactionArgument(settings),
+      transforms: settings.transforms,
+      summaries: process.argv.includes('--with-summaries') || settings.withSummaries ? { minBytes: settings.summaryMinSize, model: settings.model } : undefined,
+      partSize: settings.partSize ? Math.floor(settings.partSize) : undefined,
+      progressBars: true,
+      wait,
+      pending,
+      report,
+      failurePolicy,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    });
+    console.log(`✅ Indexed ${targets[0]} at ${summary.commit.slice(0, 12)}: ${summary.upload.successCount}/${summary.upload.totalFiles} files uploaded`
+      + (summary.since ? ` (changed since ${summary.since.slice(0, 12)})` : ''));
+    if (summary.replacedDocuments > 0) console.log(`   ${summary.replacedDocuments} earlier documents replaced`);
+    if (summary.deletedFiles.length > 0) console.log(`   ${summary.deletedFiles.length} deleted files removed from the store`);
+    if (!wait) printPending(pending, storeName);
+    // The ingestion report with the failures is already on stderr
+    if (summary.upload.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+
+  if (command === 'gdrive' || command === 'gcs') {
+    const sourceOptions = {
+      auth: command === 'gcs' && process.argv.includes('--anonymous')
+        ? undefined
+        : googleSourceAuth(command === 'gdrive' ? DRIVE_SCOPE : GCS_SCOPE, getArgValue('--source-credentials') || settings.credentialsFile),
+      retry: { maxAttempts: settings.maxAttempts ? Math.floor(settings.maxAttempts) : undefined },
+    };
+    const source: Source = command === 'gdrive' ? new DriveSource(targets[0], sourceOptions) : new GcsSource(targets[0], sourceOptions);
+    const summary = await ingestSource(client, storeName, source, {
+      include: getArgValues('--include'),
+      exclude: getArgValues('--exclude'),
+      metadata,
+      mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+      cache: new IndexCache(indexCacheFile()),
+      chunking: settings.chunking,
+      codeChunking: process.argv.includes('--code-chunking') || project?.codeChunking,
+      rowChunking: rowChunkingArgument() || project?.rowChunking,
+      concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      notebooks: { stripOutputs: process.argv.includes('--strip-notebook-outputs') || settings.stripNotebookOutputs },
+      emails: { stripQuotes: process.argv.includes('--strip-quoted-replies') || settings.stripQuotedReplies },
+      normalizeEncoding: !process.argv.includes('--no-normalize-encoding') && settings.normalizeEncoding !== false,
+      detectLanguage: !process.argv.includes('--no-detect-language') && settings.detectLanguage !== false,
+      fileAttributes: !process.argv.includes('--no-file-attributes') && settings.fileAttributes !== false,
+      redaction: redactionArgument(settings),
+      transforms: settings.transforms,
+      summaries: process.argv.includes('--with-summaries') || settings.withSummaries ? { minBytes: settings.summaryMinSize, model: settings.model } : undefined,
+      partSize: settings.partSize ? Math.floor(settings.partSize) : undefined,
+      replace: !process.argv.includes('--keep-old'),
+      progressBars: true,
+      wait,
+      pending,
+      report,
+      failurePolicy,
+      signal: shutdown.signal,
+      abortSignal: shutdown.abortSignal,
+    });
+    console.log(`✅ Ingested ${summary.uri}: ${summary.upload.successCount}/${summary.upload.totalFiles} files uploaded`
+      + (summary.filtered > 0 ? `, ${summary.filtered} of ${summary.listed} left out by the filters` : ''));
+    if (summary.replacedDocuments > 0) console.log(`   ${summary.replacedDocuments} earlier documents replaced`);
+    for (const failure of summary.fetchFailures) console.log(`   ❌ ${failure.path}: ${failure.reason}`);
+    if (!wait) printPending(pending, storeName);
+    if (summary.upload.errorCount > 0 || summary.fetchFailures.length > 0) process.exitCode = EXIT_CODES.API_ERROR;
+    return;
+  }
+
+  let urls = targets;
+  if (process.argv.includes('--sitemap')) {
+    urls = [];
+    for (const sitemap of targets) {
+      urls.push(...await collectSitemapUrls(sitemap, { maxPages: maxPages && maxPages - urls.length }));
+      if (maxPages && urls.length >= maxPages) break;
+    }
+    console.error(`🗺️  ${urls.length} pages found in ${targets.join(', ')}`);
+  }
+
+  const summary = await ingestUrls(client, storeName, [...new Set(urls)], {
+    metadata,
+    concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+    replace: !process.argv.includes('--keep-old'),
+    wait,
+    pending,
+    report,
+    signal: shutdown.signal,
+  });
+  console.log(`✅ ${summary.successCount}/${summary.totalPages} pages uploaded to ${storeName}`);
+  for (const failure of summary.failures) {
+    console.log(`   ❌ ${failure.path}: ${failure.reason}`);
+  }
+  if (!wait) printPending(pending, storeName);
+  if (summary.errorCount > 0) process.exitCode = EXIT_CODES.API_ERROR;
+}
+
+// Ctrl-C lets the pages or files in flight finish
+shutdown.install();
+shutdown.track(main()).then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,138 @@
+#!/usr/bin/env node
+
+// local-search-cli.ts - Nearest-neighbor search over a local vector file, optionally fused with File Search retrieval
+import * as fs from 'fs';
+import 'dotenv/config';
+import { FileSearchClient } from '../client.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { VectorIndex, embedTexts, formatHybridHits, formatLocalHits, fuseResults, readVectorFile } from '../embeddings.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, COMMON_VALUE_FLAGS, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings, projectsFile } from './common.js';
+
+const VALUE_FLAGS = [
+  '--store', '--project', '--top-k', '--candidates', '--query-vector', '--format',
+  ...COMMON_VALUE_FLAGS,
+];
+
+const FORMATS = ['text', 'json'];
+const DEFAULT_TOP_K = 10;
+
+function usage(): never {
+  console.error([
+    'Usage: npm run local-search -- <vectors.jsonl|vectors.fvecs> ("<query>" | --query-vector <file.json>) [--top-k <n>] [--exact | --candidates <n>] [--store <storeName> ... | --project <projectId>] [--format text|json]',
+    COMMON_USAGE,
+    `Prints the ${DEFAULT_TOP_K} passages nearest to the query; only the query is embedded online, and --query-vector (a JSON array of numbers) needs no network at all.`,
+    'With --store or --project the chunks File Search retrieves are fused with the local ones by reciprocal rank (hybrid search).',
+    'Above 10000 vectors only the --candidates with the nearest signatures are compared; --exact compares all of them.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(2);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+function integerArgument(flag: string, min: number, max: number): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= min && Number(value) <= max)) {
+    console.error(`Error: ${flag} must be an integer between ${min} and ${max}, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+function readQueryVector(filePath: string): number[] {
+  let vector: unknown;
+  try {
+    vector = JSON.parse(fs.readFileSync(filePath, 'utf8'));
+  } catch (error) {
+    console.error(`Error: Could not read --query-vector ${filePath}: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  if (!Array.isArray(vector) || !vector.every(value => typeof value === 'number')) {
+    console.error(`Error: --query-vector ${filePath} must hold a JSON array of numbers`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return vector as number[];
+}
+
+function resolveStores(): string[] {
+  const stores = getArgValues('--store');
+  const projectId = getArgValue('--project');
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+  }
+  return stores;
+}
+
+function buildClient(settings: Settings): FileSearchClient {
+  return buildClientFromArgs(settings);
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const [vectorFile, ...words] = positionalArgs();
+  const query = words.join(' ');
+  const queryVectorFile = getArgValue('--query-vector');
+  if (!vectorFile || (!query && !queryVectorFile)) usage();
+
+  const format = getArgValue('--format') || 'text';
+  if (!FORMATS.includes(format)) {
+    console.error(`Error: --format must be one of: ${FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const topK = integerArgument('--top-k', 1, 100) ?? DEFAULT_TOP_K;
+  const candidates = integerArgument('--candidates', 1, 1000000);
+  const stores = resolveStores();
+  if (stores.length > 0 && !query) {
+    console.error('Error: hybrid search with --store or --project needs a text query');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const index = new VectorIndex(readVectorFile(vectorFile));
+  if (index.records.length === 0) {
+    console.error(`Error: ${vectorFile} holds no vectors`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  // The client is only built when something is sent, so a query vector works without credentials
+  const client = !queryVectorFile || stores.length > 0 ? buildClient(settings) : undefined;
+  const vector = queryVectorFile
+    ? readQueryVector(queryVectorFile)
+    : (await embedTexts(client as FileSearchClient, [query], { model: index.model, dimensions: index.dimensions, taskType: 'RETRIEVAL_QUERY' }))[0];
+  const localHits = index.search(vector, topK, { exact: process.argv.includes('--exact'), candidates });
+
+  if (stores.length === 0) {
+    console.log(format === 'json'
+      ? JSON.stringify({ query, vectorFile, hits: localHits.map(({ record: { vector: _vector, ...record }, ...hit }) => ({ ...hit, ...record })) }, null, 2)
+      : formatLocalHits(localHits));
+    return;
+  }
+  const chunks = await (client as FileSearchClient).retrieve(stores, query, { topK, model: settings.model });
+  const hits = fuseResults(localHits, chunks, topK);
+  console.log(format === 'json' ? JSON.stringify({ query, vectorFile, stores, hits }, null, 2) : formatHybridHits(hits));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
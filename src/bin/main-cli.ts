@@ -0,0 +1,181 @@
+#!/usr/bin/env node
+
+// main-cli.ts - gemini-fs <command>: runs the built-in commands and gemini-fs-<name> plugins found on the PATH
+import * as fs from 'fs';
+import * as path from 'path';
+import { spawn } from 'child_process';
+import { fileURLToPath } from 'url';
+import { dirname } from 'path';
+import 'dotenv/config';
+import { Settings, resolveSettings } from '../config.js';
+import { AuthProvider, createAuthProvider } from '../auth.js';
+import { resolveBackend } from '../backend.js';
+import { resolveTenant, stateHome } from '../tenant.js';
+import {
+  PLUGIN_API_VERSION,
+  PLUGIN_PREFIX,
+  PluginContext,
+  PluginCredentials,
+  SCAFFOLD_LANGUAGES,
+  ScaffoldLanguage,
+  findPlugins,
+  runPlugin,
+  scaffoldPlugin,
+} from '../plugins.js';
+import { COMMANDS } from '../completions.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+
+const __filename = fileURLToPath(import.meta.url);
+const __dirname = dirname(__filename);
+
+// The modules of the gemini-fs-<name> binaries of this package
+const BUILTINS: { [name: string]: string } = Object.fromEntries(COMMANDS.filter(command => command.name.startsWith(PLUGIN_PREFIX)).map(command => {
+  const name = command.name.slice(PLUGIN_PREFIX.length);
+  return [name, name === 'mcp' ? 'mcp-server.js' : name === 'mock' ? 'mock-server.js' : `${name}-cli.js`];
+}));
+
+// Options of gemini-fs itself, given before the command
+const GLOBAL_VALUE_FLAGS = ['--config', '--profile', '--tenant', '--store'];
+
+function usage(): never {
+  const plugins = findPlugins([...Object.keys(BUILTINS), 'plugin']);
+  console.error([
+    'Usage: gemini-fs [--config <file>] [--profile <name>] [--tenant <name>] [--store <storeName>] <command> [args...]',
+    '  gemini-fs plugin list [--json]                                  List the plugins found on the PATH',
+    `  gemini-fs plugin scaffold <name> [--dir <dir>] [--lang ${SCAFFOLD_LANGUAGES.join('|')}]   Write a gemini-fs-<name> plugin to start from`,
+    `Commands: ${Object.keys(BUILTINS).sort().join(', ')}`,
+    `Plugins: ${plugins.length > 0 ? plugins.map(plugin => plugin.name).join(', ') : 'none (executables named gemini-fs-<name> on the PATH)'}`,
+    'Built-in commands get their arguments as they are. Plugins also get the resolved config, store, backend and credentials, see README_MCP.md.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+// The global options and the command with its arguments
+function parseArgs(argv: string[]): { options: { [flag: string]: string }; command?: string; args: string[] } {
+  const options: { [flag: string]: string } = {};
+  let i = 0;
+  while (i < argv.length && GLOBAL_VALUE_FLAGS.includes(argv[i]) && argv[i + 1] !== undefined) {
+    options[argv[i]] = argv[i + 1];
+    i += 2;
+  }
+  return { options, command: argv[i], args: argv.slice(i + 1) };
+}
+
+function getArgValue(args: string[], flag: string): string | undefined {
+  const index = args.indexOf(flag);
+  return index !== -1 ? args[index + 1] : undefined;
+}
+
+// The commands read --config, --profile and --tenant from the environment as well;
+// --store is added after their own arguments, where the subcommand cannot be
+function runBuiltin(file: string, args: string[], options: { [flag: string]: string }): Promise<number> {
+  const env: NodeJS.ProcessEnv = { ...process.env };
+  if (options['--config']) env.GEMINI_FS_CONFIG = options['--config'];
+  if (options['--profile']) env.GEMINI_FS_PROFILE = options['--profile'];
+  if (options['--tenant']) env.GEMINI_FS_TENANT = options['--tenant'];
+  const store = options['--store'] ? ['--store', options['--store']] : [];
+  return new Promise((resolve, reject) => {
+    const child = spawn(process.execPath, [path.join(__dirname, file), ...args, ...store], { stdio: 'inherit', env });
+    child.on('error', reject);
+    child.on('exit', (code, signal) => resolve(code ?? (signal === 'SIGINT' ? 130 : 1)));
+  });
+}
+
+async function credentialsOf(auth: AuthProvider, settings: Settings): Promise<PluginCredentials> {
+  const options = auth.clientOptions();
+  if (options.apiKey) return { apiKey: options.apiKey };
+  const headers = await auth.requestHeaders('https://generativelanguage.googleapis.com/');
+  const authorization = Object.entries(headers).find(([name]) => name.toLowerCase() === 'authorization')?.[1];
+  return {
+    accessToken: authorization?.replace(/^Bearer\s+/i, ''),
+    credentialsFile: options.googleAuthOptions?.keyFile || settings.credentialsFile,
+  };
+}
+
+async function pluginCommand(args: string[]): Promise<void> {
+  switch (args[0]) {
+    case 'list': {
+      const plugins = findPlugins([...Object.keys(BUILTINS), 'plugin']);
+      if (args.includes('--json')) {
+        console.log(JSON.stringify(plugins, null, 2));
+      } else if (plugins.length === 0) {
+        console.log('No plugins found: put an executable named gemini-fs-<name> on the PATH');
+      } else {
+        for (const plugin of plugins) console.log(`${plugin.name.padEnd(20)} ${plugin.path}`);
+      }
+      return;
+    }
+
+    case 'scaffold': {
+      const name = args[1];
+      const language = (getArgValue(args, '--lang') || 'node') as ScaffoldLanguage;
+      if (!name || name.startsWith('--') || !SCAFFOLD_LANGUAGES.includes(language)) usage();
+      if (BUILTINS[name] || name === 'plugin') {
+        console.error(`Error: "${name}" is a built-in command; a plugin of that name would never run`);
+        process.exit(EXIT_CODES.INVALID_INPUT);
+      }
+      const filePath = scaffoldPlugin(name, path.resolve(getArgValue(args, '--dir') || '.'), language);
+      console.log(`✅ Wrote ${filePath}`);
+      console.log(`   Put its directory on the PATH and run: gemini-fs ${name}`);
+      return;
+    }
+
+    default:
+      usage();
+  }
+}
+
+async function main(): Promise<void> {
+  const { options, command, args } = parseArgs(process.argv.slice(2));
+  if (!command || command === '--help' || command === 'help') usage();
+
+  if (command === 'plugin') return pluginCommand(args);
+  if (BUILTINS[command]) {
+    if (!fs.existsSync(path.join(__dirname, BUILTINS[command]))) {
+      console.error(`Error: ${BUILTINS[command]} is missing next to ${__filename}; run npm run build`);
+      process.exit(EXIT_CODES.INTERNAL);
+    }
+    process.exitCode = await runBuiltin(BUILTINS[command], args, options);
+    return;
+  }
+
+  const plugin = findPlugins([...Object.keys(BUILTINS), 'plugin']).find(candidate => candidate.name === command);
+  if (!plugin) {
+    console.error(`Error: Unknown command "${command}": no built-in command or ${PLUGIN_PREFIX}${command} on the PATH (gemini-fs --help lists both)`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const settings: Settings = resolveSettings(
+    options['--config'] || process.env.GEMINI_FS_CONFIG,
+    options['--profile'] || process.env.GEMINI_FS_PROFILE,
+    [process.cwd()]
+  );
+  const backend = resolveBackend(process.env.GEMINI_BACKEND || settings.backend, settings.vertexProject, settings.vertexLocation);
+  // Plugins that need no credentials still run without them
+  let auth: AuthProvider | undefined;
+  try {
+    auth = createAuthProvider(settings);
+  } catch (error) {
+    auth = undefined;
+  }
+  const context: PluginContext = {
+    apiVersion: PLUGIN_API_VERSION,
+    command,
+    args,
+    config: settings.source,
+    profile: settings.profile,
+    tenant: resolveTenant(options['--tenant'] || process.env.GEMINI_FS_TENANT),
+    store: options['--store'] || settings.defaultStore,
+    stateHome: stateHome(),
+    backend: { ...backend, apiVersion: process.env.GEMINI_API_VERSION || settings.apiVersion },
+    model: process.env.GEMINI_MODEL || settings.model,
+    auth: auth && { method: auth.method, description: auth.describe() },
+  };
+  process.exitCode = await runPlugin(plugin, context, auth ? await credentialsOf(auth, settings) : {});
+}
+
+main().catch(error => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,3418 @@
+#!/usr/bin/env node
+
+// mcp-server.ts - Gemini File Search MCP Server
+import { Server } from '@modelcontextprotocol/sdk/server/index.js';
+import { StdioServerTransport } from '@modelcontextprotocol/sdk/server/stdio.js';
+import {
+  CallToolRequestSchema,
+  ListToolsRequestSchema,
+  Tool,
+} from '@modelcontextprotocol/sdk/types.js';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import 'dotenv/config';
+import { GroundingMetadata, ImportFileResponse } from '@google/genai';
+import { FileSearchClient, MAX_UPLOAD_SIZE, UploadResult } from '../client.js';
+import { DirectoryIngester, IngestPlan, IngestProgress, toDisplayName } from '../ingest.js';
+import { Quarantine } from '../quarantine.js';
+import { RowChunkingOptions } from '../tables.js';
+import { relativePathOf, resolvePathOf } from '../expand.js';
+import { SyncManifest, planSync, applySync, reconcileSync, recordSnapshot, rollbackSync } from '../sync.js';
+import { SnapshotStore, planRollback } from '../snapshots.js';
+import { StoreInfo } from '../store.js';
+import { MimeRegistry } from '../file-types.js';
+import { DocumentMetadata, parseMetadataPairs, validateMetadata } from '../metadata.js';
+import { parseFilterExpression } from '../search.js';
+import { IndexCache } from '../cache.js';
+import { Citation, extractCitations, formatFootnotes } from '../citations.js';
+import { annotateCitations } from '../attributes.js';
+import { AnswerReport, OutputFormat, OUTPUT_FORMATS, getFormatter } from '../output.js';
+import { runBatchQueries } from '../batch.js';
+import { ChatSession } from '../chat.js';
+import { ChunkingConfig, validateChunkingConfig } from '../chunking.js';
+import { defaultSettings, resolveSettings } from '../config.js';
+import { AuthMethod, AuthProvider, createAuthProvider } from '../auth.js';
+import { ApiOptions, BackendConfig, describeBackend, resolveApiOptions, resolveBackend } from '../backend.js';
+import { configureTelemetry, telemetry } from '../telemetry.js';
+import { configureLogging, resolveLogLevel } from '../log.js';
+import { installHttpFixtures } from '../testing.js';
+import { Notifier } from '../notify.js';
+import { DEDUP_MODES, DedupMode } from '../dedup.js';
+import { RedactionPolicy, Redactor, describeFindings, redactionMetadata } from '../redact.js';
+import { tenantPolicy } from '../tenant.js';
+import { BudgetExceededError, EXIT_CODES, PreflightError, exitCodeFor, toFileSearchError } from '../errors.js';
+import { TokenUsage, countPlanTokens, formatCost, formatUsage, indexingCost, toTokenUsage } from '../cost.js';
+import { canExtract, extractToTempFile } from '../extract.js';
+import { convertNotebookToTempFile, isNotebook } from '../notebook.js';
+import { convertEmailToTempFile, emailMetadata, isEmail } from '../email.js';
+import { DirectoryWatcher } from '../watch.js';
+import { DocumentState, formatDocumentMarkdown, groupDocumentParts, storeOfDocument, toDocumentInfo } from '../documents.js';
+import { exportStore, readArchive, restoreArchive, writeArchive } from '../archive.js';
+import { FEDERATION_MODES, FederatedTarget, FederationMode, federatedQuery } from '../federated.js';
+import { ShardMap } from '../shards.js';
+import { IngestDuplicate, IngestFailure, formatBytes } from '../progress.js';
+import { CiReportWriter, parseCiReportTarget } from '../ci-report.js';
+import { parseFailurePolicy } from '../pipeline.js';
+import { DEFAULT_MIN_CONFIDENCE, answerConfidence, checkGrounding, formatNoGroundedAnswer } from '../grounding.js';
+import { PromptConfig, answerLanguageOf, resolveSystemInstruction, withAnswerLanguage } from '../prompts.js';
+import { enforceAnswerLanguage, parseAnswerLanguage } from '../answer-language.js';
+import { QueryCache, QueryCacheKey, storeRevision } from '../query-cache.js';
+import { QueryHistory, describeAnswer } from '../history.js';
+import { TemplateVars, renderTemplateFile } from '../templates.js';
+import { shutdown, writeFileAtomic } from '../shutdown.js';
+import { withFileLockAsync } from '../lock.js';
+import { QuotaScheduler, formatQuotaUsage, overrideQuotaConfig } from '../quota.js';
+import { describeNetwork } from '../network.js';
+import { AclPolicy, parseAclLabels, resolveAclLabels, withAclLabels } from '../acl.js';
+import { collectSitemapUrls, ingestUrls, parseHttpUrl } from '../web.js';
+import { ROUTING_MODES, RouteTarget, RoutingConfig, RoutingMode, routeQuestion } from '../routing.js';
+import {
+  BlockedAnswer,
+  GenerationParams,
+  SAFETY_CATEGORIES,
+  SAFETY_THRESHOLDS,
+  blockedAnswer,
+  describeGeneration,
+  formatBlockedAnswer,
+  mergeGenerationParams,
+  parseSafetySettings,
+  validateGenerationParams,
+} from '../generation.js';
+import { isTextMimeType, normalizeDisplayName, transcodeToTempFile } from '../encoding.js';
+import { applyTransforms, listTransforms, resolveTransforms, AnswerTransform } from '../postprocess.js';
+import { PREFLIGHT_MODES, PreflightMode, PreflightReport, formatPreflightReport, hasStoreViolation } from '../preflight.js';
+import { STORAGE_TIERS, StorageTier, formatUsageMarkdown, isStorageTier, usageReport } from '../capacity.js';
+import { corpusStats, formatCorpusStatsMarkdown } from '../stats.js';
+import { formatIntegrityReport, verifyIntegrity } from '../integrity.js';
+import { explainFile, formatExplanationMarkdown } from '../explain.js';
+import {
+  PACKAGE_DIR,
+  TENANT,
+  getArgValue,
+  getArgValues,
+  historyFile,
+  indexCacheFile,
+  projectsFile,
+  quarantineListFile,
+  queryCacheFile,
+  quotaStateFile,
+  shardsFile,
+  uploadSessionsFile,
+} from './common.js';
+
+// Types
+interface Project {
+  id: string;
+  name: string;
+  description: string;
+  storeId: string;
+  path: string;
+  createdAt: string;
+  fileCount: number;
+  include?: string[];
+  exclude?: string[];
+  respectGitignore?: boolean;
+  metadata?: DocumentMetadata;
+  codeChunking?: boolean;
+  rowChunking?: RowChunkingOptions;
+  dedup?: DedupMode;
+}
+
+interface ProjectsData {
+  projects: Project[];
+}
+
+interface UploadStatus {
+  status: 'uploading' | 'completed' | 'failed';
+  totalFiles: number;
+  successCount: number;
+  errorCount: number;
+  progress: number;
+  lastFile?: string;
+  failures?: IngestFailure[];
+  duplicates?: IngestDuplicate[];
+  retries?: number;
+  stopped?: string;  // Why the failure policy left files for the next run
+  error?: string;
+}
+
+// Command-line options, e.g. --mime-map <file.toml> --concurrency 8
+function getNumericOption(flag: string, envName: string): number | undefined {
+  const raw = getArgValue(flag) || process.env[envName];
+  if (!raw) return undefined;
+  const value = Number(raw);
+  if (!Number.isFinite(value) || value <= 0) {
+    console.error(`Error: ${flag} must be a positive number, got "${raw}"`);
+    process.exit(1);
+  }
+  return value;
+}
+
+function getFlag(flag: string, envName: string): boolean | undefined {
+  if (process.argv.includes(flag)) return true;
+  const raw = process.env[envName];
+  return raw ? raw === 'true' : undefined;
+}
+
+// Settings file: --config <file.toml>, or gemini-fs.toml in the working or server directory.
+// Flags and environment variables take precedence over its values.
+let settings = defaultSettings();
+try {
+  settings = resolveSettings(
+    getArgValue('--config') || process.env.GEMINI_FS_CONFIG,
+    getArgValue('--profile') || process.env.GEMINI_FS_PROFILE,
+    [process.cwd(), PACKAGE_DIR]
+  );
+  if (settings.source) {
+    console.error(`✅ Loaded config: ${settings.source}${settings.profile ? ` (profile: ${settings.profile})` : ''}`);
+  }
+} catch (error) {
+  const err = error as Error;
+  console.error(`Error: ${err.message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+
+// --verbose / --quiet / GEMINI_LOG_LEVEL for stderr, --log-file for a JSON-lines copy of every level
+try {
+  configureLogging({
+    level: resolveLogLevel(process.argv.includes('--verbose'), process.argv.includes('--quiet'), process.env.GEMINI_LOG_LEVEL || settings.logLevel),
+    file: getArgValue('--log-file') || process.env.GEMINI_LOG_FILE || settings.logFile,
+    secrets: [process.env[settings.apiKeyEnv], getArgValue('--api-key')],
+  });
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+configureTelemetry(getArgValue('--otlp-endpoint') || settings.otlpEndpoint);
+installHttpFixtures(getArgValue('--record') || process.env.GEMINI_FS_RECORD, getArgValue('--replay') || process.env.GEMINI_FS_REPLAY);
+
+// Credentials: API key (default), Application Default Credentials or a service account.
+// The backend is the Gemini API unless --backend vertex selects Vertex AI RAG Engine.
+let auth: AuthProvider;
+let backend: BackendConfig;
+let api: ApiOptions;
+try {
+  backend = resolveBackend(
+    getArgValue('--backend') || process.env.GEMINI_BACKEND || settings.backend,
+    getArgValue('--vertex-project') || settings.vertexProject,
+    getArgValue('--vertex-location') || settings.vertexLocation
+  );
+  api = resolveApiOptions(getArgValue('--api-version') || process.env.GEMINI_API_VERSION || settings.apiVersion, settings.headers, getArgValues('--header'));
+  auth = createAuthProvider({
+    ...settings,
+    authMethod: (getArgValue('--auth') || process.env.GEMINI_AUTH || settings.authMethod) as AuthMethod | undefined,
+    apiKey: getArgValue('--api-key'),
+    credentialsFile: getArgValue('--credentials') || settings.credentialsFile,
+  });
+  console.error(`🔑 Authenticating with ${auth.describe()} against ${describeBackend(backend)}`);
+} catch (error) {
+  const err = error as Error;
+  console.error(`Error: ${err.message}`);
+  if (!settings.authMethod || settings.authMethod === 'api-key') {
+    console.error('Please set your Gemini API key in the .env file, or store it with npm run auth -- login');
+  }
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+
+const UPLOAD_CONCURRENCY = getNumericOption('--concurrency', 'GEMINI_UPLOAD_CONCURRENCY') ?? settings.concurrency;
+const REQUESTS_PER_MINUTE = getNumericOption('--requests-per-minute', 'GEMINI_REQUESTS_PER_MINUTE') ?? settings.requestsPerMinute;
+const RETRY_MAX_ATTEMPTS = getNumericOption('--max-attempts', 'GEMINI_RETRY_MAX_ATTEMPTS') ?? settings.maxAttempts;
+const TOKEN_BUDGET = getNumericOption('--budget', 'GEMINI_TOKEN_BUDGET') ?? settings.tokenBudget;
+const EXTRACT_LOCALLY = getFlag('--extract-locally', 'GEMINI_EXTRACT_LOCALLY') ?? settings.extractLocally === true;
+const STRIP_NOTEBOOK_OUTPUTS = getFlag('--strip-notebook-outputs', 'GEMINI_STRIP_NOTEBOOK_OUTPUTS') ?? settings.stripNotebookOutputs === true;
+const STRIP_QUOTED_REPLIES = getFlag('--strip-quoted-replies', 'GEMINI_STRIP_QUOTED_REPLIES') ?? settings.stripQuotedReplies === true;
+const NORMALIZE_ENCODING = !(getFlag('--no-normalize-encoding', 'GEMINI_NO_NORMALIZE_ENCODING') ?? settings.normalizeEncoding === false);
+const DETECT_LANGUAGE = !(getFlag('--no-detect-language', 'GEMINI_NO_DETECT_LANGUAGE') ?? settings.detectLanguage === false);
+const FILE_ATTRIBUTES = !(getFlag('--no-file-attributes', 'GEMINI_NO_FILE_ATTRIBUTES') ?? settings.fileAttributes === false);
+const MODEL = getArgValue('--model') || process.env.GEMINI_MODEL || settings.model;
+const GROUNDED_ONLY = getFlag('--grounded-only', 'GEMINI_GROUNDED_ONLY') ?? settings.groundedOnly === true;
+// Set explicitly, answers below it are also marked uncertain outside grounded-only mode
+const MIN_CONFIDENCE = getNumericOption('--min-confidence', 'GEMINI_MIN_GROUNDING_CONFIDENCE') ?? settings.minGroundingConfidence;
+const MIN_GROUNDING_CONFIDENCE = MIN_CONFIDENCE ?? DEFAULT_MIN_CONFIDENCE;
+const ROUTING: RoutingConfig = { ...settings.routing, mode: (getArgValue('--routing') || process.env.GEMINI_ROUTING || settings.routing.mode) as RoutingMode };
+if (!ROUTING_MODES.includes(ROUTING.mode)) {
+  console.error(`Error: --routing must be one of: ${ROUTING_MODES.join(', ')}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+// Generation defaults of every query; the search tool's arguments override them per call
+let GENERATION: GenerationParams = settings.generation;
+try {
+  const numeric = (flag: string, envName: string) => {
+    const raw = getArgValue(flag) || process.env[envName];
+    return raw ? Number(raw) : undefined;
+  };
+  GENERATION = mergeGenerationParams(settings.generation, validateGenerationParams({
+    temperature: numeric('--temperature', 'GEMINI_TEMPERATURE'),
+    topP: numeric('--top-p', 'GEMINI_TOP_P'),
+    maxOutputTokens: numeric('--max-output-tokens', 'GEMINI_MAX_OUTPUT_TOKENS'),
+    safety: parseSafetySettings([...(process.env.GEMINI_SAFETY || '').split(',').filter(Boolean), ...getArgValues('--safety')]),
+  }));
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+// Answer language of every query over [prompt] language; the search tool's answerLanguage overrides it per call
+let PROMPT: PromptConfig = settings.prompt;
+try {
+  const answerLang = getArgValue('--answer-lang') || process.env.GEMINI_ANSWER_LANG;
+  if (answerLang) PROMPT = withAnswerLanguage(settings.prompt, parseAnswerLanguage(answerLang));
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+const TRANSLATE_SNIPPETS = getFlag('--translate-snippets', 'GEMINI_TRANSLATE_SNIPPETS') ?? PROMPT.translateSnippets === true;
+// Daily request and token budgets of every API call the server makes, persisted across restarts
+let quota: QuotaScheduler;
+try {
+  quota = new QuotaScheduler(overrideQuotaConfig(settings.quota, {
+    requestsPerDay: getArgValue('--requests-per-day') || process.env.GEMINI_REQUESTS_PER_DAY,
+    tokensPerDay: getArgValue('--tokens-per-day') || process.env.GEMINI_TOKENS_PER_DAY,
+  }), quotaStateFile(), { signal: shutdown.signal });
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+const DEFAULT_STORE = getArgValue('--default-store') || process.env.GEMINI_DEFAULT_STORE || settings.defaultStore;
+// ACL mode: every query must be scoped to access labels, passed per call or set for the server
+let ACL: AclPolicy;
+try {
+  const labels = getArgValue('--acl-labels') || process.env.GEMINI_ACL_LABELS;
+  ACL = {
+    enforce: getFlag('--acl-mode', 'GEMINI_ACL_MODE') ?? settings.aclMode === true,
+    labels: labels ? parseAclLabels(labels) : settings.aclLabels,
+  };
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+
+const clientBuilder = FileSearchClient.builder()
+  .auth(auth)
+  .backend(backend)
+  .api(api)
+  .uploadSessionsFile(uploadSessionsFile())
+  .retry({ maxAttempts: RETRY_MAX_ATTEMPTS ? Math.floor(RETRY_MAX_ATTEMPTS) : undefined })
+  .timeouts(settings.timeouts)
+  .tenant(tenantPolicy(TENANT, getFlag('--allow-cross-tenant', 'GEMINI_ALLOW_CROSS_TENANT') === true))
+  .network({
+    proxy: getArgValue('--proxy') || settings.proxy,
+    caBundle: getArgValue('--ca-bundle') || process.env.GEMINI_CA_BUNDLE || settings.caBundle,
+    nativeCerts: getFlag('--native-certs', 'GEMINI_NATIVE_CERTS') ?? settings.nativeCerts === true,
+  })
+  .acl(ACL);
+if (MODEL) clientBuilder.model(MODEL);
+
+// Local command and/or webhook told when each document finishes indexing or fails
+const notifier = new Notifier({
+  command: getArgValue('--notify-command') || process.env.GEMINI_NOTIFY_COMMAND || settings.notifyCommand,
+  webhook: getArgValue('--notify-webhook') || process.env.GEMINI_NOTIFY_WEBHOOK || settings.notifyWebhook,
+});
+if (notifier.enabled) {
+  clientBuilder.notifier(notifier);
+  console.error('🔔 Indexing notifications enabled');
+}
+if (quota.enabled) {
+  clientBuilder.quota(quota);
+  console.error(`📅 ${formatQuotaUsage(quota)}`);
+}
+const client = clientBuilder.build();
+const network = describeNetwork();
+if (network) console.error(`🌐 Connecting through ${network}`);
+if (ACL.enforce) console.error(`🔒 ACL mode: queries are scoped to access labels${ACL.labels ? ` (default: ${ACL.labels.join(', ')})` : ''}`);
+
+const indexCache = new IndexCache(indexCacheFile());
+
+// Answers to repeated questions, reused until the TTL expires or a store changes
+const QUERY_CACHE_TTL = getNumericOption('--query-cache-ttl', 'GEMINI_QUERY_CACHE_TTL') ?? settings.queryCacheTtl;
+const queryCache = getFlag('--no-cache', 'GEMINI_NO_QUERY_CACHE') || settings.queryCache === false
+  ? undefined
+  : new QueryCache(queryCacheFile(), QUERY_CACHE_TTL);
+// Answered questions are recorded for npm run history
+const queryHistory = settings.queryHistory === false ? undefined : new QueryHistory(historyFile());
+
+// Returns undefined when a store revision cannot be read, which disables caching for the query
+async function queryCacheKey(stores: string[], question: string, model: string, params: QueryCacheKey['params']): Promise<string | undefined> {
+  try {
+    const revisions = (await Promise.all(stores.map(store => client.stores.getStore(store)))).map(storeRevision);
+    return QueryCache.key({ stores, revisions, question, model, params });
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Query cache skipped: ${err.message}`);
+    return undefined;
+  }
+}
+
+// Optional MIME override table
+const MIME_MAP_FILE = getArgValue('--mime-map') || process.env.GEMINI_MIME_MAP || settings.mimeMap;
+let mimeRegistry = new MimeRegistry();
+if (MIME_MAP_FILE) {
+  try {
+    mimeRegistry = MimeRegistry.fromTomlFile(path.resolve(MIME_MAP_FILE));
+    console.error(`✅ Loaded MIME map: ${MIME_MAP_FILE}`);
+  } catch (error) {
+    const err = error as Error;
+    console.error(`Error: Failed to load MIME map ${MIME_MAP_FILE}: ${err.message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+}
+
+const chunkingPolicy = settings.chunking;
+
+// Duplicate handling (skip, replace or alias) for files already in the store or repeated in one upload
+const DEDUP_MODE = (getArgValue('--dedup') || process.env.GEMINI_DEDUP || settings.dedup) as DedupMode | undefined;
+if (DEDUP_MODE && !DEDUP_MODES.includes(DEDUP_MODE)) {
+  console.error(`Error: --dedup must be one of: ${DEDUP_MODES.join(', ')}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+const DEDUP_DISTANCE = getNumericOption('--dedup-distance', 'GEMINI_DEDUP_DISTANCE') ?? settings.dedupDistance;
+
+// Secret and PII scanning before uploads: block, mask or tag; off when omitted
+let REDACTION: Redactor | undefined;
+try {
+  REDACTION = Redactor.load(
+    (getArgValue('--redaction-policy') || process.env.GEMINI_REDACTION_POLICY || settings.redactionPolicy) as RedactionPolicy | undefined,
+    [...(settings.redactionRules || []), ...getArgValues('--redaction-rules').map(file => path.resolve(file))]
+  );
+} catch (error) {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+
+// Local checks against the File Search limits before uploads: skip, strict or off
+const PREFLIGHT_MODE = (getArgValue('--preflight') || process.env.GEMINI_PREFLIGHT || settings.preflight || 'skip') as PreflightMode;
+if (!PREFLIGHT_MODES.includes(PREFLIGHT_MODE)) {
+  console.error(`Error: --preflight must be one of: ${PREFLIGHT_MODES.join(', ')}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+const MAX_STORE_DOCUMENTS = getNumericOption('--max-store-documents', 'GEMINI_MAX_STORE_DOCUMENTS') ?? settings.maxStoreDocuments;
+const STORAGE_TIER = getArgValue('--storage-tier') || process.env.GEMINI_STORAGE_TIER || settings.storageTier || 'free';
+if (!isStorageTier(STORAGE_TIER)) {
+  console.error(`Error: --storage-tier must be one of: ${STORAGE_TIERS.join(', ')}`);
+  process.exit(EXIT_CODES.CONFIG_INVALID);
+}
+const WITH_SUMMARIES = getFlag('--with-summaries', 'GEMINI_WITH_SUMMARIES') ?? settings.withSummaries === true;
+const SUMMARY_MIN_SIZE = getNumericOption('--summary-min-size', 'GEMINI_SUMMARY_MIN_SIZE') ?? settings.summaryMinSize;
+const PART_SIZE = getNumericOption('--part-size', 'GEMINI_PART_SIZE') ?? settings.partSize;
+// --on-error continue|fail-fast|abort-after:<n>, for every upload and sync
+const FAILURE_POLICY = (() => {
+  const value = getArgValue('--on-error') || process.env.GEMINI_ON_ERROR;
+  try {
+    return value ? parseFailurePolicy(value) : settings.onError;
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+})();
+// --report <format>:<file>, repeatable, or a comma-separated GEMINI_INGEST_REPORT: rewritten after every upload and sync
+const INGEST_REPORT = (() => {
+  const values = getArgValues('--report');
+  const targets = values.length > 0 ? values : (process.env.GEMINI_INGEST_REPORT || '').split(',').map(value => value.trim()).filter(Boolean);
+  try {
+    return CiReportWriter.of(targets.map(parseCiReportTarget));
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+})();
+
+// Ingestion settings shared by every project
+const ingestDefaults = {
+  mimeRegistry,
+  chunking: chunkingPolicy,
+  cache: indexCache,
+  quarantine: new Quarantine(quarantineListFile()),
+  concurrency: UPLOAD_CONCURRENCY ? Math.floor(UPLOAD_CONCURRENCY) : undefined,
+  requestsPerMinute: REQUESTS_PER_MINUTE,
+  extractLocally: EXTRACT_LOCALLY,
+  notebooks: { stripOutputs: STRIP_NOTEBOOK_OUTPUTS },
+  emails: { stripQuotes: STRIP_QUOTED_REPLIES },
+  normalizeEncoding: NORMALIZE_ENCODING,
+  detectLanguage: DETECT_LANGUAGE,
+  fileAttributes: FILE_ATTRIBUTES,
+  dedup: DEDUP_MODE,
+  dedupDistance: DEDUP_DISTANCE,
+  redaction: REDACTION,
+  transforms: settings.transforms,
+  signal: shutdown.signal,
+  abortSignal: shutdown.abortSignal,
+  preflightLimits: MAX_STORE_DOCUMENTS ? { maxStoreDocuments: Math.floor(MAX_STORE_DOCUMENTS) } : undefined,
+  summaries: WITH_SUMMARIES ? { minBytes: SUMMARY_MIN_SIZE ? Math.floor(SUMMARY_MIN_SIZE) : undefined } : undefined,
+  partSize: PART_SIZE ? Math.floor(PART_SIZE) : undefined,
+  report: INGEST_REPORT,
+  failurePolicy: FAILURE_POLICY,
+};
+
+// Accepts either {"team": "backend"} or ["team=backend"]
+function toDocumentMetadata(value: DocumentMetadata | string[] | undefined): DocumentMetadata | undefined {
+  if (value === undefined) return undefined;
+  if (Array.isArray(value)) return parseMetadataPairs(value);
+  validateMetadata(value);
+  return value;
+}
+
+// Background upload status management
+const uploadStatus = new Map<string, UploadStatus>();
+
+// Chat sessions are kept in memory and dropped after an hour without messages
+const CHAT_SESSION_TTL = 60 * 60 * 1000;
+const chatSessions = new Map<string, { session: ChatSession; lastUsed: number }>();
+
+// Active watchers by project ID (not persisted across restarts)
+const watchers = new Map<string, DirectoryWatcher>();
+
+// Project management functions
+function loadProjects(): ProjectsData {
+  try {
+    const data = fs.readFileSync(projectsFile(), 'utf8');
+    return JSON.parse(data) as ProjectsData;
+  } catch (error) {
+    return { projects: [] };
+  }
+}
+
+// Applies a change to the current file under its lock, so a project registered by
+// another run between loading and saving is kept
+// The lock is waited for asynchronously, so other requests are served meanwhile
+async function updateProjects(update: (projectsData: ProjectsData) => void): Promise<void> {
+  await withFileLockAsync(projectsFile(), () => {
+    const projectsData = loadProjects();
+    update(projectsData);
+    writeFileAtomic(projectsFile(), JSON.stringify(projectsData, null, 2));
+  });
+}
+
+// Path validation to prevent access to dangerous system directories
+function isPathSafe(projectPath: string): boolean {
+  try {
+    const resolvedPath = path.resolve(projectPath);
+    const realPath = fs.realpathSync(projectPath);
+
+    // Dangerous system directories to block
+    const dangerousPatterns: string[] = [
+      '/etc', '/var', '/usr/bin', '/usr/sbin', '/bin', '/sbin',
+      '/System', '/Library', '/Windows', '/Program Files', '/Windows/System32'
+    ];
+
+    // Check if path starts with any dangerous pattern
+    const isDangerous = dangerousPatterns.some(pattern =>
+      realPath.startsWith(pattern) || resolvedPath.startsWith(pattern)
+    );
+
+    return !isDangerous;
+  } catch (error) {
+    // If realpath fails (symlink issues, etc.), reject the path
+    return false;
+  }
+}
+
+function formatStoreMarkdown(store: StoreInfo): string {
+  return `## ${store.displayName || store.name}\n- **Name**: ${store.name}\n- **Active documents**: ${store.activeDocuments}\n- **Pending documents**: ${store.pendingDocuments}\n- **Failed documents**: ${store.failedDocuments}\n- **Size**: ${store.sizeBytes} bytes\n- **Created**: ${store.createTime ? new Date(store.createTime).toLocaleString() : 'N/A'}\n`;
+}
+
+// Estimates indexing tokens for files about to be uploaded, with countTokens when requested
+async function planUpload(
+  ingester: DirectoryIngester,
+  storeName: string | undefined,
+  rootDir: string,
+  files: string[],
+  countTokens: boolean = false
+): Promise<IngestPlan> {
+  const plan = ingester.dryRun(storeName, rootDir, files);
+  return countTokens ? countPlanTokens(client, rootDir, plan) : plan;
+}
+
+// Drops the files that would be rejected (skip) or refuses the upload (strict, or a
+// store over its document limit), so every problem is reported before the first request
+function enforcePreflight(report: PreflightReport, files: string[], rootDir: string): string[] {
+  if (PREFLIGHT_MODE === 'off' || report.violations.length === 0) return files;
+  const message = formatPreflightReport(report);
+  if (PREFLIGHT_MODE === 'strict' || hasStoreViolation(report)) {
+    throw new PreflightError(report.violations, message);
+  }
+  console.error(`⚠️  ${message}`);
+  const rejected = new Set(report.rejected);
+  return files.filter(filePath => !rejected.has(relativePathOf(rootDir, filePath)));
+}
+
+function formatPreflightSkips(report: PreflightReport | undefined): string {
+  return report && report.rejected.length > 0 ? `\n\nNot uploaded: ${report.rejected.length} files\n${formatPreflightReport(report)}` : '';
+}
+
+function enforceBudget(plan: IngestPlan, budget: number | undefined): void {
+  if (budget !== undefined && plan.estimatedTokens > budget) {
+    throw new BudgetExceededError(plan.estimatedTokens, budget);
+  }
+}
+
+function formatIngestPlan(plan: IngestPlan): string {
+  let text = `Would upload: ${plan.uploadCount} files (${formatBytes(plan.totalBytes)}, ~${plan.estimatedTokens} tokens)\nSkipped: ${plan.skippedCount} files`;
+  text += `\nEstimated indexing cost: ~${formatCost(indexingCost(plan.estimatedTokens))}`;
+
+  const byType = new Map<string, number>();
+  for (const file of plan.files) {
+    if (!file.skipped) byType.set(file.mimeType, (byType.get(file.mimeType) || 0) + 1);
+  }
+  if (byType.size > 0) {
+    const types = [...byType.entries()].sort((a, b) => b[1] - a[1]);
+    text += `\n\nBy type:\n${types.map(([mimeType, count]) => `- ${mimeType}: ${count}`).join('\n')}`;
+  }
+
+  const largest = plan.files.filter(file => !file.skipped).sort((a, b) => b.estimatedTokens - a.estimatedTokens).slice(0, 10);
+  if (largest.length > 0) {
+    text += `\n\nLargest files:\n${largest.map(file => `- ${file.path}: ~${file.estimatedTokens} tokens (${formatBytes(file.size)}${file.documents > 1 ? `, ${file.documents} documents` : ''})`).join('\n')}`;
+  }
+
+  const skipped = plan.files.filter(file => file.skipped);
+  if (skipped.length > 0) {
+    const shown = skipped.slice(0, 20);
+    text += `\n\nSkipped files:\n${shown.map(file => `- ${file.path} (${file.skipped})`).join('\n')}`;
+    if (skipped.length > shown.length) {
+      text += `\n- ... and ${skipped.length - shown.length} more`;
+    }
+  }
+  return text;
+}
+
+interface JobResult {
+  successCount: number;
+  errorCount: number;
+  fileCount: number;  // Documents in the store once the job finished
+  failures: IngestFailure[];
+  duplicates: IngestDuplicate[];
+  retries: number;
+  stopped?: string;
+}
+
+// Runs an upload/sync job for a project in the background, tracking progress in uploadStatus
+function startBackgroundJob(
+  projectId: string,
+  totalFiles: number,
+  run: (onProgress: (progress: IngestProgress) => void) => Promise<JobResult>
+): void {
+  // Initialize upload status
+  uploadStatus.set(projectId, {
+    status: 'uploading',
+    totalFiles,
+    successCount: 0,
+    errorCount: 0,
+    progress: 0,
+  });
+
+  shutdown.track((async () => {
+    const { successCount, errorCount, fileCount, failures, duplicates, retries, stopped } = await run(
+      progress => uploadStatus.set(projectId, { status: 'uploading', ...progress })
+    );
+
+    console.error(`🎉 Completed: ${successCount}/${totalFiles} files succeeded, ${errorCount} errors`);
+
+    // Update project fileCount after upload completes
+    await updateProjects(updatedData => {
+      const updatedProject = updatedData.projects.find(p => p.id === projectId);
+      if (updatedProject) updatedProject.fileCount = fileCount;
+    });
+
+    // Update upload status to completed
+    uploadStatus.set(projectId, {
+      status: 'completed',
+      totalFiles,
+      successCount,
+      errorCount,
+      progress: 100,
+      failures,
+      duplicates,
+      retries,
+      stopped,
+    });
+
+    // Clean up upload status after 24 hours
+    setTimeout(() => {
+      uploadStatus.delete(projectId);
+      console.error(`🧹 Cleaned up upload status for project: ${projectId}`);
+    }, 24 * 60 * 60 * 1000);
+  })()).catch(error => {
+    const err = error as Error;
+    console.error(`Background upload error:`, err);
+    uploadStatus.set(projectId, {
+      status: 'failed',
+      totalFiles,
+      successCount: 0,
+      errorCount: totalFiles,
+      progress: 0,
+      error: err.message,
+    });
+  });
+}
+
+// Uploads files for a project, recording each upload in the sync manifest
+function startBackgroundUpload(
+  project: Project,
+  ingester: DirectoryIngester,
+  files: string[],
+  existingFileCount: number = 0
+): void {
+  startBackgroundJob(project.id, files.length, async onProgress => {
+    const manifest = SyncManifest.load(project.path, project.storeId);
+    const summary = await ingester.upload(
+      client,
+      project.storeId,
+      project.path,
+      files,
+      onProgress,
+      (filePath, result) => manifest.record(project.path, filePath, result)
+    );
+    if (summary.errorCount === 0 && summary.remaining.length === 0) recordSnapshot(project.path, manifest, 'upload');
+    // Duplicates add no document: they are skipped, linked, or replace the copy they match
+    return { ...summary, fileCount: existingFileCount + summary.successCount - summary.duplicateCount };
+  });
+}
+
+function projectIngester(project: Project, resume: boolean = false): DirectoryIngester {
+  return new DirectoryIngester({
+    include: project.include,
+    exclude: project.exclude,
+    respectGitignore: project.respectGitignore,
+    resume,
+    metadata: project.metadata,
+    codeChunking: project.codeChunking,
+    rowChunking: project.rowChunking,
+    ...ingestDefaults,
+    dedup: project.dedup || ingestDefaults.dedup,
+  });
+}
+
+// Create MCP server
+const server = new Server(
+  {
+    name: 'gemini-file-search-mcp-server',
+    version: '1.0.0',
+  },
+  {
+    capabilities: {
+      tools: {},
+    },
+  }
+);
+
+// List available tools
+server.setRequestHandler(ListToolsRequestSchema, async () => {
+  const tools: Tool[] = [
+    {
+      name: 'gemini_list_projects',
+      description: 'List all registered Gemini File Search projects',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: json)',
+          },
+        },
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: false,
+      },
+    },
+    {
+      name: 'gemini_create_project',
+      description: 'Create a new Gemini File Search project and upload files (runs in background)',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          name: {
+            type: 'string',
+            description: 'Project name',
+          },
+          description: {
+            type: 'string',
+            description: 'Project description (optional)',
+          },
+          projectPath: {
+            type: 'string',
+            description: 'Absolute path to the project directory',
+          },
+          include: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Glob patterns of files to upload, e.g. "**/*.ts" (optional, default: all files)',
+          },
+          exclude: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Glob patterns of files or directories to skip, e.g. "target/**" (optional)',
+          },
+          respectGitignore: {
+            type: 'boolean',
+            description: 'Skip files ignored by .gitignore (default: true)',
+          },
+          metadata: {
+            type: 'object',
+            additionalProperties: {
+              anyOf: [
+                { type: 'string' },
+                { type: 'number' },
+                { type: 'array', items: { type: 'string' } },
+              ],
+            },
+            description: 'Custom metadata attached to every uploaded document, e.g. {"team": "backend", "lang": "rust"} (optional)',
+          },
+          acl: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of every uploaded document, stored as the "acl" metadata list; in ACL mode only queries passing one of them find it (optional)',
+          },
+          codeChunking: {
+            type: 'boolean',
+            description: 'Split Rust, Python, TypeScript/JavaScript and Go files along function, class and impl boundaries, uploading each symbol as its own document (default: false)',
+          },
+          rowChunking: {
+            type: 'object',
+            properties: {
+              rowsPerDocument: { type: 'integer', description: 'Rows per document (default: 1)' },
+              metadataColumns: { type: 'array', items: { type: 'string' }, description: 'Columns stored as metadata (default: the first 10)' },
+            },
+            description: 'Upload each row, or group of rowsPerDocument rows, of CSV and TSV files as its own document with the header as context and the column values as metadata, e.g. {"rowsPerDocument": 5} (optional)',
+          },
+          dedup: {
+            type: 'string',
+            enum: DEDUP_MODES,
+            description: 'Handle files with the same or near-identical content as another file: skip them, replace the older copy, or alias them to the existing document (default: server setting, off)',
+          },
+          dryRun: {
+            type: 'boolean',
+            description: 'Only report which files would be uploaded, with sizes and estimated tokens, without creating the store (default: false)',
+          },
+          countTokens: {
+            type: 'boolean',
+            description: 'Count tokens of text files with the countTokens API instead of the local estimate (default: false)',
+          },
+          budget: {
+            type: 'number',
+            minimum: 1,
+            description: 'Abort before uploading if the estimated indexing tokens exceed this (default: --budget server option)',
+          },
+        },
+        required: ['name', 'projectPath'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_get_upload_status',
+      description: 'Check the upload status of a Gemini File Search project',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown). JSON includes every failure with its reason and retry count',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: false,
+      },
+    },
+    {
+      name: 'gemini_resume_upload',
+      description: 'Resume an interrupted project upload: skips files already in the store and continues partially uploaded large files',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_sync_project',
+      description: 'Incrementally sync a project directory: uploads new and changed files and removes deleted ones from the store (runs in background)',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          dryRun: {
+            type: 'boolean',
+            description: 'Only report what would be uploaded and removed, without calling the API (default: false)',
+          },
+          countTokens: {
+            type: 'boolean',
+            description: 'Count tokens of text files with the countTokens API instead of the local estimate (default: false)',
+          },
+          budget: {
+            type: 'number',
+            minimum: 1,
+            description: 'Abort before uploading if the estimated indexing tokens exceed this (default: --budget server option)',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_rollback_project',
+      description: 'Roll a project\'s store back to a snapshot recorded by an earlier sync: re-uploads files changed or removed since and deletes documents added since (runs in background). Without "to", lists the snapshots.',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          to: {
+            type: 'number',
+            minimum: 1,
+            description: 'Snapshot ID to restore, as listed when omitted',
+          },
+          dryRun: {
+            type: 'boolean',
+            description: 'Only report what would be restored and deleted, without calling the API (default: false)',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_watch_project',
+      description: 'Watch a project directory and sync created, modified and deleted files to the store in the background as they change',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          action: {
+            type: 'string',
+            enum: ['start', 'stop', 'status'],
+            description: 'Start or stop watching, or show the watch status (default: start)',
+          },
+          debounceMs: {
+            type: 'number',
+            description: 'Quiet period after the last change before syncing, in milliseconds (default: 2000, min: 100)',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_delete_project',
+      description: 'Delete a Gemini File Search project and its store',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+        },
+        required: ['projectId'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_search_project',
+      description: 'Search project code and documentation using Gemini File Search to answer questions. Accepts several projects or stores to answer across corpora',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          projectIds: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Additional project IDs to search',
+          },
+          storeNames: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Additional store names to search (fileSearchStores/...), registered as projects or not, or sharded corpus names to search across all of their shards',
+          },
+          mode: {
+            type: 'string',
+            enum: FEDERATION_MODES,
+            description: 'How several stores are searched: "combined" passes all stores to one generation request, "fanout" queries each store concurrently and merges the answers into one section per store (default: combined)',
+          },
+          question: {
+            type: 'string',
+            description: 'Question to ask about the project; available to the template as {{question}}',
+          },
+          template: {
+            type: 'string',
+            description: 'Path to a prompt template rendered into the question, e.g. review.tmpl containing "Summarize the API surface of {{file}} citing sources."',
+          },
+          vars: {
+            type: 'object',
+            additionalProperties: { type: 'string' },
+            description: 'Template variables, e.g. {"file": "src/lib.rs"}',
+          },
+          stream: {
+            type: 'boolean',
+            description: 'Stream the answer as progress notifications while it is generated (default: false)',
+          },
+          filter: {
+            type: 'string',
+            description: 'Metadata filter, e.g. "lang=rust AND team=backend" (supports AND, OR, NOT, parentheses and =, !=, <, <=, >, >=)',
+          },
+          response_format: {
+            type: 'string',
+            enum: OUTPUT_FORMATS,
+            description: 'Output format: markdown with numbered footnotes, plain text, json with a typed citations list, or sarif (default: markdown)',
+          },
+          post: {
+            type: 'array',
+            items: { type: 'string', enum: listTransforms().map(t => t.name) },
+            description: 'Transforms applied to the answer in order before formatting, e.g. ["code-blocks"] or ["strip-markdown", "translate-ja"]',
+          },
+          groundedOnly: {
+            type: 'boolean',
+            description: 'Answer only from the documents: replies without grounding chunks or below minConfidence are replaced by a "no grounded answer" result (default: the server setting)',
+          },
+          minConfidence: {
+            type: 'number',
+            description: 'Minimum answer confidence (0-1): grounded-only mode rejects answers below it (default: 0.5), otherwise they are marked uncertain and returned with the retrieved chunks (default: the server setting, none when unset)',
+          },
+          system: {
+            type: 'string',
+            description: 'System instruction for this question, replacing the one stored in [prompt] for the searched stores; the stored tone, language and citation format still apply',
+          },
+          answerLanguage: {
+            type: 'string',
+            description: 'Language of the answer, as a code (e.g. "ja") or a name, whatever the documents are in; an answer that comes back in another language is translated (default: the server setting)',
+          },
+          translateSnippets: {
+            type: 'boolean',
+            description: 'Also translate the cited chunks to the answer language, keeping the originals as originalChunkText (default: the server setting)',
+          },
+          noCache: {
+            type: 'boolean',
+            description: 'Skip the query cache and ask the model again; the fresh answer replaces the cached one (default: false)',
+          },
+          allProjects: {
+            type: 'boolean',
+            description: 'Search every registered project; with routing, only the ones relevant to the question are queried (default: false)',
+          },
+          route: {
+            type: 'boolean',
+            description: 'Route the question to the relevant stores first when several are given (default: true when the server has routing configured; true with routing off uses keyword rules, then the classifier)',
+          },
+          model: {
+            type: 'string',
+            description: 'Generation model for this question, e.g. "gemini-2.5-flash" for lookups or "gemini-2.5-pro" for synthesis (default: the server model)',
+          },
+          temperature: {
+            type: 'number',
+            description: 'Sampling temperature, 0-2 (default: the server setting)',
+          },
+          topP: {
+            type: 'number',
+            description: 'Nucleus sampling probability mass, 0-1 (default: the server setting)',
+          },
+          maxOutputTokens: {
+            type: 'number',
+            description: 'Maximum tokens in the answer (default: the server setting)',
+          },
+          safety: {
+            type: 'object',
+            properties: Object.fromEntries(SAFETY_CATEGORIES.map(category => [category, { type: 'string', enum: SAFETY_THRESHOLDS }])),
+            additionalProperties: false,
+            description: 'Safety thresholds per harm category, e.g. {"dangerous_content": "block_only_high"}; other categories keep the server setting',
+          },
+          aclLabels: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of the caller: only documents uploaded with one of them are searched (default: the server --acl-labels; required in ACL mode)',
+          },
+        },
+        required: [],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_batch_query',
+      description: 'Run questions from a JSONL file against a project and write answers, citations and latency to a JSONL file',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID',
+          },
+          inputFile: {
+            type: 'string',
+            description: 'Absolute path of a JSONL file with one {"id", "question", "filter"} object per line',
+          },
+          outputFile: {
+            type: 'string',
+            description: 'Absolute path of the JSONL file to write results to (overwritten)',
+          },
+          concurrency: {
+            type: 'number',
+            description: 'Maximum queries in flight at once (default: 5)',
+          },
+          aclLabels: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of the caller: only documents uploaded with one of them are searched (default: the server --acl-labels; required in ACL mode)',
+          },
+        },
+        required: ['projectId', 'inputFile', 'outputFile'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_chat',
+      description: 'Multi-turn chat grounded in a project or store. Supports /reset, /store <name>, /save <file.md> and /help commands',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          message: {
+            type: 'string',
+            description: 'Question or command',
+          },
+          sessionId: {
+            type: 'string',
+            description: 'Session returned by a previous call; omit to start a new conversation',
+          },
+          projectId: {
+            type: 'string',
+            description: 'Project to chat with when starting a session',
+          },
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...) to chat with instead of a project (default: the configured default_store)',
+          },
+          groundedOnly: {
+            type: 'boolean',
+            description: 'When starting a session: answer only from the documents and replace unsupported replies with a "no grounded answer" result (default: the server setting)',
+          },
+          aclLabels: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'When starting a session: access labels of the caller; only documents uploaded with one of them are searched (default: the server --acl-labels; required in ACL mode)',
+          },
+        },
+        required: ['message'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_explain_file',
+      description: 'Explain a file section by section, citing the related documents of the store (callers, configuration, documentation). Uploads the file first when the store does not have it',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          filePath: {
+            type: 'string',
+            description: 'Absolute path of the file to explain',
+          },
+          projectId: {
+            type: 'string',
+            description: 'Project whose store is searched; display names are relative to its directory',
+          },
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...) to search instead of a project (default: the configured default_store)',
+          },
+          upload: {
+            type: 'boolean',
+            description: 'Upload the file when the store does not have it yet (default: true)',
+          },
+          maxSections: {
+            type: 'number',
+            description: 'Most sections a long file is split into (default: 20)',
+          },
+          aclLabels: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of the caller: only documents uploaded with one of them are searched (default: the server --acl-labels; required in ACL mode)',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['markdown', 'json'],
+            description: 'Output format (default: markdown)',
+          },
+        },
+        required: ['filePath'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_list_stores',
+      description: 'List all File Search stores owned by the API key, including stores not registered as projects',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: json)',
+          },
+        },
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_create_store',
+      description: 'Create an empty File Search store',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          displayName: {
+            type: 'string',
+            description: 'Human-readable store name',
+          },
+        },
+        required: ['displayName'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_get_store',
+      description: 'Show details of a File Search store (document counts and size)',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...)',
+          },
+        },
+        required: ['storeName'],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_get_store_usage',
+      description: 'Report document counts, bytes, estimated indexed tokens and remaining capacity of stores against the File Search limits',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (default: every store)',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown)',
+          },
+        },
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_get_store_stats',
+      description: 'Report document counts by MIME type, a size histogram, metadata key cardinality, top terms of cached chunks and suspect files (lockfiles, binaries, minified code) of a store',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name',
+          },
+          topTerms: {
+            type: 'number',
+            description: 'Top terms to report (default: 20)',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown)',
+          },
+        },
+        required: ['storeName'],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_verify_store',
+      description: 'Check the sha256 metadata of every document in a store against the local files of its projects and the hashes recorded at upload, reporting files edited since upload, tampered checksums and synced files gone from the store',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown)',
+          },
+        },
+        required: ['storeName'],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_delete_store',
+      description: 'Delete a File Search store. Projects using the store are unregistered as well',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...)',
+          },
+          force: {
+            type: 'boolean',
+            description: 'Also delete the documents in the store (default: true)',
+          },
+        },
+        required: ['storeName'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_list_documents',
+      description: 'List the documents in a project\'s or store\'s File Search store with their state, size and MIME type',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID (either projectId or storeName is required)',
+          },
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...)',
+          },
+          state: {
+            type: 'string',
+            enum: ['active', 'pending', 'failed'],
+            description: 'Only list documents in this indexing state (optional)',
+          },
+          limit: {
+            type: 'integer',
+            description: 'Stop after this many documents; the reply then ends with the pageToken that continues the listing (optional)',
+          },
+          pageToken: {
+            type: 'string',
+            description: 'Continue a listing from the pageToken of an earlier reply (optional)',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown)',
+          },
+        },
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_get_document',
+      description: 'Show a document\'s metadata, size, MIME type, indexing state and estimated chunk count',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          documentName: {
+            type: 'string',
+            description: 'Document resource name (fileSearchStores/.../documents/...)',
+          },
+          response_format: {
+            type: 'string',
+            enum: ['json', 'markdown'],
+            description: 'Response format (default: markdown)',
+          },
+        },
+        required: ['documentName'],
+      },
+      annotations: {
+        readOnlyHint: true,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_delete_document',
+      description: 'Delete a single document from its File Search store. The next sync of the owning project uploads the file again',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          documentName: {
+            type: 'string',
+            description: 'Document resource name (fileSearchStores/.../documents/...)',
+          },
+        },
+        required: ['documentName'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_export_store',
+      description: 'Export a store (store info, document list and optionally the source files of its documents) to a .tar.gz file or a directory, for backup or migration to another project or API key',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          projectId: {
+            type: 'string',
+            description: 'Project ID (the project settings are exported too)',
+          },
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...), alternative to projectId',
+          },
+          outputPath: {
+            type: 'string',
+            description: 'Archive path; a name ending in .tar.gz or .tgz writes a compressed archive, anything else a directory',
+          },
+          includeContent: {
+            type: 'boolean',
+            description: 'Include the source files of the documents, read from the project directory or sourcePath (default: false)',
+          },
+          sourcePath: {
+            type: 'string',
+            description: 'Directory the documents were uploaded from (default: the project path)',
+          },
+        },
+        required: ['outputPath'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_import_archive',
+      description: 'Restore an archive written by gemini_export_store into a new store. Archives that contain a project are registered as a new project',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          archivePath: {
+            type: 'string',
+            description: 'Path to the .tar.gz file or directory written by gemini_export_store',
+          },
+          displayName: {
+            type: 'string',
+            description: 'Display name of the new store and project (default: the exported name)',
+          },
+          projectPath: {
+            type: 'string',
+            description: 'Local checkout of the project on this machine (default: the exported project path)',
+          },
+        },
+        required: ['archivePath'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_import_file',
+      description: 'Import a single file into a File Search store, either a local file or an existing Files API file',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...) (default: the configured default_store)',
+          },
+          filePath: {
+            type: 'string',
+            description: 'Absolute path of a local file to upload and import',
+          },
+          fileName: {
+            type: 'string',
+            description: 'Name of an existing Files API file (files/...), used instead of filePath',
+          },
+          metadata: {
+            type: 'object',
+            additionalProperties: {
+              anyOf: [
+                { type: 'string' },
+                { type: 'number' },
+                { type: 'array', items: { type: 'string' } },
+              ],
+            },
+            description: 'Custom metadata attached to the document, e.g. {"team": "backend", "lang": "rust"} (optional)',
+          },
+          acl: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of the document, stored as the "acl" metadata list; in ACL mode only queries passing one of them find it (optional)',
+          },
+          chunking: {
+            type: 'object',
+            properties: {
+              maxTokensPerChunk: { type: 'number' },
+              maxOverlapTokens: { type: 'number' },
+            },
+            description: 'Chunking for this document (optional, default: the configured per-extension defaults)',
+          },
+        },
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_add_document',
+      description: 'Add a document to a File Search store from text, e.g. notes, a generated summary or a page an agent fetched, without writing it to a file first',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...) (default: the configured default_store)',
+          },
+          displayName: {
+            type: 'string',
+            description: 'Document title shown in citations, e.g. "notes/2024-05-design-review.md"',
+          },
+          text: {
+            type: 'string',
+            description: 'Content of the document',
+          },
+          mimeType: {
+            type: 'string',
+            description: 'MIME type of the text, e.g. "text/markdown" (default: text/plain)',
+          },
+          metadata: {
+            type: 'object',
+            additionalProperties: {
+              anyOf: [
+                { type: 'string' },
+                { type: 'number' },
+                { type: 'array', items: { type: 'string' } },
+              ],
+            },
+            description: 'Custom metadata attached to the document, e.g. {"team": "backend"} (optional)',
+          },
+          acl: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of the document, stored as the "acl" metadata list (optional)',
+          },
+        },
+        required: ['displayName', 'text'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: false,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_import_url',
+      description: 'Fetch a web page, or every page of a sitemap, strip navigation and other boilerplate, and upload the main content as Markdown with a source_url metadata key',
+      inputSchema: {
+        type: 'object',
+        properties: {
+          storeName: {
+            type: 'string',
+            description: 'Store resource name (fileSearchStores/...) (default: the configured default_store)',
+          },
+          url: {
+            type: 'string',
+            description: 'http(s) URL of the page, or of a sitemap.xml when sitemap is true',
+          },
+          sitemap: {
+            type: 'boolean',
+            description: 'Treat url as a sitemap (or sitemap index) and upload every page it lists (default: false)',
+          },
+          maxPages: {
+            type: 'number',
+            description: 'Maximum pages taken from a sitemap (default: 500)',
+          },
+          metadata: {
+            type: 'object',
+            additionalProperties: {
+              anyOf: [
+                { type: 'string' },
+                { type: 'number' },
+                { type: 'array', items: { type: 'string' } },
+              ],
+            },
+            description: 'Custom metadata attached to every page besides source_url (optional)',
+          },
+          acl: {
+            type: 'array',
+            items: { type: 'string' },
+            description: 'Access labels of every page, stored as the "acl" metadata list; in ACL mode only queries passing one of them find it (optional)',
+          },
+        },
+        required: ['url'],
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: true,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+    {
+      name: 'gemini_prune_cache',
+      description: 'Remove index cache entries whose store or document no longer exists',
+      inputSchema: {
+        type: 'object',
+        properties: {},
+      },
+      annotations: {
+        readOnlyHint: false,
+        destructiveHint: false,
+        idempotentHint: true,
+        openWorldHint: true,
+      },
+    },
+  ];
+
+  return { tools };
+});
+
+// Handle tool execution
+server.setRequestHandler(CallToolRequestSchema, async (request, extra) => {
+  const { name, arguments: args } = request.params;
+
+  try {
+    switch (name) {
+      case 'gemini_list_projects': {
+        const { response_format = 'json' } = args as { response_format?: 'json' | 'markdown' };
+        const projectsData = loadProjects();
+
+        if (response_format === 'markdown') {
+          const markdown = `# Gemini File Search Projects\n\n` +
+            (projectsData.projects.length === 0
+              ? 'No projects found.'
+              : projectsData.projects.map(p =>
+                  `## ${p.name}\n- **ID**: ${p.id}\n- **Files**: ${p.fileCount}\n- **Created**: ${new Date(p.createdAt).toLocaleString()}\n- **Path**: ${p.path}\n- **Description**: ${p.description || 'N/A'}\n`
+                ).join('\n'));
+          return { content: [{ type: 'text', text: markdown }] };
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: JSON.stringify(projectsData.projects, null, 2),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_create_project': {
+        const { name: projectName, description, projectPath, include, exclude, respectGitignore, metadata: rawMetadata, acl, codeChunking, rowChunking, dedup, dryRun, countTokens, budget = TOKEN_BUDGET } = args as {
+          name: string;
+          description?: string;
+          projectPath: string;
+          include?: string[];
+          exclude?: string[];
+          respectGitignore?: boolean;
+          metadata?: DocumentMetadata | string[];
+          acl?: string[];
+          codeChunking?: boolean;
+          rowChunking?: RowChunkingOptions;
+          dedup?: DedupMode;
+          dryRun?: boolean;
+          countTokens?: boolean;
+          budget?: number;
+        };
+
+        let metadata: DocumentMetadata | undefined;
+        try {
+          metadata = toDocumentMetadata(rawMetadata);
+          if (acl) metadata = withAclLabels(metadata || {}, parseAclLabels(acl));
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        if (dedup !== undefined && !DEDUP_MODES.includes(dedup)) {
+          return {
+            content: [{ type: 'text', text: `Error: dedup must be one of: ${DEDUP_MODES.join(', ')}` }],
+            isError: true,
+          };
+        }
+        const rows = rowChunking?.rowsPerDocument;
+        if (rows !== undefined && !(Number.isInteger(rows) && rows >= 1 && rows <= 10000)) {
+          return {
+            content: [{ type: 'text', text: 'Error: rowChunking.rowsPerDocument must be an integer between 1 and 10000' }],
+            isError: true,
+          };
+        }
+
+        // Input validation - project name
+        if (!projectName || projectName.trim().length === 0) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project name cannot be empty' }],
+            isError: true,
+          };
+        }
+        if (projectName.length > 100) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project name too long (max 100 characters)' }],
+            isError: true,
+          };
+        }
+
+        // Check for duplicate project names
+        const projectsData = loadProjects();
+        const existingProject = projectsData.projects.find(p => p.name === projectName);
+        if (existingProject) {
+          return {
+            content: [{ type: 'text', text: 'Error: A project with this name already exists' }],
+            isError: true,
+          };
+        }
+
+        // Path validation - check existence
+        if (!fs.existsSync(projectPath)) {
+          return {
+            content: [{ type: 'text', text: 'Error: The specified directory does not exist' }],
+            isError: true,
+          };
+        }
+
+        // Path validation - security check
+        if (!isPathSafe(projectPath)) {
+          return {
+            content: [{ type: 'text', text: 'Error: Access to this directory is not allowed for security reasons' }],
+            isError: true,
+          };
+        }
+
+        // Collect files
+        const ingester = new DirectoryIngester({
+          include,
+          exclude,
+          respectGitignore,
+          metadata,
+          codeChunking,
+          rowChunking,
+          ...ingestDefaults,
+          dedup: dedup || ingestDefaults.dedup,
+        });
+        const collected = ingester.collect(projectPath);
+        const preflight = PREFLIGHT_MODE === 'off' ? undefined : ingester.preflight(undefined, projectPath, collected);
+        if (dryRun) {
+          const plan = await planUpload(ingester, undefined, projectPath, collected, countTokens);
+          return {
+            content: [
+              {
+                type: 'text',
+                text: `Dry run for project "${projectName}" (no store created, nothing uploaded).\n\nMatched files: ${collected.length}\n${formatIngestPlan(plan)}${preflight ? `\n\n${formatPreflightReport(preflight)}` : ''}`,
+              },
+            ],
+          };
+        }
+
+        const allFiles = preflight ? enforcePreflight(preflight, collected, projectPath) : collected;
+        const totalFiles = allFiles.length;
+        if (budget !== undefined) {
+          enforceBudget(await planUpload(ingester, undefined, projectPath, allFiles, countTokens), budget);
+        }
+
+        // Create file search store
+        const fileSearchStoreName = await client.createStore(projectName);
+
+        // Create project (before upload starts)
+        const projectId = `project-${randomUUID()}`;
+        const newProject: Project = {
+          id: projectId,
+          name: projectName,
+          description: description || '',
+          storeId: fileSearchStoreName,
+          path: projectPath,
+          createdAt: new Date().toISOString(),
+          fileCount: 0, // Will be updated after upload completes
+          include,
+          exclude,
+          respectGitignore,
+          metadata,
+          codeChunking,
+          rowChunking,
+          dedup,
+        };
+
+        await updateProjects(updatedData => {
+          updatedData.projects.push(newProject);
+        });
+
+        // Calculate estimated time (approximately 3 seconds per file)
+        const estimatedMinutes = Math.ceil((totalFiles * 3) / 60);
+        console.error(`📁 Collected ${totalFiles} files (estimated processing time: ${estimatedMinutes} minutes)`);
+
+        startBackgroundUpload(newProject, ingester, allFiles);
+
+        // Return response immediately
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Project "${projectName}" created successfully.\n\nProject ID: ${projectId}\nTotal files: ${totalFiles}\nEstimated processing time: ${estimatedMinutes} minutes\n\nFile upload started in background.\nTo check progress: use gemini_get_upload_status tool.${formatPreflightSkips(preflight)}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_get_upload_status': {
+        const { projectId, response_format = 'markdown' } = args as { projectId: string; response_format?: 'json' | 'markdown' };
+        const status = uploadStatus.get(projectId);
+
+        if (!status) {
+          return {
+            content: [{ type: 'text', text: 'Error: Upload status not found (may have already completed)' }],
+            isError: true,
+          };
+        }
+
+        if (response_format === 'json') {
+          return {
+            content: [{ type: 'text', text: JSON.stringify({ projectId, ...status, quota: quota.enabled ? quota.usage() : undefined }, null, 2) }],
+          };
+        }
+
+        let statusText = `Project ID: ${projectId}\n`;
+        statusText += `Status: ${status.status}\n`;
+        statusText += `Progress: ${status.progress}%\n`;
+        statusText += `Success: ${status.successCount}/${status.totalFiles} files\n`;
+        statusText += `Errors: ${status.errorCount} files`;
+
+        if (status.status === 'uploading' && status.lastFile) {
+          statusText += `\nLast file: ${status.lastFile}`;
+        }
+        if (status.retries) {
+          statusText += `\nRetries: ${status.retries}`;
+        }
+        if (status.stopped) {
+          statusText += `\nStopped: ${status.stopped}; the files left are uploaded by gemini_resume_upload or the next sync`;
+        }
+        if (quota.enabled) {
+          statusText += `\n${formatQuotaUsage(quota)}`;
+        }
+        if (status.failures && status.failures.length > 0) {
+          const shown = status.failures.slice(0, 20);
+          statusText += `\nFailed files:\n${shown.map(f => `- ${f.path}: ${f.code ? `${f.code} ` : ''}${f.reason}${f.retries > 0 ? ` (${f.retries} retries)` : ''}`).join('\n')}`;
+          if (status.failures.length > shown.length) {
+            statusText += `\n- ... and ${status.failures.length - shown.length} more`;
+          }
+        }
+        if (status.duplicates && status.duplicates.length > 0) {
+          const shown = status.duplicates.slice(0, 20);
+          statusText += `\nDuplicates (${status.duplicates.length}):\n${shown.map(d => `- ${d.path}: ${d.action} (${d.exact ? 'copy' : 'near-duplicate'} of ${d.of})`).join('\n')}`;
+          if (status.duplicates.length > shown.length) {
+            statusText += `\n- ... and ${status.duplicates.length - shown.length} more`;
+          }
+        }
+        if (status.error) {
+          statusText += `\nError details: ${status.error}`;
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: statusText,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_resume_upload': {
+        const { projectId } = args as { projectId: string };
+        const projectsData = loadProjects();
+        const project = projectsData.projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+
+        if (uploadStatus.get(projectId)?.status === 'uploading') {
+          return {
+            content: [{ type: 'text', text: 'Error: An upload is already running for this project' }],
+            isError: true,
+          };
+        }
+
+        // Skip files whose documents already made it into the store
+        const documents = await client.listDocuments(project.storeId);
+        const uploadedNames = new Set(documents.map(d => d.displayName));
+        const ingester = projectIngester(project, true);
+        const missingFiles = ingester.collect(project.path)
+          .filter(filePath => !uploadedNames.has(toDisplayName(project.path, filePath)));
+        const preflight = PREFLIGHT_MODE === 'off' ? undefined : ingester.preflight(project.storeId, project.path, missingFiles, documents.length);
+        const remainingFiles = preflight ? enforcePreflight(preflight, missingFiles, project.path) : missingFiles;
+        if (TOKEN_BUDGET !== undefined) {
+          enforceBudget(await planUpload(ingester, project.storeId, project.path, remainingFiles), TOKEN_BUDGET);
+        }
+
+        startBackgroundUpload(project, ingester, remainingFiles, documents.length);
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Resuming upload for project "${project.name}".\n\nAlready uploaded: ${documents.length} files\nRemaining files: ${remainingFiles.length}\n\nTo check progress: use gemini_get_upload_status tool.${formatPreflightSkips(preflight)}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_sync_project': {
+        const { projectId, dryRun, countTokens, budget = TOKEN_BUDGET } = args as {
+          projectId: string;
+          dryRun?: boolean;
+          countTokens?: boolean;
+          budget?: number;
+        };
+        const projectsData = loadProjects();
+        const project = projectsData.projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+
+        if (uploadStatus.get(projectId)?.status === 'uploading') {
+          return {
+            content: [{ type: 'text', text: 'Error: An upload is already running for this project' }],
+            isError: true,
+          };
+        }
+
+        if (watchers.has(projectId)) {
+          return {
+            content: [{ type: 'text', text: 'Error: This project is being watched, changes are already synced automatically' }],
+            isError: true,
+          };
+        }
+
+        const ingester = projectIngester(project);
+        const manifest = SyncManifest.load(project.path, project.storeId);
+        // A sync that crashed left its unfinished operations in the journal
+        if (!dryRun) await reconcileSync(client, project.storeId, project.path, manifest, ingester);
+        const plan = planSync(project.path, ingester.collect(project.path), manifest);
+
+        const candidates = [...plan.added, ...plan.changed].map(key => resolvePathOf(project.path, key));
+        let preflight: PreflightReport | undefined;
+        if (PREFLIGHT_MODE !== 'off') {
+          // Documents of changed and removed files are deleted by the sync
+          const store = await client.stores.getStore(project.storeId);
+          const freed = [...plan.changed, ...plan.removed]
+            .reduce((sum, key) => sum + (manifest.get(key)?.documentNames?.length || 1), 0);
+          preflight = ingester.preflight(project.storeId, project.path, candidates, Math.max(0, store.activeDocuments + store.pendingDocuments - freed));
+        }
+        if (dryRun) {
+          const uploadPlan = await planUpload(ingester, project.storeId, project.path, candidates, countTokens);
+          return {
+            content: [
+              {
+                type: 'text',
+                text: `Dry run for project "${project.name}" (nothing uploaded or removed).\n\nNew: ${plan.added.length}\nChanged: ${plan.changed.length}\nRemoved: ${plan.removed.length}\nUnchanged: ${plan.unchanged.length}\n\n${formatIngestPlan(uploadPlan)}${preflight ? `\n\n${formatPreflightReport(preflight)}` : ''}`,
+              },
+            ],
+          };
+        }
+
+        const toUpload = preflight ? enforcePreflight(preflight, candidates, project.path) : candidates;
+        if (toUpload.length < candidates.length) {
+          // Rejected files keep their manifest entry, or lack of one, so the next sync checks them again
+          const kept = new Set(toUpload.map(filePath => relativePathOf(project.path, filePath)));
+          plan.added = plan.added.filter(key => kept.has(key));
+          plan.changed = plan.changed.filter(key => kept.has(key));
+        }
+        const totalFiles = plan.added.length + plan.changed.length;
+        if (budget !== undefined) {
+          enforceBudget(await planUpload(ingester, project.storeId, project.path, toUpload, countTokens), budget);
+        }
+
+        startBackgroundJob(projectId, totalFiles, async onProgress => {
+          const summary = await applySync(client, project.storeId, project.path, plan, manifest, ingester, onProgress);
+          return {
+            successCount: summary.uploaded,
+            errorCount: summary.errorCount,
+            fileCount: manifest.size,
+            failures: summary.failures,
+            duplicates: summary.duplicates,
+            retries: summary.retries,
+            stopped: summary.stopped,
+          };
+        });
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Sync started for project "${project.name}".\n\nNew: ${plan.added.length}\nChanged: ${plan.changed.length}\nRemoved: ${plan.removed.length}\nUnchanged: ${plan.unchanged.length}\n\nTo check progress: use gemini_get_upload_status tool.${formatPreflightSkips(preflight)}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_rollback_project': {
+        const { projectId, to, dryRun } = args as {
+          projectId: string;
+          to?: number;
+          dryRun?: boolean;
+        };
+        const projectsData = loadProjects();
+        const project = projectsData.projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+
+        const snapshots = SnapshotStore.open(project.path);
+        if (to === undefined) {
+          const list = snapshots.list(project.storeId);
+          const lines = list.map(info => `- ${info.id}: ${info.createdAt} (${info.reason}), ${info.fileCount} file(s), ${info.documentCount} document(s)`);
+          return {
+            content: [
+              {
+                type: 'text',
+                text: list.length > 0
+                  ? `Snapshots of project "${project.name}", newest first:\n\n${lines.join('\n')}`
+                  : `No snapshots of project "${project.name}" yet. One is recorded after each successful upload or sync.`,
+              },
+            ],
+          };
+        }
+
+        if (uploadStatus.get(projectId)?.status === 'uploading') {
+          return {
+            content: [{ type: 'text', text: 'Error: An upload is already running for this project' }],
+            isError: true,
+          };
+        }
+
+        if (watchers.has(projectId)) {
+          return {
+            content: [{ type: 'text', text: 'Error: This project is being watched; stop watching it before rolling back' }],
+            isError: true,
+          };
+        }
+
+        const manifest = SyncManifest.load(project.path, project.storeId);
+        const plan = planRollback(manifest, snapshots.get(to), snapshots);
+        const counts = `Restored: ${plan.restored.length}\nDeleted: ${plan.deleted.length}\nUnchanged: ${plan.unchanged.length}${plan.missing.length > 0 ? `\nNot restorable (content not kept): ${plan.missing.join(', ')}` : ''}`;
+        if (dryRun) {
+          return {
+            content: [{ type: 'text', text: `Dry run of the rollback of project "${project.name}" to snapshot ${to} (nothing uploaded or deleted).\n\n${counts}` }],
+          };
+        }
+
+        const ingester = projectIngester(project);
+        await reconcileSync(client, project.storeId, project.path, manifest, ingester);
+        startBackgroundJob(projectId, plan.restored.length, async onProgress => {
+          const summary = await rollbackSync(client, project.storeId, project.path, manifest, ingester, to, onProgress);
+          return {
+            successCount: summary.restored.length - summary.failures.filter(failure => summary.restored.includes(failure.path)).length,
+            errorCount: summary.errorCount,
+            fileCount: manifest.size,
+            failures: summary.failures,
+            duplicates: [],
+            retries: 0,
+          };
+        });
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Rollback of project "${project.name}" to snapshot ${to} started.\n\n${counts}\n\nLocal files are not changed: the next sync uploads those that differ from the snapshot again.\n\nTo check progress: use gemini_get_upload_status tool.`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_watch_project': {
+        const { projectId, action = 'start', debounceMs } = args as {
+          projectId: string;
+          action?: 'start' | 'stop' | 'status';
+          debounceMs?: number;
+        };
+        const projectsData = loadProjects();
+        const project = projectsData.projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+
+        const existing = watchers.get(projectId);
+        if (action === 'stop') {
+          if (!existing) {
+            return {
+              content: [{ type: 'text', text: `Project "${project.name}" is not being watched.` }],
+            };
+          }
+          watchers.delete(projectId);
+          await existing.stop();
+          return {
+            content: [{ type: 'text', text: `Stopped watching project "${project.name}".` }],
+          };
+        }
+
+        if (action === 'status') {
+          if (!existing) {
+            return {
+              content: [{ type: 'text', text: `Project "${project.name}" is not being watched.` }],
+            };
+          }
+          const status = existing.status;
+          const last = status.lastSummary;
+          let text = `# Watch Status: ${project.name}\n\n- **Directory**: ${status.rootDir}\n- **Since**: ${new Date(status.startedAt).toLocaleString()}\n- **State**: ${status.syncing ? 'syncing' : 'idle'}\n- **Pending changes**: ${status.pendingChanges}\n- **Syncs**: ${status.syncCount}\n`;
+          if (status.lastSyncAt && last) {
+            text += `- **Last sync**: ${new Date(status.lastSyncAt).toLocaleString()} (${last.uploaded} uploaded, ${last.deleted} deleted, ${last.errorCount} errors)\n`;
+          }
+          if (status.lastError) {
+            text += `- **Last error**: ${status.lastError}\n`;
+          }
+          return { content: [{ type: 'text', text }] };
+        }
+
+        if (action !== 'start') {
+          return {
+            content: [{ type: 'text', text: 'Error: action must be one of: start, stop, status' }],
+            isError: true,
+          };
+        }
+        if (existing) {
+          return {
+            content: [{ type: 'text', text: `Project "${project.name}" is already being watched.` }],
+          };
+        }
+        if (debounceMs !== undefined && (!Number.isFinite(debounceMs) || debounceMs < 100)) {
+          return {
+            content: [{ type: 'text', text: 'Error: debounceMs must be at least 100' }],
+            isError: true,
+          };
+        }
+        if (!fs.existsSync(project.path) || !isPathSafe(project.path)) {
+          return {
+            content: [{ type: 'text', text: 'Error: The project directory does not exist or is not allowed' }],
+            isError: true,
+          };
+        }
+
+        const watcher = new DirectoryWatcher(client, project.storeId, project.path, projectIngester(project), {
+          debounceMs,
+          canSync: () => uploadStatus.get(projectId)?.status !== 'uploading',
+          onSync: async (_summary, manifest) => {
+            await updateProjects(updatedData => {
+              const updatedProject = updatedData.projects.find(p => p.id === projectId);
+              if (updatedProject) updatedProject.fileCount = manifest.size;
+            });
+          },
+        });
+        watcher.start();
+        watchers.set(projectId, watcher);
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Watching project "${project.name}" (${project.path}).\n\nChanges are synced after ${debounceMs ?? 2000}ms without further changes. Use action "status" to check, or "stop" to stop watching.`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_delete_project': {
+        const { projectId } = args as { projectId: string };
+        const projectsData = loadProjects();
+        const project = projectsData.projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+
+        const watcher = watchers.get(projectId);
+        if (watcher) {
+          watchers.delete(projectId);
+          await watcher.stop();
+        }
+
+        // Delete store
+        await client.deleteStore(project.storeId);
+        indexCache.forgetStore(project.storeId);
+
+        // Remove from project list
+        await updateProjects(updatedData => {
+          updatedData.projects = updatedData.projects.filter(p => p.id !== projectId);
+        });
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Project "${project.name}" deleted successfully.`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_search_project': {
+        const {
+          projectId,
+          projectIds = [],
+          storeNames = [],
+          mode = 'combined',
+          question: asked,
+          stream = false,
+          filter,
+          template,
+          vars = {},
+          response_format = 'markdown',
+          post = [],
+          groundedOnly = GROUNDED_ONLY,
+          minConfidence: requestedMinConfidence,
+          system,
+          answerLanguage: requestedLanguage,
+          translateSnippets = TRANSLATE_SNIPPETS,
+          noCache = false,
+          allProjects = false,
+          route,
+          model: requestedModel,
+          temperature,
+          topP,
+          maxOutputTokens,
+          safety,
+          aclLabels: requestedAclLabels,
+        } = args as {
+          projectId?: string;
+          projectIds?: string[];
+          storeNames?: string[];
+          mode?: FederationMode;
+          question?: string;
+          template?: string;
+          vars?: TemplateVars;
+          stream?: boolean;
+          filter?: string;
+          response_format?: OutputFormat;
+          post?: string[];
+          groundedOnly?: boolean;
+          minConfidence?: number;
+          system?: string;
+          answerLanguage?: string;
+          translateSnippets?: boolean;
+          noCache?: boolean;
+          allProjects?: boolean;
+          route?: boolean;
+          model?: string;
+          temperature?: number;
+          topP?: number;
+          maxOutputTokens?: number;
+          safety?: { [category: string]: string };
+          aclLabels?: string[];
+        };
+        const projectsData = loadProjects();
+
+        const targets: FederatedTarget[] = [];
+        const requestedIds = allProjects ? projectsData.projects.map(p => p.id) : [...(projectId ? [projectId] : []), ...projectIds];
+        for (const id of requestedIds) {
+          const project = projectsData.projects.find(p => p.id === id);
+          if (!project) {
+            return {
+              content: [{ type: 'text', text: `Error: Project not found: ${id}` }],
+              isError: true,
+            };
+          }
+          targets.push({ storeName: project.storeId, label: project.name });
+        }
+        const shardMap = new ShardMap(shardsFile());  // Read per call: the shards CLI may have added corpora
+        for (const storeName of storeNames) {
+          const project = projectsData.projects.find(p => p.storeId === storeName);
+          const shards = shardMap.get(storeName)?.stores;
+          targets.push({ storeName, label: project?.name || storeName, shards });
+        }
+        // The same store may be named by project and by store name
+        let uniqueTargets = targets.filter((t, i) => targets.findIndex(other => other.storeName === t.storeName) === i);
+        let stores = uniqueTargets.map(t => t.storeName);
+
+        if (stores.length === 0) {
+          return {
+            content: [{ type: 'text', text: 'Error: projectId, projectIds, storeNames or allProjects is required' }],
+            isError: true,
+          };
+        }
+        if (!FEDERATION_MODES.includes(mode)) {
+          return {
+            content: [{ type: 'text', text: `Error: mode must be one of: ${FEDERATION_MODES.join(', ')}` }],
+            isError: true,
+          };
+        }
+        if (!OUTPUT_FORMATS.includes(response_format)) {
+          return {
+            content: [{ type: 'text', text: `Error: response_format must be one of: ${OUTPUT_FORMATS.join(', ')}` }],
+            isError: true,
+          };
+        }
+
+        let transforms: AnswerTransform[];
+        try {
+          transforms = resolveTransforms(post);
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        const minConfidence = requestedMinConfidence ?? MIN_GROUNDING_CONFIDENCE;
+        if (typeof minConfidence !== 'number' || minConfidence < 0 || minConfidence > 1) {
+          return {
+            content: [{ type: 'text', text: 'Error: minConfidence must be between 0 and 1' }],
+            isError: true,
+          };
+        }
+
+        let question = asked;
+        if (template) {
+          if (!fs.existsSync(template)) {
+            return {
+              content: [{ type: 'text', text: 'Error: The specified template does not exist' }],
+              isError: true,
+            };
+          }
+          if (!isPathSafe(template)) {
+            return {
+              content: [{ type: 'text', text: 'Error: Access to this template is not allowed for security reasons' }],
+              isError: true,
+            };
+          }
+          try {
+            question = renderTemplateFile(path.resolve(template), question !== undefined ? { ...vars, question } : vars);
+          } catch (error) {
+            return {
+              content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+              isError: true,
+            };
+          }
+          console.error(`📝 Rendered template ${template}`);
+        }
+        if (!question) {
+          return {
+            content: [{ type: 'text', text: 'Error: question or template is required' }],
+            isError: true,
+          };
+        }
+
+        const model = requestedModel || client.model;
+        let generation: GenerationParams;
+        try {
+          generation = mergeGenerationParams(GENERATION, validateGenerationParams({
+            temperature,
+            topP,
+            maxOutputTokens,
+            safety: parseSafetySettings(Object.entries(safety || {}).map(([category, threshold]) => `${category}=${threshold}`)),
+          }));
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        let metadataFilter: string | undefined;
+        if (filter) {
+          try {
+            metadataFilter = parseFilterExpression(filter);
+          } catch (error) {
+            return {
+              content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+              isError: true,
+            };
+          }
+        }
+        let prompt = PROMPT;
+        if (requestedLanguage) {
+          try {
+            prompt = withAnswerLanguage(PROMPT, parseAnswerLanguage(requestedLanguage));
+          } catch (error) {
+            return {
+              content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+              isError: true,
+            };
+          }
+        }
+        // Checked before the cache, so ACL mode never answers an unscoped question from it
+        const aclLabels = resolveAclLabels(requestedAclLabels && parseAclLabels(requestedAclLabels), ACL);
+
+        const routing: RoutingConfig = route === true && ROUTING.mode === 'off' ? { ...ROUTING, mode: 'auto' } : ROUTING;
+        if (route !== false && routing.mode !== 'off' && uniqueTargets.length > 1) {
+          const routeTargets: (FederatedTarget & RouteTarget)[] = uniqueTargets.map(target => {
+            const project = projectsData.projects.find(p => p.storeId === target.storeName);
+            return { ...target, description: project?.description, aliases: project ? [project.id, project.name] : [] };
+          });
+          const decision = await routeQuestion(client, question, routeTargets, routing);
+          console.error(`🧭 Routed to ${decision.targets.length}/${uniqueTargets.length} stores by ${decision.method}: ${decision.reason}`);
+          uniqueTargets = decision.targets.map(({ storeName, label, shards }) => ({ storeName, label, shards }));
+          stores = uniqueTargets.map(t => t.storeName);
+        }
+        // Sharded corpora are routed and answered as one target, searched across all of their shards
+        const fanout = mode === 'fanout' && uniqueTargets.length > 1;
+        const systemInstruction = resolveSystemInstruction(prompt, uniqueTargets.map(t => t.storeName), system);
+        const language = answerLanguageOf(prompt, uniqueTargets.map(t => t.storeName));
+        stores = [...new Set(uniqueTargets.flatMap(t => t.shards || [t.storeName]))];
+
+        const startedAt = Date.now();
+        let answer = '';
+        let groundingMetadata: GroundingMetadata | undefined;
+        let usage: TokenUsage | undefined;
+        let citations: Citation[] | undefined;
+        let blocked: BlockedAnswer | undefined;
+
+        const cacheKey = queryCache ? await queryCacheKey(stores, question, model, {
+          ...describeGeneration(generation),
+          filter: metadataFilter,
+          acl: aclLabels?.join(','),
+          fanout,
+          groundedOnly,
+          minConfidence: groundedOnly ? minConfidence : undefined,
+          system: systemInstruction,
+        }) : undefined;
+        const cached = cacheKey && !noCache ? queryCache?.get(cacheKey) : undefined;
+
+        if (cached) {
+          console.error(`💾 Cached answer from ${cached.cachedAt}${cached.usage ? ` (saved ${cached.usage.totalTokens} tokens)` : ''}`);
+          answer = cached.answer;
+          groundingMetadata = cached.groundingMetadata;
+          citations = cached.citations;
+        } else if (fanout) {
+          // Streaming is not merged across stores; each store is answered in full
+          const federated = await federatedQuery(client, uniqueTargets, question, { model, metadataFilter, aclLabels, groundedOnly, minConfidence, generation, systemInstruction });
+          for (const result of federated.perStore) {
+            console.error(result.error
+              ? `⚠️  ${result.label}: ${result.error}`
+              : result.noGroundedAnswer
+                ? `🚫 ${result.label}: no grounded answer (${result.noGroundedAnswer.reason})`
+                : result.blocked
+                  ? `⛔ ${result.label}: blocked (${result.blocked.reason})`
+                  : `🔎 ${result.label}: ${result.citationCount} citations in ${result.latencyMs}ms`);
+          }
+          answer = federated.answer;
+          groundingMetadata = federated.groundingMetadata;
+          usage = federated.usage;
+          citations = federated.citations;
+        } else if (stream) {
+          // Forward text deltas as progress notifications when the client asked for progress
+          const progressToken = extra._meta?.progressToken;
+          let deltas = 0;
+          for await (const event of client.queryStream(stores, question, { model, metadataFilter, aclLabels, groundedOnly, generation, systemInstruction })) {
+            // In grounded-only mode nothing is shown before the whole answer has been checked
+            if (event.type === 'text' && progressToken !== undefined && !groundedOnly) {
+              await extra.sendNotification({
+                method: 'notifications/progress',
+                params: { progressToken, progress: ++deltas, message: event.text },
+              });
+            } else if (event.type === 'done') {
+              answer = event.text;
+              groundingMetadata = event.groundingMetadata;
+              usage = event.usage;
+              blocked = event.blocked;
+            }
+          }
+        } else {
+          // Send question to Gemini API
+          const response = await client.query(stores, question, { model, metadataFilter, aclLabels, groundedOnly, generation, systemInstruction });
+          answer = response.text || '';
+          groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+          usage = toTokenUsage(response.usageMetadata);
+          blocked = blockedAnswer(response, answer);
+        }
+        if (usage) {
+          console.error(`📊 Query usage: ${usage.totalTokens} tokens`);
+        }
+        queryHistory?.record({
+          source: 'mcp',
+          question,
+          stores,
+          params: { model, metadataFilter, aclLabels, groundedOnly, generation, systemInstruction },
+          ...describeAnswer(answer, groundingMetadata),
+          latencyMs: Date.now() - startedAt,
+          usage,
+          ...(blocked ? { error: `Blocked (${blocked.reason}): ${blocked.message}` } : {}),
+        });
+
+        if (blocked) {
+          console.error(`⛔ Blocked by the safety filters (${blocked.stage}, ${blocked.reason})`);
+          return {
+            content: [
+              {
+                type: 'text',
+                text: response_format === 'json'
+                  ? JSON.stringify({ question, stores, ...blocked }, null, 2)
+                  : formatBlockedAnswer(blocked),
+              },
+            ],
+          };
+        }
+
+        const rejected = groundedOnly ? checkGrounding(answer, groundingMetadata, minConfidence) : undefined;
+        if (rejected) {
+          console.error(`🚫 No grounded answer (${rejected.reason})`);
+          return {
+            content: [
+              {
+                type: 'text',
+                text: response_format === 'json'
+                  ? JSON.stringify({ question, stores, ...rejected }, null, 2)
+                  : formatNoGroundedAnswer(rejected),
+              },
+            ],
+          };
+        }
+
+        if (cacheKey && !cached) {
+          queryCache?.set(cacheKey, { answer, citations, groundingMetadata, usage });
+        }
+
+        const untransformed: AnswerReport = {
+          question,
+          answer,
+          citations: FILE_ATTRIBUTES
+            ? await annotateCitations(client, stores, citations || extractCitations(groundingMetadata, answer))
+            : citations || extractCitations(groundingMetadata, answer),
+          stores,
+          model,
+          usage,
+          groundingMetadata,
+          cachedAt: cached?.cachedAt,
+          confidence: answerConfidence(answer, groundingMetadata),
+          minConfidence: groundedOnly ? undefined : requestedMinConfidence ?? MIN_CONFIDENCE,
+        };
+        const report: AnswerReport = await applyTransforms(
+          language ? await enforceAnswerLanguage(client, untransformed, language, { translateSnippets, model }) : untransformed,
+          transforms,
+          { question, client, model }
+        );
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: getFormatter(response_format).render(report),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_batch_query': {
+        const { projectId, inputFile, outputFile, concurrency = 5, aclLabels } = args as {
+          projectId: string;
+          inputFile: string;
+          outputFile: string;
+          concurrency?: number;
+          aclLabels?: string[];
+        };
+        const project = loadProjects().projects.find(p => p.id === projectId);
+
+        if (!project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+        if (!fs.existsSync(inputFile)) {
+          return {
+            content: [{ type: 'text', text: 'Error: The specified input file does not exist' }],
+            isError: true,
+          };
+        }
+        if (!isPathSafe(inputFile) || !isPathSafe(path.dirname(outputFile))) {
+          return {
+            content: [{ type: 'text', text: 'Error: Access to this file is not allowed for security reasons' }],
+            isError: true,
+          };
+        }
+        if (!Number.isInteger(concurrency) || concurrency < 1 || concurrency > 50) {
+          return {
+            content: [{ type: 'text', text: 'Error: concurrency must be an integer between 1 and 50' }],
+            isError: true,
+          };
+        }
+
+        const progressToken = extra._meta?.progressToken;
+        const summary = await runBatchQueries(client, [project.storeId], inputFile, outputFile, {
+          concurrency,
+          aclLabels: resolveAclLabels(aclLabels && parseAclLabels(aclLabels), ACL),
+          generation: GENERATION,
+          onResult: (result, completed) => {
+            if (progressToken !== undefined) {
+              extra.sendNotification({
+                method: 'notifications/progress',
+                params: { progressToken, progress: completed, message: `Answered ${result.id}` },
+              }).catch(() => undefined);
+            }
+          },
+        });
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Batch completed.\n\nQuestions: ${summary.total}\nSucceeded: ${summary.succeeded}\nFailed: ${summary.failed}\nAverage latency: ${summary.averageLatencyMs}ms\nTotal tokens: ${summary.totalTokens}\nResults: ${outputFile}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_chat': {
+        const { message, sessionId, projectId, storeName, groundedOnly = GROUNDED_ONLY, aclLabels } = args as {
+          message: string;
+          sessionId?: string;
+          projectId?: string;
+          storeName?: string;
+          groundedOnly?: boolean;
+          aclLabels?: string[];
+        };
+
+        // Evict idle sessions
+        const now = Date.now();
+        for (const [id, entry] of chatSessions) {
+          if (now - entry.lastUsed > CHAT_SESSION_TTL) chatSessions.delete(id);
+        }
+
+        let id = sessionId;
+        let entry = id ? chatSessions.get(id) : undefined;
+        if (id && !entry) {
+          return {
+            content: [{ type: 'text', text: 'Error: Chat session not found (it may have expired)' }],
+            isError: true,
+          };
+        }
+
+        if (!entry) {
+          const targetStore = storeName || DEFAULT_STORE;
+          let stores: string[];
+          if (projectId) {
+            const project = loadProjects().projects.find(p => p.id === projectId);
+            if (!project) {
+              return {
+                content: [{ type: 'text', text: 'Error: Project not found' }],
+                isError: true,
+              };
+            }
+            stores = [project.storeId];
+          } else if (targetStore) {
+            stores = [targetStore];
+          } else {
+            return {
+              content: [{ type: 'text', text: 'Error: Either projectId or storeName is required to start a session (or configure default_store)' }],
+              isError: true,
+            };
+          }
+          id = `chat-${randomUUID()}`;
+          entry = {
+            session: new ChatSession(client, stores, {
+              canWrite: filePath => isPathSafe(path.dirname(filePath)),
+              groundedOnly,
+              minConfidence: MIN_GROUNDING_CONFIDENCE,
+              generation: GENERATION,
+              aclLabels: aclLabels && parseAclLabels(aclLabels),
+              fileAttributes: FILE_ATTRIBUTES,
+              prompt: PROMPT,
+            }),
+            lastUsed: now,
+          };
+          chatSessions.set(id, entry);
+        }
+        entry.lastUsed = now;
+
+        const reply = await entry.session.send(message);
+        let result = `Session: ${id}\n\n`;
+        if (reply.type === 'command') {
+          result += reply.message;
+        } else if (reply.type === 'no_grounded_answer') {
+          result += formatNoGroundedAnswer(reply);
+        } else if (reply.type === 'blocked') {
+          result += formatBlockedAnswer(reply);
+        } else {
+          // Replies in another language are returned translated; the session history keeps them as received
+          const language = answerLanguageOf(PROMPT, entry.session.stores);
+          if (language) {
+            const report = await enforceAnswerLanguage(client, { question: message, answer: reply.answer, citations: reply.citations, stores: entry.session.stores }, language, { translateSnippets: TRANSLATE_SNIPPETS, model: entry.session.model });
+            reply.answer = report.answer;
+            reply.citations = report.citations;
+          }
+          result += `## Answer\n\n${reply.answer}`;
+          if (reply.citations.length > 0) {
+            result += `\n\n## Citations\n\n${formatFootnotes(reply.citations)}`;
+          }
+          if (reply.usage) {
+            result += `\n\n_Usage: ${formatUsage(entry.session.model, reply.usage)}_`;
+          }
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: result,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_explain_file': {
+        const { filePath, projectId, storeName, upload = true, maxSections, response_format = 'markdown', aclLabels } = args as {
+          filePath: string;
+          projectId?: string;
+          storeName?: string;
+          upload?: boolean;
+          maxSections?: number;
+          response_format?: 'markdown' | 'json';
+          aclLabels?: string[];
+        };
+
+        if (!fs.existsSync(filePath) || !fs.statSync(filePath).isFile()) {
+          return {
+            content: [{ type: 'text', text: 'Error: The specified file does not exist' }],
+            isError: true,
+          };
+        }
+        if (!isPathSafe(filePath)) {
+          return {
+            content: [{ type: 'text', text: 'Error: Access to this file is not allowed for security reasons' }],
+            isError: true,
+          };
+        }
+        if (maxSections !== undefined && (!Number.isInteger(maxSections) || maxSections < 1)) {
+          return {
+            content: [{ type: 'text', text: 'Error: maxSections must be a positive integer' }],
+            isError: true,
+          };
+        }
+
+        const project = projectId ? loadProjects().projects.find(p => p.id === projectId) : undefined;
+        if (projectId && !project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+        const targetStore = project?.storeId || storeName || DEFAULT_STORE;
+        if (!targetStore) {
+          return {
+            content: [{ type: 'text', text: 'Error: Either projectId or storeName is required (or configure default_store)' }],
+            isError: true,
+          };
+        }
+
+        // Before the upload, which a refused query would leave behind
+        const labels = resolveAclLabels(aclLabels && parseAclLabels(aclLabels), ACL);
+        const explanation = await explainFile(client, [targetStore], filePath, {
+          rootDir: project?.path,
+          ingester: upload ? (project ? projectIngester(project) : new DirectoryIngester(ingestDefaults)) : undefined,
+          indexCache,
+          maxSections,
+          generation: GENERATION,
+          aclLabels: labels,
+        });
+        return {
+          content: [
+            {
+              type: 'text',
+              text: response_format === 'json' ? JSON.stringify(explanation, null, 2) : formatExplanationMarkdown(explanation),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_list_stores': {
+        const { response_format = 'json' } = args as { response_format?: 'json' | 'markdown' };
+        const stores = await client.stores.listStores();
+
+        if (response_format === 'markdown') {
+          const markdown = `# File Search Stores\n\n` +
+            (stores.length === 0
+              ? 'No stores found.'
+              : stores.map(formatStoreMarkdown).join('\n'));
+          return { content: [{ type: 'text', text: markdown }] };
+        }
+
+        return {
+          content: [{ type: 'text', text: JSON.stringify(stores, null, 2) }],
+        };
+      }
+
+      case 'gemini_create_store': {
+        const { displayName } = args as { displayName: string };
+
+        if (!displayName || displayName.trim().length === 0) {
+          return {
+            content: [{ type: 'text', text: 'Error: Store name cannot be empty' }],
+            isError: true,
+          };
+        }
+
+        const store = await client.stores.createStore(displayName);
+        return {
+          content: [{ type: 'text', text: `Store "${displayName}" created successfully.\n\nStore name: ${store.name}` }],
+        };
+      }
+
+      case 'gemini_get_store': {
+        const { storeName } = args as { storeName: string };
+        const store = await client.stores.getStore(storeName);
+        return {
+          content: [{ type: 'text', text: formatStoreMarkdown(store) }],
+        };
+      }
+
+      case 'gemini_get_store_usage': {
+        const { storeName, response_format = 'markdown' } = args as { storeName?: string; response_format?: 'json' | 'markdown' };
+        const report = await usageReport(client, storeName ? [storeName] : [], {
+          tier: STORAGE_TIER as StorageTier,
+          maxStoreDocuments: MAX_STORE_DOCUMENTS ? Math.floor(MAX_STORE_DOCUMENTS) : undefined,
+          indexCache,
+        });
+        return {
+          content: [{ type: 'text', text: response_format === 'json' ? JSON.stringify(report, null, 2) : formatUsageMarkdown(report) }],
+        };
+      }
+
+      case 'gemini_get_store_stats': {
+        const { storeName, topTerms, response_format = 'markdown' } = args as { storeName: string; topTerms?: number; response_format?: 'json' | 'markdown' };
+        const stats = await corpusStats(client, storeName, { queryCache, topTerms: topTerms && topTerms >= 1 ? Math.floor(topTerms) : undefined });
+        return {
+          content: [{ type: 'text', text: response_format === 'json' ? JSON.stringify(stats, null, 2) : formatCorpusStatsMarkdown(stats) }],
+        };
+      }
+
+      case 'gemini_verify_store': {
+        const { storeName, response_format = 'markdown' } = args as { storeName: string; response_format?: 'json' | 'markdown' };
+        // Only the registered projects' directories are read
+        const roots = loadProjects().projects
+          .filter(p => p.storeId === storeName && fs.existsSync(p.path))
+          .map(project => ({ rootDir: project.path, manifest: SyncManifest.load(project.path, storeName) }));
+        const report = await verifyIntegrity(client, storeName, { roots, cache: indexCache });
+        return {
+          content: [{ type: 'text', text: response_format === 'json' ? JSON.stringify(report, null, 2) : formatIntegrityReport(report) }],
+        };
+      }
+
+      case 'gemini_delete_store': {
+        const { storeName, force = true } = args as { storeName: string; force?: boolean };
+        await client.stores.deleteStore(storeName, force);
+        indexCache.forgetStore(storeName);
+
+        // Unregister projects that pointed at the deleted store
+        let removedCount = 0;
+        await updateProjects(projectsData => {
+          const remaining = projectsData.projects.filter(p => p.storeId !== storeName);
+          removedCount = projectsData.projects.length - remaining.length;
+          projectsData.projects = remaining;
+        });
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Store "${storeName}" deleted successfully.` +
+                (removedCount > 0 ? `\nUnregistered ${removedCount} project(s) using this store.` : ''),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_list_documents': {
+        const { projectId, storeName, state, limit, pageToken, response_format = 'markdown' } = args as {
+          projectId?: string;
+          storeName?: string;
+          state?: DocumentState;
+          limit?: number;
+          pageToken?: string;
+          response_format?: 'json' | 'markdown';
+        };
+
+        let targetStore = storeName;
+        if (projectId) {
+          const project = loadProjects().projects.find(p => p.id === projectId);
+          if (!project) {
+            return {
+              content: [{ type: 'text', text: 'Error: Project not found' }],
+              isError: true,
+            };
+          }
+          targetStore = project.storeId;
+        }
+        if (!targetStore) {
+          return {
+            content: [{ type: 'text', text: 'Error: Either projectId or storeName is required' }],
+            isError: true,
+          };
+        }
+
+        const pages = client.paginateDocuments(targetStore, { limit, pageToken });
+        // Files uploaded in parts are listed once, with the names of their parts
+        const documents = groupDocumentParts((await pages.all())
+          .map(document => toDocumentInfo(document, chunkingPolicy))
+          .filter(info => !state || info.state === state.toUpperCase()));
+
+        if (response_format === 'json') {
+          // A paged listing also returns where to continue
+          const paged = limit !== undefined || pageToken !== undefined;
+          return {
+            content: [{ type: 'text', text: JSON.stringify(paged ? { documents, nextPageToken: pages.nextPageToken } : documents, null, 2) }],
+          };
+        }
+
+        const totalBytes = documents.reduce((sum, info) => sum + info.sizeBytes, 0);
+        const rows = documents.map(info => `| ${info.displayName || info.name}${info.parts ? ` (${info.parts.length} parts)` : ''} | ${info.state} | ${info.mimeType || ''} | ${info.sizeBytes} | ${info.name} |`);
+        const markdown = `# Documents in ${targetStore}\n\n` +
+          (documents.length === 0
+            ? 'No documents found.'
+            : `${documents.length} documents, ${formatBytes(totalBytes)}\n\n| Display name | State | MIME type | Size (bytes) | Name |\n|---|---|---|---|---|\n${rows.join('\n')}`) +
+          (pages.nextPageToken ? `\n\nMore documents: continue with pageToken \`${pages.nextPageToken}\`` : '');
+        return { content: [{ type: 'text', text: markdown }] };
+      }
+
+      case 'gemini_get_document': {
+        const { documentName, response_format = 'markdown' } = args as {
+          documentName: string;
+          response_format?: 'json' | 'markdown';
+        };
+        if (!storeOfDocument(documentName)) {
+          return {
+            content: [{ type: 'text', text: 'Error: documentName must look like fileSearchStores/<store>/documents/<document> (or projects/.../ragCorpora/<corpus>/ragFiles/<file> on Vertex AI)' }],
+            isError: true,
+          };
+        }
+
+        const info = toDocumentInfo(await client.getDocument(documentName), chunkingPolicy);
+        return {
+          content: [{ type: 'text', text: response_format === 'json' ? JSON.stringify(info, null, 2) : formatDocumentMarkdown(info) }],
+        };
+      }
+
+      case 'gemini_delete_document': {
+        const { documentName } = args as { documentName: string };
+        const storeName = storeOfDocument(documentName);
+        if (!storeName) {
+          return {
+            content: [{ type: 'text', text: 'Error: documentName must look like fileSearchStores/<store>/documents/<document> (or projects/.../ragCorpora/<corpus>/ragFiles/<file> on Vertex AI)' }],
+            isError: true,
+          };
+        }
+
+        await client.deleteDocument(documentName);
+        indexCache.forgetDocument(storeName, documentName);
+
+        // Keep the sync manifests of projects using the store consistent
+        let sourceFile: string | undefined;
+        for (const project of loadProjects().projects.filter(p => p.storeId === storeName)) {
+          sourceFile = SyncManifest.load(project.path, project.storeId).forgetDocument(documentName) || sourceFile;
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Document "${documentName}" deleted successfully.` +
+                (sourceFile ? `\nSource file: ${sourceFile} (uploaded again on the next sync)` : ''),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_export_store': {
+        const { projectId, storeName, outputPath, includeContent = false, sourcePath } = args as {
+          projectId?: string;
+          storeName?: string;
+          outputPath: string;
+          includeContent?: boolean;
+          sourcePath?: string;
+        };
+        const project = projectId ? loadProjects().projects.find(p => p.id === projectId) : undefined;
+        if (projectId && !project) {
+          return {
+            content: [{ type: 'text', text: 'Error: Project not found' }],
+            isError: true,
+          };
+        }
+        const exportStoreName = project?.storeId || storeName;
+        if (!exportStoreName) {
+          return {
+            content: [{ type: 'text', text: 'Error: projectId or storeName is required' }],
+            isError: true,
+          };
+        }
+
+        const archive = await exportStore(client, exportStoreName, {
+          project: project ? { ...project } : undefined,
+          rootDir: sourcePath || project?.path,
+          includeContent,
+          chunking: chunkingPolicy,
+        });
+        const archivePath = path.resolve(outputPath);
+        writeArchive(archive, archivePath);
+        console.error(`📦 Exported ${exportStoreName} to ${archivePath}`);
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Store "${exportStoreName}" exported to ${archivePath}\n\nDocuments: ${archive.manifest.documents.length}\nSource files included: ${archive.manifest.files.length}` +
+                (includeContent ? '' : '\n\nOnly metadata was exported; pass includeContent: true to include the source files needed for a restore.'),
+            },
+          ],
+        };
+      }
+
+      case 'gemini_import_archive': {
+        const { archivePath, displayName, projectPath } = args as {
+          archivePath: string;
+          displayName?: string;
+          projectPath?: string;
+        };
+        const archive = readArchive(path.resolve(archivePath));
+        const archivedProject = archive.manifest.project as Project | undefined;
+        const rootDir = projectPath ? path.resolve(projectPath) : archivedProject?.path;
+
+        const summary = await restoreArchive(client, archive, {
+          displayName: displayName || archivedProject?.name,
+          ingester: archivedProject ? projectIngester(archivedProject) : new DirectoryIngester(ingestDefaults),
+          rootDir: rootDir && fs.existsSync(rootDir) ? rootDir : undefined,
+        });
+
+        let text = `Archive restored into store "${summary.storeName}"\n\nUploaded: ${summary.upload.successCount}/${summary.upload.totalFiles} files`;
+        if (summary.upload.failures.length > 0) {
+          text += `\nFailed:\n${summary.upload.failures.map(f => `- ${f.path}: ${f.reason}`).join('\n')}`;
+        }
+        if (summary.missingDocuments.length > 0) {
+          text += `\nNot restored (no content in archive): ${summary.missingDocuments.length} documents`;
+        }
+
+        if (archivedProject) {
+          const newProject: Project = {
+            ...archivedProject,
+            id: `project-${randomUUID()}`,
+            name: displayName || archivedProject.name,
+            storeId: summary.storeName,
+            path: rootDir || archivedProject.path,
+            createdAt: new Date().toISOString(),
+            fileCount: summary.upload.successCount,
+          };
+          await updateProjects(projectsData => {
+            projectsData.projects.push(newProject);
+          });
+          text += `\n\nProject "${newProject.name}" registered (Project ID: ${newProject.id})`;
+        }
+
+        return {
+          content: [{ type: 'text', text }],
+        };
+      }
+
+      case 'gemini_import_file': {
+        const { storeName = DEFAULT_STORE, filePath, fileName, metadata: rawMetadata, acl, chunking } = args as {
+          storeName?: string;
+          filePath?: string;
+          fileName?: string;
+          metadata?: DocumentMetadata | string[];
+          acl?: string[];
+          chunking?: ChunkingConfig;
+        };
+
+        let metadata: DocumentMetadata | undefined;
+        try {
+          metadata = toDocumentMetadata(rawMetadata);
+          if (acl) metadata = withAclLabels(metadata || {}, parseAclLabels(acl));
+          if (chunking) validateChunkingConfig(chunking);
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        if (!storeName) {
+          return {
+            content: [{ type: 'text', text: 'Error: storeName is required (or configure default_store)' }],
+            isError: true,
+          };
+        }
+
+        if (!filePath && !fileName) {
+          return {
+            content: [{ type: 'text', text: 'Error: Either filePath or fileName is required' }],
+            isError: true,
+          };
+        }
+
+        if (filePath) {
+          if (!fs.existsSync(filePath)) {
+            return {
+              content: [{ type: 'text', text: 'Error: The specified file does not exist' }],
+              isError: true,
+            };
+          }
+          if (!isPathSafe(filePath)) {
+            return {
+              content: [{ type: 'text', text: 'Error: Access to this file is not allowed for security reasons' }],
+              isError: true,
+            };
+          }
+        }
+
+        let response: ImportFileResponse | undefined;
+        if (filePath) {
+          const notebook = isNotebook(filePath) ? convertNotebookToTempFile(filePath, { stripOutputs: STRIP_NOTEBOOK_OUTPUTS }) : null;
+          const email = isEmail(filePath) ? convertEmailToTempFile(filePath, { stripQuotes: STRIP_QUOTED_REPLIES }) : null;
+          const extracted = !notebook && !email && EXTRACT_LOCALLY && canExtract(filePath) ? extractToTempFile(filePath) : null;
+          const mimeType = notebook ? 'text/markdown' : email ? 'text/plain' : extracted ? extracted.mimeType : mimeRegistry.detect(filePath);
+          const transcoded = !notebook && !email && !extracted && NORMALIZE_ENCODING && isTextMimeType(mimeType) ? transcodeToTempFile(filePath) : null;
+          try {
+            response = await client.stores.importLocalFile(storeName, notebook?.path || email?.path || extracted?.path || transcoded?.path || filePath, {
+              displayName: normalizeDisplayName(path.basename(filePath)),
+              mimeType,
+              metadata: notebook
+                ? { ...metadata, extracted_from: 'ipynb', notebook_cells: notebook.cells, ...(notebook.language ? { notebook_language: notebook.language } : {}) }
+                : email ? { ...metadata, extracted_from: 'eml', ...emailMetadata(email.message) }
+                  : extracted ? { ...metadata, extracted_from: extracted.format }
+                    : transcoded ? { ...metadata, source_encoding: transcoded.encoding } : metadata,
+              chunking: chunking || chunkingPolicy.forFile(filePath),
+            });
+          } finally {
+            if (notebook) fs.rmSync(notebook.path, { force: true });
+            if (email) fs.rmSync(email.path, { force: true });
+            if (extracted) fs.rmSync(extracted.path, { force: true });
+            if (transcoded) fs.rmSync(transcoded.path, { force: true });
+          }
+        } else {
+          response = await client.stores.importFile(storeName, fileName as string, { metadata, chunking });
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `File imported successfully.\n\nDocument: ${response?.documentName || 'unknown'}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_add_document': {
+        const { storeName = DEFAULT_STORE, displayName, text, mimeType = 'text/plain', metadata: rawMetadata, acl } = args as {
+          storeName?: string;
+          displayName: string;
+          text: string;
+          mimeType?: string;
+          metadata?: DocumentMetadata | string[];
+          acl?: string[];
+        };
+
+        let metadata: DocumentMetadata | undefined;
+        try {
+          metadata = toDocumentMetadata(rawMetadata);
+          if (acl) metadata = withAclLabels(metadata || {}, parseAclLabels(acl));
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        if (!storeName) {
+          return {
+            content: [{ type: 'text', text: 'Error: storeName is required (or configure default_store)' }],
+            isError: true,
+          };
+        }
+
+        if (!displayName || typeof text !== 'string' || !text.trim()) {
+          return {
+            content: [{ type: 'text', text: 'Error: displayName and a non-empty text are required' }],
+            isError: true,
+          };
+        }
+
+        if (Buffer.byteLength(text) > MAX_UPLOAD_SIZE) {
+          return {
+            content: [{ type: 'text', text: `Error: The text exceeds the ${MAX_UPLOAD_SIZE / 1024 / 1024}MB upload limit` }],
+            isError: true,
+          };
+        }
+
+        // The redaction policy applies as it does to ingested files
+        const redacted = REDACTION?.apply(text);
+        if (redacted && redacted.findings.length > 0) {
+          const found = describeFindings(redacted.findings);
+          if (REDACTION?.policy === 'block') {
+            return {
+              content: [{ type: 'text', text: `Error: Blocked by the redaction policy: the text contains ${found}` }],
+              isError: true,
+            };
+          }
+          if (REDACTION?.policy === 'tag') metadata = { ...metadata, ...redactionMetadata(redacted.findings) };
+          console.error(`${REDACTION?.policy === 'mask' ? '🔒 Masked' : '🏷️  Tagged'}: ${displayName} (${found})`);
+        }
+
+        const tempPath = path.join(os.tmpdir(), `gemini-document-${randomUUID()}.txt`);
+        let result: UploadResult;
+        try {
+          fs.writeFileSync(tempPath, redacted ? redacted.text : text);
+          result = await client.uploadFile(storeName, tempPath, {
+            displayName: normalizeDisplayName(displayName),
+            mimeType,
+            metadata,
+            chunking: chunkingPolicy.forFile(displayName),
+          });
+        } finally {
+          fs.rmSync(tempPath, { force: true });
+        }
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Document added successfully.\n\nDocument: ${result.documentName || 'unknown'}`,
+            },
+          ],
+        };
+      }
+
+      case 'gemini_import_url': {
+        const { storeName = DEFAULT_STORE, url, sitemap = false, maxPages, metadata: rawMetadata, acl } = args as {
+          storeName?: string;
+          url: string;
+          sitemap?: boolean;
+          maxPages?: number;
+          metadata?: DocumentMetadata | string[];
+          acl?: string[];
+        };
+
+        let metadata: DocumentMetadata | undefined;
+        try {
+          metadata = toDocumentMetadata(rawMetadata);
+          if (acl) metadata = withAclLabels(metadata || {}, parseAclLabels(acl));
+          parseHttpUrl(url);
+        } catch (error) {
+          return {
+            content: [{ type: 'text', text: `Error: ${(error as Error).message}` }],
+            isError: true,
+          };
+        }
+
+        if (!storeName) {
+          return {
+            content: [{ type: 'text', text: 'Error: storeName is required (or configure default_store)' }],
+            isError: true,
+          };
+        }
+
+        const urls = sitemap ? await collectSitemapUrls(url, { maxPages }) : [url];
+        const summary = await ingestUrls(client, storeName, urls, {
+          metadata,
+          concurrency: ingestDefaults.concurrency,
+          signal: shutdown.signal,
+        });
+        const failures = summary.failures.map(failure => `- ${failure.path}: ${failure.reason}`).join('\n');
+
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Imported ${summary.successCount}/${summary.totalPages} pages into ${storeName}.\n\n${summary.documents.map(doc => `- ${doc.url} → ${doc.displayName}`).join('\n')}${failures ? `\n\nFailed:\n${failures}` : ''}`,
+            },
+          ],
+          isError: summary.successCount === 0,
+        };
+      }
+
+      case 'gemini_prune_cache': {
+        const summary = await indexCache.prune(client);
+        return {
+          content: [
+            {
+              type: 'text',
+              text: `Index cache pruned.\n\nRemoved entries: ${summary.removedEntries}\nRemoved stores: ${summary.removedStores}\nRemaining entries: ${summary.remainingEntries}`,
+            },
+          ],
+        };
+      }
+
+      default:
+        return {
+          content: [{ type: 'text', text: `Unknown tool: ${name}` }],
+          isError: true,
+        };
+    }
+  } catch (error) {
+    // Log detailed error server-side
+    console.error('Tool execution error:', error);
+
+    // Typed errors carry a safe message; raw API and internal errors are sanitized
+    const err = toFileSearchError(error);
+    const message = err.code === 'API_ERROR' || err.code === 'INTERNAL'
+      ? 'An error occurred while processing your request. Please check your inputs and try again.'
+      : err.message;
+    return {
+      content: [
+        {
+          type: 'text',
+          text: `Error [${err.code}]: ${message}`,
+        },
+      ],
+      isError: true,
+    };
+  }
+});
+
+// Start server
+async function main(): Promise<void> {
+  const transport = new StdioServerTransport();
+  await server.connect(transport);
+  console.error('Gemini File Search MCP Server running on stdio');
+
+  // Background uploads drain on the first signal; watchers finish their current sync,
+  // then the spans and metrics still buffered are exported before exiting
+  shutdown.install();
+  shutdown.onFlush(async () => {
+    await Promise.all([...watchers.values()].map(watcher => watcher.stop()));
+  });
+  shutdown.onFlush(() => telemetry.shutdown());
+}
+
+main().catch(async (error) => {
+  console.error('Fatal error:', error);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(error));
+});
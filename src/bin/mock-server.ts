@@ -0,0 +1,45 @@
+#!/usr/bin/env node
+
+// mock-server.ts - Run the mock Gemini API server for integration tests without credentials
+import { MockGeminiServer } from '../testing.js';
+import { EXIT_CODES } from '../errors.js';
+import { getArgValue, getArgValues } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run mock -- [--port <port>] [--store <displayName> ...]',
+    'Point the tools at it with GEMINI_BASE_URL=<printed URL> and any GEMINI_API_KEY.',
+    '--store creates empty stores up front; their names are printed.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const port = getArgValue('--port');
+  if (port !== undefined && !(Number.isInteger(Number(port)) && Number(port) >= 0 && Number(port) <= 65535)) {
+    console.error(`Error: --port must be an integer between 0 and 65535, got "${port}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const server = new MockGeminiServer({ port: port !== undefined ? Number(port) : undefined });
+  const baseUrl = await server.start();
+  for (const displayName of getArgValues('--store')) {
+    console.log(`   ${displayName}: ${server.addStore(displayName)}`);
+  }
+  console.log(`🧪 Mock Gemini API listening on ${baseUrl}`);
+  console.log(`   export GEMINI_BASE_URL=${baseUrl} GEMINI_API_KEY=mock`);
+
+  const stop = () => {
+    console.error(`🛑 ${server.requests.length} requests served`);
+    server.stop().then(() => process.exit(0));
+  };
+  process.once('SIGINT', stop);
+  process.once('SIGTERM', stop);
+}
+
+main().catch(error => {
+  console.error(`Error: ${(error as Error).message}`);
+  process.exit(EXIT_CODES.API_ERROR);
+});
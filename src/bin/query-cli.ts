@@ -0,0 +1,406 @@
+#!/usr/bin/env node
+
+// query-cli.ts - One-shot questions against a File Search store, optionally rendered from a prompt template
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { GenerateContentResponse, GroundingMetadata } from '@google/genai';
+import { Citation, extractCitations } from '../citations.js';
+import { annotateCitations } from '../attributes.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { answerConfidence, checkGrounding, formatNoGroundedAnswer } from '../grounding.js';
+import { PROVENANCE_JSON_SCHEMA, buildProvenanceGraph, writeProvenance } from '../provenance.js';
+import { parseFilterExpression } from '../search.js';
+import { AnswerReport, Formatter, OUTPUT_FORMATS, OutputFormat, TerminalFormatter, getFormatter } from '../output.js';
+import { supportsRendering } from '../render.js';
+import { parseVarPairs, renderTemplateFile } from '../templates.js';
+import { loadJsonSchema, parseStructuredAnswer } from '../schema.js';
+import { askAll } from '../ask-all.js';
+import { answerLanguageOf, readSystemOption, resolveSystemInstruction, withAnswerLanguage } from '../prompts.js';
+import { enforceAnswerLanguage, parseAnswerLanguage } from '../answer-language.js';
+import { ShardMap } from '../shards.js';
+import { applyTransforms, listTransforms, resolveTransforms } from '../postprocess.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { ROUTING_MODES, RouteTarget, RoutingMode, routeQuestion } from '../routing.js';
+import { DoneEvent } from '../stream.js';
+import { GenerationParams, blockedAnswer, formatBlockedAnswer, mergeGenerationParams, parseSafetySettings, validateGenerationParams } from '../generation.js';
+import { QueryHistory, describeAnswer } from '../history.js';
+import { AnswerFileWriter, answerSidecar, sidecarPath } from '../answer-file.js';
+import { MAX_CONTEXT_LINES, addCitationContext, projectRoots } from '../cite-context.js';
+import { TokenUsage, toTokenUsage } from '../cost.js';
+import { shutdown } from '../shutdown.js';
+import { COMMON_USAGE, COMMON_VALUE_FLAGS, buildClientFromArgs, configureRuntime, getArgValues, historyFile, loadSettings, projectsFile, shardsFile } from './common.js';
+
+// Flags that take a value, so their values are not mistaken for the question
+const VALUE_FLAGS = [
+  '--store', '--project', '--template', '--var', '--filter', '--format', '--json-schema', '--model', '--temperature', '--top-p', '--max-output-tokens', '--safety', '--min-confidence', '--routing', '--post', '--system', '--answer-lang', '--export-provenance', '--source-root', '--out', '--cite-context',
+  ...COMMON_VALUE_FLAGS,
+];
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run query -- [--project <projectId> | --store <storeName|corpus> ...] "<question>"',
+    '  npm run query -- [--project <projectId> | --store <storeName> ...] --template <file> [--var name=value ...] ["<question>"]',
+    '  npm run query -- [--project <projectId> | --store <storeName> ...] --questions "<question>" "<question>" ... [--shared-retrieval]',
+    'Options: [--filter <expression>] [--format text|json|markdown|sarif] [--json-schema <file>] [--grounded-only] [--min-confidence <0-1>] [--routing off|keywords|model|auto] [--post <transform> ...] [--system <instruction>|@<file>] [--answer-lang <code> [--translate-snippets]] [--render] [--no-history] [--out <file>] [--cite-context <lines>] [--source-root <dir> ...]',
+    'Provenance: [--export-provenance <graph.json|graph.dot>] [--source-root <dir>]   npm run query -- --provenance-schema prints the JSON Schema of the graph',
+    'Generation: [--model <model>] [--temperature <0-2>] [--top-p <0-1>] [--max-output-tokens <n>] [--safety [<category>=]<threshold> ...]',
+    COMMON_USAGE,
+    'The question is available to the template as {{question}}.',
+    'With --json-schema the answer is printed as validated JSON (--format text) or as "structured" in the JSON report (--format json).',
+    'With --questions every argument is a question of its own; --shared-retrieval answers them all from one retrieval pass instead of one query each.',
+    'Answers carry a 0-1 confidence score; below --min-confidence they are marked uncertain and shown with the retrieved chunks, or rejected with --grounded-only.',
+    `--post runs the answer through transforms, in the order given: ${listTransforms().map(t => t.name).join(', ')}.`,
+    '--render draws the Markdown of text answers for the terminal; piped or redirected output stays plain text.',
+    '--system replaces the system instruction stored in [prompt] for the searched stores; the tone, language and citation format still apply.',
+    '--answer-lang (e.g. ja, or a language name) answers in that language whatever the documents are in, over [prompt] language; an answer that comes back in another language is translated,',
+    '  and --translate-snippets also translates the cited chunks.',
+    '--out streams the answer into <file> (as Markdown with citations with --format markdown), renamed into place once it is complete, and writes the citations, model,',
+    '  tokens and latency to <file without extension>.meta.json; an interrupted or failed run leaves an earlier file as it was.',
+    '--cite-context shows each cited chunk with that many lines before and after it, and its path and line numbers, from the file it was ingested from:',
+    '  the first found under --source-root, the directories of the registered projects of the stores, or the working directory.',
+    '--export-provenance links the question, answer segments, cited chunks and documents; chunks are located in the files under --source-root (default: the working directory).',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(2);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+// Project names, IDs and descriptions for the routing rules and classifier
+function routeTargets(stores: string[]): RouteTarget[] {
+  let projects: { id: string; name: string; description?: string; storeId: string }[] = [];
+  try {
+    projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: typeof projects }).projects;
+  } catch (error) {
+    // Stores that are not registered as projects are routed by store name
+  }
+  return stores.map(storeName => {
+    const project = projects.find(p => p.storeId === storeName);
+    return { storeName, label: project?.name || storeName, description: project?.description, aliases: project ? [project.id] : [] };
+  });
+}
+
+// --export-provenance: the question, answer segments, chunks and documents as a graph
+function exportProvenance(question: string, answer: string, metadata: GroundingMetadata | undefined, stores: string[], model: string): void {
+  const [file] = getArgValues('--export-provenance');
+  if (!file) return;
+  const [sourceRoot] = getArgValues('--source-root');
+  const graph = buildProvenanceGraph(question, answer, metadata, {
+    model,
+    stores,
+    confidence: answerConfidence(answer, metadata).score,
+    sourceRoot: path.resolve(sourceRoot || '.'),
+  });
+  const format = writeProvenance(path.resolve(file), graph);
+  console.error(`🕸️  Provenance graph (${format}, ${graph.nodes.length} nodes, ${graph.edges.length} edges) written to ${file}`);
+}
+
+// The [generation] settings with the flags of this query on top
+// Text answers go through the terminal renderer only where the escape codes are shown
+function formatterFor(format: OutputFormat): Formatter {
+  if (format === 'text' && process.argv.includes('--render') && supportsRendering()) {
+    return new TerminalFormatter({ width: process.stdout.columns });
+  }
+  return getFormatter(format);
+}
+
+function generationParams(settings: Settings): GenerationParams {
+  const number = (flag: string): number | undefined => {
+    const [value] = getArgValues(flag);
+    return value !== undefined ? Number(value) : undefined;
+  };
+  return mergeGenerationParams(settings.generation, validateGenerationParams({
+    temperature: number('--temperature'),
+    topP: number('--top-p'),
+    maxOutputTokens: number('--max-output-tokens'),
+    safety: parseSafetySettings(getArgValues('--safety')),
+  }));
+}
+
+function resolveStores(settings: Settings): string[] {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores.length > 0 ? stores : usage();
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--provenance-schema')) {
+    console.log(JSON.stringify(PROVENANCE_JSON_SCHEMA, null, 2));
+    return;
+  }
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const [format = 'text'] = getArgValues('--format');
+  if (!OUTPUT_FORMATS.includes(format as OutputFormat)) {
+    console.error(`Error: --format must be one of: ${OUTPUT_FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [schemaFile] = getArgValues('--json-schema');
+  const responseSchema = schemaFile ? loadJsonSchema(path.resolve(schemaFile)) : undefined;
+  if (responseSchema && format !== 'text' && format !== 'json') {
+    console.error('Error: --json-schema works with --format text or json');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [citeContextFlag] = getArgValues('--cite-context');
+  if (citeContextFlag !== undefined && !(Number.isInteger(Number(citeContextFlag)) && Number(citeContextFlag) >= 0 && Number(citeContextFlag) <= MAX_CONTEXT_LINES)) {
+    console.error(`Error: --cite-context must be an integer between 0 and ${MAX_CONTEXT_LINES}, got "${citeContextFlag}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const citeContext = citeContextFlag !== undefined ? Number(citeContextFlag) : settings.citeContext;
+  const [out] = getArgValues('--out');
+  if (out && (format === 'json' || format === 'sarif' || responseSchema || process.argv.includes('--questions'))) {
+    console.error('Error: --out writes text or Markdown answers of single questions; the JSON report is in its .meta.json');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [minConfidence] = getArgValues('--min-confidence');
+  if (minConfidence !== undefined && !(Number(minConfidence) >= 0 && Number(minConfidence) <= 1)) {
+    console.error(`Error: --min-confidence must be between 0 and 1, got "${minConfidence}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const questions = process.argv.includes('--questions') ? positionalArgs() : undefined;
+  const sharedRetrieval = process.argv.includes('--shared-retrieval');
+  if (sharedRetrieval && !questions) {
+    console.error('Error: --shared-retrieval needs --questions');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  if (questions && (responseSchema || getArgValues('--template').length > 0)) {
+    console.error('Error: --questions does not work with --json-schema or --template');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const transforms = resolveTransforms(getArgValues('--post'));
+  if (transforms.length > 0 && responseSchema) {
+    console.error('Error: --post does not work with --json-schema');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const answerLang = getArgValues('--answer-lang')[0] || process.env.GEMINI_ANSWER_LANG;
+  const answerLanguage = answerLang ? parseAnswerLanguage(answerLang) : undefined;
+  const prompt = answerLanguage ? withAnswerLanguage(settings.prompt, answerLanguage) : settings.prompt;
+  const translateSnippets = process.argv.includes('--translate-snippets') || prompt.translateSnippets === true;
+
+  let question: string | undefined = questions ? questions.join('\n') : positionalArgs().join(' ') || undefined;
+  const [template] = getArgValues('--template');
+  if (template) {
+    const vars = parseVarPairs(getArgValues('--var'));
+    question = renderTemplateFile(path.resolve(template), question !== undefined ? { ...vars, question } : vars);
+  }
+  if (!question) usage();
+
+  const [filter] = getArgValues('--filter');
+  const metadataFilter = filter ? parseFilterExpression(filter) : undefined;
+  let stores = resolveStores(settings);
+  const [routingFlag] = getArgValues('--routing');
+  const routingMode = (routingFlag || process.env.GEMINI_ROUTING || settings.routing.mode) as RoutingMode;
+  if (!ROUTING_MODES.includes(routingMode)) {
+    console.error(`Error: --routing must be one of: ${ROUTING_MODES.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const client = buildClientFromArgs(settings);
+
+  const [modelFlag] = getArgValues('--model');
+  const model = modelFlag || settings.model || client.model;
+  const groundedOnly = process.argv.includes('--grounded-only') || settings.groundedOnly === true;
+  const generation = generationParams(settings);
+  if (routingMode !== 'off' && stores.length > 1) {
+    const decision = await routeQuestion(client, question, routeTargets(stores), { ...settings.routing, mode: routingMode });
+    console.error(`🧭 Routed to ${decision.targets.length}/${stores.length} stores by ${decision.method}: ${decision.reason}`);
+    stores = decision.targets.map(target => target.storeName);
+  }
+  // [[prompt.stores]] entries name the stores as given, a sharded corpus included
+  const [system] = getArgValues('--system');
+  const systemInstruction = resolveSystemInstruction(prompt, stores, system !== undefined ? readSystemOption(system) : undefined);
+  const language = answerLanguageOf(prompt, stores);
+  const inLanguage = async (report: AnswerReport): Promise<AnswerReport> =>
+    language ? enforceAnswerLanguage(client, report, language, { translateSnippets, model }) : report;
+  // Before translation, so the chunks are looked up as they were indexed
+  const sourceRoots = [...getArgValues('--source-root'), ...projectRoots(projectsFile(), stores), '.'];
+  const withContext = (citations: Citation[]): Citation[] =>
+    citeContext !== undefined ? addCitationContext(citations, { sourceRoots, lines: citeContext }) : citations;
+  // Sharded corpora are routed as one target and searched across all of their shards
+  stores = new ShardMap(shardsFile()).expand(stores);
+
+  if (questions) {
+    const result = await askAll(client, stores, questions, {
+      model,
+      metadataFilter,
+      groundedOnly,
+      generation,
+      systemInstruction,
+      sharedRetrieval,
+      minConfidence: minConfidence ? Number(minConfidence) : settings.minGroundingConfidence,
+    });
+    console.error(`💬 ${result.answers.length} questions answered in ${result.requests} ${result.requests === 1 ? 'request' : 'requests'}`);
+    exportProvenance(question, result.answer, result.groundingMetadata, stores, model);
+    const report = await applyTransforms(await inLanguage({
+      question,
+      answer: result.answer,
+      answers: result.answers,
+      citations: withContext(result.citations),
+      stores,
+      model,
+      usage: result.usage,
+      groundingMetadata: result.groundingMetadata,
+    }), transforms, { question, client, model });
+    console.log(formatterFor(format as OutputFormat).render(report));
+    return;
+  }
+
+  // Single questions are recorded for npm run history, failed ones included
+  const history = settings.queryHistory !== false && !process.argv.includes('--no-history') ? new QueryHistory(historyFile()) : undefined;
+  const params = { model, metadataFilter, groundedOnly, generation, systemInstruction };
+  const threshold = minConfidence ? Number(minConfidence) : settings.minGroundingConfidence;
+  const asked = question;
+  const reportOf = async (answer: string, groundingMetadata: GroundingMetadata | undefined, usage: TokenUsage | undefined, structured?: unknown): Promise<AnswerReport> => {
+    const citations = extractCitations(groundingMetadata, answer);
+    const untransformed: AnswerReport = {
+      question: asked,
+      answer,
+      structured,
+      citations: withContext(settings.fileAttributes !== false ? await annotateCitations(client, stores, citations) : citations),
+      stores,
+      model,
+      usage,
+      groundingMetadata,
+      confidence: answerConfidence(answer, groundingMetadata),
+      // Grounded-only answers below the threshold were rejected before
+      minConfidence: groundedOnly ? undefined : threshold,
+    };
+    // A JSON answer is checked against its schema, not its language
+    return applyTransforms(structured !== undefined ? untransformed : await inLanguage(untransformed), transforms, { question: asked, client, model });
+  };
+  const startedAt = Date.now();
+
+  if (out) {
+    const writer = AnswerFileWriter.open(path.resolve(out));
+    // Ctrl-C drops the partial answer instead of leaving the temporary file behind
+    const removeFlusher = shutdown.install().onFlush(() => writer.abort());
+    let done: DoneEvent | undefined;
+    let firstTokenMs: number | undefined;
+    try {
+      for await (const event of client.queryStream(stores, question, params)) {
+        if (event.type === 'text') {
+          firstTokenMs ??= Date.now() - startedAt;
+          writer.write(event.text);
+        } else if (event.type === 'done') {
+          done = event;
+        }
+      }
+    } catch (error) {
+      writer.abort();
+      history?.record({ source: 'query', question, stores, params, sources: [], latencyMs: Date.now() - startedAt, error: toFileSearchError(error).message });
+      throw error;
+    } finally {
+      removeFlusher();
+    }
+    const latencyMs = Date.now() - startedAt;
+    const answer = done?.text || '';
+    const entry = history?.record({
+      source: 'query',
+      question,
+      stores,
+      params,
+      ...describeAnswer(answer, done?.groundingMetadata),
+      latencyMs,
+      usage: done?.usage,
+      ...(done?.blocked ? { error: `Blocked (${done.blocked.reason}): ${done.blocked.message}` } : {}),
+    });
+    if (entry) console.error(`🕘 Recorded as history #${entry.id}`);
+    if (done?.blocked) {
+      writer.abort();
+      console.log(`⛔ ${formatBlockedAnswer(done.blocked)}`);
+      process.exitCode = EXIT_CODES.ANSWER_BLOCKED;
+      return;
+    }
+    exportProvenance(question, answer, done?.groundingMetadata, stores, model);
+    const rejected = groundedOnly ? checkGrounding(answer, done?.groundingMetadata, threshold) : undefined;
+    if (rejected) {
+      writer.abort();
+      console.log(`🚫 ${formatNoGroundedAnswer(rejected)}`);
+      return;
+    }
+
+    const report = await reportOf(answer, done?.groundingMetadata, done?.usage);
+    writer.commit(format === 'markdown' ? formatterFor('markdown').render(report) : report.answer, answerSidecar(writer.filePath, report, { latencyMs, firstTokenMs }));
+    console.error(`📝 Wrote ${writer.filePath} and ${sidecarPath(writer.filePath)} (${report.citations.length} citations, ${(latencyMs / 1000).toFixed(1)}s)`);
+    return;
+  }
+
+  let response: GenerateContentResponse;
+  try {
+    response = await client.query(stores, question, { ...params, responseSchema });
+  } catch (error) {
+    history?.record({ source: 'query', question, stores, params, sources: [], latencyMs: Date.now() - startedAt, error: toFileSearchError(error).message });
+    throw error;
+  }
+  const answer = response.text || '';
+  const groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+  const blocked = blockedAnswer(response, answer);
+  const entry = history?.record({
+    source: 'query',
+    question,
+    stores,
+    params,
+    ...describeAnswer(answer, groundingMetadata),
+    latencyMs: Date.now() - startedAt,
+    usage: toTokenUsage(response.usageMetadata),
+    ...(blocked ? { error: `Blocked (${blocked.reason}): ${blocked.message}` } : {}),
+  });
+  if (entry) console.error(`🕘 Recorded as history #${entry.id}`);
+
+  // A blocked answer exits with its own code, so scripts can tell it from "no answer found"
+  if (blocked) {
+    console.log(format === 'json' ? JSON.stringify({ question, stores, ...blocked }, null, 2) : `⛔ ${formatBlockedAnswer(blocked)}`);
+    process.exitCode = EXIT_CODES.ANSWER_BLOCKED;
+    return;
+  }
+  exportProvenance(question, answer, groundingMetadata, stores, model);
+
+  const rejected = groundedOnly ? checkGrounding(answer, groundingMetadata, threshold) : undefined;
+  if (rejected) {
+    console.log(format === 'json' ? JSON.stringify({ question, stores, ...rejected }, null, 2) : `🚫 ${formatNoGroundedAnswer(rejected)}`);
+    return;
+  }
+
+  const structured = responseSchema ? parseStructuredAnswer(answer, responseSchema) : undefined;
+  if (structured !== undefined && format === 'text') {
+    console.log(JSON.stringify(structured, null, 2));
+    return;
+  }
+
+  const report = await reportOf(answer, groundingMetadata, toTokenUsage(response.usageMetadata), structured);
+  console.log(formatterFor(format as OutputFormat).render(report));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,94 @@
+#!/usr/bin/env node
+
+// retrieve-cli.ts - The chunks a query retrieves from File Search stores, without generating an answer
+import * as fs from 'fs';
+import 'dotenv/config';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { parseFilterExpression } from '../search.js';
+import { DEFAULT_TOP_K, formatRetrievedChunks } from '../retrieve.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, COMMON_VALUE_FLAGS, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, loadSettings, projectsFile } from './common.js';
+
+// Flags that take a value, so their values are not mistaken for the query
+const VALUE_FLAGS = [
+  '--store', '--project', '--top-k', '--filter', '--format', '--model',
+  ...COMMON_VALUE_FLAGS,
+];
+
+const FORMATS = ['text', 'json'];
+
+function usage(): never {
+  console.error([
+    'Usage: npm run retrieve -- [--project <projectId> | --store <storeName|corpus> ...] "<query>" [--top-k <n>] [--filter <expression>] [--format text|json] [--model <model>]',
+    COMMON_USAGE,
+    `Prints the matched chunks, their scores and source documents instead of an answer; --top-k defaults to ${DEFAULT_TOP_K}.`,
+    'Vertex AI corpora are searched directly; on the Gemini API the model runs the file search and is told not to answer, and --model picks it.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positionalArgs(): string[] {
+  const args = process.argv.slice(2);
+  return args.filter((arg, i) => !arg.startsWith('--') && !VALUE_FLAGS.includes(args[i - 1]));
+}
+
+function resolveStores(settings: Settings): string[] {
+  const stores = getArgValues('--store');
+  const projectId = getArgValue('--project');
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+  }
+  if (stores.length === 0 && settings.defaultStore) stores.push(settings.defaultStore);
+  return stores.length > 0 ? stores : usage();
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const query = positionalArgs().join(' ');
+  if (!query) usage();
+
+  const format = getArgValue('--format') || 'text';
+  if (!FORMATS.includes(format)) {
+    console.error(`Error: --format must be one of: ${FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const topK = getArgValue('--top-k');
+  if (topK !== undefined && !(Number.isInteger(Number(topK)) && Number(topK) >= 1 && Number(topK) <= 100)) {
+    console.error(`Error: --top-k must be an integer between 1 and 100, got "${topK}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const stores = resolveStores(settings);
+  const filter = getArgValue('--filter');
+  const client = buildClientFromArgs(settings);
+
+  const chunks = await client.retrieve(stores, query, {
+    topK: topK !== undefined ? Number(topK) : undefined,
+    metadataFilter: filter ? parseFilterExpression(filter) : undefined,
+    model: getArgValue('--model') || settings.model,
+  });
+  console.log(format === 'json' ? JSON.stringify({ query, stores, chunks }, null, 2) : formatRetrievedChunks(chunks));
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
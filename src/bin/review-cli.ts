@@ -0,0 +1,158 @@
+#!/usr/bin/env node
+
+// review-cli.ts - Review a patch or a git range against the project's File Search store
+import * as fs from 'fs';
+import * as path from 'path';
+import 'dotenv/config';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { parseFilterExpression } from '../search.js';
+import { parseVarPairs } from '../templates.js';
+import { diffRange, fileAtRef, openRepository } from '../git.js';
+import { DiffFile, REVIEW_FORMATS, ReviewFormat, formatReview, reviewDiff } from '../review.js';
+import { EXIT_CODES, InvalidInputError, exitCodeFor, toFileSearchError } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValues, loadSettings, projectsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run review -- [--project <projectId> | --store <storeName> ...] --diff <file.patch | ->',
+    '  npm run review -- [--project <projectId> | --store <storeName> ...] --git-range <from>..<to> [--repo <dir>]',
+    `Options: [--template <file>] [--var name=value ...] [--filter <expression>] [--format ${REVIEW_FORMATS.join('|')}] [--max-chunk-lines <n>] [--concurrency <n>] [--model <model>]`,
+    COMMON_USAGE,
+    '--diff - reads the patch from stdin. --repo defaults to the project directory with --project, otherwise the working directory.',
+    'Templates get {{file}}, {{status}}, {{symbols}} and {{diff}} on top of the --var values.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function positiveInteger(flag: string): number | undefined {
+  const [value] = getArgValues(flag);
+  if (value === undefined) return undefined;
+  if (!/^\d+$/.test(value) || Number(value) < 1) {
+    console.error(`Error: ${flag} must be a positive integer, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+// The stores to search and the project directory, if any
+function resolveTarget(settings: Settings): { stores: string[]; projectDir?: string } {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  let projectDir: string | undefined;
+  if (projectId) {
+    let projects: { id: string; name: string; storeId: string; path: string }[];
+    try {
+      projects = (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: typeof projects }).projects;
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+    const project = projects.find(p => p.id === projectId || p.name === projectId);
+    if (!project) {
+      console.error(`Error: Project not found: ${projectId}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    stores.push(project.storeId);
+    projectDir = project.path;
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores.length > 0 ? { stores, projectDir } : usage();
+}
+
+function readPatch(file: string): string {
+  try {
+    return fs.readFileSync(file === '-' ? 0 : path.resolve(file), 'utf8');
+  } catch (error) {
+    throw new InvalidInputError(`Cannot read ${file}: ${(error as Error).message}`, { cause: error });
+  }
+}
+
+function readWorkingFile(rootDir: string, relativePath: string): string | undefined {
+  try {
+    return fs.readFileSync(path.join(rootDir, relativePath), 'utf8');
+  } catch (error) {
+    return undefined;  // Not checked out here; the hunk headers name the functions instead
+  }
+}
+
+async function main(): Promise<void> {
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const [format = 'markdown'] = getArgValues('--format');
+  if (!REVIEW_FORMATS.includes(format as ReviewFormat)) {
+    console.error(`Error: --format must be one of: ${REVIEW_FORMATS.join(', ')}`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [patchFile] = getArgValues('--diff');
+  const [range] = getArgValues('--git-range');
+  if (!patchFile === !range) {
+    console.error('Error: Give either --diff or --git-range');
+    usage();
+  }
+  const maxChunkLines = positiveInteger('--max-chunk-lines');
+  const concurrency = positiveInteger('--concurrency');
+  const [filter] = getArgValues('--filter');
+  const metadataFilter = filter ? parseFilterExpression(filter) : undefined;
+  const [templateFile] = getArgValues('--template');
+  let template: string | undefined;
+  if (templateFile) {
+    try {
+      template = fs.readFileSync(path.resolve(templateFile), 'utf8');
+    } catch (error) {
+      throw new InvalidInputError(`Cannot read template ${templateFile}: ${(error as Error).message}`, { cause: error });
+    }
+  }
+  const vars = parseVarPairs(getArgValues('--var'));
+  const target = resolveTarget(settings);
+
+  // The new side of the change: the working tree for patches, the "to" ref for ranges
+  let diff: string;
+  let contentOf: (file: DiffFile) => string | undefined;
+  const [repoFlag] = getArgValues('--repo');
+  const baseDir = path.resolve(repoFlag || target.projectDir || process.cwd());
+  if (range) {
+    const { repoDir } = openRepository(baseDir);
+    diff = diffRange(repoDir, range);
+    // "A..B" and "A B" end at B; a single ref is compared with the working tree
+    const refs = range.includes('..') ? range.split(/\.{2,3}/) : range.split(/\s+/).filter(Boolean);
+    const to = refs.length > 1 ? refs[refs.length - 1] || 'HEAD' : undefined;
+    contentOf = file => to ? fileAtRef(repoDir, to, file.path) : readWorkingFile(repoDir, file.path);
+  } else {
+    diff = readPatch(patchFile);
+    contentOf = file => readWorkingFile(baseDir, file.path);
+  }
+  if (!diff.trim()) {
+    console.error('✅ Nothing to review: the diff is empty');
+    return;
+  }
+
+  const client = buildClientFromArgs(settings);
+
+  const [modelFlag] = getArgValues('--model');
+  const report = await reviewDiff(client, target.stores, diff, {
+    model: modelFlag || settings.model || client.model,
+    metadataFilter,
+    generation: settings.generation,
+    template,
+    vars,
+    maxChunkLines,
+    concurrency,
+    contentOf,
+  });
+  console.error(`🔍 Reviewed ${report.files} files in ${report.chunks} ${report.chunks === 1 ? 'query' : 'queries'}: ${report.comments.length} comments`);
+  if (report.skipped.length > 0) console.error(`⚠️  Not reviewed: ${report.skipped.join(', ')}`);
+  console.log(formatReview(report, format as ReviewFormat));
+  if (report.failures.length > 0) process.exitCode = EXIT_CODES.API_ERROR;
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
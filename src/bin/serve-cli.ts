@@ -0,0 +1,94 @@
+#!/usr/bin/env node
+
+// serve-cli.ts - Serve queries, ingestion and the list of stores over a REST API
+import * as path from 'path';
+import 'dotenv/config';
+import { IndexCache } from '../cache.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { MimeRegistry } from '../file-types.js';
+import { Redactor } from '../redact.js';
+import { API_KEYS_ENV, ApiServer, DEFAULT_API_PORT } from '../http-api.js';
+import { QueryHistory } from '../history.js';
+import { EXIT_CODES, exitCodeFor, toFileSearchError } from '../errors.js';
+import { shutdown } from '../shutdown.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, historyFile, indexCacheFile, loadSettings } from './common.js';
+
+const LOOPBACK_HOSTS = ['127.0.0.1', '::1', 'localhost'];
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run serve -- [--port <port>] [--host <address>] [--store <storeName>] [--ingest-root <dir> ...] [--model <model>] [--no-auth]',
+    COMMON_USAGE,
+    `Routes: GET /health, GET /stores, POST /query, POST /ingest (default port: ${DEFAULT_API_PORT}).`,
+    `Clients authenticate with one of the comma-separated keys in ${API_KEYS_ENV}, as "Authorization: Bearer <key>" or "X-API-Key: <key>"; --no-auth is only accepted on a loopback address.`,
+    'POST /ingest uploads inline documents, and files or directories under an --ingest-root.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  const port = getArgValue('--port');
+  if (port !== undefined && !(Number.isInteger(Number(port)) && Number(port) >= 0 && Number(port) <= 65535)) {
+    console.error(`Error: --port must be an integer between 0 and 65535, got "${port}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const host = getArgValue('--host') || '127.0.0.1';
+  const apiKeys = (process.env[API_KEYS_ENV] || '').split(',').map(key => key.trim()).filter(Boolean);
+  const noAuth = process.argv.includes('--no-auth');
+  if (apiKeys.length === 0 && !noAuth) {
+    console.error(`Error: Set ${API_KEYS_ENV} to the keys clients authenticate with, or pass --no-auth on a loopback address`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+  if (noAuth && !LOOPBACK_HOSTS.includes(host)) {
+    console.error(`Error: --no-auth is only accepted with a loopback --host (${LOOPBACK_HOSTS.join(', ')}), got "${host}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings, apiKeys);
+
+  const client = buildClientFromArgs(settings);
+
+  const server = new ApiServer({
+    client,
+    apiKeys: noAuth ? [] : apiKeys,
+    port: port !== undefined ? Number(port) : undefined,
+    host,
+    defaultStore: getArgValue('--store') || settings.defaultStore,
+    model: getArgValue('--model') || settings.model,
+    minConfidence: settings.minGroundingConfidence,
+    prompt: settings.prompt,
+    history: settings.queryHistory === false ? undefined : new QueryHistory(historyFile()),
+    ingestRoots: getArgValues('--ingest-root').map(root => path.resolve(root)),
+    ingest: {
+      mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+      chunking: settings.chunking,
+      concurrency: settings.concurrency ? Math.floor(settings.concurrency) : undefined,
+      requestsPerMinute: settings.requestsPerMinute,
+      extractLocally: settings.extractLocally,
+      normalizeEncoding: settings.normalizeEncoding !== false,
+      detectLanguage: settings.detectLanguage !== false,
+      fileAttributes: settings.fileAttributes !== false,
+      redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+      transforms: settings.transforms,
+      cache: new IndexCache(indexCacheFile()),
+      signal: shutdown.signal,
+    },
+  });
+  const url = await server.start();
+  console.error(`🌐 REST API listening on ${url}${noAuth ? ' (no authentication)' : ''}`);
+  // Waits for the requests in flight before the process exits
+  shutdown.onFlush(() => server.stop());
+}
+
+// Ctrl-C stops accepting requests; ingest requests in flight upload no further files
+shutdown.install();
+main().catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,176 @@
+#!/usr/bin/env node
+
+// shards-cli.ts - Create, fill, list and delete corpora sharded across several stores
+import * as fs from 'fs';
+import * as path from 'path';
+import * as readline from 'readline';
+import 'dotenv/config';
+import { IndexCache } from '../cache.js';
+import { parseMetadataPairs } from '../metadata.js';
+import { parseAclLabels, withAclLabels } from '../acl.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { DirectoryIngester } from '../ingest.js';
+import { Quarantine } from '../quarantine.js';
+import { Redactor } from '../redact.js';
+import { MimeRegistry } from '../file-types.js';
+import { formatBytes } from '../progress.js';
+import { MAX_SHARDS, SHARD_MAP_FILE, ShardMap, createShards, deleteShards, shardDisplayName, shardsNeeded, uploadSharded } from '../shards.js';
+import { EXIT_CODES, InvalidInputError, exitCodeFor, toFileSearchError } from '../errors.js';
+import { shutdown } from '../shutdown.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValue, getArgValues, indexCacheFile, loadSettings, quarantineListFile, shardsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage:',
+    '  npm run shards -- create <corpus> --count <n> [--concurrency <n>]',
+    '  npm run shards -- upload <corpus> <dir> [--count <n> | --count auto] [--include <glob> ...] [--exclude <glob> ...] [--metadata key=value ...] [--acl <label,...>] [--concurrency <n>] [--code-chunking] [--row-chunking] [--max-store-documents <n>]',
+    '  npm run shards -- list [--json]',
+    '  npm run shards -- delete <corpus> [--yes]',
+    COMMON_USAGE,
+    `Shards are stores named <corpus>-000, <corpus>-001, ...; each file goes to the shard of the hash of its path. The map is kept in ${SHARD_MAP_FILE}.`,
+    'upload creates the corpus first when needed, with the number of shards the directory needs unless --count is given.',
+    'Queries take the corpus name in place of a store name: npm run query -- --store <corpus> "<question>"',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+function parseCount(flag: string, max: number): number | undefined {
+  const value = getArgValue(flag);
+  if (value === undefined) return undefined;
+  if (!(Number.isInteger(Number(value)) && Number(value) >= 1 && Number(value) <= max)) {
+    console.error(`Error: ${flag} must be an integer between 1 and ${max}, got "${value}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  return Number(value);
+}
+
+function confirm(question: string): Promise<boolean> {
+  if (!process.stdin.isTTY) {
+    throw new InvalidInputError('Refusing to delete without confirmation; pass --yes to skip the prompt');
+  }
+  const rl = readline.createInterface({ input: process.stdin, output: process.stderr });
+  return new Promise(resolve => rl.question(question, answer => {
+    rl.close();
+    resolve(/^y(es)?$/i.test(answer.trim()));
+  }));
+}
+
+async function main(): Promise<void> {
+  const command = process.argv[2];
+  const corpus = process.argv[3];
+  if (!['create', 'upload', 'list', 'delete'].includes(command)) usage();
+  if (command !== 'list' && (!corpus || corpus.startsWith('--'))) usage();
+
+  const settings: Settings = loadSettings();
+  configureRuntime(settings);
+
+  const map = new ShardMap(shardsFile());
+  const indexCache = new IndexCache(indexCacheFile());
+  if (command === 'list') {
+    const sets = map.list();
+    if (process.argv.includes('--json')) {
+      console.log(JSON.stringify(sets, null, 2));
+      return;
+    }
+    for (const set of sets) {
+      console.log(`${set.corpus}: ${set.stores.length} shards, created ${set.createdAt}`);
+      set.stores.forEach((storeName, shard) => {
+        console.log(`  ${shardDisplayName(set.corpus, shard)}  ${storeName}  ${indexCache.entries(storeName).length} files`);
+      });
+    }
+    if (sets.length === 0) console.log('No sharded corpora.');
+    return;
+  }
+
+  const client = buildClientFromArgs(settings);
+  const concurrency = parseCount('--concurrency', 50);
+
+  if (command === 'delete') {
+    const set = map.get(corpus);
+    if (!set) {
+      console.error(`Error: Sharded corpus not found: ${corpus}`);
+      process.exit(EXIT_CODES.NOT_FOUND);
+    }
+    if (!process.argv.includes('--yes') && !(await confirm(`Delete the ${set.stores.length} stores of ${corpus} with all their documents? [y/N] `))) {
+      console.error('Cancelled');
+      return;
+    }
+    await deleteShards(client, map, corpus, { concurrency });
+    for (const storeName of set.stores) indexCache.forgetStore(storeName);
+    console.log(`🗑️  Deleted ${corpus} and its ${set.stores.length} stores`);
+    return;
+  }
+
+  if (command === 'create') {
+    const count = parseCount('--count', MAX_SHARDS);
+    if (!count) usage();
+    const set = await createShards(client, map, corpus, count, { concurrency });
+    console.log(`✅ ${corpus}: ${set.stores.length} shards`);
+    set.stores.forEach((storeName, shard) => console.log(`  ${shardDisplayName(corpus, shard)}  ${storeName}`));
+    return;
+  }
+
+  const rootDir = process.argv[4];
+  if (!rootDir || rootDir.startsWith('--')) usage();
+  if (!fs.existsSync(rootDir) || !fs.statSync(rootDir).isDirectory()) {
+    console.error(`Error: Directory not found: ${rootDir}`);
+    process.exit(EXIT_CODES.NOT_FOUND);
+  }
+  const acl = getArgValue('--acl');
+  const ingester = new DirectoryIngester({
+    include: getArgValues('--include'),
+    exclude: getArgValues('--exclude'),
+    metadata: withAclLabels(parseMetadataPairs(getArgValues('--metadata')), acl !== undefined ? parseAclLabels(acl) : undefined),
+    mimeRegistry: settings.mimeMap ? MimeRegistry.fromTomlFile(path.resolve(settings.mimeMap)) : undefined,
+    chunking: settings.chunking,
+    codeChunking: process.argv.includes('--code-chunking'),
+    rowChunking: process.argv.includes('--row-chunking') ? {} : undefined,
+    cache: indexCache,
+    quarantine: new Quarantine(quarantineListFile()),
+    concurrency: concurrency || (settings.concurrency ? Math.floor(settings.concurrency) : undefined),
+    requestsPerMinute: settings.requestsPerMinute,
+    extractLocally: settings.extractLocally,
+    notebooks: { stripOutputs: settings.stripNotebookOutputs },
+    emails: { stripQuotes: settings.stripQuotedReplies },
+    normalizeEncoding: settings.normalizeEncoding,
+    detectLanguage: settings.detectLanguage,
+    fileAttributes: settings.fileAttributes,
+    redaction: Redactor.load(settings.redactionPolicy, settings.redactionRules),
+    transforms: settings.transforms,
+    progressBars: true,
+    signal: shutdown.signal,
+    abortSignal: shutdown.abortSignal,
+  });
+  const files = ingester.collect(rootDir);
+  let set = map.get(corpus);
+  const countFlag = getArgValue('--count');
+  if (!set || (countFlag && countFlag !== 'auto')) {
+    let count = countFlag && countFlag !== 'auto' ? parseCount('--count', MAX_SHARDS) : undefined;
+    if (!count) {
+      const plan = ingester.dryRun(undefined, rootDir, files);
+      const maxStoreDocuments = parseCount('--max-store-documents', 1000000) || settings.maxStoreDocuments;
+      count = shardsNeeded(plan.uploadCount, plan.totalBytes, { maxStoreDocuments });
+      console.error(`📁 ${plan.uploadCount} files, ${formatBytes(plan.totalBytes)}: ${count} ${count === 1 ? 'shard' : 'shards'}`);
+    }
+    set = await createShards(client, map, corpus, count, { concurrency });
+  }
+
+  const summaries = await uploadSharded(client, ingester, set, rootDir, files);
+  let errors = 0;
+  for (const { storeName, shard, files: shardFiles, summary } of summaries) {
+    console.log(`✅ ${shardDisplayName(corpus, shard)}: ${summary.successCount}/${shardFiles} files uploaded to ${storeName}`);
+    for (const failure of summary.failures) console.log(`   ❌ ${failure.path}: ${failure.reason}`);
+    errors += summary.errorCount;
+  }
+  if (errors > 0) process.exitCode = EXIT_CODES.API_ERROR;
+}
+
+// Ctrl-C lets the files in flight finish
+shutdown.install();
+shutdown.track(main()).then(() => telemetry.shutdown()).catch(async (error) => {
+  const err = toFileSearchError(error);
+  console.error(`Error [${err.code}]: ${err.message}`);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(err));
+});
@@ -0,0 +1,133 @@
+#!/usr/bin/env node
+
+// tui-cli.ts - Terminal UI for picking stores, finding documents and asking questions with streaming answers
+import * as fs from 'fs';
+import 'dotenv/config';
+import { FileSearchClient } from '../client.js';
+import { Settings } from '../config.js';
+import { telemetry } from '../telemetry.js';
+import { GenerationParams, mergeGenerationParams, parseSafetySettings, validateGenerationParams } from '../generation.js';
+import { parseFilterExpression } from '../search.js';
+import { readSystemOption, withAnswerLanguage } from '../prompts.js';
+import { parseAnswerLanguage } from '../answer-language.js';
+import { MAX_CONTEXT_LINES, projectRoots } from '../cite-context.js';
+import { TuiApp } from '../tui.js';
+import { EXIT_CODES, exitCodeFor } from '../errors.js';
+import { COMMON_USAGE, buildClientFromArgs, configureRuntime, getArgValues, loadSettings, projectsFile } from './common.js';
+
+function usage(): never {
+  console.error([
+    'Usage: npm run tui -- [--project <projectId> | --store <storeName> ...] [--filter <expression>] [--cite-context <lines>] [--source-root <dir> ...]',
+    'Generation: [--model <model>] [--grounded-only] [--system <instruction>|@<file>] [--answer-lang <code>] [--temperature <0-2>] [--top-p <0-1>] [--max-output-tokens <n>] [--safety [<category>=]<threshold> ...]',
+    COMMON_USAGE,
+    'Every store is listed; the given ones come first and are marked, and questions go to the marked stores, else the highlighted one.',
+    'Typing in the documents pane filters them fuzzily; Enter on a citation, or a click, opens its file at the cited lines in $VISUAL or $EDITOR.',
+    '--cite-context sets the lines shown around cited chunks (default: cite_context, else 3); files are looked up under --source-root, the directories of',
+    '  the registered projects and the working directory. Only errors are logged to the screen; --log-file keeps every level.',
+  ].join('\n'));
+  process.exit(EXIT_CODES.INVALID_INPUT);
+}
+
+// The [generation] settings with the flags of this session on top
+function generationParams(settings: Settings): GenerationParams {
+  const number = (flag: string): number | undefined => {
+    const [value] = getArgValues(flag);
+    return value !== undefined ? Number(value) : undefined;
+  };
+  return mergeGenerationParams(settings.generation, validateGenerationParams({
+    temperature: number('--temperature'),
+    topP: number('--top-p'),
+    maxOutputTokens: number('--max-output-tokens'),
+    safety: parseSafetySettings(getArgValues('--safety')),
+  }));
+}
+
+// Unlike chat, no store is required: without one every store is listed and none is marked
+function resolveStores(settings: Settings): string[] {
+  const stores = getArgValues('--store');
+  const [projectId] = getArgValues('--project');
+  if (projectId) {
+    try {
+      const data = JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { id: string; name: string; storeId: string }[] };
+      const project = data.projects.find(p => p.id === projectId || p.name === projectId);
+      if (!project) {
+        console.error(`Error: Project not found: ${projectId}`);
+        process.exit(EXIT_CODES.NOT_FOUND);
+      }
+      stores.push(project.storeId);
+    } catch (error) {
+      console.error(`Error: Could not read ${projectsFile()}`);
+      process.exit(EXIT_CODES.CONFIG_INVALID);
+    }
+  }
+  if (stores.length === 0 && settings.defaultStore) {
+    stores.push(settings.defaultStore);
+  }
+  return stores;
+}
+
+async function main(): Promise<void> {
+  if (process.argv.includes('--help')) usage();
+  if (!process.stdin.isTTY || !process.stdout.isTTY) {
+    console.error('Error: npm run tui needs an interactive terminal; use npm run chat or npm run query when piping');
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  let settings: Settings;
+  try {
+    settings = loadSettings();
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+  // Log lines on stderr would be drawn over the panes; the log file still gets every level
+  configureRuntime(settings, [], 'error');
+
+  let client: FileSearchClient;
+  try {
+    client = buildClientFromArgs(settings);
+  } catch (error) {
+    console.error(`Error: ${(error as Error).message}`);
+    process.exit(EXIT_CODES.CONFIG_INVALID);
+  }
+
+  const [citeContextFlag] = getArgValues('--cite-context');
+  if (citeContextFlag !== undefined && !(Number.isInteger(Number(citeContextFlag)) && Number(citeContextFlag) >= 0 && Number(citeContextFlag) <= MAX_CONTEXT_LINES)) {
+    console.error(`Error: --cite-context must be an integer between 0 and ${MAX_CONTEXT_LINES}, got "${citeContextFlag}"`);
+    process.exit(EXIT_CODES.INVALID_INPUT);
+  }
+  const [model] = getArgValues('--model');
+  const [filter] = getArgValues('--filter');
+  const [system] = getArgValues('--system');
+  const answerLang = getArgValues('--answer-lang')[0] || process.env.GEMINI_ANSWER_LANG;
+  const stores = resolveStores(settings);
+  const app = new TuiApp(client, {
+    stores,
+    query: {
+      model: model || settings.model,
+      metadataFilter: filter ? parseFilterExpression(filter) : undefined,
+      groundedOnly: process.argv.includes('--grounded-only') || settings.groundedOnly === true,
+      generation: generationParams(settings),
+    },
+    prompt: answerLang ? withAnswerLanguage(settings.prompt, parseAnswerLanguage(answerLang)) : settings.prompt,
+    system: system !== undefined ? readSystemOption(system) : undefined,
+    chunking: settings.chunking,
+    // Every store's projects, as the marked stores change while the TUI runs
+    sourceRoots: [...getArgValues('--source-root'), ...projectRoots(projectsFile(), readProjectStores()), '.'],
+    contextLines: citeContextFlag !== undefined ? Number(citeContextFlag) : settings.citeContext,
+  });
+  await app.run();
+}
+
+function readProjectStores(): string[] {
+  try {
+    return (JSON.parse(fs.readFileSync(projectsFile(), 'utf8')) as { projects: { storeId: string }[] }).projects.map(project => project.storeId);
+  } catch (error) {
+    return [];  // No registered projects
+  }
+}
+
+main().then(() => telemetry.shutdown()).catch(async (error) => {
+  console.error('Fatal error:', error);
+  await telemetry.shutdown();
+  process.exit(exitCodeFor(error));
+});
@@ -0,0 +1,172 @@
+// cache.ts - Content-addressed record of files already indexed into each store
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+import { FileSearchClient } from './client.js';
+import { DuplicateMatch, Fingerprint, compareFingerprints } from './dedup.js';
+import { PendingChanges, withFileLock } from './lock.js';
+import { writeFileAtomic } from './shutdown.js';
+
+// Types
+export interface CacheEntry {
+  documentName?: string;
+  documentNames?: string[];  // Set when the file was uploaded as one document per symbol or with a summary
+  displayName: string;
+  indexedAt: string;
+  fingerprint?: Fingerprint;  // For duplicate detection under other names
+}
+
+interface IndexCacheData {
+  version: number;
+  stores: { [storeName: string]: { [hash: string]: CacheEntry } };
+}
+
+export interface PruneSummary {
+  removedStores: number;
+  removedEntries: number;
+  remainingEntries: number;
+}
+
+export function hashFile(filePath: string): string {
+  return createHash('sha256').update(fs.readFileSync(filePath)).digest('hex');
+}
+
+// Keyed by SHA-256 of the file content, so renamed or copied files with
+// identical content are recognized as already indexed.
+export class IndexCache {
+  private data: IndexCacheData;
+  private readonly changes = new PendingChanges<CacheEntry>();  // Keyed by "<store>\n<hash>"
+
+  constructor(private readonly filePath: string) {
+    this.data = this.load();
+  }
+
+  get(storeName: string, hash: string): CacheEntry | undefined {
+    return this.data.stores[storeName]?.[hash];
+  }
+
+  entries(storeName: string): CacheEntry[] {
+    return Object.values(this.data.stores[storeName] || {});
+  }
+
+  // Entries of the store with the content hash they are keyed by
+  hashes(storeName: string): [string, CacheEntry][] {
+    return Object.entries(this.data.stores[storeName] || {});
+  }
+
+  record(storeName: string, hash: string, entry: CacheEntry): void {
+    if (!this.data.stores[storeName]) {
+      this.data.stores[storeName] = {};
+    }
+    this.data.stores[storeName][hash] = entry;
+    this.changes.set(`${storeName}\n${hash}`, entry);
+    this.save();
+  }
+
+  // Closest indexed file of the store with the same or near-identical content under
+  // another display name; exact matches win over near ones
+  findDuplicate(storeName: string, fingerprint: Fingerprint, maxDistance: number, displayName: string): DuplicateMatch | undefined {
+    let best: DuplicateMatch | undefined;
+    for (const [hash, entry] of Object.entries(this.data.stores[storeName] || {})) {
+      if (!entry.fingerprint || entry.displayName === displayName) continue;
+      const match = compareFingerprints(fingerprint, entry.fingerprint, maxDistance);
+      if (!match || (best && best.distance <= match.distance)) continue;
+      best = {
+        of: entry.displayName,
+        ...match,
+        inStore: true,
+        hash,
+        documentName: entry.documentName,
+        documentNames: entry.documentNames,
+      };
+    }
+    return best;
+  }
+
+  // Drops entries pointing at a document that was deleted from the store
+  forgetDocument(storeName: string, documentName: string): void {
+    this.forgetDocuments(storeName, [documentName]);
+  }
+
+  forgetDocuments(storeName: string, documentNames: string[]): void {
+    const entries = this.data.stores[storeName];
+    if (!entries) return;
+    const names = new Set(documentNames);
+    for (const [hash, entry] of Object.entries(entries)) {
+      if ((entry.documentName && names.has(entry.documentName)) || entry.documentNames?.some(name => names.has(name))) {
+        this.forget(storeName, hash);
+      }
+    }
+    this.save();
+  }
+
+  forgetStore(storeName: string): void {
+    if (this.data.stores[storeName]) {
+      Object.keys(this.data.stores[storeName]).forEach(hash => this.forget(storeName, hash));
+      delete this.data.stores[storeName];
+      this.save();
+    }
+  }
+
+  // Removes entries whose store or document no longer exists
+  async prune(client: FileSearchClient): Promise<PruneSummary> {
+    const liveStores = new Set((await client.stores.listStores()).map(store => store.name));
+    let removedStores = 0;
+    let removedEntries = 0;
+    let remainingEntries = 0;
+
+    for (const [storeName, entries] of Object.entries(this.data.stores)) {
+      if (!liveStores.has(storeName)) {
+        removedEntries += Object.keys(entries).length;
+        removedStores++;
+        Object.keys(entries).forEach(hash => this.forget(storeName, hash));
+        delete this.data.stores[storeName];
+        continue;
+      }
+
+      const liveDocuments = new Set((await client.listDocuments(storeName)).map(document => document.name));
+      for (const [hash, entry] of Object.entries(entries)) {
+        const names = entry.documentNames || (entry.documentName ? [entry.documentName] : []);
+        if (names.length === 0 || !names.every(name => liveDocuments.has(name))) {
+          this.forget(storeName, hash);
+          removedEntries++;
+        } else {
+          remainingEntries++;
+        }
+      }
+    }
+
+    this.save();
+    return { removedStores, removedEntries, remainingEntries };
+  }
+
+  private load(): IndexCacheData {
+    try {
+      return JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as IndexCacheData;
+    } catch (error) {
+      return { version: 1, stores: {} };
+    }
+  }
+
+  private forget(storeName: string, hash: string): void {
+    delete this.data.stores[storeName]?.[hash];
+    this.changes.delete(`${storeName}\n${hash}`);
+  }
+
+  // Written under the cache's lock, on top of the entries other runs recorded since it
+  // was loaded, so concurrent uploads do not drop each other's records
+  private save(): void {
+    withFileLock(this.filePath, () => {
+      const current = this.load();
+      for (const [key, entry] of this.changes.entries()) {
+        const [storeName, hash] = key.split('\n');
+        const entries = current.stores[storeName] || (current.stores[storeName] = {});
+        if (entry) entries[hash] = entry;
+        else delete entries[hash];
+        if (Object.keys(entries).length === 0) delete current.stores[storeName];
+      }
+      this.changes.clear();
+      this.data = current;
+      writeFileAtomic(this.filePath, JSON.stringify(this.data, null, 2));
+    });
+  }
+}
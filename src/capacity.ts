@@ -0,0 +1,165 @@
+// capacity.ts - Store usage against the File Search document and storage limits
+import { FileSearchClient } from './client.js';
+import { IndexCache } from './cache.js';
+import { estimateTokens } from './cost.js';
+import { toDocumentInfo } from './documents.js';
+import { formatBytes } from './progress.js';
+import { DEFAULT_MAX_STORE_DOCUMENTS } from './preflight.js';
+import { StoreInfo } from './store.js';
+
+// Types
+export type StorageTier = 'free' | 'tier1' | 'tier2' | 'tier3';
+
+export interface CapacityOptions {
+  tier?: StorageTier;  // Usage tier of the project, which sets the storage limit (default: free)
+  maxStoreDocuments?: number;  // Default: 10000
+  indexCache?: IndexCache;  // Local bookkeeping: files recorded as uploaded to each store
+}
+
+export interface StoreUsage {
+  storeName: string;
+  displayName: string;
+  documents: { active: number; pending: number; failed: number; total: number };
+  sourceBytes: number;  // Sum of the document sizes
+  storedBytes: number;  // Reported by the store, input plus embeddings
+  estimatedTokens: number;  // Indexed tokens, from sourceBytes
+  trackedFiles?: number;  // Index cache entries for the store
+  untrackedDocuments?: number;  // Documents the index cache does not know about
+  remainingDocuments: number;
+  remainingBytes: number;  // Until the recommended store size, or the tier limit when lower
+  warnings: string[];
+}
+
+export interface UsageReport {
+  tier: StorageTier;
+  tierBytes: number;
+  stores: StoreUsage[];
+  storedBytes: number;  // Counted by the tier limit across every store, so only exact when all stores are listed
+  remainingBytes: number;
+  warnings: string[];
+}
+
+export const STORAGE_TIERS: StorageTier[] = ['free', 'tier1', 'tier2', 'tier3'];
+
+const GB = 1024 * 1024 * 1024;
+
+// Total storage across a project's stores by usage tier
+export const TIER_STORAGE_BYTES: { [tier in StorageTier]: number } = {
+  free: 1 * GB,
+  tier1: 10 * GB,
+  tier2: 100 * GB,
+  tier3: 1024 * GB,
+};
+
+// Larger stores still work, but retrieval latency suffers
+export const RECOMMENDED_STORE_BYTES = 20 * GB;
+
+// Share of a limit from which usage is reported as a warning
+export const CAPACITY_WARNING_RATIO = 0.8;
+
+export function isStorageTier(value: string): value is StorageTier {
+  return STORAGE_TIERS.includes(value as StorageTier);
+}
+
+function percent(used: number, limit: number): string {
+  return `${Math.round((used / limit) * 100)}%`;
+}
+
+// Document names the index cache recorded for the store
+function trackedDocuments(cache: IndexCache, storeName: string): { files: number; documents: Set<string> } {
+  const entries = cache.entries(storeName);
+  const documents = new Set<string>();
+  for (const entry of entries) {
+    if (entry.documentName) documents.add(entry.documentName);
+    for (const name of entry.documentNames || []) documents.add(name);
+  }
+  return { files: entries.length, documents };
+}
+
+// The API reports counts and the stored size per store; bytes and tokens per document
+// come from listing the documents, which takes one request per page.
+export async function storeUsage(client: FileSearchClient, store: StoreInfo, options: CapacityOptions = {}): Promise<StoreUsage> {
+  const tier = options.tier || 'free';
+  const maxDocuments = options.maxStoreDocuments || DEFAULT_MAX_STORE_DOCUMENTS;
+  const documents = (await client.listDocuments(store.name)).map(document => toDocumentInfo(document));
+  const counts = { active: 0, pending: 0, failed: 0, total: documents.length };
+  let sourceBytes = 0;
+  for (const info of documents) {
+    if (info.state === 'ACTIVE') counts.active++;
+    else if (info.state === 'PENDING') counts.pending++;
+    else if (info.state === 'FAILED') counts.failed++;
+    sourceBytes += info.sizeBytes;
+  }
+  const storedBytes = store.sizeBytes;
+  const storeLimit = Math.min(RECOMMENDED_STORE_BYTES, TIER_STORAGE_BYTES[tier]);
+
+  const warnings: string[] = [];
+  if (counts.total >= maxDocuments * CAPACITY_WARNING_RATIO) {
+    warnings.push(`${counts.total} of ${maxDocuments} documents (${percent(counts.total, maxDocuments)})`);
+  }
+  if (storedBytes >= storeLimit * CAPACITY_WARNING_RATIO) {
+    const limitName = storeLimit === RECOMMENDED_STORE_BYTES ? 'recommended store size' : `${tier} storage limit`;
+    warnings.push(`${formatBytes(storedBytes)} of the ${formatBytes(storeLimit)} ${limitName} (${percent(storedBytes, storeLimit)})`);
+  }
+  if (counts.failed > 0) warnings.push(`${counts.failed} failed documents still count against the limits`);
+
+  const usage: StoreUsage = {
+    storeName: store.name,
+    displayName: store.displayName,
+    documents: counts,
+    sourceBytes,
+    storedBytes,
+    estimatedTokens: estimateTokens(sourceBytes),
+    remainingDocuments: Math.max(0, maxDocuments - counts.total),
+    remainingBytes: Math.max(0, storeLimit - storedBytes),
+    warnings,
+  };
+  if (options.indexCache) {
+    const tracked = trackedDocuments(options.indexCache, store.name);
+    usage.trackedFiles = tracked.files;
+    usage.untrackedDocuments = documents.filter(info => !tracked.documents.has(info.name)).length;
+  }
+  return usage;
+}
+
+// Usage of the given stores, or of every store when none are given
+export async function usageReport(client: FileSearchClient, storeNames: string[] = [], options: CapacityOptions = {}): Promise<UsageReport> {
+  const tier = options.tier || 'free';
+  const tierBytes = TIER_STORAGE_BYTES[tier];
+  const stores = storeNames.length > 0
+    ? await Promise.all(storeNames.map(name => client.stores.getStore(name)))
+    : await client.stores.listStores();
+  const usages: StoreUsage[] = [];
+  for (const store of stores) usages.push(await storeUsage(client, store, options));
+
+  const storedBytes = usages.reduce((sum, usage) => sum + usage.storedBytes, 0);
+  const warnings: string[] = [];
+  if (storedBytes >= tierBytes * CAPACITY_WARNING_RATIO) {
+    warnings.push(`${formatBytes(storedBytes)} of the ${formatBytes(tierBytes)} ${tier} storage limit (${percent(storedBytes, tierBytes)})`);
+  }
+  const remainingBytes = Math.max(0, tierBytes - storedBytes);
+  // The project limit is shared, so no store has more room than is left of it
+  for (const usage of usages) usage.remainingBytes = Math.min(usage.remainingBytes, remainingBytes);
+  return { tier, tierBytes, stores: usages, storedBytes, remainingBytes, warnings };
+}
+
+export function formatUsageMarkdown(report: UsageReport): string {
+  let markdown = '';
+  for (const usage of report.stores) {
+    const { documents } = usage;
+    markdown += `## ${usage.displayName || usage.storeName}\n- **Name**: ${usage.storeName}\n`;
+    markdown += `- **Documents**: ${documents.total} (${documents.active} active, ${documents.pending} pending, ${documents.failed} failed), ${usage.remainingDocuments} remaining\n`;
+    markdown += `- **Size**: ${formatBytes(usage.sourceBytes)} of documents, ${formatBytes(usage.storedBytes)} stored with embeddings, ${formatBytes(usage.remainingBytes)} remaining\n`;
+    markdown += `- **Estimated indexed tokens**: ${usage.estimatedTokens.toLocaleString('en-US')}\n`;
+    if (usage.trackedFiles !== undefined) {
+      markdown += `- **Tracked locally**: ${usage.trackedFiles} files` +
+        (usage.untrackedDocuments ? `, ${usage.untrackedDocuments} documents not in the index cache` : '') + '\n';
+    }
+    for (const warning of usage.warnings) markdown += `- ⚠️ ${warning}\n`;
+    markdown += '\n';
+  }
+  if (report.stores.length === 0) markdown += 'No stores found.\n\n';
+  markdown += `**Total**: ${formatBytes(report.storedBytes)} of ${formatBytes(report.tierBytes)} (${report.tier} tier), ${formatBytes(report.remainingBytes)} remaining\n`;
+  for (const warning of report.warnings) markdown += `⚠️ ${warning}\n`;
+  return markdown;
+}
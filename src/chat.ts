@@ -0,0 +1,195 @@
+// chat.ts - Multi-turn chat sessions grounded in File Search stores
+import { Chat, Content, GenerateContentConfig } from '@google/genai';
+import * as fs from 'fs';
+import * as path from 'path';
+import { FileSearchClient } from './client.js';
+import { Citation, extractCitations, formatFootnotes } from './citations.js';
+import { withRetry } from './retry.js';
+import { withTimeout } from './timeouts.js';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { telemetry } from './telemetry.js';
+import { NoGroundedAnswer, checkGrounding, formatNoGroundedAnswer, withSystemInstruction } from './grounding.js';
+import { BlockedAnswer, GenerationParams, blockedAnswer, formatBlockedAnswer, toGenerationConfig } from './generation.js';
+import { annotateCitations } from './attributes.js';
+import { PromptConfig, resolveSystemInstruction } from './prompts.js';
+
+// Types
+export interface ChatTurn {
+  question: string;
+  answer: string;
+  citations: Citation[];
+  store: string;
+  at: string;
+}
+
+export type ChatReply =
+  | { type: 'answer'; answer: string; citations: Citation[]; usage?: TokenUsage }
+  | (NoGroundedAnswer & { usage?: TokenUsage })
+  | (BlockedAnswer & { usage?: TokenUsage })
+  | { type: 'command'; message: string };
+
+export interface ChatSessionOptions {
+  model?: string;
+  canWrite?: (filePath: string) => boolean;  // Guards the /save command
+  groundedOnly?: boolean;  // Replace replies the documents do not support, see checkGrounding
+  minConfidence?: number;
+  generation?: GenerationParams;  // Sampling and safety settings of every reply
+  aclLabels?: string[];  // Access labels of the user, see acl.ts
+  fileAttributes?: boolean;  // Show the path, mtime and owner of cited files (default: true)
+  prompt?: PromptConfig;  // Stored instruction and answer style, following /store switches
+  system?: string;  // Replaces the stored instruction
+}
+
+export const CHAT_HELP = [
+  '/reset               Clear the conversation history',
+  '/store <name> [...]  Switch to other File Search store(s), keeping the history',
+  '/save <file.md>      Write the transcript as Markdown',
+  '/help                Show this help',
+].join('\n');
+
+export class ChatSession {
+  private chat: Chat;
+  private storeNames: string[];
+  private turns: ChatTurn[] = [];
+  readonly model: string;
+
+  constructor(
+    private readonly client: FileSearchClient,
+    storeNames: string[],
+    private readonly options: ChatSessionOptions = {}
+  ) {
+    this.storeNames = storeNames;
+    this.model = options.model || client.model;
+    this.chat = this.createChat([]);
+  }
+
+  get stores(): string[] {
+    return [...this.storeNames];
+  }
+
+  get turnCount(): number {
+    return this.turns.length;
+  }
+
+  private chatConfig(): GenerateContentConfig {
+    return {
+      tools: [this.client.retrievalTool(this.storeNames, { aclLabels: this.options.aclLabels })],
+      ...withSystemInstruction(
+        this.options.prompt ? resolveSystemInstruction(this.options.prompt, this.storeNames, this.options.system) : this.options.system,
+        this.options.groundedOnly
+      ),
+      ...toGenerationConfig(this.options.generation || {}),
+    };
+  }
+
+  private createChat(history: Content[]): Chat {
+    return this.client.ai.chats.create({ model: this.model, config: this.chatConfig(), history });
+  }
+
+  // Sends a message, or runs it as a command when it starts with "/"
+  async send(input: string): Promise<ChatReply> {
+    const message = input.trim();
+    if (message.startsWith('/')) {
+      return { type: 'command', message: this.runCommand(message) };
+    }
+
+    // /add can name a store of another tenant
+    await this.client.stores.checkTenant(this.storeNames);
+    const response = await withRetry('chat message', () => withTimeout('query', this.client.timeouts.query, signal => this.chat.sendMessage({
+      message,
+      // A message's config replaces the chat's, so it is repeated with the deadline
+      config: { ...this.chatConfig(), abortSignal: signal },
+    })), this.client.retry);
+    const answer = response.text || '';
+    const groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+    const usage = toTokenUsage(response.usageMetadata);
+    telemetry.recordTokens(this.model, usage);
+
+    const blocked = blockedAnswer(response, answer);
+    const rejected = blocked || (this.options.groundedOnly ? checkGrounding(answer, groundingMetadata, this.options.minConfidence) : undefined);
+    if (rejected) {
+      // Drop the rejected exchange so later turns do not build on it; a reply blocked
+      // before any text has no content, and the curated history leaves it out already
+      const history = this.chat.getHistory(true);
+      this.chat = this.createChat(blocked && !answer ? history : history.slice(0, -2));
+      this.turns.push({
+        question: message,
+        answer: `_${rejected.type === 'blocked' ? formatBlockedAnswer(rejected) : formatNoGroundedAnswer(rejected)}_`,
+        citations: [],
+        store: this.storeNames.join(', '),
+        at: new Date().toISOString(),
+      });
+      return { ...rejected, usage };
+    }
+
+    const extracted = extractCitations(groundingMetadata, answer);
+    const citations = this.options.fileAttributes !== false ? await annotateCitations(this.client, this.storeNames, extracted) : extracted;
+    this.turns.push({
+      question: message,
+      answer,
+      citations,
+      store: this.storeNames.join(', '),
+      at: new Date().toISOString(),
+    });
+    return { type: 'answer', answer, citations, usage };
+  }
+
+  reset(): void {
+    this.turns = [];
+    this.chat = this.createChat([]);
+  }
+
+  setStores(storeNames: string[]): void {
+    this.storeNames = storeNames;
+    this.chat = this.createChat(this.chat.getHistory(true));
+  }
+
+  transcript(): string {
+    let markdown = `# Chat Transcript\n\n- **Stores**: ${this.storeNames.join(', ')}\n- **Model**: ${this.model}\n`;
+    for (const turn of this.turns) {
+      markdown += `\n## ${turn.question}\n\n${turn.answer}\n`;
+      if (turn.citations.length > 0) {
+        markdown += `\n### Citations\n\n${formatFootnotes(turn.citations)}\n`;
+      }
+    }
+    return markdown;
+  }
+
+  save(filePath: string): void {
+    fs.writeFileSync(filePath, this.transcript());
+  }
+
+  private runCommand(input: string): string {
+    const [command, ...rest] = input.split(/\s+/);
+    switch (command) {
+      case '/reset':
+        this.reset();
+        return 'Conversation history cleared.';
+
+      case '/store':
+        if (rest.length === 0) {
+          return `Current store(s): ${this.storeNames.join(', ')}`;
+        }
+        this.setStores(rest);
+        return `Switched to store(s): ${rest.join(', ')}`;
+
+      case '/save': {
+        if (rest.length === 0) {
+          return 'Usage: /save <file.md>';
+        }
+        const filePath = path.resolve(rest.join(' '));
+        if (this.options.canWrite && !this.options.canWrite(filePath)) {
+          return 'Error: Writing to this location is not allowed';
+        }
+        this.save(filePath);
+        return `Transcript saved to ${filePath} (${this.turns.length} turns).`;
+      }
+
+      case '/help':
+        return CHAT_HELP;
+
+      default:
+        return `Unknown command: ${command}\n\n${CHAT_HELP}`;
+    }
+  }
+}
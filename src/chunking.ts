@@ -0,0 +1,115 @@
+// chunking.ts - Chunking configuration for uploads and imports, with per-extension defaults
+import { ChunkingConfig as ApiChunkingConfig } from '@google/genai';
+import * as path from 'path';
+import { TomlTable, TomlValue } from './toml.js';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export interface ChunkingConfig {
+  maxTokensPerChunk?: number;
+  maxOverlapTokens?: number;
+}
+
+export interface ChunkingRules {
+  default?: ChunkingConfig;
+  extensions?: { [extension: string]: ChunkingConfig };  // ".rs" -> smaller chunks for source
+}
+
+export function validateChunkingConfig(config: ChunkingConfig): void {
+  const { maxTokensPerChunk, maxOverlapTokens } = config;
+  if (maxTokensPerChunk !== undefined && (!Number.isInteger(maxTokensPerChunk) || maxTokensPerChunk < 1)) {
+    throw new InvalidInputError(`maxTokensPerChunk must be a positive integer, got ${maxTokensPerChunk}`);
+  }
+  if (maxOverlapTokens !== undefined && (!Number.isInteger(maxOverlapTokens) || maxOverlapTokens < 0)) {
+    throw new InvalidInputError(`maxOverlapTokens must be a non-negative integer, got ${maxOverlapTokens}`);
+  }
+  if (maxTokensPerChunk !== undefined && maxOverlapTokens !== undefined && maxOverlapTokens >= maxTokensPerChunk) {
+    throw new InvalidInputError('maxOverlapTokens must be smaller than maxTokensPerChunk');
+  }
+}
+
+export function toApiChunkingConfig(config: ChunkingConfig | undefined): ApiChunkingConfig | undefined {
+  if (!config || (config.maxTokensPerChunk === undefined && config.maxOverlapTokens === undefined)) {
+    return undefined;
+  }
+  return {
+    whiteSpaceConfig: {
+      maxTokensPerChunk: config.maxTokensPerChunk,
+      maxOverlapTokens: config.maxOverlapTokens,
+    },
+  };
+}
+
+function fromToml(value: TomlValue, name: string): ChunkingConfig {
+  if (typeof value !== 'object' || Array.isArray(value)) {
+    throw new InvalidInputError(`chunking: ${name} must be a table`);
+  }
+  const table: TomlTable = value;
+  const read = (key: string): number | undefined => {
+    const field = table[key];
+    if (field === undefined) return undefined;
+    if (typeof field !== 'number') {
+      throw new InvalidInputError(`chunking: ${name}.${key} must be a number`);
+    }
+    return field;
+  };
+  const config = {
+    maxTokensPerChunk: read('max_tokens_per_chunk'),
+    maxOverlapTokens: read('max_overlap_tokens'),
+  };
+  validateChunkingConfig(config);
+  return config;
+}
+
+// Resolves the chunking for a file: extension rule fields override the default
+export class ChunkingPolicy {
+  private readonly defaults: ChunkingConfig;
+  private readonly extensions: { [extension: string]: ChunkingConfig } = {};
+
+  constructor(rules: ChunkingRules = {}) {
+    this.defaults = rules.default || {};
+    validateChunkingConfig(this.defaults);
+    for (const [ext, config] of Object.entries(rules.extensions || {})) {
+      validateChunkingConfig(config);
+      const normalized = ext.toLowerCase();
+      this.extensions[normalized.startsWith('.') ? normalized : `.${normalized}`] = config;
+    }
+  }
+
+  // Reads a [chunking] table:
+  //   [chunking]
+  //   max_tokens_per_chunk = 400
+  //   max_overlap_tokens = 40
+  //   [chunking.extensions]
+  //   ".rs" = { max_tokens_per_chunk = 200, max_overlap_tokens = 20 }
+  static fromToml(value: TomlValue | undefined): ChunkingPolicy {
+    if (value === undefined) return new ChunkingPolicy();
+    if (typeof value !== 'object' || Array.isArray(value)) {
+      throw new InvalidInputError('chunking must be a table');
+    }
+    const { extensions, ...defaults } = value;
+    const extensionRules: { [extension: string]: ChunkingConfig } = {};
+    if (extensions !== undefined) {
+      if (typeof extensions !== 'object' || Array.isArray(extensions)) {
+        throw new InvalidInputError('chunking: extensions must be a table');
+      }
+      for (const [ext, value] of Object.entries(extensions)) {
+        extensionRules[ext] = fromToml(value, `extensions."${ext}"`);
+      }
+    }
+    return new ChunkingPolicy({ default: fromToml(defaults, 'chunking'), extensions: extensionRules });
+  }
+
+  get rules(): ChunkingRules {
+    return { default: { ...this.defaults }, extensions: { ...this.extensions } };
+  }
+
+  forFile(filePath: string): ChunkingConfig | undefined {
+    const rule = this.extensions[path.extname(filePath).toLowerCase()];
+    const merged: ChunkingConfig = {
+      maxTokensPerChunk: rule?.maxTokensPerChunk ?? this.defaults.maxTokensPerChunk,
+      maxOverlapTokens: rule?.maxOverlapTokens ?? this.defaults.maxOverlapTokens,
+    };
+    return merged.maxTokensPerChunk === undefined && merged.maxOverlapTokens === undefined ? undefined : merged;
+  }
+}
@@ -0,0 +1,189 @@
+// ci-report.ts - Per-file ingestion and sync results as JUnit XML, Checkstyle XML or JSON for CI dashboards
+import * as fs from 'fs';
+import * as path from 'path';
+import { ErrorCode, InvalidInputError } from './errors.js';
+import { writeFileAtomic } from './shutdown.js';
+
+// Types
+export type CiReportFormat = 'junit' | 'checkstyle' | 'json';
+
+export type IngestFileStatus = 'uploaded' | 'cached' | 'duplicate' | 'deleted' | 'failed' | 'cancelled';
+
+export interface CiReportTarget {
+  format: CiReportFormat;
+  file: string;
+}
+
+// The outcome of one file of an upload or sync
+export interface IngestFileResult {
+  path: string;  // Relative to the root directory, with "/" separators
+  status: IngestFileStatus;
+  durationMs: number;  // From the file's start, including retries and indexing
+  bytes?: number;  // Sent to the API
+  retries: number;
+  code?: ErrorCode;  // Of a failure; absent for files rejected before upload
+  reason?: string;
+}
+
+// One upload or sync: a test suite in JUnit, its files the test cases
+export interface IngestSuite {
+  name: string;  // e.g. "ingest fileSearchStores/docs-1a2b"
+  store: string;
+  rootDir: string;
+  startedAt: string;
+  durationMs: number;
+  files: IngestFileResult[];
+}
+
+export const CI_REPORT_FORMATS: CiReportFormat[] = ['junit', 'checkstyle', 'json'];
+
+// Keeps the file of a long-running server from growing without end
+const MAX_SUITES = 200;
+
+// The error class of files that failed before they were sent
+const REJECTED = 'REJECTED';
+
+// "junit:reports/ingest.xml"
+export function parseCiReportTarget(value: string): CiReportTarget {
+  const separator = value.indexOf(':');
+  const format = (separator !== -1 ? value.slice(0, separator) : value) as CiReportFormat;
+  const file = separator !== -1 ? value.slice(separator + 1) : '';
+  if (!CI_REPORT_FORMATS.includes(format) || !file) {
+    throw new InvalidInputError(`--report takes <format>:<file> with a format of ${CI_REPORT_FORMATS.join(', ')}, got "${value}"`);
+  }
+  return { format, file: path.resolve(file) };
+}
+
+function escapeXml(text: string): string {
+  // Characters XML 1.0 cannot hold at all, e.g. terminal escapes in an error message
+  return text.replace(/[\u0000-\u0008\u000b\u000c\u000e-\u001f]/g, '')
+    .replace(/&/g, '&amp;')
+    .replace(/</g, '&lt;')
+    .replace(/>/g, '&gt;')
+    .replace(/"/g, '&quot;');
+}
+
+function seconds(ms: number): string {
+  return (ms / 1000).toFixed(3);
+}
+
+function counts(files: IngestFileResult[]): { tests: number; failures: number; skipped: number } {
+  return {
+    tests: files.length,
+    failures: files.filter(file => file.status === 'failed').length,
+    skipped: files.filter(file => file.status === 'cancelled').length,
+  };
+}
+
+// Failed files are test failures typed by their error code; cancelled files are skipped
+export function formatJUnitReport(suites: IngestSuite[]): string {
+  const all = counts(suites.flatMap(suite => suite.files));
+  const totalMs = suites.reduce((sum, suite) => sum + suite.durationMs, 0);
+  const lines = [
+    '<?xml version="1.0" encoding="UTF-8"?>',
+    `<testsuites name="gemini-fs ingest" tests="${all.tests}" failures="${all.failures}" errors="0" skipped="${all.skipped}" time="${seconds(totalMs)}">`,
+  ];
+  for (const suite of suites) {
+    const suiteCounts = counts(suite.files);
+    lines.push(`  <testsuite name="${escapeXml(suite.name)}" tests="${suiteCounts.tests}" failures="${suiteCounts.failures}" errors="0" skipped="${suiteCounts.skipped}" time="${seconds(suite.durationMs)}" timestamp="${suite.startedAt}">`);
+    lines.push('    <properties>');
+    lines.push(`      <property name="store" value="${escapeXml(suite.store)}"/>`);
+    lines.push(`      <property name="root" value="${escapeXml(suite.rootDir)}"/>`);
+    lines.push('    </properties>');
+    for (const file of suite.files) {
+      const testcase = `    <testcase classname="${escapeXml(suite.store)}" name="${escapeXml(file.path)}" time="${seconds(file.durationMs)}"`;
+      if (file.status === 'failed') {
+        const type = file.code || REJECTED;
+        lines.push(`${testcase}>`);
+        lines.push(`      <failure type="${escapeXml(type)}" message="${escapeXml(file.reason || 'upload failed')}">${escapeXml(`${type}: ${file.reason || 'upload failed'} (retries: ${file.retries})`)}</failure>`);
+        lines.push('    </testcase>');
+      } else if (file.status === 'cancelled') {
+        lines.push(`${testcase}>`);
+        lines.push('      <skipped message="cancelled; left for the next run"/>');
+        lines.push('    </testcase>');
+      } else {
+        lines.push(`${testcase}>`);
+        lines.push(`      <system-out>${escapeXml(`${file.status}${file.bytes ? `, ${file.bytes} bytes` : ''}${file.retries ? `, ${file.retries} retries` : ''}`)}</system-out>`);
+        lines.push('    </testcase>');
+      }
+    }
+    lines.push('  </testsuite>');
+  }
+  lines.push('</testsuites>');
+  return lines.join('\n');
+}
+
+// The file as annotators resolve it: relative to the working directory when the root is
+// below it, else relative to the root (e.g. the temporary tree of a git ref); pages keep their URL
+function checkstylePath(suite: IngestSuite, file: IngestFileResult): string {
+  if (/^[a-z][a-z0-9+.-]*:\/\//i.test(file.path)) return file.path;
+  const relative = path.relative(process.cwd(), path.resolve(suite.rootDir, file.path));
+  return relative.startsWith('..') || path.isAbsolute(relative) ? file.path : relative.split(path.sep).join('/');
+}
+
+// Every file is listed; failures are errors on line 1 and cancelled files warnings
+export function formatCheckstyleReport(suites: IngestSuite[]): string {
+  const lines = ['<?xml version="1.0" encoding="UTF-8"?>', '<checkstyle version="4.3">'];
+  for (const suite of suites) {
+    for (const file of suite.files) {
+      const name = escapeXml(checkstylePath(suite, file));
+      if (file.status === 'failed') {
+        const type = file.code || REJECTED;
+        lines.push(`  <file name="${name}">`);
+        lines.push(`    <error line="1" severity="error" message="${escapeXml(`${suite.name}: ${file.reason || 'upload failed'}`)}" source="gemini-fs.ingest.${escapeXml(type)}"/>`);
+        lines.push('  </file>');
+      } else if (file.status === 'cancelled') {
+        lines.push(`  <file name="${name}">`);
+        lines.push(`    <error line="1" severity="warning" message="${escapeXml(`${suite.name}: cancelled; left for the next run`)}" source="gemini-fs.ingest.CANCELLED"/>`);
+        lines.push('  </file>');
+      } else {
+        lines.push(`  <file name="${name}"/>`);
+      }
+    }
+  }
+  lines.push('</checkstyle>');
+  return lines.join('\n');
+}
+
+export function formatCiJsonReport(suites: IngestSuite[]): string {
+  const all = counts(suites.flatMap(suite => suite.files));
+  return JSON.stringify({ version: 1, ...all, suites }, null, 2);
+}
+
+export function formatCiReport(suites: IngestSuite[], format: CiReportFormat): string {
+  switch (format) {
+    case 'junit':
+      return formatJUnitReport(suites);
+    case 'checkstyle':
+      return formatCheckstyleReport(suites);
+    case 'json':
+      return formatCiJsonReport(suites);
+  }
+}
+
+// Collects the suites of a run and rewrites every target after each one, so the report
+// is complete even if a later step of the run fails, and a server's report stays current
+export class CiReportWriter {
+  private readonly suites: IngestSuite[] = [];
+
+  constructor(readonly targets: CiReportTarget[]) {}
+
+  // Undefined without targets, so callers can pass the result straight to the options
+  static of(targets: CiReportTarget[]): CiReportWriter | undefined {
+    return targets.length > 0 ? new CiReportWriter(targets) : undefined;
+  }
+
+  add(suite: IngestSuite): void {
+    this.suites.push(suite);
+    this.suites.splice(0, Math.max(0, this.suites.length - MAX_SUITES));
+    for (const target of this.targets) {
+      try {
+        fs.mkdirSync(path.dirname(target.file), { recursive: true });
+        writeFileAtomic(target.file, `${formatCiReport(this.suites, target.format)}\n`);
+      } catch (error) {
+        const err = error as Error;
+        console.error(`⚠️  Could not write the ${target.format} report ${target.file}: ${err.message}`);
+      }
+    }
+  }
+}
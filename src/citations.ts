@@ -0,0 +1,120 @@
+// citations.ts - Typed citations parsed from grounding metadata
+import { GroundingMetadata, GroundingChunkRetrievedContext } from '@google/genai';
+import { FileAttributes } from './attributes.js';
+import { CitationContext, formatCitationContext, formatContextLocation } from './cite-context.js';
+
+// Types
+export interface Citation {
+  index: number;  // 1-based footnote number
+  fileName: string;
+  chunkText: string;
+  startIndex?: number;  // Character range in the answer of the first segment citing this chunk
+  endIndex?: number;
+  confidence?: number;  // Highest confidence among the segments citing this chunk
+  store?: string;  // Set on fan-out queries over several stores
+  file?: FileAttributes;  // Path, mtime and owner of the cited file, see annotateCitations
+  originalChunkText?: string;  // The chunk as retrieved, when chunkText was translated to the answer language
+  context?: CitationContext;  // The chunk's lines in the local source file, see addCitationContext
+}
+
+// Segment indices are UTF-8 byte offsets; convert them to string offsets
+function toCharOffset(encoded: Buffer, byteOffset: number): number {
+  return encoded.subarray(0, byteOffset).toString('utf8').length;
+}
+
+function chunkNumbers(metadata: GroundingMetadata): Map<number, number> {
+  const numbers = new Map<number, number>();
+  (metadata.groundingChunks || []).forEach((chunk, i) => {
+    if (chunk.retrievedContext?.text) {
+      numbers.set(i, numbers.size + 1);
+    }
+  });
+  return numbers;
+}
+
+export function extractCitations(metadata: GroundingMetadata | undefined, answer: string = ''): Citation[] {
+  if (!metadata) return [];
+  const encoded = Buffer.from(answer, 'utf8');
+  const numbers = chunkNumbers(metadata);
+  const citations = new Map<number, Citation>();
+
+  (metadata.groundingChunks || []).forEach((chunk, i) => {
+    const number = numbers.get(i);
+    if (number === undefined) return;
+    const retrievedContext: GroundingChunkRetrievedContext = chunk.retrievedContext || {};
+    citations.set(i, {
+      index: number,
+      fileName: retrievedContext.title || retrievedContext.uri || 'Unknown',
+      chunkText: retrievedContext.text || '',
+    });
+  });
+
+  for (const support of metadata.groundingSupports || []) {
+    (support.groundingChunkIndices || []).forEach((chunkIndex, j) => {
+      const citation = citations.get(chunkIndex);
+      if (!citation) return;
+      if (citation.startIndex === undefined && support.segment) {
+        citation.startIndex = toCharOffset(encoded, support.segment.startIndex || 0);
+        citation.endIndex = toCharOffset(encoded, support.segment.endIndex || 0);
+      }
+      const confidence = support.confidenceScores?.[j];
+      if (confidence !== undefined && (citation.confidence === undefined || confidence > citation.confidence)) {
+        citation.confidence = confidence;
+      }
+    });
+  }
+
+  return [...citations.values()];
+}
+
+// Inserts [n] markers after each grounded segment of the answer
+export function addFootnoteMarkers(answer: string, metadata: GroundingMetadata | undefined): string {
+  if (!metadata?.groundingSupports?.length) return answer;
+  const encoded = Buffer.from(answer, 'utf8');
+  const numbers = chunkNumbers(metadata);
+
+  const markers = new Map<number, Set<number>>();
+  for (const support of metadata.groundingSupports) {
+    if (support.segment?.endIndex === undefined) continue;
+    const offset = toCharOffset(encoded, support.segment.endIndex);
+    const set = markers.get(offset) || new Set<number>();
+    for (const chunkIndex of support.groundingChunkIndices || []) {
+      const number = numbers.get(chunkIndex);
+      if (number !== undefined) set.add(number);
+    }
+    if (set.size > 0) markers.set(offset, set);
+  }
+
+  let result = answer;
+  const offsets = [...markers.keys()].sort((a, b) => b - a);
+  for (const offset of offsets) {
+    const label = [...(markers.get(offset) as Set<number>)].sort((a, b) => a - b).map(n => `[${n}]`).join('');
+    result = result.slice(0, offset) + label + result.slice(offset);
+  }
+  return result;
+}
+
+// " (services/api/config.yaml, modified 2026-01-02, alice)", or "" without file attributes
+export function formatCitationFile(citation: Citation): string {
+  const file = citation.file;
+  if (!file) return '';
+  const details = [
+    file.path !== citation.fileName.split('#')[0] ? file.path : '',
+    file.mtime !== undefined ? `modified ${new Date(file.mtime * 1000).toISOString().slice(0, 10)}` : '',
+    file.owner || '',
+  ].filter(detail => detail);
+  return details.length > 0 ? ` (${details.join(', ')})` : '';
+}
+
+export function formatFootnotes(citations: Citation[], snippetLength: number = 150): string {
+  return citations.map(c => {
+    const confidence = c.confidence !== undefined ? ` (confidence ${c.confidence.toFixed(2)})` : '';
+    const snippet = c.chunkText.length > snippetLength ? `${c.chunkText.substring(0, snippetLength)}...` : c.chunkText;
+    const store = c.store ? ` _(${c.store})_` : '';
+    // The lines of the file replace the snippet when they were found
+    const body = c.context
+      ? `\n   \`${formatContextLocation(c.context)}\`\n\n   \`\`\`\n${formatCitationContext(c.context, '   ')}\n   \`\`\``
+      : `\n   ${snippet.replace(/\s*\n\s*/g, ' ')}`;
+    return `[${c.index}] **${c.fileName}**${formatCitationFile(c)}${store}${confidence}${body}`;
+  }).join('\n\n');
+}
@@ -0,0 +1,125 @@
+// cite-context.ts - Cited chunks located in their local source files and shown with the surrounding lines
+import * as fs from 'fs';
+import * as path from 'path';
+import { Citation } from './citations.js';
+
+// Types
+export interface CitationContext {
+  path: string;  // The local file, relative to the working directory when it is below it
+  file: string;  // Absolute path
+  startLine: number;  // 1-based lines of the chunk in the file
+  endLine: number;
+  firstLine: number;  // Line number of lines[0]
+  lines: string[];  // The chunk with up to `lines` lines before and after it
+}
+
+export interface CitationContextOptions {
+  sourceRoots: string[];  // Directories the documents were ingested from, searched in order
+  lines: number;  // Lines shown before and after the chunk
+}
+
+// The most lines taken on each side of a chunk
+export const MAX_CONTEXT_LINES = 50;
+
+// Shorter first lines are too common to place a chunk by
+const MIN_PREFIX_LENGTH = 20;
+
+export function lineOf(text: string, offset: number): number {
+  let line = 1;
+  for (let i = text.indexOf('\n'); i !== -1 && i < offset; i = text.indexOf('\n', i + 1)) line++;
+  return line;
+}
+
+// Where the chunk text sits in the file's content: the whole text, else its first line
+// long enough to be telling, as the indexed text may differ from the file after conversion
+export function findChunk(content: string, chunkText: string): { start: number; length: number } | undefined {
+  if (!chunkText) return undefined;
+  const start = content.indexOf(chunkText);
+  if (start !== -1) return { start, length: chunkText.length };
+  const prefix = chunkText.split('\n').map(line => line.trim()).find(line => line.length >= MIN_PREFIX_LENGTH);
+  const found = prefix ? content.indexOf(prefix) : -1;
+  return found !== -1 ? { start: found, length: Math.min(chunkText.length, content.length - found) } : undefined;
+}
+
+// "src/lib.rs#Parser::parse" and "big.log#part-2" were uploaded from "src/lib.rs" and "big.log"
+function sourcePathOf(citation: Citation): string {
+  return citation.file?.path || citation.fileName.split('#')[0];
+}
+
+function readInside(root: string, relativePath: string): { file: string; content: string } | undefined {
+  const file = path.resolve(root, relativePath);
+  const relative = path.relative(root, file);
+  if (!relativePath || relative.startsWith('..') || path.isAbsolute(relative)) return undefined;
+  try {
+    return { file, content: fs.readFileSync(file, 'utf8') };
+  } catch (error) {
+    return undefined;  // Not in this root
+  }
+}
+
+// Adds the surrounding lines to each citation whose chunk is found in a file under one
+// of the roots; the others are returned as they were. Each file is read once.
+export function addCitationContext(citations: Citation[], options: CitationContextOptions): Citation[] {
+  const lines = Math.max(0, Math.min(Math.floor(options.lines), MAX_CONTEXT_LINES));
+  const files = new Map<string, { file: string; content: string; lines: string[] } | undefined>();
+  const fileOf = (relativePath: string) => {
+    if (!files.has(relativePath)) {
+      let found: { file: string; content: string; lines: string[] } | undefined;
+      for (const root of options.sourceRoots) {
+        const read = readInside(path.resolve(root), relativePath);
+        if (read) {
+          found = { ...read, lines: read.content.split(/\r?\n/) };
+          break;
+        }
+      }
+      files.set(relativePath, found);
+    }
+    return files.get(relativePath);
+  };
+
+  return citations.map(citation => {
+    const relativePath = sourcePathOf(citation);
+    const source = fileOf(relativePath);
+    const located = source ? findChunk(source.content, citation.originalChunkText ?? citation.chunkText) : undefined;
+    if (!source || !located) return citation;
+    const startLine = lineOf(source.content, located.start);
+    const endLine = lineOf(source.content, located.start + Math.max(located.length - 1, 0));
+    const firstLine = Math.max(1, startLine - lines);
+    const lastLine = Math.min(source.lines.length, endLine + lines);
+    const shown = path.relative(process.cwd(), source.file);
+    const context: CitationContext = {
+      path: shown.startsWith('..') || path.isAbsolute(shown) ? source.file : shown.split(path.sep).join('/'),
+      file: source.file,
+      startLine,
+      endLine,
+      firstLine,
+      lines: source.lines.slice(firstLine - 1, lastLine),
+    };
+    return { ...citation, context };
+  });
+}
+
+// The directories of the registered projects of the stores, searched after the given roots
+export function projectRoots(projectsFile: string, stores: string[]): string[] {
+  try {
+    const data = JSON.parse(fs.readFileSync(projectsFile, 'utf8')) as { projects: { storeId: string; path: string }[] };
+    return data.projects.filter(project => stores.includes(project.storeId) && project.path).map(project => project.path);
+  } catch (error) {
+    return [];  // No registered projects
+  }
+}
+
+// "src/lib.rs:42-57", which terminals and editors open at the line
+export function formatContextLocation(context: CitationContext): string {
+  return `${context.path}:${context.startLine}${context.endLine > context.startLine ? `-${context.endLine}` : ''}`;
+}
+
+// Numbered lines, the chunk's marked with "│" and the surrounding ones with ":"
+export function formatCitationContext(context: CitationContext, indent: string = ''): string {
+  const width = String(context.firstLine + context.lines.length - 1).length;
+  return context.lines.map((line, i) => {
+    const number = context.firstLine + i;
+    const marker = number >= context.startLine && number <= context.endLine ? '│' : ':';
+    return `${indent}${String(number).padStart(width)} ${marker} ${line}`.trimEnd();
+  }).join('\n');
+}
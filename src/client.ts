@@ -0,0 +1,571 @@
+// client.ts - Reusable Gemini File Search client
+import {
+  GoogleGenAI,
+  GoogleGenAIOptions,
+  GenerateContentConfig,
+  GenerateContentResponse,
+  Document,
+  Tool,
+  UploadToFileSearchStoreOperation,
+  UploadToFileSearchStoreResponse,
+} from '@google/genai';
+import * as fs from 'fs';
+import * as path from 'path';
+import { ResumableUploader, UploadSessionStore, RESUMABLE_THRESHOLD } from './upload.js';
+import { QueryEvent, toQueryEvents } from './stream.js';
+import { StoreManager } from './store.js';
+import { PollOptions, operationOf, waitForOperation } from './operations.js';
+import { DocumentMetadata, toCustomMetadata } from './metadata.js';
+import { RetryOptions, withRetry } from './retry.js';
+import { ChunkingConfig, toApiChunkingConfig } from './chunking.js';
+import { AnswerBlockedError, ConfigError, FileTooLargeError, NotFoundError, toFileSearchError } from './errors.js';
+import { ApiKeyAuthProvider, AuthProvider, KeyRingAuthProvider } from './auth.js';
+import { ApiOptions, BackendConfig, retrievalTool } from './backend.js';
+import { VertexRagTransport } from './vertex.js';
+import { telemetry } from './telemetry.js';
+import { estimateTokens, toTokenUsage } from './cost.js';
+import { GenerationParams, blockedAnswer } from './generation.js';
+import { DEFAULT_MODEL, queryConfig } from './core.js';
+import { JsonSchema, parseStructuredAnswer } from './schema.js';
+import { Notifier, indexingCompleted, indexingFailed } from './notify.js';
+import { QUERY_RESERVE_TOKENS, QuotaScheduler } from './quota.js';
+import { DaemonLink } from './daemon.js';
+import { NetworkOptions, configureNetwork } from './network.js';
+import { AclPolicy, resolveAclLabels, scopeFilter } from './acl.js';
+import { PageOptions, Paginator } from './pagination.js';
+import { KeyRing } from './keys.js';
+import { Timeouts, startDeadline, withTimeout } from './timeouts.js';
+import { TenantPolicy, statePath } from './tenant.js';
+import { storeOfDocument } from './documents.js';
+import { DEFAULT_TOP_K, RETRIEVE_ONLY_INSTRUCTION, RetrieveOptions, RetrievedChunk, chunksFromContexts, chunksFromGrounding } from './retrieve.js';
+
+// Types
+export interface FileSearchClientOptions {
+  apiKey?: string;
+  auth?: AuthProvider;  // Takes precedence over apiKey
+  backend?: BackendConfig;  // Default: the Gemini API
+  timeout?: number;  // Per-request timeout in milliseconds
+  timeouts?: Timeouts;  // Connect, upload, query and indexing deadlines, each overriding timeout for its requests
+  baseUrl?: string;  // Gemini API endpoint, e.g. a MockGeminiServer (default: GEMINI_BASE_URL or the public API)
+  api?: ApiOptions;  // Pinned API version and extra headers of every request
+  model?: string;
+  uploadSessionsFile?: string;  // Where resumable upload sessions are persisted
+  retry?: RetryOptions;  // Backoff for 429/5xx responses on upload, import and query calls
+  notifier?: Notifier;  // Told when each uploaded or imported document finishes indexing or fails
+  quota?: QuotaScheduler;  // Daily request and token budgets for uploads, queries, generation and embeddings
+  daemon?: DaemonLink | false;  // Shares rate limits and budgets with other commands (default: the daemon socket, if one runs; false: none)
+  network?: NetworkOptions;  // Proxy and extra CA certificates; process-wide, like the fetch they apply to
+  acl?: AclPolicy;  // Scope every query to access labels, see acl.ts
+  tenant?: TenantPolicy;  // Prefix created stores and refuse those of other tenants, see tenant.ts
+}
+
+export interface UploadFileOptions {
+  displayName?: string;
+  mimeType?: string;
+  pollInterval?: number;  // Milliseconds before the first operation poll; later polls back off
+  maxPolls?: number;
+  timeout?: number;  // Milliseconds to wait for indexing (default: 10 minutes)
+  wait?: boolean;  // false returns the operation name as soon as the bytes are sent (Gemini API only)
+  resume?: boolean;  // Continue a previously interrupted resumable upload
+  metadata?: DocumentMetadata;  // Custom key/value metadata usable in query filters
+  chunking?: ChunkingConfig;
+  onRetry?: RetryOptions['onRetry'];  // Called for every retried request of this upload
+  onIndexing?: () => void;  // Called once the bytes are sent and indexing has started
+  signal?: AbortSignal;  // Aborts before the upload starts or during a resumable transfer, whose session is kept
+}
+
+export interface UploadResult {
+  documentName?: string;
+  documentNames?: string[];  // Every document created when a file was split by symbol or got a summary
+  operationName?: string;  // The indexing operation, when the upload did not wait for it; see waitForUpload
+}
+
+export interface QueryOptions {
+  model?: string;
+  metadataFilter?: string;  // AIP-160 filter, see parseFilterExpression
+  aclLabels?: string[];  // Only search documents carrying one of these access labels
+  groundedOnly?: boolean;  // Instruct the model to answer only from the documents, see checkGrounding
+  systemInstruction?: string;  // Stored instruction and answer style, see resolveSystemInstruction
+  generation?: GenerationParams;  // Sampling and safety settings; an explicit temperature wins over groundedOnly's 0
+  responseSchema?: JsonSchema;  // Ask for a JSON answer matching this schema, see queryStructured
+}
+
+export const DEFAULT_EMBEDDING_MODEL = 'gemini-embedding-001';
+
+// Largest file the File Search API accepts
+export const MAX_UPLOAD_SIZE = 100 * 1024 * 1024;
+
+// Resumable upload sessions, in the state home unless the builder names another file
+export const SESSIONS_FILE = '.gemini-upload-sessions.json';
+
+// Size of a file to upload; a missing one is a NotFoundError like a missing store or document
+function uploadSize(filePath: string): number {
+  try {
+    return fs.statSync(filePath).size;
+  } catch (error) {
+    throw (error as NodeJS.ErrnoException).code === 'ENOENT' ? new NotFoundError(filePath, { cause: error }) : toFileSearchError(error);
+  }
+}
+
+export class FileSearchClientBuilder {
+  private options: Partial<FileSearchClientOptions> = {};
+
+  apiKey(apiKey: string): this {
+    this.options.apiKey = apiKey;
+    return this;
+  }
+
+  auth(auth: AuthProvider): this {
+    this.options.auth = auth;
+    return this;
+  }
+
+  backend(backend: BackendConfig): this {
+    this.options.backend = backend;
+    return this;
+  }
+
+  timeout(timeout: number): this {
+    this.options.timeout = timeout;
+    return this;
+  }
+
+  timeouts(timeouts: Timeouts): this {
+    this.options.timeouts = timeouts;
+    return this;
+  }
+
+  baseUrl(baseUrl: string): this {
+    this.options.baseUrl = baseUrl;
+    return this;
+  }
+
+  api(api: ApiOptions): this {
+    this.options.api = api;
+    return this;
+  }
+
+  model(model: string): this {
+    this.options.model = model;
+    return this;
+  }
+
+  uploadSessionsFile(filePath: string): this {
+    this.options.uploadSessionsFile = filePath;
+    return this;
+  }
+
+  retry(retry: RetryOptions): this {
+    this.options.retry = retry;
+    return this;
+  }
+
+  notifier(notifier: Notifier): this {
+    this.options.notifier = notifier;
+    return this;
+  }
+
+  quota(quota: QuotaScheduler): this {
+    this.options.quota = quota;
+    return this;
+  }
+
+  daemon(daemon: DaemonLink | false): this {
+    this.options.daemon = daemon;
+    return this;
+  }
+
+  network(network: NetworkOptions): this {
+    this.options.network = network;
+    return this;
+  }
+
+  acl(acl: AclPolicy): this {
+    this.options.acl = acl;
+    return this;
+  }
+
+  tenant(tenant: TenantPolicy | undefined): this {
+    this.options.tenant = tenant;
+    return this;
+  }
+
+  build(): FileSearchClient {
+    if (!this.options.apiKey && !this.options.auth) {
+      throw new ConfigError('FileSearchClient requires an API key or an auth provider');
+    }
+    if (this.options.backend?.kind === 'vertex' && (!this.options.auth || this.options.auth.method === 'api-key')) {
+      throw new ConfigError('The vertex backend requires adc or service-account credentials');
+    }
+    if (this.options.network || this.options.timeouts?.connect) {
+      configureNetwork({ ...this.options.network, connectTimeout: this.options.timeouts?.connect });
+    }
+    return new FileSearchClient(this.options as FileSearchClientOptions);
+  }
+}
+
+// A single client instance should be shared for the lifetime of the process:
+// the underlying fetch dispatcher keeps connections alive, so every request
+// made through the same client reuses the pooled sockets.
+export class FileSearchClient {
+  readonly model: string;
+  readonly stores: StoreManager;
+  readonly retry: RetryOptions;
+  readonly backend: BackendConfig;
+  readonly acl?: AclPolicy;
+  readonly tenant?: TenantPolicy;
+  readonly timeouts: Timeouts;
+  private readonly notifier?: Notifier;
+  private readonly quota?: QuotaScheduler;
+  private readonly daemon?: DaemonLink;
+  private readonly uploader: ResumableUploader;
+  private readonly vertex?: VertexRagTransport;
+  private readonly sdkOptions: GoogleGenAIOptions;
+  private readonly sdkClients = new Map<string, GoogleGenAI>();
+  private readonly keyRing?: KeyRing;
+
+  static builder(): FileSearchClientBuilder {
+    return new FileSearchClientBuilder();
+  }
+
+  constructor(options: FileSearchClientOptions) {
+    const auth = options.auth || new ApiKeyAuthProvider(options.apiKey || '');
+    this.backend = options.backend || { kind: 'gemini' };
+    const vertex = this.backend.kind === 'vertex';
+    const baseUrl = vertex ? undefined : options.baseUrl || process.env.GEMINI_BASE_URL || undefined;
+    const api = options.api || {};
+    this.sdkOptions = {
+      ...auth.clientOptions(),
+      ...(vertex ? { vertexai: true, project: this.backend.project, location: this.backend.location } : {}),
+      httpOptions: options.timeout || baseUrl || api.version || api.headers
+        ? { timeout: options.timeout, baseUrl, apiVersion: api.version, headers: api.headers }
+        : undefined,
+    };
+    this.model = options.model || DEFAULT_MODEL;
+    this.timeouts = options.timeouts || {};
+    this.keyRing = auth instanceof KeyRingAuthProvider ? auth.ring : undefined;
+    const ring = this.keyRing;
+    // Quota and credential errors move to the next key and repeat the request at once
+    this.retry = ring ? { ...options.retry, failover: (error, startedAt) => ring.failover(error, startedAt) } : options.retry || {};
+    this.vertex = vertex ? new VertexRagTransport(this.backend, auth, this.retry, api) : undefined;
+    this.notifier = options.notifier;
+    this.acl = options.acl;
+    this.tenant = options.tenant;
+    this.quota = options.quota?.enabled ? options.quota : undefined;
+    this.daemon = options.daemon === false ? undefined : options.daemon || DaemonLink.fromEnvironment();
+    this.stores = new StoreManager(() => this.ai, this.retry, this.vertex, this.notifier, this.timeouts.operation, this.tenant);
+    const sessionsFile = options.uploadSessionsFile || statePath(SESSIONS_FILE, this.tenant?.name, this.tenant ? [] : [path.join(process.cwd(), SESSIONS_FILE)]);
+    this.uploader = new ResumableUploader(auth, new UploadSessionStore(sessionsFile), baseUrl, api);
+  }
+
+  // The SDK client of the key in use: with several API keys, one per key, created as the
+  // key ring reaches it
+  get ai(): GoogleGenAI {
+    return this.sdk();
+  }
+
+  // With a timeout, an SDK client whose every request gives up after it: uploads send
+  // their bytes in requests the per-call options do not reach
+  private sdk(timeout?: number): GoogleGenAI {
+    const apiKey = this.keyRing?.use() ?? '';
+    const key = timeout ? `${apiKey}|${timeout}` : apiKey;
+    let ai = this.sdkClients.get(key);
+    if (!ai) {
+      ai = new GoogleGenAI({
+        ...this.sdkOptions,
+        ...(timeout ? { httpOptions: { ...this.sdkOptions.httpOptions, timeout } } : {}),
+        ...(this.keyRing ? { apiKey } : {}),
+      });
+      this.sdkClients.set(key, ai);
+    }
+    return ai;
+  }
+
+  // With a daemon running, its rate limit and budgets apply instead of the command's own
+  private scheduled<T>(label: string, tokens: number, task: () => Promise<T>, actualTokens?: (result: T) => number | undefined): Promise<T> {
+    const local = () => (this.quota ? this.quota.run(label, tokens, task, actualTokens) : task());
+    return this.daemon ? this.daemon.run(label, tokens, task, actualTokens, local) : local();
+  }
+
+  async createStore(displayName: string): Promise<string> {
+    return (await this.stores.createStore(displayName)).name;
+  }
+
+  async deleteStore(storeName: string): Promise<void> {
+    await this.stores.deleteStore(storeName);
+  }
+
+  async listDocuments(storeName: string): Promise<Document[]> {
+    return this.paginateDocuments(storeName).all();
+  }
+
+  // The store's documents a page at a time, e.g. client.paginateDocuments(store, { pageSize: 20 }).intoStream()
+  paginateDocuments(storeName: string, options: PageOptions = {}): Paginator<Document> {
+    return new Paginator(async (pageToken, pageSize) => {
+      try {
+        await this.stores.checkTenant([storeName]);
+        if (this.vertex) return await this.vertex.listFilesPage(storeName, pageToken, pageSize);
+        const pager = await this.ai.fileSearchStores.documents.list({ parent: storeName, config: { pageToken, pageSize } });
+        return { items: pager.page, nextPageToken: pager.params.config?.pageToken || undefined };
+      } catch (error) {
+        throw toFileSearchError(error, storeName);
+      }
+    }, options);
+  }
+
+  async getDocument(documentName: string): Promise<Document> {
+    await this.stores.checkTenant([storeOfDocument(documentName) || documentName]);
+    const request = this.vertex
+      ? this.vertex.getFile(documentName)
+      : this.ai.fileSearchStores.documents.get({ name: documentName });
+    return request.catch(error => {
+      throw toFileSearchError(error);
+    });
+  }
+
+  async deleteDocument(documentName: string): Promise<void> {
+    await this.stores.checkTenant([storeOfDocument(documentName) || documentName]);
+    const request = this.vertex
+      ? this.vertex.deleteFile(documentName)
+      : this.ai.fileSearchStores.documents.delete({
+        name: documentName,
+        config: { force: true },
+      });
+    await request.catch(error => {
+      throw toFileSearchError(error);
+    });
+  }
+
+  // Uploads a file and waits for the indexing operation to finish, unless wait is false.
+  // Large files use the resumable protocol so an interrupted upload can be continued.
+  async uploadFile(storeName: string, filePath: string, options: UploadFileOptions = {}): Promise<UploadResult> {
+    await this.stores.checkTenant([storeName]);
+    const bytes = uploadSize(filePath);
+    const file = options.displayName || path.basename(filePath);
+    return telemetry.trace('upload', { store: storeName, file, bytes }, async () => {
+      try {
+        const result = await this.scheduled(`upload of ${file}`, estimateTokens(bytes), () => this.doUpload(storeName, filePath, options));
+        telemetry.uploadedBytes.add(bytes, { store: storeName });
+        telemetry.uploadedFiles.add(1, { result: 'success' });
+        if (!result.operationName) await this.notifier?.notify(indexingCompleted(storeName, filePath, options.displayName, result.documentName));
+        return result;
+      } catch (error) {
+        telemetry.uploadedFiles.add(1, { result: 'failure' });
+        if (toFileSearchError(error).code !== 'CANCELLED') {
+          await this.notifier?.notify(indexingFailed(storeName, filePath, options.displayName, error));
+        }
+        throw error;
+      }
+    });
+  }
+
+  // Waits for the indexing of an upload made with wait: false
+  async waitForUpload(operationName: string, options: PollOptions = {}): Promise<UploadResult> {
+    const done = await waitForOperation<UploadToFileSearchStoreResponse>(this.ai, operationOf('upload', operationName), {
+      retry: this.retry,
+      ...options,
+      timeout: options.timeout ?? this.timeouts.operation,
+    });
+    return { documentName: done.response?.documentName };
+  }
+
+  private async doUpload(storeName: string, filePath: string, options: UploadFileOptions): Promise<UploadResult> {
+    const { pollInterval, maxPolls, timeout } = options;
+
+    const customMetadata = options.metadata ? toCustomMetadata(options.metadata) : undefined;
+    const chunkingConfig = toApiChunkingConfig(options.chunking);
+
+    const label = `upload of ${path.basename(filePath)}`;
+    const retry = { ...this.retry, onRetry: options.onRetry || this.retry.onRetry };
+
+    const size = uploadSize(filePath);
+    if (size > MAX_UPLOAD_SIZE) {
+      throw new FileTooLargeError(filePath, MAX_UPLOAD_SIZE, size);
+    }
+    options.signal?.throwIfAborted();
+
+    const vertex = this.vertex;
+    if (vertex) {
+      if (customMetadata) {
+        console.error(`⚠️  Custom metadata is not supported by the vertex backend, ignored for ${path.basename(filePath)}`);
+      }
+      const documentName = await withRetry(label, () => vertex.uploadFile(storeName, filePath, {
+        displayName: options.displayName,
+        chunking: options.chunking,
+      }), retry);
+      return { documentName };
+    }
+
+    let operation: UploadToFileSearchStoreOperation;
+    if (size > RESUMABLE_THRESHOLD) {
+      // Retries resume the session from the last acknowledged chunk
+      operation = await withRetry(label, attempt => this.uploader.upload(storeName, filePath, {
+        displayName: options.displayName,
+        mimeType: options.mimeType,
+        resume: options.resume || attempt > 1,
+        customMetadata,
+        chunkingConfig,
+        signal: options.signal,
+        timeout: this.timeouts.upload,
+      }), retry);
+    } else {
+      operation = await withRetry(label, () => withTimeout('upload', this.timeouts.upload, () => this.sdk(this.timeouts.upload).fileSearchStores.uploadToFileSearchStore({
+        file: filePath,
+        fileSearchStoreName: storeName,
+        config: {
+          displayName: options.displayName,
+          mimeType: options.mimeType,
+          customMetadata,
+          chunkingConfig,
+        },
+      })), retry);
+    }
+
+    options.onIndexing?.();
+    if (options.wait === false && operation.name) {
+      return { operationName: operation.name };
+    }
+    const done = await waitForOperation<UploadToFileSearchStoreResponse>(this.ai, operation, {
+      pollInterval,
+      maxPolls,
+      timeout: timeout ?? this.timeouts.operation,
+      retry,
+    });
+    return { documentName: done.response?.documentName };
+  }
+
+  async countTokens(text: string, model?: string): Promise<number> {
+    const response = await this.scheduled('token count', 0, () => withRetry('token count', () => this.ai.models.countTokens({
+      model: model || this.model,
+      contents: text,
+    }), this.retry));
+    return response.totalTokens || 0;
+  }
+
+  // One embedding vector per text, in the same order; taskType is e.g. RETRIEVAL_DOCUMENT
+  // or RETRIEVAL_QUERY, and dimensions truncates the vectors
+  async embed(texts: string[], model: string = DEFAULT_EMBEDDING_MODEL, options: { taskType?: string; dimensions?: number } = {}): Promise<number[][]> {
+    const tokens = estimateTokens(texts.reduce((sum, text) => sum + Buffer.byteLength(text), 0));
+    const response = await this.scheduled('embedding', tokens, () => withRetry('embedding', () => this.ai.models.embedContent({
+      model,
+      contents: texts,
+      ...(options.taskType || options.dimensions ? { config: { taskType: options.taskType, outputDimensionality: options.dimensions } } : {}),
+    }), this.retry));
+    return (response.embeddings || []).map(embedding => embedding.values || []);
+  }
+
+  // Plain generation without retrieval, e.g. for summaries
+  async generate(prompt: string, options: { model?: string; systemInstruction?: string } = {}): Promise<string> {
+    const model = options.model || this.model;
+    return telemetry.trace('generate', { model }, async span => {
+      const tokens = estimateTokens(Buffer.byteLength(prompt)) + QUERY_RESERVE_TOKENS;
+      const response = await this.scheduled('generation', tokens, () => withRetry('generation', () => withTimeout('query', this.timeouts.query, signal => this.ai.models.generateContent({
+        model,
+        contents: prompt,
+        config: { ...(options.systemInstruction ? { systemInstruction: options.systemInstruction } : {}), abortSignal: signal },
+      })), this.retry), result => result.usageMetadata?.totalTokenCount);
+      telemetry.recordTokens(model, toTokenUsage(response.usageMetadata), span);
+      return response.text || '';
+    });
+  }
+
+  // The retrieval tool for a query, its filter scoped to the access labels when
+  // given or required by the ACL policy; chat sessions build theirs here as well
+  retrievalTool(storeNames: string[], options: { metadataFilter?: string; aclLabels?: string[] } = {}): Tool {
+    const labels = resolveAclLabels(options.aclLabels, this.acl);
+    return retrievalTool(this.backend, storeNames, scopeFilter(options.metadataFilter, labels));
+  }
+
+  private queryConfig(storeNames: string[], options: QueryOptions): GenerateContentConfig {
+    return queryConfig(this.retrievalTool(storeNames, options), options);
+  }
+
+  async query(storeNames: string[], question: string, options: QueryOptions = {}): Promise<GenerateContentResponse> {
+    const model = options.model || this.model;
+    return telemetry.trace('query', { stores: storeNames.join(','), model }, async span => {
+      await this.stores.checkTenant(storeNames);
+      const tokens = estimateTokens(Buffer.byteLength(question)) + QUERY_RESERVE_TOKENS;
+      const response = await this.scheduled('query', tokens, () => withRetry('query', () => withTimeout('query', this.timeouts.query, signal => this.ai.models.generateContent({
+        model,
+        contents: question,
+        config: { ...this.queryConfig(storeNames, options), abortSignal: signal },
+      })), this.retry), result => result.usageMetadata?.totalTokenCount);
+      telemetry.recordTokens(model, toTokenUsage(response.usageMetadata), span);
+      return response;
+    });
+  }
+
+  // The chunks a query matches, ranked, without an answer to build on. Vertex AI runs the
+  // vector search alone; on the Gemini API the model still runs the file search tool,
+  // told not to answer, so a short reply's tokens are spent and no scores are returned
+  async retrieve(storeNames: string[], query: string, options: RetrieveOptions = {}): Promise<RetrievedChunk[]> {
+    const topK = options.topK || DEFAULT_TOP_K;
+    const model = options.model || this.model;
+    return telemetry.trace('retrieve', { stores: storeNames.join(','), topK }, async span => {
+      await this.stores.checkTenant(storeNames);
+      const metadataFilter = scopeFilter(options.metadataFilter, resolveAclLabels(options.aclLabels, this.acl));
+      const tokens = estimateTokens(Buffer.byteLength(query));
+      if (this.vertex) {
+        const vertex = this.vertex;
+        const contexts = await this.scheduled('retrieval', tokens, () => withTimeout('query', this.timeouts.query, signal => vertex.retrieveContexts(storeNames, query, { topK, metadataFilter, signal })));
+        return chunksFromContexts(contexts, storeNames, topK);
+      }
+      const response = await this.scheduled('retrieval', tokens + QUERY_RESERVE_TOKENS, () => withRetry('retrieval', () => withTimeout('query', this.timeouts.query, signal => this.ai.models.generateContent({
+        model,
+        contents: query,
+        config: {
+          tools: [{ fileSearch: { fileSearchStoreNames: storeNames, topK, metadataFilter } }],
+          systemInstruction: RETRIEVE_ONLY_INSTRUCTION,
+          temperature: 0,
+          abortSignal: signal,
+        },
+      })), this.retry), result => result.usageMetadata?.totalTokenCount);
+      telemetry.recordTokens(model, toTokenUsage(response.usageMetadata), span);
+      return chunksFromGrounding(response.candidates?.[0]?.groundingMetadata, storeNames, topK);
+    });
+  }
+
+  // Asks for a JSON answer matching the schema and validates it locally; throws
+  // SchemaValidationError when the model's answer does not match
+  async queryStructured<T = unknown>(
+    storeNames: string[],
+    question: string,
+    schema: JsonSchema,
+    options: QueryOptions = {}
+  ): Promise<{ value: T; response: GenerateContentResponse }> {
+    const response = await this.query(storeNames, question, { ...options, responseSchema: schema });
+    const blocked = blockedAnswer(response, response.text || '');
+    if (blocked) throw new AnswerBlockedError(blocked);
+    return { value: parseStructuredAnswer<T>(response.text || '', schema), response };
+  }
+
+  // Streams the answer as text deltas followed by citation events.
+  // Only opening the stream is retried; a stream that fails midway is not restarted.
+  // The query timeout covers the whole stream, retries included.
+  async *queryStream(storeNames: string[], question: string, options: QueryOptions = {}): AsyncGenerator<QueryEvent> {
+    const model = options.model || this.model;
+    const span = telemetry.startSpan('streaming query', { stores: storeNames.join(','), model });
+    const deadline = startDeadline('query', this.timeouts.query);
+    try {
+      await this.stores.checkTenant(storeNames);
+      // The stream's usage arrives after the budget is settled, so the estimate is what counts
+      const tokens = estimateTokens(Buffer.byteLength(question)) + QUERY_RESERVE_TOKENS;
+      const chunks = await this.scheduled('streaming query', tokens, () => withRetry('streaming query', () => this.ai.models.generateContentStream({
+        model,
+        contents: question,
+        config: { ...this.queryConfig(storeNames, options), abortSignal: deadline.signal },
+      }), this.retry));
+      for await (const event of toQueryEvents(chunks)) {
+        if (event.type === 'done') telemetry.recordTokens(model, event.usage, span);
+        yield event;
+      }
+      span.end();
+    } catch (error) {
+      const failure = deadline.error(error);
+      span.end(failure);
+      throw failure;
+    } finally {
+      deadline.clear();
+    }
+  }
+}
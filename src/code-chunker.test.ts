@@ -0,0 +1,131 @@
+// code-chunker.test.ts - Chunk boundaries around brackets in parameters, strings and comments, nested functions and decorators
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { CodeChunkOptions, chunkCode, isCodeFile } from './code-chunker.js';
+
+// "symbol:kind:startLine-endLine" of each chunk
+function boundaries(filePath: string, lines: string[], options: CodeChunkOptions = {}): string[] {
+  return chunkCode(filePath, lines.join('\n'), options).map(chunk => `${chunk.symbol}:${chunk.kind}:${chunk.startLine}-${chunk.endLine}`);
+}
+
+test('only source files of the supported languages are chunked', () => {
+  assert.ok(isCodeFile('src/lib.RS'));
+  assert.ok(!isCodeFile('README.md'));
+  assert.deepEqual(chunkCode('README.md', '# Title\n'), []);
+});
+
+test('TypeScript: braces in default values, return types, strings, comments and templates are not the body', () => {
+  assert.deepEqual(boundaries('a.ts', [
+    'function withDefaults(',
+    '  options = {},',
+    '  limits: { max: number } = { max: 1 },',
+    ') {',
+    '  return options;',
+    '}',
+    '',
+    'export function shape(): { a: string } {',
+    '  const open = \'{\';',
+    '  const close = "}"; // }',
+    '  /* { */',
+    '  return { a: `${open ? `}${close}` : \'\'}` };',
+    '}',
+    '',
+    'const pattern = /[{]/;',
+    'function after() {}',
+  ]), [
+    'withDefaults:function:1-6',
+    'shape:function:8-13',
+    '(module):module:15-15',
+    'after:function:16-16',
+  ]);
+});
+
+test('TypeScript: nested functions stay in their function, a multi-line decorator with its class', () => {
+  const lines = [
+    'import { Component } from \'./component.js\';',
+    '',
+    '@Component({',
+    '  selector: \'app-root\',',
+    '})',
+    'export class App {',
+    '  run() {',
+    '    function inner() {',
+    '      return \'}\';',
+    '    }',
+    '    return inner();',
+    '  }',
+    '',
+    '  stop() {}',
+    '}',
+  ];
+  assert.deepEqual(boundaries('a.ts', lines), ['(module):module:1-1', 'App:class:3-15']);
+  assert.deepEqual(boundaries('a.ts', lines, { maxLines: 5 }), [
+    '(module):module:1-1',
+    'App:class:3-6',
+    'App.run:method:7-12',
+    'App.stop:method:14-14',
+  ]);
+});
+
+test('Rust: raw strings, char literals and nested block comments hold no braces', () => {
+  assert.deepEqual(boundaries('a.rs', [
+    'fn paths() {',
+    '    let windows = r"C:\\dir\\";',
+    '    let quoted = r#"a "quoted" } brace"#;',
+    '    let brace = \'}\';',
+    '}',
+    '',
+    'fn commented<\'a>(s: &\'a str) -> &\'a str {',
+    '    /* outer /* nested */ } */',
+    '    s',
+    '}',
+    '',
+    'fn after() {}',
+  ]), ['paths:fn:1-5', 'commented:fn:7-10', 'after:fn:12-12']);
+});
+
+test('Rust: a long impl block is split into members named after its type, attributes kept with them', () => {
+  assert.deepEqual(boundaries('a.rs', [
+    'impl<T> Display for Wrapper<T> {',
+    '    #[inline]',
+    '    fn fmt(',
+    '        &self,',
+    '        f: &mut Formatter,',
+    '    ) -> Result {',
+    '        write!(f, "{}", "}")',
+    '    }',
+    '}',
+  ], { maxLines: 4 }), ['Display for Wrapper:impl:1-1', 'Wrapper::fmt:fn:2-8']);
+});
+
+test('Go: raw strings and runes hold no braces', () => {
+  assert.deepEqual(boundaries('a.go', [
+    'func (s *Server) Template() string {',
+    '    open := \'{\'',
+    '    return `}',
+    '    {{ .Name }`',
+    '}',
+    '',
+    'func After() {}',
+  ]), ['Server.Template:method:1-5', 'After:func:7-7']);
+});
+
+test('Python: quotes inside strings, multi-line decorators and dedented continuation lines', () => {
+  assert.deepEqual(boundaries('a.py', [
+    '@app.route(',
+    '    "/index",',
+    ')',
+    'def index():',
+    '    marker = \'"""\'',
+    '    value = call(',
+    '  1)',
+    '    # def hidden():',
+    '    return marker',
+    '',
+    'def after():',
+    '    text = """',
+    'def not_a_function():',
+    '"""',
+    '    return text',
+  ]), ['index:def:1-9', 'after:def:11-15']);
+});
@@ -0,0 +1,470 @@
+// code-chunker.ts - Splits source files along function, class and impl boundaries
+import * as path from 'path';
+
+// Types
+export interface CodeChunk {
+  symbol: string;  // "User::new", "Calculator.add", or "(module)" for top-level code
+  kind: string;  // fn, impl, class, def, ...
+  startLine: number;  // 1-based, inclusive
+  endLine: number;
+  text: string;
+}
+
+export interface CodeChunkOptions {
+  maxLines?: number;  // Containers longer than this are split into their members (default: 200)
+}
+
+type Language = 'rust' | 'python' | 'typescript' | 'go';
+
+interface Declaration {
+  kind: string;
+  name: string;
+  owner?: string;  // Type whose members the block contains (impl blocks, Go methods)
+}
+
+// The source with comments blanked and literals emptied, per line
+interface SourceScan {
+  code: string[];  // Each line with comments as spaces and string, char and regex literals as delimiters around spaces
+  depth: number[];  // Brackets of any kind open at the start of each line
+  inCode: boolean[];  // False where the line starts inside a comment, a literal or a template interpolation
+}
+
+interface Extent {
+  openLine: number;  // Line of the body's opening brace, -1 for declarations without a body
+  end: number;  // Line of the closing brace or the end of the statement
+}
+
+const LANGUAGES: { [extension: string]: Language } = {
+  '.rs': 'rust',
+  '.py': 'python',
+  '.ts': 'typescript',
+  '.tsx': 'typescript',
+  '.mts': 'typescript',
+  '.cts': 'typescript',
+  '.js': 'typescript',
+  '.jsx': 'typescript',
+  '.mjs': 'typescript',
+  '.cjs': 'typescript',
+  '.go': 'go',
+};
+
+// Kinds whose body is split into members when the block is too long
+const CONTAINER_KINDS = new Set(['impl', 'trait', 'mod', 'class', 'namespace']);
+
+const DEFAULT_MAX_LINES = 200;
+const MODULE_SYMBOL = '(module)';
+
+const RUST_DECLARATION = /^(?:pub(?:\([^)]*\))?\s+)?(?:(?:default|const|async|unsafe|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|union|mod|impl|macro_rules!)\s*(.*)$/;
+const TS_DECLARATION = /^(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|enum|namespace|module|type|const|let|var)\s+([A-Za-z_$][\w$]*)/;
+const TS_MEMBER = /^(?:(?:public|private|protected|static|readonly|async|abstract|override|declare|get|set)\s+)*\*?(#?[A-Za-z_$][\w$]*)\s*(?:<[^>]*>)?\s*\(/;
+const GO_DECLARATION = /^(func|type)\s+(?:\(\s*(?:\w+\s+)?\*?\s*(\w+)[^)]*\)\s*)?([A-Za-z_]\w*)/;
+const PYTHON_DECLARATION = /^(?:async\s+)?(def|class)\s+([A-Za-z_]\w*)/;
+
+// A declaration header that continues on the next line
+const CONTINUED_LINE = /(?:[,(=<:+|&[]|=>|->|\bwhere)\s*$/;
+const CONTINUATION_START = /^(?:\{|where\b|->|=>|\)|\.|:)/;
+
+// A slash after these starts a regex literal rather than a division
+const REGEX_PRECEDER = /(?:^|[(,=:[!&|?{};+\-*%<>~^]|\breturn|\btypeof)$/;
+
+// TypeScript: a brace after these opens an object type, as in `f(): { a: string } {`, not the body
+const TYPE_BRACE_PRECEDER = /(?:[:|&<,]|\bextends)\s*$/;
+
+const OPENING_BRACKETS = '([{';
+const CLOSING_BRACKETS = ')]}';
+
+const CONTROL_KEYWORDS = new Set(['if', 'for', 'while', 'switch', 'catch', 'return', 'super']);
+
+export function isCodeFile(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() in LANGUAGES;
+}
+
+function matchDeclaration(line: string, language: Language, member: boolean): Declaration | null {
+  const text = line.trim();
+  switch (language) {
+    case 'rust': {
+      const match = RUST_DECLARATION.exec(text);
+      if (!match) return null;
+      const [, kind, rest] = match;
+      if (kind !== 'impl') {
+        const name = /^[A-Za-z_]\w*/.exec(rest);
+        return name ? { kind: kind === 'macro_rules!' ? 'macro' : kind, name: name[0] } : null;
+      }
+      // impl<T> Trait for Type<T> -> members are named after Type
+      const header = rest.replace(/^<[^>]*>\s*/, '').replace(/\s*(?:where\b.*|\{.*)$/, '');
+      const forMatch = /^(.*?)\s+for\s+(.+)$/.exec(header);
+      const selfType = (forMatch ? forMatch[2] : header).replace(/<.*$/, '').trim();
+      if (!selfType) return null;
+      return { kind: 'impl', name: forMatch ? `${forMatch[1].trim()} for ${selfType}` : selfType, owner: selfType };
+    }
+
+    case 'typescript': {
+      const match = TS_DECLARATION.exec(text);
+      if (match) {
+        return { kind: match[1].replace('*', ''), name: match[2] };
+      }
+      if (!member) return null;
+      const method = TS_MEMBER.exec(text);
+      return method && !CONTROL_KEYWORDS.has(method[1]) ? { kind: 'method', name: method[1] } : null;
+    }
+
+    case 'go': {
+      const match = GO_DECLARATION.exec(text);
+      if (!match) return null;
+      const [, keyword, receiver, name] = match;
+      if (receiver) return { kind: 'method', name: `${receiver}.${name}` };
+      return { kind: keyword === 'func' ? 'func' : 'type', name };
+    }
+
+    case 'python': {
+      const match = PYTHON_DECLARATION.exec(text);
+      return match ? { kind: match[1], name: match[2] } : null;
+    }
+  }
+}
+
+// Walks the whole source once, so literals and comments spanning lines are
+// followed: TypeScript template literals with nested interpolations, Rust raw strings
+// (r#"..."#) and nested block comments, Go raw strings, Python triple-quoted strings.
+// Escapes are honored in every literal but the raw ones.
+function scanSource(lines: string[], language: Language): SourceScan {
+  const source = lines.join('\n');
+  const out: string[] = [];
+  const depthAt: number[] = [0];
+  const inCodeAt: boolean[] = [true];
+  const interpolations: number[] = [];  // Bracket depth at each open ${ of a template literal
+  let depth = 0;
+  let recent = '';  // The last code characters, whitespace collapsed, to tell regexes from divisions
+  let i = 0;
+
+  const emit = (c: string, code: boolean): void => {
+    out.push(c === '\n' ? c : code ? c : ' ');
+    if (c === '\n') {
+      depthAt.push(depth);
+      inCodeAt.push(code && interpolations.length === 0);
+    }
+    if (code) recent = (recent + (/\s/.test(c) ? (recent.endsWith(' ') ? '' : ' ') : c)).slice(-16);
+  };
+  // A literal from i to end (exclusive): its quotes are kept, its contents (and the braces
+  // around template interpolations) blanked
+  const literal = (end: number, open: number, close: number): void => {
+    for (let j = i; j < end; j++) {
+      const delimiter = (j < i + open || j >= end - close) && !OPENING_BRACKETS.includes(source[j]) && !CLOSING_BRACKETS.includes(source[j]);
+      out.push(source[j] === '\n' ? '\n' : delimiter ? source[j] : ' ');
+      if (source[j] === '\n') {
+        depthAt.push(depth);
+        inCodeAt.push(false);
+      }
+    }
+    recent = (recent + '"').slice(-16);
+    i = end;
+  };
+  const comment = (end: number): void => {
+    for (; i < end; i++) emit(source[i], false);
+  };
+  // Index after the closing quote, from the first character of the contents
+  const quoted = (from: number, quote: string, multiline: boolean, escapes: boolean = true): number => {
+    let j = from;
+    while (j < source.length && !source.startsWith(quote, j)) {
+      if (source[j] === '\\' && escapes) j++;
+      else if (source[j] === '\n' && !multiline) return j;
+      j++;
+    }
+    return Math.min(j + quote.length, source.length);
+  };
+  // A template literal's text from i (the backtick or the } of an interpolation) up to and
+  // including the closing backtick or the next ${
+  const templateText = (): void => {
+    let j = i + 1;
+    while (j < source.length && source[j] !== '`' && !source.startsWith('${', j)) j += source[j] === '\\' ? 2 : 1;
+    const opens = source.startsWith('${', j);
+    literal(Math.min(j + (opens ? 2 : 1), source.length), 1, opens ? 2 : 1);
+    if (opens) {
+      interpolations.push(depth);
+      recent += '{';
+    }
+  };
+
+  while (i < source.length) {
+    const c = source[i];
+    const next = source[i + 1];
+    const triple = source.slice(i, i + 3);
+    const rawString = language === 'rust' && (c === 'r' || c === 'b') && !/\w/.test(source[i - 1] || '')
+      ? /^b?r(#*)"/.exec(source.slice(i, i + 260))
+      : null;
+
+    if (language === 'python' ? c === '#' : c === '/' && next === '/') {
+      const end = source.indexOf('\n', i);
+      comment(end === -1 ? source.length : end);
+    } else if (c === '/' && next === '*' && language !== 'python') {
+      // Rust block comments nest, the others end at the first */
+      let j = i + 2;
+      for (let nesting = 1; j < source.length && nesting > 0;) {
+        if (source.startsWith('*/', j)) {
+          nesting--;
+          j += 2;
+        } else if (language === 'rust' && source.startsWith('/*', j)) {
+          nesting++;
+          j += 2;
+        } else {
+          j++;
+        }
+      }
+      comment(Math.min(j, source.length));
+    } else if (language === 'typescript' && c === '`') {
+      templateText();
+    } else if (language === 'typescript' && c === '}' && interpolations.length > 0 && interpolations[interpolations.length - 1] === depth) {
+      interpolations.pop();
+      templateText();
+    } else if (language === 'typescript' && c === '/' && REGEX_PRECEDER.test(recent.trimEnd())) {
+      let j = i + 1;
+      let inClass = false;
+      while (j < source.length && source[j] !== '\n' && (source[j] !== '/' || inClass)) {
+        if (source[j] === '\\') j++;
+        else if (source[j] === '[') inClass = true;
+        else if (source[j] === ']') inClass = false;
+        j++;
+      }
+      literal(Math.min(j + 1, source.length), 1, 1);
+    } else if (rawString) {
+      // Raw strings have no escapes; r#"..."# ends at a quote followed by as many hashes
+      const hashes = rawString[1];
+      literal(quoted(i + rawString[0].length, `"${hashes}`, true, false), rawString[0].length, hashes.length + 1);
+    } else if (language === 'rust' && c === '\'') {
+      // A char literal, otherwise a lifetime
+      const char = /^'(?:\\u\{[0-9a-fA-F]+\}|\\.|[^\\'])'/.exec(source.slice(i, i + 12));
+      if (char) literal(i + char[0].length, 1, 1);
+      else emit(source[i++], true);
+    } else if (language === 'python' && (triple === '"""' || triple === '\'\'\'')) {
+      literal(quoted(i + 3, triple, true), 3, 3);
+    } else if (c === '"' || c === '\'' || (language === 'go' && c === '`')) {
+      // Rust strings and Go raw strings span lines; Go raw strings have no escapes
+      const raw = language === 'go' && c === '`';
+      literal(quoted(i + 1, c, raw || (language === 'rust' && c === '"'), !raw), 1, 1);
+    } else {
+      if (OPENING_BRACKETS.includes(c)) depth++;
+      if (CLOSING_BRACKETS.includes(c)) depth = Math.max(0, depth - 1);
+      emit(c, true);
+      i++;
+    }
+  }
+
+  const code = out.join('').split('\n');
+  return { code, depth: depthAt.slice(0, code.length), inCode: inCodeAt.slice(0, code.length) };
+}
+
+// Where the declaration starting on line `start` ends: its body is the first brace outside
+// brackets (its parameter list, generics in parentheses, default values), and ends where
+// that brace is closed; without a body it ends at a semicolon outside brackets or at a line
+// the next one doesn't continue
+function declarationExtent(scan: SourceScan, start: number, to: number, language: Language): Extent {
+  let depth = 0;
+  let openLine = -1;
+  for (let line = start; line < to; line++) {
+    const code = scan.code[line];
+    for (let c = 0; c < code.length; c++) {
+      const char = code[c];
+      if (char === '{' && depth === 0 && openLine === -1 && !(language === 'typescript' && TYPE_BRACE_PRECEDER.test(code.slice(0, c)))) {
+        openLine = line;
+      }
+      if (OPENING_BRACKETS.includes(char)) {
+        depth++;
+      } else if (CLOSING_BRACKETS.includes(char)) {
+        depth = Math.max(0, depth - 1);
+        if (depth === 0 && openLine !== -1) return { openLine, end: line };
+      } else if (char === ';' && depth === 0 && openLine === -1) {
+        return { openLine, end: line };
+      }
+    }
+    if (openLine === -1 && depth === 0) {
+      const nextLine = scan.code.slice(line + 1, to).find(text => text.trim());
+      const continued = CONTINUED_LINE.test(code.trimEnd())
+        || (nextLine !== undefined && CONTINUATION_START.test(nextLine.trim()));
+      if (!continued) return { openLine, end: line };
+    }
+  }
+  return { openLine, end: to - 1 };
+}
+
+function isLeadingLine(line: string, language: Language): boolean {
+  const text = line.trim();
+  if (!text) return false;
+  if (language === 'python') return text.startsWith('#') || text.startsWith('@');
+  return text.startsWith('//') || text.startsWith('/*') || text.startsWith('*')
+    || text.startsWith('@') || (language === 'rust' && text.startsWith('#['));
+}
+
+// Walks back over doc comments, attributes and decorators directly above a declaration,
+// including decorators whose arguments span lines, as in `@Component({ ... })`
+function leadingStart(lines: string[], scan: SourceScan, start: number, floor: number, language: Language): number {
+  let lead = start;
+  while (lead > floor) {
+    let above = lead - 1;
+    while (above > floor && scan.depth[above] > scan.depth[start]) above--;
+    if (!isLeadingLine(lines[above], language)) break;
+    lead = above;
+  }
+  return lead;
+}
+
+function indentation(line: string): number {
+  const whitespace = /^[ \t]*/.exec(line);
+  return whitespace ? whitespace[0].replace(/\t/g, '    ').length : 0;
+}
+
+class ChunkBuilder {
+  readonly chunks: CodeChunk[] = [];
+
+  constructor(private readonly lines: string[]) {}
+
+  // Adds lines [start, end] as a chunk unless they are all blank
+  add(start: number, end: number, symbol: string, kind: string): void {
+    while (start <= end && !this.lines[start].trim()) start++;
+    while (end >= start && !this.lines[end].trim()) end--;
+    if (start > end) return;
+    this.chunks.push({
+      symbol,
+      kind,
+      startLine: start + 1,
+      endLine: end + 1,
+      text: this.lines.slice(start, end + 1).join('\n'),
+    });
+  }
+}
+
+function qualify(parent: string | undefined, name: string, language: Language): string {
+  if (!parent) return name;
+  return `${parent}${language === 'rust' ? '::' : '.'}${name}`;
+}
+
+// Brace languages: declarations outside brackets in [from, to) become chunks, code between
+// them becomes chunks named after the enclosing symbol.
+function scanBraces(
+  builder: ChunkBuilder,
+  lines: string[],
+  scan: SourceScan,
+  from: number,
+  to: number,
+  language: Language,
+  maxLines: number,
+  parent?: string
+): void {
+  const glueSymbol = parent || MODULE_SYMBOL;
+  const glueKind = parent ? 'body' : 'module';
+  const baseDepth = scan.depth[from];
+  let glueStart = from;
+  let i = from;
+
+  while (i < to) {
+    const declaration = scan.inCode[i] && scan.depth[i] === baseDepth
+      ? matchDeclaration(scan.code[i], language, parent !== undefined)
+      : null;
+    if (!declaration) {
+      i++;
+      continue;
+    }
+
+    const { openLine, end } = declarationExtent(scan, i, to, language);
+    if (openLine === -1 && end === i) {
+      // Single statement such as `type Id = u64;`: keep it with the surrounding code
+      i++;
+      continue;
+    }
+
+    const lead = leadingStart(lines, scan, i, glueStart, language);
+    builder.add(glueStart, lead - 1, glueSymbol, glueKind);
+
+    const symbol = qualify(parent, declaration.name, language);
+    if (CONTAINER_KINDS.has(declaration.kind) && openLine !== -1 && end - lead + 1 > maxLines && end - openLine > 1) {
+      builder.add(lead, openLine, symbol, declaration.kind);
+      scanBraces(builder, lines, scan, openLine + 1, end, language, maxLines, parent ? symbol : (declaration.owner || symbol));
+    } else {
+      builder.add(lead, end, symbol, declaration.kind);
+    }
+
+    i = end + 1;
+    glueStart = i;
+  }
+  builder.add(glueStart, to - 1, glueSymbol, glueKind);
+}
+
+// Python: a def/class block ends at the next code line outside brackets indented no deeper than it
+function scanIndented(
+  builder: ChunkBuilder,
+  lines: string[],
+  scan: SourceScan,
+  from: number,
+  to: number,
+  maxLines: number,
+  parent?: string
+): void {
+  const glueSymbol = parent || MODULE_SYMBOL;
+  const glueKind = parent ? 'body' : 'module';
+  const isCode = (index: number): boolean => scan.inCode[index] && scan.depth[index] === 0 && scan.code[index].trim() !== '';
+
+  let baseIndent = -1;
+  for (let i = from; i < to; i++) {
+    if (isCode(i)) {
+      baseIndent = indentation(lines[i]);
+      break;
+    }
+  }
+  if (baseIndent === -1) {
+    builder.add(from, to - 1, glueSymbol, glueKind);
+    return;
+  }
+
+  let glueStart = from;
+  let i = from;
+  while (i < to) {
+    const declaration = isCode(i) && indentation(lines[i]) === baseIndent
+      ? matchDeclaration(scan.code[i], 'python', parent !== undefined)
+      : null;
+    if (!declaration) {
+      i++;
+      continue;
+    }
+
+    let end = i;
+    for (let j = i + 1; j < to; j++) {
+      if (isCode(j) && indentation(lines[j]) <= baseIndent) break;
+      if (lines[j].trim()) end = j;
+    }
+
+    const lead = leadingStart(lines, scan, i, glueStart, 'python');
+    builder.add(glueStart, lead - 1, glueSymbol, glueKind);
+
+    const symbol = qualify(parent, declaration.name, 'python');
+    if (declaration.kind === 'class' && end - lead + 1 > maxLines && end > i) {
+      builder.add(lead, i, symbol, declaration.kind);
+      scanIndented(builder, lines, scan, i + 1, end + 1, maxLines, symbol);
+    } else {
+      builder.add(lead, end, symbol, declaration.kind);
+    }
+
+    i = end + 1;
+    glueStart = i;
+  }
+  builder.add(glueStart, to - 1, glueSymbol, glueKind);
+}
+
+// Heuristic splitter: recognizes declarations by their header line, and finds where they end
+// by matching brackets (or, in Python, by indentation) over the source with its comments and
+// literals blanked. Declarations nested in other code stay with it, two declarations on one
+// line are not split, and TypeScript generics (`<...>`) are not counted as brackets.
+// Returns an empty list for unsupported file types.
+export function chunkCode(filePath: string, source: string, options: CodeChunkOptions = {}): CodeChunk[] {
+  const language = LANGUAGES[path.extname(filePath).toLowerCase()];
+  if (!language) return [];
+
+  const maxLines = options.maxLines || DEFAULT_MAX_LINES;
+  const lines = source.split(/\r?\n/);
+  const scan = scanSource(lines, language);
+  const builder = new ChunkBuilder(lines);
+  if (language === 'python') {
+    scanIndented(builder, lines, scan, 0, lines.length, maxLines);
+  } else {
+    scanBraces(builder, lines, scan, 0, lines.length, language, maxLines);
+  }
+  return builder.chunks;
+}
@@ -0,0 +1,251 @@
+// compare.ts - Side-by-side answer diffs for the same questions against two stores or configurations
+import { FileSearchClient } from './client.js';
+import { extractCitations } from './citations.js';
+import { parseFilterExpression } from './search.js';
+import { Semaphore } from './pipeline.js';
+import { toTokenUsage } from './cost.js';
+import { loadEvalDataset } from './eval.js';
+import { ReportFormat } from './progress.js';
+
+// Types
+export interface CompareSide {
+  label: string;  // Shown in the report, e.g. the profile and stores
+  client: FileSearchClient;
+  stores: string[];
+  model?: string;
+}
+
+export interface CompareOptions {
+  concurrency?: number;  // Questions in flight at once; each asks both sides
+  threshold?: number;  // Answers less similar than this diverge (default: 0.6)
+  onResult?: (result: QuestionComparison, completed: number) => void;
+}
+
+export interface SideAnswer {
+  answer?: string;
+  sources: string[];  // Cited files, in footnote order
+  latencyMs: number;
+  totalTokens: number;
+  error?: string;
+}
+
+export type DiffOp = 'same' | 'removed' | 'added';
+
+export interface DiffSegment {
+  op: DiffOp;  // removed: only in A, added: only in B
+  text: string;
+}
+
+export interface QuestionComparison {
+  id: string | number;
+  question: string;
+  a: SideAnswer;
+  b: SideAnswer;
+  similarity: number;  // Shared words over all words of both answers, 0-1
+  sourcesOnlyA: string[];
+  sourcesOnlyB: string[];
+  diverges: boolean;
+  diff: DiffSegment[];
+}
+
+export interface ComparisonReport {
+  dataset: string;
+  a: { label: string; stores: string[]; model: string };
+  b: { label: string; stores: string[]; model: string };
+  threshold: number;
+  total: number;
+  divergent: number;
+  errors: number;  // Questions that failed on either side
+  meanSimilarity?: number;
+  results: QuestionComparison[];
+}
+
+export const DEFAULT_DIVERGENCE_THRESHOLD = 0.6;
+
+// Width of each column in the side-by-side table
+const COLUMN_WIDTH = 60;
+
+// Word-level diff over the longest common subsequence, merged into runs of the same op
+export function diffWords(a: string, b: string): DiffSegment[] {
+  const wordsA = a.split(/\s+/).filter(Boolean);
+  const wordsB = b.split(/\s+/).filter(Boolean);
+  const lengths: number[][] = Array.from({ length: wordsA.length + 1 }, () => new Array<number>(wordsB.length + 1).fill(0));
+  for (let i = wordsA.length - 1; i >= 0; i--) {
+    for (let j = wordsB.length - 1; j >= 0; j--) {
+      lengths[i][j] = wordsA[i] === wordsB[j] ? lengths[i + 1][j + 1] + 1 : Math.max(lengths[i + 1][j], lengths[i][j + 1]);
+    }
+  }
+
+  const segments: DiffSegment[] = [];
+  const push = (op: DiffOp, word: string) => {
+    const last = segments[segments.length - 1];
+    if (last?.op === op) {
+      last.text += ` ${word}`;
+    } else {
+      segments.push({ op, text: word });
+    }
+  };
+  let i = 0;
+  let j = 0;
+  while (i < wordsA.length || j < wordsB.length) {
+    if (i < wordsA.length && j < wordsB.length && wordsA[i] === wordsB[j]) {
+      push('same', wordsA[i]);
+      i++;
+      j++;
+    } else if (j >= wordsB.length || (i < wordsA.length && lengths[i + 1][j] >= lengths[i][j + 1])) {
+      push('removed', wordsA[i++]);
+    } else {
+      push('added', wordsB[j++]);
+    }
+  }
+  return segments;
+}
+
+export function diffSimilarity(diff: DiffSegment[]): number {
+  const count = (op: DiffOp) => diff.filter(segment => segment.op === op).reduce((sum, segment) => sum + segment.text.split(' ').length, 0);
+  const same = count('same');
+  const total = 2 * same + count('removed') + count('added');
+  return total > 0 ? Math.round((2 * same / total) * 1000) / 1000 : 1;
+}
+
+async function ask(side: CompareSide, question: string, metadataFilter: string | undefined): Promise<SideAnswer> {
+  const startedAt = Date.now();
+  try {
+    const response = await side.client.query(side.stores, question, { model: side.model, metadataFilter });
+    const answer = response.text || '';
+    return {
+      answer,
+      sources: [...new Set(extractCitations(response.candidates?.[0]?.groundingMetadata, answer).map(citation => citation.fileName))],
+      latencyMs: Date.now() - startedAt,
+      totalTokens: toTokenUsage(response.usageMetadata)?.totalTokens || 0,
+    };
+  } catch (error) {
+    return { sources: [], latencyMs: Date.now() - startedAt, totalTokens: 0, error: (error as Error).message };
+  }
+}
+
+// Asks every question of the dataset of both sides and compares the answers and
+// their citations. Answers diverge when either side failed, their words differ more
+// than the threshold allows or they cite different files.
+export async function compareAnswers(
+  a: CompareSide,
+  b: CompareSide,
+  datasetPath: string,
+  options: CompareOptions = {}
+): Promise<ComparisonReport> {
+  const cases = loadEvalDataset(datasetPath);
+  const threshold = options.threshold ?? DEFAULT_DIVERGENCE_THRESHOLD;
+  const semaphore = new Semaphore(options.concurrency || 5);
+  const results: QuestionComparison[] = new Array(cases.length);
+  let completed = 0;
+
+  await Promise.all(cases.map(async (evalCase, i) => {
+    await semaphore.acquire();
+    try {
+      const metadataFilter = evalCase.filter ? parseFilterExpression(evalCase.filter) : undefined;
+      const [answerA, answerB] = await Promise.all([
+        ask(a, evalCase.question, metadataFilter),
+        ask(b, evalCase.question, metadataFilter),
+      ]);
+      const diff = diffWords(answerA.answer || '', answerB.answer || '');
+      const similarity = diffSimilarity(diff);
+      const sourcesOnlyA = answerA.sources.filter(source => !answerB.sources.includes(source));
+      const sourcesOnlyB = answerB.sources.filter(source => !answerA.sources.includes(source));
+      results[i] = {
+        id: evalCase.id ?? i + 1,
+        question: evalCase.question,
+        a: answerA,
+        b: answerB,
+        similarity,
+        sourcesOnlyA,
+        sourcesOnlyB,
+        diverges: Boolean(answerA.error || answerB.error) || similarity < threshold || sourcesOnlyA.length > 0 || sourcesOnlyB.length > 0,
+        diff,
+      };
+    } finally {
+      semaphore.release();
+    }
+    options.onResult?.(results[i], ++completed);
+  }));
+
+  const answered = results.filter(result => !result.a.error && !result.b.error);
+  return {
+    dataset: datasetPath,
+    a: { label: a.label, stores: a.stores, model: a.model || a.client.model },
+    b: { label: b.label, stores: b.stores, model: b.model || b.client.model },
+    threshold,
+    total: results.length,
+    divergent: results.filter(result => result.diverges).length,
+    errors: results.length - answered.length,
+    meanSimilarity: answered.length > 0
+      ? Math.round((answered.reduce((sum, result) => sum + result.similarity, 0) / answered.length) * 1000) / 1000
+      : undefined,
+    results,
+  };
+}
+
+// Greedy word wrap; words longer than the width are split
+function wrap(text: string, width: number): string[] {
+  const lines: string[] = [];
+  let line = '';
+  for (let word of text.split(/\s+/).filter(Boolean)) {
+    while (word.length > width) {
+      if (line) lines.push(line);
+      lines.push(word.slice(0, width));
+      word = word.slice(width);
+      line = '';
+    }
+    if (!line) {
+      line = word;
+    } else if (line.length + 1 + word.length <= width) {
+      line += ` ${word}`;
+    } else {
+      lines.push(line);
+      line = word;
+    }
+  }
+  if (line) lines.push(line);
+  return lines;
+}
+
+// One side of the diff, with the words the other side lacks marked [-...-] (A) or {+...+} (B)
+function renderSide(diff: DiffSegment[], op: 'removed' | 'added'): string {
+  return diff
+    .filter(segment => segment.op === 'same' || segment.op === op)
+    .map(segment => segment.op === 'same' ? segment.text : op === 'removed' ? `[-${segment.text}-]` : `{+${segment.text}+}`)
+    .join(' ');
+}
+
+function sideBySide(left: string[], right: string[]): string[] {
+  return Array.from({ length: Math.max(left.length, right.length) }, (_, i) =>
+    `   ${(left[i] || '').padEnd(COLUMN_WIDTH)} │ ${right[i] || ''}`.trimEnd());
+}
+
+export function formatComparison(report: ComparisonReport, reportFormat: ReportFormat = 'table', divergentOnly = false): string {
+  const results = divergentOnly ? report.results.filter(result => result.diverges) : report.results;
+  if (reportFormat === 'json') {
+    return JSON.stringify({ ...report, results }, null, 2);
+  }
+
+  const describe = (side: ComparisonReport['a']) => `${side.label} (${side.model})`;
+  const lines = [
+    `🔀 Comparison of A: ${describe(report.a)} and B: ${describe(report.b)}: ${report.total} questions`,
+    `   Divergent:  ${report.divergent}`,
+    `   Errors:     ${report.errors}`,
+    `   Similarity: ${report.meanSimilarity !== undefined ? report.meanSimilarity.toFixed(3) : 'n/a'} mean, divergent below ${report.threshold}`,
+  ];
+  for (const result of results) {
+    lines.push('', `${result.diverges ? '≠' : '='} ${result.id}: ${result.question} (similarity ${result.similarity.toFixed(3)})`);
+    lines.push(...sideBySide([`A: ${report.a.label}`], [`B: ${report.b.label}`]));
+    lines.push(...sideBySide(
+      result.a.error ? [`❌ ${result.a.error}`] : wrap(renderSide(result.diff, 'removed'), COLUMN_WIDTH),
+      result.b.error ? [`❌ ${result.b.error}`] : wrap(renderSide(result.diff, 'added'), COLUMN_WIDTH)
+    ));
+    lines.push(...sideBySide(
+      wrap(`Sources: ${result.a.sources.map(source => result.sourcesOnlyA.includes(source) ? `[-${source}-]` : source).join(', ') || '(none)'}`, COLUMN_WIDTH),
+      wrap(`Sources: ${result.b.sources.map(source => result.sourcesOnlyB.includes(source) ? `{+${source}+}` : source).join(', ') || '(none)'}`, COLUMN_WIDTH)
+    ));
+    lines.push(...sideBySide([`${result.a.latencyMs}ms, ${result.a.totalTokens} tokens`], [`${result.b.latencyMs}ms, ${result.b.totalTokens} tokens`]));
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,789 @@
+// completions.ts - Shell completion scripts and man page generated from the command-line flag specs
+import { AUTH_METHODS } from './auth.js';
+import { BACKENDS } from './backend.js';
+import { CREDENTIAL_BACKENDS } from './credentials.js';
+import { DEDUP_MODES } from './dedup.js';
+import { PREFLIGHT_MODES } from './preflight.js';
+import { REDACTION_POLICIES } from './redact.js';
+import { STORAGE_TIERS } from './capacity.js';
+import { ROUTING_MODES } from './routing.js';
+import { SAFETY_THRESHOLDS } from './generation.js';
+import { ANSWER_MATCHES } from './eval.js';
+import { OUTPUT_FORMATS } from './output.js';
+import { REPORT_FORMATS } from './progress.js';
+import { CI_REPORT_FORMATS } from './ci-report.js';
+import { listTransforms } from './postprocess.js';
+import { REVIEW_FORMATS } from './review.js';
+import { SCAFFOLD_LANGUAGES } from './plugins.js';
+
+// Types
+export type Shell = 'bash' | 'zsh' | 'fish' | 'powershell';
+
+export interface OptionSpec {
+  flag: string;
+  value?: string;  // Placeholder shown in help; options without one are boolean switches
+  choices?: readonly string[];
+  file?: boolean;  // Complete the value as a path
+  description: string;
+}
+
+export interface CommandSpec {
+  name: string;  // Installed binary name
+  summary: string;
+  subcommands?: { name: string; summary: string }[];
+  positional?: { name: string; file?: boolean };  // Argument after the subcommand, if any
+  options: OptionSpec[];
+}
+
+export const SHELLS: Shell[] = ['bash', 'zsh', 'fish', 'powershell'];
+
+const COMMON_OPTIONS: OptionSpec[] = [
+  { flag: '--config', value: 'file', file: true, description: 'Settings file (default: gemini-fs.toml in the working directory)' },
+  { flag: '--profile', value: 'name', description: 'Profile section of the settings file' },
+  { flag: '--tenant', value: 'name', description: 'Namespace of store names and local state (default: GEMINI_FS_TENANT)' },
+  { flag: '--allow-cross-tenant', description: 'Allow stores of other tenants, or of none' },
+  { flag: '--api-key', value: 'key', description: 'API key (prefer gemini-fs-auth login, flags end up in shell history)' },
+  { flag: '--backend', value: 'kind', choices: BACKENDS, description: 'Gemini API or Vertex AI RAG Engine' },
+  { flag: '--vertex-project', value: 'id', description: 'Google Cloud project for the Vertex AI backend' },
+  { flag: '--vertex-location', value: 'region', description: 'Region for the Vertex AI backend' },
+  { flag: '--api-version', value: 'version', description: 'API version of the requests, e.g. v1 (default: v1beta on the Gemini API)' },
+  { flag: '--header', value: 'header', description: 'Extra request header as "Name: value", repeatable' },
+  { flag: '--otlp-endpoint', value: 'url', description: 'Export traces and metrics to an OTLP/HTTP collector' },
+  { flag: '--proxy', value: 'url', description: 'HTTP(S) proxy for every request (default: HTTPS_PROXY)' },
+  { flag: '--ca-bundle', value: 'file', file: true, description: 'PEM certificates to trust as well, e.g. an intercepting proxy\'s CA' },
+  { flag: '--native-certs', description: 'Also trust the operating system certificate store' },
+  { flag: '--acl-mode', description: 'Refuse queries that name no access labels' },
+  { flag: '--acl-labels', value: 'label,...', description: 'Access labels the queries are scoped to' },
+  { flag: '--verbose', description: 'Log debug details, including every HTTP request' },
+  { flag: '--quiet', description: 'Log errors only' },
+  { flag: '--log-file', value: 'file', file: true, description: 'Append every log line as JSON, API keys redacted' },
+  { flag: '--record', value: 'dir', file: true, description: 'Write every HTTP interaction to fixtures, API keys redacted' },
+  { flag: '--replay', value: 'dir', file: true, description: 'Answer HTTP requests from recorded fixtures, without the network' },
+];
+
+const NOTIFY_OPTIONS: OptionSpec[] = [
+  { flag: '--notify-command', value: 'cmd', description: 'Run a shell command when a document finishes indexing' },
+  { flag: '--notify-webhook', value: 'url', description: 'POST a JSON event when a document finishes indexing' },
+];
+
+const QUOTA_OPTIONS: OptionSpec[] = [
+  { flag: '--requests-per-day', value: 'n', description: 'Daily request budget; work pauses until the reset once it is used up' },
+  { flag: '--tokens-per-day', value: 'n', description: 'Daily token budget for indexing and answers' },
+];
+
+const STORE_OPTIONS: OptionSpec[] = [
+  { flag: '--store', value: 'storeName', description: 'Store to search (fileSearchStores/...); may be repeated' },
+  { flag: '--project', value: 'projectId', description: 'Registered project whose store is searched' },
+];
+
+const GROUNDING_OPTIONS: OptionSpec[] = [
+  { flag: '--model', value: 'model', description: 'Generation model' },
+  { flag: '--grounded-only', description: 'Answer only from the documents' },
+  { flag: '--min-confidence', value: '0-1', description: 'Confidence below which answers are marked uncertain, or rejected with --grounded-only' },
+  { flag: '--system', value: 'instruction', description: 'System instruction replacing the stored one, or @<file>' },
+];
+
+const ANSWER_LANGUAGE_OPTIONS: OptionSpec[] = [
+  { flag: '--answer-lang', value: 'code', description: 'Language of the answers (e.g. ja), whatever the documents are in' },
+  { flag: '--translate-snippets', description: 'Also translate the cited chunks to the answer language' },
+];
+
+const CITE_CONTEXT_OPTIONS: OptionSpec[] = [
+  { flag: '--cite-context', value: 'lines', description: 'Show cited chunks with this many surrounding lines from their local files' },
+  { flag: '--source-root', value: 'dir', file: true, description: 'Where the ingested files are, to locate chunks; may be repeated (default: .)' },
+];
+
+const GENERATION_OPTIONS: OptionSpec[] = [
+  { flag: '--temperature', value: '0-2', description: 'Sampling temperature' },
+  { flag: '--top-p', value: '0-1', description: 'Nucleus sampling probability mass' },
+  { flag: '--max-output-tokens', value: 'n', description: 'Longest answer in tokens' },
+  { flag: '--safety', value: '[category=]threshold', description: `Safety threshold (${SAFETY_THRESHOLDS.join(', ')}); may be repeated` },
+];
+
+// Keep in sync with the flags each binary reads; the usage() of each bin/ module is the reference
+export const COMMANDS: CommandSpec[] = [
+  {
+    name: 'gemini-fs-mcp',
+    summary: 'MCP server exposing File Search tools over stdio',
+    options: [
+      ...COMMON_OPTIONS,
+      { flag: '--auth', value: 'method', choices: AUTH_METHODS, description: 'Credentials to use' },
+      { flag: '--credentials', value: 'file', file: true, description: 'Service account key file for --auth service-account' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      { flag: '--default-store', value: 'storeName', description: 'Store used when a tool call names none' },
+      { flag: '--concurrency', value: 'n', description: 'Files uploaded at once' },
+      { flag: '--requests-per-minute', value: 'n', description: 'Upload rate limit' },
+      { flag: '--max-attempts', value: 'n', description: 'Attempts per request, including the first' },
+      { flag: '--budget', value: 'tokens', description: 'Token budget for indexing per upload' },
+      { flag: '--extract-locally', description: 'Extract PDF, DOCX and XLSX text before uploading' },
+      { flag: '--strip-notebook-outputs', description: 'Leave cell outputs out of converted notebooks' },
+      { flag: '--strip-quoted-replies', description: 'Leave quoted replies out of converted e-mails' },
+      { flag: '--no-normalize-encoding', description: 'Upload text in legacy charsets as it is instead of as UTF-8' },
+      { flag: '--no-detect-language', description: 'Leave out the detected lang and code_lang metadata' },
+      { flag: '--no-file-attributes', description: 'Leave out the path, size, mtime, owner and depth metadata' },
+      { flag: '--routing', value: 'mode', choices: ROUTING_MODES, description: 'Route searches over several stores to the relevant ones' },
+      { flag: '--with-summaries', description: 'Upload a one-page summary next to each large file' },
+      { flag: '--summary-min-size', value: 'bytes', description: 'Smallest file that gets a summary (default: 204800)' },
+      { flag: '--part-size', value: 'bytes', description: 'Largest text document before it is uploaded in parts (default: 100MB)' },
+      { flag: '--mime-map', value: 'file', file: true, description: 'Extra extension to MIME type mappings' },
+      { flag: '--dedup', value: 'mode', choices: DEDUP_MODES, description: 'What to do with duplicate files' },
+      { flag: '--dedup-distance', value: 'bits', description: 'Simhash distance for near-duplicates' },
+      { flag: '--redaction-policy', value: 'policy', choices: REDACTION_POLICIES, description: 'What to do with files containing secrets or PII' },
+      { flag: '--redaction-rules', value: 'file', file: true, description: 'Extra redaction rules (TOML); may be repeated' },
+      { flag: '--preflight', value: 'mode', choices: PREFLIGHT_MODES, description: 'What to do with files over the File Search limits' },
+      { flag: '--max-store-documents', value: 'n', description: 'Document limit per store checked by preflight' },
+      { flag: '--on-error', value: 'policy', description: 'Whether failed files stop uploads and syncs: continue (default), fail-fast or abort-after:<n>' },
+      { flag: '--storage-tier', value: 'tier', choices: STORAGE_TIERS, description: 'Usage tier for the storage limit in usage reports' },
+      { flag: '--grounded-only', description: 'Answer only from the documents' },
+      { flag: '--min-confidence', value: '0-1', description: 'Confidence below which answers are marked uncertain, or rejected with --grounded-only' },
+      ...ANSWER_LANGUAGE_OPTIONS,
+      ...GENERATION_OPTIONS,
+      { flag: '--query-cache-ttl', value: 'seconds', description: 'Lifetime of cached answers' },
+      { flag: '--no-cache', description: 'Disable the query cache' },
+      { flag: '--no-history', description: 'Do not record the query in the query history' },
+      ...QUOTA_OPTIONS,
+      ...NOTIFY_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-chat',
+    summary: 'Interactive chat grounded in a File Search store',
+    options: [
+      ...STORE_OPTIONS,
+      ...GROUNDING_OPTIONS,
+      ...ANSWER_LANGUAGE_OPTIONS,
+      ...CITE_CONTEXT_OPTIONS,
+      { flag: '--render', description: 'Render the Markdown of replies on a terminal' },
+      ...GENERATION_OPTIONS,
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-tui',
+    summary: 'Terminal UI: pick stores, fuzzy-find documents, ask with streaming answers',
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--filter', value: 'expression', description: 'Metadata filter of every question' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      { flag: '--grounded-only', description: 'Answer only from the documents' },
+      { flag: '--system', value: 'instruction', description: 'System instruction replacing the stored one, or @<file>' },
+      { flag: '--answer-lang', value: 'code', description: 'Language of the answers (e.g. ja), whatever the documents are in' },
+      ...CITE_CONTEXT_OPTIONS,
+      ...GENERATION_OPTIONS,
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-query',
+    summary: 'Ask one question, optionally rendered from a prompt template',
+    positional: { name: 'question' },
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--template', value: 'file', file: true, description: 'Prompt template rendered into the question' },
+      { flag: '--var', value: 'name=value', description: 'Template variable; may be repeated' },
+      { flag: '--filter', value: 'expression', description: 'Metadata filter, e.g. "lang=rust AND team=backend"' },
+      { flag: '--format', value: 'format', choices: OUTPUT_FORMATS, description: 'Output format (default: text)' },
+      { flag: '--json-schema', value: 'file', file: true, description: 'Answer as JSON validated against this schema' },
+      { flag: '--render', description: 'Render the Markdown of text answers on a terminal' },
+      { flag: '--questions', description: 'Treat every argument as a separate question' },
+      { flag: '--shared-retrieval', description: 'Answer all --questions from one retrieval pass' },
+      { flag: '--routing', value: 'mode', choices: ROUTING_MODES, description: 'Route the question to the relevant stores first' },
+      { flag: '--post', value: 'transform', choices: listTransforms().map(t => t.name), description: 'Answer transform; may be repeated' },
+      { flag: '--export-provenance', value: 'file', file: true, description: 'Write the provenance graph as JSON, or DOT for .dot/.gv' },
+      { flag: '--out', value: 'file', file: true, description: 'Stream the answer into a file, renamed into place when complete, with a .meta.json sidecar' },
+      ...CITE_CONTEXT_OPTIONS,
+      { flag: '--provenance-schema', description: 'Print the JSON Schema of the provenance graph' },
+      ...GROUNDING_OPTIONS,
+      ...ANSWER_LANGUAGE_OPTIONS,
+      ...GENERATION_OPTIONS,
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-retrieve',
+    summary: 'Print the chunks a query retrieves, without generating an answer',
+    positional: { name: 'query' },
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--top-k', value: 'n', description: 'Chunks to retrieve, 1-100 (default: 10)' },
+      { flag: '--filter', value: 'expression', description: 'Metadata filter, e.g. "lang=rust AND team=backend"' },
+      { flag: '--format', value: 'format', choices: ['text', 'json'], description: 'Output format (default: text)' },
+      { flag: '--model', value: 'model', description: 'Model that runs the file search (Gemini API only)' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-embed',
+    summary: 'Embed the text of a directory into a local vector file',
+    positional: { name: 'dir', file: true },
+    options: [
+      { flag: '--out', value: 'file', file: true, description: 'Vector file to write: .jsonl, or .fvecs with a .meta.jsonl beside it' },
+      { flag: '--include', value: 'glob', description: 'Only embed matching files; may be repeated' },
+      { flag: '--exclude', value: 'glob', description: 'Skip matching files; may be repeated' },
+      { flag: '--metadata', value: 'key=value', description: 'Metadata stored with every passage; may be repeated' },
+      { flag: '--model', value: 'model', description: 'Embedding model (default: gemini-embedding-001)' },
+      { flag: '--dimensions', value: 'n', description: 'Truncate the vectors to n dimensions' },
+      { flag: '--passage-size', value: 'chars', description: 'Characters per passage (default: 2000)' },
+      { flag: '--code-chunking', description: 'Embed each function/class/impl as its own document' },
+      { flag: '--strip-notebook-outputs', description: 'Leave cell outputs out of converted notebooks' },
+      { flag: '--strip-quoted-replies', description: 'Leave quoted replies out of converted e-mails' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-local-search',
+    summary: 'Search a local vector file, optionally fused with File Search retrieval',
+    positional: { name: 'vectors', file: true },
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--top-k', value: 'n', description: 'Passages to print, 1-100 (default: 10)' },
+      { flag: '--exact', description: 'Compare every vector, also in large files' },
+      { flag: '--candidates', value: 'n', description: 'Vectors compared after the signature pass in large files' },
+      { flag: '--query-vector', value: 'file', file: true, description: 'JSON array to search with instead of embedding a query' },
+      { flag: '--format', value: 'format', choices: ['text', 'json'], description: 'Output format (default: text)' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-explain',
+    summary: 'Explain a file section by section, citing the related documents of its store',
+    positional: { name: 'path', file: true },
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--root', value: 'dir', file: true, description: 'Directory display names are relative to (default: the project directory or the working directory)' },
+      { flag: '--no-upload', description: 'Do not upload the file when the store does not have it' },
+      { flag: '--section-lines', value: 'n', description: 'Target section length in lines (default: 150)' },
+      { flag: '--max-sections', value: 'n', description: 'Most sections a long file is split into (default: 20)' },
+      { flag: '--concurrency', value: 'n', description: 'Section queries in flight at once (default: 3)' },
+      { flag: '--filter', value: 'expression', description: 'Metadata filter for the related documents' },
+      { flag: '--format', value: 'format', choices: ['markdown', 'json'], description: 'Output format (default: markdown)' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-review',
+    summary: 'Review a patch or a git range, with comments keyed to file and line citing the store',
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--diff', value: 'file', file: true, description: 'Unified diff to review; - reads it from stdin' },
+      { flag: '--git-range', value: 'range', description: 'Review git diff <range>, e.g. main..HEAD' },
+      { flag: '--repo', value: 'dir', file: true, description: 'Repository of --git-range (default: the project directory or the working directory)' },
+      { flag: '--template', value: 'file', file: true, description: 'Prompt template for each chunk of the diff' },
+      { flag: '--var', value: 'name=value', description: 'Template variable, e.g. focus=error handling' },
+      { flag: '--filter', value: 'expression', description: 'Metadata filter for the documents the review relies on' },
+      { flag: '--format', value: 'format', choices: REVIEW_FORMATS, description: 'Output format (default: markdown)' },
+      { flag: '--max-chunk-lines', value: 'n', description: 'Diff lines sent per query (default: 400)' },
+      { flag: '--concurrency', value: 'n', description: 'Queries in flight at once (default: 3)' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-eval',
+    summary: 'Score retrieval and answer quality against a labelled dataset',
+    positional: { name: 'dataset.jsonl', file: true },
+    options: [
+      ...STORE_OPTIONS,
+      { flag: '--match', value: 'mode', choices: ANSWER_MATCHES, description: 'Default answer comparison (default: exact)' },
+      { flag: '--threshold', value: '0-1', description: 'Minimum similarity for embedding matches' },
+      { flag: '--concurrency', value: 'n', description: 'Questions in flight at once' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      { flag: '--format', value: 'format', choices: REPORT_FORMATS, description: 'Scorecard format (default: table)' },
+      { flag: '--out', value: 'file', file: true, description: 'Also write the scorecard as JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-bench',
+    summary: 'Measure upload throughput and query latency',
+    options: [
+      { flag: '--dir', value: 'dir', file: true, description: 'Directory uploaded into a scratch store per run' },
+      { flag: '--question', value: 'question', description: 'Question whose latency is measured; may be repeated' },
+      ...STORE_OPTIONS,
+      { flag: '--concurrency', value: 'n,...', description: 'Upload concurrency levels (default: 1,5,10)' },
+      { flag: '--chunking', value: 'tokens/overlap', description: 'Chunking variant uploaded; may be repeated' },
+      { flag: '--runs', value: 'n', description: 'Times each question is asked (default: 10)' },
+      { flag: '--query-concurrency', value: 'n', description: 'Queries in flight at once (default: 1)' },
+      { flag: '--warmup', value: 'n', description: 'Unmeasured queries first (default: 1)' },
+      { flag: '--model', value: 'model', description: 'Generation model' },
+      { flag: '--keep-stores', description: 'Do not delete the scratch stores' },
+      { flag: '--format', value: 'format', choices: REPORT_FORMATS, description: 'Report format (default: json)' },
+      { flag: '--out', value: 'file', file: true, description: 'Also write the report as JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-history',
+    summary: 'List, show and replay recorded queries',
+    subcommands: [
+      { name: 'list', summary: 'List recorded queries, newest first' },
+      { name: 'show', summary: 'Show one recorded query' },
+      { name: 'replay', summary: 'Ask a recorded query again and compare the answer' },
+    ],
+    options: [
+      { flag: '--limit', value: 'n', description: 'Queries listed (default: 20)' },
+      { flag: '--store', value: 'storeName', description: 'Only list queries of this store; for replay, stores searched instead; may be repeated' },
+      { flag: '--since', value: 'age', description: 'Only list queries recorded within this age, e.g. 7d' },
+      { flag: '--search', value: 'text', description: 'Only list queries whose question contains this text' },
+      { flag: '--model', value: 'model', description: 'Model the replay uses instead' },
+      { flag: '--json', description: 'Print JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-compare',
+    summary: 'Diff the answers of two stores or config profiles',
+    options: [
+      { flag: '--questions', value: 'file', file: true, description: 'JSONL questions asked of both sides' },
+      { flag: '--store-a', value: 'storeName', description: 'Store of side A; may be repeated' },
+      { flag: '--store-b', value: 'storeName', description: 'Store of side B; may be repeated' },
+      { flag: '--profile-a', value: 'name', description: 'Config profile of side A' },
+      { flag: '--profile-b', value: 'name', description: 'Config profile of side B' },
+      { flag: '--model-a', value: 'model', description: 'Generation model of side A' },
+      { flag: '--model-b', value: 'model', description: 'Generation model of side B' },
+      { flag: '--threshold', value: '0-1', description: 'Word similarity below which answers diverge (default: 0.6)' },
+      { flag: '--concurrency', value: 'n', description: 'Questions in flight at once (default: 5)' },
+      { flag: '--divergent-only', description: 'Only print the questions whose answers diverge' },
+      { flag: '--format', value: 'format', choices: REPORT_FORMATS, description: 'Report format (default: table)' },
+      { flag: '--out', value: 'file', file: true, description: 'Also write the report as JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-docs',
+    summary: 'List, inspect, delete, copy and verify the documents in a store',
+    subcommands: [
+      { name: 'list', summary: 'List the documents of a store' },
+      { name: 'info', summary: 'Show one document' },
+      { name: 'delete', summary: 'Delete one document, or every document matching the criteria' },
+      { name: 'copy', summary: 'Copy or move the matching documents to another store from their local files' },
+      { name: 'usage', summary: 'Report store usage against the File Search limits' },
+      { name: 'stats', summary: 'Report MIME types, sizes, metadata keys and top terms of a store' },
+      { name: 'verify', summary: 'Check the sha256 metadata of the documents against their local files' },
+    ],
+    options: [
+      { flag: '--store', value: 'storeName', description: 'Store to list, report or verify (default: default_store; usage: every store)' },
+      { flag: '--top', value: 'n', description: 'Top terms reported by stats (default: 20)' },
+      { flag: '--state', value: 'state', choices: ['active', 'pending', 'failed'], description: 'Only list or delete documents in this state' },
+      { flag: '--filter', value: 'expression', description: 'Delete the documents whose metadata matches, e.g. "metadata.team=frontend"' },
+      { flag: '--older-than', value: 'age', description: 'Delete the documents created longer ago, e.g. 30d' },
+      { flag: '--from', value: 'storeName', description: 'Store copy takes the documents from' },
+      { flag: '--to', value: 'storeName', description: 'Store copy uploads the documents into' },
+      { flag: '--source-root', value: 'dir', file: true, description: 'Directory copy and verify look for the source files in; may be repeated' },
+      { flag: '--move', description: 'Delete the copied documents from the source store' },
+      { flag: '--page-size', value: 'n', description: 'Documents requested per page when listing' },
+      { flag: '--limit', value: 'n', description: 'Stop listing after this many documents' },
+      { flag: '--page-token', value: 'token', description: 'Continue a listing where an earlier one stopped' },
+      { flag: '--parts', description: 'List the parts of files uploaded in parts one by one' },
+      { flag: '--dry-run', description: 'Show the documents a bulk delete would remove' },
+      { flag: '--yes', description: 'Delete without the confirmation prompt' },
+      { flag: '--tier', value: 'tier', choices: STORAGE_TIERS, description: 'Usage tier that sets the storage limit (default: free)' },
+      { flag: '--max-store-documents', value: 'n', description: 'Document limit per store (default: 10000)' },
+      { flag: '--json', description: 'Print JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-archive',
+    summary: 'Export a store to an archive or restore one into a new store',
+    subcommands: [
+      { name: 'export', summary: 'Write a store to a .tar.gz file or directory' },
+      { name: 'import', summary: 'Restore an archive into a new store' },
+    ],
+    options: [
+      { flag: '--store', value: 'storeName', description: 'Store to export' },
+      { flag: '--project', value: 'projectId', description: 'Project whose store is exported' },
+      { flag: '--out', value: 'file', file: true, description: 'Archive file or directory to write' },
+      { flag: '--include-content', description: 'Include the source files in the archive' },
+      { flag: '--source', value: 'dir', file: true, description: 'Directory the source files are read from' },
+      { flag: '--from-archive', value: 'file', file: true, description: 'Archive file or directory to restore' },
+      { flag: '--name', value: 'displayName', description: 'Display name of the restored store' },
+      { flag: '--path', value: 'dir', file: true, description: 'Project directory to register the restored store for' },
+      { flag: '--report', value: 'format', description: `Ingestion report format (${REPORT_FORMATS.join(', ')}), or <format>:<file> for a CI report (${CI_REPORT_FORMATS.join(', ')}); may be repeated` },
+      { flag: '--report-file', value: 'file', file: true, description: 'Write the ingestion report to a file' },
+      ...QUOTA_OPTIONS,
+      ...NOTIFY_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-config',
+    summary: 'Scaffold and inspect gemini-fs.toml settings',
+    subcommands: [
+      { name: 'init', summary: 'Write a commented gemini-fs.toml' },
+      { name: 'show', summary: 'Print the resolved settings' },
+    ],
+    options: [
+      { flag: '--path', value: 'file', file: true, description: 'File to write (default: gemini-fs.toml)' },
+      { flag: '--force', description: 'Overwrite an existing file' },
+      { flag: '--config', value: 'file', file: true, description: 'Settings file to show' },
+      { flag: '--profile', value: 'name', description: 'Profile section of the settings file' },
+    ],
+  },
+  {
+    name: 'gemini-fs-ingest',
+    summary: 'Upload web pages, sitemaps, a git repository at a ref, a Drive folder, a Cloud Storage prefix or a staged directory, wait for pending indexing, roll synced directories back and verify manifests',
+    subcommands: [
+      { name: 'url', summary: 'Fetch the URLs and upload their main content' },
+      { name: 'git', summary: 'Index a repository at a ref with commit metadata' },
+      { name: 'gdrive', summary: 'Upload the files of a Google Drive folder and its subfolders' },
+      { name: 'gcs', summary: 'Upload the objects below a Cloud Storage prefix (gs://bucket/prefix)' },
+      { name: 'stage', summary: 'Prepare a directory for upload without API access' },
+      { name: 'push', summary: 'Upload a staged directory' },
+      { name: 'wait', summary: 'Wait for uploads made with --no-wait to finish indexing' },
+      { name: 'retry-failed', summary: 'Upload the files whose upload or indexing failed again' },
+      { name: 'snapshots', summary: 'List the sync snapshots of a directory' },
+      { name: 'rollback', summary: 'Roll the store of a synced directory back to a snapshot' },
+      { name: 'manifest', summary: 'manifest verify: check the store against a stage or sync manifest' },
+    ],
+    options: [
+      { flag: '--store', value: 'storeName', description: 'Store to upload to' },
+      { flag: '--project', value: 'projectId', description: 'Registered project whose store is used' },
+      { flag: '--sitemap', description: 'Treat the URLs as sitemaps and upload every page they list' },
+      { flag: '--max-pages', value: 'n', description: 'Maximum pages taken from the sitemaps (default: 500)' },
+      { flag: '--metadata', value: 'key=value', description: 'Custom metadata for every page; may be repeated' },
+      { flag: '--acl', value: 'label,...', description: 'Access labels stored as the acl metadata list' },
+      { flag: '--concurrency', value: 'n', description: 'Pages fetched and uploaded at a time (default: 3)' },
+      { flag: '--keep-old', description: 'Keep earlier documents of the same page' },
+      { flag: '--ref', value: 'ref', description: 'Branch, tag or commit to index (default: HEAD)' },
+      { flag: '--since', value: 'ref', description: 'Only re-index files changed since this ref' },
+      { flag: '--include', value: 'glob', description: 'Only index matching files; may be repeated' },
+      { flag: '--exclude', value: 'glob', description: 'Skip matching files; may be repeated' },
+      { flag: '--source-credentials', value: 'key.json', file: true, description: 'Service account key gdrive and gcs read with (default: credentials_file, else ADC)' },
+      { flag: '--anonymous', description: 'Read a public bucket with gcs without credentials' },
+      { flag: '--report', value: 'format:file', description: `Write per-file results for CI (${CI_REPORT_FORMATS.join(', ')}); may be repeated` },
+      { flag: '--on-error', value: 'policy', description: 'Whether failed files stop the run: continue (default), fail-fast or abort-after:<n>' },
+      { flag: '--code-chunking', description: 'Upload each function/class/impl as its own document' },
+      { flag: '--row-chunking', description: 'Upload each row of CSV and TSV files as its own document' },
+      { flag: '--rows-per-document', value: 'n', description: 'Rows per document with --row-chunking (default: 1)' },
+      { flag: '--metadata-columns', value: 'name,...', description: 'Columns stored as metadata with --row-chunking (default: the first 10)' },
+      { flag: '--with-summaries', description: 'Upload a one-page summary next to each large file' },
+      { flag: '--strip-notebook-outputs', description: 'Leave cell outputs out of converted notebooks' },
+      { flag: '--strip-quoted-replies', description: 'Leave quoted replies out of converted e-mails' },
+      { flag: '--no-normalize-encoding', description: 'Upload text in legacy charsets as it is instead of as UTF-8' },
+      { flag: '--no-detect-language', description: 'Leave out the detected lang and code_lang metadata' },
+      { flag: '--no-file-attributes', description: 'Leave out the path, size, mtime, owner and depth metadata' },
+      { flag: '--redaction-policy', value: 'policy', choices: REDACTION_POLICIES, description: 'What to do with files containing secrets or PII' },
+      { flag: '--redaction-rules', value: 'file', file: true, description: 'Extra redaction rules (TOML); may be repeated' },
+      { flag: '--wait', description: 'Poll every upload until it is indexed (default)' },
+      { flag: '--no-wait', description: 'Return once the uploads are accepted; check on them with wait' },
+      { flag: '--timeout', value: 'seconds', description: 'How long wait polls before giving up (default: 600)' },
+      { flag: '--to', value: 'snapshot', description: 'Snapshot to roll back to' },
+      { flag: '--dry-run', description: 'Show what a rollback would restore and delete' },
+      { flag: '--list', description: 'Show the quarantined files and their errors instead of retrying them' },
+      { flag: '--clear', description: 'Drop the quarantined files without retrying them' },
+      { flag: '--mime-map', value: 'file', file: true, description: 'Extension to MIME type mappings for the retry' },
+      { flag: '--mime-type', value: 'type', description: 'Upload every retried file as this MIME type' },
+      { flag: '--extract-locally', description: 'Extract PDF, DOCX and XLSX text before retrying' },
+      { flag: '--no-extract-locally', description: 'Retry without local extraction even if extract_locally is set' },
+      { flag: '--json', description: 'Print snapshots, quarantined files or the manifest check as JSON' },
+      ...QUOTA_OPTIONS,
+      ...NOTIFY_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-shards',
+    summary: 'Create, fill, list and delete corpora sharded across several stores',
+    subcommands: [
+      { name: 'create', summary: 'Create the shard stores of a corpus' },
+      { name: 'upload', summary: 'Upload a directory, each file to the shard of its path' },
+      { name: 'list', summary: 'List the sharded corpora and their stores' },
+      { name: 'delete', summary: 'Delete the shard stores of a corpus' },
+    ],
+    options: [
+      { flag: '--count', value: 'n', description: 'Number of shards, or auto to size them from the directory (default: auto)' },
+      { flag: '--concurrency', value: 'n', description: 'Stores created or files uploaded at a time' },
+      { flag: '--include', value: 'glob', description: 'Only upload matching files; may be repeated' },
+      { flag: '--exclude', value: 'glob', description: 'Skip matching files; may be repeated' },
+      { flag: '--metadata', value: 'key=value', description: 'Custom metadata for every document; may be repeated' },
+      { flag: '--acl', value: 'label,...', description: 'Access labels stored as the acl metadata list' },
+      { flag: '--code-chunking', description: 'Upload each function/class/impl as its own document' },
+      { flag: '--row-chunking', description: 'Upload each row of CSV and TSV files as its own document' },
+      { flag: '--max-store-documents', value: 'n', description: 'Document limit per shard for --count auto (default: 10000)' },
+      { flag: '--yes', description: 'Delete without the confirmation prompt' },
+      { flag: '--json', description: 'Print JSON' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-auth',
+    summary: 'Store the API key in the OS keychain or an encrypted file',
+    subcommands: [
+      { name: 'login', summary: 'Prompt for the API key and store it' },
+      { name: 'logout', summary: 'Remove the stored API key' },
+      { name: 'status', summary: 'Show which credentials the tools would use' },
+      { name: 'keys', summary: 'keys status: usage of each API key; keys reset: make them usable again' },
+    ],
+    options: [
+      { flag: '--storage', value: 'kind', choices: CREDENTIAL_BACKENDS, description: 'Where login stores the key (default: keychain when available)' },
+      { flag: '--config', value: 'file', file: true, description: 'Settings file' },
+      { flag: '--profile', value: 'name', description: 'Profile whose key is stored' },
+      { flag: '--json', description: 'keys status as JSON' },
+    ],
+  },
+  {
+    name: 'gemini-fs-mock',
+    summary: 'Run a mock Gemini API server for tests without credentials',
+    options: [
+      { flag: '--port', value: 'port', description: 'Port to listen on (default: a free port)' },
+      { flag: '--store', value: 'displayName', description: 'Create an empty store up front; may be repeated' },
+    ],
+  },
+  {
+    name: 'gemini-fs-serve',
+    summary: 'Serve queries, ingestion and the list of stores over a REST API',
+    options: [
+      { flag: '--port', value: 'port', description: 'Port to listen on (default: 8787)' },
+      { flag: '--host', value: 'address', description: 'Address to listen on (default: 127.0.0.1)' },
+      { flag: '--store', value: 'storeName', description: 'Store of requests that name none' },
+      { flag: '--ingest-root', value: 'dir', file: true, description: 'Directory POST /ingest may read from; may be repeated' },
+      { flag: '--model', value: 'model', description: 'Model answering queries' },
+      { flag: '--no-auth', description: 'Accept requests without an API key (loopback only)' },
+      ...QUOTA_OPTIONS,
+      ...COMMON_OPTIONS,
+    ],
+  },
+  {
+    name: 'gemini-fs-daemon',
+    summary: 'Share rate limits, daily quotas and 429 pauses between commands through a background daemon',
+    subcommands: [
+      { name: 'start', summary: 'Run the daemon' },
+      { name: 'status', summary: 'Show the connected commands, calls and quota usage' },
+      { name: 'stop', summary: 'Stop the daemon' },
+    ],
+    options: [
+      { flag: '--detach', description: 'Run the daemon in the background, with its output in daemon.log' },
+      { flag: '--requests-per-minute', value: 'n', description: 'Calls started per minute across all commands (default: requests_per_minute)' },
+      ...QUOTA_OPTIONS,
+      { flag: '--socket', value: 'path', file: true, description: 'Socket of the daemon (default: daemon.sock in the state directory)' },
+      { flag: '--json', description: 'Print the status as JSON' },
+      { flag: '--config', value: 'file', file: true, description: 'Settings file (default: gemini-fs.toml in the working directory)' },
+      { flag: '--profile', value: 'name', description: 'Profile section of the settings file' },
+    ],
+  },
+  {
+    name: 'gemini-fs-completions',
+    summary: 'Generate shell completions and the man page',
+    subcommands: [
+      ...SHELLS.map(shell => ({ name: shell, summary: `Completion script for ${shell}` })),
+      { name: 'man', summary: 'Man page in roff format' },
+    ],
+    options: [],
+  },
+  {
+    name: 'gemini-fs',
+    summary: 'Run a built-in command or a gemini-fs-<name> plugin',
+    subcommands: [
+      { name: 'plugin', summary: 'List the plugins on the PATH, or scaffold a new one' },
+    ],
+    options: [
+      { flag: '--config', value: 'file', file: true, description: 'Config file given to the command' },
+      { flag: '--profile', value: 'name', description: 'Config profile given to the command' },
+      { flag: '--tenant', value: 'name', description: 'Tenant given to the command' },
+      { flag: '--store', value: 'storeName', description: 'Store given to the command' },
+      { flag: '--dir', value: 'dir', file: true, description: 'Where plugin scaffold writes the plugin (default: .)' },
+      { flag: '--lang', value: 'language', choices: SCAFFOLD_LANGUAGES, description: 'Language of the scaffolded plugin (default: node)' },
+      { flag: '--json', description: 'Print JSON' },
+    ],
+  },
+];
+
+function functionName(command: CommandSpec): string {
+  return `_${command.name.replace(/-/g, '_')}`;
+}
+
+function valueOptions(command: CommandSpec): OptionSpec[] {
+  return command.options.filter(option => option.value);
+}
+
+function bashCompletion(commands: CommandSpec[]): string {
+  const functions = commands.map(command => {
+    const cases = valueOptions(command).map(option => {
+      const reply = option.choices
+        ? `COMPREPLY=($(compgen -W "${option.choices.join(' ')}" -- "$cur"))`
+        : option.file ? 'COMPREPLY=($(compgen -f -- "$cur"))' : 'COMPREPLY=()';
+      return `    ${option.flag}) ${reply}; return ;;`;
+    });
+    const subcommands = command.subcommands
+      ? [
+        '  if [[ $COMP_CWORD -eq 1 ]]; then',
+        `    COMPREPLY=($(compgen -W "${command.subcommands.map(s => s.name).join(' ')}" -- "$cur"))`,
+        '    return',
+        '  fi',
+      ]
+      : [];
+    const positional = command.positional?.file
+      ? ['  if [[ $cur != -* ]]; then', '    COMPREPLY=($(compgen -f -- "$cur"))', '    return', '  fi']
+      : [];
+    return [
+      `${functionName(command)}() {`,
+      '  local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"',
+      ...(cases.length > 0 ? ['  case "$prev" in', ...cases, '  esac'] : []),
+      ...subcommands,
+      ...positional,
+      `  COMPREPLY=($(compgen -W "${command.options.map(o => o.flag).join(' ')}" -- "$cur"))`,
+      '}',
+      `complete -F ${functionName(command)} ${command.name}`,
+    ].join('\n');
+  });
+  return ['# bash completion for gemini-fs commands', ...functions].join('\n\n') + '\n';
+}
+
+function zshEscape(text: string): string {
+  return text.replace(/'/g, "'\\''").replace(/([[\]:])/g, '\\$1');
+}
+
+function zshCompletion(commands: CommandSpec[]): string {
+  const functions = commands.map(command => {
+    const specs = command.options.map(option => {
+      const action = option.choices ? `(${option.choices.join(' ')})` : option.file ? '_files' : '';
+      return `'${option.flag}[${zshEscape(option.description)}]${option.value ? `:${zshEscape(option.value)}:${action}` : ''}'`;
+    });
+    if (command.subcommands) {
+      const names = command.subcommands.map(s => `${s.name}\\:${zshEscape(`"${s.summary}"`)}`).join(' ');
+      specs.unshift(`'1:command:((${names}))'`);
+    } else if (command.positional) {
+      specs.unshift(`'1:${zshEscape(command.positional.name)}:${command.positional.file ? '_files' : ''}'`);
+    }
+    return [`${functionName(command)}() {`, '  _arguments \\', ...specs.map((spec, i) => `    ${spec}${i < specs.length - 1 ? ' \\' : ''}`), '}'].join('\n');
+  });
+  return [
+    `#compdef ${commands.map(command => command.name).join(' ')}`,
+    ...functions,
+    ['case $service in', ...commands.map(command => `  ${command.name}) ${functionName(command)} "$@" ;;`), 'esac'].join('\n'),
+  ].join('\n\n') + '\n';
+}
+
+function fishEscape(text: string): string {
+  return `'${text.replace(/\\/g, '\\\\').replace(/'/g, "\\'")}'`;
+}
+
+function fishCompletion(commands: CommandSpec[]): string {
+  const blocks = commands.map(command => {
+    const lines = command.positional?.file ? [] : [`complete -c ${command.name} -f`];
+    for (const subcommand of command.subcommands || []) {
+      lines.push(`complete -c ${command.name} -n __fish_use_subcommand -a ${subcommand.name} -d ${fishEscape(subcommand.summary)}`);
+    }
+    for (const option of command.options) {
+      const value = option.choices ? ` -x -a ${fishEscape(option.choices.join(' '))}` : option.value ? (option.file ? ' -r -F' : ' -x') : '';
+      lines.push(`complete -c ${command.name} -l ${option.flag.slice(2)}${value} -d ${fishEscape(option.description)}`);
+    }
+    return lines.join('\n');
+  });
+  return ['# fish completion for gemini-fs commands', ...blocks].join('\n\n') + '\n';
+}
+
+function powershellList(values: readonly string[]): string {
+  return `@(${values.map(value => `'${value.replace(/'/g, "''")}'`).join(', ')})`;
+}
+
+function powershellCompletion(commands: CommandSpec[]): string {
+  const blocks = commands.map(command => {
+    const cases = valueOptions(command).map(option => `        '${option.flag}' { ${option.choices ? powershellList(option.choices) : '@()'} }`);
+    const options = powershellList(command.options.map(o => o.flag));
+    const fallback = command.subcommands
+      ? `if ($words.Count -le 1 -or ($words.Count -eq 2 -and $wordToComplete)) { ${powershellList(command.subcommands.map(s => s.name))} } else { ${options} }`
+      : options;
+    return [
+      `Register-ArgumentCompleter -Native -CommandName '${command.name}' -ScriptBlock {`,
+      '    param($wordToComplete, $commandAst, $cursorPosition)',
+      '    $words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })',
+      '    $previous = if ($wordToComplete) { $words[-2] } else { $words[-1] }',
+      '    $candidates = switch ($previous) {',
+      ...cases,
+      `        default { ${fallback} }`,
+      '    }',
+      '    $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {',
+      "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)",
+      '    }',
+      '}',
+    ].join('\n');
+  });
+  return ['# PowerShell completion for gemini-fs commands', ...blocks].join('\n\n') + '\n';
+}
+
+export function generateCompletion(shell: Shell, commands: CommandSpec[] = COMMANDS): string {
+  switch (shell) {
+    case 'bash':
+      return bashCompletion(commands);
+    case 'zsh':
+      return zshCompletion(commands);
+    case 'fish':
+      return fishCompletion(commands);
+    case 'powershell':
+      return powershellCompletion(commands);
+  }
+}
+
+function roff(text: string): string {
+  return text.replace(/\\/g, '\\e').replace(/-/g, '\\-').replace(/^([.'])/, '\\&$1');
+}
+
+export function generateManPage(version: string, commands: CommandSpec[] = COMMANDS, date: Date = new Date()): string {
+  const lines = [
+    `.TH GEMINI\\-FS 1 "${date.toISOString().slice(0, 10)}" "gemini-fs ${roff(version)}" "User Commands"`,
+    '.SH NAME',
+    'gemini\\-fs \\- Gemini File Search MCP server and command-line tools',
+    '.SH SYNOPSIS',
+  ];
+  for (const [index, command] of commands.entries()) {
+    if (index > 0) lines.push('.br');
+    const subcommand = command.subcommands ? ` \\fI${command.subcommands.map(s => s.name).join('|')}\\fR` : '';
+    const positional = command.positional ? ` \\fI${roff(command.positional.name)}\\fR` : '';
+    const options = command.options.length > 0 ? ' [\\fIoptions\\fR]' : '';
+    lines.push(`.B ${roff(command.name)}`, `${subcommand}${positional}${options}`.trim());
+  }
+  lines.push(
+    '.SH DESCRIPTION',
+    'Index local files into Gemini File Search stores and answer questions grounded in them, from an MCP client or the terminal.',
+    'Settings are read from \\fBgemini\\-fs.toml\\fR; command-line flags override them.'
+  );
+  for (const command of commands) {
+    lines.push(`.SH ${roff(command.name.toUpperCase())}`, roff(command.summary));
+    if (command.subcommands) {
+      lines.push('.SS Commands');
+      for (const subcommand of command.subcommands) {
+        lines.push('.TP', `.B ${roff(subcommand.name)}`, roff(subcommand.summary));
+      }
+    }
+    if (command.options.length > 0) {
+      lines.push('.SS Options');
+      for (const option of command.options) {
+        const value = option.choices ? option.choices.join('|') : option.value;
+        lines.push('.TP', value ? `.BI "${roff(option.flag)} " ${roff(value)}` : `.B ${roff(option.flag)}`, roff(option.description));
+      }
+    }
+  }
+  lines.push(
+    '.SH ENVIRONMENT',
+    '.TP', '.B GEMINI_API_KEY', 'API key for the Gemini API',
+    '.TP', '.B GEMINI_API_KEYS', 'Further comma\\-separated API keys, used in turn when one runs out of quota',
+    '.TP', '.B GEMINI_FS_CONFIG', 'Settings file, as \\fB\\-\\-config\\fR',
+    '.TP', '.B GEMINI_FS_PROFILE', 'Settings profile, as \\fB\\-\\-profile\\fR',
+    '.TP', '.B GEMINI_FS_TENANT', 'Tenant, as \\fB\\-\\-tenant\\fR',
+    '.TP', '.B GEMINI_FS_HOME', 'Root of the per\\-tenant state directories (default: ~/.gemini\\-fs if it exists, else $XDG_STATE_HOME/gemini\\-fs)',
+    '.TP', '.B GEMINI_BACKEND', 'Backend, as \\fB\\-\\-backend\\fR',
+    '.SH FILES',
+    '.TP', '.I gemini\\-fs.toml', 'Settings, read from the working directory',
+    '.TP', '.I projects.json', 'Registered projects and their stores',
+    '.SH SEE ALSO',
+    'README_MCP.md for the MCP tools and every setting.'
+  );
+  return lines.join('\n') + '\n';
+}
@@ -0,0 +1,32 @@
+// config.test.ts - Settings read from gemini-fs.toml, at the bounds of their ranges
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { loadSettings } from './config.js';
+import { ConfigError } from './errors.js';
+
+// Settings of a gemini-fs.toml holding text
+function settingsOf(t: TestContext, text: string): ReturnType<typeof loadSettings> {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'config-test-'));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  const file = path.join(dir, 'gemini-fs.toml');
+  fs.writeFileSync(file, text);
+  return loadSettings(file);
+}
+
+test('min_grounding_confidence accepts 0 and 1 and refuses what is outside them', t => {
+  assert.equal(settingsOf(t, 'min_grounding_confidence = 0\n').minGroundingConfidence, 0);
+  assert.equal(settingsOf(t, 'min_grounding_confidence = 0.0\n').minGroundingConfidence, 0);
+  assert.equal(settingsOf(t, 'min_grounding_confidence = 0.35\n').minGroundingConfidence, 0.35);
+  assert.equal(settingsOf(t, 'min_grounding_confidence = 1\n').minGroundingConfidence, 1);
+  assert.equal(settingsOf(t, '').minGroundingConfidence, undefined);
+  for (const value of ['-0.1', '1.5', 'nan', '"0.5"']) {
+    assert.throws(() => settingsOf(t, `min_grounding_confidence = ${value}\n`), (error: unknown) => {
+      assert.ok(error instanceof ConfigError);
+      assert.equal(error.message, 'min_grounding_confidence must be between 0 and 1');
+      return true;
+    });
+  }
+});
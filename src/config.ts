@@ -0,0 +1,581 @@
+// config.ts - gemini-fs.toml settings with named profiles
+import * as fs from 'fs';
+import * as path from 'path';
+import { TomlTable, isTable, parseToml } from './toml.js';
+import { ChunkingPolicy } from './chunking.js';
+import { ConfigError } from './errors.js';
+import { AUTH_METHODS, AuthMethod } from './auth.js';
+import { BACKENDS, BackendKind, parseHeadersConfig } from './backend.js';
+import { DEDUP_MODES, DedupMode } from './dedup.js';
+import { PREFLIGHT_MODES, PreflightMode } from './preflight.js';
+import { REDACTION_POLICIES, RedactionPolicy } from './redact.js';
+import { STORAGE_TIERS, StorageTier } from './capacity.js';
+import { DEFAULT_ROUTING_CONFIG, RoutingConfig, parseRoutingConfig } from './routing.js';
+import { GenerationParams, parseGenerationConfig } from './generation.js';
+import { DEFAULT_QUOTA_CONFIG, QuotaConfig, parseQuotaConfig } from './quota.js';
+import { LOG_LEVELS, LogLevel } from './log.js';
+import { parseAclLabels } from './acl.js';
+import { Timeouts, parseTimeoutsConfig } from './timeouts.js';
+import { TransformRule, parseTransformRules } from './transforms.js';
+import { DEFAULT_PROMPT_CONFIG, PromptConfig, parsePromptConfig } from './prompts.js';
+import { MAX_CONTEXT_LINES } from './cite-context.js';
+import { FAILURE_POLICIES, FailurePolicy, parseFailurePolicy } from './pipeline.js';
+
+// Types
+export interface Settings {
+  apiKeyEnv: string;  // Name of the environment variable holding the API key
+  apiKeyEnvs?: string[];  // Variables holding further keys, used in turn on quota or auth errors
+  authMethod?: AuthMethod;
+  credentialsFile?: string;  // Resolved against the config file's directory
+  backend?: BackendKind;
+  vertexProject?: string;
+  vertexLocation?: string;
+  apiVersion?: string;  // Pinned API version path, e.g. "v1"; --api-version overrides it
+  headers: { [name: string]: string };  // Extra headers of every API request; --header adds to them
+  otlpEndpoint?: string;  // OTLP/HTTP collector for traces and metrics
+  proxy?: string;  // HTTP(S) proxy for every request; HTTPS_PROXY is used when unset
+  caBundle?: string;  // PEM certificates to trust as well; resolved against the config file's directory
+  nativeCerts?: boolean;  // Also trust the operating system's certificate store
+  aclMode?: boolean;  // Refuse queries that pass no access labels
+  aclLabels?: string[];  // Access labels of queries that pass none
+  logLevel?: LogLevel;  // Lowest level shown on stderr
+  logFile?: string;  // JSON-lines log of every level; resolved against the config file's directory
+  notifyCommand?: string;  // Run when each document finishes indexing or fails
+  notifyWebhook?: string;  // POSTed the same events as JSON
+  defaultStore?: string;
+  model?: string;
+  concurrency?: number;
+  requestsPerMinute?: number;
+  maxAttempts?: number;
+  tokenBudget?: number;  // Maximum estimated indexing tokens per upload or sync
+  extractLocally?: boolean;
+  stripNotebookOutputs?: boolean;  // Leave cell outputs out of converted notebooks
+  stripQuotedReplies?: boolean;  // Leave quoted replies out of converted e-mails
+  normalizeEncoding?: boolean;  // Upload text in legacy charsets or with a BOM as UTF-8, default: true
+  detectLanguage?: boolean;  // Add lang and code_lang metadata to text files, default: true
+  fileAttributes?: boolean;  // Add path, size, mtime, owner and depth metadata and show them in citations, default: true
+  withSummaries?: boolean;  // Upload a one-page summary next to each large file
+  summaryMinSize?: number;  // Bytes
+  partSize?: number;  // Bytes; larger text documents are uploaded in parts, at most 100MB
+  onError?: FailurePolicy;  // Default: continue
+  dedup?: DedupMode;  // Off when omitted
+  dedupDistance?: number;  // Simhash bits, 0-64
+  redactionPolicy?: RedactionPolicy;  // Off when omitted
+  redactionRules?: string[];  // Rule files, resolved against the config file's directory
+  preflight?: PreflightMode;  // Default: skip
+  maxStoreDocuments?: number;
+  storageTier?: StorageTier;  // Sets the storage limit in usage reports, default: free
+  groundedOnly?: boolean;  // Answer only from the documents, see grounding.ts
+  minGroundingConfidence?: number;  // 0-1
+  citeContext?: number;  // Lines shown around each cited chunk from its local source file, see cite-context.ts
+  queryCache?: boolean;  // Default: true
+  queryCacheTtl?: number;  // Seconds
+  queryHistory?: boolean;  // Record queries for npm run history (default: true)
+  mimeMap?: string;  // Resolved against the config file's directory
+  chunking: ChunkingPolicy;
+  routing: RoutingConfig;
+  generation: GenerationParams;  // Defaults for queries; flags override single values
+  prompt: PromptConfig;  // System instruction and answer style of queries; --system overrides the instruction
+  quota: QuotaConfig;  // Daily budgets; --requests-per-day and --tokens-per-day override them
+  timeouts: Timeouts;  // Milliseconds, per operation class
+  transforms: TransformRule[];  // Document transforms per glob, from [[transforms]]
+  profile?: string;
+  source?: string;  // Config file the settings were read from
+}
+
+export const CONFIG_FILE_NAME = 'gemini-fs.toml';
+export const DEFAULT_API_KEY_ENV = 'GEMINI_API_KEY';
+
+export const CONFIG_TEMPLATE = `# gemini-fs.toml - Settings for the Gemini File Search server and CLI tools
+# Command-line flags and environment variables override these values.
+
+# Environment variable that holds the API key
+api_key_env = "GEMINI_API_KEY"
+
+# Further keys, tried in order when one runs out of quota or is refused (on top of
+# the comma-separated GEMINI_API_KEYS); usage: gemini-fs-auth keys status
+# api_key_envs = ["CLASS_KEY_2", "CLASS_KEY_3"]
+
+# Credentials: "api-key" (default), "adc" (Application Default Credentials,
+# e.g. gcloud auth application-default login) or "service-account"
+# auth = "api-key"
+# credentials_file = "service-account.json"
+
+# Retrieval backend: "gemini" (File Search stores, default) or "vertex"
+# (Vertex AI RAG Engine corpora; needs adc or service-account credentials)
+# backend = "vertex"
+# vertex_project = "my-gcp-project"
+# vertex_location = "us-central1"
+
+# Pin the API version in request paths ("v1beta" on the Gemini API and "v1" on
+# Vertex AI by default), e.g. when the default path changes under you; also
+# GEMINI_API_VERSION or --api-version
+# api_version = "v1"
+
+# model = "gemini-2.5-flash"
+# default_store = "fileSearchStores/your-store-id"
+
+# Uploads in flight at once, and an optional per-minute request limit
+concurrency = 5
+# requests_per_minute = 60
+
+# Attempts per API call for transient errors (429, 5xx)
+max_attempts = 5
+
+# Refuse uploads and syncs whose estimated indexing tokens exceed this
+# token_budget = 5000000
+
+# Convert PDF, DOCX and XLSX to text locally before upload
+extract_locally = false
+
+# Jupyter notebooks are always uploaded as Markdown; leave their cell outputs out
+# strip_notebook_outputs = false
+
+# .eml files are uploaded as text and .mbox files as one document per message, with
+# subject, sender, date and thread metadata; leave quoted replies out of the bodies
+# strip_quoted_replies = false
+
+# Text files with a BOM or in a legacy charset (Shift_JIS, EUC-JP, GB18030, Big5,
+# EUC-KR, Windows-1252, UTF-16) are uploaded as UTF-8; false uploads them as they are
+# normalize_encoding = true
+
+# Text files get "lang" (ISO 639-1, from the script and common words) and source
+# files "code_lang" (from the extension, "#!" line and content) metadata, so queries
+# can filter with lang = "ja"; metadata set explicitly wins over detected values
+# detect_language = true
+
+# Every file gets "path", "size" (bytes), "mtime" (seconds since the epoch), "owner"
+# and "depth" metadata, so queries can filter with mtime > 1735689600 and citations
+# show which of several files with the same name was cited
+# file_attributes = true
+
+# Ask the model for a one-page summary of every file of at least summary_min_size
+# bytes and upload it next to the file as "<path>#summary" with kind = "summary"
+# with_summaries = false
+# summary_min_size = 204800
+
+# Text documents larger than part_size bytes (at most and by default the 100MB upload
+# limit) are split at headings, symbols or paragraphs and uploaded as "<path>#part-N"
+# with part_of, part and part_count metadata; listings show them as one document
+# part_size = 104857600
+
+# Whether failed files stop an upload or sync: "continue" with the other files
+# (default), "fail-fast" at the first failure, or "abort-after:<n>" failures; files
+# in flight still finish, and the ones left are uploaded by the next run
+# on_error = "continue"
+
+# Detect files whose content (or near-identical content, by simhash) is already
+# in the store or the same upload under another name: "skip" them, "replace"
+# the old copy, or "alias" the file to the existing document
+# dedup = "skip"
+# dedup_distance = 3
+
+# Scan the text of every document for secrets (API keys, tokens, private keys,
+# passwords) and PII (emails, card numbers) before upload: "block" the files with
+# matches, "mask" the matches, or "tag" the documents with redaction_rules metadata;
+# redaction_rules adds TOML rule files, see redact.ts
+# redaction_policy = "block"
+# redaction_rules = ["redaction.toml"]
+
+# Check file sizes, types, PDF page counts and the store document limit before
+# uploading: "skip" the files that would be rejected (default), refuse the whole
+# upload ("strict"), or leave it to the API ("off")
+# preflight = "skip"
+# max_store_documents = 10000
+
+# Usage tier of the Google Cloud project ("free", "tier1", "tier2" or "tier3"),
+# which sets the total File Search storage that "npm run docs -- usage" compares against
+# storage_tier = "free"
+
+# Answer only from the documents: replies without grounding, or with a confidence
+# score below the threshold, are replaced by "no grounded answer". Without
+# grounded_only, answers below a threshold that is set are marked uncertain and
+# shown with the retrieved chunks
+# grounded_only = false
+# min_grounding_confidence = 0.5
+
+# Show each cited chunk with this many lines before and after it, from the file it
+# was ingested from (query --cite-context, chat)
+# cite_context = 5
+
+# Multi-tenant stores: documents uploaded with --acl carry access labels, and in
+# ACL mode every query must name the labels of its caller (acl_labels is used
+# when it names none); queries without labels are refused
+# acl_mode = false
+# acl_labels = "tenant-a"
+
+# Reuse answers to repeated questions while the stores are unchanged
+# query_cache = true
+# query_cache_ttl = 3600
+
+# Record every query (question, parameters, stores, answer hash, latency) for
+# npm run history list, show and replay
+# query_history = true
+
+# mime_map = "mime-map.toml"
+
+# Export traces and metrics to an OpenTelemetry collector (OTLP/HTTP)
+# otlp_endpoint = "http://localhost:4318"
+
+# Behind a corporate proxy: HTTPS_PROXY and NO_PROXY are used when proxy is unset.
+# A TLS-intercepting proxy re-signs traffic with its own CA; trust it with
+# ca_bundle (a PEM file) or, when it is installed system-wide, native_certs
+# proxy = "http://proxy.example.com:3128"
+# ca_bundle = "corp-ca.pem"
+# native_certs = false
+
+# Lowest level shown on stderr: "error", "warn", "info" (default) or "debug";
+# --quiet and --verbose override it. log_file gets every level as JSON lines,
+# with API keys redacted, e.g. to attach to a support ticket
+# log_level = "info"
+# log_file = "logs/gemini-fs.jsonl"
+
+# Run a command and/or POST a webhook when each document finishes indexing or
+# fails. The event is passed as JSON (stdin for the command) and, for the
+# command, as GEMINI_FS_EVENT, GEMINI_FS_FILE, GEMINI_FS_DOCUMENT, ... variables
+# notify_command = "./scripts/on-indexed.sh"
+# notify_webhook = "https://ci.example.com/hooks/gemini-fs"
+
+[chunking]
+# max_tokens_per_chunk = 400
+# max_overlap_tokens = 40
+
+[chunking.extensions]
+# ".rs" = { max_tokens_per_chunk = 200, max_overlap_tokens = 20 }
+
+# Sampling and safety settings of answers; --temperature, --top-p,
+# --max-output-tokens and --safety override them per query. safety is one
+# threshold for every category or a table, with thresholds "off", "block_none",
+# "block_only_high", "block_medium_and_above" or "block_low_and_above"
+# [generation]
+# temperature = 0.2
+# top_p = 0.95
+# max_output_tokens = 2048
+# safety = { harassment = "block_only_high", dangerous_content = "block_medium_and_above" }
+
+# System instruction and answer style applied to every query, chat and search, so
+# long prompts need not be passed each time; --system "<text>" or --system @file
+# replaces the instruction for one call. system_file is read instead of system;
+# citation_format is "footnotes" (default), "inline" (the answer names the file
+# after each statement) or "none". language takes a name or a code ("ja"), and
+# --answer-lang overrides it; answers that come back in another language are
+# translated, and translate_snippets translates the cited chunks too. A
+# [[prompt.stores]] entry replaces any of these values for queries that search its store
+# [prompt]
+# system_file = "prompts/handbook.md"
+# tone = "concise and friendly"
+# language = "Japanese"
+# citation_format = "footnotes"
+# translate_snippets = false
+#
+# [[prompt.stores]]
+# store = "fileSearchStores/billing-docs"
+# system = "You answer billing questions for support agents. Quote amounts exactly."
+# tone = "formal"
+
+# Daily budgets, counted in .gemini-quota.json. Once one is used up, work pauses
+# until the day resets in time_zone (on_exhausted = "wait", default) or stops
+# with an error ("fail"). Uploads count their estimated indexing tokens and
+# queries their prompt and answer tokens.
+# [quota]
+# requests_per_day = 1000
+# tokens_per_day = 5000000
+# on_exhausted = "wait"
+# time_zone = "America/Los_Angeles"
+
+# How long each kind of request may take, as "500ms", "45s", "2m" or "1h": opening
+# a connection, each request carrying upload bytes, each generation (a streamed
+# answer until its last chunk) and waiting for indexing (default: 10 minutes), so
+# large uploads are not cut short by a limit meant for hung queries
+# [timeouts]
+# connect = "10s"
+# upload = "120s"
+# query = "60s"
+# operation = "30m"
+
+# Headers added to every API request, e.g. to bill another project or route through
+# a gateway; --header "Name: value" adds more. Credentials cannot be replaced here
+# [headers]
+# x-goog-user-project = "billing-project"
+# x-goog-request-params = "team=search"
+
+# Rewrite the text of matching files before upload (and before redaction), in
+# rule order: "front-matter" moves YAML or TOML front matter into metadata,
+# "strip-comments" removes code comments, "line-numbers" prefixes each line with
+# its number so answers can cite lines, and "minify" removes insignificant
+# whitespace. Patterns match as --include globs do; every matching rule applies
+# [[transforms]]
+# pattern = "docs/**/*.md"
+# apply = ["front-matter"]
+#
+# [[transforms]]
+# pattern = "*.{ts,rs,py}"
+# apply = ["strip-comments", "line-numbers"]
+
+# Route questions that name several stores to the relevant ones before searching:
+# "keywords" (the rules below), "model" (a classifier call with the project
+# descriptions), "auto" (rules first, then the classifier) or "off" (default)
+# [routing]
+# mode = "auto"
+# max_stores = 2
+# model = "gemini-2.5-flash-lite"
+#
+# [[routing.rules]]
+# store = "billing-docs"  # Project ID or name, or store name
+# keywords = ["invoice", "refund", "subscription"]
+
+# Profiles override any of the settings above, selected with --profile <name>
+# or GEMINI_FS_PROFILE. A profile named "default" is used when none is given.
+# [profiles.work]
+# api_key_env = "WORK_GEMINI_API_KEY"
+# model = "gemini-2.5-pro"
+# default_store = "fileSearchStores/work-docs"
+#
+# [profiles.work.generation]
+# temperature = 0.7
+#
+# [profiles.work.prompt]
+# system = "You help the platform team with internal runbooks."
+#
+# [profiles.work.chunking]
+# max_tokens_per_chunk = 600
+`;
+
+// Profile values replace top-level ones; the chunking table is merged one level deep
+// so a profile can override a single extension rule, and generation, prompt, headers
+// and timeouts so it can override a single parameter.
+function mergeProfile(base: TomlTable, profile: TomlTable): TomlTable {
+  const merged: TomlTable = { ...base, ...profile };
+  const baseChunking = base.chunking;
+  const profileChunking = profile.chunking;
+  if (isTable(baseChunking) && isTable(profileChunking)) {
+    const chunking: TomlTable = { ...baseChunking, ...profileChunking };
+    const baseExtensions = baseChunking.extensions;
+    const profileExtensions = profileChunking.extensions;
+    if (isTable(baseExtensions) && isTable(profileExtensions)) {
+      chunking.extensions = { ...baseExtensions, ...profileExtensions };
+    }
+    merged.chunking = chunking;
+  }
+  if (isTable(base.generation) && isTable(profile.generation)) {
+    merged.generation = { ...base.generation, ...profile.generation };
+  }
+  if (isTable(base.prompt) && isTable(profile.prompt)) {
+    const prompt: TomlTable = { ...base.prompt, ...profile.prompt };
+    // The instruction of a profile replaces the base one however either is given
+    if (profile.prompt.system !== undefined && profile.prompt.system_file === undefined) delete prompt.system_file;
+    if (profile.prompt.system_file !== undefined && profile.prompt.system === undefined) delete prompt.system;
+    merged.prompt = prompt;
+  }
+  if (isTable(base.headers) && isTable(profile.headers)) {
+    merged.headers = { ...base.headers, ...profile.headers };
+  }
+  if (isTable(base.timeouts) && isTable(profile.timeouts)) {
+    merged.timeouts = { ...base.timeouts, ...profile.timeouts };
+  }
+  return merged;
+}
+
+function readString(table: TomlTable, key: string): string | undefined {
+  const value = table[key];
+  if (value === undefined) return undefined;
+  if (typeof value !== 'string' || !value) {
+    throw new ConfigError(`${key} must be a non-empty string`);
+  }
+  return value;
+}
+
+function readPositive(table: TomlTable, key: string): number | undefined {
+  const value = table[key];
+  if (value === undefined) return undefined;
+  if (typeof value !== 'number' || !Number.isFinite(value) || value <= 0) {
+    throw new ConfigError(`${key} must be a positive number`);
+  }
+  return value;
+}
+
+function readBoolean(table: TomlTable, key: string): boolean | undefined {
+  const value = table[key];
+  if (value === undefined) return undefined;
+  if (typeof value !== 'boolean') {
+    throw new ConfigError(`${key} must be true or false`);
+  }
+  return value;
+}
+
+export function defaultSettings(): Settings {
+  return { apiKeyEnv: DEFAULT_API_KEY_ENV, chunking: new ChunkingPolicy(), routing: { ...DEFAULT_ROUTING_CONFIG }, headers: {}, generation: {}, prompt: { ...DEFAULT_PROMPT_CONFIG, stores: [] }, quota: { ...DEFAULT_QUOTA_CONFIG }, timeouts: {}, transforms: [] };
+}
+
+// Returns the first existing gemini-fs.toml in the given directories
+export function findConfigFile(dirs: string[]): string | undefined {
+  for (const dir of dirs) {
+    const candidate = path.join(dir, CONFIG_FILE_NAME);
+    if (fs.existsSync(candidate)) return candidate;
+  }
+  return undefined;
+}
+
+export function loadSettings(filePath: string, profile?: string): Settings {
+  const root = parseToml(fs.readFileSync(filePath, 'utf8'));
+  const { profiles, ...base } = root;
+  if (profiles !== undefined && !isTable(profiles)) {
+    throw new ConfigError('profiles must be a table of [profiles.<name>] sections');
+  }
+  const profileTables: TomlTable = isTable(profiles) ? profiles : {};
+
+  let table: TomlTable = base;
+  const selected = profile || (profileTables.default !== undefined ? 'default' : undefined);
+  if (selected) {
+    const profileTable = profileTables[selected];
+    if (!isTable(profileTable)) {
+      const available = Object.keys(profileTables);
+      throw new ConfigError(`Profile "${selected}" not found` + (available.length > 0 ? ` (available: ${available.join(', ')})` : ''));
+    }
+    table = mergeProfile(base, profileTable);
+  }
+
+  const mimeMap = readString(table, 'mime_map');
+  const apiKeyEnvs = table.api_key_envs;
+  if (apiKeyEnvs !== undefined && (!Array.isArray(apiKeyEnvs) || !apiKeyEnvs.every(name => typeof name === 'string' && name))) {
+    throw new ConfigError('api_key_envs must be a list of environment variable names');
+  }
+  const authMethod = readString(table, 'auth');
+  if (authMethod !== undefined && !AUTH_METHODS.includes(authMethod as AuthMethod)) {
+    throw new ConfigError(`auth must be one of: ${AUTH_METHODS.join(', ')}`);
+  }
+  const credentialsFile = readString(table, 'credentials_file');
+  const minGroundingConfidence = table.min_grounding_confidence;
+  if (minGroundingConfidence !== undefined && (typeof minGroundingConfidence !== 'number' || !(minGroundingConfidence >= 0 && minGroundingConfidence <= 1))) {
+    throw new ConfigError('min_grounding_confidence must be between 0 and 1');
+  }
+  const onError = readString(table, 'on_error');
+  if (onError !== undefined && !(onError === 'continue' || onError === 'fail-fast' || /^abort-after:[1-9]\d*$/.test(onError))) {
+    throw new ConfigError(`on_error must be one of: ${FAILURE_POLICIES.join(', ')}`);
+  }
+  const citeContext = readPositive(table, 'cite_context');
+  if (citeContext !== undefined && (!Number.isInteger(citeContext) || citeContext > MAX_CONTEXT_LINES)) {
+    throw new ConfigError(`cite_context must be an integer between 1 and ${MAX_CONTEXT_LINES}`);
+  }
+  const dedup = readString(table, 'dedup');
+  if (dedup !== undefined && !DEDUP_MODES.includes(dedup as DedupMode)) {
+    throw new ConfigError(`dedup must be one of: ${DEDUP_MODES.join(', ')}`);
+  }
+  const dedupDistance = table.dedup_distance;
+  if (dedupDistance !== undefined && (typeof dedupDistance !== 'number' || !Number.isInteger(dedupDistance) || dedupDistance < 0 || dedupDistance > 64)) {
+    throw new ConfigError('dedup_distance must be an integer between 0 and 64');
+  }
+  const redactionPolicy = readString(table, 'redaction_policy');
+  if (redactionPolicy !== undefined && !REDACTION_POLICIES.includes(redactionPolicy as RedactionPolicy)) {
+    throw new ConfigError(`redaction_policy must be one of: ${REDACTION_POLICIES.join(', ')}`);
+  }
+  const redactionRules = table.redaction_rules;
+  if (redactionRules !== undefined && (!Array.isArray(redactionRules) || !redactionRules.every(file => typeof file === 'string' && file))) {
+    throw new ConfigError('redaction_rules must be a list of rule files');
+  }
+  const preflight = readString(table, 'preflight');
+  if (preflight !== undefined && !PREFLIGHT_MODES.includes(preflight as PreflightMode)) {
+    throw new ConfigError(`preflight must be one of: ${PREFLIGHT_MODES.join(', ')}`);
+  }
+  const storageTier = readString(table, 'storage_tier');
+  if (storageTier !== undefined && !STORAGE_TIERS.includes(storageTier as StorageTier)) {
+    throw new ConfigError(`storage_tier must be one of: ${STORAGE_TIERS.join(', ')}`);
+  }
+  const logLevel = readString(table, 'log_level');
+  if (logLevel !== undefined && !LOG_LEVELS.includes(logLevel as LogLevel)) {
+    throw new ConfigError(`log_level must be one of: ${LOG_LEVELS.join(', ')}`);
+  }
+  const logFile = readString(table, 'log_file');
+  const caBundle = readString(table, 'ca_bundle');
+  const aclLabels = readString(table, 'acl_labels');
+  const backend = readString(table, 'backend');
+  if (backend !== undefined && !BACKENDS.includes(backend as BackendKind)) {
+    throw new ConfigError(`backend must be one of: ${BACKENDS.join(', ')}`);
+  }
+  return {
+    apiKeyEnv: readString(table, 'api_key_env') || DEFAULT_API_KEY_ENV,
+    apiKeyEnvs: apiKeyEnvs as string[] | undefined,
+    authMethod: authMethod as AuthMethod | undefined,
+    credentialsFile: credentialsFile ? path.resolve(path.dirname(filePath), credentialsFile) : undefined,
+    backend: backend as BackendKind | undefined,
+    vertexProject: readString(table, 'vertex_project'),
+    vertexLocation: readString(table, 'vertex_location'),
+    apiVersion: readString(table, 'api_version'),
+    headers: parseHeadersConfig(table.headers),
+    otlpEndpoint: readString(table, 'otlp_endpoint'),
+    proxy: readString(table, 'proxy'),
+    caBundle: caBundle ? path.resolve(path.dirname(filePath), caBundle) : undefined,
+    nativeCerts: readBoolean(table, 'native_certs'),
+    aclMode: readBoolean(table, 'acl_mode'),
+    aclLabels: aclLabels ? parseAclLabels(aclLabels) : undefined,
+    logLevel: logLevel as LogLevel | undefined,
+    logFile: logFile ? path.resolve(path.dirname(filePath), logFile) : undefined,
+    notifyCommand: readString(table, 'notify_command'),
+    notifyWebhook: readString(table, 'notify_webhook'),
+    defaultStore: readString(table, 'default_store'),
+    model: readString(table, 'model'),
+    concurrency: readPositive(table, 'concurrency'),
+    requestsPerMinute: readPositive(table, 'requests_per_minute'),
+    maxAttempts: readPositive(table, 'max_attempts'),
+    tokenBudget: readPositive(table, 'token_budget'),
+    extractLocally: readBoolean(table, 'extract_locally'),
+    stripNotebookOutputs: readBoolean(table, 'strip_notebook_outputs'),
+    stripQuotedReplies: readBoolean(table, 'strip_quoted_replies'),
+    normalizeEncoding: readBoolean(table, 'normalize_encoding'),
+    detectLanguage: readBoolean(table, 'detect_language'),
+    fileAttributes: readBoolean(table, 'file_attributes'),
+    withSummaries: readBoolean(table, 'with_summaries'),
+    summaryMinSize: readPositive(table, 'summary_min_size'),
+    partSize: readPositive(table, 'part_size'),
+    onError: onError !== undefined ? parseFailurePolicy(onError) : undefined,
+    dedup: dedup as DedupMode | undefined,
+    dedupDistance: dedupDistance as number | undefined,
+    redactionPolicy: redactionPolicy as RedactionPolicy | undefined,
+    redactionRules: (redactionRules as string[] | undefined)?.map(file => path.resolve(path.dirname(filePath), file)),
+    preflight: preflight as PreflightMode | undefined,
+    maxStoreDocuments: readPositive(table, 'max_store_documents'),
+    storageTier: storageTier as StorageTier | undefined,
+    groundedOnly: readBoolean(table, 'grounded_only'),
+    minGroundingConfidence: minGroundingConfidence as number | undefined,
+    citeContext,
+    queryCache: readBoolean(table, 'query_cache'),
+    queryCacheTtl: readPositive(table, 'query_cache_ttl'),
+    queryHistory: readBoolean(table, 'query_history'),
+    mimeMap: mimeMap ? path.resolve(path.dirname(filePath), mimeMap) : undefined,
+    chunking: ChunkingPolicy.fromToml(table.chunking),
+    routing: parseRoutingConfig(table.routing),
+    generation: parseGenerationConfig(table.generation),
+    prompt: parsePromptConfig(table.prompt, path.dirname(filePath)),
+    quota: parseQuotaConfig(table.quota),
+    timeouts: parseTimeoutsConfig(table.timeouts),
+    transforms: parseTransformRules(table.transforms),
+    profile: selected,
+    source: filePath,
+  };
+}
+
+// Uses the explicit config file, else the first gemini-fs.toml found in searchDirs,
+// else the defaults
+export function resolveSettings(configFile: string | undefined, profile: string | undefined, searchDirs: string[]): Settings {
+  const filePath = configFile || findConfigFile(searchDirs);
+  if (!filePath) {
+    if (profile) {
+      throw new ConfigError(`Profile "${profile}" requested but no ${CONFIG_FILE_NAME} was found`);
+    }
+    return defaultSettings();
+  }
+  try {
+    return loadSettings(path.resolve(filePath), profile);
+  } catch (error) {
+    const err = error as Error;
+    throw new ConfigError(`Failed to load config ${filePath}: ${err.message}`, { cause: error });
+  }
+}
+
+// Writes the commented template; refuses to overwrite unless force is set
+export function initConfig(filePath: string, force: boolean = false): void {
+  if (fs.existsSync(filePath) && !force) {
+    throw new ConfigError(`${filePath} already exists (use --force to overwrite)`);
+  }
+  fs.writeFileSync(filePath, CONFIG_TEMPLATE);
+}
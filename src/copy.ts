@@ -0,0 +1,168 @@
+// copy.ts - Copy and move of documents between stores, re-uploaded from the local files they were indexed from
+import * as fs from 'fs';
+import * as path from 'path';
+import { FileSearchClient } from './client.js';
+import { DocumentInfo } from './documents.js';
+import { DirectoryIngester } from './ingest.js';
+import { IndexCache, hashFile } from './cache.js';
+import { SyncManifest } from './sync.js';
+import { DocumentMetadata } from './metadata.js';
+import { InvalidInputError } from './errors.js';
+import { Semaphore } from './pipeline.js';
+import { uploadedChecksum } from './integrity.js';
+
+// Types
+// ready: the local file is the one that was uploaded; changed: it was edited since;
+// unverified: no record of what was uploaded to compare it with; missing: not found
+export type CopyStatus = 'ready' | 'changed' | 'unverified' | 'missing';
+
+export interface CopySource {
+  path: string;  // Relative path the documents were uploaded from
+  documents: DocumentInfo[];  // Every document of the file in the source store
+  status: CopyStatus;
+  rootDir?: string;  // Where the file was found
+}
+
+export interface CopyPlan {
+  from: string;
+  to: string;
+  sources: CopySource[];
+}
+
+export interface SourceRoot {
+  rootDir: string;
+  manifest?: SyncManifest;  // Of a project synced with the source store
+}
+
+export interface PlanCopyOptions {
+  roots: SourceRoot[];  // Searched in order
+  cache?: IndexCache;  // Content hashes of the files indexed into the source store
+}
+
+export interface CopyOptions {
+  ingester: DirectoryIngester;  // Settings of the upload into the destination store
+  move?: boolean;  // Delete the documents from the source store once their file was copied
+}
+
+export interface CopySummary {
+  copied: string[];  // Relative paths
+  flagged: CopySource[];  // Not copied, as their content is not available locally
+  deleted: string[];  // Document names removed from the source store with move
+  failures: { path: string; reason: string }[];
+}
+
+// Set by the upload itself, so the copy gets its own
+const DERIVED_METADATA = ['part_of', 'part', 'part_count', 'line_range'];
+
+// "src/lib.rs#Parser::parse" and "big.log#part-2" were uploaded from "src/lib.rs" and "big.log"
+export function sourcePathOf(document: DocumentInfo): string {
+  const partOf = document.metadata.part_of;
+  return (typeof partOf === 'string' ? partOf : document.displayName).split('#')[0];
+}
+
+export function documentNamesOf(entry: { documentName?: string; documentNames?: string[] }): string[] {
+  return entry.documentNames || (entry.documentName ? [entry.documentName] : []);
+}
+
+// The hash the sync manifest of the root recorded when the file was uploaded
+export function manifestHash(source: string, documents: DocumentInfo[], root: SourceRoot): string | undefined {
+  const names = new Set(documents.map(document => document.name));
+  const entry = root.manifest?.get(source);
+  return entry?.hash && documentNamesOf(entry).some(name => names.has(name)) ? entry.hash : undefined;
+}
+
+// A selected document brings along every other document of its file, so a file split
+// into symbols, parts or a summary is copied, and with move deleted, as a whole
+export function planCopy(from: string, to: string, selected: DocumentInfo[], storeDocuments: DocumentInfo[], options: PlanCopyOptions): CopyPlan {
+  if (from === to) throw new InvalidInputError('The source and destination stores are the same');
+  const wanted = new Set(selected.map(sourcePathOf));
+  const bySource = new Map<string, DocumentInfo[]>();
+  for (const document of storeDocuments) {
+    const source = sourcePathOf(document);
+    if (!wanted.has(source)) continue;
+    bySource.set(source, [...(bySource.get(source) || []), document]);
+  }
+
+  // Hashes of the index cache entries pointing at each document of the source store
+  const cachedHashes = new Map<string, string>();
+  if (options.cache) {
+    for (const [hash, entry] of options.cache.hashes(from)) {
+      for (const name of documentNamesOf(entry)) cachedHashes.set(name, hash);
+    }
+  }
+
+  const sources = [...bySource].map(([source, documents]): CopySource => {
+    if (!source || path.posix.isAbsolute(source) || path.posix.normalize(source).startsWith('..')) return { path: source, documents, status: 'missing' };
+    let status: CopyStatus = 'missing';
+    for (const root of options.roots) {
+      const filePath = path.join(root.rootDir, source);
+      if (!fs.existsSync(filePath) || !fs.statSync(filePath).isFile()) continue;
+      const hash = hashFile(filePath);
+      const recorded = manifestHash(source, documents, root)
+        || documents.map(document => cachedHashes.get(document.name)).find(known => known)
+        || uploadedChecksum(documents);
+      if (recorded === hash) return { path: source, documents, status: 'ready', rootDir: root.rootDir };
+      // A root further down may hold the version that was uploaded
+      if (status !== 'changed') status = recorded ? 'changed' : 'unverified';
+    }
+    return { path: source, documents, status };
+  });
+  return { from, to, sources: sources.sort((a, b) => a.path.localeCompare(b.path)) };
+}
+
+// For the ingester's fileMetadata: the metadata of the documents a file had in the
+// source store, without what the upload derives again
+export function carriedMetadata(plan: CopyPlan): (filePath: string) => DocumentMetadata | undefined {
+  const byFile = new Map<string, DocumentMetadata>();
+  for (const source of plan.sources) {
+    const whole = source.documents.find(document => document.displayName === source.path) || source.documents[0];
+    if (!source.rootDir || !whole) continue;
+    const metadata = Object.fromEntries(Object.entries(whole.metadata).filter(([key]) => !DERIVED_METADATA.includes(key)));
+    if (Object.keys(metadata).length > 0) byFile.set(path.resolve(source.rootDir, source.path), metadata);
+  }
+  return filePath => byFile.get(path.resolve(filePath));
+}
+
+// Uploads the ready files into the destination store through the ingester, so they are
+// converted, chunked and split as they would be by ingest. The API does not return
+// document content, so documents without their file are only reported.
+export async function copyDocuments(client: FileSearchClient, plan: CopyPlan, options: CopyOptions): Promise<CopySummary> {
+  const ready = plan.sources.filter(source => source.status === 'ready');
+  const summary: CopySummary = { copied: [], flagged: plan.sources.filter(source => source.status !== 'ready'), deleted: [], failures: [] };
+  const byRoot = new Map<string, CopySource[]>();
+  for (const source of ready) byRoot.set(source.rootDir as string, [...(byRoot.get(source.rootDir as string) || []), source]);
+
+  for (const [rootDir, sources] of byRoot) {
+    const upload = await options.ingester.upload(client, plan.to, rootDir, sources.map(source => path.join(rootDir, source.path)));
+    // Files already indexed in the destination, or linked to a copy there, count as copied
+    const posix = (relativePath: string) => relativePath.split(path.sep).join('/');
+    const unfinished = new Set([...upload.failures.map(failure => posix(failure.path)), ...upload.remaining.map(posix)]);
+    for (const source of sources) {
+      if (!unfinished.has(source.path)) summary.copied.push(source.path);
+    }
+    for (const failure of upload.failures) summary.failures.push({ path: posix(failure.path), reason: failure.reason });
+  }
+
+  if (options.move) {
+    const copied = new Set(summary.copied);
+    const semaphore = new Semaphore(5);
+    const documents = ready.filter(source => copied.has(source.path)).flatMap(source => source.documents);
+    await Promise.all(documents.map(document => semaphore.run(async () => {
+      try {
+        await client.deleteDocument(document.name);
+        summary.deleted.push(document.name);
+      } catch (error) {
+        summary.failures.push({ path: document.displayName || document.name, reason: `copied, but not deleted from ${plan.from}: ${(error as Error).message}` });
+      }
+    })));
+  }
+  return summary;
+}
+
+export function formatCopyPlan(plan: CopyPlan, move: boolean = false): string {
+  const lines = plan.sources.map(source =>
+    `${source.status.padEnd(10)} ${source.path}${source.documents.length > 1 ? ` (${source.documents.length} documents)` : ''}${source.rootDir ? `  from ${source.rootDir}` : ''}`);
+  const ready = plan.sources.filter(source => source.status === 'ready').length;
+  lines.push('', `${ready} of ${plan.sources.length} files can be ${move ? 'moved' : 'copied'} from ${plan.from} to ${plan.to}`);
+  return lines.join('\n');
+}
@@ -0,0 +1,159 @@
+// core.ts - Browser-safe query core: request building, response parsing and citations, with HTTP behind a transport
+import { GenerateContentConfig, GenerateContentResponse, GenerateContentResponseUsageMetadata, GroundingMetadata, Tool } from '@google/genai';
+import { Citation, extractCitations } from './citations.js';
+import { AnswerConfidence, NoGroundedAnswer, answerConfidence, checkGrounding, withSystemInstruction } from './grounding.js';
+import { BlockedAnswer, GenerationParams, blockedAnswer, toGenerationConfig } from './generation.js';
+import { ApiOptions, BackendConfig, DEFAULT_API_VERSION, retrievalTool } from './backend.js';
+import { HttpStatusError } from './errors.js';
+
+// The other browser-safe pieces, so one import serves a web page
+export { Citation, extractCitations, addFootnoteMarkers, formatFootnotes } from './citations.js';
+export { AnswerConfidence, NoGroundedAnswer, formatNoGroundedAnswer } from './grounding.js';
+export { BlockedAnswer, formatBlockedAnswer } from './generation.js';
+export { parseFilterExpression } from './search.js';
+
+// Types
+export interface TokenUsage {
+  promptTokens: number;  // Includes the retrieved passages
+  responseTokens: number;  // Includes thinking tokens
+  totalTokens: number;
+}
+
+export interface HttpRequest {
+  method: 'GET' | 'POST';
+  url: string;
+  headers: { [name: string]: string };
+  body?: string;
+}
+
+export interface HttpResponse {
+  status: number;
+  body: string;
+  retryAfter?: string | null;
+}
+
+// The only way the core reaches the network, so it runs wherever a transport can be written
+export interface HttpTransport {
+  send(request: HttpRequest): Promise<HttpResponse>;
+}
+
+export interface CoreQueryOptions {
+  model?: string;
+  metadataFilter?: string;  // AIP-160 filter, see parseFilterExpression
+  groundedOnly?: boolean;  // Answers without grounding are returned as noGroundedAnswer
+  minConfidence?: number;  // For groundedOnly, see checkGrounding
+  systemInstruction?: string;
+  generation?: GenerationParams;
+  responseSchema?: object;  // Ask for a JSON answer matching this schema
+}
+
+export interface CoreAnswer {
+  answer: string;  // Empty when noGroundedAnswer or blocked is set
+  citations: Citation[];
+  groundingMetadata?: GroundingMetadata;
+  usage?: TokenUsage;
+  confidence: AnswerConfidence;
+  noGroundedAnswer?: NoGroundedAnswer;
+  blocked?: BlockedAnswer;  // The safety filters stopped the question or the reply
+}
+
+export interface CoreClientOptions {
+  apiKey: string;
+  transport?: HttpTransport;  // Default: fetchTransport
+  model?: string;
+  baseUrl?: string;  // Default: the Gemini API
+  api?: ApiOptions;  // Pinned API version and extra headers
+}
+
+export const DEFAULT_MODEL = 'gemini-2.5-flash';
+
+const API_BASE_URL = 'https://generativelanguage.googleapis.com';
+
+// fetch() is all that is needed in browsers, Deno and Node.js 18+
+export const fetchTransport: HttpTransport = {
+  async send(request) {
+    const response = await fetch(request.url, { method: request.method, headers: request.headers, body: request.body });
+    return { status: response.status, body: await response.text(), retryAfter: response.headers.get('retry-after') };
+  },
+};
+
+export function toTokenUsage(metadata: GenerateContentResponseUsageMetadata | undefined): TokenUsage | undefined {
+  if (!metadata?.totalTokenCount) return undefined;
+  return {
+    promptTokens: (metadata.promptTokenCount || 0) + (metadata.toolUsePromptTokenCount || 0),
+    responseTokens: (metadata.candidatesTokenCount || 0) + (metadata.thoughtsTokenCount || 0),
+    totalTokens: metadata.totalTokenCount,
+  };
+}
+
+// The SDK config of a File Search query; FileSearchClient sends it through the SDK
+export function queryConfig(tool: Tool, options: CoreQueryOptions): GenerateContentConfig {
+  return {
+    tools: [tool],
+    ...withSystemInstruction(options.systemInstruction, options.groundedOnly),
+    ...toGenerationConfig(options.generation || {}),
+    ...(options.responseSchema ? { responseMimeType: 'application/json', responseJsonSchema: options.responseSchema } : {}),
+  };
+}
+
+// The REST body of generateContent for a config: the SDK moves sampling settings into
+// generationConfig and wraps the system instruction in a content
+export function generateContentBody(question: string, config: GenerateContentConfig): object {
+  const { tools, systemInstruction, safetySettings, ...generationConfig } = config;
+  return {
+    contents: [{ role: 'user', parts: [{ text: question }] }],
+    tools,
+    systemInstruction: typeof systemInstruction === 'string' ? { parts: [{ text: systemInstruction }] } : systemInstruction,
+    safetySettings,
+    generationConfig: Object.keys(generationConfig).length > 0 ? generationConfig : undefined,
+  };
+}
+
+// The answer text leaves out thought summaries, as GenerateContentResponse.text does
+export function answerText(response: GenerateContentResponse): string {
+  const parts = response.candidates?.[0]?.content?.parts || [];
+  return parts.filter(part => part.text && !part.thought).map(part => part.text).join('');
+}
+
+export function parseAnswer(response: GenerateContentResponse, options: CoreQueryOptions = {}): CoreAnswer {
+  const answer = answerText(response);
+  const groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+  const usage = toTokenUsage(response.usageMetadata);
+  const blocked = blockedAnswer(response, answer);
+  if (blocked) {
+    return { answer: '', citations: [], groundingMetadata, usage, confidence: answerConfidence('', groundingMetadata), blocked };
+  }
+  const rejected = options.groundedOnly ? checkGrounding(answer, groundingMetadata, options.minConfidence) : undefined;
+  if (rejected) {
+    return { answer: '', citations: [], groundingMetadata, usage, confidence: answerConfidence(answer, groundingMetadata), noGroundedAnswer: rejected };
+  }
+  return { answer, citations: extractCitations(groundingMetadata, answer), groundingMetadata, usage, confidence: answerConfidence(answer, groundingMetadata) };
+}
+
+// Queries File Search stores of the Gemini API with an API key, without Node.js modules;
+// the browser demo and other non-Node hosts use it in place of FileSearchClient
+export class CoreClient {
+  readonly model: string;
+  private readonly transport: HttpTransport;
+
+  constructor(private readonly options: CoreClientOptions) {
+    this.model = options.model || DEFAULT_MODEL;
+    this.transport = options.transport || fetchTransport;
+  }
+
+  async query(storeNames: string[], question: string, options: CoreQueryOptions = {}): Promise<CoreAnswer> {
+    const model = (options.model || this.model).replace(/^models\//, '');
+    const backend: BackendConfig = { kind: 'gemini' };
+    const config = queryConfig(retrievalTool(backend, storeNames, options.metadataFilter), options);
+    const response = await this.transport.send({
+      method: 'POST',
+      url: `${(this.options.baseUrl || API_BASE_URL).replace(/\/+$/, '')}/${this.options.api?.version || DEFAULT_API_VERSION}/models/${model}:generateContent`,
+      headers: { ...this.options.api?.headers, 'Content-Type': 'application/json', 'x-goog-api-key': this.options.apiKey },
+      body: JSON.stringify(generateContentBody(question, config)),
+    });
+    if (response.status < 200 || response.status >= 300) {
+      throw new HttpStatusError(`Query failed: HTTP ${response.status} ${response.body}`, response.status, response.retryAfter);
+    }
+    return parseAnswer(JSON.parse(response.body) as GenerateContentResponse, options);
+  }
+}
@@ -0,0 +1,100 @@
+// cost.ts - Token counting and cost estimates for indexing and queries
+import { FileSearchClient } from './client.js';
+import { IngestPlan } from './ingest.js';
+import { Semaphore } from './pipeline.js';
+import { resolvePathOf } from './expand.js';
+import { readText } from './encoding.js';
+import { TokenUsage } from './core.js';
+
+// Usage parsing is part of the browser-safe core
+export { TokenUsage, toTokenUsage } from './core.js';
+
+// Types
+interface ModelPricing {
+  input: number;  // USD per million tokens
+  output: number;
+}
+
+// Rough average for English text and source code
+export const BYTES_PER_TOKEN = 4;
+
+// Indexing is billed at the embedding rate; storage and query-time embeddings are free
+export const INDEXING_PRICE_PER_MILLION = 0.15;
+
+// Matched by longest prefix, so "gemini-2.5-flash-lite" is not priced as flash
+const MODEL_PRICING: { [modelPrefix: string]: ModelPricing } = {
+  'gemini-2.5-pro': { input: 1.25, output: 10 },
+  'gemini-2.5-flash': { input: 0.30, output: 2.50 },
+  'gemini-2.5-flash-lite': { input: 0.10, output: 0.40 },
+};
+
+// countTokens requests are limited in size; larger files keep the local estimate
+const MAX_COUNT_BYTES = 1024 * 1024;
+
+export function estimateTokens(bytes: number): number {
+  return Math.ceil(bytes / BYTES_PER_TOKEN);
+}
+
+export function indexingCost(tokens: number): number {
+  return (tokens / 1_000_000) * INDEXING_PRICE_PER_MILLION;
+}
+
+function pricingFor(model: string): ModelPricing | undefined {
+  const name = model.replace(/^models\//, '');
+  const prefix = Object.keys(MODEL_PRICING)
+    .filter(candidate => name.startsWith(candidate))
+    .sort((a, b) => b.length - a.length)[0];
+  return prefix ? MODEL_PRICING[prefix] : undefined;
+}
+
+// Returns undefined for models without a known price
+export function queryCost(model: string, usage: TokenUsage): number | undefined {
+  const pricing = pricingFor(model);
+  if (!pricing) return undefined;
+  return (usage.promptTokens * pricing.input + usage.responseTokens * pricing.output) / 1_000_000;
+}
+
+export function formatCost(usd: number): string {
+  return usd < 0.01 ? `$${usd.toFixed(4)}` : `$${usd.toFixed(2)}`;
+}
+
+export function formatUsage(model: string, usage: TokenUsage): string {
+  const cost = queryCost(model, usage);
+  return `${usage.totalTokens} tokens (prompt ${usage.promptTokens}, response ${usage.responseTokens})${cost !== undefined ? `, ~${formatCost(cost)}` : ''}`;
+}
+
+function isCountable(mimeType: string): boolean {
+  return mimeType.startsWith('text/') || /json|xml|yaml|javascript|typescript/.test(mimeType);
+}
+
+// Replaces the local estimate of text files in the plan with the countTokens result.
+// Binary files, converted files and files above 1MB keep the local estimate.
+export async function countPlanTokens(
+  client: FileSearchClient,
+  rootDir: string,
+  plan: IngestPlan,
+  concurrency: number = 5
+): Promise<IngestPlan> {
+  const semaphore = new Semaphore(concurrency);
+  const files = await Promise.all(plan.files.map(async file => {
+    // Converted files are counted by size only, their text is not kept by the plan
+    if (file.skipped || file.extractedFrom || !isCountable(file.mimeType) || file.size > MAX_COUNT_BYTES) return file;
+    await semaphore.acquire();
+    try {
+      const text = readText(resolvePathOf(rootDir, file.path));
+      return { ...file, estimatedTokens: await client.countTokens(text) };
+    } catch (error) {
+      const err = error as Error;
+      console.error(`⚠️  countTokens failed, using local estimate: ${file.path} - ${err.message}`);
+      return file;
+    } finally {
+      semaphore.release();
+    }
+  }));
+
+  return {
+    ...plan,
+    files,
+    estimatedTokens: files.filter(file => !file.skipped).reduce((sum, file) => sum + file.estimatedTokens, 0),
+  };
+}
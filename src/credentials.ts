@@ -0,0 +1,271 @@
+// credentials.ts - API keys stored in the OS keychain or an encrypted file
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { execFileSync } from 'child_process';
+import { createCipheriv, createDecipheriv, randomBytes, scryptSync } from 'crypto';
+import { CancelledError, ConfigError } from './errors.js';
+
+// Types
+export type CredentialBackend = 'keychain' | 'file';
+
+export interface CredentialOptions {
+  account?: string;  // One key per account; the CLIs use the config profile name
+  file?: string;  // Default: credentialsFilePath()
+  passphrase?: string;  // Encrypted file only; default: GEMINI_FS_PASSPHRASE
+}
+
+export interface StoredCredential {
+  apiKey: string;
+  backend: CredentialBackend;
+}
+
+// One entry of the encrypted file, all fields base64
+interface EncryptedEntry {
+  salt: string;
+  iv: string;
+  tag: string;
+  data: string;
+}
+
+interface CredentialsFileData {
+  version: number;
+  kdf: 'scrypt';
+  accounts: { [account: string]: EncryptedEntry };
+}
+
+export const CREDENTIAL_BACKENDS: CredentialBackend[] = ['keychain', 'file'];
+export const DEFAULT_ACCOUNT = 'default';
+export const PASSPHRASE_ENV = 'GEMINI_FS_PASSPHRASE';
+
+// Keychain service name, shared by every account
+const SERVICE = 'gemini-fs';
+const KEY_LENGTH = 32;
+
+export function credentialsFilePath(env: NodeJS.ProcessEnv = process.env): string {
+  const configDir = process.platform === 'win32'
+    ? env.APPDATA || path.join(os.homedir(), 'AppData', 'Roaming')
+    : env.XDG_CONFIG_HOME || path.join(os.homedir(), '.config');
+  return path.join(configDir, 'gemini-fs', 'credentials.enc');
+}
+
+// macOS Keychain through `security`, the Secret Service (GNOME Keyring, KWallet) through
+// `secret-tool`; other platforms use the encrypted file
+export function keychainAvailable(): boolean {
+  const tool = process.platform === 'darwin' ? 'security' : process.platform === 'linux' ? 'secret-tool' : undefined;
+  if (!tool) return false;
+  try {
+    execFileSync('which', [tool], { stdio: 'ignore' });
+    return true;
+  } catch (error) {
+    return false;
+  }
+}
+
+// A word of a `security -i` command line, which splits on spaces outside double quotes
+function securityWord(value: string): string {
+  return `"${value.replace(/[\\"]/g, '\\$&')}"`;
+}
+
+function keychainSave(account: string, apiKey: string): void {
+  if (process.platform === 'darwin') {
+    // security only takes the password as an argument, so the command is given on stdin to
+    // its interactive mode rather than in argv, where the process list would show the key;
+    // -U updates an existing entry
+    const command = ['add-generic-password', '-U', '-s', SERVICE, '-a', account, '-w', apiKey].map(securityWord).join(' ');
+    execFileSync('security', ['-i'], { input: `${command}\n`, stdio: ['pipe', 'ignore', 'ignore'] });
+    // The interactive mode exits cleanly even when the command failed
+    if (keychainLoad(account) !== apiKey) throw new ConfigError('Could not save the API key to the macOS Keychain; is it locked?');
+  } else {
+    // secret-tool reads the secret from stdin, so it never appears in the process list
+    execFileSync('secret-tool', ['store', `--label=Gemini API key (${account})`, 'service', SERVICE, 'account', account], { input: apiKey, stdio: ['pipe', 'ignore', 'ignore'] });
+  }
+}
+
+function keychainLoad(account: string): string | undefined {
+  try {
+    const output = process.platform === 'darwin'
+      ? execFileSync('security', ['find-generic-password', '-s', SERVICE, '-a', account, '-w'], { stdio: ['ignore', 'pipe', 'ignore'] })
+      : execFileSync('secret-tool', ['lookup', 'service', SERVICE, 'account', account], { stdio: ['ignore', 'pipe', 'ignore'] });
+    return output.toString('utf8').trim() || undefined;
+  } catch (error) {
+    // Not found, or the keychain is locked
+    return undefined;
+  }
+}
+
+function keychainDelete(account: string): boolean {
+  try {
+    if (process.platform === 'darwin') {
+      execFileSync('security', ['delete-generic-password', '-s', SERVICE, '-a', account], { stdio: 'ignore' });
+    } else {
+      if (!keychainLoad(account)) return false;
+      execFileSync('secret-tool', ['clear', 'service', SERVICE, 'account', account], { stdio: 'ignore' });
+    }
+    return true;
+  } catch (error) {
+    return false;
+  }
+}
+
+function readCredentialsFile(file: string): CredentialsFileData | undefined {
+  if (!fs.existsSync(file)) return undefined;
+  try {
+    return JSON.parse(fs.readFileSync(file, 'utf8')) as CredentialsFileData;
+  } catch (error) {
+    throw new ConfigError(`Credentials file is not valid JSON: ${file}`, { cause: error });
+  }
+}
+
+function writeCredentialsFile(file: string, data: CredentialsFileData): void {
+  fs.mkdirSync(path.dirname(file), { recursive: true, mode: 0o700 });
+  fs.writeFileSync(file, JSON.stringify(data, null, 2), { mode: 0o600 });
+}
+
+// AES-256-GCM with a key derived from the passphrase by scrypt, a fresh salt and IV per entry
+function encrypt(apiKey: string, passphrase: string): EncryptedEntry {
+  const salt = randomBytes(16);
+  const iv = randomBytes(12);
+  const cipher = createCipheriv('aes-256-gcm', scryptSync(passphrase, salt, KEY_LENGTH), iv);
+  const data = Buffer.concat([cipher.update(apiKey, 'utf8'), cipher.final()]);
+  return {
+    salt: salt.toString('base64'),
+    iv: iv.toString('base64'),
+    tag: cipher.getAuthTag().toString('base64'),
+    data: data.toString('base64'),
+  };
+}
+
+function decrypt(entry: EncryptedEntry, passphrase: string): string {
+  const key = scryptSync(passphrase, Buffer.from(entry.salt, 'base64'), KEY_LENGTH);
+  const decipher = createDecipheriv('aes-256-gcm', key, Buffer.from(entry.iv, 'base64'));
+  decipher.setAuthTag(Buffer.from(entry.tag, 'base64'));
+  try {
+    return Buffer.concat([decipher.update(Buffer.from(entry.data, 'base64')), decipher.final()]).toString('utf8');
+  } catch (error) {
+    throw new ConfigError('Wrong passphrase for the stored API key, or the credentials file was modified', { cause: error });
+  }
+}
+
+export function saveCredential(apiKey: string, backend: CredentialBackend, options: CredentialOptions = {}): string {
+  const account = options.account || DEFAULT_ACCOUNT;
+  if (backend === 'keychain') {
+    if (!keychainAvailable()) {
+      throw new ConfigError('No OS keychain found (needs `security` on macOS or `secret-tool` on Linux); use --storage file');
+    }
+    keychainSave(account, apiKey);
+    return process.platform === 'darwin' ? 'macOS Keychain' : 'Secret Service keyring';
+  }
+
+  const passphrase = options.passphrase || process.env[PASSPHRASE_ENV];
+  if (!passphrase) {
+    throw new ConfigError(`A passphrase is required to encrypt the credentials file (${PASSPHRASE_ENV})`);
+  }
+  const file = options.file || credentialsFilePath();
+  const data = readCredentialsFile(file) || { version: 1, kdf: 'scrypt', accounts: {} };
+  data.accounts[account] = encrypt(apiKey, passphrase);
+  writeCredentialsFile(file, data);
+  return file;
+}
+
+// Keychain first, then the encrypted file; the file is skipped without a passphrase
+export function loadCredential(options: CredentialOptions = {}): StoredCredential | undefined {
+  const account = options.account || DEFAULT_ACCOUNT;
+  if (keychainAvailable()) {
+    const apiKey = keychainLoad(account);
+    if (apiKey) return { apiKey, backend: 'keychain' };
+  }
+
+  const file = options.file || credentialsFilePath();
+  const entry = readCredentialsFile(file)?.accounts[account];
+  if (!entry) return undefined;
+  const passphrase = options.passphrase || process.env[PASSPHRASE_ENV];
+  if (!passphrase) {
+    console.error(`⚠️  An encrypted API key is stored in ${file}; set ${PASSPHRASE_ENV} to use it`);
+    return undefined;
+  }
+  return { apiKey: decrypt(entry, passphrase), backend: 'file' };
+}
+
+// Returns the backends the key was removed from
+export function deleteCredential(options: CredentialOptions = {}): CredentialBackend[] {
+  const account = options.account || DEFAULT_ACCOUNT;
+  const removed: CredentialBackend[] = [];
+  if (keychainAvailable() && keychainDelete(account)) removed.push('keychain');
+
+  const file = options.file || credentialsFilePath();
+  const data = readCredentialsFile(file);
+  if (data?.accounts[account]) {
+    delete data.accounts[account];
+    if (Object.keys(data.accounts).length > 0) {
+      writeCredentialsFile(file, data);
+    } else {
+      fs.rmSync(file, { force: true });
+    }
+    removed.push('file');
+  }
+  return removed;
+}
+
+// Whether a key is stored for the account, without decrypting or prompting
+export function hasStoredCredential(options: CredentialOptions = {}): CredentialBackend | undefined {
+  const account = options.account || DEFAULT_ACCOUNT;
+  if (keychainAvailable() && keychainLoad(account)) return 'keychain';
+  return readCredentialsFile(options.file || credentialsFilePath())?.accounts[account] ? 'file' : undefined;
+}
+
+// Reads a line without echoing it; falls back to a plain line read when stdin is not a terminal
+export function promptSecret(question: string): Promise<string> {
+  return new Promise((resolve, reject) => {
+    const stdin = process.stdin;
+    process.stderr.write(question);
+    if (!stdin.isTTY) {
+      let buffered = '';
+      const onData = (chunk: Buffer) => {
+        buffered += chunk.toString('utf8');
+        const newline = buffered.indexOf('\n');
+        if (newline === -1) return;
+        stdin.off('data', onData);
+        stdin.pause();
+        // Keeps the following lines for the next prompt
+        if (newline + 1 < buffered.length) stdin.unshift(Buffer.from(buffered.slice(newline + 1), 'utf8'));
+        process.stderr.write('\n');
+        resolve(buffered.slice(0, newline).replace(/\r$/, ''));
+      };
+      stdin.on('data', onData);
+      stdin.once('end', () => resolve(buffered.trim()));
+      stdin.resume();
+      return;
+    }
+
+    let value = '';
+    stdin.setRawMode(true);
+    stdin.resume();
+    const onData = (chunk: Buffer) => {
+      for (const char of chunk.toString('utf8')) {
+        if (char === '\r' || char === '\n') {
+          finish();
+          resolve(value);
+          return;
+        }
+        if (char === '\u0003') {  // Ctrl-C
+          finish();
+          reject(new CancelledError());
+          return;
+        }
+        if (char === '\u007f' || char === '\b') {
+          value = value.slice(0, -1);
+        } else {
+          value += char;
+        }
+      }
+    };
+    const finish = () => {
+      stdin.off('data', onData);
+      stdin.setRawMode(false);
+      stdin.pause();
+      process.stderr.write('\n');
+    };
+    stdin.on('data', onData);
+  });
+}
@@ -0,0 +1,427 @@
+// daemon.ts - A background process sharing rate limits, daily quotas and 429 backoff between commands over a local socket
+import * as fs from 'fs';
+import * as net from 'net';
+import * as path from 'path';
+import { createHash } from 'crypto';
+import { TokenBucket } from './pipeline.js';
+import { QuotaScheduler, rateLimitPauseMs } from './quota.js';
+import { stateHome } from './tenant.js';
+import { CancelledError, ErrorCode, FileSearchError, InvalidInputError, toFileSearchError } from './errors.js';
+
+// Types
+// One JSON object per line in both directions; replies carry the id of their message
+export type DaemonMessage =
+  | { op: 'acquire'; label: string; tokens: number }  // Replied to once the call may start
+  | { op: 'release'; tokens: number }  // Ends the call of the acquire with the same id; tokens actually used
+  | { op: 'backoff'; label: string; pauseMs: number }  // A 429 outlived the retries: hold every call back
+  | { op: 'status' }
+  | { op: 'stop' };
+
+export type DaemonRequest = DaemonMessage & { id: number };
+
+export interface DaemonReply {
+  id: number;
+  error?: { code: ErrorCode; message: string };
+  status?: DaemonStatus;
+}
+
+export interface DaemonStatus {
+  pid: number;
+  socket: string;
+  startedAt: string;
+  clients: number;  // Commands connected
+  inFlight: number;  // Calls granted and not yet released
+  waiting: number;  // Calls held back by the rate limit, a budget or a pause
+  granted: number;  // Since the start
+  rateLimited: number;  // 429 pauses reported by the commands
+  pausedUntil?: string;  // While every call is held back after a 429
+  requestsPerMinute?: number;
+  quota?: { day: string; requests: number; tokens: number; requestsPerDay?: number; tokensPerDay?: number };
+}
+
+export interface DaemonServerOptions {
+  socketPath: string;
+  quota: QuotaScheduler;  // Daily budgets of every connected command
+  requestsPerMinute?: number;  // Calls started per minute across the commands, unlimited when omitted
+  signal?: AbortSignal;  // Ends the pauses of waiting calls
+}
+
+interface Lease {
+  tokens: number;
+  release: (tokens: number) => void;
+}
+
+// Set to "off" and commands schedule their own calls even while a daemon runs
+export const DAEMON_ENV = 'GEMINI_FS_DAEMON';
+export const DAEMON_SOCKET_ENV = 'GEMINI_FS_DAEMON_SOCKET';
+export const DAEMON_SOCKET = 'daemon.sock';
+export const DAEMON_LOG = 'daemon.log';
+
+// Answers from the daemon to status and stop; a daemon that takes longer is taken as gone
+const CONTROL_TIMEOUT_MS = 5000;
+
+function sleep(ms: number, signal?: AbortSignal): Promise<void> {
+  return new Promise((resolve, reject) => {
+    if (signal?.aborted) {
+      reject(new CancelledError('Cancelled while paused after a rate limit'));
+      return;
+    }
+    const onAbort = () => {
+      clearTimeout(timer);
+      reject(new CancelledError('Cancelled while paused after a rate limit'));
+    };
+    const timer = setTimeout(() => {
+      signal?.removeEventListener('abort', onAbort);
+      resolve();
+    }, ms);
+    signal?.addEventListener('abort', onAbort, { once: true });
+  });
+}
+
+// The socket in the state directory, so every command of a user finds the same daemon.
+// Windows has no unix sockets in every version; a named pipe per state directory is used there.
+export function daemonSocketPath(env: NodeJS.ProcessEnv = process.env): string {
+  if (env[DAEMON_SOCKET_ENV]) return env[DAEMON_SOCKET_ENV] as string;
+  const home = stateHome(env);
+  if (process.platform === 'win32') {
+    return `\\\\.\\pipe\\gemini-fs-daemon-${createHash('sha256').update(home).digest('hex').slice(0, 12)}`;
+  }
+  return path.join(home, DAEMON_SOCKET);
+}
+
+// Serves the commands connected to the socket. A call is granted once the shared rate
+// limit, the daily budgets and any pause after a 429 allow it, and holds its reservation
+// until the command releases it or disconnects.
+export class DaemonServer {
+  private server?: net.Server;
+  private readonly sockets = new Set<net.Socket>();
+  private readonly bucket?: TokenBucket;
+  private readonly startedAt = new Date();
+  private pausedUntil = 0;
+  private inFlight = 0;
+  private waiting = 0;
+  private granted = 0;
+  private rateLimited = 0;
+  private stopRequested?: () => void;
+
+  constructor(private readonly options: DaemonServerOptions) {
+    this.bucket = options.requestsPerMinute ? new TokenBucket(options.requestsPerMinute) : undefined;
+  }
+
+  // Refuses to start next to a daemon that still answers; a socket file without one is
+  // left by a daemon that did not stop cleanly and is replaced
+  async start(): Promise<void> {
+    const { socketPath } = this.options;
+    const link = new DaemonLink(socketPath);
+    const running = await link.status();
+    link.close();
+    if (running) throw new InvalidInputError(`A daemon is already running on ${socketPath} (pid ${running.pid})`);
+    if (process.platform !== 'win32') {
+      fs.mkdirSync(path.dirname(socketPath), { recursive: true, mode: 0o700 });
+      fs.rmSync(socketPath, { force: true });
+    }
+    const server = net.createServer(socket => this.accept(socket));
+    // Only the user's own commands may take part: the socket is created readable by the
+    // user only, so no other user can connect before its mode is set
+    const umask = process.platform !== 'win32' ? process.umask(0o177) : undefined;
+    try {
+      await new Promise<void>((resolve, reject) => {
+        server.once('error', reject);
+        server.listen(socketPath, () => {
+          server.off('error', reject);
+          resolve();
+        });
+      });
+    } finally {
+      if (umask !== undefined) process.umask(umask);
+    }
+    this.server = server;
+  }
+
+  // Resolves once a command sent stop
+  stopped(): Promise<void> {
+    return new Promise(resolve => {
+      this.stopRequested = resolve;
+    });
+  }
+
+  // Disconnects the commands, which schedule their own calls from then on
+  stop(): Promise<void> {
+    const server = this.server;
+    this.server = undefined;
+    if (!server) return Promise.resolve();
+    for (const socket of this.sockets) socket.destroy();
+    return new Promise(resolve => server.close(() => resolve()));
+  }
+
+  status(): DaemonStatus {
+    const { quota } = this.options;
+    return {
+      pid: process.pid,
+      socket: this.options.socketPath,
+      startedAt: this.startedAt.toISOString(),
+      clients: this.sockets.size,
+      inFlight: this.inFlight,
+      waiting: this.waiting,
+      granted: this.granted,
+      rateLimited: this.rateLimited,
+      pausedUntil: this.pausedUntil > Date.now() ? new Date(this.pausedUntil).toISOString() : undefined,
+      requestsPerMinute: this.options.requestsPerMinute,
+      quota: quota.enabled ? { ...quota.usage(), requestsPerDay: quota.config.requestsPerDay, tokensPerDay: quota.config.tokensPerDay } : undefined,
+    };
+  }
+
+  private accept(socket: net.Socket): void {
+    this.sockets.add(socket);
+    const leases = new Map<number, Lease>();
+    let buffer = '';
+    socket.setEncoding('utf8');
+    socket.on('data', (chunk: string) => {
+      buffer += chunk;
+      for (let newline = buffer.indexOf('\n'); newline !== -1; newline = buffer.indexOf('\n')) {
+        const line = buffer.slice(0, newline);
+        buffer = buffer.slice(newline + 1);
+        if (line.trim()) this.handle(socket, leases, line);
+      }
+    });
+    socket.on('error', () => {
+      // Followed by close
+    });
+    // A command that exits mid-call has spent its reservation
+    socket.on('close', () => {
+      this.sockets.delete(socket);
+      for (const lease of leases.values()) lease.release(lease.tokens);
+      leases.clear();
+    });
+  }
+
+  private reply(socket: net.Socket, reply: DaemonReply): void {
+    if (!socket.destroyed) socket.write(`${JSON.stringify(reply)}\n`);
+  }
+
+  private handle(socket: net.Socket, leases: Map<number, Lease>, line: string): void {
+    let message: DaemonRequest;
+    try {
+      message = JSON.parse(line) as DaemonRequest;
+    } catch (error) {
+      this.reply(socket, { id: 0, error: { code: 'INVALID_INPUT', message: 'Messages are JSON objects, one per line' } });
+      return;
+    }
+    switch (message.op) {
+      case 'acquire':
+        this.acquire(socket, leases, message.id, message.label || 'call', Math.max(0, Math.floor(Number(message.tokens) || 0)));
+        return;
+      case 'release': {
+        const lease = leases.get(message.id);
+        leases.delete(message.id);
+        if (lease) lease.release(Number.isFinite(message.tokens) ? message.tokens : lease.tokens);
+        return;
+      }
+      case 'backoff':
+        this.backoff(message.label || 'a call', Number(message.pauseMs) || 0);
+        return;
+      case 'status':
+        this.reply(socket, { id: message.id, status: this.status() });
+        return;
+      case 'stop':
+        this.reply(socket, { id: message.id });
+        this.stopRequested?.();
+        return;
+      default:
+        this.reply(socket, { id: (message as { id: number }).id, error: { code: 'INVALID_INPUT', message: `Unknown op "${(message as { op: string }).op}"` } });
+    }
+  }
+
+  private acquire(socket: net.Socket, leases: Map<number, Lease>, id: number, label: string, tokens: number): void {
+    this.waiting++;
+    // Runs for as long as the command's call: the quota keeps the tokens reserved until release
+    const call = () => new Promise<number>(resolve => {
+      this.waiting--;
+      this.inFlight++;
+      this.granted++;
+      const lease: Lease = {
+        tokens,
+        release: used => {
+          this.inFlight--;
+          resolve(used);
+        },
+      };
+      if (socket.destroyed) {
+        lease.release(tokens);
+        return;
+      }
+      leases.set(id, lease);
+      this.reply(socket, { id });
+    });
+    const paced = async () => {
+      await this.paused();
+      await this.bucket?.take();
+      return call();
+    };
+    const { quota } = this.options;
+    (quota.enabled ? quota.run(label, tokens, paced, used => used) : paced()).catch(error => {
+      // Raised before the call was granted: a used up budget with on_exhausted = "fail", or a stop
+      this.waiting--;
+      const err = toFileSearchError(error);
+      this.reply(socket, { id, error: { code: err.code, message: err.message } });
+    });
+  }
+
+  private async paused(): Promise<void> {
+    while (Date.now() < this.pausedUntil) await sleep(this.pausedUntil - Date.now(), this.options.signal);
+  }
+
+  private backoff(label: string, pauseMs: number): void {
+    const until = Date.now() + Math.max(0, pauseMs);
+    if (until <= this.pausedUntil) return;  // Another command's pause already covers it
+    this.pausedUntil = until;
+    this.rateLimited++;
+    console.error(`⏸️  Rate limited on ${label}; holding every command back ${Math.round(pauseMs / 1000)}s`);
+  }
+}
+
+// A command's connection to the daemon, made on its first call. Without a daemon, or once
+// it goes away, the calls fall back to the command's own scheduling. The socket does not
+// keep the process alive between calls.
+export class DaemonLink {
+  private socket?: net.Socket;
+  private connecting?: Promise<net.Socket | undefined>;
+  private readonly pending = new Map<number, (reply: DaemonReply | undefined) => void>();
+  private nextId = 1;
+  private buffer = '';
+
+  constructor(readonly socketPath: string = daemonSocketPath()) {}
+
+  // The link of the commands, unless GEMINI_FS_DAEMON=off
+  static fromEnvironment(env: NodeJS.ProcessEnv = process.env): DaemonLink | undefined {
+    return env[DAEMON_ENV] === 'off' ? undefined : new DaemonLink(daemonSocketPath(env));
+  }
+
+  // Runs task once the daemon grants it, as QuotaScheduler.run does locally; local runs the
+  // task the command's own way when there is no daemon. A 429 that outlived the retries
+  // pauses every connected command, and the task is started again afterwards.
+  async run<T>(label: string, tokens: number, task: () => Promise<T>, actualTokens: ((result: T) => number | undefined) | undefined, local: () => Promise<T>): Promise<T> {
+    for (let pauses = 0; ; pauses++) {
+      const reply = await this.request({ op: 'acquire', label, tokens });
+      if (!reply) return local();
+      if (reply.error) throw new FileSearchError(reply.error.code, reply.error.message);
+      let result: T;
+      try {
+        result = await task();
+      } catch (error) {
+        this.send({ id: reply.id, op: 'release', tokens: 0 });
+        const err = toFileSearchError(error);
+        if (err.code !== 'QUOTA_EXCEEDED') throw error;
+        const pauseMs = rateLimitPauseMs(err.cause, pauses);
+        console.error(`⏸️  Rate limited on ${label}; the daemon pauses every command ${Math.round(pauseMs / 1000)}s before trying again`);
+        this.send({ id: reply.id, op: 'backoff', label, pauseMs });
+        continue;
+      }
+      this.send({ id: reply.id, op: 'release', tokens: actualTokens?.(result) ?? tokens });
+      return result;
+    }
+  }
+
+  // The daemon's status, or undefined when none answers on the socket
+  async status(): Promise<DaemonStatus | undefined> {
+    return (await this.request({ op: 'status' }, CONTROL_TIMEOUT_MS))?.status;
+  }
+
+  // Whether a daemon was there to stop
+  async stop(): Promise<boolean> {
+    return (await this.request({ op: 'stop' }, CONTROL_TIMEOUT_MS)) !== undefined;
+  }
+
+  close(): void {
+    this.socket?.end();
+  }
+
+  // Resolves to undefined when no daemon answers
+  async request(message: DaemonMessage, timeoutMs?: number): Promise<DaemonReply | undefined> {
+    const socket = await this.connect();
+    if (!socket) return undefined;
+    const id = this.nextId++;
+    return new Promise(resolve => {
+      let timer: NodeJS.Timeout | undefined;
+      const done = (reply: DaemonReply | undefined) => {
+        clearTimeout(timer);
+        this.pending.delete(id);
+        if (this.pending.size === 0) socket.unref();
+        resolve(reply);
+      };
+      this.pending.set(id, done);
+      socket.ref();
+      if (timeoutMs) timer = setTimeout(() => done(undefined), timeoutMs);
+      socket.write(`${JSON.stringify({ ...message, id })}\n`);
+    });
+  }
+
+  private send(message: DaemonRequest): void {
+    if (this.socket && !this.socket.destroyed) this.socket.write(`${JSON.stringify(message)}\n`);
+  }
+
+  private connect(): Promise<net.Socket | undefined> {
+    if (!this.connecting) {
+      this.connecting = new Promise(resolve => {
+        // Most commands run without a daemon; skip the attempt when there is no socket
+        if (process.platform !== 'win32' && !fs.existsSync(this.socketPath)) {
+          resolve(undefined);
+          return;
+        }
+        const socket = net.connect(this.socketPath);
+        socket.once('error', () => resolve(undefined));
+        socket.once('connect', () => {
+          socket.unref();
+          socket.setEncoding('utf8');
+          socket.on('data', (chunk: string) => this.receive(chunk));
+          socket.on('error', () => {
+            // Followed by close
+          });
+          socket.on('close', () => this.disconnected());
+          this.socket = socket;
+          resolve(socket);
+        });
+      });
+    }
+    return this.connecting;
+  }
+
+  private receive(chunk: string): void {
+    this.buffer += chunk;
+    for (let newline = this.buffer.indexOf('\n'); newline !== -1; newline = this.buffer.indexOf('\n')) {
+      const line = this.buffer.slice(0, newline);
+      this.buffer = this.buffer.slice(newline + 1);
+      try {
+        const reply = JSON.parse(line) as DaemonReply;
+        this.pending.get(reply.id)?.(reply);
+      } catch (error) {
+        // Not a reply of this protocol
+      }
+    }
+  }
+
+  // Calls waiting for a grant go ahead on the command's own scheduling
+  private disconnected(): void {
+    const waiting = this.pending.size;
+    this.socket = undefined;
+    this.connecting = Promise.resolve(undefined);
+    for (const done of [...this.pending.values()]) done(undefined);
+    if (waiting > 0) console.error(`⚠️  Lost the daemon on ${this.socketPath}; this command schedules its own calls from now on`);
+  }
+}
+
+export function formatDaemonStatus(status: DaemonStatus): string {
+  const lines = [
+    `Daemon ${status.pid} on ${status.socket}, running since ${status.startedAt}`,
+    `  Commands connected: ${status.clients}`,
+    `  Calls:              ${status.inFlight} in flight, ${status.waiting} waiting, ${status.granted} granted`,
+    `  Rate limit:         ${status.requestsPerMinute ? `${status.requestsPerMinute} requests per minute` : 'none'}`,
+    `  429 pauses:         ${status.rateLimited}${status.pausedUntil ? `, paused until ${status.pausedUntil}` : ''}`,
+  ];
+  if (status.quota) {
+    const { day, requests, tokens, requestsPerDay, tokensPerDay } = status.quota;
+    const of = (value: number, budget?: number) => (budget !== undefined ? `${value}/${budget}` : String(value));
+    lines.push(`  Quota:              ${of(requests, requestsPerDay)} requests, ${of(tokens, tokensPerDay)} tokens on ${day}`);
+  }
+  return lines.join('\n');
+}
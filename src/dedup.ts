@@ -0,0 +1,91 @@
+// dedup.ts - Exact and near-duplicate detection with normalized hashes and simhash
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+
+// Types
+export type DedupMode = 'skip' | 'replace' | 'alias';
+
+export interface Fingerprint {
+  normalized: string;  // SHA-256 of the normalized text, or of the raw bytes for binary files
+  simhash?: string;  // 64-bit hex; absent for binary files and texts too short to compare
+}
+
+export interface DuplicateMatch {
+  of: string;  // Display name of the copy that is kept
+  exact: boolean;  // Same normalized content, as opposed to within the simhash distance
+  distance: number;  // Differing simhash bits, 0 for exact matches
+  inStore: boolean;  // The copy is already indexed, rather than part of the same upload
+  hash?: string;  // Index cache key of the copy, when it is in the store
+  documentName?: string;
+  documentNames?: string[];
+}
+
+export const DEDUP_MODES: DedupMode[] = ['skip', 'replace', 'alias'];
+
+// Near-duplicates differ in at most this many of the 64 simhash bits
+export const DEFAULT_DEDUP_DISTANCE = 3;
+
+// Shingles of this many words are the simhash features
+const SHINGLE_SIZE = 3;
+const MIN_SHINGLES = 8;
+
+// Files with a NUL byte in the first block are compared by their raw bytes only
+const BINARY_SNIFF_BYTES = 8000;
+
+// Line endings, trailing whitespace, blank-line runs and a BOM do not change the content
+export function normalizeText(text: string): string {
+  return text
+    .replace(/^\uFEFF/, '')
+    .replace(/\r\n?/g, '\n')
+    .split('\n')
+    .map(line => line.replace(/\s+$/, ''))
+    .join('\n')
+    .replace(/\n{3,}/g, '\n\n')
+    .trim();
+}
+
+export function simhash(text: string): string | undefined {
+  const words = text.toLowerCase().match(/[\p{L}\p{N}_]+/gu) || [];
+  if (words.length < SHINGLE_SIZE + MIN_SHINGLES - 1) return undefined;
+
+  const weights = new Array<number>(64).fill(0);
+  for (let i = 0; i + SHINGLE_SIZE <= words.length; i++) {
+    const digest = createHash('md5').update(words.slice(i, i + SHINGLE_SIZE).join(' ')).digest();
+    const bits = digest.readBigUInt64BE(0);
+    for (let bit = 0; bit < 64; bit++) {
+      weights[bit] += (bits >> BigInt(bit)) & 1n ? 1 : -1;
+    }
+  }
+  let value = 0n;
+  for (let bit = 0; bit < 64; bit++) {
+    if (weights[bit] > 0) value |= 1n << BigInt(bit);
+  }
+  return value.toString(16).padStart(16, '0');
+}
+
+export function hammingDistance(a: string, b: string): number {
+  let diff = BigInt(`0x${a}`) ^ BigInt(`0x${b}`);
+  let count = 0;
+  while (diff > 0n) {
+    count += Number(diff & 1n);
+    diff >>= 1n;
+  }
+  return count;
+}
+
+export function fingerprintFile(filePath: string): Fingerprint {
+  const bytes = fs.readFileSync(filePath);
+  if (bytes.subarray(0, BINARY_SNIFF_BYTES).includes(0)) {
+    return { normalized: createHash('sha256').update(bytes).digest('hex') };
+  }
+  const text = normalizeText(bytes.toString('utf8'));
+  return { normalized: createHash('sha256').update(text).digest('hex'), simhash: simhash(text) };
+}
+
+// Returns the match when the fingerprints are the same content or within maxDistance
+export function compareFingerprints(a: Fingerprint, b: Fingerprint, maxDistance: number): { exact: boolean; distance: number } | undefined {
+  if (a.normalized === b.normalized) return { exact: true, distance: 0 };
+  if (!a.simhash || !b.simhash) return undefined;
+  const distance = hammingDistance(a.simhash, b.simhash);
+  return distance <= maxDistance ? { exact: false, distance } : undefined;
+}
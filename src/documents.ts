@@ -0,0 +1,145 @@
+// documents.ts - Inspection of the documents indexed in a File Search store
+import { Document } from '@google/genai';
+import { DocumentMetadata, fromCustomMetadata } from './metadata.js';
+import { ChunkingPolicy } from './chunking.js';
+import { estimateTokens } from './cost.js';
+import { InvalidInputError } from './errors.js';
+import { metadataMatcher } from './search.js';
+
+// Types
+export interface DocumentInfo {
+  name: string;
+  displayName: string;
+  state: string;  // ACTIVE, PENDING, FAILED
+  mimeType?: string;
+  sizeBytes: number;
+  createTime?: string;
+  updateTime?: string;
+  metadata: DocumentMetadata;
+  estimatedChunks?: number;  // Only when a chunk size is configured for the file type
+  parts?: string[];  // Document names of a file uploaded in parts, see groupDocumentParts
+}
+
+export type DocumentState = 'active' | 'pending' | 'failed';
+
+export interface DocumentSelection {
+  filter?: string;  // Metadata expression, e.g. "metadata.team=frontend AND NOT stage=final"
+  olderThanMs?: number;  // Created longer ago than this
+  state?: DocumentState;
+}
+
+const AGE_UNITS_MS: { [unit: string]: number } = {
+  s: 1000,
+  m: 60 * 1000,
+  h: 60 * 60 * 1000,
+  d: 24 * 60 * 60 * 1000,
+  w: 7 * 24 * 60 * 60 * 1000,
+};
+
+// Gemini API documents, or RAG files of a Vertex AI corpus
+export function storeOfDocument(documentName: string): string | undefined {
+  return /^(fileSearchStores\/[^/]+)\/documents\/[^/]+$/.exec(documentName)?.[1]
+    || /^(projects\/[^/]+\/locations\/[^/]+\/ragCorpora\/[^/]+)\/ragFiles\/[^/]+$/.exec(documentName)?.[1];
+}
+
+// Chunks are estimated from the size and the chunking rule of the source file;
+// "src/lib.rs#Parser::parse" resolves to the rule for ".rs"
+export function toDocumentInfo(document: Document, chunking?: ChunkingPolicy): DocumentInfo {
+  const displayName = document.displayName || '';
+  const sizeBytes = Number(document.sizeBytes || 0);
+  const config = chunking?.forFile(displayName.split('#')[0]);
+  let estimatedChunks: number | undefined;
+  if (config?.maxTokensPerChunk && sizeBytes > 0) {
+    const step = config.maxTokensPerChunk - (config.maxOverlapTokens || 0);
+    estimatedChunks = Math.max(1, Math.ceil((estimateTokens(sizeBytes) - (config.maxOverlapTokens || 0)) / step));
+  }
+  return {
+    name: document.name || '',
+    displayName,
+    state: (document.state || 'STATE_UNSPECIFIED').replace(/^STATE_/, ''),
+    mimeType: document.mimeType,
+    sizeBytes,
+    createTime: document.createTime,
+    updateTime: document.updateTime,
+    metadata: fromCustomMetadata(document.customMetadata),
+    estimatedChunks,
+  };
+}
+
+export function formatDocumentMarkdown(info: DocumentInfo): string {
+  let markdown = `## ${info.displayName || info.name}\n- **Name**: ${info.name}\n- **State**: ${info.state}\n- **MIME type**: ${info.mimeType || 'N/A'}\n- **Size**: ${info.sizeBytes} bytes\n`;
+  if (info.estimatedChunks !== undefined) {
+    markdown += `- **Estimated chunks**: ${info.estimatedChunks}\n`;
+  }
+  if (info.parts) {
+    markdown += `- **Parts**: ${info.parts.length} (${info.parts.join(', ')})\n`;
+  }
+  markdown += `- **Created**: ${info.createTime ? new Date(info.createTime).toLocaleString() : 'N/A'}\n`;
+  if (info.updateTime && info.updateTime !== info.createTime) {
+    markdown += `- **Updated**: ${new Date(info.updateTime).toLocaleString()}\n`;
+  }
+  const entries = Object.entries(info.metadata);
+  if (entries.length > 0) {
+    markdown += `- **Metadata**: ${entries.map(([key, value]) => `${key}=${Array.isArray(value) ? value.join(',') : value}`).join(', ')}\n`;
+  }
+  return markdown;
+}
+
+// A failed part fails the whole document, a pending one keeps it pending
+const STATE_ORDER = ['FAILED', 'PENDING', 'ACTIVE'];
+
+function leastReady(a: string, b: string): string {
+  // Other states rank as pending
+  const rank = (state: string) => (STATE_ORDER.includes(state) ? STATE_ORDER.indexOf(state) : 1);
+  return rank(b) < rank(a) ? b : a;
+}
+
+// The parts of a file uploaded in parts (part_of metadata) as one document at the
+// position of its first part: named after the file, with the document names of the
+// parts, their total size and chunks, and the state of the least ready part
+export function groupDocumentParts(documents: DocumentInfo[]): DocumentInfo[] {
+  const groups = new Map<string, DocumentInfo>();
+  const grouped: DocumentInfo[] = [];
+  for (const info of documents) {
+    const whole = info.metadata.part_of;
+    if (typeof whole !== 'string') {
+      grouped.push(info);
+      continue;
+    }
+    const group = groups.get(whole);
+    if (!group) {
+      const { part: _part, line_range: _lineRange, ...metadata } = info.metadata;
+      const first: DocumentInfo = { ...info, displayName: whole, metadata, parts: [info.name] };
+      groups.set(whole, first);
+      grouped.push(first);
+      continue;
+    }
+    group.parts?.push(info.name);
+    group.sizeBytes += info.sizeBytes;
+    group.state = leastReady(group.state, info.state);
+    if (group.estimatedChunks !== undefined || info.estimatedChunks !== undefined) {
+      group.estimatedChunks = (group.estimatedChunks || 0) + (info.estimatedChunks || 0);
+    }
+  }
+  return grouped;
+}
+
+// "30d", "12h", "2w", "90m" or "45s"
+export function parseAge(value: string): number {
+  const match = /^(\d+(?:\.\d+)?)\s*([smhdw])$/.exec(value.trim());
+  if (!match) throw new InvalidInputError(`Invalid age "${value}" (expected a number followed by s, m, h, d or w, e.g. 30d)`);
+  return Number(match[1]) * AGE_UNITS_MS[match[2]];
+}
+
+// Documents matching every given criterion; without a creation time a document is never too old
+export function selectDocuments(documents: DocumentInfo[], selection: DocumentSelection, now: number = Date.now()): DocumentInfo[] {
+  const matches = selection.filter ? metadataMatcher(selection.filter) : undefined;
+  return documents.filter(info => {
+    if (selection.state && info.state !== selection.state.toUpperCase()) return false;
+    if (selection.olderThanMs !== undefined) {
+      const created = info.createTime ? Date.parse(info.createTime) : NaN;
+      if (Number.isNaN(created) || now - created <= selection.olderThanMs) return false;
+    }
+    return !matches || matches(info.metadata);
+  });
+}
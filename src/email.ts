@@ -0,0 +1,281 @@
+// email.ts - E-mail (.eml) and mailbox (.mbox) parsing into text documents with thread metadata
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { decodeText } from './encoding.js';
+import { htmlToMarkdown, parseHtml } from './html.js';
+import { DocumentMetadata } from './metadata.js';
+
+// Types
+export interface EmailOptions {
+  stripQuotes?: boolean;  // Leave quoted replies and "Original Message" history out of the body
+}
+
+export interface EmailMessage {
+  subject?: string;
+  from?: string;  // As written, e.g. "Alice <alice@example.com>"
+  fromAddress?: string;  // Lower case
+  to?: string;
+  date?: string;  // ISO 8601, when the Date header parses
+  messageId?: string;  // Without angle brackets
+  threadId?: string;  // Message-ID of the first message of the thread
+  body: string;
+  attachments: string[];  // File names of the parts that are not text
+}
+
+export interface ConvertedEmailFile {
+  path: string;  // Temporary text file; the caller removes it
+  message: EmailMessage;
+}
+
+interface MimePart {
+  headers: Map<string, string>;  // Lower-case names, unfolded values of the first occurrence
+  body: string;  // Raw bytes as latin1, still transfer-encoded
+}
+
+interface ContentType {
+  type: string;  // e.g. "text/plain", lower case
+  params: { [name: string]: string };
+}
+
+const MAX_METADATA_VALUE = 256;
+// Deeper multipart nesting is treated as an attachment
+const MAX_PART_DEPTH = 10;
+
+export function isEmail(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() === '.eml';
+}
+
+export function isMailbox(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() === '.mbox';
+}
+
+// Messages are kept as latin1 strings so every byte survives until the charset is known
+function parsePart(raw: string): MimePart {
+  const match = /\r?\n\r?\n/.exec(raw);
+  const head = match ? raw.slice(0, match.index) : raw;
+  const headers = new Map<string, string>();
+  for (const line of head.replace(/\r?\n[ \t]+/g, ' ').split(/\r?\n/)) {
+    const separator = line.indexOf(':');
+    if (separator <= 0) continue;
+    const name = line.slice(0, separator).trim().toLowerCase();
+    if (!headers.has(name)) headers.set(name, line.slice(separator + 1).trim());
+  }
+  return { headers, body: match ? raw.slice(match.index + match[0].length) : '' };
+}
+
+function escapeRegExp(text: string): string {
+  return text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
+
+function parseContentType(value: string | undefined): ContentType {
+  const [type, ...rest] = (value || 'text/plain').split(';');
+  const params: { [name: string]: string } = {};
+  for (const param of rest) {
+    const separator = param.indexOf('=');
+    if (separator <= 0) continue;
+    params[param.slice(0, separator).trim().toLowerCase()] = param.slice(separator + 1).trim().replace(/^"(.*)"$/, '$1');
+  }
+  return { type: type.trim().toLowerCase() || 'text/plain', params };
+}
+
+function decodeCharset(data: Buffer, charset: string | undefined): string {
+  if (charset) {
+    try {
+      return new TextDecoder(charset).decode(data);
+    } catch (error) {
+      // Unknown label: detected like any other text below
+    }
+  }
+  return decodeText(data).text;
+}
+
+function decodeQuotedPrintable(text: string): Buffer {
+  const unfolded = text.replace(/=\r?\n/g, '');
+  const bytes: number[] = [];
+  for (let i = 0; i < unfolded.length; i++) {
+    const hex = unfolded[i] === '=' ? unfolded.slice(i + 1, i + 3) : '';
+    if (/^[0-9A-Fa-f]{2}$/.test(hex)) {
+      bytes.push(parseInt(hex, 16));
+      i += 2;
+    } else {
+      bytes.push(unfolded.charCodeAt(i) & 0xff);
+    }
+  }
+  return Buffer.from(bytes);
+}
+
+function transferDecode(part: MimePart): Buffer {
+  const encoding = (part.headers.get('content-transfer-encoding') || '').toLowerCase();
+  if (encoding === 'base64') return Buffer.from(part.body.replace(/\s+/g, ''), 'base64');
+  if (encoding === 'quoted-printable') return decodeQuotedPrintable(part.body);
+  return Buffer.from(part.body, 'latin1');
+}
+
+// RFC 2047 encoded words; raw 8-bit header text (usually UTF-8) is decoded first, and
+// the space between two adjacent encoded words is dropped
+function decodeHeader(value: string | undefined): string | undefined {
+  if (value === undefined) return undefined;
+  const text = decodeText(Buffer.from(value, 'latin1')).text;
+  return text
+    .replace(/(=\?[^?\s]+\?[BbQq]\?[^?\s]*\?=)\s+(?==\?)/g, '$1')
+    .replace(/=\?([^?\s]+)\?([BbQq])\?([^?\s]*)\?=/g, (_word, charset: string, encoding: string, encoded: string) => {
+      const data = encoding.toUpperCase() === 'B'
+        ? Buffer.from(encoded, 'base64')
+        : decodeQuotedPrintable(encoded.replace(/_/g, ' '));
+      return decodeCharset(data, charset.replace(/\*.*$/, ''));
+    })
+    .trim() || undefined;
+}
+
+function messageId(value: string | undefined): string | undefined {
+  return value?.match(/<([^>]+)>/)?.[1] || value?.trim() || undefined;
+}
+
+function fileNameOf(part: MimePart, contentType: ContentType): string {
+  const disposition = parseContentType(part.headers.get('content-disposition'));
+  return decodeHeader(disposition.params.filename || contentType.params.name) || contentType.type;
+}
+
+// multipart/alternative keeps its plain text version, or else its HTML one; the other
+// multiparts keep every inline text part. Non-text parts are listed by file name.
+function collectText(part: MimePart, depth: number, texts: string[], attachments: string[]): void {
+  const contentType = parseContentType(part.headers.get('content-type'));
+  const boundary = contentType.params.boundary;
+  if (contentType.type.startsWith('multipart/') && boundary && depth < MAX_PART_DEPTH) {
+    // The preamble before the first boundary and the epilogue after the closing one are dropped
+    const delimiter = `--${escapeRegExp(boundary)}`;
+    const sections = part.body.split(new RegExp(`^${delimiter}(?:--)?[ \\t]*\\r?$`, 'm'));
+    const closed = new RegExp(`^${delimiter}--`, 'm').test(part.body);
+    const parts = sections.slice(1, closed ? -1 : undefined).map(section => parsePart(section.replace(/^\r?\n/, '')));
+    if (contentType.type === 'multipart/alternative') {
+      const types = parts.map(child => parseContentType(child.headers.get('content-type')).type);
+      const preferred = types.indexOf('text/plain') !== -1 ? types.indexOf('text/plain') : types.indexOf('text/html');
+      if (preferred !== -1) {
+        collectText(parts[preferred], depth + 1, texts, attachments);
+        return;
+      }
+    }
+    for (const child of parts) collectText(child, depth + 1, texts, attachments);
+    return;
+  }
+  const disposition = parseContentType(part.headers.get('content-disposition')).type;
+  if (disposition === 'attachment' || (contentType.type !== 'text/plain' && contentType.type !== 'text/html')) {
+    attachments.push(fileNameOf(part, contentType));
+    return;
+  }
+  const text = decodeCharset(transferDecode(part), contentType.params.charset);
+  texts.push(contentType.type === 'text/html' ? htmlToMarkdown(parseHtml(text)) : text);
+}
+
+// Drops ">" lines, the "On ... wrote:" line above them and everything from an
+// "Original Message" separator on
+export function stripQuotedReplies(body: string): string {
+  const lines = body.split('\n');
+  const kept: string[] = [];
+  for (const [i, line] of lines.entries()) {
+    if (/^\s*-{2,}\s*Original Message\s*-{2,}\s*$/i.test(line)) break;
+    if (/^\s*>/.test(line)) continue;
+    if (/\bwrote:\s*$/.test(line)) {
+      const next = lines.slice(i + 1).find(following => following.trim());
+      if (next && /^\s*>/.test(next)) continue;
+    }
+    kept.push(line);
+  }
+  return kept.join('\n');
+}
+
+export function parseEmail(raw: Buffer, options: EmailOptions = {}): EmailMessage {
+  const part = parsePart(raw.toString('latin1'));
+  const texts: string[] = [];
+  const attachments: string[] = [];
+  collectText(part, 0, texts, attachments);
+  let body = texts.join('\n\n').replace(/\r\n?/g, '\n');
+  if (options.stripQuotes) body = stripQuotedReplies(body);
+
+  const from = decodeHeader(part.headers.get('from'));
+  const date = part.headers.get('date') ? new Date(part.headers.get('date') as string) : undefined;
+  const id = messageId(part.headers.get('message-id'));
+  // References lists the thread from its first message; In-Reply-To only names the parent
+  const references = part.headers.get('references')?.match(/<[^>]+>/g) || [];
+  return {
+    subject: decodeHeader(part.headers.get('subject')),
+    from,
+    fromAddress: (from?.match(/<([^>]+)>/)?.[1] || from?.match(/[^\s<>"]+@[^\s<>"]+/)?.[0])?.toLowerCase(),
+    to: decodeHeader(part.headers.get('to')),
+    date: date && !Number.isNaN(date.getTime()) ? date.toISOString() : undefined,
+    messageId: id,
+    threadId: messageId(references[0]) || messageId(part.headers.get('in-reply-to')) || id,
+    body: body.replace(/\n{3,}/g, '\n\n').trim(),
+    attachments,
+  };
+}
+
+// mboxrd: a message starts at a "From " line at the top or after a blank line, and
+// ">From " lines inside messages lose one ">"
+export function splitMailbox(raw: Buffer): Buffer[] {
+  const text = raw.toString('latin1');
+  const starts: number[] = [];
+  const pattern = /(^|\n\r?\n)From [^\n]*\n/g;
+  for (let match = pattern.exec(text); match; match = pattern.exec(text)) {
+    starts.push(match.index + match[1].length);
+  }
+  return starts.map((start, i) => {
+    const message = text.slice(text.indexOf('\n', start) + 1, starts[i + 1] ?? text.length);
+    return Buffer.from(message.replace(/^>(>*From )/gm, '$1'), 'latin1');
+  });
+}
+
+export function readMailbox(filePath: string, options: EmailOptions = {}): EmailMessage[] {
+  return splitMailbox(fs.readFileSync(filePath)).map(raw => parseEmail(raw, options));
+}
+
+// The headers a reader needs stay in the text, so retrieved passages name sender and date
+export function emailText(message: EmailMessage): string {
+  const lines = [
+    message.subject ? `Subject: ${message.subject}` : undefined,
+    message.from ? `From: ${message.from}` : undefined,
+    message.to ? `To: ${message.to}` : undefined,
+    message.date ? `Date: ${message.date}` : undefined,
+    message.attachments.length > 0 ? `Attachments: ${message.attachments.join(', ')}` : undefined,
+  ].filter(line => line);
+  return `${lines.join('\n')}\n\n${message.body}\n`;
+}
+
+export function emailMetadata(message: EmailMessage): DocumentMetadata {
+  const metadata: DocumentMetadata = {};
+  const values: [string, string | undefined][] = [
+    ['email_subject', message.subject],
+    ['email_from', message.fromAddress],
+    ['email_date', message.date],
+    ['message_id', message.messageId],
+    ['thread_id', message.threadId],
+  ];
+  for (const [key, value] of values) {
+    if (value) metadata[key] = value.slice(0, MAX_METADATA_VALUE);
+  }
+  return metadata;
+}
+
+// The message of an .eml file, or the first message of a mailbox
+export function readEmailFile(filePath: string, options: EmailOptions = {}): EmailMessage {
+  const message = isMailbox(filePath) ? readMailbox(filePath, options)[0] : parseEmail(fs.readFileSync(filePath), options);
+  if (!message) throw new Error('no messages in the mailbox');
+  return message;
+}
+
+// Writes the text of the message to a temporary file, or returns null after logging
+// when there is none so the caller can upload the original
+export function convertEmailToTempFile(filePath: string, options: EmailOptions = {}): ConvertedEmailFile | null {
+  try {
+    const message = readEmailFile(filePath, options);
+    const tempPath = path.join(os.tmpdir(), `gemini-email-${randomUUID()}.txt`);
+    fs.writeFileSync(tempPath, emailText(message));
+    return { path: tempPath, message };
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  E-mail conversion failed, uploading original: ${path.basename(filePath)} - ${err.message}`);
+    return null;
+  }
+}
@@ -0,0 +1,317 @@
+// embeddings.ts - Local vector files of Gemini embeddings, and nearest-neighbor search over them offline
+import * as fs from 'fs';
+import { DEFAULT_EMBEDDING_MODEL, FileSearchClient } from './client.js';
+import { InvalidInputError } from './errors.js';
+import { DocumentMetadata } from './metadata.js';
+import { RetrievedChunk } from './retrieve.js';
+
+// Types
+export type VectorFormat = 'jsonl' | 'fvecs';
+
+export interface EmbeddingRecord {
+  id: string;  // "<source>#<passage>"
+  source: string;  // Display name of the document, as File Search would cite it
+  passage: number;  // 0-based position in the document
+  text: string;
+  model: string;
+  metadata?: DocumentMetadata;
+  lsh?: string;  // Random-hyperplane signature in hex, see signature
+  vector: number[];
+}
+
+export interface PassageOptions {
+  size?: number;  // Characters per passage (default: 2000)
+  overlap?: number;  // Characters repeated from the end of the previous passage (default: 200)
+}
+
+export interface EmbedOptions {
+  model?: string;  // Default: gemini-embedding-001
+  dimensions?: number;  // Truncated vectors; the query must use the same
+  taskType?: string;  // Default: RETRIEVAL_DOCUMENT
+}
+
+export interface LocalSearchOptions {
+  exact?: boolean;  // Compare every vector, also above EXACT_SEARCH_LIMIT
+  candidates?: number;  // Vectors compared after the signature pass (default: max(200, 20 * topK))
+}
+
+export interface LocalHit {
+  rank: number;  // 1-based
+  score: number;  // Cosine similarity
+  record: EmbeddingRecord;
+}
+
+export interface HybridHit {
+  rank: number;  // 1-based
+  score: number;  // Reciprocal rank fusion score
+  fileName: string;
+  text: string;
+  found: ('local' | 'store')[];  // Which searches returned the passage
+}
+
+export const DEFAULT_PASSAGE_SIZE = 2000;
+export const DEFAULT_PASSAGE_OVERLAP = 200;
+// Above this many vectors, search compares only the ones with the nearest signatures
+export const EXACT_SEARCH_LIMIT = 10000;
+export const VECTOR_FORMATS: VectorFormat[] = ['jsonl', 'fvecs'];
+
+// The embedding API takes at most 100 texts per request
+const EMBED_BATCH_SIZE = 100;
+const SIGNATURE_BITS = 64;
+const SIGNATURE_SEED = 0x5eed1234;
+// The usual constant of reciprocal rank fusion; larger values flatten the ranks
+const RRF_K = 60;
+
+// Passages end at a blank line, or else a line end, in their second half when there is one
+export function splitPassages(text: string, options: PassageOptions = {}): string[] {
+  const size = options.size ?? DEFAULT_PASSAGE_SIZE;
+  const overlap = Math.min(options.overlap ?? DEFAULT_PASSAGE_OVERLAP, Math.floor(size / 2));
+  const passages: string[] = [];
+  let start = 0;
+  while (start < text.length) {
+    let end = Math.min(start + size, text.length);
+    if (end < text.length) {
+      const window = text.slice(start, end);
+      const paragraph = window.lastIndexOf('\n\n');
+      const line = window.lastIndexOf('\n');
+      const cut = paragraph > size / 2 ? paragraph : line > size / 2 ? line : -1;
+      if (cut !== -1) end = start + cut + 1;
+    }
+    const passage = text.slice(start, end).trim();
+    if (passage) passages.push(passage);
+    if (end >= text.length) break;
+    start = Math.max(end - overlap, start + 1);
+  }
+  return passages;
+}
+
+// Batches of EMBED_BATCH_SIZE; onBatch reports the texts embedded so far
+export async function embedTexts(client: FileSearchClient, texts: string[], options: EmbedOptions = {}, onBatch?: (done: number) => void): Promise<number[][]> {
+  const vectors: number[][] = [];
+  for (let i = 0; i < texts.length; i += EMBED_BATCH_SIZE) {
+    const batch = texts.slice(i, i + EMBED_BATCH_SIZE);
+    const embedded = await client.embed(batch, options.model || DEFAULT_EMBEDDING_MODEL, {
+      taskType: options.taskType || 'RETRIEVAL_DOCUMENT',
+      dimensions: options.dimensions,
+    });
+    if (embedded.length !== batch.length) throw new Error(`Expected ${batch.length} embeddings, got ${embedded.length}`);
+    vectors.push(...embedded);
+    onBatch?.(vectors.length);
+  }
+  return vectors;
+}
+
+// mulberry32, so every run draws the same hyperplanes
+function random(seed: number): () => number {
+  let state = seed >>> 0;
+  return () => {
+    state = (state + 0x6d2b79f5) >>> 0;
+    let t = state;
+    t = Math.imul(t ^ (t >>> 15), t | 1);
+    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+}
+
+const planeCache = new Map<number, Float64Array[]>();
+
+// Gaussian hyperplanes (Box-Muller) per vector length
+function hyperplanes(dimensions: number): Float64Array[] {
+  const cached = planeCache.get(dimensions);
+  if (cached) return cached;
+  const next = random(SIGNATURE_SEED);
+  const planes = Array.from({ length: SIGNATURE_BITS }, () => {
+    const plane = new Float64Array(dimensions);
+    for (let i = 0; i < dimensions; i++) plane[i] = Math.sqrt(-2 * Math.log(1 - next())) * Math.cos(2 * Math.PI * next());
+    return plane;
+  });
+  planeCache.set(dimensions, planes);
+  return planes;
+}
+
+// One bit per hyperplane: the side the vector lies on. Vectors at a small angle share
+// most bits, so the Hamming distance of two signatures estimates their angle.
+export function signature(vector: number[]): string {
+  const words = [0, 0];
+  hyperplanes(vector.length).forEach((plane, bit) => {
+    let dot = 0;
+    for (let i = 0; i < vector.length; i++) dot += plane[i] * vector[i];
+    if (dot >= 0) words[bit >> 5] |= 1 << (bit & 31);
+  });
+  return words.map(word => (word >>> 0).toString(16).padStart(8, '0')).join('');
+}
+
+function popcount(word: number): number {
+  let bits = word - ((word >>> 1) & 0x55555555);
+  bits = (bits & 0x33333333) + ((bits >>> 2) & 0x33333333);
+  return (Math.imul((bits + (bits >>> 4)) & 0x0f0f0f0f, 0x01010101) >>> 24);
+}
+
+function signatureWords(lsh: string): [number, number] {
+  return [parseInt(lsh.slice(0, 8), 16), parseInt(lsh.slice(8, 16), 16)];
+}
+
+function norm(vector: number[]): number {
+  let sum = 0;
+  for (const value of vector) sum += value * value;
+  return Math.sqrt(sum);
+}
+
+export function vectorFormat(filePath: string): VectorFormat {
+  return filePath.toLowerCase().endsWith('.fvecs') ? 'fvecs' : 'jsonl';
+}
+
+// The records without their vectors, next to an .fvecs file in the same order
+export function vectorMetadataPath(filePath: string): string {
+  return `${filePath.replace(/\.fvecs$/i, '')}.meta.jsonl`;
+}
+
+function writeAtomic(filePath: string, data: string | Buffer): void {
+  const tempPath = `${filePath}.${process.pid}.tmp`;
+  fs.writeFileSync(tempPath, data);
+  fs.renameSync(tempPath, filePath);
+}
+
+// JSONL: one record per line, importable with LanceDB or pandas as it is. fvecs: the
+// FAISS layout (per vector an int32 length, then float32 values, little-endian) with
+// the text and metadata in the .meta.jsonl file beside it.
+export function writeVectorFile(filePath: string, records: EmbeddingRecord[]): void {
+  if (vectorFormat(filePath) === 'jsonl') {
+    writeAtomic(filePath, records.map(record => `${JSON.stringify(record)}\n`).join(''));
+    return;
+  }
+  const size = records.reduce((sum, record) => sum + 4 + record.vector.length * 4, 0);
+  const data = Buffer.alloc(size);
+  let offset = 0;
+  for (const record of records) {
+    offset = data.writeInt32LE(record.vector.length, offset);
+    for (const value of record.vector) offset = data.writeFloatLE(value, offset);
+  }
+  writeAtomic(filePath, data);
+  writeAtomic(vectorMetadataPath(filePath), records.map(({ vector: _vector, ...record }) => `${JSON.stringify(record)}\n`).join(''));
+}
+
+function readLines(filePath: string): EmbeddingRecord[] {
+  return fs.readFileSync(filePath, 'utf8').split('\n').filter(line => line.trim()).map((line, i) => {
+    try {
+      return JSON.parse(line) as EmbeddingRecord;
+    } catch (error) {
+      throw new InvalidInputError(`${filePath}:${i + 1}: ${(error as Error).message}`);
+    }
+  });
+}
+
+export function readVectorFile(filePath: string): EmbeddingRecord[] {
+  if (!fs.existsSync(filePath)) throw new InvalidInputError(`Vector file not found: ${filePath}`);
+  if (vectorFormat(filePath) === 'jsonl') return readLines(filePath);
+
+  const metaPath = vectorMetadataPath(filePath);
+  if (!fs.existsSync(metaPath)) throw new InvalidInputError(`${filePath}: ${metaPath} with the texts of the vectors is missing`);
+  const records = readLines(metaPath);
+  const data = fs.readFileSync(filePath);
+  let offset = 0;
+  for (const [i, record] of records.entries()) {
+    if (offset + 4 > data.length) throw new InvalidInputError(`${filePath}: ${i} vectors for ${records.length} records`);
+    const dimensions = data.readInt32LE(offset);
+    offset += 4;
+    if (dimensions <= 0 || offset + dimensions * 4 > data.length) throw new InvalidInputError(`${filePath}: vector ${i} is truncated`);
+    record.vector = Array.from({ length: dimensions }, (_value, j) => data.readFloatLE(offset + j * 4));
+    offset += dimensions * 4;
+  }
+  if (offset !== data.length) throw new InvalidInputError(`${filePath}: more vectors than the ${records.length} records of ${metaPath}`);
+  return records;
+}
+
+// Small files are searched exhaustively. Larger ones first rank every vector by the
+// Hamming distance of its signature to the query's, and compare only the nearest
+// candidates by cosine: approximate, but without an index to build or keep.
+export class VectorIndex {
+  readonly dimensions: number;
+  private readonly norms: number[];
+  private signatures?: [number, number][];
+
+  constructor(readonly records: EmbeddingRecord[]) {
+    this.dimensions = records[0]?.vector.length || 0;
+    const mismatched = records.find(record => record.vector.length !== this.dimensions);
+    if (mismatched) {
+      throw new InvalidInputError(`${mismatched.id} has ${mismatched.vector.length} dimensions, the first vector ${this.dimensions}`);
+    }
+    this.norms = records.map(record => norm(record.vector));
+  }
+
+  // The model of the vectors, so queries are embedded with the same one
+  get model(): string {
+    return this.records[0]?.model || DEFAULT_EMBEDDING_MODEL;
+  }
+
+  private candidates(vector: number[], limit: number): number[] {
+    // Records written without a signature get one here, once
+    this.signatures ||= this.records.map(record => signatureWords(record.lsh || signature(record.vector)));
+    const [high, low] = signatureWords(signature(vector));
+    // Counting sort by distance, 0 to SIGNATURE_BITS
+    const buckets: number[][] = Array.from({ length: SIGNATURE_BITS + 1 }, () => []);
+    this.signatures.forEach(([a, b], i) => buckets[popcount(a ^ high) + popcount(b ^ low)].push(i));
+    const selected: number[] = [];
+    for (const bucket of buckets) {
+      if (selected.length >= limit) break;
+      selected.push(...bucket);
+    }
+    return selected;
+  }
+
+  search(vector: number[], topK: number, options: LocalSearchOptions = {}): LocalHit[] {
+    if (vector.length !== this.dimensions) {
+      throw new InvalidInputError(`The query vector has ${vector.length} dimensions, the vector file ${this.dimensions}`);
+    }
+    const queryNorm = norm(vector);
+    const indices = !options.exact && this.records.length > EXACT_SEARCH_LIMIT
+      ? this.candidates(vector, options.candidates || Math.max(200, 20 * topK))
+      : this.records.map((_record, i) => i);
+    const scored = indices.map(i => {
+      const values = this.records[i].vector;
+      let dot = 0;
+      for (let j = 0; j < values.length; j++) dot += values[j] * vector[j];
+      return { i, score: this.norms[i] > 0 && queryNorm > 0 ? dot / (this.norms[i] * queryNorm) : 0 };
+    });
+    scored.sort((a, b) => b.score - a.score || a.i - b.i);
+    return scored.slice(0, topK).map((entry, rank) => ({ rank: rank + 1, score: entry.score, record: this.records[entry.i] }));
+  }
+}
+
+// Passages are the same when document and whitespace-normalized text are
+function passageKey(fileName: string, text: string): string {
+  return `${fileName}\n${text.replace(/\s+/g, ' ').trim()}`;
+}
+
+// Reciprocal rank fusion: each list adds 1 / (RRF_K + rank) to the passages it returned,
+// so scores of different kinds (cosine, store confidence) never need to be compared
+export function fuseResults(local: LocalHit[], remote: RetrievedChunk[], topK: number): HybridHit[] {
+  const hits = new Map<string, HybridHit>();
+  const add = (fileName: string, text: string, rank: number, found: 'local' | 'store') => {
+    const key = passageKey(fileName, text);
+    const hit = hits.get(key) || { rank: 0, score: 0, fileName, text, found: [] };
+    hit.score += 1 / (RRF_K + rank);
+    if (!hit.found.includes(found)) hit.found.push(found);
+    hits.set(key, hit);
+  };
+  for (const hit of local) add(hit.record.source, hit.record.text, hit.rank, 'local');
+  for (const chunk of remote) add(chunk.fileName, chunk.text, chunk.rank, 'store');
+  return [...hits.values()]
+    .sort((a, b) => b.score - a.score)
+    .slice(0, topK)
+    .map((hit, i) => ({ ...hit, rank: i + 1 }));
+}
+
+function indent(text: string): string {
+  return text.split('\n').map(line => `    ${line}`).join('\n');
+}
+
+export function formatLocalHits(hits: LocalHit[]): string {
+  if (hits.length === 0) return 'No passages found.';
+  return hits.map(hit => `[${hit.rank}] ${hit.record.source} #${hit.record.passage} (score ${hit.score.toFixed(3)})\n${indent(hit.record.text)}`).join('\n\n');
+}
+
+export function formatHybridHits(hits: HybridHit[]): string {
+  if (hits.length === 0) return 'No passages found.';
+  return hits.map(hit => `[${hit.rank}] ${hit.fileName} (${hit.found.join(' + ')}, rrf ${hit.score.toFixed(4)})\n${indent(hit.text)}`).join('\n\n');
+}
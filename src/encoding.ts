@@ -0,0 +1,198 @@
+// encoding.ts - Charset detection, UTF-8 transcoding and normalized names for mixed-encoding corpora
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+
+// Types
+export type TextEncoding =
+  | 'utf-8'
+  | 'utf-16le'
+  | 'utf-16be'
+  | 'shift_jis'
+  | 'euc-jp'
+  | 'gb18030'
+  | 'big5'
+  | 'euc-kr'
+  | 'windows-1252';
+
+export interface DecodedText {
+  text: string;
+  encoding: TextEncoding;
+  bom: boolean;
+}
+
+export interface TranscodedFile {
+  path: string;  // Temporary UTF-8 copy; the caller removes it
+  encoding: TextEncoding;  // Encoding of the original
+  bom: boolean;
+}
+
+// Tried in this order when the bytes are not UTF-8; ties go to the earlier one
+const LEGACY_ENCODINGS: TextEncoding[] = ['shift_jis', 'euc-jp', 'gb18030', 'big5', 'euc-kr', 'windows-1252'];
+
+// Scoring looks at the start of the file only
+const SAMPLE_BYTES = 64 * 1024;
+
+// The most frequent Chinese characters (simplified and traditional) and Hangul syllables:
+// text decoded with the wrong CJK charset rarely produces them
+const COMMON_CJK = new Set([...'的一是不了在人有我他这這个個们們中来來上大为為和国國地到以说說时時要就出会會可也你对對生能而子那得于於着著下自之年过過发發后後作里裏']);
+const COMMON_HANGUL = new Set([...'이다는의에가을하고지한서로기도사리수있니를게어해으자나요대것']);
+
+const MIME_TEXT_TYPES = /^(text\/|application\/(json|xml|sql|javascript|typescript|x-(sh|python|ruby|perl|php|yaml|toml|sql|httpd-php|tex))\b)/;
+
+export function isTextMimeType(mimeType: string | undefined): boolean {
+  return !!mimeType && MIME_TEXT_TYPES.test(mimeType);
+}
+
+function bomOf(data: Buffer): TextEncoding | undefined {
+  if (data.length >= 3 && data[0] === 0xef && data[1] === 0xbb && data[2] === 0xbf) return 'utf-8';
+  if (data.length >= 2 && data[0] === 0xff && data[1] === 0xfe) return 'utf-16le';
+  if (data.length >= 2 && data[0] === 0xfe && data[1] === 0xff) return 'utf-16be';
+  return undefined;
+}
+
+// UTF-16 without a BOM: mostly-ASCII text has a zero in every other byte
+function utf16Without(data: Buffer): TextEncoding | undefined {
+  const sample = data.subarray(0, Math.min(data.length, SAMPLE_BYTES) & ~1);
+  if (sample.length < 4) return undefined;
+  let evenZeros = 0;
+  let oddZeros = 0;
+  for (let i = 0; i < sample.length; i += 2) {
+    if (sample[i] === 0) evenZeros++;
+    if (sample[i + 1] === 0) oddZeros++;
+  }
+  const pairs = sample.length / 2;
+  if (oddZeros > pairs * 0.3 && evenZeros < pairs * 0.05) return 'utf-16le';
+  if (evenZeros > pairs * 0.3 && oddZeros < pairs * 0.05) return 'utf-16be';
+  return undefined;
+}
+
+function decodes(data: Buffer, encoding: TextEncoding): string | undefined {
+  try {
+    return new TextDecoder(encoding, { fatal: true }).decode(data);
+  } catch (error) {
+    return undefined;  // Invalid in this encoding, or not available without full ICU
+  }
+}
+
+// How plausible the decoded text is: letters of the script the charset is for count,
+// control characters, replacement characters and private use code points count against it
+function plausibility(text: string): number {
+  let score = 0;
+  for (const char of text) {
+    const code = char.codePointAt(0) || 0;
+    if (code < 0x80) continue;
+    if (code === 0xfffd || (code >= 0x80 && code <= 0x9f) || (code >= 0xe000 && code <= 0xf8ff)) score -= 10;
+    else if (code >= 0xc0 && code <= 0x24f) score += 1;
+    else if (code >= 0x3040 && code <= 0x30ff) score += 2;
+    else if (COMMON_CJK.has(char) || COMMON_HANGUL.has(char)) score += 3;
+    else if ((code >= 0x4e00 && code <= 0x9fff) || (code >= 0xac00 && code <= 0xd7af)) score += 0.5;
+    else if (code >= 0xff61 && code <= 0xff9f) score -= 1;
+  }
+  return score;
+}
+
+// The sample ends at a line break, so no multibyte character is cut in half
+function sampleOf(data: Buffer): Buffer {
+  if (data.length <= SAMPLE_BYTES) return data;
+  const end = data.lastIndexOf(0x0a, SAMPLE_BYTES);
+  return data.subarray(0, end > 0 ? end : SAMPLE_BYTES);
+}
+
+// BOM first, then UTF-16 by its zero bytes, then strict UTF-8, then the most plausible
+// legacy charset. Returns undefined for binary data.
+export function detectEncoding(data: Buffer): { encoding: TextEncoding; bom: boolean } | undefined {
+  const bom = bomOf(data);
+  if (bom) return { encoding: bom, bom: true };
+  const utf16 = utf16Without(data);
+  if (utf16) return { encoding: utf16, bom: false };
+  if (data.includes(0)) return undefined;
+  if (decodes(data, 'utf-8') !== undefined) return { encoding: 'utf-8', bom: false };
+
+  const sample = sampleOf(data);
+  let best: { encoding: TextEncoding; score: number } | undefined;
+  for (const encoding of LEGACY_ENCODINGS) {
+    const text = decodes(sample, encoding);
+    if (text === undefined) continue;
+    const score = plausibility(text);
+    if (!best || score > best.score) best = { encoding, score };
+  }
+  return { encoding: best?.encoding || 'windows-1252', bom: false };
+}
+
+// Text of any detected encoding as a string; undecodable bytes become U+FFFD
+export function decodeText(data: Buffer): DecodedText {
+  const detected = detectEncoding(data) || { encoding: 'utf-8' as TextEncoding, bom: false };
+  const text = new TextDecoder(detected.encoding).decode(data);
+  return { text: text.replace(/^\uFEFF/, ''), ...detected };
+}
+
+export function readText(filePath: string): string {
+  return decodeText(fs.readFileSync(filePath)).text;
+}
+
+// A UTF-8 copy without BOM, or null when the file already is one or is binary
+export function transcodeToTempFile(filePath: string): TranscodedFile | null {
+  const data = fs.readFileSync(filePath);
+  const detected = detectEncoding(data);
+  if (!detected || (detected.encoding === 'utf-8' && !detected.bom)) return null;
+  const { text, encoding, bom } = decodeText(data);
+  const tempPath = path.join(os.tmpdir(), `gemini-utf8-${randomUUID()}${path.extname(filePath)}`);
+  fs.writeFileSync(tempPath, text, 'utf8');
+  return { path: tempPath, encoding, bom };
+}
+
+// "/" separators, composed Unicode (macOS stores names decomposed) and no control
+// characters or characters the API rejects
+export function normalizeDisplayName(name: string): string {
+  return name
+    .replace(/\\/g, '/')
+    .normalize('NFC')
+    .replace(/[\u0000-\u001f\u007f\uFFFD]/g, '_')
+    .replace(/[<>:"|?*]/g, '_');
+}
+
+// File names whose bytes are not UTF-8 (e.g. Shift_JIS names from old Windows
+// archives) cannot be opened through a string path. Such files are reached through a
+// symlink with a UTF-8 name, and reported under the name decoded from their bytes.
+const namedLinks = new Map<string, string>();  // Link path to relative path under the root
+const linkTargets = new Map<string, string>();  // Resolved path of the decoded name to link path
+let linkDir: string | undefined;
+
+export function isUtf8Name(name: Buffer): boolean {
+  return decodes(name, 'utf-8') !== undefined;
+}
+
+export function decodeName(name: Buffer): string {
+  return normalizeDisplayName(decodeText(name).text);
+}
+
+export function linkLegacyName(rootDir: string, dirPath: string, name: Buffer, relativeDir: string): { path: string; relativePath: string } {
+  const decoded = decodeName(name);
+  const relativePath = relativeDir ? `${relativeDir}/${decoded}` : decoded;
+  if (!linkDir) {
+    linkDir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-names-'));
+    process.once('exit', cleanupNameLinks);
+  }
+  const linkPath = path.join(linkDir, `${randomUUID()}${path.extname(decoded)}`);
+  fs.symlinkSync(Buffer.concat([Buffer.from(path.resolve(dirPath) + path.sep), name]), linkPath);
+  namedLinks.set(linkPath, relativePath);
+  linkTargets.set(path.resolve(rootDir, ...relativePath.split('/')), linkPath);
+  return { path: linkPath, relativePath };
+}
+
+export function linkedRelativePath(filePath: string): string | undefined {
+  return namedLinks.get(filePath);
+}
+
+export function linkedPathOf(filePath: string): string | undefined {
+  return linkTargets.get(path.resolve(filePath));
+}
+
+export function cleanupNameLinks(): void {
+  if (linkDir) fs.rmSync(linkDir, { recursive: true, force: true });
+  linkDir = undefined;
+  namedLinks.clear();
+  linkTargets.clear();
+}
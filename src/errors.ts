@@ -0,0 +1,274 @@
+// errors.ts - Typed errors with machine-readable codes and CLI exit codes
+import { ApiError } from '@google/genai';
+import { BlockedAnswer } from './generation.js';
+
+// Types
+export type ErrorCode =
+  | 'QUOTA_EXCEEDED'
+  | 'FILE_TOO_LARGE'
+  | 'UNSUPPORTED_MIME'
+  | 'STORE_NOT_FOUND'
+  | 'NOT_FOUND'
+  | 'AUTH_FAILED'
+  | 'INVALID_INPUT'
+  | 'CONFIG_INVALID'
+  | 'TIMEOUT'
+  | 'OPERATION_FAILED'
+  | 'NETWORK'
+  | 'BUDGET_EXCEEDED'
+  | 'PREFLIGHT_FAILED'
+  | 'ACCESS_DENIED'
+  | 'ANSWER_BLOCKED'
+  | 'STATE_LOCKED'
+  | 'CANCELLED'
+  | 'API_ERROR'
+  | 'INTERNAL';
+
+// Exit codes for the CLI tools, one per failure category
+export const EXIT_CODES: Record<ErrorCode, number> = {
+  INTERNAL: 1,
+  API_ERROR: 1,
+  INVALID_INPUT: 2,
+  CONFIG_INVALID: 2,
+  AUTH_FAILED: 3,
+  STORE_NOT_FOUND: 4,
+  NOT_FOUND: 4,
+  QUOTA_EXCEEDED: 5,
+  FILE_TOO_LARGE: 6,
+  UNSUPPORTED_MIME: 6,
+  TIMEOUT: 7,
+  NETWORK: 7,
+  OPERATION_FAILED: 8,
+  BUDGET_EXCEEDED: 9,
+  PREFLIGHT_FAILED: 6,
+  ACCESS_DENIED: 10,
+  ANSWER_BLOCKED: 11,
+  STATE_LOCKED: 12,
+  CANCELLED: 130,  // As for a process stopped by SIGINT
+};
+
+// Base class for every error raised by this package; match on code or instanceof
+export class FileSearchError extends Error {
+  constructor(readonly code: ErrorCode, message: string, options?: { cause?: unknown }) {
+    super(message, options);
+    this.name = 'FileSearchError';
+  }
+
+  // Reports and API responses that carry the error show what failed, not the stack or cause
+  toJSON(): { name: string; code: ErrorCode; message: string } {
+    return { name: this.name, code: this.code, message: this.message };
+  }
+}
+
+export class QuotaExceededError extends FileSearchError {
+  constructor(options?: { cause?: unknown }) {
+    super('QUOTA_EXCEEDED', 'API quota exceeded (HTTP 429); wait and retry, or lower --concurrency / --requests-per-minute', options);
+    this.name = 'QuotaExceededError';
+  }
+}
+
+export class FileTooLargeError extends FileSearchError {
+  constructor(readonly filePath: string, readonly limit: number, readonly actual: number) {
+    super('FILE_TOO_LARGE', `File too large: ${filePath} is ${actual} bytes (limit ${limit})`);
+    this.name = 'FileTooLargeError';
+  }
+}
+
+export class UnsupportedMimeError extends FileSearchError {
+  constructor(readonly mimeType?: string, options?: { cause?: unknown }) {
+    super('UNSUPPORTED_MIME', `Unsupported file type${mimeType ? `: ${mimeType}` : ''}`, options);
+    this.name = 'UnsupportedMimeError';
+  }
+}
+
+export class StoreNotFoundError extends FileSearchError {
+  constructor(readonly storeName: string, options?: { cause?: unknown }) {
+    super('STORE_NOT_FOUND', `Store not found: ${storeName}`, options);
+    this.name = 'StoreNotFoundError';
+  }
+}
+
+export class NotFoundError extends FileSearchError {
+  constructor(readonly resource?: string, options?: { cause?: unknown }) {
+    super('NOT_FOUND', `Not found${resource ? `: ${resource}` : ''}`, options);
+    this.name = 'NotFoundError';
+  }
+}
+
+export class AuthFailedError extends FileSearchError {
+  constructor(options?: { cause?: unknown }) {
+    super('AUTH_FAILED', 'Authentication failed: check that the API key is valid and has access to the resource', options);
+    this.name = 'AuthFailedError';
+  }
+}
+
+export class InvalidInputError extends FileSearchError {
+  constructor(message: string, options?: { cause?: unknown }) {
+    super('INVALID_INPUT', message, options);
+    this.name = 'InvalidInputError';
+  }
+}
+
+export class ConfigError extends FileSearchError {
+  constructor(message: string, options?: { cause?: unknown }) {
+    super('CONFIG_INVALID', message, options);
+    this.name = 'ConfigError';
+  }
+}
+
+export class OperationTimeoutError extends FileSearchError {
+  constructor(readonly polls: number, readonly elapsedMs?: number) {
+    super('TIMEOUT', `Timed out waiting for operation after ${polls} polls${elapsedMs !== undefined ? ` (${Math.round(elapsedMs / 1000)}s)` : ''}`);
+    this.name = 'OperationTimeoutError';
+  }
+}
+
+export class RequestTimeoutError extends FileSearchError {
+  constructor(readonly kind: string, readonly timeoutMs: number, options?: { cause?: unknown }) {
+    super('TIMEOUT', `${kind[0].toUpperCase()}${kind.slice(1)} timed out after ${timeoutMs / 1000}s (timeouts.${kind})`, options);
+    this.name = 'RequestTimeoutError';
+  }
+}
+
+export class OperationFailedError extends FileSearchError {
+  constructor(readonly details: unknown) {
+    super('OPERATION_FAILED', `Operation failed: ${JSON.stringify(details)}`);
+    this.name = 'OperationFailedError';
+  }
+}
+
+export class BudgetExceededError extends FileSearchError {
+  constructor(readonly estimatedTokens: number, readonly budget: number) {
+    super('BUDGET_EXCEEDED', `Estimated ${estimatedTokens} tokens exceeds the budget of ${budget} tokens`);
+    this.name = 'BudgetExceededError';
+  }
+}
+
+// Raised by QuotaScheduler when a daily budget is used up and on_exhausted is "fail"
+export class DailyBudgetExceededError extends FileSearchError {
+  constructor(message: string) {
+    super('BUDGET_EXCEEDED', message);
+    this.name = 'DailyBudgetExceededError';
+  }
+}
+
+// Carries every violation found, see formatPreflightReport for the message
+export class PreflightError extends FileSearchError {
+  constructor(readonly violations: { path: string; message: string }[], message: string) {
+    super('PREFLIGHT_FAILED', message);
+    this.name = 'PreflightError';
+  }
+}
+
+// A structured answer that is not valid JSON or does not match the requested schema
+export class SchemaValidationError extends FileSearchError {
+  constructor(readonly errors: string[]) {
+    super('API_ERROR', `Answer does not match the JSON schema: ${errors.slice(0, 5).join('; ')}${errors.length > 5 ? ` (and ${errors.length - 5} more)` : ''}`);
+    this.name = 'SchemaValidationError';
+  }
+}
+
+// A query without access labels while ACL mode is on, see acl.ts
+export class AccessDeniedError extends FileSearchError {
+  constructor(message: string) {
+    super('ACCESS_DENIED', message);
+    this.name = 'AccessDeniedError';
+  }
+}
+
+// Raised where an answer is required (queryStructured, query-cli's exit code) and the
+// safety filters stopped the question or the reply
+export class AnswerBlockedError extends FileSearchError {
+  constructor(readonly blocked: BlockedAnswer) {
+    super('ANSWER_BLOCKED', `${blocked.stage === 'prompt' ? 'Question' : 'Answer'} blocked (${blocked.reason}): ${blocked.message}`);
+    this.name = 'AnswerBlockedError';
+  }
+}
+
+// Another run holds the lock of a local state file, see lock.ts
+export class StateLockedError extends FileSearchError {
+  constructor(readonly filePath: string, readonly lockPath: string, readonly owner?: { pid: number; host: string; acquiredAt: string }) {
+    super('STATE_LOCKED', `${filePath} is in use by ${owner ? `process ${owner.pid} on ${owner.host} since ${owner.acquiredAt}` : 'another run'}; `
+      + `wait for it to finish, or delete ${lockPath} if no other run is active`);
+    this.name = 'StateLockedError';
+  }
+}
+
+export class CancelledError extends FileSearchError {
+  constructor(message: string = 'Cancelled', options?: { cause?: unknown }) {
+    super('CANCELLED', message, options);
+    this.name = 'CancelledError';
+  }
+}
+
+export class NetworkError extends FileSearchError {
+  constructor(message: string, options?: { cause?: unknown }) {
+    super('NETWORK', `Network error: ${message}`, options);
+    this.name = 'NetworkError';
+  }
+}
+
+export class ApiRequestError extends FileSearchError {
+  constructor(readonly status: number, message: string, options?: { cause?: unknown }) {
+    super('API_ERROR', message, options);
+    this.name = 'ApiRequestError';
+  }
+}
+
+// Raised for non-2xx responses from raw fetch calls (e.g. resumable uploads)
+export class HttpStatusError extends Error {
+  constructor(message: string, readonly status: number, readonly retryAfter?: string | null) {
+    super(message);
+    this.name = 'HttpStatusError';
+  }
+}
+
+export function statusOf(error: unknown): number | undefined {
+  if (error instanceof ApiError || error instanceof HttpStatusError || error instanceof ApiRequestError) {
+    return error.status;
+  }
+  return undefined;
+}
+
+export function isNetworkError(error: unknown): boolean {
+  // Failures from fetch (connection reset, DNS, socket timeout)
+  return error instanceof TypeError && /fetch failed|network|socket/i.test(error.message);
+}
+
+// Maps an API or runtime failure to its typed error. storeName, when known,
+// turns a 404 into a StoreNotFoundError.
+export function toFileSearchError(error: unknown, storeName?: string): FileSearchError {
+  if (error instanceof FileSearchError) return error;
+
+  const message = error instanceof Error ? error.message : String(error);
+  const status = statusOf(error);
+  if (status !== undefined) {
+    if (status === 401 || status === 403 || /API[_ ]key not valid|API_KEY_INVALID/i.test(message)) {
+      return new AuthFailedError({ cause: error });
+    }
+    if (status === 429) {
+      return new QuotaExceededError({ cause: error });
+    }
+    if (status === 404) {
+      const document = /(fileSearchStores\/[\w-]+\/documents|ragCorpora\/[\w-]+\/ragFiles)\/[\w-]+/.exec(message)?.[0];
+      if (document) return new NotFoundError(document, { cause: error });
+      const store = storeName || /(fileSearchStores|ragCorpora)\/[\w-]+/.exec(message)?.[0];
+      return store ? new StoreNotFoundError(store, { cause: error }) : new NotFoundError(undefined, { cause: error });
+    }
+    if (status === 400 && /mime|content.type|unsupported file/i.test(message)) {
+      return new UnsupportedMimeError(undefined, { cause: error });
+    }
+    return new ApiRequestError(status, message, { cause: error });
+  }
+  if (isNetworkError(error)) {
+    return new NetworkError(message, { cause: error });
+  }
+  if (error instanceof Error && error.name === 'AbortError') {
+    return new CancelledError(message, { cause: error });
+  }
+  return new FileSearchError('INTERNAL', message, { cause: error });
+}
+
+export function exitCodeFor(error: unknown): number {
+  return EXIT_CODES[toFileSearchError(error).code];
+}
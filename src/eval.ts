@@ -0,0 +1,259 @@
+// eval.ts - Retrieval and answer quality scorecards over a labelled question dataset
+import * as fs from 'fs';
+import { FileSearchClient } from './client.js';
+import { Citation, extractCitations } from './citations.js';
+import { parseFilterExpression } from './search.js';
+import { Semaphore } from './pipeline.js';
+import { InvalidInputError } from './errors.js';
+import { toTokenUsage } from './cost.js';
+import { normalizeQuestion } from './query-cache.js';
+import { ReportFormat } from './progress.js';
+
+// Types
+export type AnswerMatch = 'exact' | 'regex' | 'embedding';
+
+export interface EvalCase {
+  id?: string | number;
+  question: string;
+  expectedSource?: string | string[];  // File(s) the answer should be retrieved from, as display names or path suffixes
+  expectedAnswer?: string;  // Substring, pattern or reference answer, depending on match
+  match?: AnswerMatch;
+  filter?: string;  // Metadata filter expression
+}
+
+export interface EvalCaseResult {
+  id: string | number;
+  question: string;
+  answer?: string;
+  sources: string[];  // Distinct files of the retrieved chunks
+  hit?: boolean;  // An expected source was retrieved
+  precision?: number;  // Cited files that are expected sources
+  recall?: number;  // Expected sources that were cited
+  answerMatch?: boolean;
+  similarity?: number;  // Cosine similarity to expectedAnswer in embedding mode
+  latencyMs: number;
+  error?: string;
+}
+
+export interface EvalOptions {
+  concurrency?: number;
+  model?: string;
+  match?: AnswerMatch;  // Default for cases that do not set one
+  threshold?: number;  // Minimum similarity for an embedding match
+  embeddingModel?: string;
+  onResult?: (result: EvalCaseResult, completed: number) => void;
+}
+
+export interface EvalScorecard {
+  dataset: string;
+  stores: string[];
+  model: string;
+  total: number;
+  errors: number;
+  hitRate?: number;  // Averages are over the cases with the labels they need; absent when none have them
+  citationPrecision?: number;
+  citationRecall?: number;
+  answerAccuracy?: number;
+  meanSimilarity?: number;
+  averageLatencyMs: number;
+  totalTokens: number;
+  cases: EvalCaseResult[];
+}
+
+export const ANSWER_MATCHES: AnswerMatch[] = ['exact', 'regex', 'embedding'];
+export const DEFAULT_SIMILARITY_THRESHOLD = 0.8;
+
+function parseCase(line: string, lineNumber: number): EvalCase {
+  let parsed: EvalCase;
+  try {
+    parsed = JSON.parse(line) as EvalCase;
+  } catch (error) {
+    throw new InvalidInputError(`Line ${lineNumber}: ${(error as Error).message}`, { cause: error });
+  }
+  if (!parsed.question || typeof parsed.question !== 'string') {
+    throw new InvalidInputError(`Line ${lineNumber}: missing "question"`);
+  }
+  if (parsed.match !== undefined && !ANSWER_MATCHES.includes(parsed.match)) {
+    throw new InvalidInputError(`Line ${lineNumber}: match must be one of: ${ANSWER_MATCHES.join(', ')}`);
+  }
+  return parsed;
+}
+
+// The whole dataset is validated before any question is sent
+export function loadEvalDataset(filePath: string): EvalCase[] {
+  return fs.readFileSync(filePath, 'utf8')
+    .split('\n')
+    .map((line, i) => ({ line, lineNumber: i + 1 }))
+    .filter(({ line }) => line.trim())
+    .map(({ line, lineNumber }) => parseCase(line, lineNumber));
+}
+
+function normalizePath(fileName: string): string {
+  return fileName.replace(/\\/g, '/').replace(/^\.\//, '').toLowerCase();
+}
+
+// "src/lib.rs" matches "crate/src/lib.rs" and the other way round, but not "src/mylib.rs"
+export function sourceMatches(fileName: string, expected: string): boolean {
+  const a = normalizePath(fileName);
+  const b = normalizePath(expected);
+  return a === b || a.endsWith(`/${b}`) || b.endsWith(`/${a}`);
+}
+
+export function cosineSimilarity(a: number[], b: number[]): number {
+  let dot = 0;
+  let normA = 0;
+  let normB = 0;
+  for (let i = 0; i < Math.min(a.length, b.length); i++) {
+    dot += a[i] * b[i];
+    normA += a[i] * a[i];
+    normB += b[i] * b[i];
+  }
+  return normA > 0 && normB > 0 ? dot / Math.sqrt(normA * normB) : 0;
+}
+
+function unique(values: string[]): string[] {
+  return [...new Set(values)];
+}
+
+// Chunks referenced by grounding supports are citations; the rest were only retrieved.
+// Answers without supports count every retrieved chunk as cited.
+function scoreSources(citations: Citation[], expected: string[]): Pick<EvalCaseResult, 'sources' | 'hit' | 'precision' | 'recall'> {
+  const sources = unique(citations.map(c => c.fileName));
+  if (expected.length === 0) return { sources };
+  const supported = citations.filter(c => c.startIndex !== undefined);
+  const cited = unique((supported.length > 0 ? supported : citations).map(c => c.fileName));
+  const relevant = cited.filter(file => expected.some(e => sourceMatches(file, e)));
+  return {
+    sources,
+    hit: sources.some(file => expected.some(e => sourceMatches(file, e))),
+    precision: cited.length > 0 ? relevant.length / cited.length : 0,
+    recall: expected.filter(e => cited.some(file => sourceMatches(file, e))).length / expected.length,
+  };
+}
+
+async function scoreAnswer(
+  client: FileSearchClient,
+  answer: string,
+  expected: string,
+  match: AnswerMatch,
+  options: EvalOptions
+): Promise<Pick<EvalCaseResult, 'answerMatch' | 'similarity'>> {
+  if (match === 'regex') {
+    let pattern: RegExp;
+    try {
+      pattern = new RegExp(expected, 'i');
+    } catch (error) {
+      throw new InvalidInputError(`Invalid expectedAnswer pattern: ${(error as Error).message}`, { cause: error });
+    }
+    return { answerMatch: pattern.test(answer) };
+  }
+  if (match === 'embedding') {
+    const [a, b] = await client.embed([answer, expected], options.embeddingModel);
+    const similarity = Math.round(cosineSimilarity(a || [], b || []) * 1000) / 1000;
+    return { answerMatch: similarity >= (options.threshold ?? DEFAULT_SIMILARITY_THRESHOLD), similarity };
+  }
+  return { answerMatch: normalizeQuestion(answer).includes(normalizeQuestion(expected)) };
+}
+
+function mean(values: (number | undefined)[]): number | undefined {
+  const defined = values.filter((value): value is number => value !== undefined);
+  return defined.length > 0 ? Math.round((defined.reduce((sum, value) => sum + value, 0) / defined.length) * 1000) / 1000 : undefined;
+}
+
+export async function runEvaluation(
+  client: FileSearchClient,
+  storeNames: string[],
+  datasetPath: string,
+  options: EvalOptions = {}
+): Promise<EvalScorecard> {
+  const cases = loadEvalDataset(datasetPath);
+  const semaphore = new Semaphore(options.concurrency || 5);
+  const results: EvalCaseResult[] = new Array(cases.length);
+  let completed = 0;
+  let totalTokens = 0;
+
+  await Promise.all(cases.map(async (evalCase, i) => {
+    await semaphore.acquire();
+    const startedAt = Date.now();
+    const id = evalCase.id ?? i + 1;
+    try {
+      const metadataFilter = evalCase.filter ? parseFilterExpression(evalCase.filter) : undefined;
+      const response = await client.query(storeNames, evalCase.question, { model: options.model, metadataFilter });
+      const answer = response.text || '';
+      const latencyMs = Date.now() - startedAt;
+      totalTokens += toTokenUsage(response.usageMetadata)?.totalTokens || 0;
+
+      const expected = evalCase.expectedSource === undefined ? [] : ([] as string[]).concat(evalCase.expectedSource);
+      const citations = extractCitations(response.candidates?.[0]?.groundingMetadata, answer);
+      results[i] = {
+        id,
+        question: evalCase.question,
+        answer,
+        ...scoreSources(citations, expected),
+        ...(evalCase.expectedAnswer !== undefined
+          ? await scoreAnswer(client, answer, evalCase.expectedAnswer, evalCase.match || options.match || 'exact', options)
+          : {}),
+        latencyMs,
+      };
+    } catch (error) {
+      const err = error as Error;
+      console.error(`❌ Eval error (${id}): ${err.message}`);
+      results[i] = { id, question: evalCase.question, sources: [], latencyMs: Date.now() - startedAt, error: err.message };
+    } finally {
+      semaphore.release();
+    }
+    options.onResult?.(results[i], ++completed);
+  }));
+
+  const scored = results.filter(result => !result.error);
+  return {
+    dataset: datasetPath,
+    stores: storeNames,
+    model: options.model || client.model,
+    total: results.length,
+    errors: results.length - scored.length,
+    hitRate: mean(scored.map(result => result.hit === undefined ? undefined : Number(result.hit))),
+    citationPrecision: mean(scored.map(result => result.precision)),
+    citationRecall: mean(scored.map(result => result.recall)),
+    answerAccuracy: mean(scored.map(result => result.answerMatch === undefined ? undefined : Number(result.answerMatch))),
+    meanSimilarity: mean(scored.map(result => result.similarity)),
+    averageLatencyMs: results.length > 0 ? Math.round(results.reduce((sum, result) => sum + result.latencyMs, 0) / results.length) : 0,
+    totalTokens,
+    cases: results,
+  };
+}
+
+function percent(value: number | undefined): string {
+  return value === undefined ? 'n/a' : `${(value * 100).toFixed(1)}%`;
+}
+
+export function formatScorecard(scorecard: EvalScorecard, reportFormat: ReportFormat = 'table'): string {
+  if (reportFormat === 'json') {
+    return JSON.stringify(scorecard, null, 2);
+  }
+
+  const lines = [
+    `📋 Evaluation of ${scorecard.stores.join(', ')} with ${scorecard.model}: ${scorecard.total} questions`,
+    `   Hit rate:   ${percent(scorecard.hitRate)}`,
+    `   Precision:  ${percent(scorecard.citationPrecision)}`,
+    `   Recall:     ${percent(scorecard.citationRecall)}`,
+    `   Answers:    ${percent(scorecard.answerAccuracy)}${scorecard.meanSimilarity !== undefined ? ` (mean similarity ${scorecard.meanSimilarity.toFixed(3)})` : ''}`,
+    `   Errors:     ${scorecard.errors}`,
+    `   Latency:    ${scorecard.averageLatencyMs}ms average`,
+    `   Tokens:     ${scorecard.totalTokens}`,
+  ];
+  const misses = scorecard.cases.filter(result => result.error || result.hit === false || result.answerMatch === false);
+  if (misses.length > 0) {
+    lines.push('');
+    for (const result of misses) {
+      const reasons = result.error
+        ? [`error: ${result.error}`]
+        : [
+          ...(result.hit === false ? [`expected source not retrieved (got ${result.sources.join(', ') || 'none'})`] : []),
+          ...(result.answerMatch === false ? [`answer did not match${result.similarity !== undefined ? ` (similarity ${result.similarity.toFixed(3)})` : ''}`] : []),
+        ];
+      lines.push(`   ✗ ${result.id}: ${reasons.join('; ')}`);
+    }
+  }
+  return lines.join('\n');
+}
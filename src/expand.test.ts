@@ -0,0 +1,87 @@
+// expand.test.ts - Expansion of archives into their members, and what is left when an entry fails to extract
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { archiveMemberOf, expandArchive } from './expand.js';
+
+// Types
+interface TestEntry {
+  name: string;
+  data: string;
+  method?: number;  // 0 (stored) unless given; zip.ts refuses anything but 0 and 8
+}
+
+function tempDir(t: TestContext): string {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'expand-test-'));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  return dir;
+}
+
+// A ZIP archive of the entries, uncompressed; zip.ts does not check the CRC, so it is left 0
+function zip(entries: TestEntry[]): Buffer {
+  const locals: Buffer[] = [];
+  const centrals: Buffer[] = [];
+  let offset = 0;
+  for (const entry of entries) {
+    const name = Buffer.from(entry.name);
+    const data = Buffer.from(entry.data);
+    const local = Buffer.alloc(30);
+    local.writeUInt32LE(0x04034b50, 0);
+    local.writeUInt16LE(entry.method ?? 0, 8);
+    local.writeUInt32LE(data.length, 18);
+    local.writeUInt32LE(data.length, 22);
+    local.writeUInt16LE(name.length, 26);
+    const central = Buffer.alloc(46);
+    central.writeUInt32LE(0x02014b50, 0);
+    central.writeUInt16LE(entry.method ?? 0, 10);
+    central.writeUInt32LE(data.length, 20);
+    central.writeUInt32LE(data.length, 24);
+    central.writeUInt16LE(name.length, 28);
+    central.writeUInt32LE(offset, 42);
+    locals.push(local, name, data);
+    centrals.push(central, name);
+    offset += local.length + name.length + data.length;
+  }
+  const directory = Buffer.concat(centrals);
+  const end = Buffer.alloc(22);
+  end.writeUInt32LE(0x06054b50, 0);
+  end.writeUInt16LE(entries.length, 8);
+  end.writeUInt16LE(entries.length, 10);
+  end.writeUInt32LE(directory.length, 12);
+  end.writeUInt32LE(offset, 16);
+  return Buffer.concat([...locals, directory, end]);
+}
+
+function expandedDirs(): string[] {
+  return fs.readdirSync(os.tmpdir()).filter(name => name.startsWith('gemini-archive-'));
+}
+
+test('the members of an archive are extracted once and reused while it is unchanged', t => {
+  const archivePath = path.join(tempDir(t), 'docs.zip');
+  fs.writeFileSync(archivePath, zip([{ name: 'a.md', data: '# A\n' }, { name: 'sub/b.md', data: '# B\n' }]));
+
+  const members = expandArchive(archivePath, 'docs.zip');
+  assert.deepEqual(members.map(member => member.innerPath), ['a.md', 'sub/b.md']);
+  assert.equal(fs.readFileSync(members[1].path, 'utf8'), '# B\n');
+  assert.equal(archiveMemberOf(members[0].path)?.archive, 'docs.zip');
+  assert.equal(expandArchive(archivePath, 'docs.zip'), members);
+});
+
+test('an archive whose extraction fails partway is not cached, and its extracted members are removed', t => {
+  const archivePath = path.join(tempDir(t), 'broken.zip');
+  fs.writeFileSync(archivePath, zip([{ name: 'a.md', data: '# A\n' }, { name: 'b.md', data: '# B\n', method: 99 }]));
+  const before = expandedDirs();
+
+  assert.throws(() => expandArchive(archivePath, 'broken.zip'), /Unsupported ZIP compression method 99: b\.md/);
+  assert.deepEqual(expandedDirs(), before);
+  // The unchanged archive fails again instead of returning the members extracted before the failure
+  assert.throws(() => expandArchive(archivePath, 'broken.zip'), /Unsupported ZIP compression method 99/);
+
+  const stats = fs.statSync(archivePath);
+  fs.writeFileSync(archivePath, zip([{ name: 'a.md', data: '# A\n' }, { name: 'b.md', data: '# B, fixed\n' }]));
+  fs.utimesSync(archivePath, stats.atime, new Date(stats.mtimeMs + 1000));
+  const members = expandArchive(archivePath, 'broken.zip');
+  assert.deepEqual(members.map(member => fs.readFileSync(member.path, 'utf8')), ['# A\n', '# B, fixed\n']);
+});
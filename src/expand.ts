@@ -0,0 +1,134 @@
+// expand.ts - Expansion of .zip and .tar.gz inputs into their files for ingestion
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { linkedPathOf, linkedRelativePath } from './encoding.js';
+import { readTarGz } from './tar.js';
+import { readZip } from './zip.js';
+
+// Types
+export interface ArchiveMember {
+  archive: string;  // Relative path of the archive in the ingested tree
+  innerPath: string;  // Path inside the archive with "/" separators
+  path: string;  // Expanded copy in the temp directory
+}
+
+interface Expansion {
+  dir: string;
+  size: number;
+  mtimeMs: number;
+  members: ArchiveMember[];
+}
+
+export const ARCHIVE_EXTENSIONS = ['.zip', '.tar.gz', '.tgz'];
+
+// Larger archives are uploaded as they are rather than expanded
+export const MAX_EXPANDED_BYTES = 2 * 1024 * 1024 * 1024;
+
+// By archive path; reused while the archive is unchanged, so watch and sync do not re-expand it
+const expansions = new Map<string, Expansion>();
+const members = new Map<string, ArchiveMember>();  // By expanded path
+const virtualPaths = new Map<string, string>();  // "<archive path>/<inner path>" to expanded path
+
+export function isArchive(filePath: string): boolean {
+  const name = filePath.toLowerCase();
+  return ARCHIVE_EXTENSIONS.some(extension => name.endsWith(extension));
+}
+
+// Entry names that are absolute or climb out of the archive are dropped
+function safeInnerPath(name: string): string | undefined {
+  const normalized = path.posix.normalize(name.replace(/\\/g, '/'));
+  if (normalized.startsWith('/') || normalized === '..' || normalized.startsWith('../') || /^[a-zA-Z]:/.test(normalized)) {
+    return undefined;
+  }
+  return normalized === '.' ? undefined : normalized;
+}
+
+function readEntries(filePath: string): { name: string; size: number; read: () => Buffer }[] {
+  if (filePath.toLowerCase().endsWith('.zip')) {
+    return readZip(fs.readFileSync(filePath)).filter(entry => !entry.name.endsWith('/'));
+  }
+  return readTarGz(filePath, MAX_EXPANDED_BYTES).map(entry => ({ name: entry.name, size: entry.data.length, read: () => entry.data }));
+}
+
+function discard(expansion: Expansion): void {
+  for (const member of expansion.members) members.delete(member.path);
+  for (const [virtualPath, memberPath] of virtualPaths) {
+    if (memberPath.startsWith(expansion.dir + path.sep)) virtualPaths.delete(virtualPath);
+  }
+  fs.rmSync(expansion.dir, { recursive: true, force: true });
+}
+
+let cleanupRegistered = false;
+
+// Extracts the archive's regular files into a temp directory. The expanded copies
+// carry the archive's mtime, so sync sees them as unchanged until the archive changes.
+// The expansion is only reused once every member was extracted: when one fails, the
+// others are removed again and the next call retries the whole archive.
+export function expandArchive(filePath: string, archive: string): ArchiveMember[] {
+  const stats = fs.statSync(filePath);
+  const previous = expansions.get(filePath);
+  if (previous && previous.size === stats.size && previous.mtimeMs === stats.mtimeMs) return previous.members;
+  if (previous) {
+    discard(previous);
+    expansions.delete(filePath);
+  }
+
+  const entries = readEntries(filePath);
+  const total = entries.reduce((sum, entry) => sum + entry.size, 0);
+  if (total > MAX_EXPANDED_BYTES) {
+    throw new Error(`${archive} expands to ${total} bytes (limit ${MAX_EXPANDED_BYTES})`);
+  }
+
+  if (!cleanupRegistered) {
+    process.once('exit', cleanupExpandedArchives);
+    cleanupRegistered = true;
+  }
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-archive-'));
+  const expansion: Expansion = { dir, size: stats.size, mtimeMs: stats.mtimeMs, members: [] };
+  try {
+    for (const entry of entries) {
+      const innerPath = safeInnerPath(entry.name);
+      if (!innerPath) {
+        console.error(`⚠️  Skipped unsafe archive entry: ${archive}:${entry.name}`);
+        continue;
+      }
+      const memberPath = path.join(dir, ...innerPath.split('/'));
+      fs.mkdirSync(path.dirname(memberPath), { recursive: true });
+      fs.writeFileSync(memberPath, entry.read());
+      fs.utimesSync(memberPath, stats.atime, stats.mtime);
+      const member = { archive, innerPath, path: memberPath };
+      expansion.members.push(member);
+      members.set(memberPath, member);
+      virtualPaths.set(path.join(path.resolve(filePath), ...innerPath.split('/')), memberPath);
+    }
+  } catch (error) {
+    discard(expansion);
+    throw error;
+  }
+  expansions.set(filePath, expansion);
+  return expansion.members;
+}
+
+export function archiveMemberOf(filePath: string): ArchiveMember | undefined {
+  return members.get(filePath);
+}
+
+// Relative path with "/" separators; expanded members are named "<archive>/<inner path>",
+// files with non-UTF-8 names by their decoded name
+export function relativePathOf(rootDir: string, filePath: string): string {
+  const member = members.get(filePath);
+  if (member) return `${member.archive}/${member.innerPath}`;
+  return linkedRelativePath(filePath) || path.relative(rootDir, filePath).split(path.sep).join('/');
+}
+
+// The inverse of relativePathOf: where the file named by a relative path can be read
+export function resolvePathOf(rootDir: string, relativePath: string): string {
+  const filePath = path.join(rootDir, relativePath);
+  return virtualPaths.get(path.resolve(filePath)) || linkedPathOf(filePath) || filePath;
+}
+
+export function cleanupExpandedArchives(): void {
+  for (const expansion of expansions.values()) discard(expansion);
+  expansions.clear();
+}
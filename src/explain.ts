@@ -0,0 +1,311 @@
+// explain.ts - Section-by-section explanations of one file, grounded in the rest of its store
+import * as path from 'path';
+import { GroundingMetadata } from '@google/genai';
+import { FileSearchClient, QueryOptions, UploadResult } from './client.js';
+import { IndexCache, hashFile } from './cache.js';
+import { Citation, extractCitations, formatFootnotes } from './citations.js';
+import { chunkCode } from './code-chunker.js';
+import { TokenUsage, formatUsage, toTokenUsage } from './cost.js';
+import { toDocumentInfo } from './documents.js';
+import { readText } from './encoding.js';
+import { InvalidInputError } from './errors.js';
+import { DirectoryIngester, toDisplayName } from './ingest.js';
+import { Semaphore } from './pipeline.js';
+
+// Types
+export interface FileSection {
+  title: string;  // Symbols or headings the section covers
+  startLine: number;  // 1-based, inclusive
+  endLine: number;
+  text: string;
+}
+
+export interface SectionExplanation {
+  title: string;
+  startLine: number;
+  endLine: number;
+  explanation: string;
+  citations: number[];  // Footnote numbers of the FileExplanation citations used by this section
+  error?: string;
+}
+
+export interface FileExplanation {
+  file: string;
+  displayName: string;
+  documentName?: string;  // Document of the file in the store, when it has one
+  uploaded: boolean;  // Uploaded for this explanation
+  overview?: string;  // Only for files of several sections
+  sections: SectionExplanation[];
+  citations: Citation[];
+  related: string[];  // Other documents cited, in order of first citation
+  model?: string;
+  usage?: TokenUsage;
+  requests: number;
+}
+
+export interface ExplainOptions extends QueryOptions {
+  rootDir?: string;  // Display names are relative to it (default: the directory of the file)
+  ingester?: DirectoryIngester;  // Uploads the file when the store does not have it; without one it is explained from the local text only
+  indexCache?: IndexCache;  // Finds the document of the file by its content
+  sectionLines?: number;  // Target section length (default: 150)
+  maxSections?: number;  // Sections grow beyond sectionLines to stay within this (default: 20)
+  concurrency?: number;  // Section queries in flight at once (default: 3)
+}
+
+export const DEFAULT_SECTION_LINES = 150;
+export const DEFAULT_MAX_SECTIONS = 20;
+
+// Section explanations quoted in the overview prompt are cut to this length
+const OVERVIEW_EXCERPT_CHARS = 1500;
+
+const MARKDOWN_EXTENSIONS = new Set(['.md', '.mdx', '.markdown']);
+
+// Titles of the units a section is made of; long lists are shortened
+function joinTitles(titles: string[]): string {
+  const unique = [...new Set(titles)];
+  return unique.length > 3 ? `${unique.slice(0, 3).join(', ')} and ${unique.length - 3} more` : unique.join(', ');
+}
+
+// Headings outside code fences start a unit; text before the first heading is its own
+function markdownUnits(lines: string[]): { title: string; start: number; end: number }[] {
+  const starts: { title: string; start: number }[] = [];
+  let fence: string | undefined;
+  lines.forEach((line, i) => {
+    const marker = /^(`{3,}|~{3,})/.exec(line)?.[1];
+    if (marker && (!fence || marker.startsWith(fence))) fence = fence ? undefined : marker;
+    const heading = !fence && /^#{1,3}\s+(.+?)\s*#*$/.exec(line);
+    if (heading) starts.push({ title: heading[1], start: i });
+  });
+  if (starts.length === 0 || starts[0].start > 0) starts.unshift({ title: '(preamble)', start: 0 });
+  return starts.map((unit, i) => ({ ...unit, end: i + 1 < starts.length ? starts[i + 1].start - 1 : lines.length - 1 }));
+}
+
+function sectionUnits(filePath: string, lines: string[], sectionLines: number): { title: string; start: number; end: number }[] {
+  const chunks = chunkCode(filePath, lines.join('\n'), { maxLines: sectionLines });
+  if (chunks.length > 0) return chunks.map(chunk => ({ title: chunk.symbol, start: chunk.startLine - 1, end: chunk.endLine - 1 }));
+  if (MARKDOWN_EXTENSIONS.has(path.extname(filePath).toLowerCase())) return markdownUnits(lines);
+  return [{ title: '', start: 0, end: lines.length - 1 }];  // Untitled: named after its line range
+}
+
+// Consecutive units are packed into sections of up to limit lines; longer units are cut
+function pack(units: { title: string; start: number; end: number }[], limit: number): { titles: string[]; start: number; end: number }[] {
+  const sections: { titles: string[]; start: number; end: number }[] = [];
+  for (const unit of units) {
+    for (let start = unit.start, part = 1; start <= unit.end; start += limit, part++) {
+      const end = Math.min(unit.end, start + limit - 1);
+      const whole = start === unit.start && end === unit.end;
+      const title = whole || !unit.title ? unit.title : `${unit.title} (part ${part})`;
+      const last = sections[sections.length - 1];
+      if (whole && last && end - last.start < limit) {
+        last.titles.push(title);
+        last.end = end;
+      } else {
+        sections.push({ titles: [title], start, end });
+      }
+    }
+  }
+  return sections;
+}
+
+// Code files are split along declarations, Markdown along headings and anything else
+// into runs of lines. Files of up to sectionLines lines are a single section.
+export function splitSections(filePath: string, text: string, options: { sectionLines?: number; maxSections?: number } = {}): FileSection[] {
+  const sectionLines = options.sectionLines || DEFAULT_SECTION_LINES;
+  const maxSections = options.maxSections || DEFAULT_MAX_SECTIONS;
+  const lines = text.replace(/\r?\n$/, '').split(/\r?\n/);
+  const units = lines.length <= sectionLines
+    ? [{ title: path.basename(filePath), start: 0, end: lines.length - 1 }]
+    : sectionUnits(filePath, lines, sectionLines);
+
+  let packed = pack(units, sectionLines);
+  for (let limit = sectionLines; packed.length > maxSections; ) {
+    limit = Math.ceil(limit * 1.5);
+    packed = pack(units, limit);
+  }
+  return packed.map(section => ({
+    title: joinTitles(section.titles.filter(Boolean)) || `Lines ${section.start + 1}-${section.end + 1}`,
+    startLine: section.start + 1,
+    endLine: section.end + 1,
+    text: lines.slice(section.start, section.end + 1).join('\n'),
+  }));
+}
+
+// The document of the file: the one the index cache recorded for its content, else the
+// one with its display name, else a fresh upload through the ingester
+async function locateDocument(
+  client: FileSearchClient,
+  storeName: string,
+  filePath: string,
+  displayName: string,
+  rootDir: string,
+  options: ExplainOptions
+): Promise<{ documentName?: string; uploaded: boolean }> {
+  const cached = options.indexCache?.get(storeName, hashFile(filePath));
+  if (cached && (cached.documentName || cached.documentNames?.length)) {
+    return { documentName: cached.documentName || cached.documentNames?.[0], uploaded: false };
+  }
+
+  const documents = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document));
+  const indexed = documents.find(info => info.displayName === displayName)
+    || documents.find(info => info.displayName.split('#')[0] === displayName);
+  if (indexed) {
+    if (options.indexCache?.entries(storeName).some(entry => entry.displayName === displayName)) {
+      console.error(`⚠️  The indexed copy of ${displayName} differs from the local file; related documents may cite the old version`);
+    }
+    return { documentName: indexed.name, uploaded: false };
+  }
+
+  if (!options.ingester) {
+    console.error(`⚠️  ${displayName} is not in the store; explaining it from the local text only`);
+    return { uploaded: false };
+  }
+  console.error(`📤 Uploading ${displayName} to ${storeName}...`);
+  const results: UploadResult[] = [];
+  const summary = await options.ingester.upload(client, storeName, rootDir, [filePath], undefined, (_file, result) => results.push(result));
+  const [result] = results;
+  if (summary.errorCount > 0) {
+    console.error(`⚠️  Upload of ${displayName} failed: ${summary.failures[0]?.reason || 'unknown error'}`);
+  }
+  return { documentName: result?.documentName || result?.documentNames?.[0], uploaded: !!result };
+}
+
+function fenceLanguage(filePath: string): string {
+  return path.extname(filePath).slice(1).toLowerCase();
+}
+
+function sectionPrompt(displayName: string, filePath: string, section: FileSection, single: boolean): string {
+  const fence = section.text.includes('```') ? '~~~~' : '```';
+  return [
+    single
+      ? `Explain the file "${displayName}" to a developer who is new to the codebase.`
+      : `Explain lines ${section.startLine}-${section.endLine} (${section.title}) of the file "${displayName}" to a developer who is new to the codebase.`,
+    'Say what the code or text does and why. Then look through the other documents for code that calls or uses it, configuration that affects it and documentation that describes it, and explain how they relate, naming each document.',
+    'Do not repeat the code itself.',
+    '',
+    `${fence}${fenceLanguage(filePath)}`,
+    section.text,
+    fence,
+  ].join('\n');
+}
+
+function overviewPrompt(displayName: string, sections: SectionExplanation[]): string {
+  return [
+    `Give a short overview of the file "${displayName}": its purpose, its main parts and how it fits into the rest of the codebase.`,
+    'Use the other documents to say where it is used and how it is configured. The parts of the file have already been explained as follows:',
+    '',
+    ...sections.map((section, i) => {
+      const excerpt = section.explanation.length > OVERVIEW_EXCERPT_CHARS ? `${section.explanation.slice(0, OVERVIEW_EXCERPT_CHARS)}...` : section.explanation;
+      return `${i + 1}. Lines ${section.startLine}-${section.endLine} (${section.title}): ${excerpt.replace(/\s*\n\s*/g, ' ')}`;
+    }),
+  ].join('\n');
+}
+
+function addUsage(total: TokenUsage | undefined, usage: TokenUsage | undefined): TokenUsage | undefined {
+  if (!usage) return total;
+  return {
+    promptTokens: (total?.promptTokens || 0) + usage.promptTokens,
+    responseTokens: (total?.responseTokens || 0) + usage.responseTokens,
+    totalTokens: (total?.totalTokens || 0) + usage.totalTokens,
+  };
+}
+
+// Explains a file in the context of the stores: each section is sent with its text and a
+// request to find the callers, configuration and documentation of it among the other
+// documents. The file is uploaded first when the store does not have it yet, so its own
+// passages are retrievable too. Files of several sections get an overview on top.
+export async function explainFile(
+  client: FileSearchClient,
+  storeNames: string[],
+  filePath: string,
+  options: ExplainOptions = {}
+): Promise<FileExplanation> {
+  if (storeNames.length === 0) throw new InvalidInputError('At least one store is required');
+  const file = path.resolve(filePath);
+  const rootDir = path.resolve(options.rootDir || path.dirname(file));
+  const relative = path.relative(rootDir, file);
+  const displayName = relative.startsWith('..') || path.isAbsolute(relative) ? path.basename(file) : toDisplayName(rootDir, file);
+
+  const text = readText(file);
+  if (!text.trim()) throw new InvalidInputError(`${displayName} is empty`);
+  const sections = splitSections(file, text, options);
+  const { documentName, uploaded } = await locateDocument(client, storeNames[0], file, displayName, rootDir, options);
+
+  const citations: Citation[] = [];
+  const related: string[] = [];
+  let usage: TokenUsage | undefined;
+  let requests = 0;
+  const isSelf = (fileName: string) => fileName === displayName || fileName.split('#')[0] === displayName;
+  // Numbered across all queries; offsets are dropped since they point into the single answers
+  const collect = (metadata: GroundingMetadata | undefined, answer: string): number[] =>
+    extractCitations(metadata, answer).map(citation => {
+      citations.push({ ...citation, index: citations.length + 1, startIndex: undefined, endIndex: undefined });
+      if (!isSelf(citation.fileName) && !related.includes(citation.fileName)) related.push(citation.fileName);
+      return citations.length;
+    });
+
+  const ask = async (prompt: string) => {
+    const response = await client.query(storeNames, prompt, options);
+    requests++;
+    usage = addUsage(usage, toTokenUsage(response.usageMetadata));
+    const answer = response.text || '';
+    return { answer, metadata: response.candidates?.[0]?.groundingMetadata };
+  };
+
+  const semaphore = new Semaphore(options.concurrency || 3);
+  const answered = await Promise.all(sections.map(async section => {
+    try {
+      return { section, ...(await semaphore.run(() => ask(sectionPrompt(displayName, file, section, sections.length === 1)))) };
+    } catch (error) {
+      return { section, error: error as Error };
+    }
+  }));
+  if (answered.every(result => result.error)) throw answered[0].error;
+
+  // Citations are numbered in section order, not in completion order
+  const explained: SectionExplanation[] = answered.map(({ section, answer, metadata, error }) => ({
+    title: section.title,
+    startLine: section.startLine,
+    endLine: section.endLine,
+    explanation: answer || '',
+    citations: error ? [] : collect(metadata, answer || ''),
+    ...(error ? { error: error.message } : {}),
+  }));
+
+  let overview: string | undefined;
+  if (sections.length > 1) {
+    try {
+      const result = await ask(overviewPrompt(displayName, explained.filter(section => !section.error)));
+      overview = result.answer;
+      collect(result.metadata, result.answer);
+    } catch (error) {
+      console.error(`⚠️  Overview of ${displayName} failed: ${(error as Error).message}`);
+    }
+  }
+
+  return { file, displayName, documentName, uploaded, overview, sections: explained, citations, related, model: options.model || client.model, usage, requests };
+}
+
+export function formatExplanationMarkdown(explanation: FileExplanation): string {
+  let markdown = `# ${explanation.displayName}\n\n`;
+  markdown += explanation.documentName
+    ? `_Document: ${explanation.documentName}${explanation.uploaded ? ' (uploaded for this explanation)' : ''}_\n\n`
+    : '_Not indexed in the store; explained from the local file_\n\n';
+  if (explanation.overview) markdown += `## Overview\n\n${explanation.overview}\n\n`;
+  const single = explanation.sections.length === 1;
+  for (const section of explanation.sections) {
+    if (!single) markdown += `## Lines ${section.startLine}-${section.endLine}: ${section.title}\n\n`;
+    markdown += section.error ? `_Explanation failed: ${section.error}_\n\n` : `${section.explanation}\n\n`;
+    if (section.citations.length > 0) markdown += `_Sources: ${section.citations.map(n => `[${n}]`).join(' ')}_\n\n`;
+  }
+  if (explanation.related.length > 0) {
+    markdown += `## Related Documents\n\n${explanation.related.map(name => {
+      const numbers = explanation.citations.filter(c => c.fileName === name).map(c => `[${c.index}]`);
+      return `- ${name} ${numbers.join(' ')}`;
+    }).join('\n')}\n\n`;
+  }
+  if (explanation.citations.length > 0) markdown += `## Citations\n\n${formatFootnotes(explanation.citations)}\n\n`;
+  if (explanation.usage) {
+    markdown += `_Usage: ${formatUsage(explanation.model || '', explanation.usage)} in ${explanation.requests} ${explanation.requests === 1 ? 'request' : 'requests'}_\n`;
+  }
+  return markdown.trimEnd() + '\n';
+}
@@ -0,0 +1,450 @@
+// extract.ts - Local text extraction for PDF, DOCX and XLSX files
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import * as zlib from 'zlib';
+import { randomUUID } from 'crypto';
+import { ZipEntry, readZip } from './zip.js';
+
+// Types
+export type ExtractFormat = 'pdf' | 'docx' | 'xlsx';
+
+export interface ExtractedText {
+  format: ExtractFormat;
+  text: string;
+  mimeType: 'text/plain' | 'text/markdown';
+}
+
+export interface ExtractedFile {
+  path: string;  // Temporary file holding the text; the caller removes it
+  format: ExtractFormat;
+  mimeType: string;
+}
+
+interface PdfToken {
+  type: 'string' | 'number' | 'operator' | 'array';
+  value: string | number | PdfToken[];
+}
+
+const FORMATS: { [extension: string]: ExtractFormat } = {
+  '.pdf': 'pdf',
+  '.docx': 'docx',
+  '.xlsx': 'xlsx',
+};
+
+export function canExtract(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() in FORMATS;
+}
+
+function decodeXml(text: string): string {
+  return text
+    .replace(/&#x([0-9a-fA-F]+);/g, (_, hex: string) => String.fromCodePoint(parseInt(hex, 16)))
+    .replace(/&#(\d+);/g, (_, decimal: string) => String.fromCodePoint(parseInt(decimal, 10)))
+    .replace(/&lt;/g, '<')
+    .replace(/&gt;/g, '>')
+    .replace(/&quot;/g, '"')
+    .replace(/&apos;/g, '\'')
+    .replace(/&amp;/g, '&');
+}
+
+function attribute(tag: string, name: string): string | undefined {
+  const match = new RegExp(`\\s${name}="([^"]*)"`).exec(tag);
+  return match ? decodeXml(match[1]) : undefined;
+}
+
+function readXml(entries: Map<string, ZipEntry>, name: string): string | undefined {
+  return entries.get(name)?.read().toString('utf8');
+}
+
+function openArchive(data: Buffer): Map<string, ZipEntry> {
+  return new Map(readZip(data).map(entry => [entry.name, entry]));
+}
+
+function markdownTable(rows: string[][]): string {
+  const width = Math.max(...rows.map(row => row.length));
+  const cell = (value: string | undefined): string => (value || '').replace(/\|/g, '\\|').replace(/\s*\n\s*/g, ' ');
+  const line = (row: string[]): string => `| ${Array.from({ length: width }, (_, i) => cell(row[i])).join(' | ')} |`;
+  return [line(rows[0]), `|${' --- |'.repeat(width)}`, ...rows.slice(1).map(line)].join('\n');
+}
+
+// DOCX: paragraphs of word/document.xml, with headings, list items and tables as Markdown
+function docxParagraph(xml: string): string {
+  let text = '';
+  const runs = /<w:t\b[^>]*>([\s\S]*?)<\/w:t>|<w:tab\/>|<w:(?:br|cr)\b[^>]*\/>/g;
+  let match: RegExpExecArray | null;
+  while ((match = runs.exec(xml)) !== null) {
+    if (match[1] !== undefined) {
+      text += decodeXml(match[1]);
+    } else {
+      text += match[0].startsWith('<w:tab') ? '\t' : '\n';
+    }
+  }
+  return text;
+}
+
+function extractDocx(data: Buffer): string {
+  const document = readXml(openArchive(data), 'word/document.xml');
+  if (document === undefined) {
+    throw new Error('Not a Word document (word/document.xml missing)');
+  }
+
+  const blocks: string[] = [];
+  const pattern = /<w:tbl\b[\s\S]*?<\/w:tbl>|<w:p\b[^>]*\/>|<w:p\b[\s\S]*?<\/w:p>/g;
+  let match: RegExpExecArray | null;
+  while ((match = pattern.exec(document)) !== null) {
+    const xml = match[0];
+    if (xml.startsWith('<w:tbl')) {
+      const rows = (xml.match(/<w:tr\b[\s\S]*?<\/w:tr>/g) || []).map(row =>
+        (row.match(/<w:tc\b[\s\S]*?<\/w:tc>/g) || []).map(cellXml =>
+          (cellXml.match(/<w:p\b[\s\S]*?<\/w:p>/g) || []).map(docxParagraph).join(' ').trim()));
+      if (rows.length > 0 && rows.some(row => row.length > 0)) blocks.push(markdownTable(rows));
+      continue;
+    }
+
+    const text = docxParagraph(xml).trim();
+    if (!text) continue;
+    const style = /<w:pStyle w:val="([^"]*)"/.exec(xml)?.[1] || '';
+    const heading = /^(?:Heading|heading)\s?(\d)$/.exec(style);
+    if (heading) {
+      blocks.push(`${'#'.repeat(Math.min(Number(heading[1]), 6))} ${text}`);
+    } else if (style === 'Title') {
+      blocks.push(`# ${text}`);
+    } else if (xml.includes('<w:numPr>')) {
+      blocks.push(`- ${text}`);
+    } else {
+      blocks.push(text);
+    }
+  }
+  return blocks.join('\n\n');
+}
+
+// XLSX: one Markdown table per worksheet, first row as the header
+function columnIndex(reference: string): number {
+  const letters = /^[A-Z]+/.exec(reference)?.[0] || '';
+  let index = 0;
+  for (const letter of letters) {
+    index = index * 26 + (letter.charCodeAt(0) - 64);
+  }
+  return index - 1;
+}
+
+function xlsxText(xml: string): string {
+  // Rich text runs carry their text in <t>, phonetic hints in <rPh> are skipped
+  return (xml.replace(/<rPh\b[\s\S]*?<\/rPh>/g, '').match(/<t\b[^>]*>[\s\S]*?<\/t>/g) || [])
+    .map(t => decodeXml(t.replace(/^<t\b[^>]*>|<\/t>$/g, '')))
+    .join('');
+}
+
+function extractXlsx(data: Buffer): string {
+  const entries = openArchive(data);
+  const workbook = readXml(entries, 'xl/workbook.xml');
+  if (workbook === undefined) {
+    throw new Error('Not an Excel workbook (xl/workbook.xml missing)');
+  }
+
+  const sharedStrings = (readXml(entries, 'xl/sharedStrings.xml')?.match(/<si>[\s\S]*?<\/si>/g) || []).map(xlsxText);
+  const targets = new Map<string, string>();
+  for (const tag of readXml(entries, 'xl/_rels/workbook.xml.rels')?.match(/<Relationship\b[^>]*>/g) || []) {
+    const id = attribute(tag, 'Id');
+    const target = attribute(tag, 'Target');
+    if (id && target) {
+      targets.set(id, target.startsWith('/') ? target.slice(1) : `xl/${target}`);
+    }
+  }
+
+  const sections: string[] = [];
+  for (const tag of workbook.match(/<sheet\b[^>]*>/g) || []) {
+    const name = attribute(tag, 'name') || 'Sheet';
+    const target = targets.get(attribute(tag, 'r:id') || '');
+    const sheet = target ? readXml(entries, target) : undefined;
+    if (sheet === undefined) continue;
+
+    const rows: string[][] = [];
+    for (const rowXml of sheet.match(/<row\b[^>]*>[\s\S]*?<\/row>/g) || []) {
+      const row: string[] = [];
+      const cells = /<c\b([^>]*?)(?:\/>|>([\s\S]*?)<\/c>)/g;
+      let cell: RegExpExecArray | null;
+      while ((cell = cells.exec(rowXml)) !== null) {
+        const [, attributes, body = ''] = cell;
+        const type = attribute(attributes, 't');
+        const raw = /<v>([\s\S]*?)<\/v>/.exec(body)?.[1];
+        let value: string;
+        if (type === 's') {
+          value = sharedStrings[Number(raw)] ?? '';
+        } else if (type === 'inlineStr') {
+          value = xlsxText(body);
+        } else if (type === 'b') {
+          value = raw === '1' ? 'TRUE' : 'FALSE';
+        } else {
+          value = raw !== undefined ? decodeXml(raw) : '';
+        }
+        const reference = attribute(attributes, 'r');
+        row[reference ? columnIndex(reference) : row.length] = value;
+      }
+      if (row.some(value => value)) rows.push(Array.from(row, value => value || ''));
+    }
+    if (rows.length > 0) {
+      sections.push(`## ${name}\n\n${markdownTable(rows)}`);
+    }
+  }
+  return sections.join('\n\n');
+}
+
+// PDF: text-showing operators of every content stream, in file order
+function decodePdfString(bytes: string): string {
+  if (bytes.startsWith('\xfe\xff')) {
+    const even = bytes.slice(2, bytes.length - (bytes.length % 2));
+    return Buffer.from(even, 'latin1').swap16().toString('utf16le');
+  }
+  return bytes;
+}
+
+function readLiteralString(content: string, start: number): { value: string; end: number } {
+  const escapes: { [c: string]: string } = { n: '\n', r: '\r', t: '\t', b: '\b', f: '\f' };
+  let value = '';
+  let depth = 1;
+  let i = start + 1;
+  while (i < content.length) {
+    const c = content[i];
+    if (c === '\\') {
+      const next = content[i + 1];
+      const octal = /^[0-7]{1,3}/.exec(content.slice(i + 1, i + 4));
+      if (octal) {
+        value += String.fromCharCode(parseInt(octal[0], 8) & 0xff);
+        i += 1 + octal[0].length;
+        continue;
+      }
+      if (next === '\r' || next === '\n') {
+        // Line continuation
+        i += next === '\r' && content[i + 2] === '\n' ? 3 : 2;
+        continue;
+      }
+      value += escapes[next] ?? next ?? '';
+      i += 2;
+      continue;
+    }
+    if (c === '(') depth++;
+    if (c === ')' && --depth === 0) break;
+    value += c;
+    i++;
+  }
+  return { value, end: i + 1 };
+}
+
+function tokenize(content: string, cmap: Map<string, string>): PdfToken[] {
+  const stack: PdfToken[][] = [[]];
+  let i = 0;
+  while (i < content.length) {
+    const c = content[i];
+    const tokens = stack[stack.length - 1];
+    if (/\s/.test(c)) {
+      i++;
+    } else if (c === '%') {
+      const end = content.indexOf('\n', i);
+      i = end === -1 ? content.length : end;
+    } else if (c === '(') {
+      const { value, end } = readLiteralString(content, i);
+      tokens.push({ type: 'string', value: decodePdfString(value) });
+      i = end;
+    } else if (c === '<' && content[i + 1] !== '<') {
+      const end = content.indexOf('>', i);
+      const hex = content.slice(i + 1, end === -1 ? content.length : end).replace(/\s/g, '');
+      tokens.push({ type: 'string', value: decodeHexString(hex, cmap) });
+      i = end === -1 ? content.length : end + 1;
+    } else if (c === '[') {
+      stack.push([]);
+      i++;
+    } else if (c === ']') {
+      const array = stack.length > 1 ? stack.pop() : undefined;
+      if (array) stack[stack.length - 1].push({ type: 'array', value: array });
+      i++;
+    } else {
+      const word = /^(?:[+-]?(?:\d+\.?\d*|\.\d+)|<<|>>|\/[^\s/[\]()<>{}%]*|[^\s/[\]()<>{}%]+|.)/.exec(content.slice(i, i + 256));
+      const text = word ? word[0] : c;
+      if (/^[+-]?(?:\d|\.\d)/.test(text)) {
+        tokens.push({ type: 'number', value: Number(text) });
+      } else if (/^[A-Za-z'"*]+$/.test(text)) {
+        tokens.push({ type: 'operator', value: text });
+      }
+      i += text.length;
+    }
+  }
+  return stack[0];
+}
+
+function decodeHexString(hex: string, cmap: Map<string, string>): string {
+  const padded = hex.length % 2 === 1 ? `${hex}0` : hex;
+  if (cmap.size > 0 && padded.length % 4 === 0) {
+    let mapped = '';
+    for (let i = 0; i < padded.length; i += 4) {
+      const code = padded.slice(i, i + 4).toLowerCase();
+      const text = cmap.get(code);
+      if (text === undefined) {
+        mapped = '';
+        break;
+      }
+      mapped += text;
+    }
+    if (mapped) return mapped;
+  }
+  return decodePdfString(Buffer.from(padded, 'hex').toString('latin1'));
+}
+
+function utf16Hex(hex: string): string {
+  return Buffer.from(hex, 'hex').swap16().toString('utf16le');
+}
+
+// Merges the bfchar/bfrange mappings of every ToUnicode CMap in the file
+function parseCMap(content: string, cmap: Map<string, string>): void {
+  for (const block of content.match(/beginbfchar[\s\S]*?endbfchar/g) || []) {
+    for (const [, code, text] of block.matchAll(/<([0-9a-fA-F]{4})>\s*<([0-9a-fA-F]+)>/g)) {
+      if (text.length % 4 === 0) cmap.set(code.toLowerCase(), utf16Hex(text));
+    }
+  }
+  for (const block of content.match(/beginbfrange[\s\S]*?endbfrange/g) || []) {
+    for (const [, low, high, text] of block.matchAll(/<([0-9a-fA-F]{4})>\s*<([0-9a-fA-F]{4})>\s*<([0-9a-fA-F]{4})>/g)) {
+      const start = parseInt(low, 16);
+      const base = parseInt(text, 16);
+      for (let code = start; code <= parseInt(high, 16) && code - start < 0x10000; code++) {
+        cmap.set(code.toString(16).padStart(4, '0'), String.fromCharCode(base + code - start));
+      }
+    }
+  }
+}
+
+function showText(tokens: PdfToken[]): string {
+  let text = '';
+  let operands: PdfToken[] = [];
+  const newline = (): void => {
+    if (text && !text.endsWith('\n')) text += '\n';
+  };
+  const space = (): void => {
+    if (text && !/\s$/.test(text)) text += ' ';
+  };
+
+  for (const token of tokens) {
+    if (token.type !== 'operator') {
+      operands.push(token);
+      continue;
+    }
+    switch (token.value) {
+      case 'Tj':
+      case '\'':
+      case '"': {
+        if (token.value !== 'Tj') newline();
+        const last = operands[operands.length - 1];
+        if (last?.type === 'string') text += last.value as string;
+        break;
+      }
+      case 'TJ': {
+        const array = operands[operands.length - 1];
+        if (array?.type !== 'array') break;
+        for (const item of array.value as PdfToken[]) {
+          if (item.type === 'string') {
+            text += item.value as string;
+          } else if (item.type === 'number' && (item.value as number) < -200) {
+            // Large kerning adjustments separate words
+            space();
+          }
+        }
+        break;
+      }
+      case 'Td':
+      case 'TD': {
+        const ty = operands[operands.length - 1];
+        if (ty?.type === 'number' && ty.value !== 0) {
+          newline();
+        } else {
+          space();
+        }
+        break;
+      }
+      case 'T*':
+      case 'Tm':
+      case 'ET':
+        newline();
+        break;
+    }
+    operands = [];
+  }
+  return text;
+}
+
+function extractPdf(data: Buffer): string {
+  const raw = data.toString('latin1');
+  if (/\/Encrypt\b/.test(raw)) {
+    throw new Error('Encrypted PDFs are not supported');
+  }
+
+  const contents: string[] = [];
+  const cmap = new Map<string, string>();
+  const streams = /<<((?:(?!\bstream\b|\bendobj\b)[\s\S])*?)>>\s*stream\r?\n/g;
+  let match: RegExpExecArray | null;
+  while ((match = streams.exec(raw)) !== null) {
+    const dictionary = match[1];
+    const start = match.index + match[0].length;
+    const declared = /\/Length\s+(\d+)\b(?!\s+\d+\s+R)/.exec(dictionary);
+    let end = declared ? start + Number(declared[1]) : -1;
+    if (end === -1 || raw.slice(end, end + 20).indexOf('endstream') === -1) {
+      end = raw.indexOf('endstream', start);
+    }
+    if (end === -1) break;
+    streams.lastIndex = end;
+
+    // Images, fonts and cross-reference streams carry no page text
+    if (/\/Subtype\s*\/Image|\/Type\s*\/(?:XRef|ObjStm|Metadata)|\/Length[123]\b|\/Subtype\s*\/(?:Type1C|CIDFontType0C|OpenType)/.test(dictionary)) {
+      continue;
+    }
+    const filters = /\/Filter\s*(\[[^\]]*\]|\/\w+)/.exec(dictionary)?.[1] || '';
+    const names = filters.match(/\/\w+/g) || [];
+    if (names.some(name => name !== '/FlateDecode')) continue;
+
+    let content: string;
+    try {
+      const body = Buffer.from(raw.slice(start, end), 'latin1');
+      content = (names.length > 0 ? zlib.inflateSync(body) : body).toString('latin1');
+    } catch (error) {
+      continue;
+    }
+    if (content.includes('begincmap')) {
+      parseCMap(content, cmap);
+    } else if (/\bBT\b/.test(content)) {
+      contents.push(content);
+    }
+  }
+
+  const text = contents.map(content => showText(tokenize(content, cmap))).join('\n');
+  if (!text.trim()) {
+    throw new Error('No extractable text (scanned or image-only PDF?)');
+  }
+  return text;
+}
+
+export function extractText(filePath: string): ExtractedText {
+  const format = FORMATS[path.extname(filePath).toLowerCase()];
+  if (!format) {
+    throw new Error(`Local extraction is not supported for ${path.basename(filePath)}`);
+  }
+  const extract = format === 'pdf' ? extractPdf : format === 'docx' ? extractDocx : extractXlsx;
+  const text = extract(fs.readFileSync(filePath)).replace(/[ \t]+\n/g, '\n').replace(/\n{3,}/g, '\n\n').trim();
+  if (!text) {
+    throw new Error('No extractable text');
+  }
+  return { format, text, mimeType: format === 'pdf' ? 'text/plain' : 'text/markdown' };
+}
+
+// Writes the extracted text to a temporary file, or returns null after logging when
+// extraction fails so the caller can upload the original file instead
+export function extractToTempFile(filePath: string): ExtractedFile | null {
+  try {
+    const { format, text, mimeType } = extractText(filePath);
+    const extension = mimeType === 'text/markdown' ? '.md' : '.txt';
+    const tempPath = path.join(os.tmpdir(), `gemini-extract-${randomUUID()}${extension}`);
+    fs.writeFileSync(tempPath, text);
+    return { path: tempPath, format, mimeType };
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Local extraction failed, uploading original: ${path.basename(filePath)} - ${err.message}`);
+    return null;
+  }
+}
@@ -0,0 +1,155 @@
+// federated.ts - Fan a question out to several File Search stores and merge the answers
+import { GroundingChunk, GroundingMetadata, GroundingSupport } from '@google/genai';
+import { FileSearchClient, QueryOptions } from './client.js';
+import { Citation, extractCitations } from './citations.js';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { toFileSearchError } from './errors.js';
+import { Semaphore } from './pipeline.js';
+import { NoGroundedAnswer, checkGrounding, formatNoGroundedAnswer } from './grounding.js';
+import { BlockedAnswer, blockedAnswer, formatBlockedAnswer } from './generation.js';
+
+// Types
+export type FederationMode = 'combined' | 'fanout';
+
+export interface FederatedTarget {
+  storeName: string;
+  label: string;  // Project name or store name, used as the section heading
+  shards?: string[];  // Stores searched together in place of storeName, for a sharded corpus
+}
+
+export interface StoreAnswer {
+  storeName: string;
+  label: string;
+  answer: string;
+  citationCount: number;
+  usage?: TokenUsage;
+  latencyMs: number;
+  error?: string;
+  noGroundedAnswer?: NoGroundedAnswer;  // Set in grounded-only mode when the store's answer was rejected
+  blocked?: BlockedAnswer;  // Set when the safety filters stopped the store's answer
+}
+
+export interface FederatedAnswer {
+  answer: string;
+  citations: Citation[];  // Numbered across all stores, each tagged with its store
+  groundingMetadata: GroundingMetadata;  // Offsets relative to the merged answer
+  usage?: TokenUsage;
+  perStore: StoreAnswer[];
+}
+
+export const FEDERATION_MODES: FederationMode[] = ['combined', 'fanout'];
+
+function addUsage(total: TokenUsage | undefined, usage: TokenUsage | undefined): TokenUsage | undefined {
+  if (!usage) return total;
+  if (!total) return { ...usage };
+  return {
+    promptTokens: total.promptTokens + usage.promptTokens,
+    responseTokens: total.responseTokens + usage.responseTokens,
+    totalTokens: total.totalTokens + usage.totalTokens,
+  };
+}
+
+// Queries every store on its own, concurrently, and merges the answers into one
+// section per store. Grounding chunks and segments are re-based onto the merged
+// answer so citations and footnote markers work as for a single query.
+// Fails only when every store fails.
+export async function federatedQuery(
+  client: FileSearchClient,
+  targets: FederatedTarget[],
+  question: string,
+  options: QueryOptions & { concurrency?: number; minConfidence?: number } = {}
+): Promise<FederatedAnswer> {
+  const semaphore = new Semaphore(options.concurrency || 5);
+  const responses = await Promise.all(targets.map(async target => {
+    const start = Date.now();
+    try {
+      const response = await semaphore.run(() => client.query(target.shards || [target.storeName], question, options));
+      return { target, response, latencyMs: Date.now() - start };
+    } catch (error) {
+      return { target, error: toFileSearchError(error, target.storeName), latencyMs: Date.now() - start };
+    }
+  }));
+
+  const failed = responses.filter(r => r.error);
+  if (failed.length === responses.length && failed[0]?.error) {
+    throw failed[0].error;
+  }
+
+  let answer = '';
+  const groundingChunks: GroundingChunk[] = [];
+  const groundingSupports: GroundingSupport[] = [];
+  const citations: Citation[] = [];
+  const perStore: StoreAnswer[] = [];
+  let usage: TokenUsage | undefined;
+
+  for (const { target, response, error, latencyMs } of responses) {
+    if (answer) answer += '\n\n';
+    answer += `### ${target.label}\n\n`;
+
+    if (!response) {
+      const message = error?.message || 'Unknown error';
+      answer += `_Query failed: ${message}_`;
+      perStore.push({ storeName: target.storeName, label: target.label, answer: '', citationCount: 0, latencyMs, error: message });
+      continue;
+    }
+
+    const text = response.text || '';
+    const metadata = response.candidates?.[0]?.groundingMetadata;
+    const storeUsage = toTokenUsage(response.usageMetadata);
+    usage = addUsage(usage, storeUsage);
+
+    // Blocked and rejected answers keep their section but contribute no text or grounding
+    const blocked = blockedAnswer(response, text);
+    if (blocked) {
+      answer += `_${formatBlockedAnswer(blocked)}_`;
+      perStore.push({ storeName: target.storeName, label: target.label, answer: '', citationCount: 0, usage: storeUsage, latencyMs, blocked });
+      continue;
+    }
+    const rejected = options.groundedOnly ? checkGrounding(text, metadata, options.minConfidence) : undefined;
+    if (rejected) {
+      answer += `_${formatNoGroundedAnswer(rejected)}_`;
+      perStore.push({ storeName: target.storeName, label: target.label, answer: '', citationCount: 0, usage: storeUsage, latencyMs, noGroundedAnswer: rejected });
+      continue;
+    }
+
+    const byteOffset = Buffer.byteLength(answer, 'utf8');
+    const chunkOffset = groundingChunks.length;
+    const storeCitations = extractCitations(metadata, text);
+
+    groundingChunks.push(...(metadata?.groundingChunks || []));
+    for (const support of metadata?.groundingSupports || []) {
+      groundingSupports.push({
+        ...support,
+        segment: support.segment && {
+          ...support.segment,
+          startIndex: (support.segment.startIndex || 0) + byteOffset,
+          endIndex: (support.segment.endIndex || 0) + byteOffset,
+        },
+        groundingChunkIndices: (support.groundingChunkIndices || []).map(i => i + chunkOffset),
+      });
+    }
+
+    const charOffset = answer.length;
+    for (const citation of storeCitations) {
+      citations.push({
+        ...citation,
+        index: citations.length + 1,
+        store: target.label,
+        startIndex: citation.startIndex !== undefined ? citation.startIndex + charOffset : undefined,
+        endIndex: citation.endIndex !== undefined ? citation.endIndex + charOffset : undefined,
+      });
+    }
+
+    answer += text;
+    perStore.push({
+      storeName: target.storeName,
+      label: target.label,
+      answer: text,
+      citationCount: storeCitations.length,
+      usage: storeUsage,
+      latencyMs,
+    });
+  }
+
+  return { answer, citations, groundingMetadata: { groundingChunks, groundingSupports }, usage, perStore };
+}
@@ -0,0 +1,207 @@
+// file-types.ts - MIME type detection for uploads
+import * as fs from 'fs';
+import * as path from 'path';
+import { parseToml, TomlValue } from './toml.js';
+import { ConfigError } from './errors.js';
+
+interface MimeTypeMap {
+  [key: string]: string;
+}
+
+export interface MimeOverrides {
+  extensions?: MimeTypeMap;  // ".proto" -> "text/plain"
+  filenames?: MimeTypeMap;   // "Dockerfile" -> "text/plain"
+}
+
+const EXTENSION_TYPES: MimeTypeMap = {
+  '.txt': 'text/plain',
+  '.md': 'text/markdown',
+  '.js': 'text/plain',  // Use text/plain instead of application/javascript for Gemini API compatibility
+  '.mjs': 'text/plain',
+  '.ts': 'text/plain',  // Use text/plain instead of application/typescript for Gemini API compatibility
+  '.tsx': 'text/plain',
+  '.json': 'application/json',
+  '.html': 'text/html',
+  '.htm': 'text/html',
+  '.css': 'text/css',
+  '.csv': 'text/csv',
+  '.tsv': 'text/plain',
+  '.py': 'text/plain',
+  '.java': 'text/plain',
+  '.cpp': 'text/plain',
+  '.c': 'text/plain',
+  '.h': 'text/plain',
+  '.go': 'text/plain',
+  '.rs': 'text/plain',
+  '.rb': 'text/plain',
+  '.php': 'text/plain',
+  '.kt': 'text/plain',
+  '.swift': 'text/plain',
+  '.sql': 'text/plain',
+  '.sh': 'text/plain',
+  '.bash': 'text/plain',
+  '.yaml': 'text/plain',
+  '.yml': 'text/plain',
+  '.toml': 'text/plain',
+  '.ini': 'text/plain',
+  '.xml': 'application/xml',
+  '.svg': 'image/svg+xml',
+  '.png': 'image/png',
+  '.jpg': 'image/jpeg',
+  '.jpeg': 'image/jpeg',
+  '.gif': 'image/gif',
+  '.pdf': 'application/pdf',
+  '.docx': 'application/vnd.openxmlformats-officedocument.wordprocessingml.document',
+  '.xlsx': 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+  '.pptx': 'application/vnd.openxmlformats-officedocument.presentationml.presentation',
+  '.eml': 'message/rfc822',  // Uploaded as text, see email.ts
+  '.mbox': 'application/mbox',
+};
+
+// Well-known files that have no extension
+const FILENAME_TYPES: MimeTypeMap = {
+  'Dockerfile': 'text/plain',
+  'Containerfile': 'text/plain',
+  'Makefile': 'text/plain',
+  'GNUmakefile': 'text/plain',
+  'Jenkinsfile': 'text/plain',
+  'Vagrantfile': 'text/plain',
+  'Gemfile': 'text/plain',
+  'Rakefile': 'text/plain',
+  'Procfile': 'text/plain',
+  'LICENSE': 'text/plain',
+  'README': 'text/plain',
+  'CHANGELOG': 'text/plain',
+  '.gitignore': 'text/plain',
+  '.dockerignore': 'text/plain',
+  '.editorconfig': 'text/plain',
+};
+
+// Leading bytes of common binary formats
+const MAGIC_NUMBERS: { bytes: number[]; mimeType: string }[] = [
+  { bytes: [0x25, 0x50, 0x44, 0x46], mimeType: 'application/pdf' },               // %PDF
+  { bytes: [0x89, 0x50, 0x4e, 0x47], mimeType: 'image/png' },
+  { bytes: [0xff, 0xd8, 0xff], mimeType: 'image/jpeg' },
+  { bytes: [0x47, 0x49, 0x46, 0x38], mimeType: 'image/gif' },                     // GIF8
+  { bytes: [0x50, 0x4b, 0x03, 0x04], mimeType: 'application/zip' },               // PK
+  { bytes: [0x1f, 0x8b], mimeType: 'application/gzip' },
+];
+
+const SNIFF_LENGTH = 4096;
+
+function readHead(filePath: string): Buffer {
+  const fd = fs.openSync(filePath, 'r');
+  try {
+    const buffer = Buffer.alloc(SNIFF_LENGTH);
+    const bytesRead = fs.readSync(fd, buffer, 0, SNIFF_LENGTH, 0);
+    return buffer.subarray(0, bytesRead);
+  } finally {
+    fs.closeSync(fd);
+  }
+}
+
+// Content without NUL bytes that decodes as UTF-8 is treated as text
+function looksLikeText(head: Buffer): boolean {
+  if (head.includes(0)) return false;
+  try {
+    new TextDecoder('utf-8', { fatal: true }).decode(head.subarray(0, lastCompleteCharEnd(head)));
+    return true;
+  } catch (error) {
+    return false;
+  }
+}
+
+// Avoids rejecting a multi-byte character cut off at the end of the sniff window
+function lastCompleteCharEnd(head: Buffer): number {
+  for (let i = head.length - 1; i >= Math.max(0, head.length - 4); i--) {
+    const byte = head[i];
+    if ((byte & 0xc0) === 0xc0) return i;  // Lead byte of a multi-byte sequence
+    if ((byte & 0x80) === 0) break;
+  }
+  return head.length;
+}
+
+export function sniffMimeType(head: Buffer): string | undefined {
+  for (const magic of MAGIC_NUMBERS) {
+    if (magic.bytes.every((byte, i) => head[i] === byte)) {
+      return magic.mimeType;
+    }
+  }
+  if (head.length > 0 && looksLikeText(head)) {
+    // Includes extensionless scripts starting with "#!"
+    return 'text/plain';
+  }
+  return undefined;
+}
+
+function toMimeTypeMap(value: TomlValue | undefined, name: string): MimeTypeMap {
+  if (value === undefined) return {};
+  if (typeof value !== 'object' || Array.isArray(value)) {
+    throw new ConfigError(`MIME map: [${name}] must be a table`);
+  }
+  const map: MimeTypeMap = {};
+  for (const [key, mimeType] of Object.entries(value)) {
+    if (typeof mimeType !== 'string') {
+      throw new ConfigError(`MIME map: ${name}.${key} must be a string`);
+    }
+    map[key] = mimeType;
+  }
+  return map;
+}
+
+// Resolves MIME types in order: user overrides (file name, then extension),
+// built-in extensions, well-known file names, then content sniffing.
+export class MimeRegistry {
+  private readonly extensions: MimeTypeMap;
+  private readonly filenames: MimeTypeMap;
+
+  constructor(overrides: MimeOverrides = {}) {
+    this.extensions = {};
+    for (const [ext, mimeType] of Object.entries(overrides.extensions || {})) {
+      const normalized = ext.toLowerCase();
+      this.extensions[normalized.startsWith('.') ? normalized : `.${normalized}`] = mimeType;
+    }
+    this.filenames = { ...(overrides.filenames || {}) };
+  }
+
+  // Reads an override table such as:
+  //   [extensions]
+  //   ".proto" = "text/plain"
+  //   [filenames]
+  //   "BUILD" = "text/plain"
+  static fromTomlFile(filePath: string): MimeRegistry {
+    const table = parseToml(fs.readFileSync(filePath, 'utf8'));
+    return new MimeRegistry({
+      extensions: toMimeTypeMap(table.extensions, 'extensions'),
+      filenames: toMimeTypeMap(table.filenames, 'filenames'),
+    });
+  }
+
+  // Lookup by name only, without touching the file
+  lookup(filePath: string): string | undefined {
+    const baseName = path.basename(filePath);
+    const ext = path.extname(baseName).toLowerCase();
+    return this.filenames[baseName]
+      || this.extensions[ext]
+      || EXTENSION_TYPES[ext]
+      || FILENAME_TYPES[baseName];
+  }
+
+  detect(filePath: string): string {
+    const known = this.lookup(filePath);
+    if (known) return known;
+
+    try {
+      return sniffMimeType(readHead(filePath)) || 'application/octet-stream';
+    } catch (error) {
+      // Unreadable files keep the previous default
+      return 'text/plain';
+    }
+  }
+}
+
+const defaultRegistry = new MimeRegistry();
+
+export function getMimeType(filePath: string): string {
+  return defaultRegistry.detect(filePath);
+}
@@ -0,0 +1,222 @@
+// generation.ts - Sampling parameters and safety settings of generated answers
+import { GenerateContentConfig, GenerateContentResponse, HarmBlockThreshold, HarmCategory, SafetyRating, SafetySetting } from '@google/genai';
+import { ConfigError, InvalidInputError } from './errors.js';
+import { TomlValue, expectTable, isTable } from './toml.js';
+
+// Types
+export type SafetyCategory = 'harassment' | 'hate_speech' | 'sexually_explicit' | 'dangerous_content' | 'civic_integrity';
+
+export type SafetyThreshold = 'off' | 'block_none' | 'block_only_high' | 'block_medium_and_above' | 'block_low_and_above';
+
+export interface GenerationParams {
+  temperature?: number;  // 0-2
+  topP?: number;  // 0-1
+  maxOutputTokens?: number;
+  safety?: { [category in SafetyCategory]?: SafetyThreshold };  // Categories left out keep the API default
+}
+
+export type BlockedStage = 'prompt' | 'response';
+
+export interface BlockedCategory {
+  category: string;  // A SafetyCategory, or the API name lowercased for others
+  probability?: string;  // e.g. "HIGH"
+  severity?: string;  // Vertex AI only
+}
+
+// Returned in place of an answer the safety filters stopped, so "blocked" is not
+// mistaken for "nothing found". The API gives no offset into the prompt, so for a
+// blocked prompt the message and categories are all there is.
+export interface BlockedAnswer {
+  type: 'blocked';
+  stage: BlockedStage;  // The question itself, or the reply while it was generated
+  reason: string;  // The API's blockReason or finishReason, e.g. "SAFETY", "RECITATION"
+  message: string;
+  categories: BlockedCategory[];  // Empty when the reason is not a harm category
+  partialAnswer?: string;  // Text generated before the reply was stopped
+}
+
+export const SAFETY_CATEGORIES: SafetyCategory[] = ['harassment', 'hate_speech', 'sexually_explicit', 'dangerous_content', 'civic_integrity'];
+
+export const SAFETY_THRESHOLDS: SafetyThreshold[] = ['off', 'block_none', 'block_only_high', 'block_medium_and_above', 'block_low_and_above'];
+
+const HARM_CATEGORIES: { [category in SafetyCategory]: HarmCategory } = {
+  harassment: HarmCategory.HARM_CATEGORY_HARASSMENT,
+  hate_speech: HarmCategory.HARM_CATEGORY_HATE_SPEECH,
+  sexually_explicit: HarmCategory.HARM_CATEGORY_SEXUALLY_EXPLICIT,
+  dangerous_content: HarmCategory.HARM_CATEGORY_DANGEROUS_CONTENT,
+  civic_integrity: HarmCategory.HARM_CATEGORY_CIVIC_INTEGRITY,
+};
+
+// Finish reasons that mean the reply was cut off by a filter rather than finished
+const BLOCKING_FINISH_REASONS = ['SAFETY', 'RECITATION', 'BLOCKLIST', 'PROHIBITED_CONTENT', 'SPII', 'IMAGE_SAFETY'];
+
+// Accepts the API spelling too ("HARM_CATEGORY_HATE_SPEECH", "BLOCK_NONE")
+function normalize(value: string): string {
+  return value.trim().toLowerCase().replace(/-/g, '_').replace(/^harm_category_/, '');
+}
+
+function toThreshold(value: string, context: string): SafetyThreshold {
+  const threshold = normalize(value) as SafetyThreshold;
+  if (!SAFETY_THRESHOLDS.includes(threshold)) {
+    throw new InvalidInputError(`${context}: threshold must be one of: ${SAFETY_THRESHOLDS.join(', ')} (got "${value}")`);
+  }
+  return threshold;
+}
+
+function toCategory(value: string, context: string): SafetyCategory {
+  const category = normalize(value) as SafetyCategory;
+  if (!SAFETY_CATEGORIES.includes(category)) {
+    throw new InvalidInputError(`${context}: category must be one of: ${SAFETY_CATEGORIES.join(', ')} (got "${value}")`);
+  }
+  return category;
+}
+
+// "<category>=<threshold>" sets one category, a bare "<threshold>" every category;
+// later values win, so "--safety block_only_high --safety harassment=block_none" works
+export function parseSafetySettings(values: string[]): GenerationParams['safety'] {
+  if (values.length === 0) return undefined;
+  const safety: NonNullable<GenerationParams['safety']> = {};
+  for (const value of values) {
+    const separator = value.indexOf('=');
+    if (separator === -1) {
+      const threshold = toThreshold(value, 'safety');
+      SAFETY_CATEGORIES.forEach(category => { safety[category] = threshold; });
+    } else {
+      safety[toCategory(value.slice(0, separator), 'safety')] = toThreshold(value.slice(separator + 1), 'safety');
+    }
+  }
+  return safety;
+}
+
+export function validateGenerationParams(params: GenerationParams): GenerationParams {
+  const { temperature, topP, maxOutputTokens } = params;
+  if (temperature !== undefined && !(Number.isFinite(temperature) && temperature >= 0 && temperature <= 2)) {
+    throw new InvalidInputError(`temperature must be between 0 and 2, got ${temperature}`);
+  }
+  if (topP !== undefined && !(Number.isFinite(topP) && topP >= 0 && topP <= 1)) {
+    throw new InvalidInputError(`top_p must be between 0 and 1, got ${topP}`);
+  }
+  if (maxOutputTokens !== undefined && !(Number.isInteger(maxOutputTokens) && maxOutputTokens >= 1)) {
+    throw new InvalidInputError(`max_output_tokens must be a positive integer, got ${maxOutputTokens}`);
+  }
+  return params;
+}
+
+// The [generation] table: temperature, top_p, max_output_tokens and safety, which is
+// either one threshold for every category or a table of category = threshold
+export function parseGenerationConfig(value: TomlValue | undefined): GenerationParams {
+  if (value === undefined) return {};
+  expectTable(value, 'generation');
+  const number = (key: string): number | undefined => {
+    const item = value[key];
+    if (item !== undefined && typeof item !== 'number') throw new ConfigError(`generation.${key} must be a number`);
+    return item as number | undefined;
+  };
+
+  try {
+    const safety = value.safety;
+    let parsed: GenerationParams['safety'];
+    if (typeof safety === 'string') {
+      parsed = parseSafetySettings([safety]);
+    } else if (isTable(safety)) {
+      parsed = parseSafetySettings(Object.entries(safety).map(([category, threshold]) => {
+        if (typeof threshold !== 'string') throw new ConfigError(`generation.safety.${category} must be a string`);
+        return `${category}=${threshold}`;
+      }));
+    } else if (safety !== undefined) {
+      throw new ConfigError('generation.safety must be a threshold or a table of category = threshold');
+    }
+    return validateGenerationParams({
+      temperature: number('temperature'),
+      topP: number('top_p'),
+      maxOutputTokens: number('max_output_tokens'),
+      safety: parsed,
+    });
+  } catch (error) {
+    if (error instanceof ConfigError) throw error;
+    throw new ConfigError(`generation: ${(error as Error).message}`);
+  }
+}
+
+// Later layers win; safety settings are merged per category
+export function mergeGenerationParams(...layers: (GenerationParams | undefined)[]): GenerationParams {
+  const merged: GenerationParams = {};
+  for (const layer of layers) {
+    if (!layer) continue;
+    if (layer.temperature !== undefined) merged.temperature = layer.temperature;
+    if (layer.topP !== undefined) merged.topP = layer.topP;
+    if (layer.maxOutputTokens !== undefined) merged.maxOutputTokens = layer.maxOutputTokens;
+    if (layer.safety) merged.safety = { ...merged.safety, ...layer.safety };
+  }
+  return merged;
+}
+
+export function toGenerationConfig(params: GenerationParams): Pick<GenerateContentConfig, 'temperature' | 'topP' | 'maxOutputTokens' | 'safetySettings'> {
+  const safetySettings: SafetySetting[] = Object.entries(params.safety || {}).map(([category, threshold]) => ({
+    category: HARM_CATEGORIES[category as SafetyCategory],
+    threshold: threshold.toUpperCase() as HarmBlockThreshold,
+  }));
+  return {
+    ...(params.temperature !== undefined ? { temperature: params.temperature } : {}),
+    ...(params.topP !== undefined ? { topP: params.topP } : {}),
+    ...(params.maxOutputTokens !== undefined ? { maxOutputTokens: params.maxOutputTokens } : {}),
+    ...(safetySettings.length > 0 ? { safetySettings } : {}),
+  };
+}
+
+// The ratings that caused the block; prompt ratings often lack the blocked flag, so
+// without it the medium and high probabilities are taken
+function toBlockedCategories(ratings: SafetyRating[] = []): BlockedCategory[] {
+  const names = new Map<string, string>(Object.entries(HARM_CATEGORIES).map(([name, category]) => [category, name]));
+  const flagged = ratings.filter(rating => rating.blocked);
+  return (flagged.length > 0 ? flagged : ratings.filter(rating => rating.probability === 'MEDIUM' || rating.probability === 'HIGH'))
+    .map(rating => ({
+      category: names.get(rating.category || '') || normalize(rating.category || 'unspecified'),
+      ...(rating.probability ? { probability: rating.probability } : {}),
+      ...(rating.severity ? { severity: rating.severity.replace(/^HARM_SEVERITY_/, '') } : {}),
+    }));
+}
+
+// Why a response holds no (complete) answer, if a filter stopped it: a blocked prompt
+// carries promptFeedback.blockReason and no candidates, a stopped reply a blocking
+// finishReason. answer is the text received, kept as partialAnswer.
+export function blockedAnswer(response: GenerateContentResponse, answer: string = ''): BlockedAnswer | undefined {
+  const feedback = response.promptFeedback;
+  if (feedback?.blockReason) {
+    return {
+      type: 'blocked',
+      stage: 'prompt',
+      reason: feedback.blockReason,
+      message: feedback.blockReasonMessage || `The question was blocked (${feedback.blockReason}).`,
+      categories: toBlockedCategories(feedback.safetyRatings),
+    };
+  }
+  const candidate = response.candidates?.[0];
+  if (!candidate?.finishReason || !BLOCKING_FINISH_REASONS.includes(candidate.finishReason)) return undefined;
+  return {
+    type: 'blocked',
+    stage: 'response',
+    reason: candidate.finishReason,
+    message: candidate.finishMessage || `The answer was stopped (${candidate.finishReason}).`,
+    categories: toBlockedCategories(candidate.safetyRatings),
+    ...(answer ? { partialAnswer: answer } : {}),
+  };
+}
+
+export function formatBlockedAnswer(blocked: BlockedAnswer): string {
+  const categories = blocked.categories.map(category => category.probability ? `${category.category} (${category.probability.toLowerCase()})` : category.category);
+  return `${blocked.stage === 'prompt' ? 'Question' : 'Answer'} blocked by the safety filters: ${blocked.message}`
+    + (categories.length > 0 ? ` Categories: ${categories.join(', ')}.` : '')
+    + (blocked.reason === 'SAFETY' ? ' The thresholds can be lowered per category with the safety settings.' : '');
+}
+
+// Flat values for query cache keys and reports
+export function describeGeneration(params: GenerationParams): { [name: string]: string | number | undefined } {
+  const safety = Object.entries(params.safety || {}).sort(([a], [b]) => a.localeCompare(b)).map(([category, threshold]) => `${category}=${threshold}`);
+  return {
+    temperature: params.temperature,
+    topP: params.topP,
+    maxOutputTokens: params.maxOutputTokens,
+    safety: safety.length > 0 ? safety.join(',') : undefined,
+  };
+}
@@ -0,0 +1,222 @@
+// git.ts - Indexes a git repository at a ref with per-file commit metadata
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { execFileSync } from 'child_process';
+import { randomUUID } from 'crypto';
+import { Document } from '@google/genai';
+import { FileSearchClient, UploadResult } from './client.js';
+import { DirectoryIngester, IngestOptions, IngestSummary, toDisplayName } from './ingest.js';
+import { DocumentMetadata } from './metadata.js';
+import { archiveMemberOf, isArchive, relativePathOf } from './expand.js';
+import { InvalidInputError, NotFoundError } from './errors.js';
+
+// Types
+export interface GitFileHistory {
+  commit: string;  // Last commit that touched the file
+  author: string;
+  date: string;  // ISO 8601 author date
+}
+
+export interface GitChanges {
+  changed: string[];  // Added or modified paths, relative to the repository root
+  deleted: string[];
+}
+
+export interface GitIngestOptions extends Omit<IngestOptions, 'fileMetadata'> {
+  ref?: string;  // Branch, tag or commit to index (default: HEAD)
+  since?: string;  // Only index files changed between this ref and ref, and remove deleted ones
+}
+
+export interface GitIngestSummary {
+  commit: string;  // Full hash of the indexed ref
+  since?: string;  // Full hash of the since ref
+  upload: IngestSummary;
+  deletedFiles: string[];  // Paths whose documents were removed
+  replacedDocuments: number;  // Earlier documents of re-indexed files that were removed
+}
+
+// Field and record separators of the git log format, which cannot occur in names
+const FIELD = '\x1f';
+const RECORD = '\x1e';
+const MAX_BUFFER = 512 * 1024 * 1024;
+
+function git(repoDir: string, args: string[], env?: NodeJS.ProcessEnv): string {
+  try {
+    return execFileSync('git', ['-C', repoDir, ...args], {
+      encoding: 'utf8',
+      maxBuffer: MAX_BUFFER,
+      stdio: ['ignore', 'pipe', 'pipe'],
+      env: env ? { ...process.env, ...env } : undefined,
+    });
+  } catch (error) {
+    const err = error as Error & { code?: string; stderr?: string };
+    if (err.code === 'ENOENT') throw new InvalidInputError('git is not installed or not on PATH');
+    throw new InvalidInputError(`git ${args[0]} failed: ${(err.stderr || err.message).trim()}`);
+  }
+}
+
+export function isRemoteRepository(source: string): boolean {
+  return /^[a-z][a-z0-9+.-]*:\/\//i.test(source) || /^[^/\\]+@[^/\\]+:/.test(source);
+}
+
+// Local paths are used in place; remote URLs are cloned without a checkout into a
+// temporary directory that dispose removes
+export function openRepository(source: string): { repoDir: string; dispose: () => void } {
+  if (!isRemoteRepository(source)) {
+    const repoDir = path.resolve(source);
+    if (!fs.existsSync(repoDir)) throw new NotFoundError(`Repository not found: ${source}`);
+    git(repoDir, ['rev-parse', '--git-dir']);
+    return { repoDir, dispose: () => undefined };
+  }
+  const repoDir = path.join(os.tmpdir(), `gemini-fs-git-${randomUUID()}`);
+  console.error(`📥 Cloning ${source}`);
+  try {
+    execFileSync('git', ['clone', '--quiet', '--no-checkout', source, repoDir], { stdio: ['ignore', 'ignore', 'pipe'] });
+  } catch (error) {
+    fs.rmSync(repoDir, { recursive: true, force: true });
+    const err = error as Error & { stderr?: Buffer };
+    throw new InvalidInputError(`git clone failed: ${(err.stderr?.toString('utf8') || err.message).trim()}`);
+  }
+  return { repoDir, dispose: () => fs.rmSync(repoDir, { recursive: true, force: true }) };
+}
+
+export function resolveCommit(repoDir: string, ref: string): string {
+  // Remote branches are only available as origin/<branch> in a fresh clone
+  for (const candidate of [ref, `origin/${ref}`]) {
+    try {
+      return git(repoDir, ['rev-parse', '--verify', '--quiet', `${candidate}^{commit}`]).trim();
+    } catch (error) {
+      // Try the next candidate
+    }
+  }
+  throw new NotFoundError(`Unknown git ref: ${ref}`);
+}
+
+// Writes the tree of commit to destDir through a temporary index, so the
+// repository's own index and working tree are left alone
+export function exportTree(repoDir: string, commit: string, destDir: string): void {
+  const env = { GIT_INDEX_FILE: path.join(destDir, '.gemini-fs-index') };
+  fs.mkdirSync(destDir, { recursive: true });
+  try {
+    git(repoDir, ['read-tree', commit], env);
+    git(repoDir, [`--work-tree=${destDir}`, 'checkout-index', '--all', '--force'], env);
+  } finally {
+    fs.rmSync(env.GIT_INDEX_FILE, { force: true });
+  }
+}
+
+// The last commit touching each path, from one walk of the history of commit
+export function fileHistory(repoDir: string, commit: string): Map<string, GitFileHistory> {
+  const output = git(repoDir, ['log', '--no-renames', '--name-only', '-z', `--format=${RECORD}%H${FIELD}%an${FIELD}%aI`, commit]);
+  const history = new Map<string, GitFileHistory>();
+  for (const record of output.split(RECORD).slice(1)) {
+    const [header, ...names] = record.split('\0');
+    const [hash, author, date] = header.split(FIELD);
+    for (const name of names.map(item => item.replace(/^\n/, '')).filter(Boolean)) {
+      if (!history.has(name)) history.set(name, { commit: hash, author, date: date.trim() });
+    }
+  }
+  return history;
+}
+
+export function changedFiles(repoDir: string, from: string, to: string): GitChanges {
+  const fields = git(repoDir, ['diff', '--name-status', '--no-renames', '-z', from, to]).split('\0');
+  const changes: GitChanges = { changed: [], deleted: [] };
+  for (let i = 0; i + 1 < fields.length; i += 2) {
+    (fields[i] === 'D' ? changes.deleted : changes.changed).push(fields[i + 1]);
+  }
+  return changes;
+}
+
+// Unified diff of a range such as "main..feature" or "HEAD~3", or of two refs
+export function diffRange(repoDir: string, range: string, contextLines: number = 3): string {
+  const refs = range.includes('..') ? [range] : range.split(/\s+/).filter(Boolean);
+  return git(repoDir, ['diff', '--no-color', '--no-ext-diff', `--unified=${contextLines}`, ...refs, '--']);
+}
+
+// Content of a file at a ref, or undefined when it does not exist there
+export function fileAtRef(repoDir: string, ref: string, relativePath: string): string | undefined {
+  try {
+    return git(repoDir, ['show', `${ref}:${relativePath}`]);
+  } catch (error) {
+    return undefined;
+  }
+}
+
+// A file's documents are "<path>", or "<path>#<symbol>" with code chunking;
+// an archive's are "<path>/<inner path>"
+function documentsOf(documents: Document[], relativePath: string): Document[] {
+  return documents.filter(doc => doc.displayName === relativePath || doc.displayName?.startsWith(`${relativePath}#`)
+    || (isArchive(relativePath) && doc.displayName?.startsWith(`${relativePath}/`)));
+}
+
+export async function ingestGitRepository(
+  client: FileSearchClient,
+  storeName: string,
+  source: string,
+  options: GitIngestOptions = {}
+): Promise<GitIngestSummary> {
+  const { repoDir, dispose } = openRepository(source);
+  const treeDir = path.join(os.tmpdir(), `gemini-fs-tree-${randomUUID()}`);
+  try {
+    const commit = resolveCommit(repoDir, options.ref || 'HEAD');
+    const since = options.since ? resolveCommit(repoDir, options.since) : undefined;
+    console.error(`🔖 Indexing ${source} at ${commit.slice(0, 12)}${since ? ` (changes since ${since.slice(0, 12)})` : ''}`);
+
+    exportTree(repoDir, commit, treeDir);
+    const history = fileHistory(repoDir, commit);
+    const toRelative = (filePath: string) => relativePathOf(treeDir, filePath);
+    // Files expanded from an archive share the archive's history; the exported tree is
+    // new, so mtime and owner come from the last commit of each file instead
+    const sourceOf = (filePath: string) => archiveMemberOf(filePath)?.archive || toRelative(filePath);
+    const ingester = new DirectoryIngester({
+      ...options,
+      fileMetadata: (filePath): DocumentMetadata => {
+        const relativePath = toRelative(filePath);
+        const last = history.get(sourceOf(filePath));
+        return {
+          commit,
+          path: relativePath,
+          ...(last ? { last_commit: last.commit, last_author: last.author, last_modified: last.date } : {}),
+          ...(last && options.fileAttributes !== false ? { mtime: Math.floor(Date.parse(last.date) / 1000), owner: last.author } : {}),
+        };
+      },
+    });
+
+    let files = ingester.collect(treeDir);
+    let deletedFiles: string[] = [];
+    if (since) {
+      const changes = changedFiles(repoDir, since, commit);
+      const changed = new Set(changes.changed);
+      files = files.filter(filePath => changed.has(sourceOf(filePath)));
+      deletedFiles = changes.deleted;
+    }
+
+    const existing = await client.listDocuments(storeName);
+    let replacedDocuments = 0;
+    const replaced: Promise<void>[] = [];
+    const upload = await ingester.upload(client, storeName, treeDir, files, undefined, (filePath: string, result: UploadResult) => {
+      const kept = new Set(result.documentNames || (result.documentName ? [result.documentName] : []));
+      const stale = documentsOf(existing, toDisplayName(treeDir, filePath).split(path.sep).join('/'))
+        .filter(doc => doc.name && !kept.has(doc.name));
+      replacedDocuments += stale.length;
+      replaced.push(...stale.map(doc => client.deleteDocument(doc.name!).catch(error => {
+        console.error(`⚠️  Could not delete the earlier document ${doc.name}: ${(error as Error).message}`);
+      })));
+    });
+    await Promise.all(replaced);
+
+    for (const relativePath of deletedFiles) {
+      for (const doc of documentsOf(existing, relativePath.replace(/[<>:"|?*]/g, '_'))) {
+        if (doc.name) await client.deleteDocument(doc.name);
+      }
+    }
+    if (deletedFiles.length > 0) console.error(`🗑️  Removed the documents of ${deletedFiles.length} deleted files`);
+
+    return { commit, since, upload, deletedFiles, replacedDocuments };
+  } finally {
+    fs.rmSync(treeDir, { recursive: true, force: true });
+    dispose();
+  }
+}
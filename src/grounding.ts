@@ -0,0 +1,140 @@
+// grounding.ts - Strict "answer only from documents" mode
+import { GroundingMetadata } from '@google/genai';
+
+// Types
+export type NoGroundedAnswerReason = 'declined' | 'no_grounding' | 'low_confidence';
+
+// Returned in place of an answer that the documents do not support
+export interface NoGroundedAnswer {
+  type: 'no_grounded_answer';
+  reason: NoGroundedAnswerReason;
+  message: string;
+  confidence?: number;
+  threshold: number;
+}
+
+// How well the retrieved documents back an answer
+export interface AnswerConfidence {
+  score: number;  // 0-1, the weighted mean of the parts below
+  support?: number;  // Mean grounding confidence of the answer segments, when the response has scores
+  coverage: number;  // Share of the answer inside segments attributed to a chunk
+  overlap: number;  // Share of the answer's distinct words found in the retrieved chunks
+}
+
+export const DEFAULT_MIN_CONFIDENCE = 0.5;
+
+// The model is told to reply with this marker alone when the documents lack the answer
+export const NO_ANSWER_MARKER = 'NO_GROUNDED_ANSWER';
+
+export const GROUNDED_ONLY_INSTRUCTION = [
+  'Answer only with information found in the documents retrieved by the file search tool.',
+  'Do not use prior knowledge, do not guess and do not fill gaps with assumptions.',
+  'Every statement must be supported by the retrieved documents.',
+  `If the documents do not contain the answer, reply with exactly ${NO_ANSWER_MARKER} and nothing else.`,
+].join(' ');
+
+// Config of a grounded query: the stored instruction, followed by the grounded-only one
+// (which also sets temperature 0) when asked for
+export function withSystemInstruction(systemInstruction: string | undefined, groundedOnly?: boolean): { systemInstruction?: string; temperature?: number } {
+  const instruction = [systemInstruction, groundedOnly ? GROUNDED_ONLY_INSTRUCTION : undefined].filter(text => text).join('\n\n');
+  return {
+    ...(instruction ? { systemInstruction: instruction } : {}),
+    ...(groundedOnly ? { temperature: 0 } : {}),
+  };
+}
+
+const REASON_MESSAGES: { [reason in NoGroundedAnswerReason]: string } = {
+  declined: 'The documents do not contain an answer to this question.',
+  no_grounding: 'The reply is not grounded in any retrieved document.',
+  low_confidence: 'The reply is not supported by the documents with enough confidence.',
+};
+
+// Mean over the answer segments of the best confidence score of each segment.
+// Undefined when the response carries no confidence scores.
+export function groundingConfidence(metadata: GroundingMetadata | undefined): number | undefined {
+  const scores = (metadata?.groundingSupports || [])
+    .map(support => support.confidenceScores || [])
+    .filter(segmentScores => segmentScores.length > 0)
+    .map(segmentScores => Math.max(...segmentScores));
+  if (scores.length === 0) return undefined;
+  return scores.reduce((sum, score) => sum + score, 0) / scores.length;
+}
+
+// Words of three letters or more; runs of CJK characters, written without spaces, in pairs
+const ANSWER_TOKENS = /[\p{Script=Han}\p{Script=Hiragana}\p{Script=Katakana}]{2}|[\p{L}\p{N}]{3,}/gu;
+
+// Aggregate confidence of an answer: the support scores count for half and coverage
+// and chunk overlap for a quarter each; without support scores coverage and overlap
+// count for half each. An answer without retrieved chunks scores 0.
+export function answerConfidence(answer: string, metadata: GroundingMetadata | undefined): AnswerConfidence {
+  const chunkText = (metadata?.groundingChunks || [])
+    .map(chunk => chunk.retrievedContext?.text || '')
+    .join('\n')
+    .toLowerCase();
+  if (!chunkText.trim()) return { score: 0, coverage: 0, overlap: 0 };
+
+  const support = groundingConfidence(metadata);
+  const coverage = segmentCoverage(answer, metadata);
+  const chunkWords = new Set(chunkText.match(/[\p{L}\p{N}]+/gu) || []);
+  const tokens = [...new Set(answer.toLowerCase().match(ANSWER_TOKENS) || [])];
+  const found = tokens.filter(token => chunkWords.has(token) || (/^[\p{Script=Han}\p{Script=Hiragana}\p{Script=Katakana}]/u.test(token) && chunkText.includes(token)));
+  const overlap = tokens.length > 0 ? found.length / tokens.length : 0;
+  const score = support !== undefined
+    ? support * 0.5 + coverage * 0.25 + overlap * 0.25
+    : coverage * 0.5 + overlap * 0.5;
+  return { score: round(score), support: support !== undefined ? round(support) : undefined, coverage: round(coverage), overlap: round(overlap) };
+}
+
+// Segment offsets are UTF-8 bytes; overlapping segments are counted once
+function segmentCoverage(answer: string, metadata: GroundingMetadata | undefined): number {
+  if (!answer.trim()) return 0;
+  const length = Buffer.byteLength(answer, 'utf8');
+  const ranges = (metadata?.groundingSupports || [])
+    .filter(support => support.segment && (support.groundingChunkIndices || []).length > 0)
+    .map((support): [number, number] => [support.segment?.startIndex || 0, Math.min(support.segment?.endIndex || 0, length)])
+    .sort((a, b) => a[0] - b[0]);
+  let covered = 0;
+  let end = 0;
+  for (const [start, stop] of ranges) {
+    if (stop <= end) continue;
+    covered += stop - Math.max(start, end);
+    end = stop;
+  }
+  return covered / length;
+}
+
+function round(value: number): number {
+  return Math.round(value * 1000) / 1000;
+}
+
+// Returns undefined when the answer may be shown, otherwise why it was rejected.
+// The threshold applies to the aggregate score of answerConfidence.
+export function checkGrounding(
+  answer: string,
+  metadata: GroundingMetadata | undefined,
+  minConfidence: number = DEFAULT_MIN_CONFIDENCE
+): NoGroundedAnswer | undefined {
+  const reject = (reason: NoGroundedAnswerReason, confidence?: number): NoGroundedAnswer => ({
+    type: 'no_grounded_answer',
+    reason,
+    message: REASON_MESSAGES[reason],
+    confidence,
+    threshold: minConfidence,
+  });
+
+  if (!answer.trim() || answer.includes(NO_ANSWER_MARKER)) return reject('declined');
+
+  const retrieved = (metadata?.groundingChunks || []).filter(chunk => chunk.retrievedContext?.text);
+  if (retrieved.length === 0) return reject('no_grounding');
+
+  const { score } = answerConfidence(answer, metadata);
+  if (score < minConfidence) return reject('low_confidence', score);
+  return undefined;
+}
+
+export function formatNoGroundedAnswer(result: NoGroundedAnswer): string {
+  const confidence = result.confidence !== undefined
+    ? ` (confidence ${result.confidence.toFixed(2)}, threshold ${result.threshold.toFixed(2)})`
+    : '';
+  return `No grounded answer: ${result.message}${confidence}`;
+}
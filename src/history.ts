@@ -0,0 +1,197 @@
+// history.ts - Local history of queries, for listing, inspecting and replaying them
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+import { GenerateContentResponse, GroundingMetadata } from '@google/genai';
+import { FileSearchClient } from './client.js';
+import { extractCitations } from './citations.js';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { GenerationParams } from './generation.js';
+import { NotFoundError, toFileSearchError } from './errors.js';
+import { withFileLock } from './lock.js';
+
+// Types
+export type HistorySource = 'query' | 'mcp' | 'api' | 'replay';
+
+export interface HistoryParams {
+  model: string;
+  metadataFilter?: string;
+  aclLabels?: string[];
+  groundedOnly?: boolean;
+  generation?: GenerationParams;
+  systemInstruction?: string;  // Stored instruction and answer style, or --system
+}
+
+export interface HistoryEntry {
+  id: number;  // Increasing within the history file
+  timestamp: string;  // ISO 8601
+  source: HistorySource;
+  question: string;
+  stores: string[];  // As searched, after routing and shard expansion
+  params: HistoryParams;
+  answerHash?: string;  // SHA-256 of the answer text; the answer itself is not kept
+  answerLength?: number;
+  sources: string[];  // Cited file names, in footnote order
+  latencyMs: number;
+  usage?: TokenUsage;
+  error?: string;
+  replayOf?: number;  // The entry this one replayed
+}
+
+export interface HistoryFilter {
+  limit?: number;  // Newest entries kept (default: 20)
+  store?: string;
+  since?: Date;
+  search?: string;  // Case-insensitive substring of the question
+}
+
+export interface ReplayResult {
+  original: HistoryEntry;
+  replay: HistoryEntry;
+  answerChanged: boolean;
+  sourcesAdded: string[];
+  sourcesRemoved: string[];
+  latencyDeltaMs: number;
+}
+
+export const QUERY_HISTORY_FILE = '.gemini-query-history.jsonl';
+
+// Oldest entries are dropped once the history holds this many queries
+const MAX_ENTRIES = 10_000;
+
+export function hashAnswer(answer: string): string {
+  return createHash('sha256').update(answer).digest('hex');
+}
+
+// The answer-derived fields of an entry
+export function describeAnswer(answer: string, groundingMetadata: GroundingMetadata | undefined): Pick<HistoryEntry, 'answerHash' | 'answerLength' | 'sources'> {
+  return {
+    answerHash: hashAnswer(answer),
+    answerLength: answer.length,
+    sources: [...new Set(extractCitations(groundingMetadata, answer).map(citation => citation.fileName))],
+  };
+}
+
+// One JSON line per query, appended as queries finish, so concurrent writers
+// (the MCP server and a terminal) do not overwrite each other's entries; the lock
+// keeps their ids unique and a trim from dropping an entry appended meanwhile
+export class QueryHistory {
+  constructor(private readonly filePath: string) {}
+
+  record(entry: Omit<HistoryEntry, 'id' | 'timestamp'>): HistoryEntry {
+    return withFileLock(this.filePath, () => {
+      const entries = this.read();
+      const recorded: HistoryEntry = { id: (entries[entries.length - 1]?.id || 0) + 1, timestamp: new Date().toISOString(), ...entry };
+      if (entries.length >= MAX_ENTRIES) {
+        const kept = [...entries.slice(entries.length - MAX_ENTRIES + 1), recorded];
+        fs.writeFileSync(this.filePath, kept.map(line => JSON.stringify(line)).join('\n') + '\n');
+      } else {
+        fs.appendFileSync(this.filePath, JSON.stringify(recorded) + '\n');
+      }
+      return recorded;
+    });
+  }
+
+  // Newest first
+  list(filter: HistoryFilter = {}): HistoryEntry[] {
+    const search = filter.search?.toLowerCase();
+    return this.read()
+      .filter(entry => !filter.store || entry.stores.includes(filter.store))
+      .filter(entry => !filter.since || new Date(entry.timestamp) >= filter.since)
+      .filter(entry => !search || entry.question.toLowerCase().includes(search))
+      .reverse()
+      .slice(0, filter.limit ?? 20);
+  }
+
+  get(id: number): HistoryEntry {
+    const entry = this.read().find(candidate => candidate.id === id);
+    if (!entry) throw new NotFoundError(`history entry ${id}`);
+    return entry;
+  }
+
+  // Lines that do not parse, e.g. one cut short by a crash, are skipped
+  private read(): HistoryEntry[] {
+    let content: string;
+    try {
+      content = fs.readFileSync(this.filePath, 'utf8');
+    } catch (error) {
+      return [];
+    }
+    const entries: HistoryEntry[] = [];
+    for (const line of content.split('\n')) {
+      if (!line.trim()) continue;
+      try {
+        entries.push(JSON.parse(line) as HistoryEntry);
+      } catch (error) {
+        // Skipped
+      }
+    }
+    return entries;
+  }
+}
+
+// Asks the question of an entry again with its stores and parameters, records the
+// replay and compares it with the original. Routed or fanned-out queries are replayed
+// as one query over the stores they searched.
+export async function replayQuery(
+  client: FileSearchClient,
+  history: QueryHistory,
+  id: number,
+  overrides: Partial<Pick<HistoryEntry, 'stores'>> & { model?: string } = {}
+): Promise<ReplayResult> {
+  const original = history.get(id);
+  const stores = overrides.stores && overrides.stores.length > 0 ? overrides.stores : original.stores;
+  const params: HistoryParams = { ...original.params, model: overrides.model || original.params.model };
+
+  const startedAt = Date.now();
+  let response: GenerateContentResponse | undefined;
+  let error: string | undefined;
+  try {
+    response = await client.query(stores, original.question, params);
+  } catch (err) {
+    error = toFileSearchError(err).message;
+  }
+  const answer = response?.text || '';
+  const replay = history.record({
+    source: 'replay',
+    question: original.question,
+    stores,
+    params,
+    ...(response ? describeAnswer(answer, response.candidates?.[0]?.groundingMetadata) : { sources: [] }),
+    latencyMs: Date.now() - startedAt,
+    usage: toTokenUsage(response?.usageMetadata),
+    error,
+    replayOf: original.id,
+  });
+
+  return {
+    original,
+    replay,
+    answerChanged: replay.answerHash !== original.answerHash,
+    sourcesAdded: replay.sources.filter(source => !original.sources.includes(source)),
+    sourcesRemoved: original.sources.filter(source => !replay.sources.includes(source)),
+    latencyDeltaMs: replay.latencyMs - original.latencyMs,
+  };
+}
+
+export function formatHistoryEntry(entry: HistoryEntry): string {
+  const lines = [
+    `#${entry.id}  ${entry.timestamp}  (${entry.source}${entry.replayOf !== undefined ? ` of #${entry.replayOf}` : ''})`,
+    `Question: ${entry.question}`,
+    `Stores:   ${entry.stores.join(', ')}`,
+    `Model:    ${entry.params.model}`,
+  ];
+  if (entry.params.metadataFilter) lines.push(`Filter:   ${entry.params.metadataFilter}`);
+  if (entry.params.aclLabels) lines.push(`ACL:      ${entry.params.aclLabels.join(', ')}`);
+  if (entry.params.groundedOnly) lines.push('Grounded: only');
+  if (entry.params.systemInstruction) lines.push(`System:   ${entry.params.systemInstruction.replace(/\s+/g, ' ').slice(0, 80)}`);
+  const generation = Object.entries(entry.params.generation || {}).filter(([, value]) => value !== undefined);
+  if (generation.length > 0) lines.push(`Generation: ${generation.map(([name, value]) => `${name}=${typeof value === 'object' ? JSON.stringify(value) : value}`).join(' ')}`);
+  lines.push(`Latency:  ${entry.latencyMs}ms${entry.usage ? `, ${entry.usage.totalTokens} tokens` : ''}`);
+  if (entry.error) {
+    lines.push(`Error:    ${entry.error}`);
+  } else {
+    lines.push(`Answer:   ${entry.answerLength} characters, sha256 ${entry.answerHash}`);
+    lines.push(`Sources:  ${entry.sources.length > 0 ? entry.sources.join(', ') : '(none cited)'}`);
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,513 @@
+// html.ts - Minimal HTML parser, readability-style content extraction and Markdown conversion
+
+// Types
+export interface HtmlElement {
+  tag: string;  // Lower case; "#root" for the document
+  attrs: { [name: string]: string };
+  children: HtmlNode[];
+  parent?: HtmlElement;
+}
+
+export type HtmlNode = HtmlElement | string;
+
+export interface ReadableContent {
+  title?: string;
+  markdown: string;
+}
+
+interface Candidate {
+  element: HtmlElement;
+  score: number;
+}
+
+const VOID_TAGS = new Set(['area', 'base', 'br', 'col', 'embed', 'hr', 'img', 'input', 'link', 'meta', 'param', 'source', 'track', 'wbr']);
+// Content kept as text without parsing; only title and textarea survive
+const RAW_TEXT_TAGS = new Set(['script', 'style', 'title', 'textarea', 'noscript', 'template', 'xmp']);
+const DROPPED_RAW_TAGS = new Set(['script', 'style', 'noscript', 'template']);
+
+// An opening tag implicitly closes an open element of these tags
+const IMPLICIT_CLOSE: { [tag: string]: string[] } = {
+  p: ['p'],
+  li: ['li'],
+  dt: ['dt', 'dd'],
+  dd: ['dt', 'dd'],
+  tr: ['tr', 'td', 'th'],
+  td: ['td', 'th'],
+  th: ['td', 'th'],
+  option: ['option'],
+  thead: ['tbody', 'tfoot'],
+  tbody: ['thead', 'tbody', 'tfoot'],
+  tfoot: ['thead', 'tbody'],
+};
+const BLOCK_CLOSES_P = new Set(['address', 'article', 'aside', 'blockquote', 'div', 'dl', 'fieldset', 'footer', 'form', 'h1', 'h2', 'h3', 'h4', 'h5', 'h6', 'header', 'hr', 'main', 'nav', 'ol', 'pre', 'section', 'table', 'ul']);
+
+const ENTITIES: { [name: string]: string } = {
+  amp: '&', lt: '<', gt: '>', quot: '"', apos: "'", nbsp: ' ', copy: '©', reg: '®', trade: '™',
+  mdash: '—', ndash: '–', hellip: '…', laquo: '«', raquo: '»', lsquo: '‘', rsquo: '’', ldquo: '“', rdquo: '”',
+  bull: '•', middot: '·', times: '×', divide: '÷', rarr: '→', larr: '←', deg: '°', euro: '€', pound: '£', yen: '¥',
+};
+
+// Never part of the main content
+const BOILERPLATE_TAGS = new Set(['nav', 'aside', 'footer', 'form', 'button', 'input', 'select', 'iframe', 'svg', 'canvas', 'object', 'embed', 'dialog', 'menu']);
+const BOILERPLATE_ROLES = new Set(['navigation', 'banner', 'contentinfo', 'complementary', 'search', 'dialog', 'alert']);
+const UNLIKELY = /banner|breadcrumb|combx|comment|community|cookie|consent|disqus|extra|footer|gdpr|header|legends|menu|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|ad-break|agegate|pagination|pager|popup|newsletter|subscribe|toc|table-of-contents/i;
+const MAYBE_CANDIDATE = /and|article|body|column|content|main|shadow/i;
+const POSITIVE = /article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story|docs?|markdown|prose/i;
+const NEGATIVE = /hidden|banner|combx|comment|contact|foot|footer|footnote|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget/i;
+const SCORED_TAGS = new Set(['p', 'pre', 'td', 'blockquote', 'li', 'section', 'h2', 'h3', 'h4', 'h5', 'h6']);
+
+const BLOCK_TAGS = new Set([
+  'address', 'article', 'blockquote', 'details', 'div', 'dl', 'fieldset', 'figcaption', 'figure', 'footer', 'header',
+  'h1', 'h2', 'h3', 'h4', 'h5', 'h6', 'hr', 'li', 'main', 'ol', 'p', 'pre', 'section', 'summary', 'table', 'ul', '#root', 'body', 'html',
+]);
+
+const TAG_PATTERN = /<(\/?)([a-zA-Z][\w:-]*)((?:\s+[^\s"'>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(\/?)>/y;
+const ATTR_PATTERN = /([^\s"'>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?/g;
+
+export function decodeEntities(text: string): string {
+  return text.replace(/&(#x[0-9a-f]+|#\d+|[a-z]+\d*);/gi, (match, entity: string) => {
+    if (entity[0] === '#') {
+      const code = entity[1] === 'x' || entity[1] === 'X' ? parseInt(entity.slice(2), 16) : parseInt(entity.slice(1), 10);
+      return Number.isFinite(code) && code > 0 && code <= 0x10ffff ? String.fromCodePoint(code) : match;
+    }
+    return ENTITIES[entity.toLowerCase()] ?? match;
+  });
+}
+
+function parseAttributes(source: string): { [name: string]: string } {
+  const attrs: { [name: string]: string } = {};
+  for (const match of source.matchAll(ATTR_PATTERN)) {
+    attrs[match[1].toLowerCase()] = decodeEntities(match[2] ?? match[3] ?? match[4] ?? '');
+  }
+  return attrs;
+}
+
+// Tolerant of the usual tag soup: unclosed elements, stray end tags and missing quotes
+export function parseHtml(html: string): HtmlElement {
+  const root: HtmlElement = { tag: '#root', attrs: {}, children: [] };
+  const stack: HtmlElement[] = [root];
+  const current = () => stack[stack.length - 1];
+  const append = (node: HtmlNode) => {
+    current().children.push(node);
+    if (typeof node !== 'string') node.parent = current();
+  };
+  const closeTo = (tags: string[], stopAt?: Set<string>) => {
+    for (let i = stack.length - 1; i > 0; i--) {
+      if (tags.includes(stack[i].tag)) {
+        stack.length = i;
+        return;
+      }
+      if (stopAt?.has(stack[i].tag)) return;
+    }
+  };
+
+  let i = 0;
+  while (i < html.length) {
+    const lt = html.indexOf('<', i);
+    if (lt === -1) {
+      append(decodeEntities(html.slice(i)));
+      break;
+    }
+    if (lt > i) append(decodeEntities(html.slice(i, lt)));
+
+    if (html.startsWith('<!--', lt)) {
+      const end = html.indexOf('-->', lt + 4);
+      i = end === -1 ? html.length : end + 3;
+      continue;
+    }
+    if (html[lt + 1] === '!' || html[lt + 1] === '?') {
+      // Doctype, CDATA and processing instructions
+      const end = html.indexOf('>', lt);
+      i = end === -1 ? html.length : end + 1;
+      continue;
+    }
+
+    TAG_PATTERN.lastIndex = lt;
+    const match = TAG_PATTERN.exec(html);
+    if (!match) {
+      append('<');
+      i = lt + 1;
+      continue;
+    }
+    i = TAG_PATTERN.lastIndex;
+    const [, closing, rawTag, attrSource, selfClosing] = match;
+    const tag = rawTag.toLowerCase();
+
+    if (closing) {
+      closeTo([tag], tag === 'li' ? new Set(['ul', 'ol']) : undefined);
+      continue;
+    }
+
+    closeTo(IMPLICIT_CLOSE[tag] || [], new Set(['table', 'ul', 'ol', 'dl']));
+    if (BLOCK_CLOSES_P.has(tag)) closeTo(['p'], new Set(['div', 'section', 'article', 'td', 'li', 'blockquote']));
+    const element: HtmlElement = { tag, attrs: parseAttributes(attrSource), children: [] };
+
+    if (RAW_TEXT_TAGS.has(tag) && !selfClosing) {
+      const endPattern = new RegExp(`</${tag}\\s*>`, 'ig');
+      endPattern.lastIndex = i;
+      const end = endPattern.exec(html);
+      const text = html.slice(i, end ? end.index : html.length);
+      i = end ? end.index + end[0].length : html.length;
+      if (DROPPED_RAW_TAGS.has(tag)) continue;
+      element.children.push(tag === 'title' || tag === 'textarea' ? decodeEntities(text) : text);
+      append(element);
+      continue;
+    }
+
+    append(element);
+    if (!VOID_TAGS.has(tag) && !selfClosing) stack.push(element);
+  }
+  return root;
+}
+
+export function textContent(node: HtmlNode): string {
+  if (typeof node === 'string') return node;
+  return node.children.map(textContent).join('');
+}
+
+function normalizedText(node: HtmlNode): string {
+  return textContent(node).replace(/\s+/g, ' ').trim();
+}
+
+function* elements(node: HtmlElement): Generator<HtmlElement> {
+  for (const child of node.children) {
+    if (typeof child === 'string') continue;
+    yield child;
+    yield* elements(child);
+  }
+}
+
+function findFirst(root: HtmlElement, predicate: (element: HtmlElement) => boolean): HtmlElement | undefined {
+  for (const element of elements(root)) {
+    if (predicate(element)) return element;
+  }
+  return undefined;
+}
+
+function remove(element: HtmlElement): void {
+  const siblings = element.parent?.children;
+  if (!siblings) return;
+  const index = siblings.indexOf(element);
+  if (index !== -1) siblings.splice(index, 1);
+}
+
+function classAndId(element: HtmlElement): string {
+  return `${element.attrs.class || ''} ${element.attrs.id || ''}`;
+}
+
+function linkDensity(element: HtmlElement): number {
+  const length = normalizedText(element).length;
+  if (length === 0) return 0;
+  let linkLength = 0;
+  for (const link of elements(element)) {
+    if (link.tag === 'a') linkLength += normalizedText(link).length;
+  }
+  return linkLength / length;
+}
+
+function classWeight(element: HtmlElement): number {
+  const names = classAndId(element);
+  let weight = 0;
+  if (NEGATIVE.test(names)) weight -= 25;
+  if (POSITIVE.test(names)) weight += 25;
+  return weight;
+}
+
+function baseScore(element: HtmlElement): number {
+  switch (element.tag) {
+    case 'article':
+    case 'main':
+      return 10;
+    case 'div':
+      return 5;
+    case 'pre':
+    case 'td':
+    case 'blockquote':
+      return 3;
+    case 'address':
+    case 'ol':
+    case 'ul':
+    case 'dl':
+    case 'dd':
+    case 'dt':
+    case 'li':
+    case 'form':
+      return -3;
+    case 'h1':
+    case 'h2':
+    case 'h3':
+    case 'h4':
+    case 'h5':
+    case 'h6':
+    case 'th':
+      return -5;
+    default:
+      return 0;
+  }
+}
+
+function isHidden(element: HtmlElement): boolean {
+  return 'hidden' in element.attrs
+    || element.attrs['aria-hidden'] === 'true'
+    || /display\s*:\s*none|visibility\s*:\s*hidden/i.test(element.attrs.style || '');
+}
+
+// Drops navigation, hidden elements and blocks whose class or id marks them as boilerplate
+function removeBoilerplate(root: HtmlElement): void {
+  const doomed: HtmlElement[] = [];
+  for (const element of elements(root)) {
+    if (element.tag === 'header' && findAncestor(element, ancestor => ancestor.tag === 'article' || ancestor.tag === 'main')) continue;
+    const unlikely = UNLIKELY.test(classAndId(element)) && !MAYBE_CANDIDATE.test(classAndId(element))
+      && !['html', 'body', 'article', 'main', 'a', 'code', 'pre'].includes(element.tag);
+    if (BOILERPLATE_TAGS.has(element.tag) || element.tag === 'header' || BOILERPLATE_ROLES.has(element.attrs.role) || isHidden(element) || unlikely) {
+      doomed.push(element);
+    }
+  }
+  doomed.forEach(remove);
+}
+
+function findAncestor(element: HtmlElement, predicate: (ancestor: HtmlElement) => boolean): HtmlElement | undefined {
+  for (let ancestor = element.parent; ancestor; ancestor = ancestor.parent) {
+    if (predicate(ancestor)) return ancestor;
+  }
+  return undefined;
+}
+
+// Readability scoring: paragraphs vote for their parent and, at half weight, their
+// grandparent; the best container (discounted by its link density) is the content
+function findContent(body: HtmlElement): HtmlElement {
+  const scores = new Map<HtmlElement, Candidate>();
+  const addScore = (element: HtmlElement | undefined, points: number) => {
+    if (!element || element.tag === '#root') return;
+    let candidate = scores.get(element);
+    if (!candidate) {
+      candidate = { element, score: baseScore(element) + classWeight(element) };
+      scores.set(element, candidate);
+    }
+    candidate.score += points;
+  };
+
+  for (const element of elements(body)) {
+    // Divs holding only text and inline elements count as paragraphs
+    const paragraph = SCORED_TAGS.has(element.tag)
+      || (element.tag === 'div' && !element.children.some(child => typeof child !== 'string' && BLOCK_TAGS.has(child.tag)));
+    if (!paragraph) continue;
+    const text = normalizedText(element);
+    if (text.length < 25) continue;
+    const points = 1 + (text.match(/[,、，]/g)?.length || 0) + Math.min(Math.floor(text.length / 100), 3);
+    addScore(element.parent, points);
+    addScore(element.parent?.parent, points / 2);
+  }
+
+  let best: Candidate | undefined;
+  for (const candidate of scores.values()) {
+    candidate.score *= 1 - linkDensity(candidate.element);
+    if (!best || candidate.score > best.score) best = candidate;
+  }
+  if (!best) return body;
+
+  // A container spanning the whole article often holds the winner and its siblings
+  let content = best.element;
+  const threshold = Math.max(10, best.score * 0.2);
+  for (let parent = content.parent; parent && parent !== body && parent.tag !== '#root'; parent = parent.parent) {
+    const parentScore = scores.get(parent)?.score ?? 0;
+    if (parentScore < threshold) break;
+    content = parent;
+  }
+  return content;
+}
+
+// Removes link lists and other low-text blocks left inside the content
+function cleanContent(content: HtmlElement): void {
+  const doomed: HtmlElement[] = [];
+  for (const element of elements(content)) {
+    if (!['div', 'section', 'ul', 'ol', 'table', 'dl'].includes(element.tag)) continue;
+    if (findAncestor(element, ancestor => ancestor.tag === 'pre')) continue;
+    const text = normalizedText(element);
+    const density = linkDensity(element);
+    if (text.length === 0 && !findFirst(element, child => child.tag === 'img' || child.tag === 'pre')) {
+      doomed.push(element);
+    } else if (classWeight(element) < 0 && density > 0.2) {
+      doomed.push(element);
+    } else if (density > 0.5 && text.length < 500 && element.tag !== 'table') {
+      doomed.push(element);
+    }
+  }
+  doomed.forEach(remove);
+}
+
+function resolveUrl(href: string, baseUrl?: string): string | undefined {
+  if (!href || href.startsWith('#') || /^(javascript|data|mailto):/i.test(href)) return undefined;
+  try {
+    return new URL(href, baseUrl).toString();
+  } catch (error) {
+    return undefined;
+  }
+}
+
+function languageOf(element: HtmlElement): string {
+  const code = findFirst(element, child => child.tag === 'code');
+  const classes = `${element.attrs.class || ''} ${code?.attrs.class || ''}`;
+  return /(?:language|lang)-([\w+#-]+)/.exec(classes)?.[1] || '';
+}
+
+function renderTable(table: HtmlElement, baseUrl?: string): string {
+  const rows: string[][] = [];
+  const collect = (element: HtmlElement) => {
+    for (const child of element.children) {
+      if (typeof child === 'string' || child.tag === 'table') continue;
+      if (child.tag === 'tr') {
+        rows.push(child.children
+          .filter((cell): cell is HtmlElement => typeof cell !== 'string' && (cell.tag === 'td' || cell.tag === 'th'))
+          .map(cell => renderChildren(cell, { baseUrl, pre: false, listDepth: 0 }).replace(/\s+/g, ' ').trim().replace(/\|/g, '\\|')));
+      } else {
+        collect(child);
+      }
+    }
+  };
+  collect(table);
+  if (rows.length === 0) return '';
+  const width = Math.max(...rows.map(row => row.length));
+  const line = (row: string[]) => `| ${Array.from({ length: width }, (_, i) => row[i] || '').join(' | ')} |`;
+  return [line(rows[0]), `|${' --- |'.repeat(width)}`, ...rows.slice(1).map(line)].join('\n');
+}
+
+// Keeps surrounding whitespace outside of the markers: "a<b> bold </b>b" -> "a **bold** b"
+function emphasize(text: string, marker: string): string {
+  const [, leading, core, trailing] = /^(\s*)([\s\S]*?)(\s*)$/.exec(text) as RegExpExecArray;
+  return core ? `${leading}${marker}${core}${marker}${trailing}` : text;
+}
+
+interface RenderContext {
+  baseUrl?: string;
+  pre: boolean;
+  listDepth: number;
+}
+
+function renderChildren(element: HtmlElement, context: RenderContext): string {
+  return element.children.map(child => renderNode(child, context)).join('');
+}
+
+function renderNode(node: HtmlNode, context: RenderContext): string {
+  if (typeof node === 'string') {
+    return context.pre ? node : node.replace(/\s+/g, ' ');
+  }
+  const inner = () => renderChildren(node, context);
+  const block = (text: string) => `\n\n${text.trim()}\n\n`;
+
+  switch (node.tag) {
+    case 'h1':
+    case 'h2':
+    case 'h3':
+    case 'h4':
+    case 'h5':
+    case 'h6': {
+      const text = inner().replace(/\s+/g, ' ').trim();
+      return text ? block(`${'#'.repeat(Number(node.tag[1]))} ${text}`) : '';
+    }
+    case 'br':
+      return '\n';
+    case 'hr':
+      return block('---');
+    case 'pre': {
+      const code = textContent(node).replace(/^\n/, '').replace(/\s+$/, '');
+      return code ? `\n\n\`\`\`${languageOf(node)}\n${code}\n\`\`\`\n\n` : '';
+    }
+    case 'code':
+    case 'kbd':
+    case 'samp': {
+      if (context.pre) return textContent(node);
+      const text = textContent(node).replace(/\s+/g, ' ');
+      return text.trim() ? `\`${text}\`` : '';
+    }
+    case 'strong':
+    case 'b':
+      return emphasize(inner(), '**');
+    case 'em':
+    case 'i':
+      return emphasize(inner(), '_');
+    case 'a': {
+      const text = inner().replace(/\s+/g, ' ').trim();
+      const href = resolveUrl(node.attrs.href, context.baseUrl);
+      return href && text ? `[${text}](${href})` : text;
+    }
+    case 'img': {
+      const src = resolveUrl(node.attrs.src, context.baseUrl);
+      return src && node.attrs.alt ? `![${node.attrs.alt}](${src})` : '';
+    }
+    case 'ul':
+    case 'ol': {
+      const items = node.children.filter((child): child is HtmlElement => typeof child !== 'string' && child.tag === 'li');
+      const nested = { ...context, listDepth: context.listDepth + 1 };
+      const indent = '  '.repeat(context.listDepth);
+      const lines = items.map((item, index) => {
+        const marker = node.tag === 'ol' ? `${Number(node.attrs.start || 1) + index}.` : '-';
+        const text = renderChildren(item, nested).trim().replace(/\n{2,}/g, '\n');
+        return `${indent}${marker} ${text}`;
+      });
+      return lines.length > 0 ? `\n\n${lines.join('\n')}\n\n` : '';
+    }
+    case 'li':
+      // Outside of a list
+      return block(`- ${inner().trim()}`);
+    case 'blockquote': {
+      const text = inner().trim().replace(/\n{3,}/g, '\n\n');
+      return text ? block(text.split('\n').map(line => `> ${line}`.trimEnd()).join('\n')) : '';
+    }
+    case 'table':
+      return block(renderTable(node, context.baseUrl));
+    case 'dt':
+      return `\n\n**${inner().trim()}**\n`;
+    case 'dd':
+      return `${inner().trim()}\n\n`;
+    case 'title':
+    case 'head':
+    case 'textarea':
+      return '';
+    default: {
+      const text = inner();
+      return BLOCK_TAGS.has(node.tag) ? block(text) : text;
+    }
+  }
+}
+
+// Tidies whitespace outside of fenced code blocks; list indentation is kept
+function tidy(markdown: string): string {
+  const parts = markdown.split(/\n*(```[^\n]*\n[\s\S]*?\n```)\n*/);
+  return parts.map((part, index) => index % 2 === 1 ? part : part
+    .replace(/[ \t]+\n/g, '\n')
+    .replace(/\n[ \t]+(?![ \t]|[-*] |\d+\. )/g, '\n')
+    .replace(/\n{3,}/g, '\n\n')
+    .replace(/^\n*/, index > 0 ? '\n\n' : '')
+    .replace(/\n*$/, index < parts.length - 1 ? '\n\n' : '')).join('').trim();
+}
+
+export function htmlToMarkdown(root: HtmlElement, baseUrl?: string): string {
+  return tidy(renderNode(root, { baseUrl, pre: false, listDepth: 0 }));
+}
+
+function metaContent(root: HtmlElement, name: string): string | undefined {
+  const meta = findFirst(root, element => element.tag === 'meta' && (element.attrs.property === name || element.attrs.name === name));
+  return meta?.attrs.content?.trim() || undefined;
+}
+
+// The page title, then the main content as Markdown headed by the title
+export function extractReadable(html: string, baseUrl?: string): ReadableContent {
+  const root = parseHtml(html);
+  const titleElement = findFirst(root, element => element.tag === 'title');
+  const h1 = findFirst(root, element => element.tag === 'h1');
+  const title = metaContent(root, 'og:title') || (titleElement && normalizedText(titleElement)) || (h1 && normalizedText(h1)) || undefined;
+  const base = findFirst(root, element => element.tag === 'base')?.attrs.href;
+  const resolvedBase = base ? resolveUrl(base, baseUrl) || baseUrl : baseUrl;
+
+  const body = findFirst(root, element => element.tag === 'body') || root;
+  removeBoilerplate(body);
+  const content = findContent(body);
+  cleanContent(content);
+
+  let markdown = htmlToMarkdown(content, resolvedBase);
+  if (title && !markdown.startsWith('# ')) {
+    markdown = `# ${title}\n\n${markdown}`;
+  }
+  return { title, markdown };
+}
@@ -0,0 +1,339 @@
+// http-api.ts - A small REST API over the client: questions, ingestion and the list of stores
+import * as fs from 'fs';
+import * as http from 'http';
+import * as os from 'os';
+import * as path from 'path';
+import { AddressInfo } from 'net';
+import { createHash, timingSafeEqual } from 'crypto';
+import { FileSearchClient, UploadResult } from './client.js';
+import { DirectoryIngester, IngestOptions, IngestSummary, isWithin } from './ingest.js';
+import { DocumentMetadata } from './metadata.js';
+import { StoreInfo } from './store.js';
+import { AnswerReport } from './output.js';
+import { extractCitations } from './citations.js';
+import { toTokenUsage } from './cost.js';
+import { NoGroundedAnswer, answerConfidence, checkGrounding } from './grounding.js';
+import { BlockedAnswer, blockedAnswer } from './generation.js';
+import { parseFilterExpression } from './search.js';
+import { PromptConfig, resolveSystemInstruction } from './prompts.js';
+import { QueryHistory, describeAnswer } from './history.js';
+import { AccessDeniedError, ErrorCode, FileSearchError, InvalidInputError, NotFoundError, toFileSearchError } from './errors.js';
+
+// Types
+export interface ApiServerOptions {
+  client: FileSearchClient;
+  apiKeys: string[];  // Accepted as "Authorization: Bearer <key>" or "X-API-Key: <key>"; none turns authentication off
+  port?: number;  // Default: 8787, 0 for a free port
+  host?: string;  // Default: 127.0.0.1
+  defaultStore?: string;  // Used by /query and /ingest requests that name no store
+  model?: string;
+  minConfidence?: number;
+  prompt?: PromptConfig;  // Stored instruction and answer style per store
+  ingestRoots?: string[];  // Directories POST /ingest may read paths from; without any only inline documents are accepted
+  ingest?: IngestOptions;  // MIME, chunking and redaction settings for uploads
+  maxBodyBytes?: number;  // Default: 10 MB
+  history?: QueryHistory;  // Records every POST /query
+}
+
+export interface QueryRequest {
+  question: string;
+  stores?: string[];
+  filter?: string;  // Metadata filter expression, as for --filter
+  model?: string;
+  groundedOnly?: boolean;
+  minConfidence?: number;
+  system?: string;  // Replaces the stored system instruction
+}
+
+export interface InlineDocument {
+  displayName: string;
+  text: string;
+  mimeType?: string;  // Default: text/plain
+  metadata?: DocumentMetadata;
+}
+
+export interface IngestRequest {
+  store?: string;
+  path?: string;  // File or directory under one of the ingest roots
+  include?: string[];
+  exclude?: string[];
+  metadata?: DocumentMetadata;
+  documents?: InlineDocument[];
+}
+
+export interface IngestResponse {
+  store: string;
+  summary?: IngestSummary;  // Of the path
+  documents?: (UploadResult & { displayName: string })[];  // Of the inline documents, in request order
+}
+
+export interface ApiErrorBody {
+  error: { code: ErrorCode; message: string };
+}
+
+export const DEFAULT_API_PORT = 8787;
+
+// Comma-separated keys clients authenticate with
+export const API_KEYS_ENV = 'GEMINI_FS_SERVE_KEYS';
+
+const DEFAULT_MAX_BODY_BYTES = 10 * 1024 * 1024;
+
+const HTTP_STATUS: Record<ErrorCode, number> = {
+  INVALID_INPUT: 400,
+  CONFIG_INVALID: 400,
+  FILE_TOO_LARGE: 413,
+  UNSUPPORTED_MIME: 415,
+  PREFLIGHT_FAILED: 422,
+  BUDGET_EXCEEDED: 422,
+  AUTH_FAILED: 502,  // The server's own credentials; a client without a valid key gets 401
+  ACCESS_DENIED: 403,
+  ANSWER_BLOCKED: 422,
+  STATE_LOCKED: 409,
+  STORE_NOT_FOUND: 404,
+  NOT_FOUND: 404,
+  QUOTA_EXCEEDED: 429,
+  CANCELLED: 503,
+  TIMEOUT: 504,
+  NETWORK: 502,
+  OPERATION_FAILED: 502,
+  API_ERROR: 502,
+  INTERNAL: 500,
+};
+
+class HttpError extends FileSearchError {
+  constructor(readonly status: number, code: ErrorCode, message: string) {
+    super(code, message);
+    this.name = 'HttpError';
+  }
+}
+
+function digest(key: string): Buffer {
+  return createHash('sha256').update(key).digest();
+}
+
+function stringArray(value: unknown, field: string): string[] | undefined {
+  if (value === undefined) return undefined;
+  if (!Array.isArray(value) || !value.every(item => typeof item === 'string')) throw new InvalidInputError(`${field} must be an array of strings`);
+  return value as string[];
+}
+
+function metadataOf(value: unknown, field: string): DocumentMetadata | undefined {
+  if (value === undefined) return undefined;
+  if (typeof value !== 'object' || value === null || Array.isArray(value)) throw new InvalidInputError(`${field} must be an object`);
+  return value as DocumentMetadata;
+}
+
+// Serves GET /health, GET /stores, POST /query and POST /ingest. Responses are the
+// library types as JSON (StoreInfo[], AnswerReport, IngestResponse) and failures are
+// { "error": { "code", "message" } } with a status matching the error code.
+export class ApiServer {
+  private server?: http.Server;
+  private readonly keys: Buffer[];
+
+  constructor(private readonly options: ApiServerOptions) {
+    this.keys = options.apiKeys.filter(Boolean).map(digest);
+  }
+
+  async start(): Promise<string> {
+    this.server = http.createServer((req, res) => {
+      this.handle(req).then(
+        body => this.send(res, 200, body),
+        error => {
+          const err = toFileSearchError(error);
+          const status = error instanceof HttpError ? error.status : HTTP_STATUS[err.code];
+          if (status >= 500) console.error(`❌ ${req.method} ${req.url}: ${err.message}`);
+          const body: ApiErrorBody = { error: { code: err.code, message: err.message } };
+          this.send(res, status, body);
+        }
+      );
+    });
+    const host = this.options.host || '127.0.0.1';
+    await new Promise<void>((resolve, reject) => {
+      this.server!.once('error', reject);
+      this.server!.listen(this.options.port ?? DEFAULT_API_PORT, host, resolve);
+    });
+    const address = this.server.address() as AddressInfo;
+    return `http://${address.family === 'IPv6' ? `[${address.address}]` : address.address}:${address.port}`;
+  }
+
+  async stop(): Promise<void> {
+    const server = this.server;
+    this.server = undefined;
+    if (server) await new Promise<void>(resolve => server.close(() => resolve()));
+  }
+
+  private send(res: http.ServerResponse, status: number, body: unknown): void {
+    res.writeHead(status, { 'Content-Type': 'application/json' });
+    res.end(`${JSON.stringify(body, null, 2)}\n`);
+  }
+
+  private async handle(req: http.IncomingMessage): Promise<unknown> {
+    const route = `${req.method} ${new URL(req.url || '/', 'http://localhost').pathname.replace(/\/+$/, '') || '/'}`;
+    if (route === 'GET /health') return { status: 'ok' };
+    this.authenticate(req);
+    switch (route) {
+      case 'GET /stores':
+        return this.stores();
+      case 'POST /query':
+        return this.query(await this.json(req));
+      case 'POST /ingest':
+        return this.ingest(await this.json(req));
+      default:
+        if (['/stores', '/query', '/ingest'].includes(route.split(' ')[1])) throw new HttpError(405, 'INVALID_INPUT', `Method not allowed: ${route}`);
+        throw new NotFoundError(`route ${route}`);
+    }
+  }
+
+  private authenticate(req: http.IncomingMessage): void {
+    if (this.keys.length === 0) return;
+    const header = req.headers.authorization;
+    const key = header?.startsWith('Bearer ') ? header.slice('Bearer '.length).trim() : req.headers['x-api-key'];
+    const given = typeof key === 'string' && key ? digest(key) : undefined;
+    if (!given || !this.keys.some(known => timingSafeEqual(known, given))) {
+      throw new HttpError(401, 'AUTH_FAILED', 'Missing or unknown API key: send it as "Authorization: Bearer <key>" or "X-API-Key: <key>"');
+    }
+  }
+
+  private async json(req: http.IncomingMessage): Promise<{ [key: string]: unknown }> {
+    const limit = this.options.maxBodyBytes || DEFAULT_MAX_BODY_BYTES;
+    const chunks: Buffer[] = [];
+    let size = 0;
+    for await (const chunk of req) {
+      size += (chunk as Buffer).length;
+      if (size > limit) throw new HttpError(413, 'INVALID_INPUT', `Request body exceeds ${limit} bytes`);
+      chunks.push(chunk as Buffer);
+    }
+    let body: unknown;
+    try {
+      body = JSON.parse(Buffer.concat(chunks).toString('utf8') || '{}');
+    } catch (error) {
+      throw new InvalidInputError('Request body is not valid JSON');
+    }
+    if (typeof body !== 'object' || body === null || Array.isArray(body)) throw new InvalidInputError('Request body must be a JSON object');
+    return body as { [key: string]: unknown };
+  }
+
+  private storesOf(stores: string[] | undefined): string[] {
+    const names = stores && stores.length > 0 ? stores : this.options.defaultStore ? [this.options.defaultStore] : [];
+    if (names.length === 0) throw new InvalidInputError('No store given and the server has no default store');
+    return names;
+  }
+
+  private async stores(): Promise<StoreInfo[]> {
+    return this.options.client.stores.listStores();
+  }
+
+  private async query(body: { [key: string]: unknown }): Promise<AnswerReport | ((NoGroundedAnswer | BlockedAnswer) & { question: string; stores: string[] })> {
+    const request = body as Partial<QueryRequest>;
+    if (typeof request.question !== 'string' || !request.question.trim()) throw new InvalidInputError('question must be a non-empty string');
+    if (request.filter !== undefined && typeof request.filter !== 'string') throw new InvalidInputError('filter must be a string');
+    if (request.system !== undefined && typeof request.system !== 'string') throw new InvalidInputError('system must be a string');
+    if (request.minConfidence !== undefined && !(typeof request.minConfidence === 'number' && request.minConfidence >= 0 && request.minConfidence <= 1)) {
+      throw new InvalidInputError('minConfidence must be a number between 0 and 1');
+    }
+    const stores = this.storesOf(stringArray(request.stores, 'stores'));
+    const model = request.model || this.options.model || this.options.client.model;
+    const groundedOnly = request.groundedOnly === true;
+    const systemInstruction = this.options.prompt ? resolveSystemInstruction(this.options.prompt, stores, request.system) : request.system;
+    const params = { model, metadataFilter: request.filter ? parseFilterExpression(request.filter) : undefined, groundedOnly, systemInstruction };
+    const startedAt = Date.now();
+    const response = await this.options.client.query(stores, request.question, params).catch(error => {
+      this.options.history?.record({ source: 'api', question: request.question!, stores, params, sources: [], latencyMs: Date.now() - startedAt, error: toFileSearchError(error).message });
+      throw error;
+    });
+    const answer = response.text || '';
+    const groundingMetadata = response.candidates?.[0]?.groundingMetadata;
+    const blocked = blockedAnswer(response, answer);
+    this.options.history?.record({
+      source: 'api',
+      question: request.question,
+      stores,
+      params,
+      ...describeAnswer(answer, groundingMetadata),
+      latencyMs: Date.now() - startedAt,
+      usage: toTokenUsage(response.usageMetadata),
+      ...(blocked ? { error: `Blocked (${blocked.reason}): ${blocked.message}` } : {}),
+    });
+    if (blocked) return { question: request.question, stores, ...blocked };
+    const threshold = request.minConfidence ?? this.options.minConfidence;
+    const rejected = groundedOnly ? checkGrounding(answer, groundingMetadata, threshold) : undefined;
+    if (rejected) return { question: request.question, stores, ...rejected };
+    return {
+      question: request.question,
+      answer,
+      citations: extractCitations(groundingMetadata, answer),
+      stores,
+      model,
+      usage: toTokenUsage(response.usageMetadata),
+      groundingMetadata,
+      confidence: answerConfidence(answer, groundingMetadata),
+      minConfidence: threshold,
+    };
+  }
+
+  private async ingest(body: { [key: string]: unknown }): Promise<IngestResponse> {
+    const request = body as Partial<IngestRequest>;
+    if (request.store !== undefined && typeof request.store !== 'string') throw new InvalidInputError('store must be a string');
+    if (request.path === undefined && request.documents === undefined) throw new InvalidInputError('Give a path or documents to ingest');
+    const [store] = this.storesOf(request.store ? [request.store] : undefined);
+    const metadata = metadataOf(request.metadata, 'metadata');
+    const response: IngestResponse = { store };
+
+    if (request.path !== undefined) {
+      if (typeof request.path !== 'string') throw new InvalidInputError('path must be a string');
+      const target = this.resolveIngestPath(request.path);
+      const isDirectory = fs.statSync(target).isDirectory();
+      const rootDir = isDirectory ? target : path.dirname(target);
+      const ingester = new DirectoryIngester({
+        ...this.options.ingest,
+        include: stringArray(request.include, 'include'),
+        exclude: stringArray(request.exclude, 'exclude'),
+        metadata: metadata || this.options.ingest?.metadata,
+        confineTo: this.options.ingestRoots,
+      });
+      response.summary = await ingester.upload(this.options.client, store, rootDir, isDirectory ? ingester.collect(rootDir) : [target]);
+    }
+
+    if (request.documents !== undefined) {
+      if (!Array.isArray(request.documents)) throw new InvalidInputError('documents must be an array');
+      const documents = request.documents.map((document, i): InlineDocument => {
+        const entry = document as Partial<InlineDocument>;
+        if (typeof entry?.displayName !== 'string' || !entry.displayName) throw new InvalidInputError(`documents[${i}].displayName must be a non-empty string`);
+        if (typeof entry.text !== 'string') throw new InvalidInputError(`documents[${i}].text must be a string`);
+        return { displayName: entry.displayName, text: entry.text, mimeType: entry.mimeType, metadata: metadataOf(entry.metadata, `documents[${i}].metadata`) };
+      });
+      const tempDir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-api-'));
+      try {
+        response.documents = [];
+        for (const [i, document] of documents.entries()) {
+          const filePath = path.join(tempDir, `${i}.txt`);
+          fs.writeFileSync(filePath, document.text);
+          const result = await this.options.client.uploadFile(store, filePath, {
+            displayName: document.displayName,
+            mimeType: document.mimeType || 'text/plain',
+            metadata: { ...metadata, ...document.metadata },
+          });
+          response.documents.push({ displayName: document.displayName, ...result });
+        }
+      } finally {
+        fs.rmSync(tempDir, { recursive: true, force: true });
+      }
+    }
+    return response;
+  }
+
+  // Relative paths are taken from the first ingest root; symlinks may not lead out of the
+  // roots, neither the requested path nor any below it, which the walk skips (confineTo)
+  private resolveIngestPath(requested: string): string {
+    const roots = (this.options.ingestRoots || []).map(root => fs.realpathSync(path.resolve(root)));
+    if (roots.length === 0) throw new AccessDeniedError('This server accepts no paths to ingest; send the documents inline');
+    let target: string;
+    try {
+      target = fs.realpathSync(path.resolve(roots[0], requested));
+    } catch (error) {
+      throw new NotFoundError(requested);
+    }
+    if (!roots.some(root => isWithin(root, target))) throw new AccessDeniedError(`${requested} is outside of the ingest roots`);
+    return target;
+  }
+}
@@ -0,0 +1,407 @@
+// index.ts - Public library API for embedding File Search in other services
+//
+// Everything exported here is part of the stable surface; modules under bin/
+// are command-line entry points built on top of it.
+
+// Client
+export {
+  FileSearchClient,
+  FileSearchClientBuilder,
+  FileSearchClientOptions,
+  UploadFileOptions,
+  UploadResult,
+  QueryOptions,
+  MAX_UPLOAD_SIZE,
+  SESSIONS_FILE,
+  DEFAULT_EMBEDDING_MODEL,
+} from './client.js';
+
+// Stores and documents
+export { StoreManager, StoreInfo, ImportFileOptions } from './store.js';
+export { Page, PageOptions, PageFetcher, Paginator } from './pagination.js';
+export { DocumentInfo, DocumentState, DocumentSelection, storeOfDocument, toDocumentInfo, formatDocumentMarkdown, groupDocumentParts, parseAge, selectDocuments } from './documents.js';
+export { StorageTier, CapacityOptions, StoreUsage, UsageReport, STORAGE_TIERS, TIER_STORAGE_BYTES, RECOMMENDED_STORE_BYTES, storeUsage, usageReport, formatUsageMarkdown } from './capacity.js';
+export { PollOptions, PendingOperation, OperationKind, DEFAULT_POLL_OPTIONS, pollDelay, pollUntilDone, waitForOperation, operationOf, PendingOperations } from './operations.js';
+export { QuarantinedFile, QUARANTINE_FILE, Quarantine } from './quarantine.js';
+export { Notifier, NotifyOptions, IndexingEvent, IndexingEventType } from './notify.js';
+export { ExportOptions, RestoreOptions, RestoreSummary, ArchiveManifest, ArchivedFile, StoreArchive, exportStore, writeArchive, readArchive, restoreArchive } from './archive.js';
+export { SourceObject, Source, SourceAuth, SourceOptions, SourceIngestOptions, SourceIngestSummary, DRIVE_SCOPE, GCS_SCOPE, DriveSource, GcsSource, googleSourceAuth, parseSource, ingestSource } from './sources.js';
+export { CitationContext, CitationContextOptions, MAX_CONTEXT_LINES, addCitationContext, findChunk, formatCitationContext, formatContextLocation, projectRoots } from './cite-context.js';
+export { AnswerSidecar, SIDECAR_SUFFIX, AnswerFileWriter, answerSidecar, sidecarPath } from './answer-file.js';
+export { PluginInfo, PluginContext, PluginCredentials, ScaffoldLanguage, PLUGIN_PREFIX, PLUGIN_API_VERSION, SCAFFOLD_LANGUAGES, findPlugins, pluginEnvironment, runPlugin, scaffoldPlugin } from './plugins.js';
+export { CopyStatus, CopySource, CopyPlan, SourceRoot, PlanCopyOptions, CopyOptions, CopySummary, sourcePathOf, planCopy, carriedMetadata, copyDocuments, formatCopyPlan } from './copy.js';
+export { IntegrityStatus, IntegrityCheck, IntegrityReport, VerifyIntegrityOptions, CHECKSUM_KEY, checksumMetadata, uploadedChecksum, verifyIntegrity, integrityDrift, formatIntegrityReport } from './integrity.js';
+
+// Upload
+export { ResumableUploader, ResumableUploadOptions, UploadSessionStore, RESUMABLE_THRESHOLD } from './upload.js';
+export { DirectoryIngester, IngestOptions, IngestProgress, IngestPlan, IngestSummary, UploadedFile, PendingUploadsSummary, waitForPendingUploads, QuarantineRetrySummary, retryQuarantined, PlannedFile, PreparedDocument, PreparedFile, DEFAULT_EXCLUDE_PATTERNS, isExcludedByDefault, CHECKPOINT_FILE } from './ingest.js';
+export { StageManifest, StagedFile, StagedDocument, PushOptions, STAGE_MANIFEST_FILE, PUSH_STATE_FILE, stageDirectory, readStageManifest, pushStaged } from './staging.js';
+export { BatchFailure, BatchResult, FailurePolicy, FAILURE_POLICIES, FailureBudget, parseFailurePolicy, formatFailurePolicy } from './pipeline.js';
+export { NotebookOptions, ConvertedNotebook, notebookToMarkdown, convertNotebookToTempFile, isNotebook } from './notebook.js';
+export { EmailOptions, EmailMessage, ConvertedEmailFile, parseEmail, splitMailbox, readMailbox, readEmailFile, stripQuotedReplies, emailText, emailMetadata, convertEmailToTempFile, isEmail, isMailbox } from './email.js';
+export { TextEncoding, DecodedText, TranscodedFile, detectEncoding, decodeText, readText, transcodeToTempFile, normalizeDisplayName, isTextMimeType } from './encoding.js';
+export { RowChunkingOptions, RowChunk, isTable, parseDelimited, chunkTable } from './tables.js';
+export { DetectedLanguages, detectFileLanguages, detectLanguage, detectCodeLanguage, isStopword, languageMetadata } from './language.js';
+export { FileAttributes, fileAttributes, fileAttributeMetadata, attributesFromMetadata, annotateCitations } from './attributes.js';
+export { ArchiveMember, ARCHIVE_EXTENSIONS, isArchive, expandArchive, archiveMemberOf, relativePathOf, resolvePathOf, cleanupExpandedArchives } from './expand.js';
+export { SyncManifest, SyncPlan, SyncSummary, ReconcileSummary, RollbackSummary, ManifestEntry, MANIFEST_FILE, planSync, applySync, reconcileSync, recordSnapshot, rollbackSync } from './sync.js';
+export { ManifestMismatch, ManifestVerification, canonicalJson, verifyStageManifest, verifySyncManifest, manifestMatches, formatManifestVerification } from './manifest.js';
+export { SnapshotStore, Snapshot, SnapshotInfo, RollbackPlan, SNAPSHOTS_DIR, DEFAULT_MAX_SNAPSHOTS, planRollback } from './snapshots.js';
+export { SyncJournal, JournalEntry, JournalOperation, JOURNAL_FILE } from './journal.js';
+export { DirectoryWatcher, WatchOptions, WatchStatus } from './watch.js';
+export { Shutdown, Flusher, shutdown, writeFileAtomic } from './shutdown.js';
+export { LockOptions, FileLock, PendingChanges, LOCK_SUFFIX, DEFAULT_LOCK_TIMEOUT_MS, withFileLock, withFileLockAsync } from './lock.js';
+export { IndexCache, CacheEntry, PruneSummary, hashFile } from './cache.js';
+export { IngestFailure, IngestDuplicate, IngestReport, ReportFormat, ProgressBars, formatIngestReport } from './progress.js';
+export { DedupMode, DuplicateMatch, Fingerprint, DEDUP_MODES, fingerprintFile, compareFingerprints } from './dedup.js';
+export {
+  RedactionPolicy,
+  RedactionKind,
+  RedactionRule,
+  RedactionFinding,
+  RedactionResult,
+  REDACTION_POLICIES,
+  BUILTIN_RULES,
+  Redactor,
+  parseRuleFile,
+  describeFindings,
+  redactionMetadata,
+} from './redact.js';
+export {
+  DocumentTransform,
+  DocumentContext,
+  TransformedText,
+  TransformRule,
+  FrontMatterTransform,
+  StripCommentsTransform,
+  LineNumbersTransform,
+  MinifyTransform,
+  registerDocumentTransform,
+  listDocumentTransforms,
+  resolveDocumentTransforms,
+  parseTransformRules,
+  applyDocumentTransforms,
+} from './transforms.js';
+export {
+  PreflightMode,
+  PreflightRule,
+  PreflightLimits,
+  PreflightViolation,
+  PreflightReport,
+  PREFLIGHT_MODES,
+  SUPPORTED_MIME_TYPES,
+  DEFAULT_PREFLIGHT_LIMITS,
+  runPreflight,
+  countPdfPages,
+  isSupportedMimeType,
+  formatPreflightReport,
+} from './preflight.js';
+export { DocumentMetadata, MetadataValue, validateMetadata, parseMetadataPairs } from './metadata.js';
+export {
+  AclPolicy,
+  ACL_METADATA_KEY,
+  parseAclLabels,
+  withAclLabels,
+  resolveAclLabels,
+  aclFilter,
+  scopeFilter,
+} from './acl.js';
+export {
+  TenantPolicy,
+  TENANT_SEPARATOR,
+  parseTenantName,
+  resolveTenant,
+  tenantPolicy,
+  stateHome,
+  tenantHome,
+  tenantDir,
+  tenantStatePath,
+  statePath,
+  syncStatePath,
+  tenantDisplayName,
+  isTenantStore,
+  withoutTenant,
+  crossTenantError,
+} from './tenant.js';
+export { ChunkingConfig, ChunkingRules, ChunkingPolicy, validateChunkingConfig } from './chunking.js';
+export { MimeRegistry, MimeOverrides, getMimeType } from './file-types.js';
+
+// Query
+export { QueryEvent, TextEvent, CitationEvent, DoneEvent } from './stream.js';
+export { Citation, extractCitations, addFootnoteMarkers, formatCitationFile, formatFootnotes } from './citations.js';
+export { AnswerTransform, TransformContext, StripMarkdownTransform, CodeBlocksTransform, TranslateTransform, registerTransform, listTransforms, resolveTransforms, applyTransforms } from './postprocess.js';
+export { NoGroundedAnswer, NoGroundedAnswerReason, AnswerConfidence, DEFAULT_MIN_CONFIDENCE, answerConfidence, checkGrounding, groundingConfidence, formatNoGroundedAnswer } from './grounding.js';
+export {
+  ProvenanceNodeKind,
+  ProvenanceEdgeKind,
+  ProvenanceNode,
+  ProvenanceEdge,
+  ProvenanceGraph,
+  ProvenanceOptions,
+  PROVENANCE_SCHEMA_ID,
+  PROVENANCE_JSON_SCHEMA,
+  buildProvenanceGraph,
+  provenanceToDot,
+  writeProvenance,
+} from './provenance.js';
+export { federatedQuery, FederationMode, FederatedTarget, FederatedAnswer, StoreAnswer } from './federated.js';
+export {
+  ShardSet,
+  ShardCountOptions,
+  ShardUploadSummary,
+  SHARD_MAP_FILE,
+  MAX_SHARDS,
+  ShardMap,
+  shardDisplayName,
+  shardOf,
+  partitionByShard,
+  shardsNeeded,
+  createShards,
+  uploadSharded,
+  deleteShards,
+} from './shards.js';
+export { runBatchQueries, BatchQuestion, BatchResult, BatchOptions, BatchSummary } from './batch.js';
+export { runEvaluation, loadEvalDataset, formatScorecard, AnswerMatch, EvalCase, EvalCaseResult, EvalOptions, EvalScorecard, ANSWER_MATCHES } from './eval.js';
+export { runBenchmark, formatBenchReport, latencyStats, percentile, parseChunkingArgument, BenchOptions, BenchReport, UploadBenchRun, QueryBench, LatencyStats, DEFAULT_BENCH_CONCURRENCY, DEFAULT_QUERY_RUNS } from './bench.js';
+export { ChatSession, ChatSessionOptions, ChatReply, ChatTurn } from './chat.js';
+export { QueryCache, QueryCacheKey, CachedAnswer, storeRevision } from './query-cache.js';
+export { EmbeddingRecord, EmbedOptions, PassageOptions, LocalSearchOptions, LocalHit, HybridHit, VectorFormat, VectorIndex, DEFAULT_PASSAGE_SIZE, DEFAULT_PASSAGE_OVERLAP, EXACT_SEARCH_LIMIT, VECTOR_FORMATS, splitPassages, embedTexts, signature, vectorFormat, vectorMetadataPath, writeVectorFile, readVectorFile, fuseResults, formatLocalHits, formatHybridHits } from './embeddings.js';
+export { RetrieveOptions, RetrievedChunk, DEFAULT_TOP_K, RETRIEVE_ONLY_INSTRUCTION, chunksFromGrounding, chunksFromContexts, formatRetrievedChunks } from './retrieve.js';
+export { CitationFormat, PromptPreset, PromptRule, PromptConfig, CITATION_FORMATS, DEFAULT_PROMPT_CONFIG, parsePromptConfig, promptPreset, presetInstruction, resolveSystemInstruction, withAnswerLanguage, answerLanguageOf, readSystemOption } from './prompts.js';
+export { AnswerLanguage, AnswerLanguageReport, AnswerLanguageOptions, ANSWER_LANGUAGES, parseAnswerLanguage, answerLanguageInstruction, answerLanguageMismatch, enforceAnswerLanguage, translateCitations } from './answer-language.js';
+export { CorpusStatsOptions, CorpusStats, MimeTypeStats, SizeBucket, MetadataKeyStats, TermStats, SuspectDocument, DEFAULT_TOP_TERMS, corpusStats, formatCorpusStatsMarkdown } from './stats.js';
+export { compareAnswers, formatComparison, diffWords, diffSimilarity, CompareSide, CompareOptions, ComparisonReport, QuestionComparison, SideAnswer, DiffSegment, DiffOp, DEFAULT_DIVERGENCE_THRESHOLD } from './compare.js';
+export { QueryHistory, HistoryEntry, HistoryFilter, HistoryParams, HistorySource, ReplayResult, replayQuery, describeAnswer, hashAnswer, formatHistoryEntry, QUERY_HISTORY_FILE } from './history.js';
+export { FilterSyntaxError, parseFilterExpression, metadataMatcher } from './search.js';
+export { TemplateVars, TemplateError, renderTemplate, renderTemplateFile, parseVarPairs } from './templates.js';
+export {
+  RoutingMode,
+  RoutingRule,
+  RoutingConfig,
+  RouteTarget,
+  RouteDecision,
+  ROUTING_MODES,
+  parseRoutingConfig,
+  routeByKeywords,
+  routeByModel,
+  routeQuestion,
+} from './routing.js';
+export {
+  GenerationParams,
+  SafetyCategory,
+  SafetyThreshold,
+  SAFETY_CATEGORIES,
+  SAFETY_THRESHOLDS,
+  parseSafetySettings,
+  parseGenerationConfig,
+  mergeGenerationParams,
+  validateGenerationParams,
+  toGenerationConfig,
+  BlockedAnswer,
+  BlockedCategory,
+  BlockedStage,
+  blockedAnswer,
+  formatBlockedAnswer,
+} from './generation.js';
+export { QuestionAnswer, MultiAnswer, AskAllOptions, MAX_SHARED_QUESTIONS, askAll } from './ask-all.js';
+export { FileSection, SectionExplanation, FileExplanation, ExplainOptions, DEFAULT_SECTION_LINES, DEFAULT_MAX_SECTIONS, splitSections, explainFile, formatExplanationMarkdown } from './explain.js';
+export {
+  DiffLine,
+  DiffHunk,
+  DiffFile,
+  ReviewChunk,
+  ReviewSeverity,
+  ReviewComment,
+  ReviewReport,
+  ReviewOptions,
+  ReviewFormat,
+  REVIEW_FORMATS,
+  DEFAULT_REVIEW_TEMPLATE,
+  parseUnifiedDiff,
+  chunkDiff,
+  reviewDiff,
+  formatReview,
+} from './review.js';
+export { JsonSchema, JsonSchemaType, loadJsonSchema, validateJsonSchema, parseStructuredAnswer } from './schema.js';
+export { FilePart, DEFAULT_PART_SIZE, isMarkdownFile, splitIntoParts } from './parts.js';
+export { SummaryOptions, SUMMARY_KIND, DEFAULT_SUMMARY_MIN_BYTES, summarize, uploadSummary, summaryDisplayName } from './summaries.js';
+export { HtmlElement, HtmlNode, ReadableContent, parseHtml, htmlToMarkdown, extractReadable } from './html.js';
+export {
+  WebPage,
+  Sitemap,
+  WebIngestOptions,
+  WebIngestSummary,
+  fetchPage,
+  parseSitemap,
+  collectSitemapUrls,
+  pageDisplayName,
+  ingestUrls,
+} from './web.js';
+export {
+  GitFileHistory,
+  GitChanges,
+  GitIngestOptions,
+  GitIngestSummary,
+  openRepository,
+  resolveCommit,
+  exportTree,
+  fileHistory,
+  changedFiles,
+  diffRange,
+  fileAtRef,
+  ingestGitRepository,
+} from './git.js';
+export { AnswerReport, Formatter, OutputFormat, OUTPUT_FORMATS, TerminalFormatter, getFormatter } from './output.js';
+export { RenderOptions, renderMarkdown, highlightCode, displayWidth, supportsRendering } from './render.js';
+export { HttpRequest, HttpResponse, HttpTransport, CoreQueryOptions, CoreAnswer, CoreClientOptions, CoreClient, DEFAULT_MODEL, fetchTransport, queryConfig, generateContentBody, answerText, parseAnswer } from './core.js';
+export { TokenUsage, toTokenUsage, estimateTokens, indexingCost, queryCost, formatCost, formatUsage } from './cost.js';
+
+// Configuration, credentials and errors
+export { Settings, loadSettings, resolveSettings, findConfigFile } from './config.js';
+export { Shell, CommandSpec, OptionSpec, SHELLS, COMMANDS, generateCompletion, generateManPage } from './completions.js';
+export { AuthMethod, AuthSettings, AuthProvider, KeyRingAuthProvider, createAuthProvider, resolveApiKey, resolveApiKeys, hasApplicationDefaultCredentials } from './auth.js';
+export { ApiKeyEntry, KeyState, KeyUsage, KeyStatus, KeyRingOptions, KeyRing, KEYS_ENV, keyUsageFilePath, keyId } from './keys.js';
+export {
+  CredentialBackend,
+  CredentialOptions,
+  StoredCredential,
+  CREDENTIAL_BACKENDS,
+  PASSPHRASE_ENV,
+  credentialsFilePath,
+  keychainAvailable,
+  saveCredential,
+  loadCredential,
+  deleteCredential,
+  hasStoredCredential,
+} from './credentials.js';
+export { BackendKind, BackendConfig, ApiOptions, DEFAULT_API_VERSION, DEFAULT_VERTEX_API_VERSION, resolveBackend, resolveApiOptions, parseHeadersConfig, parseHeaderFlags } from './backend.js';
+export {
+  GenerateContentRequest,
+  ApiErrorBody,
+  ResumableUploadStartRequest,
+  RagCorpus,
+  RagFile,
+  RagFileState,
+  ListRagCorporaResponse,
+  ListRagFilesResponse,
+  UploadRagFileMetadata,
+  UploadRagFileResponse,
+  VertexOperation,
+} from './models.js';
+export { RetryOptions, DEFAULT_RETRY_OPTIONS, withRetry } from './retry.js';
+export {
+  QuotaConfig,
+  QuotaExhaustedAction,
+  DailyUsage,
+  QuotaSchedulerOptions,
+  QuotaScheduler,
+  QUOTA_FILE,
+  parseQuotaConfig,
+  overrideQuotaConfig,
+  formatQuotaUsage,
+  rateLimitPauseMs,
+} from './quota.js';
+export {
+  DaemonMessage,
+  DaemonRequest,
+  DaemonReply,
+  DaemonStatus,
+  DaemonServerOptions,
+  DaemonServer,
+  DaemonLink,
+  DAEMON_ENV,
+  DAEMON_SOCKET_ENV,
+  DAEMON_SOCKET,
+  DAEMON_LOG,
+  daemonSocketPath,
+  formatDaemonStatus,
+} from './daemon.js';
+export {
+  CiReportFormat,
+  IngestFileStatus,
+  CiReportTarget,
+  IngestFileResult,
+  IngestSuite,
+  CI_REPORT_FORMATS,
+  parseCiReportTarget,
+  formatJUnitReport,
+  formatCheckstyleReport,
+  formatCiJsonReport,
+  formatCiReport,
+  CiReportWriter,
+} from './ci-report.js';
+export { TuiPane, TuiOptions, Key, FuzzyMatch, TuiApp, parseKeys, fuzzyMatch, filterDocuments, fitText, wrapText } from './tui.js';
+export { telemetry, configureTelemetry, TelemetryOptions } from './telemetry.js';
+export {
+  LogLevel,
+  LoggingOptions,
+  LogFields,
+  LogRecord,
+  LOG_LEVELS,
+  logger,
+  configureLogging,
+  resolveLogLevel,
+  redactSecrets,
+} from './log.js';
+export {
+  NetworkOptions,
+  configureNetwork,
+  describeNetwork,
+  systemCertificates,
+} from './network.js';
+export { Timeouts, TimeoutKind, TIMEOUT_KINDS, parseDuration, parseTimeoutsConfig, Deadline, startDeadline, withTimeout } from './timeouts.js';
+export {
+  ErrorCode,
+  FileSearchError,
+  QuotaExceededError,
+  FileTooLargeError,
+  UnsupportedMimeError,
+  StoreNotFoundError,
+  NotFoundError,
+  AuthFailedError,
+  InvalidInputError,
+  ConfigError,
+  OperationTimeoutError,
+  RequestTimeoutError,
+  OperationFailedError,
+  BudgetExceededError,
+  DailyBudgetExceededError,
+  PreflightError,
+  SchemaValidationError,
+  AccessDeniedError,
+  AnswerBlockedError,
+  StateLockedError,
+  CancelledError,
+  NetworkError,
+  ApiRequestError,
+  toFileSearchError,
+  exitCodeFor,
+} from './errors.js';
+
+// Testing
+export {
+  MockGeminiServer,
+  MockServerOptions,
+  MockRequest,
+  MockReply,
+  MockHandler,
+  MockDocument,
+  RecordedInteraction,
+  RecorderOptions,
+  recordHttp,
+  replayHttp,
+  loadFixtures,
+} from './testing.js';
+
+// REST API
+export {
+  ApiServer,
+  ApiServerOptions,
+  QueryRequest,
+  IngestRequest,
+  IngestResponse,
+  InlineDocument,
+  ApiErrorBody,
+  API_KEYS_ENV,
+  DEFAULT_API_PORT,
+} from './http-api.js';
@@ -0,0 +1,54 @@
+// ingest.test.ts - Which files a directory walk collects when links lead out of the directories it is confined to
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { DirectoryIngester, isWithin } from './ingest.js';
+
+function tempDir(t: TestContext): string {
+  const dir = fs.realpathSync(fs.mkdtempSync(path.join(os.tmpdir(), 'ingest-test-')));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  return dir;
+}
+
+function write(filePath: string, text: string): void {
+  fs.mkdirSync(path.dirname(filePath), { recursive: true });
+  fs.writeFileSync(filePath, text);
+}
+
+test('isWithin: names starting with two dots are inside, .. and what is above it are not', () => {
+  const root = path.join(os.tmpdir(), 'root');
+  assert.ok(isWithin(root, root));
+  assert.ok(isWithin(root, path.join(root, '..notes', 'a.md')));
+  assert.ok(!isWithin(root, path.dirname(root)));
+  assert.ok(!isWithin(root, path.join(root, '..', 'other', 'a.md')));
+  assert.ok(!isWithin(root, path.join(root + '-other', 'a.md')));
+});
+
+test('a confined walk skips links that resolve outside its roots and follows the ones inside', t => {
+  const dir = tempDir(t);
+  const root = path.join(dir, 'docs');
+  const outside = path.join(dir, 'secrets');
+  write(path.join(root, 'a.md'), '# A\n');
+  write(path.join(root, '..notes', 'b.md'), '# B\n');
+  write(path.join(outside, 'key.md'), 'secret\n');
+  fs.symlinkSync(outside, path.join(root, 'linked-dir'));
+  fs.symlinkSync(path.join(outside, 'key.md'), path.join(root, 'linked-file.md'));
+  fs.symlinkSync(path.join(root, '..notes'), path.join(root, 'notes-link'));
+
+  const relative = (files: string[]) => files.map(file => path.relative(root, file)).sort();
+  assert.deepEqual(relative(new DirectoryIngester({ confineTo: [root] }).collect(root)), [
+    path.join('..notes', 'b.md'),
+    'a.md',
+    path.join('notes-link', 'b.md'),
+  ]);
+  // Without confineTo links are followed anywhere, as for the command-line tools
+  assert.deepEqual(relative(new DirectoryIngester().collect(root)), [
+    path.join('..notes', 'b.md'),
+    'a.md',
+    path.join('linked-dir', 'key.md'),
+    'linked-file.md',
+    path.join('notes-link', 'b.md'),
+  ]);
+});
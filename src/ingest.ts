@@ -0,0 +1,1533 @@
+// ingest.ts - Recursive directory ingestion with glob include/exclude filters
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { FileSearchClient, MAX_UPLOAD_SIZE, UploadFileOptions, UploadResult } from './client.js';
+import { ErrorCode, FileSearchError, FileTooLargeError, InvalidInputError, toFileSearchError } from './errors.js';
+import { MimeRegistry } from './file-types.js';
+import { DocumentMetadata } from './metadata.js';
+import { IndexCache, hashFile } from './cache.js';
+import { BatchResult, FailureBudget, FailurePolicy, TokenBucket, runPipeline } from './pipeline.js';
+import { ChunkingPolicy } from './chunking.js';
+import { chunkCode, isCodeFile } from './code-chunker.js';
+import { RowChunkingOptions, chunkTable, isTable } from './tables.js';
+import { ExtractFormat, ExtractedText, canExtract, extractText, extractToTempFile } from './extract.js';
+import { estimateTokens } from './cost.js';
+import { telemetry } from './telemetry.js';
+import { IngestDuplicate, IngestFailure, ProgressBars, formatBytes, formatIngestReport } from './progress.js';
+import { DEFAULT_DEDUP_DISTANCE, DedupMode, DuplicateMatch, Fingerprint, compareFingerprints, fingerprintFile } from './dedup.js';
+import { writeFileAtomic } from './shutdown.js';
+import { PreflightLimits, PreflightReport, runPreflight } from './preflight.js';
+import { SummaryOptions, canSummarize, needsSummary, uploadSummary } from './summaries.js';
+import { NotebookOptions, convertNotebookToTempFile, isNotebook, notebookToMarkdown } from './notebook.js';
+import { EmailOptions, convertEmailToTempFile, emailMetadata, emailText, isEmail, isMailbox, readEmailFile, readMailbox } from './email.js';
+import { ArchiveMember, archiveMemberOf, expandArchive, isArchive, relativePathOf } from './expand.js';
+import { detectFileLanguages, languageMetadata } from './language.js';
+import { fileAttributeMetadata, fileAttributes } from './attributes.js';
+import { decodeName, isTextMimeType, isUtf8Name, linkLegacyName, normalizeDisplayName, readText, transcodeToTempFile } from './encoding.js';
+import { RedactionFinding, Redactor, describeFindings, redactionMetadata } from './redact.js';
+import { PendingOperation, PendingOperations, PollOptions } from './operations.js';
+import { DocumentTransform, TransformRule, applyDocumentTransforms } from './transforms.js';
+import { Quarantine, QuarantinedFile } from './quarantine.js';
+import { DEFAULT_PART_SIZE, splitIntoParts } from './parts.js';
+import { CiReportWriter, IngestFileResult } from './ci-report.js';
+import { checksumMetadata } from './integrity.js';
+import { syncStatePath } from './tenant.js';
+
+// Types
+export interface IngestOptions {
+  include?: string[];
+  exclude?: string[];
+  respectGitignore?: boolean;
+  concurrency?: number;  // Uploads in flight at once (default: 5)
+  requestsPerMinute?: number;  // Upload rate limit, unlimited when omitted
+  resume?: boolean;
+  mimeRegistry?: MimeRegistry;
+  metadata?: DocumentMetadata;  // Attached to every uploaded document
+  fileMetadata?: (filePath: string) => DocumentMetadata | undefined;  // Per-file metadata, merged over metadata
+  cache?: IndexCache;  // Skip files whose content is already indexed in the store
+  chunking?: ChunkingPolicy;
+  codeChunking?: boolean;  // Upload each function/class/impl of source files as its own document
+  rowChunking?: RowChunkingOptions;  // Upload each row, or group of rows, of CSV and TSV files as its own document
+  extractLocally?: boolean;  // Convert PDF, DOCX and XLSX to text before upload
+  progressBars?: boolean;  // Draw progress bars on stderr when it is a terminal
+  dedup?: DedupMode;  // What to do with copies of content already in the store or the same upload; needs the cache
+  dedupDistance?: number;  // Simhash bits within which two files are near-duplicates
+  signal?: AbortSignal;  // Stops starting new files; files in flight still finish
+  abortSignal?: AbortSignal;  // Aborts in-flight transfers; their resumable sessions are kept
+  preflightLimits?: Partial<PreflightLimits>;  // Overrides the File Search limits checked by preflight
+  summaries?: SummaryOptions;  // Upload a one-page summary next to each large file
+  expandArchives?: boolean;  // Ingest the files inside .zip and .tar.gz inputs instead of the archives (default: true)
+  notebooks?: NotebookOptions;  // Jupyter notebooks are always uploaded as Markdown
+  emails?: EmailOptions;  // .eml files are always uploaded as text, .mbox files as a document per message
+  normalizeEncoding?: boolean;  // Upload text files with a BOM or a legacy charset as UTF-8 (default: true)
+  detectLanguage?: boolean;  // Add lang and code_lang metadata detected from text files (default: true)
+  fileAttributes?: boolean;  // Add path, size, mtime, owner and depth metadata from the file system (default: true)
+  redaction?: Redactor;  // Scans the text of every document for secrets and PII before upload
+  transforms?: TransformRule[];  // Document transforms for the text of files matching each pattern, applied before redaction
+  wait?: boolean;  // false: whole files are not waited on while they index (default: true)
+  pending?: PendingOperations;  // Where uploads that did not wait are kept until a later wait confirms them
+  quarantine?: Quarantine;  // Where files that failed are kept for retryQuarantined, and dropped from once they upload
+  partSize?: number;  // Text documents larger than this are uploaded in parts "<path>#part-N" (default and most: 100MB)
+  report?: CiReportWriter;  // Gets the per-file results of every upload, see ci-report.ts
+  failurePolicy?: FailurePolicy;  // Whether failed files stop the run; the files left are checkpointed as when cancelled (default: continue)
+  confineTo?: string[];  // Directories the walk may not leave: links resolving outside all of them are skipped (default: links are followed anywhere)
+}
+
+export interface PendingUploadsSummary {
+  indexed: { operation: string; displayName: string; documentName?: string }[];
+  failed: { operation: string; displayName: string; reason: string }[];  // Removed from the pending list
+  pending: PendingOperation[];  // Still indexing when the wait timed out or was cancelled
+}
+
+export interface QuarantineRetrySummary {
+  retried: number;  // Files uploaded again
+  uploaded: number;
+  failed: number;  // Still in the quarantine, with the new error
+  missing: QuarantinedFile[];  // No longer on disk, dropped from the quarantine
+}
+
+export interface IngestProgress {
+  totalFiles: number;
+  successCount: number;
+  errorCount: number;
+  progress: number;
+  lastFile?: string;  // Relative path of the most recently finished file
+}
+
+export interface PlannedFile {
+  path: string;  // Relative path
+  size: number;  // Bytes that would be uploaded (the extracted text for converted files)
+  mimeType: string;
+  extractedFrom?: ExtractFormat | 'ipynb' | 'eml' | 'mbox';
+  documents: number;  // One per symbol with code chunking
+  parts?: number;  // Estimated parts of a text document over the part size
+  estimatedTokens: number;
+  skipped?: string;  // Why the file would not be uploaded
+}
+
+export interface IngestPlan {
+  files: PlannedFile[];
+  uploadCount: number;
+  skippedCount: number;
+  totalBytes: number;
+  estimatedTokens: number;
+}
+
+export interface PreparedDocument {
+  uploadPath: string;  // The file itself, or a converted copy
+  temporary: boolean;  // uploadPath is a converted copy, deleted once it is used
+  options: UploadFileOptions;  // Display name, MIME type, metadata and chunking
+}
+
+export interface PreparedFile {
+  path: string;  // Relative path
+  documents: PreparedDocument[];  // One per symbol with code chunking
+  skipped?: string;  // Why the file would not be uploaded
+}
+
+export interface UploadedFile {
+  path: string;  // Relative path
+  result: UploadResult;  // Of the upload, the cached upload or the copy it duplicates
+}
+
+// succeeded and failed hold the files in the order they finished, failed with the error
+// each failed on; a file skipped before upload fails with the reason it was skipped
+export interface IngestSummary extends BatchResult<UploadedFile> {
+  totalFiles: number;
+  successCount: number;  // Includes files skipped because they were already indexed or duplicates
+  errorCount: number;
+  cachedCount: number;
+  duplicateCount: number;
+  failures: IngestFailure[];  // Relative paths with the reason and retry count
+  duplicates: IngestDuplicate[];
+  remaining: string[];  // Relative paths not uploaded because the run was cancelled
+  uploadedBytes: number;
+  retries: number;
+  durationMs: number;
+  files: IngestFileResult[];  // Every file in the order given, with its status and duration
+  stopped?: string;  // Why the failure policy left the remaining files unstarted
+}
+
+// Written to the root directory when a run is cancelled, so the next run over the
+// same store resumes the resumable sessions of the files that were interrupted
+interface IngestCheckpoint {
+  version: number;
+  storeName: string;
+  cancelledAt: string;
+  interrupted: string[];  // Relative paths that were in flight
+  remaining: string[];  // Relative paths, including the interrupted ones
+}
+
+// A piece of a file uploaded as its own document "<path>#<name>"
+interface FileChunk {
+  name: string;
+  text: string;
+  mimeType?: string;  // Replaces the file's when the piece is not in its format
+  metadata: DocumentMetadata;  // Wins over the file's metadata
+  values?: DocumentMetadata;  // Gives way to the file's metadata, e.g. the columns of a table row
+}
+
+// Collected while a file is uploaded, for the end-of-run report
+interface FileOutcome {
+  retries: number;
+  reason?: string;
+  code?: ErrorCode;  // Unset for files skipped before upload
+  error?: FileSearchError;
+}
+
+interface IgnoreRule {
+  base: string;  // Directory of the .gitignore that declared the rule, relative to the root
+  regex: RegExp;
+  negate: boolean;
+  dirOnly: boolean;
+}
+
+// Globs that are always skipped, matched against each segment of a relative path, so
+// "dist" skips dist/ and everything below it but not distance.md
+export const DEFAULT_EXCLUDE_PATTERNS: string[] = [
+  'node_modules', '.git', 'dist', 'build', '.env', '.env.*', '.DS_Store',
+  'package-lock.json', 'yarn.lock', 'logs', '*.log', '__tests__',
+  '*.test.*', '*.spec.*', 'policies-store', '.gemini-sync.json',
+  '.gemini-ingest-checkpoint.json', '.gemini-sync-journal.jsonl', '.gemini-sync-snapshots',
+];
+
+export const CHECKPOINT_FILE = '.gemini-ingest-checkpoint.json';
+
+// Whether target is root or below it; "..notes" is a name like any other, only ".." itself climbs out
+export function isWithin(root: string, target: string): boolean {
+  const relative = path.relative(root, target);
+  return relative !== '..' && !relative.startsWith(`..${path.sep}`) && !path.isAbsolute(relative);
+}
+
+function escapeRegExp(text: string): string {
+  return text.replace(/[.+^${}()|[\]\\]/g, '\\$&');
+}
+
+function findClosingBrace(glob: string, start: number): number {
+  let depth = 0;
+  for (let i = start; i < glob.length; i++) {
+    if (glob[i] === '{') depth++;
+    if (glob[i] === '}' && --depth === 0) return i;
+  }
+  return -1;
+}
+
+function splitAlternatives(body: string): string[] {
+  const parts: string[] = [];
+  let depth = 0;
+  let current = '';
+  for (const c of body) {
+    if (c === '{') depth++;
+    if (c === '}') depth--;
+    if (c === ',' && depth === 0) {
+      parts.push(current);
+      current = '';
+    } else {
+      current += c;
+    }
+  }
+  parts.push(current);
+  return parts;
+}
+
+function globSource(glob: string): string {
+  let source = '';
+  for (let i = 0; i < glob.length; i++) {
+    const c = glob[i];
+    if (c === '*') {
+      if (glob[i + 1] === '*') {
+        // '**/' matches zero or more directories, a trailing '**' matches everything below
+        if (glob[i + 2] === '/') {
+          source += '(?:.*/)?';
+          i += 2;
+        } else {
+          source += '.*';
+          i += 1;
+        }
+      } else {
+        source += '[^/]*';
+      }
+    } else if (c === '?') {
+      source += '[^/]';
+    } else if (c === '[') {
+      const close = glob.indexOf(']', i + 1);
+      if (close === -1) {
+        source += '\\[';
+      } else {
+        const body = glob.slice(i + 1, close).replace(/\\/g, '\\\\');
+        source += body.startsWith('!') ? `[^${body.slice(1)}]` : `[${body}]`;
+        i = close;
+      }
+    } else if (c === '{') {
+      const close = findClosingBrace(glob, i);
+      if (close === -1) {
+        source += '\\{';
+      } else {
+        source += `(?:${splitAlternatives(glob.slice(i + 1, close)).map(globSource).join('|')})`;
+        i = close;
+      }
+    } else {
+      source += escapeRegExp(c);
+    }
+  }
+  return source;
+}
+
+export function globToRegExp(glob: string): RegExp {
+  return new RegExp(`^${globSource(glob)}$`);
+}
+
+// Patterns without a slash match the file name at any depth
+export function matchesGlob(relativePath: string, glob: string): boolean {
+  const target = glob.includes('/') ? relativePath : path.posix.basename(relativePath);
+  return globToRegExp(glob).test(target);
+}
+
+const DEFAULT_EXCLUDE_REGEXPS = DEFAULT_EXCLUDE_PATTERNS.map(globToRegExp);
+
+export function isExcludedByDefault(relativePath: string): boolean {
+  return relativePath.split('/').some(segment => DEFAULT_EXCLUDE_REGEXPS.some(regex => regex.test(segment)));
+}
+
+export function parseGitignore(content: string, base: string): IgnoreRule[] {
+  const rules: IgnoreRule[] = [];
+  for (const rawLine of content.split(/\r?\n/)) {
+    let line = rawLine.replace(/\s+$/, '');
+    if (!line || line.startsWith('#')) continue;
+
+    const negate = line.startsWith('!');
+    if (negate) line = line.slice(1);
+    if (line.startsWith('\\')) line = line.slice(1);
+
+    const dirOnly = line.endsWith('/');
+    if (dirOnly) line = line.slice(0, -1);
+
+    // A slash anywhere but the end anchors the pattern to the .gitignore directory
+    const anchored = line.includes('/');
+    if (line.startsWith('/')) line = line.slice(1);
+    if (!line) continue;
+
+    rules.push({
+      base,
+      regex: globToRegExp(anchored ? line : `**/${line}`),
+      negate,
+      dirOnly,
+    });
+  }
+  return rules;
+}
+
+function isIgnored(rules: IgnoreRule[], relativePath: string, isDirectory: boolean): boolean {
+  let ignored = false;
+  for (const rule of rules) {
+    if (rule.dirOnly && !isDirectory) continue;
+    let target = relativePath;
+    if (rule.base) {
+      if (!relativePath.startsWith(`${rule.base}/`)) continue;
+      target = relativePath.slice(rule.base.length + 1);
+    }
+    if (rule.regex.test(target)) ignored = !rule.negate;
+  }
+  return ignored;
+}
+
+export class DirectoryIngester {
+  private readonly include: string[];
+  private readonly exclude: string[];
+  private readonly respectGitignore: boolean;
+  private readonly concurrency: number;
+  private readonly rateLimiter?: TokenBucket;
+  private readonly resume: boolean;
+  private readonly mimeRegistry: MimeRegistry;
+  private readonly metadata?: DocumentMetadata;
+  private readonly fileMetadata?: (filePath: string) => DocumentMetadata | undefined;
+  readonly cache?: IndexCache;
+  private readonly chunking: ChunkingPolicy;
+  private readonly codeChunking: boolean;
+  private readonly rowChunking?: RowChunkingOptions;
+  private readonly extractLocally: boolean;
+  private readonly progressBars: boolean;
+  private readonly dedup?: DedupMode;
+  private readonly dedupDistance: number;
+  private readonly signal?: AbortSignal;
+  private readonly abortSignal?: AbortSignal;
+  private readonly preflightLimits: Partial<PreflightLimits>;
+  private readonly summaries?: SummaryOptions;
+  private readonly expandArchives: boolean;
+  private readonly notebooks: NotebookOptions;
+  private readonly emails: EmailOptions;
+  private readonly normalizeEncoding: boolean;
+  private readonly detectLanguage: boolean;
+  private readonly fileAttributes: boolean;
+  private readonly redaction?: Redactor;
+  private readonly transforms: TransformRule[];
+  private readonly wait: boolean;
+  private readonly pending?: PendingOperations;
+  private readonly quarantine?: Quarantine;
+  private readonly partSize: number;
+  readonly report?: CiReportWriter;
+  readonly failurePolicy: FailurePolicy;
+  private readonly confineTo?: string[];
+
+  constructor(options: IngestOptions = {}) {
+    this.include = options.include || [];
+    this.exclude = options.exclude || [];
+    this.respectGitignore = options.respectGitignore !== false;
+    this.concurrency = options.concurrency || 5;
+    this.rateLimiter = options.requestsPerMinute ? new TokenBucket(options.requestsPerMinute) : undefined;
+    this.resume = options.resume === true;
+    this.mimeRegistry = options.mimeRegistry || new MimeRegistry();
+    this.metadata = options.metadata;
+    this.fileMetadata = options.fileMetadata;
+    this.cache = options.cache;
+    this.chunking = options.chunking || new ChunkingPolicy();
+    this.codeChunking = options.codeChunking === true;
+    this.rowChunking = options.rowChunking;
+    this.extractLocally = options.extractLocally === true;
+    this.progressBars = options.progressBars === true;
+    this.dedup = options.dedup;
+    this.dedupDistance = options.dedupDistance ?? DEFAULT_DEDUP_DISTANCE;
+    this.signal = options.signal;
+    this.abortSignal = options.abortSignal;
+    this.preflightLimits = options.preflightLimits || {};
+    this.summaries = options.summaries;
+    this.expandArchives = options.expandArchives !== false;
+    this.notebooks = options.notebooks || {};
+    this.emails = options.emails || {};
+    this.normalizeEncoding = options.normalizeEncoding !== false;
+    this.detectLanguage = options.detectLanguage !== false;
+    this.fileAttributes = options.fileAttributes !== false;
+    this.redaction = options.redaction;
+    this.transforms = options.transforms || [];
+    this.wait = options.wait !== false;
+    this.pending = options.pending;
+    this.quarantine = options.quarantine;
+    this.report = options.report;
+    this.failurePolicy = options.failurePolicy || 'continue';
+    this.partSize = Math.min(options.partSize || DEFAULT_PART_SIZE, MAX_UPLOAD_SIZE);
+    this.confineTo = options.confineTo?.map(root => fs.realpathSync(path.resolve(root)));
+  }
+
+  // Walks the tree and returns every file that passes the filters
+  collect(rootDir: string): string[] {
+    const files: string[] = [];
+    this.walk(rootDir, rootDir, [], files, new Set());
+    return files;
+  }
+
+  // Links are followed, within confineTo when given; ancestors holds the real paths of the
+  // directories being walked, so a link back to one of them is skipped instead of walked forever
+  private walk(rootDir: string, dirPath: string, inheritedRules: IgnoreRule[], files: string[], ancestors: Set<string>): void {
+    let rules = inheritedRules;
+    const base = path.relative(rootDir, dirPath).split(path.sep).join('/');
+    let realPath: string;
+    try {
+      realPath = fs.realpathSync(dirPath);
+    } catch (error) {
+      console.error(`⚠️  Skipped unreadable entry: ${base} - ${(error as Error).message}`);
+      return;
+    }
+    if (ancestors.has(realPath)) {
+      console.error(`⚠️  Skipped link to a parent directory: ${base}`);
+      return;
+    }
+    ancestors.add(realPath);
+
+    if (this.respectGitignore) {
+      const gitignorePath = path.join(dirPath, '.gitignore');
+      if (fs.existsSync(gitignorePath)) {
+        rules = [...rules, ...parseGitignore(fs.readFileSync(gitignorePath, 'utf8'), base)];
+      }
+    }
+
+    try {
+      // readdir order depends on the file system; byte order is the same everywhere, so
+      // files, artifacts and manifests come out in the same order on every machine
+      const items = fs.readdirSync(dirPath, { encoding: 'buffer' }).sort(Buffer.compare);
+      for (const rawName of items) {
+        // Names that are not UTF-8 are reached through a link with the decoded name
+        const legacy = isUtf8Name(rawName) ? undefined : this.linkLegacyFile(rootDir, dirPath, rawName, base);
+        if (legacy === null) continue;
+        const item = legacy ? path.posix.basename(legacy.relativePath) : rawName.toString('utf8');
+        const fullPath = legacy ? legacy.path : path.join(dirPath, item);
+        const relativePath = legacy ? legacy.relativePath : base ? `${base}/${item}` : item;
+        if (isExcludedByDefault(relativePath)) continue;
+
+        let stat: fs.Stats;
+        try {
+          stat = fs.statSync(fullPath);
+        } catch (error) {
+          console.error(`⚠️  Skipped unreadable entry: ${relativePath} - ${(error as Error).message}`);
+          continue;
+        }
+        const isDirectory = stat.isDirectory();
+        if (isIgnored(rules, relativePath, isDirectory)) continue;
+        if (this.confineTo && !this.isConfined(fullPath)) {
+          console.error(`⚠️  Skipped link leading out of the ingest roots: ${relativePath}`);
+          continue;
+        }
+
+        // Directories are tested with a trailing slash so "target/**" prunes "target" itself
+        const excludeTarget = isDirectory ? `${relativePath}/` : relativePath;
+        if (this.exclude.some(glob => matchesGlob(excludeTarget, glob))) continue;
+
+        if (isDirectory) {
+          this.walk(rootDir, fullPath, rules, files, ancestors);
+        } else if (this.expandArchives && isArchive(item) && this.addArchive(fullPath, relativePath, rules, files)) {
+          continue;
+        } else if (this.include.length === 0 || this.include.some(glob => matchesGlob(relativePath, glob))) {
+          files.push(fullPath);
+        }
+      }
+    } catch (error) {
+      const err = error as Error;
+      console.error(`Directory read error: ${dirPath}`, err.message);
+    } finally {
+      ancestors.delete(realPath);
+    }
+  }
+
+  // Whether the real path of an entry, through every link on the way, is in one of confineTo
+  private isConfined(filePath: string): boolean {
+    let realPath: string;
+    try {
+      realPath = fs.realpathSync(filePath);
+    } catch (error) {
+      return false;
+    }
+    return (this.confineTo || []).some(root => isWithin(root, realPath));
+  }
+
+  // Directories with such names are skipped: the files below them would need links too
+  private linkLegacyFile(rootDir: string, dirPath: string, rawName: Buffer, base: string): { path: string; relativePath: string } | null {
+    const rawPath = Buffer.concat([Buffer.from(dirPath + path.sep), rawName]);
+    try {
+      if (fs.statSync(rawPath).isDirectory()) {
+        console.error(`⚠️  Skipped directory with a non-UTF-8 name: ${base ? `${base}/` : ''}${decodeName(rawName)}`);
+        return null;
+      }
+      return linkLegacyName(rootDir, dirPath, rawName, base);
+    } catch (error) {
+      console.error(`⚠️  Skipped file with a non-UTF-8 name: ${decodeName(rawName)} - ${(error as Error).message}`);
+      return null;
+    }
+  }
+
+  // Adds the archive's files that pass the same filters, under "<archive>/<inner path>";
+  // returns false when the archive cannot be expanded, so it is treated as a plain file
+  private addArchive(archivePath: string, relativePath: string, rules: IgnoreRule[], files: string[]): boolean {
+    let expanded: ArchiveMember[];
+    try {
+      expanded = expandArchive(archivePath, relativePath);
+    } catch (error) {
+      const err = error as Error;
+      console.error(`⚠️  Could not expand ${relativePath}: ${err.message}`);
+      return false;
+    }
+    for (const member of [...expanded].sort((a, b) => (a.innerPath < b.innerPath ? -1 : a.innerPath > b.innerPath ? 1 : 0))) {
+      const memberPath = `${relativePath}/${member.innerPath}`;
+      if (isExcludedByDefault(member.innerPath)) continue;
+      if (isIgnored(rules, memberPath, false)) continue;
+      if (this.exclude.some(glob => matchesGlob(memberPath, glob))) continue;
+      if (this.include.length === 0 || this.include.some(glob => matchesGlob(memberPath, glob))) {
+        files.push(member.path);
+      }
+    }
+    return true;
+  }
+
+  // Uploads files with bounded concurrency, reporting progress as each file finishes
+  async upload(
+    client: FileSearchClient,
+    storeName: string,
+    rootDir: string,
+    files: string[],
+    onProgress?: (progress: IngestProgress) => void,
+    onFileUploaded?: (filePath: string, result: UploadResult) => void
+  ): Promise<IngestSummary> {
+    const startedAt = Date.now();
+    const totalFiles = files.length;
+    const failures: IngestFailure[] = [];
+    const succeeded: UploadedFile[] = [];
+    const failed: IngestSummary['failed'] = [];
+    const duplicates: IngestDuplicate[] = [];
+    const uploaded: string[] = [];  // Relative paths, dropped from the quarantine
+    let successCount = 0;
+    let errorCount = 0;
+    let cachedCount = 0;
+    let uploadedBytes = 0;
+    let retries = 0;
+
+    const sizes = new Map(files.map(filePath => [filePath, fileSize(filePath)]));
+    const bars = this.progressBars && ProgressBars.supported() ? new ProgressBars() : undefined;
+    bars?.start(files.map(filePath => ({ path: relativePathOf(rootDir, filePath), size: sizes.get(filePath) || 0 })));
+
+    onProgress?.({ totalFiles, successCount, errorCount, progress: 0 });
+
+    // Copies of files in this batch wait for the first copy, whose document they may link to
+    const matches = this.findDuplicates(storeName, rootDir, files);
+    const results = new Map<string, UploadResult>();  // By display name
+    const inBatch = (filePath: string) => matches.get(filePath)?.inStore === false;
+
+    const interrupted = new Set(readCheckpoint(rootDir, storeName)?.interrupted);
+    const finished = new Set<string>();  // Files that succeeded or failed, as opposed to cancelled
+    const aborted: string[] = [];
+    const fileResults = new Map<string, IngestFileResult>();
+    // The failure policy stops the pipeline the way cancelling does
+    const budget = new FailureBudget(this.failurePolicy, this.signal);
+
+    const uploadFile = async (filePath: string): Promise<void> => {
+      const fileStartedAt = Date.now();
+      const relativePath = relativePathOf(rootDir, filePath);
+      const outcome: FileOutcome = { retries: 0 };
+      const record = (result: Omit<IngestFileResult, 'path' | 'durationMs' | 'retries'>) => fileResults.set(filePath, {
+        path: relativePath.split(path.sep).join('/'),
+        durationMs: Date.now() - fileStartedAt,
+        retries: outcome.retries,
+        ...result,
+      });
+      const resume = interrupted.has(relativePath);
+      const match = matches.get(filePath);
+      const replacing = match?.inStore === true && this.dedup === 'replace';
+      // Falls back to a normal upload when the first copy in the batch failed
+      const original = match && !replacing ? (match.inStore ? match : results.get(match.of)) : undefined;
+
+      let result: UploadResult | null;
+      let cached = false;
+      if (match && original) {
+        result = this.linkDuplicate(storeName, rootDir, filePath, match, original);
+        duplicates.push({ path: relativePath, of: match.of, exact: match.exact, action: this.dedup === 'alias' ? 'alias' : 'skip' });
+      } else {
+        bars?.update(relativePath, 'uploading');
+        ({ result, cached } = await this.uploadCached(client, storeName, rootDir, filePath, {
+          onRetry: () => {
+            outcome.retries++;
+            bars?.retry(relativePath);
+          },
+          onIndexing: () => bars?.update(relativePath, 'indexing'),
+          signal: this.abortSignal,
+          ...(resume ? { resume } : {}),
+        }, outcome, replacing));
+        if (result && match && replacing) {
+          await this.replaceDuplicate(client, storeName, relativePath, match, result);
+          duplicates.push({ path: relativePath, of: match.of, exact: match.exact, action: 'replace' });
+        }
+      }
+
+      retries += outcome.retries;
+      if (!result && outcome.code === 'CANCELLED') {
+        aborted.push(relativePath);
+        record({ status: 'cancelled', code: outcome.code, reason: outcome.reason });
+        bars?.update(relativePath, 'failed');
+        return;
+      }
+      finished.add(filePath);
+      if (result) {
+        successCount++;
+        uploaded.push(relativePath);
+        succeeded.push({ path: relativePath, result });
+        results.set(toDisplayName(rootDir, filePath), result);
+        if (match && original) {
+          if (this.dedup === 'alias') onFileUploaded?.(filePath, result);
+          record({ status: 'duplicate' });
+          bars?.update(relativePath, 'cached');
+        } else {
+          if (cached) cachedCount++;
+          else uploadedBytes += sizes.get(filePath) || 0;
+          record(cached ? { status: 'cached' } : { status: 'uploaded', bytes: sizes.get(filePath) || 0 });
+          onFileUploaded?.(filePath, result);
+          bars?.update(relativePath, cached ? 'cached' : 'done');
+        }
+      } else {
+        errorCount++;
+        failures.push({ path: relativePath, reason: outcome.reason || 'upload failed', code: outcome.code, retries: outcome.retries });
+        failed.push({ input: relativePath, error: outcome.error || new FileSearchError('OPERATION_FAILED', outcome.reason || 'upload failed') });
+        record({ status: 'failed', code: outcome.code, reason: outcome.reason || 'upload failed' });
+        bars?.update(relativePath, 'failed');
+        if (budget.fail() && !bars) console.error(`🛑 Stopping (${budget.stopped}); files in flight still finish`);
+      }
+
+      const done = successCount + errorCount;
+      const progress = Math.round((done / totalFiles) * 100);
+      onProgress?.({ totalFiles, successCount, errorCount, progress, lastFile: relativePath });
+      if (!bars) console.error(`⏳ Progress: ${progress}% (${done}/${totalFiles} files)`);
+    };
+
+    try {
+      // Upload spans of the files become children of the ingest span
+      await telemetry.trace('ingest', { store: storeName, files: totalFiles }, async span => {
+        const pipeline = { concurrency: this.concurrency, rateLimiter: this.rateLimiter, signal: budget.signal };
+        await runPipeline(files.filter(filePath => !inBatch(filePath)), uploadFile, pipeline);
+        await runPipeline(files.filter(inBatch), uploadFile, pipeline);
+        span.setAttribute('succeeded', successCount).setAttribute('failed', errorCount).setAttribute('cached', cachedCount)
+          .setAttribute('duplicates', duplicates.length);
+      });
+    } finally {
+      bars?.finish();
+      budget.dispose();
+    }
+    this.quarantine?.record(storeName, rootDir, failures, uploaded, this.metadata);
+
+    const remaining = files.filter(filePath => !finished.has(filePath)).map(filePath => relativePathOf(rootDir, filePath));
+    if (remaining.length > 0) {
+      writeCheckpoint(rootDir, {
+        version: 1,
+        storeName,
+        cancelledAt: new Date().toISOString(),
+        interrupted: aborted.map(relativePath => relativePath.split(path.sep).join('/')),
+        remaining: remaining.map(relativePath => relativePath.split(path.sep).join('/')),
+      });
+    } else {
+      fs.rmSync(syncStatePath(rootDir, CHECKPOINT_FILE), { force: true });
+    }
+
+    const summary = {
+      totalFiles,
+      successCount,
+      errorCount,
+      cachedCount,
+      duplicateCount: duplicates.length,
+      succeeded,
+      failed,
+      failures,
+      duplicates,
+      remaining,
+      uploadedBytes,
+      retries,
+      durationMs: Date.now() - startedAt,
+      // Files the run never started were cancelled as well
+      files: files.map(filePath => fileResults.get(filePath) || {
+        path: relativePathOf(rootDir, filePath).split(path.sep).join('/'),
+        status: 'cancelled' as const,
+        durationMs: 0,
+        retries: 0,
+      }),
+      ...(budget.stopped ? { stopped: budget.stopped } : {}),
+    };
+    this.report?.add({
+      name: `ingest ${storeName}`,
+      store: storeName,
+      rootDir,
+      startedAt: new Date(startedAt).toISOString(),
+      durationMs: summary.durationMs,
+      files: summary.files,
+    });
+    console.error(formatIngestReport({ store: storeName, ...summary }));
+    return summary;
+  }
+
+  // Applies the same checks as upload (size limits, MIME detection, local extraction,
+  // code chunking and the index cache) without calling the API
+  dryRun(storeName: string | undefined, rootDir: string, files: string[]): IngestPlan {
+    const matches = this.findDuplicates(storeName, rootDir, files);
+    const planned = files.map(filePath => {
+      const match = matches.get(filePath);
+      const file = this.planFile(storeName, rootDir, filePath);
+      if (!match || file.skipped || (match.inStore && this.dedup === 'replace')) return file;
+      return { ...file, skipped: `${match.exact ? 'duplicate' : 'near-duplicate'} of ${match.of}` };
+    });
+    const uploads = planned.filter(file => !file.skipped);
+    return {
+      files: planned,
+      uploadCount: uploads.length,
+      skippedCount: planned.length - uploads.length,
+      totalBytes: uploads.reduce((sum, file) => sum + file.size, 0),
+      estimatedTokens: uploads.reduce((sum, file) => sum + file.estimatedTokens, 0),
+    };
+  }
+
+  // Checks the files against the size, type, page and store limits without uploading;
+  // storeDocuments is the number of documents the store will already hold
+  preflight(storeName: string | undefined, rootDir: string, files: string[], storeDocuments: number = 0): PreflightReport {
+    const planned = files.map(filePath => this.planFile(storeName, rootDir, filePath));
+    return runPreflight(rootDir, storeName, planned, storeDocuments, this.preflightLimits);
+  }
+
+  private planFile(storeName: string | undefined, rootDir: string, filePath: string): PlannedFile {
+    const relativePath = relativePathOf(rootDir, filePath);
+    let stats: fs.Stats;
+    try {
+      stats = fs.statSync(filePath);
+    } catch (error) {
+      return { path: relativePath, size: 0, mimeType: '', documents: 0, estimatedTokens: 0, skipped: 'unreadable' };
+    }
+
+    const planned: PlannedFile = {
+      path: relativePath,
+      size: stats.size,
+      mimeType: this.mimeRegistry.detect(filePath),
+      documents: 1,
+      estimatedTokens: estimateTokens(stats.size),
+    };
+    if (stats.size === 0) return { ...planned, skipped: 'empty file' };
+
+    if (this.cache && storeName) {
+      try {
+        if (this.cache.get(storeName, hashFile(filePath))) return { ...planned, skipped: 'already indexed' };
+      } catch (error) {
+        return { ...planned, skipped: 'unreadable' };
+      }
+    }
+
+    const chunks = this.split(filePath);
+    if (chunks.length > 1) {
+      planned.documents = chunks.length;
+    } else if (isNotebook(filePath)) {
+      try {
+        planned.size = Buffer.byteLength(notebookToMarkdown(fs.readFileSync(filePath, 'utf8'), this.notebooks).markdown);
+        planned.mimeType = 'text/markdown';
+        planned.extractedFrom = 'ipynb';
+        planned.estimatedTokens = estimateTokens(planned.size);
+      } catch (error) {
+        // The original file is uploaded when conversion fails
+      }
+    } else if (isEmail(filePath) || isMailbox(filePath)) {
+      try {
+        planned.size = Buffer.byteLength(emailText(readEmailFile(filePath, this.emails)));
+        planned.mimeType = 'text/plain';
+        planned.extractedFrom = isMailbox(filePath) ? 'mbox' : 'eml';
+        planned.estimatedTokens = estimateTokens(planned.size);
+      } catch (error) {
+        // The original file is uploaded when conversion fails
+      }
+    } else if (this.extractLocally && canExtract(filePath)) {
+      try {
+        const extracted = extractText(filePath);
+        planned.size = Buffer.byteLength(extracted.text);
+        planned.mimeType = extracted.mimeType;
+        planned.extractedFrom = extracted.format;
+        planned.estimatedTokens = estimateTokens(planned.size);
+      } catch (error) {
+        // The original file is uploaded when extraction fails
+      }
+    }
+
+    if (planned.size > this.partSize && planned.documents === 1 && isTextMimeType(planned.mimeType)) {
+      planned.parts = Math.ceil(planned.size / this.partSize);
+      planned.documents = planned.parts;
+    } else if (planned.size > MAX_UPLOAD_SIZE) {
+      return { ...planned, skipped: 'exceeds 100MB' };
+    }
+    if (this.redaction?.policy === 'block') {
+      const prepared = this.prepare(rootDir, filePath);
+      for (const document of prepared.documents) {
+        if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+      }
+      if (prepared.skipped) return { ...planned, skipped: prepared.skipped };
+    }
+    if (this.summaries && needsSummary(stats.size, this.summaries) && canSummarize(filePath, this.mimeRegistry.detect(filePath))) {
+      planned.documents++;
+    }
+    return planned;
+  }
+
+  // The dedup pass: fingerprints every file before upload and matches it against the
+  // files already indexed in the store and the files earlier in the same batch
+  private findDuplicates(storeName: string | undefined, rootDir: string, files: string[]): Map<string, DuplicateMatch> {
+    const matches = new Map<string, DuplicateMatch>();
+    if (!this.dedup) return matches;
+
+    const seen: { displayName: string; fingerprint: Fingerprint }[] = [];
+    for (const filePath of files) {
+      const size = fileSize(filePath);
+      if (size === 0 || size > MAX_UPLOAD_SIZE) continue;
+      let fingerprint: Fingerprint;
+      try {
+        fingerprint = fingerprintFile(filePath);
+      } catch (error) {
+        continue;  // Unreadable files are reported by the upload
+      }
+
+      const displayName = toDisplayName(rootDir, filePath);
+      let match = storeName ? this.cache?.findDuplicate(storeName, fingerprint, this.dedupDistance, displayName) : undefined;
+      for (const earlier of seen) {
+        if (match?.exact) break;
+        const candidate = compareFingerprints(fingerprint, earlier.fingerprint, this.dedupDistance);
+        if (candidate && (!match || candidate.distance < match.distance)) {
+          match = { of: earlier.displayName, ...candidate, inStore: false };
+        }
+      }
+      if (match) {
+        matches.set(filePath, match);
+      } else {
+        seen.push({ displayName, fingerprint });
+      }
+    }
+    return matches;
+  }
+
+  // Skips a duplicate, or with alias records it in the cache as another name of the kept document
+  private linkDuplicate(storeName: string, rootDir: string, filePath: string, match: DuplicateMatch, original: UploadResult): UploadResult {
+    const relativePath = relativePathOf(rootDir, filePath);
+    const kind = match.exact ? 'duplicate' : `near-duplicate (${match.distance} bits)`;
+    if (this.dedup !== 'alias') {
+      console.error(`⏭️  Skipped ${kind}: ${relativePath} (same as ${match.of})`);
+      return original;
+    }
+    try {
+      this.cache?.record(storeName, hashFile(filePath), {
+        documentName: original.documentName,
+        documentNames: original.documentNames,
+        displayName: toDisplayName(rootDir, filePath),
+        indexedAt: new Date().toISOString(),
+        fingerprint: fingerprintFile(filePath),
+      });
+    } catch (error) {
+      // The alias is still reported, only not remembered
+    }
+    console.error(`🔗 Linked ${kind}: ${relativePath} → ${match.of}`);
+    return { documentName: original.documentName, documentNames: original.documentNames };
+  }
+
+  // Deletes the documents of the copy that the new upload replaces
+  private async replaceDuplicate(
+    client: FileSearchClient,
+    storeName: string,
+    relativePath: string,
+    match: DuplicateMatch,
+    result: UploadResult
+  ): Promise<void> {
+    const kept = new Set(result.documentNames || (result.documentName ? [result.documentName] : []));
+    const stale = (match.documentNames || (match.documentName ? [match.documentName] : [])).filter(name => !kept.has(name));
+    for (const documentName of stale) {
+      try {
+        await client.deleteDocument(documentName);
+        this.cache?.forgetDocument(storeName, documentName);
+      } catch (error) {
+        const err = error as Error;
+        console.error(`⚠️  Could not delete replaced duplicate ${documentName}: ${err.message}`);
+      }
+    }
+    console.error(`♻️  Replaced ${match.exact ? 'duplicate' : 'near-duplicate'} ${match.of} with ${relativePath}`);
+  }
+
+  // The documents upload sends for a file, converted, transcoded, split by symbol,
+  // transformed or redacted as configured, without calling the API. Summaries and dedup are left out
+  // since they need the model or the store.
+  prepare(rootDir: string, filePath: string): PreparedFile {
+    const relativePath = relativePathOf(rootDir, filePath);
+    let size: number;
+    try {
+      size = fs.statSync(filePath).size;
+    } catch (error) {
+      return { path: relativePath, documents: [], skipped: 'unreadable' };
+    }
+    if (size === 0) return { path: relativePath, documents: [], skipped: 'empty file' };
+
+    const displayName = toDisplayName(rootDir, filePath);
+    const mimeType = this.mimeRegistry.detect(filePath);
+    const options: UploadFileOptions = {
+      displayName,
+      mimeType,
+      metadata: this.metadataFor(rootDir, filePath, mimeType),
+      chunking: this.chunking.forFile(filePath),
+    };
+    const { chunks, blocked } = this.redactChunks(relativePath, this.transformChunks(relativePath, filePath, this.split(filePath)));
+    const converted = blocked || chunks.length > 1
+      ? undefined
+      : this.redactDocument(relativePath, filePath, this.transformDocument(relativePath, filePath, this.convert(filePath, options)));
+    const reason = blocked || converted?.blocked;
+    if (reason) return { path: relativePath, documents: [], skipped: reason };
+    const parts = converted?.document ? this.partsOf(displayName, converted.document) : [];
+    const documents = converted?.document && parts.length === 0
+      ? [converted.document]
+      : (parts.length > 0 ? parts : chunks).map(chunk => {
+        const chunkPath = writeChunk(filePath, chunk);
+        const base = parts.length > 0 && converted?.document ? converted.document.options : options;
+        return { uploadPath: chunkPath, temporary: true, options: chunkUploadOptions(base, displayName, relativePath, chunk) };
+      });
+    if (documents.some(document => fileSize(document.uploadPath) > MAX_UPLOAD_SIZE)) {
+      for (const document of documents) {
+        if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+      }
+      return { path: relativePath, documents: [], skipped: 'exceeds 100MB' };
+    }
+    return { path: relativePath, documents };
+  }
+
+  // The file as it is uploaded: notebooks as Markdown, e-mails as text, PDF, DOCX and XLSX
+  // as text with extractLocally, and text in a legacy charset or with a BOM as UTF-8
+  private convert(filePath: string, options: UploadFileOptions): PreparedDocument {
+    const notebook = isNotebook(filePath) ? convertNotebookToTempFile(filePath, this.notebooks) : null;
+    if (notebook) {
+      return {
+        uploadPath: notebook.path,
+        temporary: true,
+        options: {
+          ...options,
+          mimeType: 'text/markdown',
+          resume: false,
+          metadata: {
+            ...options.metadata,
+            extracted_from: 'ipynb',
+            notebook_cells: notebook.cells,
+            ...(notebook.language ? { notebook_language: notebook.language } : {}),
+          },
+        },
+      };
+    }
+    const email = isEmail(filePath) || isMailbox(filePath) ? convertEmailToTempFile(filePath, this.emails) : null;
+    if (email) {
+      return {
+        uploadPath: email.path,
+        temporary: true,
+        options: {
+          ...options,
+          mimeType: 'text/plain',
+          resume: false,
+          metadata: { ...options.metadata, extracted_from: isMailbox(filePath) ? 'mbox' : 'eml', ...emailMetadata(email.message) },
+        },
+      };
+    }
+    const extracted = this.extractLocally && canExtract(filePath) ? extractToTempFile(filePath) : null;
+    if (extracted) {
+      return {
+        uploadPath: extracted.path,
+        temporary: true,
+        options: { ...options, mimeType: extracted.mimeType, resume: false, metadata: { ...options.metadata, extracted_from: extracted.format } },
+      };
+    }
+    const transcoded = this.normalizeEncoding && isTextMimeType(options.mimeType) ? transcodeToTempFile(filePath) : null;
+    if (transcoded) {
+      return {
+        uploadPath: transcoded.path,
+        temporary: true,
+        options: { ...options, resume: false, metadata: { ...options.metadata, source_encoding: transcoded.encoding } },
+      };
+    }
+    return { uploadPath: filePath, temporary: false, options };
+  }
+
+  // File attributes and detected languages give way to metadata set explicitly; the
+  // checksum is always that of the local file, for npm run docs -- verify
+  private metadataFor(rootDir: string, filePath: string, mimeType: string): DocumentMetadata | undefined {
+    const member = archiveMemberOf(filePath);
+    const archiveMetadata = member ? { archive: member.archive, inner_path: member.innerPath } : undefined;
+    const fileMetadata = this.fileMetadata?.(filePath);
+    const attributes = this.fileAttributes ? fileAttributeMetadata(fileAttributes(filePath, relativePathOf(rootDir, filePath))) : {};
+    const detected = this.detectLanguage && isTextMimeType(mimeType) ? languageMetadata(detectFileLanguages(filePath)) : {};
+    const checksum = checksumMetadata(filePath);
+    return archiveMetadata || fileMetadata || Object.keys(attributes).length > 0 || Object.keys(detected).length > 0 || checksum
+      ? { ...attributes, ...detected, ...this.metadata, ...archiveMetadata, ...fileMetadata, ...checksum }
+      : this.metadata;
+  }
+
+  private async uploadCached(
+    client: FileSearchClient,
+    storeName: string,
+    rootDir: string,
+    filePath: string,
+    hooks: Pick<UploadFileOptions, 'onRetry' | 'onIndexing' | 'signal' | 'resume'>,
+    outcome: FileOutcome,
+    fresh: boolean = false  // Upload even when the content is already indexed
+  ): Promise<{ result: UploadResult | null; cached: boolean }> {
+    let hash: string | undefined;
+    if (this.cache) {
+      try {
+        hash = hashFile(filePath);
+      } catch (error) {
+        // Unreadable files are reported by uploadOne
+      }
+      const entry = hash && !fresh ? this.cache.get(storeName, hash) : undefined;
+      if (entry) {
+        return { result: { documentName: entry.documentName, documentNames: entry.documentNames }, cached: true };
+      }
+    }
+
+    const mimeType = this.mimeRegistry.detect(filePath);
+    const options: UploadFileOptions = {
+      mimeType,
+      resume: this.resume,
+      metadata: this.metadataFor(rootDir, filePath, mimeType),
+      chunking: this.chunking.forFile(filePath),
+      ...hooks,
+    };
+    const relativePath = relativePathOf(rootDir, filePath);
+    const { chunks, blocked } = this.redactChunks(relativePath, this.transformChunks(relativePath, filePath, this.split(filePath)));
+    const redacted = blocked || chunks.length > 1
+      ? undefined
+      : this.redactDocument(relativePath, filePath, this.transformDocument(relativePath, filePath, this.convert(filePath, options)));
+    const reason = blocked || redacted?.blocked;
+    if (reason) {
+      outcome.reason = reason;
+      outcome.error = new InvalidInputError(reason);
+      return { result: null, cached: false };
+    }
+    const converted = redacted?.document;
+    const parts = converted ? this.partsOf(toDisplayName(rootDir, filePath), converted) : [];
+    // Split files and files with a summary wait, so their documents can be cleaned up or linked
+    const wait = this.wait || !!(this.summaries && needsSummary(fileSize(filePath), this.summaries));
+    let result: UploadResult | null;
+    try {
+      if (converted && parts.length === 0) {
+        result = await uploadOne(client, storeName, rootDir, filePath, { ...converted.options, wait }, outcome, converted.uploadPath);
+      } else if (converted) {
+        result = await this.uploadChunks(client, storeName, rootDir, filePath, parts, converted.options, outcome);
+      } else {
+        result = await this.uploadChunks(client, storeName, rootDir, filePath, chunks, options, outcome);
+      }
+    } finally {
+      if (converted?.temporary) fs.rmSync(converted.uploadPath, { force: true });
+    }
+    if (result && this.summaries && needsSummary(fileSize(filePath), this.summaries)) {
+      // The model only sees the summarized text masked as the document is
+      const redaction = this.redaction;
+      const summaries = redaction?.policy === 'mask' ? { ...this.summaries, redact: (text: string) => redaction.apply(text).text } : this.summaries;
+      result = await uploadSummary(client, storeName, filePath, toDisplayName(rootDir, filePath), result, options, summaries);
+    }
+    if (result?.operationName) {
+      // Cached only once a wait confirms the document it was indexed as
+      this.pending?.add({
+        operation: result.operationName,
+        kind: 'upload',
+        storeName,
+        displayName: toDisplayName(rootDir, filePath),
+        createdAt: new Date().toISOString(),
+        source: this.quarantine ? { rootDir: path.resolve(rootDir), path: relativePathOf(rootDir, filePath) } : undefined,
+        cache: hash && this.cache ? { hash, entry: { displayName: toDisplayName(rootDir, filePath), fingerprint: fingerprintOf(filePath) } } : undefined,
+      });
+    } else if (result && hash) {
+      this.cache?.record(storeName, hash, {
+        documentName: result.documentName,
+        documentNames: result.documentNames,
+        displayName: toDisplayName(rootDir, filePath),
+        indexedAt: new Date().toISOString(),
+        fingerprint: fingerprintOf(filePath),
+      });
+    }
+    return { result, cached: false };
+  }
+
+  // The transforms of every rule whose pattern matches, in the order of the rules
+  private transformsFor(relativePath: string): DocumentTransform[] {
+    return this.transforms.filter(rule => matchesGlob(relativePath, rule.pattern)).flatMap(rule => rule.transforms);
+  }
+
+  // The transforms over the text of each piece of a split file, numbered from the line it starts at
+  private transformChunks(relativePath: string, filePath: string, chunks: FileChunk[]): FileChunk[] {
+    const chain = this.transformsFor(relativePath);
+    if (chain.length === 0 || chunks.length < 2) return chunks;
+    return chunks.map(chunk => {
+      const firstLine = Number(/^(\d+)-/.exec(String(chunk.metadata.line_range))?.[1] || 1);
+      const result = applyDocumentTransforms(chain, chunk.text, { filePath, relativePath, firstLine });
+      return { ...chunk, text: result.text, metadata: { ...chunk.metadata, ...result.metadata } };
+    });
+  }
+
+  // The same for the document a file is uploaded as, when it is text: the file itself or
+  // its converted copy. Binary files are uploaded as they are.
+  private transformDocument(relativePath: string, filePath: string, document: PreparedDocument): PreparedDocument {
+    const chain = this.transformsFor(relativePath);
+    if (chain.length === 0 || !isTextMimeType(document.options.mimeType)) return document;
+    let text: string;
+    try {
+      text = readText(document.uploadPath);
+    } catch (error) {
+      return document;  // Unreadable files are reported by the upload
+    }
+    const result = applyDocumentTransforms(chain, text, { filePath, relativePath, firstLine: 1 });
+    const metadata = { ...document.options.metadata, ...result.metadata };
+    if (result.text === text) return { ...document, options: { ...document.options, metadata } };
+
+    const transformedPath = document.temporary
+      ? document.uploadPath
+      : path.join(os.tmpdir(), `gemini-transformed-${randomUUID()}${path.extname(filePath)}`);
+    fs.writeFileSync(transformedPath, result.text);
+    return { uploadPath: transformedPath, temporary: true, options: { ...document.options, resume: false, metadata } };
+  }
+
+  // The redaction pass over the pieces of a split file: block leaves the whole file out,
+  // mask replaces the matches, and both mask and tag add redaction metadata to the
+  // pieces with matches. A file that is not split is scanned by redactDocument.
+  private redactChunks(relativePath: string, chunks: FileChunk[]): { chunks: FileChunk[]; blocked?: string } {
+    const redaction = this.redaction;
+    if (!redaction || chunks.length < 2) return { chunks };
+    const findings: RedactionFinding[] = [];
+    const redacted = chunks.map(chunk => {
+      const result = redaction.apply(chunk.text);
+      if (result.findings.length === 0) return chunk;
+      findings.push(...result.findings);
+      return { ...chunk, text: result.text, metadata: { ...chunk.metadata, ...redactionMetadata(result.findings) } };
+    });
+    const blocked = this.reportRedaction(relativePath, findings);
+    return blocked ? { chunks: [], blocked } : { chunks: redacted };
+  }
+
+  // The same for the document a file is uploaded as. Text is scanned as uploaded; PDF, DOCX
+  // and XLSX through their extracted text, and when masked they are uploaded as that text.
+  // Other binary files are uploaded unscanned.
+  private redactDocument(relativePath: string, filePath: string, document: PreparedDocument): { document?: PreparedDocument; blocked?: string } {
+    if (!this.redaction) return { document };
+    let text: string | undefined;
+    let extracted: ExtractedText | undefined;
+    try {
+      if (isTextMimeType(document.options.mimeType)) text = readText(document.uploadPath);
+      else if (canExtract(filePath)) extracted = extractText(filePath);
+    } catch (error) {
+      // Unreadable files are reported by the upload
+    }
+    const source = extracted ? extracted.text : text;
+    const result = source !== undefined ? this.redaction.apply(source) : undefined;
+    if (!result || result.findings.length === 0) return { document };
+
+    const blocked = this.reportRedaction(relativePath, result.findings);
+    if (blocked) {
+      if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+      return { blocked };
+    }
+    const metadata = { ...document.options.metadata, ...redactionMetadata(result.findings) };
+    if (this.redaction.policy === 'tag') return { document: { ...document, options: { ...document.options, metadata } } };
+
+    const maskedPath = document.temporary
+      ? document.uploadPath
+      : path.join(os.tmpdir(), `gemini-redacted-${randomUUID()}${extracted ? '.txt' : path.extname(filePath)}`);
+    fs.writeFileSync(maskedPath, result.text);
+    return {
+      document: {
+        uploadPath: maskedPath,
+        temporary: true,
+        options: {
+          ...document.options,
+          resume: false,
+          ...(extracted ? { mimeType: extracted.mimeType } : {}),
+          metadata: extracted ? { ...metadata, extracted_from: extracted.format } : metadata,
+        },
+      },
+    };
+  }
+
+  // Logs what the redaction pass found; returns why the file is blocked
+  private reportRedaction(relativePath: string, findings: RedactionFinding[]): string | undefined {
+    if (!this.redaction || findings.length === 0) return undefined;
+    const found = describeFindings(findings);
+    if (this.redaction.policy === 'block') {
+      console.error(`🛑 Blocked by the redaction policy: ${relativePath} (${found})`);
+      return `blocked by the redaction policy: ${found}`;
+    }
+    const matches = `${findings.length} ${findings.length === 1 ? 'match' : 'matches'}`;
+    console.error(`${this.redaction.policy === 'mask' ? '🔒 Masked' : '🏷️  Tagged'} ${matches} in ${relativePath} (${found})`);
+    return undefined;
+  }
+
+  // A text document over the part size as its parts, at headings for Markdown, symbols
+  // for source files and paragraphs otherwise; each part names the document it belongs
+  // to, so listings can show them as one. No parts for smaller or binary documents, or
+  // when the text cannot be read; the document is then uploaded (or refused) whole.
+  private partsOf(displayName: string, document: PreparedDocument): FileChunk[] {
+    const size = fileSize(document.uploadPath);
+    if (!isTextMimeType(document.options.mimeType) || size <= this.partSize) return [];
+    let text: string;
+    try {
+      text = readText(document.uploadPath);
+    } catch (error) {
+      return [];
+    }
+    const parts = splitIntoParts(displayName, text, this.partSize, document.options.mimeType);
+    if (parts.length < 2) return [];
+    if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+    console.error(`✂️  Splitting ${displayName} (${formatBytes(size)}) into ${parts.length} parts`);
+    return parts.map(part => ({
+      name: `part-${part.index}`,
+      text: part.text,
+      mimeType: document.options.mimeType,
+      metadata: {
+        part_of: displayName,
+        part: part.index,
+        part_count: parts.length,
+        line_range: `${part.startLine}-${part.endLine}`,
+      },
+    }));
+  }
+
+  // Tables by rows with row chunking, source files by symbol with code chunking
+  private split(filePath: string): FileChunk[] {
+    if (isMailbox(filePath)) return readMailboxChunks(filePath, this.emails);
+    if (this.rowChunking && isTable(filePath)) return readRowChunks(filePath, this.rowChunking);
+    return this.codeChunking ? readCodeChunks(filePath) : [];
+  }
+
+  // Uploads each chunk as "<path>#<name>". If any chunk fails, the chunks
+  // already uploaded are deleted so the file is either fully indexed or not at all.
+  private async uploadChunks(
+    client: FileSearchClient,
+    storeName: string,
+    rootDir: string,
+    filePath: string,
+    chunks: FileChunk[],
+    options: UploadFileOptions,
+    outcome: FileOutcome
+  ): Promise<UploadResult | null> {
+    const relativePath = relativePathOf(rootDir, filePath);
+    const displayName = toDisplayName(rootDir, filePath);
+    const documentNames: string[] = [];
+
+    try {
+      for (const [index, chunk] of chunks.entries()) {
+        // The pipeline already took a token for the first request
+        if (index > 0) await this.rateLimiter?.take();
+
+        const chunkPath = writeChunk(filePath, chunk);
+        try {
+          const result = await client.uploadFile(storeName, chunkPath, chunkUploadOptions(options, displayName, relativePath, chunk));
+          if (result.documentName) documentNames.push(result.documentName);
+        } finally {
+          fs.rmSync(chunkPath, { force: true });
+        }
+      }
+    } catch (error) {
+      const err = toFileSearchError(error);
+      console.error(`❌ Upload error: ${path.basename(filePath)} - ${err.message}`);
+      outcome.reason = err.message;
+      outcome.code = err.code;
+      outcome.error = err;
+      for (const documentName of documentNames) {
+        await client.deleteDocument(documentName).catch(() => undefined);
+      }
+      return null;
+    }
+
+    console.error(`✅ Upload complete: ${relativePath} (${chunks.length} documents)`);
+    return { documentName: documentNames[0], documentNames };
+  }
+}
+
+// Returns no chunks for non-code, oversized or unreadable files so they are uploaded whole
+function readCodeChunks(filePath: string): FileChunk[] {
+  if (!isCodeFile(filePath)) return [];
+  try {
+    if (fs.statSync(filePath).size > MAX_UPLOAD_SIZE) return [];
+    return chunkCode(filePath, readText(filePath)).map(chunk => ({
+      name: chunk.symbol,
+      text: chunk.text,
+      metadata: { symbol: chunk.symbol, symbol_kind: chunk.kind, line_range: `${chunk.startLine}-${chunk.endLine}` },
+    }));
+  } catch (error) {
+    return [];
+  }
+}
+
+// Rows are uploaded as text, the columns of each row as "column: value" lines
+function readRowChunks(filePath: string, options: RowChunkingOptions): FileChunk[] {
+  try {
+    if (fs.statSync(filePath).size > MAX_UPLOAD_SIZE) return [];
+    return chunkTable(filePath, readText(filePath), options).map(chunk => ({
+      name: chunk.name,
+      text: chunk.text,
+      mimeType: 'text/plain',
+      metadata: { line_range: `${chunk.startLine}-${chunk.endLine}` },
+      values: chunk.values,
+    }));
+  } catch (error) {
+    return [];
+  }
+}
+
+// Each message of a mailbox is a document of its own, with its thread metadata
+function readMailboxChunks(filePath: string, options: EmailOptions): FileChunk[] {
+  try {
+    return readMailbox(filePath, options).map((message, i) => ({
+      name: `message-${i + 1}`,
+      text: emailText(message),
+      mimeType: 'text/plain',
+      metadata: { extracted_from: 'mbox', mbox_index: i + 1, ...emailMetadata(message) },
+    }));
+  } catch (error) {
+    return [];
+  }
+}
+
+function writeChunk(filePath: string, chunk: FileChunk): string {
+  const chunkPath = path.join(os.tmpdir(), `gemini-chunk-${randomUUID()}${chunk.mimeType === 'text/plain' ? '.txt' : path.extname(filePath)}`);
+  fs.writeFileSync(chunkPath, chunk.text);
+  return chunkPath;
+}
+
+// A chunk's document is named "<path>#<name>"
+function chunkUploadOptions(options: UploadFileOptions, displayName: string, relativePath: string, chunk: FileChunk): UploadFileOptions {
+  return {
+    ...options,
+    resume: false,
+    displayName: `${displayName}#${chunk.name}`.replace(/[<>:"|?*]/g, '_'),
+    mimeType: chunk.mimeType || options.mimeType,
+    metadata: {
+      ...chunk.values,
+      ...options.metadata,
+      ...chunk.metadata,
+      source_path: relativePath,
+    },
+  };
+}
+
+function fingerprintOf(filePath: string): Fingerprint | undefined {
+  try {
+    return fingerprintFile(filePath);
+  } catch (error) {
+    return undefined;
+  }
+}
+
+function readCheckpoint(rootDir: string, storeName: string): IngestCheckpoint | undefined {
+  try {
+    const checkpoint = JSON.parse(fs.readFileSync(syncStatePath(rootDir, CHECKPOINT_FILE), 'utf8')) as IngestCheckpoint;
+    return checkpoint.storeName === storeName ? checkpoint : undefined;
+  } catch (error) {
+    return undefined;
+  }
+}
+
+function writeCheckpoint(rootDir: string, checkpoint: IngestCheckpoint): void {
+  try {
+    writeFileAtomic(syncStatePath(rootDir, CHECKPOINT_FILE), JSON.stringify(checkpoint, null, 2));
+    console.error(`⏸️  Cancelled with ${checkpoint.remaining.length} files left; the next upload or sync continues from ${CHECKPOINT_FILE}`);
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Could not write ${CHECKPOINT_FILE}: ${err.message}`);
+  }
+}
+
+function fileSize(filePath: string): number {
+  try {
+    return fs.statSync(filePath).size;
+  } catch (error) {
+    return 0;
+  }
+}
+
+// "/"-separated relative path without characters the Gemini API does not accept
+export function toDisplayName(rootDir: string, filePath: string): string {
+  return normalizeDisplayName(relativePathOf(rootDir, filePath));
+}
+
+async function uploadOne(
+  client: FileSearchClient,
+  storeName: string,
+  rootDir: string,
+  filePath: string,
+  options: UploadFileOptions,
+  outcome: FileOutcome,
+  uploadPath: string = filePath  // Converted text to upload in place of the file
+): Promise<UploadResult | null> {
+  try {
+    const stats = fs.statSync(uploadPath);
+    if (stats.size > MAX_UPLOAD_SIZE) {
+      console.error(`⚠️  Skipped (exceeds 100MB): ${path.basename(filePath)}`);
+      outcome.reason = 'exceeds 100MB';
+      outcome.error = new FileTooLargeError(filePath, MAX_UPLOAD_SIZE, stats.size);
+      return null;
+    }
+
+    // Skip empty files
+    if (stats.size === 0) {
+      console.error(`⚠️  Skipped (empty file): ${path.basename(filePath)}`);
+      outcome.reason = 'empty file';
+      outcome.error = new InvalidInputError(`${filePath} is empty`);
+      return null;
+    }
+
+    const relativePath = relativePathOf(rootDir, filePath);
+    const sanitizedDisplayName = toDisplayName(rootDir, filePath);
+
+    const result = await client.uploadFile(storeName, uploadPath, {
+      ...options,
+      displayName: sanitizedDisplayName,
+    });
+
+    console.error(result.operationName ? `⏳ Uploaded, indexing: ${relativePath}` : `✅ Upload complete: ${relativePath}`);
+    return result;
+  } catch (error) {
+    const err = toFileSearchError(error);
+    console.error(`❌ Upload error (${err.code}): ${path.basename(filePath)} - ${err.message}`);
+    outcome.reason = err.message;
+    outcome.code = err.code;
+    outcome.error = err;
+    return null;
+  }
+}
+
+// Waits for uploads made without waiting, records the indexed ones in the cache and
+// drops them and the failed ones from the pending list. Operations given by name that
+// are not on the list are waited on all the same.
+export async function waitForPendingUploads(
+  client: FileSearchClient,
+  pending: PendingOperations,
+  options: { operations?: string[]; storeName?: string; cache?: IndexCache; quarantine?: Quarantine; concurrency?: number; poll?: PollOptions } = {}
+): Promise<PendingUploadsSummary> {
+  const listed = pending.list(options.storeName);
+  const operations: PendingOperation[] = options.operations && options.operations.length > 0
+    ? options.operations.map(name => listed.find(entry => entry.operation === name)
+      || { operation: name, kind: 'upload', storeName: options.storeName || '', displayName: name, createdAt: new Date().toISOString() })
+    : listed;
+  const summary: PendingUploadsSummary = { indexed: [], failed: [], pending: [] };
+
+  await runPipeline(operations, async (entry) => {
+    try {
+      const result = await client.waitForUpload(entry.operation, options.poll);
+      if (entry.cache && entry.storeName) {
+        options.cache?.record(entry.storeName, entry.cache.hash, { ...entry.cache.entry, documentName: result.documentName, indexedAt: new Date().toISOString() });
+      }
+      summary.indexed.push({ operation: entry.operation, displayName: entry.displayName, documentName: result.documentName });
+      if (entry.source) options.quarantine?.record(entry.storeName, entry.source.rootDir, [], [entry.source.path]);
+      pending.remove([entry.operation]);
+    } catch (error) {
+      const err = toFileSearchError(error);
+      // Timed out or cancelled operations stay pending for the next wait
+      if (err.code !== 'TIMEOUT' && err.code !== 'CANCELLED') {
+        summary.failed.push({ operation: entry.operation, displayName: entry.displayName, reason: err.message });
+        if (entry.source) {
+          options.quarantine?.record(entry.storeName, entry.source.rootDir, [{ path: entry.source.path, reason: err.message, code: err.code, retries: 0 }]);
+        }
+        pending.remove([entry.operation]);
+      }
+    }
+  }, { concurrency: options.concurrency || 5, signal: options.poll?.signal });
+  const finished = new Set([...summary.indexed, ...summary.failed].map(entry => entry.operation));
+  summary.pending = operations.filter(entry => !finished.has(entry.operation));
+  return summary;
+}
+
+// Uploads the quarantined files again, a directory at a time, with the given settings
+// (another MIME map or local extraction, say). Files that upload are dropped from the
+// quarantine and files that fail are kept with the new error; files no longer on disk
+// are dropped.
+export async function retryQuarantined(
+  client: FileSearchClient,
+  quarantine: Quarantine,
+  options: IngestOptions = {},
+  storeName?: string
+): Promise<QuarantineRetrySummary> {
+  const summary: QuarantineRetrySummary = { retried: 0, uploaded: 0, failed: 0, missing: [] };
+  const groups = new Map<string, QuarantinedFile[]>();
+  for (const file of quarantine.list(storeName)) {
+    const key = `${file.storeName}\n${file.rootDir}\n${JSON.stringify(file.metadata || {})}`;
+    groups.set(key, [...(groups.get(key) || []), file]);
+  }
+
+  for (const files of groups.values()) {
+    const { storeName: store, rootDir, metadata } = files[0];
+    const ingester = new DirectoryIngester({ ...options, metadata: metadata || options.metadata ? { ...metadata, ...options.metadata } : undefined, quarantine });
+    const wanted = new Set(files.map(file => file.path));
+    // Collecting finds members of archives and files with non-UTF-8 names by their relative path
+    const targets = fs.existsSync(rootDir) ? ingester.collect(rootDir).filter(filePath => wanted.has(relativePathOf(rootDir, filePath))) : [];
+    const found = new Set(targets.map(filePath => relativePathOf(rootDir, filePath)));
+    const missing: QuarantinedFile[] = [];
+    for (const file of files) {
+      if (found.has(file.path)) continue;
+      // Files uploaded by name, e.g. ones ignored by .gitignore
+      const filePath = path.join(rootDir, file.path);
+      if (fs.existsSync(filePath) && fs.statSync(filePath).isFile()) targets.push(filePath);
+      else missing.push(file);
+    }
+    quarantine.remove(missing);
+    summary.missing.push(...missing);
+    if (targets.length === 0) continue;
+
+    console.error(`🔁 Retrying ${targets.length} quarantined files of ${rootDir} in ${store}`);
+    const result = await ingester.upload(client, store, rootDir, targets);
+    summary.retried += targets.length;
+    summary.uploaded += result.successCount;
+    summary.failed += result.errorCount;
+  }
+  return summary;
+}
@@ -0,0 +1,162 @@
+// integrity.ts - SHA-256 checksums of uploaded files, and checks of a store's documents against the local files
+import * as fs from 'fs';
+import * as path from 'path';
+import { FileSearchClient } from './client.js';
+import { DocumentInfo, toDocumentInfo } from './documents.js';
+import { DocumentMetadata } from './metadata.js';
+import { IndexCache, hashFile } from './cache.js';
+import { SourceRoot, documentNamesOf, manifestHash, sourcePathOf } from './copy.js';
+
+// Types
+// ok: the store holds the local file; changed: the file was edited since it was uploaded;
+// tampered: the store's checksum is not the one recorded at upload; mismatch: the checksums
+// differ and nothing was recorded to tell which side changed; inconsistent: the documents of
+// one file disagree; unverified: uploaded without a checksum; missing: no local file;
+// absent: recorded by a sync manifest but no longer in the store
+export type IntegrityStatus = 'ok' | 'changed' | 'tampered' | 'mismatch' | 'inconsistent' | 'unverified' | 'missing' | 'absent';
+
+export interface IntegrityCheck {
+  path: string;  // Relative path the documents were uploaded from
+  status: IntegrityStatus;
+  documents: string[];  // Document names
+  remote?: string;  // SHA-256 in the documents' metadata
+  local?: string;  // SHA-256 of the local file
+  recorded?: string;  // SHA-256 the sync manifest or index cache recorded at upload
+  rootDir?: string;  // Where the file was found
+}
+
+export interface IntegrityReport {
+  storeName: string;
+  checkedAt: string;
+  files: IntegrityCheck[];
+  counts: Partial<Record<IntegrityStatus, number>>;
+}
+
+export interface VerifyIntegrityOptions {
+  roots: SourceRoot[];  // Searched in order
+  cache?: IndexCache;
+}
+
+export const CHECKSUM_KEY = 'sha256';
+
+// What the store and the local files disagree on; missing and unverified files can't be checked
+const DRIFT: IntegrityStatus[] = ['changed', 'tampered', 'mismatch', 'inconsistent', 'absent'];
+
+// The checksum metadata of a file as it is on disk, whatever is uploaded from it; undefined
+// when the file can't be read, which the upload reports itself
+export function checksumMetadata(filePath: string): DocumentMetadata | undefined {
+  try {
+    return { [CHECKSUM_KEY]: hashFile(filePath) };
+  } catch (error) {
+    return undefined;
+  }
+}
+
+// The checksum a file was uploaded with, when all of its documents agree on one
+export function uploadedChecksum(documents: DocumentInfo[]): string | undefined {
+  const checksums = remoteChecksums(documents);
+  return checksums.length === 1 ? checksums[0] : undefined;
+}
+
+// The checksums of a file's documents: its symbols, parts and summary all carry the file's
+function remoteChecksums(documents: DocumentInfo[]): string[] {
+  return [...new Set(documents.map(document => document.metadata[CHECKSUM_KEY]).filter((value): value is string => typeof value === 'string'))];
+}
+
+function checkFile(source: string, documents: DocumentInfo[], options: VerifyIntegrityOptions, cachedHashes: Map<string, string>): IntegrityCheck {
+  const check: IntegrityCheck = { path: source, status: 'missing', documents: documents.map(document => document.name).sort() };
+  const checksums = remoteChecksums(documents);
+  if (checksums.length > 1) return { ...check, status: 'inconsistent' };
+  if (checksums.length === 0) return { ...check, status: 'unverified' };
+  check.remote = checksums[0];
+  if (!source || path.posix.isAbsolute(source) || path.posix.normalize(source).startsWith('..')) return check;
+
+  let drifted: IntegrityCheck | undefined;
+  for (const root of options.roots) {
+    const filePath = path.join(root.rootDir, source);
+    if (!fs.existsSync(filePath) || !fs.statSync(filePath).isFile()) continue;
+    const local = hashFile(filePath);
+    const recorded = manifestHash(source, documents, root)
+      || documents.map(document => cachedHashes.get(document.name)).find(known => known);
+    const found = { ...check, local, recorded, rootDir: root.rootDir };
+    if (local === check.remote) return { ...found, status: 'ok' };
+    // A root further down may hold the version that was uploaded
+    drifted ||= { ...found, status: !recorded ? 'mismatch' : recorded === check.remote ? 'changed' : 'tampered' };
+  }
+  return drifted || check;
+}
+
+// Every file of the store is checked, plus the files the sync manifests of the roots
+// recorded whose documents are gone
+export async function verifyIntegrity(client: FileSearchClient, storeName: string, options: VerifyIntegrityOptions): Promise<IntegrityReport> {
+  const documents = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document));
+  const bySource = new Map<string, DocumentInfo[]>();
+  for (const document of documents) {
+    const source = sourcePathOf(document);
+    bySource.set(source, [...(bySource.get(source) || []), document]);
+  }
+
+  const cachedHashes = new Map<string, string>();
+  if (options.cache) {
+    for (const [hash, entry] of options.cache.hashes(storeName)) {
+      for (const name of documentNamesOf(entry)) cachedHashes.set(name, hash);
+    }
+  }
+
+  const files = [...bySource].map(([source, group]) => checkFile(source, group, options, cachedHashes));
+  const names = new Set(documents.map(document => document.name));
+  const seen = new Set(bySource.keys());
+  for (const root of options.roots) {
+    for (const [source, entry] of Object.entries(root.manifest?.entries() || {})) {
+      const recorded = documentNamesOf(entry);
+      if (seen.has(source) || recorded.length === 0 || recorded.some(name => names.has(name))) continue;
+      seen.add(source);
+      files.push({ path: source, status: 'absent', documents: recorded, recorded: entry.hash, rootDir: root.rootDir });
+    }
+  }
+
+  files.sort((a, b) => a.path.localeCompare(b.path));
+  const counts: Partial<Record<IntegrityStatus, number>> = {};
+  for (const file of files) counts[file.status] = (counts[file.status] || 0) + 1;
+  return { storeName, checkedAt: new Date().toISOString(), files, counts };
+}
+
+export function integrityDrift(report: IntegrityReport): IntegrityCheck[] {
+  return report.files.filter(file => DRIFT.includes(file.status));
+}
+
+function short(hash: string | undefined): string {
+  return hash ? hash.slice(0, 12) : '(none)';
+}
+
+function describe(check: IntegrityCheck): string {
+  switch (check.status) {
+    case 'changed':
+      return `edited since upload: local ${short(check.local)}, store ${short(check.remote)}`;
+    case 'tampered':
+      return `store ${short(check.remote)} is not the uploaded ${short(check.recorded)} (local ${short(check.local)})`;
+    case 'mismatch':
+      return `local ${short(check.local)}, store ${short(check.remote)}, nothing recorded at upload`;
+    case 'inconsistent':
+      return 'its documents carry different checksums';
+    case 'unverified':
+      return `uploaded without ${CHECKSUM_KEY} metadata`;
+    case 'missing':
+      return 'no local file under the source roots';
+    case 'absent':
+      return `${check.documents.length} documents the sync manifest recorded are not in the store`;
+    default:
+      return short(check.local);
+  }
+}
+
+// Files that check out are counted, not listed
+export function formatIntegrityReport(report: IntegrityReport): string {
+  const ok = report.counts.ok || 0;
+  const drift = integrityDrift(report);
+  const lines = [`${drift.length === 0 ? '✅' : '❌'} ${report.storeName}: ${ok}/${report.files.length} files match their local copy`];
+  for (const check of report.files.filter(file => file.status !== 'ok')) {
+    lines.push(`   ${check.status.padEnd(12)} ${check.path}: ${describe(check)}`);
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,107 @@
+// journal.ts - Append-only journal of sync mutations, written ahead of each one
+import * as fs from 'fs';
+import { FileLock } from './lock.js';
+import { syncStatePath } from './tenant.js';
+
+// Types
+export type JournalOperation = 'upload' | 'delete';
+
+export interface JournalEntry {
+  id: number;
+  op: JournalOperation;
+  storeName: string;
+  path: string;  // Relative path of the file, "/"-separated
+  documentName?: string;  // delete: the document removed
+  displayName?: string;  // upload: finds the documents created before a crash
+  hash?: string;  // upload: content of the file when the upload started
+  replaces?: string[];  // upload: documents of the earlier version, deleted once the new one is recorded
+  startedAt: string;
+}
+
+type JournalRecord =
+  | { type: 'begin'; entry: JournalEntry }
+  | { type: 'done'; id: number }
+  | { type: 'failed'; id: number; reason: string };
+
+export const JOURNAL_FILE = '.gemini-sync-journal.jsonl';
+
+// One JSON record per line. Every mutation is begun before it is sent and marked done
+// once the store and the manifest both reflect it, or failed when it was not applied;
+// entries left begun by a crash are reconciled by the next sync (see reconcileSync).
+// An open journal holds the directory's sync lock until it is closed, so a second sync
+// of the same directory fails with StateLockedError instead of interleaving with it.
+export class SyncJournal {
+  private readonly entries = new Map<number, JournalEntry>();  // Begun and not yet settled
+  private nextId = 1;
+
+  private constructor(private readonly journalPath: string, private readonly lock: FileLock) {}
+
+  static open(rootDir: string): SyncJournal {
+    const journalPath = syncStatePath(rootDir, JOURNAL_FILE);
+    const journal = new SyncJournal(journalPath, FileLock.acquire(journalPath, { timeoutMs: 0 }));
+    let text = '';
+    try {
+      text = fs.readFileSync(journal.journalPath, 'utf8');
+    } catch (error) {
+      // No journal: the last sync finished cleanly
+    }
+    // A line torn by a crash is ended, so the next record starts on its own line
+    if (text && !text.endsWith('\n')) fs.appendFileSync(journal.journalPath, '\n');
+    for (const line of text.split('\n')) {
+      let record: JournalRecord;
+      try {
+        record = JSON.parse(line) as JournalRecord;
+      } catch (error) {
+        continue;  // Blank or torn
+      }
+      if (record.type === 'begin') {
+        journal.entries.set(record.entry.id, record.entry);
+        journal.nextId = Math.max(journal.nextId, record.entry.id + 1);
+      } else {
+        journal.entries.delete(record.id);
+      }
+    }
+    return journal;
+  }
+
+  pending(storeName: string): JournalEntry[] {
+    return [...this.entries.values()].filter(entry => entry.storeName === storeName);
+  }
+
+  begin(op: JournalOperation, storeName: string, relativePath: string, fields: Pick<JournalEntry, 'documentName' | 'displayName' | 'hash' | 'replaces'> = {}): number {
+    const entry: JournalEntry = { id: this.nextId++, op, storeName, path: relativePath, ...fields, startedAt: new Date().toISOString() };
+    this.append({ type: 'begin', entry });
+    this.entries.set(entry.id, entry);
+    return entry.id;
+  }
+
+  done(id: number): void {
+    this.append({ type: 'done', id });
+    this.entries.delete(id);
+  }
+
+  failed(id: number, reason: string): void {
+    this.append({ type: 'failed', id, reason });
+    this.entries.delete(id);
+  }
+
+  // Removes the file once every entry is settled, so it only grows within one sync
+  compact(): void {
+    if (this.entries.size === 0) fs.rmSync(this.journalPath, { force: true });
+  }
+
+  close(): void {
+    this.lock.release();
+  }
+
+  // Flushed to disk before the mutation it announces is sent
+  private append(record: JournalRecord): void {
+    const fd = fs.openSync(this.journalPath, 'a');
+    try {
+      fs.writeSync(fd, `${JSON.stringify(record)}\n`);
+      fs.fsyncSync(fd);
+    } finally {
+      fs.closeSync(fd);
+    }
+  }
+}
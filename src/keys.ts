@@ -0,0 +1,230 @@
+// keys.ts - Several API keys used in turn, moving on when one runs out of quota or is rejected
+import * as fs from 'fs';
+import * as path from 'path';
+import { createHash } from 'crypto';
+import { credentialsFilePath } from './credentials.js';
+import { toFileSearchError } from './errors.js';
+import { retryAfterMs } from './retry.js';
+import { redactSecrets } from './log.js';
+
+// Types
+export interface ApiKeyEntry {
+  key: string;
+  source: string;  // Where it came from, e.g. "CLASS_KEY_2" or "GEMINI_API_KEYS #2"
+}
+
+export type KeyState = 'active' | 'cooling' | 'invalid';
+
+export interface KeyUsage {
+  id: string;  // First characters of the key's SHA-256; the key itself is never written
+  requests: number;
+  quotaErrors: number;
+  authErrors: number;
+  lastUsed?: string;  // ISO 8601
+  lastError?: string;
+  coolingUntil?: string;  // Skipped until then after a quota or permission error
+  invalid?: boolean;  // Rejected as not valid; skipped until it is replaced or reset
+}
+
+export interface KeyStatus extends KeyUsage {
+  source: string;
+  state: KeyState;
+  current: boolean;
+}
+
+export interface KeyRingOptions {
+  usageFile?: string;  // Default: keyUsageFilePath()
+  quotaCooldownMs?: number;  // When the API gives no Retry-After (default: 1 minute, 1 hour for daily quotas)
+  authCooldownMs?: number;  // After a 401/403 that is not an invalid key (default: 1 hour)
+}
+
+interface UsageFileData {
+  version: number;
+  keys: { [id: string]: KeyUsage };
+}
+
+type Counter = 'requests' | 'quotaErrors' | 'authErrors';
+
+// Comma-separated keys, on top of api_key_env and api_key_envs
+export const KEYS_ENV = 'GEMINI_API_KEYS';
+
+const DEFAULT_QUOTA_COOLDOWN_MS = 60_000;
+const DAILY_QUOTA_COOLDOWN_MS = 60 * 60_000;
+const DEFAULT_AUTH_COOLDOWN_MS = 60 * 60_000;
+const SAVE_INTERVAL_MS = 5000;
+const MAX_ERROR_LENGTH = 200;
+
+// Quota ids of daily limits, e.g. GenerateRequestsPerDayPerProjectPerModel
+const DAILY_QUOTA = /PerDay|per day/i;
+const INVALID_KEY = /API[_ ]key not valid|API_KEY_INVALID|API key expired/i;
+
+export function keyUsageFilePath(env: NodeJS.ProcessEnv = process.env): string {
+  return path.join(path.dirname(credentialsFilePath(env)), 'key-usage.json');
+}
+
+export function keyId(key: string): string {
+  return createHash('sha256').update(key).digest('hex').slice(0, 12);
+}
+
+function emptyUsage(id: string): KeyUsage {
+  return { id, requests: 0, quotaErrors: 0, authErrors: 0 };
+}
+
+function readUsageFile(file: string): UsageFileData {
+  try {
+    const data = JSON.parse(fs.readFileSync(file, 'utf8')) as UsageFileData;
+    if (data && typeof data.keys === 'object') return data;
+  } catch (error) {
+    // Missing or unreadable: usage starts over
+  }
+  return { version: 1, keys: {} };
+}
+
+// Sticks with one key until the API refuses it for quota or credentials, then moves to
+// the next usable one. Usage and cooldowns are kept in a file shared by every process,
+// so `gemini-fs-auth keys status` can report them and a new process skips keys that are
+// still cooling down. Counts are added to the file rather than overwriting it.
+export class KeyRing {
+  private readonly file: string;
+  private readonly usage: KeyUsage[];
+  private readonly pending: Record<Counter, number>[];
+  private index = 0;
+  private switchedAt = 0;
+  private savedAt = 0;
+
+  constructor(readonly keys: ApiKeyEntry[], private readonly options: KeyRingOptions = {}) {
+    this.file = options.usageFile || keyUsageFilePath();
+    const saved = readUsageFile(this.file).keys;
+    this.usage = keys.map(entry => {
+      const id = keyId(entry.key);
+      return { ...emptyUsage(id), ...saved[id], id };
+    });
+    this.pending = keys.map(() => ({ requests: 0, quotaErrors: 0, authErrors: 0 }));
+    this.index = this.pick();
+    process.once('exit', () => this.save());
+  }
+
+  get current(): ApiKeyEntry {
+    return this.keys[this.index];
+  }
+
+  // The current key, counted as used by one request
+  use(): string {
+    this.count('requests');
+    this.usage[this.index].lastUsed = new Date().toISOString();
+    if (Date.now() - this.savedAt >= SAVE_INTERVAL_MS) this.save();
+    return this.current.key;
+  }
+
+  // Called with a failed request and the time it started. Quota and credential errors
+  // put the current key aside and switch to the next usable one; true when the request
+  // should be repeated with it. A request that started before the last switch used an
+  // earlier key, so it is repeated without blaming the current one.
+  failover(error: unknown, startedAt: number = Date.now()): boolean {
+    const failure = toFileSearchError(error);
+    if (failure.code !== 'QUOTA_EXCEEDED' && failure.code !== 'AUTH_FAILED') return false;
+    if (this.keys.length < 2) return false;
+    if (startedAt < this.switchedAt) return this.available(this.index);
+
+    const usage = this.usage[this.index];
+    const message = error instanceof Error ? error.message : String(error);
+    usage.lastError = redactSecrets(message).slice(0, MAX_ERROR_LENGTH);
+    if (failure.code === 'QUOTA_EXCEEDED') {
+      this.count('quotaErrors');
+      const cooldown = retryAfterMs(error)
+        ?? (DAILY_QUOTA.test(message) ? DAILY_QUOTA_COOLDOWN_MS : this.options.quotaCooldownMs ?? DEFAULT_QUOTA_COOLDOWN_MS);
+      usage.coolingUntil = new Date(Date.now() + cooldown).toISOString();
+    } else {
+      this.count('authErrors');
+      if (INVALID_KEY.test(message)) {
+        usage.invalid = true;
+      } else {
+        usage.coolingUntil = new Date(Date.now() + (this.options.authCooldownMs ?? DEFAULT_AUTH_COOLDOWN_MS)).toISOString();
+      }
+    }
+
+    const previous = this.current;
+    const next = this.pick();
+    this.save();
+    if (next === this.index) return false;
+    this.index = next;
+    this.switchedAt = Date.now();
+    const reason = failure.code === 'QUOTA_EXCEEDED' ? 'is out of quota' : usage.invalid ? 'is not valid' : 'was refused';
+    console.error(`🔑 API key from ${previous.source} ${reason}; switching to ${this.current.source}`);
+    return this.available(next);
+  }
+
+  status(): KeyStatus[] {
+    return this.keys.map((entry, i) => ({
+      ...this.usage[i],
+      requests: this.usage[i].requests + this.pending[i].requests,
+      quotaErrors: this.usage[i].quotaErrors + this.pending[i].quotaErrors,
+      authErrors: this.usage[i].authErrors + this.pending[i].authErrors,
+      source: entry.source,
+      state: this.usage[i].invalid ? 'invalid' : this.available(i) ? 'active' : 'cooling',
+      current: i === this.index,
+    }));
+  }
+
+  // Makes every key usable again; counts are kept
+  reset(): void {
+    for (const usage of this.usage) {
+      delete usage.coolingUntil;
+      delete usage.invalid;
+    }
+    this.index = 0;
+    this.save(true);
+  }
+
+  // Writes the counts added since the last save and the latest cooldowns
+  save(clearMarks = false): void {
+    this.savedAt = Date.now();
+    const data = readUsageFile(this.file);
+    this.usage.forEach((usage, i) => {
+      const saved = data.keys[usage.id] || emptyUsage(usage.id);
+      const merged: KeyUsage = {
+        ...saved,
+        ...usage,
+        requests: saved.requests + this.pending[i].requests,
+        quotaErrors: saved.quotaErrors + this.pending[i].quotaErrors,
+        authErrors: saved.authErrors + this.pending[i].authErrors,
+      };
+      // Another process may have marked the key since it was read; the later cooldown wins
+      if (clearMarks || !usage.coolingUntil || (saved.coolingUntil && saved.coolingUntil > usage.coolingUntil)) merged.coolingUntil = clearMarks ? undefined : saved.coolingUntil;
+      if (clearMarks || !saved.invalid) merged.invalid = usage.invalid;
+      if (!merged.coolingUntil) delete merged.coolingUntil;
+      if (!merged.invalid) delete merged.invalid;
+      data.keys[usage.id] = merged;
+      this.usage[i] = merged;
+      this.pending[i] = { requests: 0, quotaErrors: 0, authErrors: 0 };
+    });
+    try {
+      fs.mkdirSync(path.dirname(this.file), { recursive: true, mode: 0o700 });
+      fs.writeFileSync(this.file, JSON.stringify(data, null, 2), { mode: 0o600 });
+    } catch (error) {
+      console.error(`⚠️  Could not save API key usage to ${this.file}: ${(error as Error).message}`);
+    }
+  }
+
+  private count(counter: Counter): void {
+    this.pending[this.index][counter]++;
+  }
+
+  private available(i: number): boolean {
+    const usage = this.usage[i];
+    return !usage.invalid && (!usage.coolingUntil || Date.parse(usage.coolingUntil) <= Date.now());
+  }
+
+  // The current key while usable, else the next usable one after it; when every key is
+  // cooling down, the one that is usable again first
+  private pick(): number {
+    for (let offset = 0; offset < this.keys.length; offset++) {
+      const i = (this.index + offset) % this.keys.length;
+      if (this.available(i)) return i;
+    }
+    const cooling = this.usage
+      .map((usage, i) => ({ i, until: usage.invalid ? Infinity : Date.parse(usage.coolingUntil || '') || 0 }))
+      .sort((a, b) => a.until - b.until);
+    return cooling[0].until === Infinity ? this.index : cooling[0].i;
+  }
+}
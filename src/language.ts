@@ -0,0 +1,229 @@
+// language.ts - Natural-language and programming-language detection for document metadata
+import * as fs from 'fs';
+import * as path from 'path';
+import { DocumentMetadata } from './metadata.js';
+import { decodeText } from './encoding.js';
+
+// Types
+export interface DetectedLanguages {
+  lang?: string;  // ISO 639-1 code of the prose, e.g. "ja"
+  codeLang?: string;  // Programming language of source files, e.g. "rust"
+}
+
+const SAMPLE_BYTES = 64 * 1024;
+const MIN_LETTERS = 20;
+const MIN_STOPWORD_HITS = 3;
+
+// Scripts written by one language, or by one language in most corpora
+const SCRIPTS: [RegExp, string][] = [
+  [/\p{Script=Hangul}/u, 'ko'],
+  [/\p{Script=Cyrillic}/u, 'ru'],
+  [/\p{Script=Arabic}/u, 'ar'],
+  [/\p{Script=Hebrew}/u, 'he'],
+  [/\p{Script=Greek}/u, 'el'],
+  [/\p{Script=Thai}/u, 'th'],
+  [/\p{Script=Devanagari}/u, 'hi'],
+];
+const KANA = /[\p{Script=Hiragana}\p{Script=Katakana}]/u;
+const HAN = /\p{Script=Han}/u;
+const LATIN = /\p{Script=Latin}/u;
+
+// Letters that set a language apart from the main one of its script
+const VARIANTS: { [lang: string]: [RegExp, string] } = {
+  ru: [/[іїєґІЇЄҐ]/u, 'uk'],
+  ar: [/[پچژگ]/u, 'fa'],
+};
+
+// The most frequent function words of each Latin-script language, kept to ones the
+// other languages here rarely use
+const STOPWORDS: { [lang: string]: string[] } = {
+  en: ['the', 'and', 'of', 'to', 'is', 'that', 'for', 'it', 'with', 'was', 'on', 'are', 'be', 'this', 'by', 'not', 'or', 'from', 'have', 'which'],
+  de: ['der', 'die', 'und', 'das', 'ist', 'nicht', 'ein', 'eine', 'zu', 'den', 'mit', 'sich', 'des', 'auf', 'für', 'im', 'dem', 'von', 'auch', 'wird'],
+  fr: ['le', 'la', 'les', 'et', 'des', 'est', 'une', 'du', 'que', 'pour', 'dans', 'pas', 'qui', 'sur', 'au', 'avec', 'ce', 'sont', 'par', 'nous'],
+  es: ['el', 'los', 'las', 'del', 'que', 'una', 'por', 'con', 'para', 'es', 'se', 'lo', 'como', 'más', 'pero', 'sus', 'al', 'está', 'muy', 'también'],
+  it: ['il', 'di', 'che', 'per', 'una', 'non', 'sono', 'della', 'gli', 'con', 'le', 'è', 'dei', 'anche', 'più', 'nel', 'alla', 'questo', 'essere', 'come'],
+  pt: ['os', 'de', 'que', 'não', 'uma', 'com', 'para', 'do', 'da', 'em', 'por', 'são', 'dos', 'mais', 'como', 'seu', 'ao', 'pelo', 'também', 'você'],
+  nl: ['het', 'een', 'en', 'van', 'dat', 'niet', 'op', 'te', 'zijn', 'voor', 'met', 'die', 'ook', 'maar', 'aan', 'om', 'wordt', 'bij', 'naar', 'deze'],
+  sv: ['och', 'att', 'det', 'som', 'är', 'på', 'för', 'med', 'inte', 'av', 'den', 'till', 'har', 'jag', 'ett', 'var', 'om', 'kan', 'eller', 'från'],
+  pl: ['i', 'w', 'nie', 'na', 'się', 'z', 'że', 'do', 'jest', 'to', 'jak', 'co', 'ale', 'po', 'tak', 'od', 'przez', 'są', 'dla', 'czy'],
+};
+
+const ALL_STOPWORDS = new Set(Object.values(STOPWORDS).flat());
+
+const CODE_EXTENSIONS: { [extension: string]: string } = {
+  '.rs': 'rust',
+  '.py': 'python',
+  '.pyi': 'python',
+  '.ts': 'typescript',
+  '.tsx': 'typescript',
+  '.mts': 'typescript',
+  '.cts': 'typescript',
+  '.js': 'javascript',
+  '.jsx': 'javascript',
+  '.mjs': 'javascript',
+  '.cjs': 'javascript',
+  '.go': 'go',
+  '.java': 'java',
+  '.kt': 'kotlin',
+  '.kts': 'kotlin',
+  '.scala': 'scala',
+  '.c': 'c',
+  '.cc': 'cpp',
+  '.cpp': 'cpp',
+  '.cxx': 'cpp',
+  '.hpp': 'cpp',
+  '.hh': 'cpp',
+  '.cs': 'csharp',
+  '.rb': 'ruby',
+  '.php': 'php',
+  '.swift': 'swift',
+  '.mm': 'objective-c',
+  '.sh': 'shell',
+  '.bash': 'shell',
+  '.zsh': 'shell',
+  '.ps1': 'powershell',
+  '.sql': 'sql',
+  '.r': 'r',
+  '.lua': 'lua',
+  '.pm': 'perl',
+  '.hs': 'haskell',
+  '.ex': 'elixir',
+  '.exs': 'elixir',
+  '.erl': 'erlang',
+  '.clj': 'clojure',
+  '.dart': 'dart',
+  '.jl': 'julia',
+  '.vue': 'vue',
+  '.svelte': 'svelte',
+};
+
+const CODE_FILE_NAMES: { [name: string]: string } = {
+  'makefile': 'make',
+  'gnumakefile': 'make',
+  'dockerfile': 'dockerfile',
+  'rakefile': 'ruby',
+  'gemfile': 'ruby',
+};
+
+// Interpreter named on the "#!" line
+const INTERPRETERS: [RegExp, string][] = [
+  [/^python/, 'python'],
+  [/^(node|nodejs)$/, 'javascript'],
+  [/^(deno|ts-node|tsx|bun)$/, 'typescript'],
+  [/^(sh|bash|zsh|dash|ksh)$/, 'shell'],
+  [/^ruby/, 'ruby'],
+  [/^perl/, 'perl'],
+  [/^php/, 'php'],
+];
+
+// Extensions shared by several languages, told apart by their content
+const AMBIGUOUS: { [extension: string]: (text: string) => string } = {
+  '.h': text => /@(interface|implementation|property)\b|^#import\b/m.test(text)
+    ? 'objective-c'
+    : /\b(class|namespace|template)\b|\bstd::|^#include\s*<[a-z_]+>$/m.test(text) ? 'cpp' : 'c',
+  '.m': text => /@(interface|implementation)\b|^#import\b/m.test(text) ? 'objective-c' : 'matlab',
+  '.pl': text => /^:-\s|^\w+\([^)]*\)\s*:-/m.test(text) ? 'prolog' : 'perl',
+};
+
+// A comment line of the C, shell, SQL and Lisp families, or a docstring line
+const COMMENT_LINE = /^\s*(\/\/+|\/\*+|\*|#+|--|;+|"""|''')\s?(.*)$/;
+
+// The languages of a text file, from its first 64KB
+export function detectFileLanguages(filePath: string): DetectedLanguages {
+  let text: string;
+  try {
+    const fd = fs.openSync(filePath, 'r');
+    try {
+      const buffer = Buffer.alloc(SAMPLE_BYTES);
+      const length = fs.readSync(fd, buffer, 0, SAMPLE_BYTES, 0);
+      text = decodeText(buffer.subarray(0, length)).text;
+    } finally {
+      fs.closeSync(fd);
+    }
+  } catch (error) {
+    return {};
+  }
+  const codeLang = detectCodeLanguage(filePath, text);
+  // The prose of source files is in their comments; identifiers would read as English
+  const prose = codeLang
+    ? text.split(/\r?\n/).map(line => COMMENT_LINE.exec(line)?.[2] || '').join('\n')
+    : text;
+  return { lang: detectLanguage(prose), codeLang };
+}
+
+// "lang" and "code_lang" metadata for the detected languages
+export function languageMetadata(languages: DetectedLanguages): DocumentMetadata {
+  return {
+    ...(languages.lang ? { lang: languages.lang } : {}),
+    ...(languages.codeLang ? { code_lang: languages.codeLang } : {}),
+  };
+}
+
+// ISO 639-1 code of the language most of the text is written in, or undefined when
+// the text is too short or mixed to tell
+export function detectLanguage(text: string): string | undefined {
+  const counts = new Map<string, number>();
+  let kana = 0;
+  let han = 0;
+  let latin = 0;
+  let letters = 0;
+  for (const char of text) {
+    if (!/\p{L}/u.test(char)) continue;
+    letters++;
+    if (LATIN.test(char)) {
+      latin++;
+    } else if (KANA.test(char)) {
+      kana++;
+    } else if (HAN.test(char)) {
+      han++;
+    } else {
+      const script = SCRIPTS.find(([pattern]) => pattern.test(char));
+      if (script) counts.set(script[1], (counts.get(script[1]) || 0) + 1);
+    }
+  }
+  if (letters < MIN_LETTERS) return undefined;
+
+  // Japanese mixes kana into Han text; Chinese uses none
+  if (kana + han > 0) counts.set(kana > (kana + han) * 0.05 ? 'ja' : 'zh', kana + han);
+  const [script] = [...counts.entries()].sort((a, b) => b[1] - a[1]);
+  // Latin letters also appear in code, names and URLs of non-Latin text, and one CJK
+  // character carries about a word, so another script wins from 30% of the Latin count
+  if (script && script[1] >= latin * 0.3) {
+    const variant = VARIANTS[script[0]];
+    return variant && variant[0].test(text) ? variant[1] : script[0];
+  }
+  return latin >= MIN_LETTERS ? detectLatinLanguage(text) : undefined;
+}
+
+function detectLatinLanguage(text: string): string | undefined {
+  const words = text.toLowerCase().match(/\p{L}+/gu) || [];
+  const frequency = new Map<string, number>();
+  for (const word of words) frequency.set(word, (frequency.get(word) || 0) + 1);
+  const scores = Object.entries(STOPWORDS)
+    .map(([lang, stopwords]) => ({ lang, hits: stopwords.reduce((sum, word) => sum + (frequency.get(word) || 0), 0) }))
+    .sort((a, b) => b.hits - a.hits);
+  const [best, second] = scores;
+  if (best.hits < MIN_STOPWORD_HITS || best.hits === second.hits) return undefined;
+  return best.lang;
+}
+
+// Function word of any of the Latin-script languages above
+export function isStopword(word: string): boolean {
+  return ALL_STOPWORDS.has(word.toLowerCase());
+}
+
+// Programming language from the extension or file name, the "#!" line, and content
+// heuristics for extensions several languages share
+export function detectCodeLanguage(filePath: string, text: string): string | undefined {
+  const extension = path.extname(filePath).toLowerCase();
+  if (extension in CODE_EXTENSIONS) return CODE_EXTENSIONS[extension];
+  if (extension in AMBIGUOUS) return AMBIGUOUS[extension](text);
+  const name = path.basename(filePath).toLowerCase();
+  if (name in CODE_FILE_NAMES) return CODE_FILE_NAMES[name];
+
+  const shebang = /^#!\s*(\S+)(?:\s+(\S+))?/.exec(text);
+  if (!shebang) return undefined;
+  // "#!/usr/bin/env python3" names the interpreter after env
+  const program = path.basename(shebang[1]) === 'env' && shebang[2] ? shebang[2] : path.basename(shebang[1]);
+  return INTERPRETERS.find(([pattern]) => pattern.test(program))?.[1];
+}
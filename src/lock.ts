@@ -0,0 +1,183 @@
+// lock.ts - Advisory lock files, so concurrent runs (a watcher and a manual sync, the MCP server and a terminal) do not clobber local state
+import * as fs from 'fs';
+import * as os from 'os';
+import { StateLockedError } from './errors.js';
+
+// Types
+export interface LockOptions {
+  timeoutMs?: number;  // How long to wait for another run to let go; 0 fails at once
+  staleMs?: number;  // Age after which a lock of another host is taken over
+}
+
+interface LockOwner {
+  pid: number;
+  host: string;
+  acquiredAt: string;
+}
+
+// The lock of a state file is the file's path plus this suffix
+export const LOCK_SUFFIX = '.lock';
+
+// State files are locked for a read and a write at a time, so a short wait is enough
+export const DEFAULT_LOCK_TIMEOUT_MS = 10000;
+
+// The owner of a lock on another host cannot be checked, so its lock counts as left
+// behind once it is this old
+const DEFAULT_STALE_MS = 60 * 60 * 1000;
+
+const POLL_MS = 50;
+
+function sleepSync(ms: number): void {
+  Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, ms);
+}
+
+function readOwner(lockPath: string): LockOwner | undefined {
+  try {
+    return JSON.parse(fs.readFileSync(lockPath, 'utf8')) as LockOwner;
+  } catch (error) {
+    return undefined;  // Gone, or still being written
+  }
+}
+
+function isAlive(pid: number): boolean {
+  try {
+    process.kill(pid, 0);
+    return true;
+  } catch (error) {
+    return (error as NodeJS.ErrnoException).code === 'EPERM';
+  }
+}
+
+// Left behind by a run that crashed or was killed: its process is gone, or it is on
+// another host and older than staleMs
+function isStale(lockPath: string, staleMs: number): boolean {
+  const owner = readOwner(lockPath);
+  if (!owner) {
+    // A lock without an owner is only stale once its writer had time to finish
+    try {
+      return Date.now() - fs.statSync(lockPath).mtimeMs > 1000;
+    } catch (error) {
+      return false;
+    }
+  }
+  if (owner.host === os.hostname()) return owner.pid !== process.pid && !isAlive(owner.pid);
+  return Date.now() - Date.parse(owner.acquiredAt) > staleMs;
+}
+
+// Held through a file created exclusively next to the state file, which records
+// the process holding it. Other runs wait for it; one of a crashed run is taken over.
+// acquire blocks the thread while it waits, which suits a command-line run; the
+// long-running servers use acquireAsync, so requests keep being served meanwhile.
+export class FileLock {
+  private released = false;
+
+  private constructor(readonly lockPath: string) {}
+
+  static acquire(filePath: string, options: LockOptions = {}): FileLock {
+    const lockPath = `${filePath}${LOCK_SUFFIX}`;
+    const deadline = Date.now() + (options.timeoutMs ?? DEFAULT_LOCK_TIMEOUT_MS);
+    for (;;) {
+      const lock = FileLock.tryAcquire(lockPath, options);
+      if (lock) return lock;
+      if (Date.now() >= deadline) throw new StateLockedError(filePath, lockPath, readOwner(lockPath));
+      sleepSync(POLL_MS);
+    }
+  }
+
+  static async acquireAsync(filePath: string, options: LockOptions = {}): Promise<FileLock> {
+    const lockPath = `${filePath}${LOCK_SUFFIX}`;
+    const deadline = Date.now() + (options.timeoutMs ?? DEFAULT_LOCK_TIMEOUT_MS);
+    for (;;) {
+      const lock = FileLock.tryAcquire(lockPath, options);
+      if (lock) return lock;
+      if (Date.now() >= deadline) throw new StateLockedError(filePath, lockPath, readOwner(lockPath));
+      await new Promise(resolve => setTimeout(resolve, POLL_MS));
+    }
+  }
+
+  // The lock when it is free or was left behind, else undefined
+  private static tryAcquire(lockPath: string, options: LockOptions): FileLock | undefined {
+    for (;;) {
+      try {
+        const fd = fs.openSync(lockPath, 'wx');
+        try {
+          const owner: LockOwner = { pid: process.pid, host: os.hostname(), acquiredAt: new Date().toISOString() };
+          fs.writeSync(fd, JSON.stringify(owner));
+        } finally {
+          fs.closeSync(fd);
+        }
+        return new FileLock(lockPath);
+      } catch (error) {
+        if ((error as NodeJS.ErrnoException).code !== 'EEXIST') throw error;
+      }
+      const owner = readOwner(lockPath);
+      if (!isStale(lockPath, options.staleMs ?? DEFAULT_STALE_MS)) return undefined;
+      // Another run may have taken it over first; only the lock found stale is removed
+      if (readOwner(lockPath)?.acquiredAt === owner?.acquiredAt) {
+        console.error(`🔓 Taking over a lock left behind: ${lockPath}`);
+        fs.rmSync(lockPath, { force: true });
+      }
+    }
+  }
+
+  release(): void {
+    if (this.released) return;
+    this.released = true;
+    fs.rmSync(this.lockPath, { force: true });
+  }
+}
+
+// Runs fn while holding the lock of filePath; for a read-modify-write of a state file
+export function withFileLock<T>(filePath: string, fn: () => T, options: LockOptions = {}): T {
+  const lock = FileLock.acquire(filePath, options);
+  try {
+    return fn();
+  } finally {
+    lock.release();
+  }
+}
+
+// withFileLock for the long-running servers: waiting for the lock does not block other work.
+// fn runs synchronously once the lock is held, so nothing else of this process interleaves
+export async function withFileLockAsync<T>(filePath: string, fn: () => T, options: LockOptions = {}): Promise<T> {
+  const lock = await FileLock.acquireAsync(filePath, options);
+  try {
+    return fn();
+  } finally {
+    lock.release();
+  }
+}
+
+// Changes made in memory since the last save, replayed onto the file's current content
+// when saving, so entries written by other runs in the meantime are kept
+export class PendingChanges<T> {
+  private readonly changes = new Map<string, T | undefined>();
+
+  set(key: string, value: T): void {
+    this.changes.set(key, value);
+  }
+
+  delete(key: string): void {
+    this.changes.set(key, undefined);
+  }
+
+  get size(): number {
+    return this.changes.size;
+  }
+
+  // undefined values stand for deletions
+  entries(): [string, T | undefined][] {
+    return [...this.changes.entries()];
+  }
+
+  applyTo(target: { [key: string]: T }): void {
+    for (const [key, value] of this.changes) {
+      if (value === undefined) delete target[key];
+      else target[key] = value;
+    }
+  }
+
+  clear(): void {
+    this.changes.clear();
+  }
+}
@@ -0,0 +1,140 @@
+// log.ts - Log levels, JSON-lines log files and secret redaction for the stderr logs
+import * as fs from 'fs';
+import * as path from 'path';
+import { format } from 'util';
+import { ConfigError } from './errors.js';
+
+// Types
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug';
+
+export interface LoggingOptions {
+  level?: LogLevel;  // Lowest level written to stderr (default: info)
+  file?: string;  // Every level, including debug, is appended here as JSON lines
+  secrets?: (string | undefined)[];  // Redacted wherever they occur, on top of GEMINI_API_KEY and GOOGLE_API_KEY
+}
+
+export type LogFields = { [field: string]: unknown };
+
+export interface LogRecord {
+  time: string;
+  level: LogLevel;
+  message: string;
+  [field: string]: unknown;
+}
+
+export const LOG_LEVELS: LogLevel[] = ['error', 'warn', 'info', 'debug'];
+
+export const REDACTED = 'REDACTED';
+
+// Secrets shorter than this would redact ordinary words
+const MIN_SECRET_LENGTH = 8;
+
+const writeStderr = console.error.bind(console);
+let threshold: LogLevel = 'info';
+let logFile: number | undefined;
+let secrets: string[] = [];
+let installed = false;
+
+export function defaultSecrets(): string[] {
+  return [process.env.GEMINI_API_KEY, process.env.GOOGLE_API_KEY, ...(process.env.GEMINI_API_KEYS || '').split(',').map(key => key.trim())].filter((value): value is string => !!value && value.length >= MIN_SECRET_LENGTH);
+}
+
+// Known secrets, "key=" query parameters, bearer tokens and anything shaped like a Google API key
+export function redactSecrets(text: string, known: string[] = [...defaultSecrets(), ...secrets]): string {
+  const redacted = known.reduce((result, secret) => result.split(secret).join(REDACTED), text);
+  return redacted
+    .replace(/([?&](?:key|access_token)=)[^&#\s"']+/g, `$1${REDACTED}`)
+    .replace(/(Bearer )[\w.~+/=-]+/g, `$1${REDACTED}`)
+    .replace(/\bAIza[\w-]{35}\b/g, REDACTED);
+}
+
+// The stderr logs mark their kind with a leading emoji or word
+export function levelOf(message: string): LogLevel {
+  const text = message.trimStart();
+  if (/^(Error|Fatal|Usage|❌|🛑)/.test(text)) return 'error';
+  if (text.startsWith('⚠️')) return 'warn';
+  return 'info';
+}
+
+export function isLogLevel(value: string): value is LogLevel {
+  return LOG_LEVELS.includes(value as LogLevel);
+}
+
+// --quiet keeps errors only, --verbose adds debug details; otherwise GEMINI_LOG_LEVEL or log_level
+export function resolveLogLevel(verbose: boolean, quiet: boolean, configured?: string): LogLevel {
+  if (verbose && quiet) throw new ConfigError('--verbose and --quiet cannot be used together');
+  if (verbose) return 'debug';
+  if (quiet) return 'error';
+  if (configured === undefined) return 'info';
+  if (!isLogLevel(configured)) throw new ConfigError(`log level must be one of: ${LOG_LEVELS.join(', ')}`);
+  return configured;
+}
+
+export function log(level: LogLevel, message: string, fields: LogFields = {}): void {
+  const text = redactSecrets(message);
+  if (LOG_LEVELS.indexOf(level) <= LOG_LEVELS.indexOf(threshold)) writeStderr(text);
+  if (logFile !== undefined) {
+    const record: LogRecord = { time: new Date().toISOString(), level, message: text.replace(/^\n+/, ''), ...fields };
+    try {
+      fs.writeSync(logFile, `${redactSecrets(JSON.stringify(record))}\n`);
+    } catch (error) {
+      // A full disk or closed file must not take the command down with it
+    }
+  }
+}
+
+export const logger = {
+  error: (message: string, fields?: LogFields) => log('error', message, fields),
+  warn: (message: string, fields?: LogFields) => log('warn', message, fields),
+  info: (message: string, fields?: LogFields) => log('info', message, fields),
+  debug: (message: string, fields?: LogFields) => log('debug', message, fields),
+};
+
+function requestUrl(input: string | URL | Request): string {
+  return typeof input === 'string' ? input : input instanceof URL ? input.toString() : input.url;
+}
+
+// Debug records of every HTTP request, so a log file shows what was sent where
+function traceFetch(): void {
+  const fetchImpl = globalThis.fetch;
+  globalThis.fetch = async (input: string | URL | Request, init?: RequestInit): Promise<Response> => {
+    const method = (init?.method || (input instanceof Request ? input.method : 'GET')).toUpperCase();
+    const url = requestUrl(input);
+    const start = Date.now();
+    try {
+      const response = await fetchImpl(input, init);
+      const ms = Date.now() - start;
+      logger.debug(`🌐 ${method} ${url} → ${response.status} (${ms}ms)`, { method, url, status: response.status, ms });
+      return response;
+    } catch (error) {
+      logger.debug(`🌐 ${method} ${url} failed: ${(error as Error).message}`, { method, url, ms: Date.now() - start });
+      throw error;
+    }
+  };
+}
+
+// Routes console.error through the logger: its messages are levelled by their
+// prefix, filtered by the stderr level, redacted and copied to the log file.
+// stdout (answers, reports, the MCP protocol) is left alone.
+export function configureLogging(options: LoggingOptions = {}): void {
+  threshold = options.level || 'info';
+  secrets = (options.secrets || []).filter((value): value is string => !!value && value.length >= MIN_SECRET_LENGTH);
+  if (options.file && logFile === undefined) {
+    const filePath = path.resolve(options.file);
+    try {
+      fs.mkdirSync(path.dirname(filePath), { recursive: true });
+      logFile = fs.openSync(filePath, 'a');
+    } catch (error) {
+      throw new ConfigError(`Could not open log file ${filePath}: ${(error as Error).message}`, { cause: error });
+    }
+  }
+  if (installed) return;
+  installed = true;
+  console.error = (...args: unknown[]) => {
+    const message = format(...args);
+    log(levelOf(message), message);
+  };
+  console.warn = (...args: unknown[]) => log('warn', format(...args));
+  console.debug = (...args: unknown[]) => log('debug', format(...args));
+  if (threshold === 'debug' || logFile !== undefined) traceFetch();
+}
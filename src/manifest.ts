@@ -0,0 +1,138 @@
+// manifest.ts - Canonical JSON for manifests, and checks of a store against a stage or sync manifest
+import { FileSearchClient } from './client.js';
+import { DocumentInfo, toDocumentInfo } from './documents.js';
+import { MetadataValue } from './metadata.js';
+import { StageManifest } from './staging.js';
+import { SyncManifest } from './sync.js';
+
+// Types
+export interface ManifestMismatch {
+  document: string;  // Display name (stage manifest) or document name (sync manifest)
+  field: string;  // "state", "mimeType", "size" or "metadata.<key>"
+  expected: string;
+  actual: string;
+}
+
+export interface ManifestVerification {
+  storeName: string;
+  kind: 'stage' | 'sync';
+  expected: number;  // Documents the manifest lists
+  matched: number;
+  missing: string[];  // Listed but not in the store
+  extra: string[];  // In the store but not listed
+  duplicates: string[];  // Display names found more than once in the store
+  mismatched: ManifestMismatch[];
+}
+
+// Object keys sorted by code point, two-space indent and a final newline: the same
+// data serializes to the same bytes whatever order it was built in
+export function canonicalJson(value: unknown): string {
+  return `${JSON.stringify(value, (_key, item: unknown) => {
+    if (!item || typeof item !== 'object' || Array.isArray(item)) return item;
+    const sorted: { [key: string]: unknown } = {};
+    for (const key of Object.keys(item).sort()) sorted[key] = (item as { [key: string]: unknown })[key];
+    return sorted;
+  }, 2)}\n`;
+}
+
+// List values compare as sets; numbers and strings by their text
+function metadataText(value: MetadataValue | undefined): string {
+  if (value === undefined) return '(none)';
+  return Array.isArray(value) ? [...value].map(String).sort().join(', ') : String(value);
+}
+
+function stateMismatch(document: string, info: DocumentInfo): ManifestMismatch[] {
+  return info.state === 'ACTIVE' ? [] : [{ document, field: 'state', expected: 'ACTIVE', actual: info.state }];
+}
+
+// Documents are matched by display name, since document names differ per push. Metadata
+// added at push time (--metadata, project metadata) is allowed on top of the staged one.
+export async function verifyStageManifest(client: FileSearchClient, storeName: string, manifest: StageManifest): Promise<ManifestVerification> {
+  const remote = new Map<string, DocumentInfo[]>();
+  for (const document of await client.listDocuments(storeName)) {
+    const info = toDocumentInfo(document);
+    remote.set(info.displayName, [...(remote.get(info.displayName) || []), info]);
+  }
+  const expected = manifest.files.filter(file => !file.skipped).flatMap(file => file.documents);
+  const listed = new Set(expected.map(document => document.displayName));
+  const result: ManifestVerification = {
+    storeName,
+    kind: 'stage',
+    expected: expected.length,
+    matched: 0,
+    missing: [],
+    extra: [...remote.keys()].filter(displayName => !listed.has(displayName)).sort(),
+    duplicates: [...remote.entries()].filter(([displayName, infos]) => listed.has(displayName) && infos.length > 1).map(([displayName]) => displayName).sort(),
+    mismatched: [],
+  };
+  for (const document of expected) {
+    const info = remote.get(document.displayName)?.[0];
+    if (!info) {
+      result.missing.push(document.displayName);
+      continue;
+    }
+    const mismatches = stateMismatch(document.displayName, info);
+    if (document.mimeType && info.mimeType && document.mimeType !== info.mimeType) {
+      mismatches.push({ document: document.displayName, field: 'mimeType', expected: document.mimeType, actual: info.mimeType });
+    }
+    if (info.sizeBytes > 0 && info.sizeBytes !== document.size) {
+      mismatches.push({ document: document.displayName, field: 'size', expected: String(document.size), actual: String(info.sizeBytes) });
+    }
+    const metadata = document.metadata || {};
+    for (const key of Object.keys(metadata).sort()) {
+      const value = metadata[key];
+      if (metadataText(value) !== metadataText(info.metadata[key])) {
+        mismatches.push({ document: document.displayName, field: `metadata.${key}`, expected: metadataText(value), actual: metadataText(info.metadata[key]) });
+      }
+    }
+    if (mismatches.length === 0) result.matched++;
+    result.mismatched.push(...mismatches);
+  }
+  return result;
+}
+
+// A sync manifest records the document names it uploaded, so they are compared directly
+export async function verifySyncManifest(client: FileSearchClient, storeName: string, manifest: SyncManifest): Promise<ManifestVerification> {
+  const remote = new Map((await client.listDocuments(storeName)).map(document => {
+    const info = toDocumentInfo(document);
+    return [info.name, info] as const;
+  }));
+  const expected = [...manifest.documentNames()].sort();
+  const listed = new Set(expected);
+  const result: ManifestVerification = {
+    storeName,
+    kind: 'sync',
+    expected: expected.length,
+    matched: 0,
+    missing: [],
+    extra: [...remote.keys()].filter(name => !listed.has(name)).sort(),
+    duplicates: [],
+    mismatched: [],
+  };
+  for (const name of expected) {
+    const info = remote.get(name);
+    if (!info) {
+      result.missing.push(name);
+      continue;
+    }
+    const mismatches = stateMismatch(name, info);
+    if (mismatches.length === 0) result.matched++;
+    result.mismatched.push(...mismatches);
+  }
+  return result;
+}
+
+export function manifestMatches(result: ManifestVerification): boolean {
+  return result.missing.length === 0 && result.extra.length === 0 && result.duplicates.length === 0 && result.mismatched.length === 0;
+}
+
+export function formatManifestVerification(result: ManifestVerification): string {
+  const lines = [`${manifestMatches(result) ? '✅' : '❌'} ${result.storeName}: ${result.matched}/${result.expected} documents of the ${result.kind} manifest match`];
+  for (const name of result.missing) lines.push(`   missing    ${name}`);
+  for (const name of result.extra) lines.push(`   extra      ${name}`);
+  for (const name of result.duplicates) lines.push(`   duplicate  ${name}`);
+  for (const mismatch of result.mismatched) {
+    lines.push(`   changed    ${mismatch.document}: ${mismatch.field} is ${mismatch.actual}, expected ${mismatch.expected}`);
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,66 @@
+// metadata.ts - Custom key/value metadata attached to uploaded documents
+import { CustomMetadata } from '@google/genai';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export type MetadataValue = string | number | string[];
+
+export interface DocumentMetadata {
+  [key: string]: MetadataValue;
+}
+
+const KEY_PATTERN = /^[A-Za-z_][A-Za-z0-9_.-]*$/;
+
+export function validateMetadata(metadata: DocumentMetadata): void {
+  for (const [key, value] of Object.entries(metadata)) {
+    if (!KEY_PATTERN.test(key)) {
+      throw new InvalidInputError(`Invalid metadata key "${key}"`);
+    }
+    const valid = typeof value === 'string'
+      || (typeof value === 'number' && Number.isFinite(value))
+      || (Array.isArray(value) && value.every(item => typeof item === 'string'));
+    if (!valid) {
+      throw new InvalidInputError(`Metadata "${key}" must be a string, number or list of strings`);
+    }
+  }
+}
+
+export function toCustomMetadata(metadata: DocumentMetadata): CustomMetadata[] {
+  return Object.entries(metadata).map(([key, value]) => {
+    if (typeof value === 'number') return { key, numericValue: value };
+    if (Array.isArray(value)) return { key, stringListValue: { values: value } };
+    return { key, stringValue: value };
+  });
+}
+
+export function fromCustomMetadata(customMetadata: CustomMetadata[] = []): DocumentMetadata {
+  const metadata: DocumentMetadata = {};
+  for (const item of customMetadata) {
+    if (!item.key) continue;
+    if (item.numericValue !== undefined) {
+      metadata[item.key] = item.numericValue;
+    } else if (item.stringListValue) {
+      metadata[item.key] = item.stringListValue.values || [];
+    } else {
+      metadata[item.key] = item.stringValue || '';
+    }
+  }
+  return metadata;
+}
+
+// Parses "team=backend" style pairs; values that look numeric become numbers
+export function parseMetadataPairs(pairs: string[]): DocumentMetadata {
+  const metadata: DocumentMetadata = {};
+  for (const pair of pairs) {
+    const index = pair.indexOf('=');
+    if (index <= 0) {
+      throw new InvalidInputError(`Invalid metadata "${pair}": expected key=value`);
+    }
+    const key = pair.slice(0, index).trim();
+    const raw = pair.slice(index + 1).trim();
+    metadata[key] = /^-?\d+(\.\d+)?$/.test(raw) ? Number(raw) : raw;
+  }
+  validateMetadata(metadata);
+  return metadata;
+}
+
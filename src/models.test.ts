@@ -0,0 +1,199 @@
+// models.test.ts - Recorded API responses parsed into the models, with the fields the models don't declare and the ones a response leaves out
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { fileURLToPath } from 'url';
+import { Document, DocumentState } from '@google/genai';
+import { AuthProvider } from './auth.js';
+import { extractCitations } from './citations.js';
+import { toDocumentInfo } from './documents.js';
+import { HttpStatusError } from './errors.js';
+import { GenerateContentResponse, GroundingMetadata, RetrieveContextsRequest, RetrieveContextsResponse } from './models.js';
+import { chunksFromContexts, chunksFromGrounding } from './retrieve.js';
+import { VertexRagTransport } from './vertex.js';
+
+// Types
+interface FetchCall {
+  url: string;
+  init?: RequestInit;
+}
+
+// dist-test/ is one level down from the package directory
+const FIXTURES_DIR = path.join(path.dirname(fileURLToPath(import.meta.url)), '..', 'test-fixtures', 'models');
+
+const STORE = 'fileSearchStores/handbook-4f2k9x1q';
+const CORPUS = 'projects/demo-project/locations/us-central1/ragCorpora/2305843009213693952';
+const VERTEX_HOST = 'https://us-central1-aiplatform.googleapis.com';
+
+const auth: AuthProvider = {
+  method: 'adc',
+  clientOptions: () => ({}),
+  requestHeaders: async () => ({ Authorization: 'Bearer test-token' }),
+  describe: () => 'test credentials',
+};
+
+function fixture<T>(name: string): T {
+  return JSON.parse(fs.readFileSync(path.join(FIXTURES_DIR, name), 'utf8')) as T;
+}
+
+function json(name: string, status: number = 200): Response {
+  return new Response(fs.readFileSync(path.join(FIXTURES_DIR, name), 'utf8'), { status, headers: { 'Content-Type': 'application/json' } });
+}
+
+// Replaces fetch for the rest of the test; each call is answered by reply and recorded
+function stubFetch(t: TestContext, reply: (url: string) => Response): FetchCall[] {
+  const calls: FetchCall[] = [];
+  t.mock.method(globalThis, 'fetch', async (input: string | URL | Request, init?: RequestInit) => {
+    const url = String(input);
+    calls.push({ url, init });
+    return reply(url);
+  });
+  return calls;
+}
+
+function groundingMetadata(): { answer: string; metadata?: GroundingMetadata } {
+  const response = fixture<GenerateContentResponse>('generate-content-file-search.json');
+  return { answer: response.candidates?.[0]?.content?.parts?.[0]?.text || '', metadata: response.candidates?.[0]?.groundingMetadata };
+}
+
+test('citations of a file search answer: chunks without text are skipped, byte offsets become string offsets', () => {
+  const { answer, metadata } = groundingMetadata();
+  assert.deepEqual(extractCitations(metadata, answer), [
+    {
+      index: 1,
+      fileName: 'docs/limits.md',
+      chunkText: 'Files up to 100 MB can be uploaded to a File Search store.',
+      startIndex: 0,
+      endIndex: 46,
+      confidence: 0.91,
+    },
+    {
+      index: 2,
+      fileName: 'docs/uploads.md',
+      chunkText: 'Larger files are split into parts before upload.',
+      startIndex: 47,
+      endIndex: 92,
+      confidence: 0.77,
+    },
+    {
+      index: 3,
+      fileName: 'docs/limits.md',
+      chunkText: 'Files up to 100 MB can be uploaded to a File Search store.',
+      startIndex: 0,
+      endIndex: 46,
+      confidence: 0.42,
+    },
+  ]);
+  assert.equal(answer.slice(47, 92), 'Größere Dateien werden in Teilen hochgeladen.');
+});
+
+test('retrieved chunks of the grounding: repeated chunks are dropped, the store is the one reported', () => {
+  const { metadata } = groundingMetadata();
+  assert.deepEqual(chunksFromGrounding(metadata, [STORE, 'fileSearchStores/other-2b7d0c1e']), [
+    { rank: 1, text: 'Files up to 100 MB can be uploaded to a File Search store.', fileName: 'docs/limits.md', uri: undefined, store: STORE, score: 0.91 },
+    { rank: 2, text: 'Larger files are split into parts before upload.', fileName: 'docs/uploads.md', uri: undefined, store: STORE, score: 0.77 },
+  ]);
+  assert.deepEqual(chunksFromGrounding(undefined, [STORE]), []);
+});
+
+test('document info of a Gemini API document, with and without the optional fields', () => {
+  assert.deepEqual(toDocumentInfo(fixture<Document>('document.json')), {
+    name: `${STORE}/documents/limitsmd-8sd6q0b2xw3u`,
+    displayName: 'docs/limits.md',
+    state: 'ACTIVE',
+    mimeType: 'text/markdown',
+    sizeBytes: 8192,
+    createTime: '2025-11-03T09:12:40.102977Z',
+    updateTime: '2025-11-03T09:12:44.518203Z',
+    metadata: { team: 'platform', revision: 7, acl: ['staff', 'contractors'], reviewed: '' },
+    estimatedChunks: undefined,
+  });
+  const pending = toDocumentInfo(fixture<Document>('document-pending.json'));
+  assert.equal(pending.displayName, '');
+  assert.equal(pending.state, 'UNSPECIFIED');
+  assert.equal(pending.sizeBytes, 0);
+  assert.deepEqual(pending.metadata, {});
+});
+
+test('RAG files of a Vertex AI corpus are listed across pages and mapped to documents', async t => {
+  const calls = stubFetch(t, url => json(new URL(url).searchParams.get('pageToken') ? 'rag-files-page-2.json' : 'rag-files-page-1.json'));
+  const documents = await new VertexRagTransport({ kind: 'vertex', project: 'demo-project' }, auth).listFiles(CORPUS);
+
+  assert.deepEqual(calls.map(call => call.url), [
+    `${VERTEX_HOST}/v1/${CORPUS}/ragFiles`,
+    `${VERTEX_HOST}/v1/${CORPUS}/ragFiles?pageToken=Cg4KDBoKCAEQgICAgICAgIA%3D`,
+  ]);
+  assert.deepEqual(documents.map(document => [document.displayName, document.state, document.sizeBytes]), [
+    ['limits.md', DocumentState.STATE_ACTIVE, '8192'],
+    ['scan.pdf', DocumentState.STATE_FAILED, '1048576'],
+    ['uploads.md', DocumentState.STATE_PENDING, undefined],
+  ]);
+  assert.deepEqual(documents.map(document => toDocumentInfo(document)).map(info => [info.state, info.sizeBytes, info.updateTime]), [
+    ['ACTIVE', 8192, '2025-11-03T09:12:44.518203Z'],
+    ['FAILED', 1048576, '2025-11-03T09:13:09.004115Z'],
+    ['PENDING', 0, undefined],
+  ]);
+});
+
+test('retrieveContexts sends the declared request body and returns the contexts as recorded', async t => {
+  const calls = stubFetch(t, () => json('retrieve-contexts.json'));
+  const transport = new VertexRagTransport({ kind: 'vertex', project: 'demo-project' }, auth);
+  const contexts = await transport.retrieveContexts([CORPUS], 'How large can a file be?', { topK: 5, metadataFilter: 'team = "platform"' });
+
+  assert.equal(calls[0].url, `${VERTEX_HOST}/v1/projects/demo-project/locations/us-central1:retrieveContexts`);
+  const expected: RetrieveContextsRequest = {
+    vertexRagStore: { ragResources: [{ ragCorpus: CORPUS }] },
+    query: { text: 'How large can a file be?', ragRetrievalConfig: { topK: 5, filter: { metadataFilter: 'team = "platform"' } } },
+  };
+  assert.deepEqual(JSON.parse(String(calls[0].init?.body)), expected);
+  assert.deepEqual(contexts, fixture<RetrieveContextsResponse>('retrieve-contexts.json').contexts?.contexts);
+
+  assert.deepEqual(chunksFromContexts(contexts, [CORPUS]), [
+    {
+      rank: 1,
+      text: 'Files up to 100 MB can be uploaded to a File Search store.',
+      fileName: 'limits.md',
+      uri: 'gs://demo-handbook/docs/limits.md',
+      store: CORPUS,
+      score: 0.2314,
+    },
+    {
+      rank: 2,
+      text: 'Larger files are split into parts before upload.',
+      fileName: 'gs://demo-handbook/docs/uploads.md',
+      uri: 'gs://demo-handbook/docs/uploads.md',
+      store: CORPUS,
+      score: undefined,
+    },
+  ]);
+});
+
+test('retrieveContexts returns no contexts when the response has none', async t => {
+  stubFetch(t, () => json('retrieve-contexts-empty.json'));
+  const transport = new VertexRagTransport({ kind: 'vertex', project: 'demo-project' }, auth);
+  assert.deepEqual(await transport.retrieveContexts([CORPUS], 'nothing'), []);
+});
+
+test('uploadFile returns the RAG file of the response, and reports the error body of a failed one', async t => {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'models-test-'));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  const filePath = path.join(dir, 'notes.txt');
+  fs.writeFileSync(filePath, 'Uploads larger than 100 MB are split.\n');
+  const transport = new VertexRagTransport({ kind: 'vertex', project: 'demo-project' }, auth);
+
+  const calls = stubFetch(t, () => json('upload-rag-file.json'));
+  assert.equal(await transport.uploadFile(CORPUS, filePath), `${CORPUS}/ragFiles/5764607523034234883`);
+  assert.equal(calls[0].url, `${VERTEX_HOST}/upload/v1/${CORPUS}/ragFiles:upload`);
+  const form = calls[0].init?.body as FormData;
+  assert.deepEqual(JSON.parse(String(form.get('metadata'))), { rag_file: { display_name: 'notes.txt' } });
+
+  stubFetch(t, () => json('upload-rag-file-error.json', 400));
+  await assert.rejects(transport.uploadFile(CORPUS, filePath), (error: unknown) => {
+    assert.ok(error instanceof HttpStatusError);
+    assert.equal(error.status, 400);
+    assert.match(error.message, /Unsupported file type: application\/x-sqlite3\./);
+    return true;
+  });
+});
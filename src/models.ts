@@ -0,0 +1,111 @@
+// models.ts - Typed request and response bodies of the REST calls made without the SDK
+//
+// Gemini API requests go through @google/genai, whose types are re-exported here so
+// there is one place to look them up. The resumable upload protocol and Vertex AI RAG
+// Engine are called with fetch; their bodies are declared below so that a misspelled
+// field is a compile error instead of a silently ignored one. Responses are cast, not
+// validated: fields the API adds later are carried along and ignored.
+import { ChunkingConfig, CustomMetadata } from '@google/genai';
+
+export type {
+  GenerateContentParameters as GenerateContentRequest,
+  GenerateContentConfig,
+  GenerateContentResponse,
+  Tool,
+  FileSearch,
+  Retrieval,
+  VertexRagStore,
+  GroundingMetadata,
+  GroundingChunk,
+  GroundingSupport,
+  CustomMetadata,
+} from '@google/genai';
+
+// Types
+// Error body of Google APIs
+export interface ApiErrorBody {
+  error?: { code?: number; message?: string; status?: string };
+}
+
+// Gemini API: POST upload/v1beta/<store>:uploadToFileSearchStore with X-Goog-Upload-Command: start
+export interface ResumableUploadStartRequest {
+  displayName?: string;
+  customMetadata?: CustomMetadata[];
+  chunkingConfig?: ChunkingConfig;
+}
+
+// Vertex AI RAG Engine
+export type RagFileState = 'STATE_UNSPECIFIED' | 'ACTIVE' | 'ERROR';
+
+export interface RagCorpus {
+  name?: string;
+  displayName?: string;
+  createTime?: string;
+  updateTime?: string;
+}
+
+export interface RagFile {
+  name?: string;
+  displayName?: string;
+  createTime?: string;
+  updateTime?: string;
+  sizeBytes?: string;
+  fileStatus?: { state?: RagFileState; errorStatus?: string };
+}
+
+export interface CreateRagCorpusRequest {
+  displayName: string;
+}
+
+export interface ListRagCorporaResponse {
+  ragCorpora?: RagCorpus[];
+  nextPageToken?: string;
+}
+
+export interface ListRagFilesResponse {
+  ragFiles?: RagFile[];
+  nextPageToken?: string;
+}
+
+export interface VertexOperation {
+  name: string;
+  done?: boolean;
+  error?: unknown;
+  response?: unknown;
+}
+
+// The "metadata" part of a multipart ragFiles:upload; unlike the rest of the API it is snake_case
+export interface UploadRagFileMetadata {
+  rag_file: { display_name: string };
+  upload_rag_file_config?: {
+    rag_file_transformation_config: {
+      rag_file_chunking_config: {
+        fixed_length_chunking: { chunk_size: number; chunk_overlap: number };
+      };
+    };
+  };
+}
+
+export interface UploadRagFileResponse extends ApiErrorBody {
+  ragFile?: RagFile;
+}
+
+// POST <location>:retrieveContexts, the vector search without generation
+export interface RetrieveContextsRequest {
+  vertexRagStore: { ragResources: { ragCorpus: string }[] };
+  query: {
+    text: string;
+    ragRetrievalConfig?: { topK?: number; filter?: { metadataFilter?: string } };
+  };
+}
+
+export interface RagContext {
+  sourceUri?: string;
+  sourceDisplayName?: string;
+  text?: string;
+  score?: number;  // Distance or similarity, depending on the vector database of the corpus
+}
+
+export interface RetrieveContextsResponse {
+  contexts?: { contexts?: RagContext[] };
+}
@@ -0,0 +1,304 @@
+// network.ts - HTTP proxy and TLS trust settings for every request the process makes
+import * as fs from 'fs';
+import * as http from 'http';
+import * as https from 'https';
+import * as net from 'net';
+import * as tls from 'tls';
+import * as zlib from 'zlib';
+import { Readable } from 'stream';
+import { ConfigError } from './errors.js';
+
+// Types
+export interface NetworkOptions {
+  proxy?: string;  // http(s)://[user:password@]host:port (default: HTTPS_PROXY / HTTP_PROXY)
+  noProxy?: string;  // Comma-separated hosts and domains reached directly (default: NO_PROXY)
+  caBundle?: string;  // PEM file trusted on top of the bundled roots, e.g. the CA of a TLS-intercepting proxy
+  nativeCerts?: boolean;  // Also trust the certificates of the operating system store
+  connectTimeout?: number;  // Milliseconds to open a connection, see timeouts.ts
+}
+
+interface NetworkState {
+  proxy?: URL;
+  noProxy?: string;
+  ca?: string[];  // Unset while the bundled roots are enough
+  caLabel?: string;
+  connectTimeout?: number;
+}
+
+// Where Linux distributions and macOS keep the system store as one PEM file
+const SYSTEM_CA_FILES = [
+  '/etc/ssl/certs/ca-certificates.crt',
+  '/etc/pki/tls/certs/ca-bundle.crt',
+  '/etc/ssl/ca-bundle.pem',
+  '/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem',
+  '/etc/ssl/cert.pem',
+];
+
+// Always reached directly, e.g. the mock server of the tests
+const LOCAL_HOSTS = ['localhost', '127.0.0.1', '::1'];
+
+const MAX_REDIRECTS = 20;
+
+const PEM_CERTIFICATE = /-----BEGIN CERTIFICATE-----[\s\S]+?-----END CERTIFICATE-----/g;
+
+let state: NetworkState = {};
+const agents = new Map<string, https.Agent>();
+const nativeFetch = globalThis.fetch;
+
+function parseProxy(value: string, source: string): URL {
+  let url: URL;
+  try {
+    url = new URL(/^[a-z][a-z0-9+.-]*:\/\//i.test(value) ? value : `http://${value}`);
+  } catch (error) {
+    throw new ConfigError(`Invalid proxy URL in ${source}: ${value}`, { cause: error });
+  }
+  if (url.protocol !== 'http:' && url.protocol !== 'https:') {
+    throw new ConfigError(`Unsupported proxy protocol in ${source}: ${url.protocol} (use http:// or https://)`);
+  }
+  return url;
+}
+
+function readPemBundle(filePath: string): string[] {
+  let text: string;
+  try {
+    text = fs.readFileSync(filePath, 'utf8');
+  } catch (error) {
+    throw new ConfigError(`Cannot read CA bundle ${filePath}: ${(error as Error).message}`, { cause: error });
+  }
+  const certificates = text.match(PEM_CERTIFICATE) || [];
+  if (certificates.length === 0) throw new ConfigError(`No PEM certificates found in ${filePath}`);
+  return certificates;
+}
+
+// Node 22.15+ reads the store itself; older versions get the distribution's bundle file
+export function systemCertificates(): string[] {
+  const getCACertificates = (tls as { getCACertificates?: (type: string) => string[] }).getCACertificates;
+  if (getCACertificates) return getCACertificates('system');
+  const bundle = SYSTEM_CA_FILES.find(file => fs.existsSync(file));
+  return bundle ? fs.readFileSync(bundle, 'utf8').match(PEM_CERTIFICATE) || [] : [];
+}
+
+// Settings for the requests that follow; explicit options win over the environment.
+// The certificates given here add to the bundled roots rather than replacing them.
+export function configureNetwork(options: NetworkOptions = {}): void {
+  const ca: string[] = [];
+  const labels: string[] = [];
+  if (options.caBundle) {
+    ca.push(...readPemBundle(options.caBundle));
+    labels.push(options.caBundle);
+  }
+  if (options.nativeCerts) {
+    const system = systemCertificates();
+    if (system.length === 0) console.error('⚠️  No system certificate store found; using the bundled roots only');
+    ca.push(...system);
+    labels.push('system store');
+  }
+  state = {
+    proxy: options.proxy ? parseProxy(options.proxy, '--proxy') : undefined,
+    noProxy: options.noProxy,
+    ca: ca.length > 0 ? [...tls.rootCertificates, ...ca] : undefined,
+    caLabel: labels.join(' + ') || undefined,
+    connectTimeout: options.connectTimeout,
+  };
+  agents.clear();
+}
+
+// "proxy http://proxy:3128, CA corp-ca.pem", or undefined for direct connections
+export function describeNetwork(): string | undefined {
+  const proxy = state.proxy || proxyFromEnvironment('https:');
+  const parts = [
+    proxy ? `proxy ${proxy.protocol}//${proxy.host}` : undefined,
+    state.caLabel ? `CA ${state.caLabel}` : undefined,
+  ].filter(Boolean);
+  return parts.length > 0 ? parts.join(', ') : undefined;
+}
+
+function proxyFromEnvironment(protocol: string): URL | undefined {
+  const env = process.env;
+  const value = protocol === 'https:'
+    ? env.HTTPS_PROXY || env.https_proxy || env.ALL_PROXY || env.all_proxy
+    : env.HTTP_PROXY || env.http_proxy || env.ALL_PROXY || env.all_proxy;
+  return value ? parseProxy(value, protocol === 'https:' ? 'HTTPS_PROXY' : 'HTTP_PROXY') : undefined;
+}
+
+// NO_PROXY entries match the host and its subdomains; "*" matches everything
+function bypassesProxy(url: URL): boolean {
+  const host = url.hostname.replace(/^\[|\]$/g, '').toLowerCase();
+  if (LOCAL_HOSTS.includes(host)) return true;
+  const noProxy = state.noProxy ?? process.env.NO_PROXY ?? process.env.no_proxy ?? '';
+  return noProxy.split(/[\s,]+/).filter(Boolean).some(entry => {
+    const domain = entry.toLowerCase().replace(/:\d+$/, '').replace(/^\*?\./, '');
+    return entry === '*' || host === domain || host.endsWith(`.${domain}`);
+  });
+}
+
+function proxyFor(url: URL): URL | undefined {
+  if (bypassesProxy(url)) return undefined;
+  return state.proxy || proxyFromEnvironment(url.protocol);
+}
+
+function proxyHeaders(proxy: URL): { [name: string]: string } {
+  if (!proxy.username) return {};
+  const credentials = `${decodeURIComponent(proxy.username)}:${decodeURIComponent(proxy.password)}`;
+  return { 'proxy-authorization': `Basic ${Buffer.from(credentials).toString('base64')}` };
+}
+
+function proxyPort(proxy: URL): number {
+  return Number(proxy.port) || (proxy.protocol === 'https:' ? 443 : 80);
+}
+
+// Destroys the request when its socket is not connected, through TLS for HTTPS, in time.
+// The error looks like the one of fetch, so the request is retried as a network failure.
+function connectDeadline(request: http.ClientRequest, host: string, secure: boolean): void {
+  const timeoutMs = state.connectTimeout;
+  if (!timeoutMs) return;
+  const timer = setTimeout(() => {
+    request.destroy(new TypeError(`fetch failed: connect timeout after ${timeoutMs / 1000}s to ${host}`));
+  }, timeoutMs);
+  request.once('close', () => clearTimeout(timer));
+  request.once('socket', socket => {
+    // A keep-alive socket is connected already
+    if (!socket.connecting) clearTimeout(timer);
+    socket.once(secure ? 'secureConnect' : 'connect', () => clearTimeout(timer));
+  });
+}
+
+// A TLS connection to the target through an HTTP CONNECT tunnel
+function tunnel(proxy: URL, url: URL, signal: AbortSignal): Promise<tls.TLSSocket> {
+  const host = url.hostname.replace(/^\[|\]$/g, '');
+  const port = Number(url.port) || 443;
+  return new Promise((resolve, reject) => {
+    const connect = (proxy.protocol === 'https:' ? https : http).request({
+      host: proxy.hostname,
+      port: proxyPort(proxy),
+      method: 'CONNECT',
+      path: `${net.isIPv6(host) ? `[${host}]` : host}:${port}`,
+      headers: { host: `${host}:${port}`, ...proxyHeaders(proxy) },
+      ca: state.ca,
+      signal,
+    });
+    connectDeadline(connect, proxy.host, proxy.protocol === 'https:');
+    connect.once('connect', (response, socket) => {
+      if (response.statusCode !== 200) {
+        socket.destroy();
+        reject(new Error(`Proxy ${proxy.host} refused the tunnel to ${host}:${port}: ${response.statusCode} ${response.statusMessage || ''}`.trim()));
+        return;
+      }
+      const secure = tls.connect({ socket, servername: net.isIP(host) ? undefined : host, ca: state.ca, ALPNProtocols: ['http/1.1'] });
+      secure.once('secureConnect', () => resolve(secure));
+      secure.once('error', reject);
+    });
+    connect.once('error', reject);
+    connect.end();
+  });
+}
+
+function agentFor(url: URL): https.Agent | undefined {
+  if (url.protocol !== 'https:' || !state.ca) return undefined;
+  let agent = agents.get('ca');
+  if (!agent) {
+    agent = new https.Agent({ keepAlive: true, ca: state.ca });
+    agents.set('ca', agent);
+  }
+  return agent;
+}
+
+function decoded(response: http.IncomingMessage): Readable {
+  const encoding = (response.headers['content-encoding'] || '').toLowerCase();
+  if (encoding === 'gzip' || encoding === 'x-gzip') return response.pipe(zlib.createGunzip());
+  if (encoding === 'deflate') return response.pipe(zlib.createInflate());
+  if (encoding === 'br') return response.pipe(zlib.createBrotliDecompress());
+  return response;
+}
+
+async function send(url: URL, method: string, headers: { [name: string]: string }, body: Buffer | undefined, signal: AbortSignal): Promise<http.IncomingMessage> {
+  const proxy = proxyFor(url);
+  const options: https.RequestOptions = { method, headers: { ...headers }, signal };
+  if (body && !Object.keys(headers).some(name => name.toLowerCase() === 'content-length')) {
+    options.headers = { ...options.headers, 'content-length': String(body.length) };
+  }
+  let request: http.ClientRequest;
+  if (proxy && url.protocol === 'https:') {
+    const socket = await tunnel(proxy, url, signal);
+    request = https.request(url, { ...options, createConnection: () => socket });
+  } else if (proxy) {
+    // Plain HTTP goes to the proxy with the absolute URL as the request target
+    request = (proxy.protocol === 'https:' ? https : http).request({
+      ...options,
+      host: proxy.hostname,
+      port: proxyPort(proxy),
+      path: url.href,
+      headers: { ...options.headers, host: url.host, ...proxyHeaders(proxy) },
+      ca: state.ca,
+    });
+    connectDeadline(request, proxy.host, proxy.protocol === 'https:');
+  } else {
+    request = (url.protocol === 'https:' ? https : http).request(url, { ...options, agent: agentFor(url) });
+    connectDeadline(request, url.host, url.protocol === 'https:');
+  }
+  return new Promise((resolve, reject) => {
+    request.once('response', resolve);
+    request.once('error', reject);
+    request.end(body);
+  });
+}
+
+// fetch() over node:http, for the requests that need a proxy, extra certificates or a
+// connect timeout; follows redirects like fetch does
+async function networkFetch(input: string | URL | Request, init?: RequestInit): Promise<Response> {
+  const request = new Request(input, { ...init, duplex: 'half' } as RequestInit);
+  let url = new URL(request.url);
+  if ((url.protocol !== 'http:' && url.protocol !== 'https:') || (!proxyFor(url) && !state.ca && !state.connectTimeout)) {
+    return nativeFetch(input, init);
+  }
+  let method = request.method;
+  const headers: { [name: string]: string } = {};
+  request.headers.forEach((value, name) => { headers[name] = value; });
+  let body = request.body ? Buffer.from(await request.arrayBuffer()) : undefined;
+
+  for (let redirects = 0; ; redirects++) {
+    const response = await send(url, method, headers, body, request.signal);
+    const status = response.statusCode || 0;
+    const location = response.headers.location;
+    if ([301, 302, 303, 307, 308].includes(status) && location && request.redirect !== 'manual') {
+      response.resume();
+      if (request.redirect === 'error') throw new TypeError(`Redirected from ${url.href}`);
+      if (redirects >= MAX_REDIRECTS) throw new TypeError(`Too many redirects from ${request.url}`);
+      if (status === 303 || ((status === 301 || status === 302) && method === 'POST')) {
+        method = 'GET';
+        body = undefined;
+        for (const name of Object.keys(headers)) {
+          if (/^content-/i.test(name)) delete headers[name];
+        }
+      }
+      const next = new URL(location, url);
+      if (next.origin !== url.origin) {
+        for (const name of Object.keys(headers)) {
+          if (/^(authorization|cookie|x-goog-api-key)$/i.test(name)) delete headers[name];
+        }
+      }
+      url = next;
+      continue;
+    }
+
+    const responseHeaders = new Headers();
+    for (let i = 0; i < response.rawHeaders.length; i += 2) {
+      responseHeaders.append(response.rawHeaders[i], response.rawHeaders[i + 1]);
+    }
+    const empty = method === 'HEAD' || [204, 205, 304].includes(status);
+    if (empty) response.resume();
+    const result = new Response(empty ? null : Readable.toWeb(decoded(response)) as ReadableStream, {
+      status,
+      statusText: response.statusMessage,
+      headers: responseHeaders,
+    });
+    Object.defineProperty(result, 'url', { value: url.href });
+    Object.defineProperty(result, 'redirected', { value: redirects > 0 });
+    return result;
+  }
+}
+
+// Installed on import, so the logging and HTTP fixture wrappers, which keep the fetch
+// they find when they are set up, send their requests through it. Without a proxy,
+// extra certificates or a connect timeout every request goes to the built-in fetch unchanged.
+globalThis.fetch = networkFetch;
@@ -0,0 +1,126 @@
+// notebook.ts - Jupyter notebook (.ipynb) to Markdown conversion before upload
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+
+// Types
+export interface NotebookOptions {
+  stripOutputs?: boolean;  // Leave cell outputs out entirely
+  maxOutputChars?: number;  // Longer text outputs are truncated (default: 2000)
+}
+
+export interface ConvertedNotebook {
+  markdown: string;
+  cells: number;
+  language?: string;  // Kernel language, used to fence code cells
+}
+
+export interface ConvertedNotebookFile {
+  path: string;  // Temporary Markdown file; the caller removes it
+  cells: number;
+  language?: string;
+}
+
+// nbformat 4; "source" and text outputs are a string or a list of lines
+type MultilineString = string | string[];
+
+interface NotebookOutput {
+  output_type: 'stream' | 'execute_result' | 'display_data' | 'error';
+  text?: MultilineString;
+  data?: { [mimeType: string]: MultilineString | object };
+  ename?: string;
+  evalue?: string;
+}
+
+interface NotebookCell {
+  cell_type: 'markdown' | 'code' | 'raw';
+  source: MultilineString;
+  outputs?: NotebookOutput[];
+}
+
+interface NotebookJson {
+  cells?: NotebookCell[];
+  metadata?: { kernelspec?: { language?: string }; language_info?: { name?: string } };
+}
+
+const DEFAULT_MAX_OUTPUT_CHARS = 2000;
+
+export function isNotebook(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() === '.ipynb';
+}
+
+function joinLines(value: MultilineString | undefined): string {
+  return Array.isArray(value) ? value.join('') : value || '';
+}
+
+// A fence longer than any backtick run inside the text
+function fence(text: string, info: string = ''): string {
+  const longest = Math.max(2, ...(text.match(/`+/g) || []).map(run => run.length));
+  const marks = '`'.repeat(longest + 1);
+  return `${marks}${info}\n${text.replace(/\n+$/, '')}\n${marks}`;
+}
+
+// Images, HTML and widget state are dropped; only the text forms of an output are kept
+function outputText(output: NotebookOutput): string {
+  switch (output.output_type) {
+    case 'stream':
+      return joinLines(output.text);
+    case 'error':
+      return `${output.ename || 'Error'}: ${output.evalue || ''}`;
+    default: {
+      const data = output.data || {};
+      const text = data['text/markdown'] ?? data['text/plain'];
+      return typeof text === 'string' || Array.isArray(text) ? joinLines(text as MultilineString) : '';
+    }
+  }
+}
+
+export function notebookToMarkdown(source: string, options: NotebookOptions = {}): ConvertedNotebook {
+  let notebook: NotebookJson;
+  try {
+    notebook = JSON.parse(source) as NotebookJson;
+  } catch (error) {
+    throw new Error(`Not a notebook: ${(error as Error).message}`);
+  }
+  if (!Array.isArray(notebook.cells)) {
+    throw new Error('Not a notebook: no cells (nbformat 4 is required)');
+  }
+
+  const language = notebook.metadata?.kernelspec?.language || notebook.metadata?.language_info?.name;
+  const maxOutputChars = options.maxOutputChars ?? DEFAULT_MAX_OUTPUT_CHARS;
+  // Each cell starts with a comment carrying its index, so retrieved chunks point back to the cell
+  const sections = notebook.cells.map((cell, index) => {
+    const text = joinLines(cell.source);
+    const parts = [`<!-- cell ${index} (${cell.cell_type}) -->`];
+    if (cell.cell_type === 'markdown') {
+      parts.push(text);
+    } else if (cell.cell_type === 'raw') {
+      parts.push(fence(text));
+    } else {
+      parts.push(fence(text, language || ''));
+      const outputs = options.stripOutputs ? '' : (cell.outputs || []).map(output => outputText(output).replace(/\n+$/, '')).filter(Boolean).join('\n').trim();
+      if (outputs) {
+        const shown = outputs.length > maxOutputChars ? `${outputs.slice(0, maxOutputChars)}\n[... ${outputs.length - maxOutputChars} characters truncated]` : outputs;
+        parts.push(`Output:\n\n${fence(shown, 'text')}`);
+      }
+    }
+    return parts.join('\n\n');
+  });
+  return { markdown: sections.join('\n\n') + '\n', cells: notebook.cells.length, language };
+}
+
+// Writes the Markdown to a temporary file, or returns null after logging when the
+// notebook cannot be parsed so the caller can upload the original file instead
+export function convertNotebookToTempFile(filePath: string, options: NotebookOptions = {}): ConvertedNotebookFile | null {
+  try {
+    const { markdown, cells, language } = notebookToMarkdown(fs.readFileSync(filePath, 'utf8'), options);
+    const tempPath = path.join(os.tmpdir(), `gemini-notebook-${randomUUID()}.md`);
+    fs.writeFileSync(tempPath, markdown);
+    return { path: tempPath, cells, language };
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Notebook conversion failed, uploading original: ${path.basename(filePath)} - ${err.message}`);
+    return null;
+  }
+}
@@ -0,0 +1,111 @@
+// notify.ts - Local command and webhook notifications when documents finish indexing
+import { spawn } from 'child_process';
+import { FileSearchError, HttpStatusError } from './errors.js';
+import { RetryOptions, withRetry } from './retry.js';
+
+// Types
+export type IndexingEventType = 'indexing.completed' | 'indexing.failed';
+
+export interface IndexingEvent {
+  event: IndexingEventType;
+  store: string;
+  file: string;  // Local path that was uploaded
+  displayName?: string;
+  documentName?: string;  // Set when indexing completed
+  error?: string;  // Set when indexing failed
+  code?: string;  // FileSearchError code of the failure
+  timestamp: string;
+}
+
+export interface NotifyOptions {
+  command?: string;  // Run through the shell with the event as JSON on stdin and GEMINI_FS_* variables
+  webhook?: string;  // URL that receives the event as a JSON POST
+  timeoutMs?: number;  // Per notification, for the command and each webhook attempt
+  retry?: RetryOptions;  // Backoff for webhook 429/5xx responses
+}
+
+const DEFAULT_TIMEOUT_MS = 10_000;
+
+export function indexingCompleted(store: string, file: string, displayName?: string, documentName?: string): IndexingEvent {
+  return { event: 'indexing.completed', store, file, displayName, documentName, timestamp: new Date().toISOString() };
+}
+
+export function indexingFailed(store: string, file: string, displayName: string | undefined, error: unknown): IndexingEvent {
+  return {
+    event: 'indexing.failed',
+    store,
+    file,
+    displayName,
+    error: error instanceof Error ? error.message : String(error),
+    code: error instanceof FileSearchError ? error.code : undefined,
+    timestamp: new Date().toISOString(),
+  };
+}
+
+// Notification failures are logged and never fail the upload that triggered them
+export class Notifier {
+  constructor(private readonly options: NotifyOptions) {}
+
+  get enabled(): boolean {
+    return Boolean(this.options.command || this.options.webhook);
+  }
+
+  async notify(event: IndexingEvent): Promise<void> {
+    await Promise.all([
+      this.options.command ? this.runCommand(this.options.command, event) : undefined,
+      this.options.webhook ? this.postWebhook(this.options.webhook, event) : undefined,
+    ]);
+  }
+
+  // stdout goes to stderr so the command cannot corrupt the MCP stdio stream
+  private runCommand(command: string, event: IndexingEvent): Promise<void> {
+    return new Promise(resolve => {
+      const child = spawn(command, {
+        shell: true,
+        stdio: ['pipe', 2, 2],
+        timeout: this.options.timeoutMs || DEFAULT_TIMEOUT_MS,
+        env: {
+          ...process.env,
+          GEMINI_FS_EVENT: event.event,
+          GEMINI_FS_STORE: event.store,
+          GEMINI_FS_FILE: event.file,
+          GEMINI_FS_DISPLAY_NAME: event.displayName || '',
+          GEMINI_FS_DOCUMENT: event.documentName || '',
+          GEMINI_FS_ERROR: event.error || '',
+          GEMINI_FS_ERROR_CODE: event.code || '',
+        },
+      });
+      child.on('error', error => {
+        console.error(`⚠️  Notify command failed: ${error.message}`);
+        resolve();
+      });
+      child.on('close', (code, signal) => {
+        if (code !== 0) {
+          console.error(`⚠️  Notify command exited with ${signal || `code ${code}`} for ${event.file}`);
+        }
+        resolve();
+      });
+      child.stdin?.on('error', () => undefined);  // The command may exit without reading stdin
+      child.stdin?.end(JSON.stringify(event));
+    });
+  }
+
+  private async postWebhook(url: string, event: IndexingEvent): Promise<void> {
+    try {
+      await withRetry('webhook notification', async () => {
+        const response = await fetch(url, {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json' },
+          body: JSON.stringify(event),
+          signal: AbortSignal.timeout(this.options.timeoutMs || DEFAULT_TIMEOUT_MS),
+        });
+        if (!response.ok) {
+          throw new HttpStatusError(`Webhook ${url} returned HTTP ${response.status}`, response.status, response.headers.get('retry-after'));
+        }
+      }, this.options.retry);
+    } catch (error) {
+      const err = error as Error;
+      console.error(`⚠️  Notify webhook failed for ${event.file}: ${err.message}`);
+    }
+  }
+}
@@ -0,0 +1,165 @@
+// operations.ts - Long-running operation polling
+import * as fs from 'fs';
+import {
+  GoogleGenAI,
+  ImportFileOperation,
+  ImportFileResponse,
+  Operation,
+  UploadToFileSearchStoreOperation,
+  UploadToFileSearchStoreResponse,
+} from '@google/genai';
+import { RetryOptions, withRetry } from './retry.js';
+import { CacheEntry } from './cache.js';
+import { writeFileAtomic } from './shutdown.js';
+import { withFileLock } from './lock.js';
+import { CancelledError, OperationFailedError, OperationTimeoutError } from './errors.js';
+
+export interface PollOptions {
+  pollInterval?: number;  // Milliseconds before the first poll (default: 1000)
+  maxInterval?: number;  // Longest wait between polls (default: 30000)
+  multiplier?: number;  // Growth of the wait after every poll (default: 2)
+  timeout?: number;  // Milliseconds from the first poll until giving up (default: 600000)
+  maxPolls?: number;  // Also give up after this many polls
+  retry?: RetryOptions;
+  signal?: AbortSignal;  // Stops waiting; the operation itself carries on
+  onPoll?: (polls: number, elapsedMs: number) => void;
+}
+
+// What kind of result a pending operation produces
+export type OperationKind = 'upload' | 'import';
+
+// An upload accepted without waiting for indexing, see PendingOperations
+export interface PendingOperation {
+  operation: string;  // Operation name
+  kind: OperationKind;
+  storeName: string;
+  displayName: string;
+  createdAt: string;  // ISO 8601
+  source?: { rootDir: string; path: string };  // The ingested file, quarantined when indexing fails
+  cache?: { hash: string; entry: Pick<CacheEntry, 'displayName' | 'fingerprint'> };  // Recorded in the index cache once indexed
+}
+
+// Anything shaped like a long-running operation: Gemini API or Vertex AI
+interface PollableOperation {
+  done?: boolean;
+  error?: unknown;
+}
+
+export const DEFAULT_POLL_OPTIONS: Required<Pick<PollOptions, 'pollInterval' | 'maxInterval' | 'multiplier' | 'timeout'>> = {
+  pollInterval: 1000,
+  maxInterval: 30_000,
+  multiplier: 2,
+  timeout: 600_000,
+};
+
+// The wait before each poll: pollInterval, then growing by multiplier up to maxInterval
+export function pollDelay(polls: number, options: PollOptions = {}): number {
+  const { pollInterval, maxInterval, multiplier } = { ...DEFAULT_POLL_OPTIONS, ...withoutUndefined(options) };
+  return Math.min(maxInterval, pollInterval * multiplier ** polls);
+}
+
+function withoutUndefined(options: PollOptions): PollOptions {
+  return Object.fromEntries(Object.entries(options).filter(([, value]) => value !== undefined)) as PollOptions;
+}
+
+function sleep(ms: number, signal?: AbortSignal): Promise<void> {
+  return new Promise((resolve, reject) => {
+    if (signal?.aborted) {
+      reject(new CancelledError('Cancelled while waiting for the operation'));
+      return;
+    }
+    const onAbort = () => {
+      clearTimeout(timer);
+      reject(new CancelledError('Cancelled while waiting for the operation'));
+    };
+    const timer = setTimeout(() => {
+      signal?.removeEventListener('abort', onAbort);
+      resolve();
+    }, ms);
+    signal?.addEventListener('abort', onAbort, { once: true });
+  });
+}
+
+// Refreshes the operation with poll until it reports done, throwing on timeout or operation error.
+// Polls start quickly for small files and back off for long indexing, so they neither hammer
+// the API nor give up on large documents; the last wait is cut short to meet the deadline.
+export async function pollUntilDone<T extends PollableOperation>(
+  operation: T,
+  poll: (current: T) => Promise<T>,
+  options: PollOptions = {}
+): Promise<T> {
+  const { timeout } = { ...DEFAULT_POLL_OPTIONS, ...withoutUndefined(options) };
+  const startedAt = Date.now();
+
+  let current = operation;
+  let polls = 0;
+  while (!current.done && (options.maxPolls === undefined || polls < options.maxPolls)) {
+    const remaining = timeout - (Date.now() - startedAt);
+    if (remaining <= 0) break;
+    await sleep(Math.min(pollDelay(polls, options), remaining), options.signal);
+    const pending = current;
+    current = await withRetry('operation poll', () => poll(pending), options.retry);
+    polls++;
+    options.onPoll?.(polls, Date.now() - startedAt);
+  }
+
+  if (!current.done) {
+    throw new OperationTimeoutError(polls, Date.now() - startedAt);
+  }
+  if (current.error) {
+    throw new OperationFailedError(current.error);
+  }
+  return current;
+}
+
+// Polls a Gemini API operation until it is done
+export async function waitForOperation<T>(
+  ai: GoogleGenAI,
+  operation: Operation<T>,
+  options: PollOptions = {}
+): Promise<Operation<T>> {
+  return pollUntilDone(operation, pending => ai.operations.get<T, Operation<T>>({ operation: pending }), options);
+}
+
+// The typed operation behind a name returned earlier, e.g. by an upload that did not wait
+export function operationOf(kind: 'upload', name: string): Operation<UploadToFileSearchStoreResponse>;
+export function operationOf(kind: 'import', name: string): Operation<ImportFileResponse>;
+export function operationOf(kind: OperationKind, name: string): Operation<UploadToFileSearchStoreResponse> | Operation<ImportFileResponse> {
+  const operation = kind === 'upload' ? new UploadToFileSearchStoreOperation() : new ImportFileOperation();
+  operation.name = name;
+  return operation;
+}
+
+// Uploads accepted without waiting for indexing, kept in a JSON file until a later
+// wait confirms them. Their index cache entries are only recorded then, with the
+// document names indexing produced.
+export class PendingOperations {
+  constructor(private readonly filePath: string) {}
+
+  list(storeName?: string): PendingOperation[] {
+    let operations: PendingOperation[];
+    try {
+      operations = (JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as { operations: PendingOperation[] }).operations || [];
+    } catch (error) {
+      return [];
+    }
+    return operations.filter(operation => !storeName || operation.storeName === storeName);
+  }
+
+  add(operation: PendingOperation): void {
+    withFileLock(this.filePath, () => this.save([...this.list().filter(pending => pending.operation !== operation.operation), operation]));
+  }
+
+  remove(names: string[]): void {
+    const removed = new Set(names);
+    withFileLock(this.filePath, () => this.save(this.list().filter(pending => !removed.has(pending.operation))));
+  }
+
+  private save(operations: PendingOperation[]): void {
+    if (operations.length === 0) {
+      fs.rmSync(this.filePath, { force: true });
+      return;
+    }
+    writeFileAtomic(this.filePath, JSON.stringify({ version: 1, operations }, null, 2));
+  }
+}
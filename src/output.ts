@@ -0,0 +1,236 @@
+// output.ts - Pluggable formatters for query answers
+import { GroundingMetadata } from '@google/genai';
+import { Citation, addFootnoteMarkers, formatCitationFile, formatFootnotes } from './citations.js';
+import { InvalidInputError } from './errors.js';
+import { TokenUsage, formatUsage, queryCost } from './cost.js';
+import { QuestionAnswer } from './ask-all.js';
+import { AnswerConfidence } from './grounding.js';
+import { RenderOptions, renderMarkdown } from './render.js';
+import { AnswerLanguageReport } from './answer-language.js';
+import { formatCitationContext, formatContextLocation } from './cite-context.js';
+
+// Types
+export type OutputFormat = 'text' | 'json' | 'markdown' | 'sarif';
+
+export interface AnswerReport {
+  question: string;
+  answer: string;
+  citations: Citation[];
+  stores: string[];
+  model?: string;
+  usage?: TokenUsage;
+  groundingMetadata?: GroundingMetadata;  // Used to place footnote markers
+  cachedAt?: string;  // Set when the answer came from the query cache
+  structured?: unknown;  // Validated JSON answer of a query with a response schema
+  answers?: QuestionAnswer[];  // Set when several questions were asked at once, see askAll
+  confidence?: AnswerConfidence;
+  minConfidence?: number;  // Answers scoring below are marked uncertain and shown with the retrieved chunks
+  language?: AnswerLanguageReport;  // Set when an answer language was requested
+}
+
+function estimatedCost(report: AnswerReport): number | undefined {
+  return report.model && report.usage ? queryCost(report.model, report.usage) : undefined;
+}
+
+export function isUncertain(report: AnswerReport): boolean {
+  return report.confidence !== undefined && report.minConfidence !== undefined && report.confidence.score < report.minConfidence;
+}
+
+function answerStatus(report: AnswerReport): 'confident' | 'uncertain' | undefined {
+  if (report.confidence === undefined || report.minConfidence === undefined) return undefined;
+  return isUncertain(report) ? 'uncertain' : 'confident';
+}
+
+function uncertainNote(report: AnswerReport): string {
+  return `Uncertain answer: confidence ${report.confidence?.score.toFixed(2)} is below ${report.minConfidence?.toFixed(2)}; check it against the retrieved chunks`;
+}
+
+export interface Formatter {
+  readonly format: OutputFormat;
+  render(report: AnswerReport): string;
+}
+
+export const OUTPUT_FORMATS: OutputFormat[] = ['text', 'json', 'markdown', 'sarif'];
+
+const TOOL_NAME = 'gemini-file-search-mcp-server';
+const TOOL_VERSION = '1.0.0';
+
+export class TextFormatter implements Formatter {
+  readonly format = 'text';
+
+  render(report: AnswerReport): string {
+    let text = isUncertain(report) ? `${uncertainNote(report)}\n\n${report.answer}` : report.answer;
+    if (report.citations.length > 0) {
+      text += '\n\nSources:\n' + report.citations
+        .map(c => `  [${c.index}] ${c.fileName}${formatCitationFile(c)}${c.store ? ` (${c.store})` : ''}${c.confidence !== undefined ? ` (${c.confidence.toFixed(2)})` : ''}`
+          + (c.context ? `\n      ${formatContextLocation(c.context)}\n${formatCitationContext(c.context, '      ')}` : ''))
+        .join('\n');
+    }
+    if (isUncertain(report) && report.citations.length > 0) {
+      text += '\n\nRetrieved chunks:\n' + report.citations.map(c => `\n  [${c.index}] ${c.fileName}\n${c.chunkText}`).join('\n');
+    }
+    if (report.confidence) {
+      text += `\n\nConfidence: ${report.confidence.score.toFixed(2)}`;
+    }
+    if (report.usage) {
+      text += `\n\nUsage: ${formatUsage(report.model || '', report.usage)}`;
+    }
+    if (report.cachedAt) {
+      text += `\n\nCached answer from ${report.cachedAt}`;
+    }
+    return text;
+  }
+}
+
+// The text format on a terminal: the answer's Markdown rendered, footnote markers as
+// superscripts; only for streams that supportsRendering accepts
+export class TerminalFormatter implements Formatter {
+  readonly format = 'text';
+
+  constructor(private readonly options: RenderOptions = {}) {}
+
+  render(report: AnswerReport): string {
+    let markdown = isUncertain(report) ? `> ⚠️ ${uncertainNote(report)}\n\n` : '';
+    markdown += addFootnoteMarkers(report.answer, report.groundingMetadata);
+    if (report.citations.length > 0) {
+      markdown += '\n\n## Sources\n\n' + report.citations
+        .map(c => `[${c.index}] **${c.fileName}**${formatCitationFile(c)}${c.store ? ` _(${c.store})_` : ''}${c.confidence !== undefined ? ` (${c.confidence.toFixed(2)})` : ''}`
+          + (c.context ? `\n\n\`${formatContextLocation(c.context)}\`\n\n\`\`\`\n${formatCitationContext(c.context)}\n\`\`\`\n` : ''))
+        .join('\n');
+    }
+    if (isUncertain(report) && report.citations.length > 0) {
+      markdown += '\n\n## Retrieved chunks\n\n' + report.citations
+        .map(c => `[${c.index}] **${c.fileName}**\n\n${c.chunkText.split('\n').map(line => `> ${line}`).join('\n')}`)
+        .join('\n\n');
+    }
+    const footer = [
+      report.confidence ? `Confidence: ${report.confidence.score.toFixed(2)}` : undefined,
+      report.usage ? `Usage: ${formatUsage(report.model || '', report.usage)}` : undefined,
+      report.cachedAt ? `Cached answer from ${report.cachedAt}` : undefined,
+    ].filter(line => line);
+    if (footer.length > 0) markdown += `\n\n${footer.map(line => `_${line}_`).join('\n')}`;
+    return renderMarkdown(markdown, this.options);
+  }
+}
+
+export class JsonFormatter implements Formatter {
+  readonly format = 'json';
+
+  render(report: AnswerReport): string {
+    return JSON.stringify({
+      question: report.question,
+      answer: report.answer,
+      status: answerStatus(report),
+      confidence: report.confidence,
+      structured: report.structured,
+      answers: report.answers,
+      citations: report.citations,
+      chunks: isUncertain(report)
+        ? report.citations.map(c => ({ index: c.index, fileName: c.fileName, store: c.store, text: c.chunkText }))
+        : undefined,
+      stores: report.stores,
+      model: report.model,
+      usage: report.usage,
+      estimatedCostUsd: estimatedCost(report),
+      cachedAt: report.cachedAt,
+      language: report.language,
+    }, null, 2);
+  }
+}
+
+export class MarkdownFormatter implements Formatter {
+  readonly format = 'markdown';
+
+  render(report: AnswerReport): string {
+    let markdown = `## Answer\n\n${isUncertain(report) ? `> ⚠️ ${uncertainNote(report)}\n\n` : ''}${addFootnoteMarkers(report.answer, report.groundingMetadata)}`;
+    if (report.citations.length > 0) {
+      markdown += `\n\n## Citations\n\n${formatFootnotes(report.citations)}`;
+    }
+    if (isUncertain(report) && report.citations.length > 0) {
+      markdown += '\n\n## Retrieved Chunks\n\n' + report.citations
+        .map(c => `**[${c.index}] ${c.fileName}**\n\n${c.chunkText.split('\n').map(line => `> ${line}`).join('\n')}`)
+        .join('\n\n');
+    }
+    if (report.confidence) {
+      markdown += `\n\n_Confidence: ${report.confidence.score.toFixed(2)}_`;
+    }
+    if (report.usage) {
+      markdown += `\n\n_Usage: ${formatUsage(report.model || '', report.usage)}_`;
+    }
+    if (report.cachedAt) {
+      markdown += `\n\n_Cached answer from ${new Date(report.cachedAt).toLocaleString()}_`;
+    }
+    return markdown;
+  }
+}
+
+// SARIF 2.1.0 layout: one result per citation, located in the cited file
+export class SarifFormatter implements Formatter {
+  readonly format = 'sarif';
+
+  render(report: AnswerReport): string {
+    const results = report.citations.map(c => ({
+      ruleId: 'grounding-citation',
+      level: 'note',
+      message: {
+        text: c.startIndex !== undefined && c.endIndex !== undefined
+          ? report.answer.slice(c.startIndex, c.endIndex)
+          : `Cited by answer to: ${report.question}`,
+      },
+      locations: [{
+        physicalLocation: {
+          artifactLocation: { uri: c.context?.path || c.fileName },
+          // Lines are known once the chunk was found in the local file
+          region: c.context
+            ? { startLine: c.context.startLine, endLine: c.context.endLine, snippet: { text: c.chunkText } }
+            : { snippet: { text: c.chunkText } },
+        },
+      }],
+      properties: {
+        citationIndex: c.index,
+        store: c.store,
+        confidence: c.confidence,
+      },
+    }));
+
+    return JSON.stringify({
+      $schema: 'https://json.schemastore.org/sarif-2.1.0.json',
+      version: '2.1.0',
+      runs: [{
+        tool: {
+          driver: {
+            name: TOOL_NAME,
+            version: TOOL_VERSION,
+            rules: [{ id: 'grounding-citation', shortDescription: { text: 'Source passage used to ground the answer' } }],
+          },
+        },
+        results,
+        properties: {
+          question: report.question,
+          answer: report.answer,
+          status: answerStatus(report),
+          confidence: report.confidence,
+          stores: report.stores,
+          model: report.model,
+          usage: report.usage,
+          cachedAt: report.cachedAt,
+        },
+      }],
+    }, null, 2);
+  }
+}
+
+export function getFormatter(format: OutputFormat = 'markdown'): Formatter {
+  switch (format) {
+    case 'text':
+      return new TextFormatter();
+    case 'json':
+      return new JsonFormatter();
+    case 'sarif':
+      return new SarifFormatter();
+    case 'markdown':
+      return new MarkdownFormatter();
+    default:
+      throw new InvalidInputError(`Unknown output format "${format}" (expected one of: ${OUTPUT_FORMATS.join(', ')})`);
+  }
+}
@@ -0,0 +1,90 @@
+// pagination.ts - Pages of list endpoints, following nextPageToken as they are read
+import { Readable } from 'stream';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export interface Page<T> {
+  items: T[];
+  nextPageToken?: string;
+}
+
+export interface PageOptions {
+  pageSize?: number;  // Items asked for per request; the API may return fewer and caps it (default: the API's)
+  pageToken?: string;  // Start at this page, e.g. the nextPageToken of an earlier listing
+  limit?: number;  // Stop after this many items
+}
+
+export type PageFetcher<T> = (pageToken: string | undefined, pageSize: number | undefined) => Promise<Page<T>>;
+
+// Requests one page at a time, only when the reader gets to it, so a listing can be
+// stopped early or streamed without holding the whole store in memory. Every
+// iteration starts over from options.pageToken.
+export class Paginator<T> implements AsyncIterable<T> {
+  private itemCount = 0;
+  private pageCountInternal = 0;
+  private nextPageTokenInternal?: string;
+
+  constructor(private readonly fetchPage: PageFetcher<T>, private readonly options: PageOptions = {}) {
+    for (const [name, value] of [['pageSize', options.pageSize], ['limit', options.limit]] as const) {
+      if (value !== undefined && !(Number.isInteger(value) && value >= 1)) {
+        throw new InvalidInputError(`${name} must be a positive integer, got ${value}`);
+      }
+    }
+  }
+
+  // Items read so far by the current or last iteration
+  get count(): number {
+    return this.itemCount;
+  }
+
+  get pageCount(): number {
+    return this.pageCountInternal;
+  }
+
+  // Where the last iteration stopped: pass it as pageToken to continue. Undefined once
+  // the last page was read. A listing cut short by limit inside a page resumes at the
+  // start of that page, so items are repeated rather than skipped.
+  get nextPageToken(): string | undefined {
+    return this.nextPageTokenInternal;
+  }
+
+  async *pages(): AsyncGenerator<T[]> {
+    this.itemCount = 0;
+    this.pageCountInternal = 0;
+    let pageToken = this.options.pageToken;
+    this.nextPageTokenInternal = pageToken;
+    const limit = this.options.limit ?? Infinity;
+    while (this.itemCount < limit) {
+      const pageSize = this.options.pageSize !== undefined ? Math.min(this.options.pageSize, limit - this.itemCount) : undefined;
+      const page = await this.fetchPage(pageToken, pageSize);
+      this.pageCountInternal++;
+      const items = page.items.slice(0, limit - this.itemCount);
+      this.itemCount += items.length;
+      this.nextPageTokenInternal = items.length < page.items.length ? pageToken : page.nextPageToken;
+      if (items.length > 0) yield items;
+      if (!page.nextPageToken || items.length < page.items.length) return;
+      pageToken = page.nextPageToken;
+    }
+  }
+
+  async *[Symbol.asyncIterator](): AsyncGenerator<T> {
+    for await (const items of this.pages()) yield* items;
+  }
+
+  // Object-mode stream of the items, for pipelines
+  intoStream(): Readable {
+    return Readable.from(this);
+  }
+
+  async all(): Promise<T[]> {
+    const all: T[] = [];
+    for await (const items of this.pages()) all.push(...items);
+    return all;
+  }
+
+  // Total number of items, without keeping them
+  async countAll(): Promise<number> {
+    for await (const items of this.pages()) void items;
+    return this.itemCount;
+  }
+}
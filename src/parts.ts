@@ -0,0 +1,101 @@
+// parts.ts - Splitting of text too large for one document into parts at headings, symbols or paragraphs
+import * as path from 'path';
+import { MAX_UPLOAD_SIZE } from './client.js';
+import { chunkCode, isCodeFile } from './code-chunker.js';
+
+// Types
+export interface FilePart {
+  index: number;  // 1-based
+  text: string;
+  startLine: number;  // 1-based, inclusive
+  endLine: number;
+}
+
+// The upload limit, so no part is refused for its size
+export const DEFAULT_PART_SIZE = MAX_UPLOAD_SIZE;
+
+const MARKDOWN_EXTENSIONS = ['.md', '.markdown', '.mdx'];
+
+export function isMarkdownFile(filePath: string, mimeType?: string): boolean {
+  return mimeType === 'text/markdown' || MARKDOWN_EXTENSIONS.includes(path.extname(filePath).toLowerCase());
+}
+
+// 0-based lines a part may start at: headings outside code fences for Markdown, the
+// first line of each symbol for source files, and the line after a blank one otherwise
+function breakLines(filePath: string, text: string, lines: string[], mimeType?: string): Set<number> {
+  const breaks = new Set<number>([0]);
+  if (isMarkdownFile(filePath, mimeType)) {
+    let fence = false;
+    lines.forEach((line, i) => {
+      if (/^\s*(```|~~~)/.test(line)) fence = !fence;
+      else if (!fence && /^#{1,6}\s/.test(line)) breaks.add(i);
+    });
+    return breaks;
+  }
+  if (isCodeFile(filePath)) {
+    for (const chunk of chunkCode(filePath, text)) breaks.add(chunk.startLine - 1);
+    if (breaks.size > 1) return breaks;
+  }
+  lines.forEach((line, i) => {
+    if (i > 0 && !lines[i - 1].trim() && line.trim()) breaks.add(i);
+  });
+  return breaks;
+}
+
+// The longest prefix of a line within maxBytes that does not cut a UTF-8 sequence
+function slicePrefix(line: string, maxBytes: number): string {
+  const bytes = Buffer.from(line);
+  let end = Math.min(maxBytes, bytes.length);
+  while (end > 0 && end < bytes.length && (bytes[end] & 0xc0) === 0x80) end--;
+  return bytes.subarray(0, end).toString();
+}
+
+// Parts of at most maxBytes each. Sections between break lines are kept together while
+// they fit; a section larger than a part is split at line ends, and a line larger than
+// a part by bytes, so any text splits.
+export function splitIntoParts(filePath: string, text: string, maxBytes: number = DEFAULT_PART_SIZE, mimeType?: string): FilePart[] {
+  if (Buffer.byteLength(text) <= maxBytes) return [{ index: 1, text, startLine: 1, endLine: text.split('\n').length }];
+  const lines = text.split('\n');
+  const breaks = breakLines(filePath, text, lines, mimeType);
+  const parts: FilePart[] = [];
+  let current: string[] = [];
+  let currentBytes = 0;
+  let startLine = 1;
+
+  const flush = (endLine: number) => {
+    if (current.length > 0) parts.push({ index: parts.length + 1, text: current.join('\n'), startLine, endLine });
+    current = [];
+    currentBytes = 0;
+    startLine = endLine + 1;
+  };
+
+  // The bytes of each section, so a section that does not fit starts a new part
+  const sectionBytes = new Map<number, number>();
+  let sectionStart = 0;
+  lines.forEach((line, i) => {
+    if (breaks.has(i)) sectionStart = i;
+    sectionBytes.set(sectionStart, (sectionBytes.get(sectionStart) || 0) + Buffer.byteLength(line) + 1);
+  });
+
+  lines.forEach((line, i) => {
+    const section = sectionBytes.get(i);
+    if (section !== undefined && currentBytes > 0 && currentBytes + section > maxBytes) flush(i);
+    let rest = line;
+    let bytes = Buffer.byteLength(rest) + 1;
+    if (currentBytes > 0 && currentBytes + bytes > maxBytes) flush(i);
+    while (bytes > maxBytes) {
+      const prefix = slicePrefix(rest, maxBytes - 1);
+      current.push(prefix);
+      currentBytes = maxBytes;
+      // A line split across parts ends one and starts the next
+      flush(i + 1);
+      startLine = i + 1;
+      rest = rest.slice(prefix.length);
+      bytes = Buffer.byteLength(rest) + 1;
+    }
+    current.push(rest);
+    currentBytes += bytes;
+  });
+  flush(lines.length);
+  return parts;
+}
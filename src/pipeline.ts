@@ -0,0 +1,165 @@
+// pipeline.ts - Bounded concurrency, rate limiting and failure policies for API calls
+import { FileSearchError, InvalidInputError } from './errors.js';
+
+// Types
+// continue: every item is tried; fail-fast: no item is started once one failed; abortAfter:
+// none once that many failed. Items in flight still finish
+export type FailurePolicy = 'continue' | 'fail-fast' | { abortAfter: number };
+
+// What a batch did with each of its items, so callers can decide per item, e.g. retry the
+// failed ones whose error is retryable
+export interface BatchResult<T, I = string> {
+  succeeded: T[];
+  failed: BatchFailure<I>[];  // In the order they failed
+}
+
+export interface BatchFailure<I = string> {
+  input: I;
+  error: FileSearchError;  // As the item failed, with its code and cause
+}
+
+export const FAILURE_POLICIES = ['continue', 'fail-fast', 'abort-after:<n>'];
+
+// Limits the number of tasks running at once
+export class Semaphore {
+  private available: number;
+  private readonly waiters: (() => void)[] = [];
+
+  constructor(permits: number) {
+    if (!Number.isInteger(permits) || permits < 1) {
+      throw new Error(`Semaphore permits must be a positive integer, got ${permits}`);
+    }
+    this.available = permits;
+  }
+
+  async acquire(): Promise<void> {
+    if (this.available > 0) {
+      this.available--;
+      return;
+    }
+    await new Promise<void>(resolve => this.waiters.push(resolve));
+  }
+
+  release(): void {
+    const next = this.waiters.shift();
+    if (next) {
+      // Hand the permit directly to the next waiter
+      next();
+    } else {
+      this.available++;
+    }
+  }
+
+  async run<T>(task: () => Promise<T>): Promise<T> {
+    await this.acquire();
+    try {
+      return await task();
+    } finally {
+      this.release();
+    }
+  }
+}
+
+// Token bucket refilled continuously at ratePerMinute, allowing bursts up to capacity
+export class TokenBucket {
+  private tokens: number;
+  private lastRefill = Date.now();
+  private queue: Promise<void> = Promise.resolve();
+
+  constructor(
+    private readonly ratePerMinute: number,
+    private readonly capacity: number = ratePerMinute
+  ) {
+    if (!(ratePerMinute > 0)) {
+      throw new Error(`Rate must be positive, got ${ratePerMinute}`);
+    }
+    this.tokens = capacity;
+  }
+
+  // Resolves once a token is available; callers are served in order
+  take(): Promise<void> {
+    const turn = this.queue.then(() => this.waitForToken());
+    this.queue = turn;
+    return turn;
+  }
+
+  private async waitForToken(): Promise<void> {
+    this.refill();
+    if (this.tokens < 1) {
+      const waitMs = Math.ceil(((1 - this.tokens) * 60_000) / this.ratePerMinute);
+      await new Promise(resolve => setTimeout(resolve, waitMs));
+      this.refill();
+    }
+    this.tokens -= 1;
+  }
+
+  private refill(): void {
+    const now = Date.now();
+    this.tokens = Math.min(this.capacity, this.tokens + ((now - this.lastRefill) * this.ratePerMinute) / 60_000);
+    this.lastRefill = now;
+  }
+}
+
+// "continue", "fail-fast" or "abort-after:<n>"
+export function parseFailurePolicy(value: string): FailurePolicy {
+  if (value === 'continue' || value === 'fail-fast') return value;
+  const count = /^abort-after:(\d+)$/.exec(value)?.[1];
+  if (count && Number(count) >= 1) return { abortAfter: Number(count) };
+  throw new InvalidInputError(`The failure policy is one of ${FAILURE_POLICIES.join(', ')}, got "${value}"`);
+}
+
+export function formatFailurePolicy(policy: FailurePolicy): string {
+  return typeof policy === 'string' ? policy : `abort-after:${policy.abortAfter}`;
+}
+
+// Counts the failures of a run against its policy. Its signal, for runPipeline, aborts
+// once the policy stops the run or the run's own signal aborts.
+export class FailureBudget {
+  readonly signal: AbortSignal;
+  private readonly controller = new AbortController();
+  private readonly limit?: number;
+  private readonly cancel = () => this.controller.abort();
+  private failures = 0;
+  stopped?: string;  // Why the policy stopped the run, e.g. "fail-fast after 1 failure"
+
+  constructor(readonly policy: FailurePolicy = 'continue', private readonly parent?: AbortSignal) {
+    this.signal = this.controller.signal;
+    this.limit = policy === 'continue' ? undefined : policy === 'fail-fast' ? 1 : Math.max(1, Math.floor(policy.abortAfter));
+    if (parent?.aborted) this.controller.abort();
+    else parent?.addEventListener('abort', this.cancel, { once: true });
+  }
+
+  get exhausted(): boolean {
+    return this.stopped !== undefined;
+  }
+
+  // True when this failure stopped the run
+  fail(): boolean {
+    this.failures++;
+    if (this.limit === undefined || this.failures < this.limit || this.exhausted) return false;
+    this.stopped = `${formatFailurePolicy(this.policy)} after ${this.failures} ${this.failures === 1 ? 'failure' : 'failures'}`;
+    this.controller.abort();
+    return true;
+  }
+
+  dispose(): void {
+    this.parent?.removeEventListener('abort', this.cancel);
+  }
+}
+
+// Runs task for every item with at most `concurrency` in flight, optionally rate limited.
+// Results keep the order of the input. Once signal is aborted no further task is started;
+// tasks already running are awaited and the skipped items have an undefined result.
+export async function runPipeline<T, R>(
+  items: T[],
+  task: (item: T, index: number) => Promise<R>,
+  options: { concurrency: number; rateLimiter?: TokenBucket; signal?: AbortSignal }
+): Promise<(R | undefined)[]> {
+  const semaphore = new Semaphore(options.concurrency);
+  return Promise.all(items.map((item, index) => semaphore.run(async () => {
+    if (options.signal?.aborted) return undefined;
+    await options.rateLimiter?.take();
+    if (options.signal?.aborted) return undefined;
+    return task(item, index);
+  })));
+}
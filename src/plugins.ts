@@ -0,0 +1,221 @@
+// plugins.ts - External gemini-fs-<name> executables run as subcommands of gemini-fs, with the resolved context
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { spawn } from 'child_process';
+import { AuthMethod } from './auth.js';
+import { BackendConfig } from './backend.js';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export interface PluginInfo {
+  name: string;  // The subcommand, e.g. "report" for gemini-fs-report
+  path: string;
+}
+
+// Written to the file named by GEMINI_FS_PLUGIN_CONTEXT; the contract plugins rely on
+export interface PluginContext {
+  apiVersion: number;  // PLUGIN_API_VERSION; raised when a field changes meaning or goes away
+  command: string;
+  args: string[];
+  config?: string;  // The config file the settings were read from
+  profile?: string;
+  tenant?: string;
+  store?: string;  // --store of gemini-fs, or default_store
+  stateHome: string;  // Directory of the local state files, see stateHome
+  backend: BackendConfig & { apiVersion?: string };
+  model?: string;
+  auth?: { method: AuthMethod; description: string };  // Unset when no credentials were found
+}
+
+export interface PluginCredentials {
+  apiKey?: string;
+  accessToken?: string;  // OAuth token of adc and service-account credentials; short-lived
+  credentialsFile?: string;
+}
+
+export type ScaffoldLanguage = 'node' | 'sh';
+
+export const PLUGIN_PREFIX = 'gemini-fs-';
+export const PLUGIN_API_VERSION = 1;
+export const SCAFFOLD_LANGUAGES: ScaffoldLanguage[] = ['node', 'sh'];
+
+function isExecutable(filePath: string): boolean {
+  try {
+    if (!fs.statSync(filePath).isFile()) return false;
+    if (process.platform === 'win32') return true;
+    fs.accessSync(filePath, fs.constants.X_OK);
+    return true;
+  } catch (error) {
+    return false;
+  }
+}
+
+// Executables named gemini-fs-<name> on the PATH, the first of each name winning as
+// the shell would pick it. Built-in commands are left out, so a plugin cannot shadow one.
+export function findPlugins(builtins: string[], env: NodeJS.ProcessEnv = process.env): PluginInfo[] {
+  const extensions = process.platform === 'win32' ? (env.PATHEXT || '.EXE;.CMD;.BAT').toLowerCase().split(';') : [''];
+  const plugins = new Map<string, PluginInfo>();
+  for (const dir of (env.PATH || '').split(path.delimiter).filter(Boolean)) {
+    let entries: string[];
+    try {
+      entries = fs.readdirSync(dir);
+    } catch (error) {
+      continue;  // Missing or unreadable PATH entry
+    }
+    for (const entry of entries.sort()) {
+      if (!entry.startsWith(PLUGIN_PREFIX)) continue;
+      const extension = extensions.find(ext => ext && entry.toLowerCase().endsWith(ext)) || '';
+      if (process.platform === 'win32' && !extension) continue;
+      const name = entry.slice(PLUGIN_PREFIX.length, entry.length - extension.length);
+      if (!/^[a-z0-9][a-z0-9-]*$/.test(name) || builtins.includes(name) || plugins.has(name)) continue;
+      const filePath = path.join(dir, entry);
+      if (isExecutable(filePath)) plugins.set(name, { name, path: filePath });
+    }
+  }
+  return [...plugins.values()].sort((a, b) => a.name.localeCompare(b.name));
+}
+
+// The environment of a plugin: its own, with the context and the credentials the
+// built-in commands would use. The settings are plain variables so shell scripts need no JSON.
+export function pluginEnvironment(context: PluginContext, contextFile: string, credentials: PluginCredentials, env: NodeJS.ProcessEnv = process.env): NodeJS.ProcessEnv {
+  const vars: { [name: string]: string | undefined } = {
+    GEMINI_FS_PLUGIN_API: String(context.apiVersion),
+    GEMINI_FS_PLUGIN_CONTEXT: contextFile,
+    GEMINI_FS_CONFIG: context.config,
+    GEMINI_FS_PROFILE: context.profile,
+    GEMINI_FS_TENANT: context.tenant,
+    GEMINI_FS_STORE: context.store,
+    GEMINI_FS_HOME: context.stateHome,
+    GEMINI_BACKEND: context.backend.kind,
+    GOOGLE_CLOUD_PROJECT: context.backend.project,
+    GOOGLE_CLOUD_LOCATION: context.backend.location,
+    GEMINI_API_VERSION: context.backend.apiVersion,
+    GEMINI_MODEL: context.model,
+    GEMINI_FS_AUTH: context.auth?.method,
+    GEMINI_API_KEY: credentials.apiKey,
+    GEMINI_FS_ACCESS_TOKEN: credentials.accessToken,
+    GOOGLE_APPLICATION_CREDENTIALS: credentials.credentialsFile,
+  };
+  const merged: NodeJS.ProcessEnv = { ...env };
+  for (const [name, value] of Object.entries(vars)) {
+    if (value !== undefined) merged[name] = value;
+  }
+  return merged;
+}
+
+// Runs the plugin with the terminal's stdin, stdout and stderr, so it can be piped like
+// any command, and resolves to its exit code. The context file only lives while it runs.
+export async function runPlugin(plugin: PluginInfo, context: PluginContext, credentials: PluginCredentials): Promise<number> {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-fs-plugin-'));
+  const contextFile = path.join(dir, 'context.json');
+  fs.writeFileSync(contextFile, JSON.stringify(context, null, 2), { mode: 0o600 });
+  try {
+    return await new Promise<number>((resolve, reject) => {
+      const child = spawn(plugin.path, context.args, {
+        stdio: 'inherit',
+        env: pluginEnvironment(context, contextFile, credentials),
+        // .cmd and .bat files need a shell on Windows
+        shell: process.platform === 'win32' && /\.(cmd|bat)$/i.test(plugin.path),
+      });
+      child.on('error', reject);
+      child.on('exit', (code, signal) => resolve(code ?? (signal === 'SIGINT' ? 130 : 1)));
+    });
+  } finally {
+    fs.rmSync(dir, { recursive: true, force: true });
+  }
+}
+
+const NODE_TEMPLATE = `#!/usr/bin/env node
+// {{file}} - gemini-fs plugin, run as: gemini-fs {{name}} [args...]
+//
+// gemini-fs finds executables named gemini-fs-<name> on the PATH and runs them with
+// the arguments after the subcommand, the terminal's stdin/stdout/stderr, and:
+//   GEMINI_FS_PLUGIN_API      contract version ({{version}}); check it before relying on the rest
+//   GEMINI_FS_PLUGIN_CONTEXT  JSON file: command, args, config, profile, tenant, store,
+//                             stateHome, backend {kind, project, location, apiVersion}, model, auth {method}
+//   GEMINI_FS_CONFIG, GEMINI_FS_PROFILE, GEMINI_FS_TENANT, GEMINI_FS_STORE, GEMINI_FS_HOME,
+//   GEMINI_BACKEND, GOOGLE_CLOUD_PROJECT, GOOGLE_CLOUD_LOCATION, GEMINI_API_VERSION, GEMINI_MODEL
+//   GEMINI_FS_AUTH            api-key, adc or service-account
+//   GEMINI_API_KEY            with api-key
+//   GEMINI_FS_ACCESS_TOKEN    OAuth access token with adc and service-account (about an hour)
+// Exit with 0 on success; the exit code is passed on by gemini-fs.
+const fs = require('fs');
+
+const SUPPORTED_API = {{version}};
+if (Number(process.env.GEMINI_FS_PLUGIN_API) !== SUPPORTED_API) {
+  console.error(\`{{file}} supports plugin API \${SUPPORTED_API}, gemini-fs provides \${process.env.GEMINI_FS_PLUGIN_API || 'none'}\`);
+  process.exit(2);
+}
+const context = JSON.parse(fs.readFileSync(process.env.GEMINI_FS_PLUGIN_CONTEXT, 'utf8'));
+
+async function main() {
+  if (!context.store) {
+    console.error('Usage: gemini-fs --store <storeName> {{name}}   (or set default_store)');
+    process.exit(2);
+  }
+  if (context.backend.kind !== 'gemini') throw new Error('This plugin only supports the Gemini API backend');
+  const headers = process.env.GEMINI_API_KEY
+    ? { 'x-goog-api-key': process.env.GEMINI_API_KEY }
+    : { authorization: \`Bearer \${process.env.GEMINI_FS_ACCESS_TOKEN}\` };
+  const version = context.backend.apiVersion || 'v1beta';
+  const response = await fetch(\`https://generativelanguage.googleapis.com/\${version}/\${context.store}\`, { headers });
+  if (!response.ok) throw new Error(\`\${response.status} \${await response.text()}\`);
+  const store = await response.json();
+  console.log(\`\${store.displayName || store.name}: \${store.activeDocumentsCount || 0} active documents\`);
+}
+
+main().catch(error => {
+  console.error(\`Error: \${error.message}\`);
+  process.exit(1);
+});
+`;
+
+const SH_TEMPLATE = `#!/bin/sh
+# {{file}} - gemini-fs plugin, run as: gemini-fs {{name}} [args...]
+#
+# gemini-fs finds executables named gemini-fs-<name> on the PATH and runs them with
+# the arguments after the subcommand, the terminal's stdin/stdout/stderr, and:
+#   GEMINI_FS_PLUGIN_API      contract version ({{version}}); check it before relying on the rest
+#   GEMINI_FS_PLUGIN_CONTEXT  JSON file with all of the values below, and the arguments
+#   GEMINI_FS_CONFIG, GEMINI_FS_PROFILE, GEMINI_FS_TENANT, GEMINI_FS_STORE, GEMINI_FS_HOME,
+#   GEMINI_BACKEND, GOOGLE_CLOUD_PROJECT, GOOGLE_CLOUD_LOCATION, GEMINI_API_VERSION, GEMINI_MODEL
+#   GEMINI_FS_AUTH            api-key, adc or service-account
+#   GEMINI_API_KEY            with api-key
+#   GEMINI_FS_ACCESS_TOKEN    OAuth access token with adc and service-account (about an hour)
+# Exit with 0 on success; the exit code is passed on by gemini-fs.
+set -eu
+
+if [ "\${GEMINI_FS_PLUGIN_API:-}" != "{{version}}" ]; then
+  echo "{{file}} supports plugin API {{version}}, gemini-fs provides \${GEMINI_FS_PLUGIN_API:-none}" >&2
+  exit 2
+fi
+if [ -z "\${GEMINI_FS_STORE:-}" ]; then
+  echo "Usage: gemini-fs --store <storeName> {{name}}   (or set default_store)" >&2
+  exit 2
+fi
+
+if [ -n "\${GEMINI_API_KEY:-}" ]; then
+  auth="x-goog-api-key: $GEMINI_API_KEY"
+else
+  auth="Authorization: Bearer $GEMINI_FS_ACCESS_TOKEN"
+fi
+curl -sSf -H "$auth" "https://generativelanguage.googleapis.com/\${GEMINI_API_VERSION:-v1beta}/$GEMINI_FS_STORE"
+echo
+`;
+
+// A working plugin to start from, with the contract in its header: it prints the
+// document count of the selected store. Refuses to overwrite an existing file.
+export function scaffoldPlugin(name: string, dir: string, language: ScaffoldLanguage = 'node'): string {
+  if (!/^[a-z0-9][a-z0-9-]*$/.test(name)) {
+    throw new InvalidInputError(`Plugin names are lowercase letters, digits and dashes, got "${name}"`);
+  }
+  const file = `${PLUGIN_PREFIX}${name}`;
+  const filePath = path.join(dir, file);
+  if (fs.existsSync(filePath)) throw new InvalidInputError(`${filePath} already exists`);
+  const template = language === 'sh' ? SH_TEMPLATE : NODE_TEMPLATE;
+  fs.mkdirSync(dir, { recursive: true });
+  fs.writeFileSync(filePath, template.replace(/\{\{(\w+)\}\}/g, (_match, key: string) =>
+    key === 'file' ? file : key === 'name' ? name : String(PLUGIN_API_VERSION)), { mode: 0o755 });
+  return filePath;
+}
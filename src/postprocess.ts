@@ -0,0 +1,116 @@
+// postprocess.ts - Named answer transforms applied before an answer is formatted
+import { FileSearchClient } from './client.js';
+import { InvalidInputError } from './errors.js';
+import { AnswerReport } from './output.js';
+
+// Types
+export interface TransformContext {
+  question: string;
+  client?: FileSearchClient;  // For transforms that call the model, such as translations
+  model?: string;
+}
+
+export interface AnswerTransform {
+  readonly name: string;  // Selected with --post <name>
+  readonly description: string;
+  apply(answer: string, context: TransformContext): string | Promise<string>;
+}
+
+// Plain text: headings, emphasis, links, quotes and code fences are reduced to their text
+export class StripMarkdownTransform implements AnswerTransform {
+  readonly name = 'strip-markdown';
+  readonly description = 'Remove Markdown formatting and keep the text';
+
+  apply(answer: string): string {
+    return answer
+      .replace(/^(`{3,}|~{3,})[^\n]*\n?/gm, '')
+      .replace(/!\[([^\]]*)\]\([^)]*\)/g, '$1')
+      .replace(/\[([^\]]+)\]\(([^)]*)\)/g, '$1 ($2)')
+      .replace(/^#{1,6}\s+/gm, '')
+      .replace(/^>\s?/gm, '')
+      .replace(/^(\s*)[*+]\s+/gm, '$1- ')
+      .replace(/(\*\*|__)(.+?)\1/g, '$2')
+      .replace(/(^|[^\w*])\*(?!\s)([^*\n]+?)\*(?!\w)/g, '$1$2')
+      .replace(/`([^`\n]+)`/g, '$1')
+      .replace(/^\s*([-*_])(\s*\1){2,}\s*$/gm, '')
+      .replace(/\n{3,}/g, '\n\n')
+      .trim();
+  }
+}
+
+// Only the contents of the fenced code blocks, separated by blank lines
+export class CodeBlocksTransform implements AnswerTransform {
+  readonly name = 'code-blocks';
+  readonly description = 'Keep only the contents of fenced code blocks';
+
+  apply(answer: string): string {
+    const blocks = [...answer.matchAll(/^(`{3,}|~{3,})[^\n]*\n([\s\S]*?)^\1[ \t]*$/gm)].map(match => match[2].replace(/\n+$/, ''));
+    if (blocks.length === 0) console.error('⚠️  The answer has no code blocks');
+    return blocks.join('\n\n');
+  }
+}
+
+// Asks the model for a translation that leaves code, file names and footnote markers alone
+export class TranslateTransform implements AnswerTransform {
+  readonly name: string;
+  readonly description: string;
+
+  constructor(readonly languageCode: string, readonly language: string) {
+    this.name = `translate-${languageCode}`;
+    this.description = `Translate the answer to ${language}`;
+  }
+
+  async apply(answer: string, context: TransformContext): Promise<string> {
+    if (!context.client) throw new InvalidInputError(`${this.name} needs a client to call the model`);
+    if (!answer.trim()) return answer;
+    return context.client.generate(answer, {
+      model: context.model,
+      systemInstruction: `Translate the user's text to ${this.language}. Keep the Markdown formatting, code blocks, identifiers, file names and footnote markers such as [1] unchanged. Reply with the translation only.`,
+    });
+  }
+}
+
+const transforms = new Map<string, AnswerTransform>();
+
+// Adds a transform, or replaces the one registered under the same name
+export function registerTransform(transform: AnswerTransform): void {
+  transforms.set(transform.name, transform);
+}
+
+registerTransform(new StripMarkdownTransform());
+registerTransform(new CodeBlocksTransform());
+registerTransform(new TranslateTransform('ja', 'Japanese'));
+registerTransform(new TranslateTransform('en', 'English'));
+
+export function listTransforms(): AnswerTransform[] {
+  return [...transforms.values()];
+}
+
+export function resolveTransforms(names: string[]): AnswerTransform[] {
+  return names.map(name => {
+    const transform = transforms.get(name);
+    if (!transform) {
+      throw new InvalidInputError(`Unknown answer transform "${name}" (expected one of: ${[...transforms.keys()].join(', ')})`);
+    }
+    return transform;
+  });
+}
+
+// Runs the transforms in order over the answer and each single answer of a multi-question
+// report. Grounding offsets point into the original text, so they are dropped: citations
+// stay as a list, without footnote markers placed in the answer.
+export async function applyTransforms(report: AnswerReport, chain: AnswerTransform[], context: TransformContext): Promise<AnswerReport> {
+  if (chain.length === 0) return report;
+  const run = async (text: string): Promise<string> => {
+    let result = text;
+    for (const transform of chain) result = await transform.apply(result, context);
+    return result;
+  };
+  return {
+    ...report,
+    answer: await run(report.answer),
+    answers: report.answers && await Promise.all(report.answers.map(async single => ({ ...single, answer: await run(single.answer) }))),
+    citations: report.citations.map(citation => ({ ...citation, startIndex: undefined, endIndex: undefined })),
+    groundingMetadata: undefined,
+  };
+}
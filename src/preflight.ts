@@ -0,0 +1,163 @@
+// preflight.ts - Local checks against the File Search limits before anything is uploaded
+import * as fs from 'fs';
+import { MAX_UPLOAD_SIZE } from './client.js';
+import { resolvePathOf } from './expand.js';
+import { PlannedFile } from './ingest.js';
+import { formatBytes } from './progress.js';
+
+// Types
+export type PreflightMode = 'skip' | 'strict' | 'off';
+export type PreflightRule = 'size' | 'mime' | 'pages' | 'documents';
+
+export interface PreflightLimits {
+  maxFileSize: number;  // Bytes per file
+  maxPdfPages: number;  // Pages per PDF uploaded as is
+  maxStoreDocuments: number;  // Documents in the store once the upload is done
+}
+
+export interface PreflightViolation {
+  path: string;  // Relative path; the store name for the documents rule
+  rule: PreflightRule;
+  message: string;  // What is wrong and how to fix it
+}
+
+export interface PreflightReport {
+  checkedFiles: number;
+  projectedDocuments: number;  // Documents in the store after the upload
+  violations: PreflightViolation[];
+  rejected: string[];  // Relative paths with at least one file violation
+}
+
+// skip: leave the files that would be rejected out of the upload and report them;
+// strict: refuse the whole upload; off: let the API decide file by file
+export const PREFLIGHT_MODES: PreflightMode[] = ['skip', 'strict', 'off'];
+
+export const DEFAULT_MAX_PDF_PAGES = 1000;
+export const DEFAULT_MAX_STORE_DOCUMENTS = 10000;
+
+export const DEFAULT_PREFLIGHT_LIMITS: PreflightLimits = {
+  maxFileSize: MAX_UPLOAD_SIZE,
+  maxPdfPages: DEFAULT_MAX_PDF_PAGES,
+  maxStoreDocuments: DEFAULT_MAX_STORE_DOCUMENTS,
+};
+
+// Accepted besides every text/* type
+export const SUPPORTED_MIME_TYPES: string[] = [
+  'application/pdf',
+  'application/json',
+  'application/xml',
+  'application/sql',
+  'application/x-yaml',
+  'application/rtf',
+  'application/msword',
+  'application/vnd.ms-excel',
+  'application/vnd.ms-powerpoint',
+  'application/vnd.oasis.opendocument.text',
+  'application/vnd.openxmlformats-officedocument.wordprocessingml.document',
+  'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+  'application/vnd.openxmlformats-officedocument.presentationml.presentation',
+];
+
+const MAX_LISTED_VIOLATIONS = 20;
+
+export function isSupportedMimeType(mimeType: string): boolean {
+  return mimeType.startsWith('text/') || SUPPORTED_MIME_TYPES.includes(mimeType);
+}
+
+// Counts page objects, falling back to the largest /Count of a page tree node when
+// the page objects sit in compressed object streams; undefined when neither is found
+export function countPdfPages(filePath: string): number | undefined {
+  const content = fs.readFileSync(filePath).toString('latin1');
+  const pages = content.match(/\/Type\s*\/Page(?![a-zA-Z])/g)?.length || 0;
+  let treeCount = 0;
+  for (const match of content.matchAll(/\/Type\s*\/Pages\b[^>]*?\/Count\s+(\d+)|\/Count\s+(\d+)[^>]*?\/Type\s*\/Pages\b/g)) {
+    treeCount = Math.max(treeCount, Number(match[1] || match[2]));
+  }
+  const count = Math.max(pages, treeCount);
+  return count > 0 ? count : undefined;
+}
+
+function checkFile(rootDir: string, file: PlannedFile, limits: PreflightLimits): PreflightViolation[] {
+  const violations: PreflightViolation[] = [];
+  // Text over the limit is uploaded in parts
+  if (file.size > limits.maxFileSize && !file.parts) {
+    violations.push({
+      path: file.path,
+      rule: 'size',
+      message: `${formatBytes(file.size)} exceeds the ${formatBytes(limits.maxFileSize)} per-file limit; only text is split into parts, so enable extract_locally, split the file or add it to exclude`,
+    });
+  }
+  if (!isSupportedMimeType(file.mimeType)) {
+    violations.push({
+      path: file.path,
+      rule: 'mime',
+      message: `${file.mimeType || 'unknown type'} is not supported; map the extension to text/plain in the MIME map or add it to exclude`,
+    });
+  }
+  if (file.mimeType === 'application/pdf' && !file.extractedFrom) {
+    let pages: number | undefined;
+    try {
+      pages = countPdfPages(resolvePathOf(rootDir, file.path));
+    } catch (error) {
+      // Unreadable files are reported by the upload
+    }
+    if (pages !== undefined && pages > limits.maxPdfPages) {
+      violations.push({
+        path: file.path,
+        rule: 'pages',
+        message: `${pages} pages exceeds the ${limits.maxPdfPages}-page limit; split the PDF or enable extract_locally to upload its text`,
+      });
+    }
+  }
+  return violations;
+}
+
+// Checks every planned file and the store total in one pass, so all problems are
+// reported together. Files skipped for other reasons (empty, already indexed,
+// duplicates) are not uploaded and only count when they are too large.
+export function runPreflight(
+  rootDir: string,
+  storeName: string | undefined,
+  files: PlannedFile[],
+  storeDocuments: number,
+  limits: Partial<PreflightLimits> = {}
+): PreflightReport {
+  const resolved = { ...DEFAULT_PREFLIGHT_LIMITS, ...limits };
+  const candidates = files.filter(file => !file.skipped || file.size > resolved.maxFileSize);
+  const violations: PreflightViolation[] = [];
+  const rejected: string[] = [];
+  let projectedDocuments = storeDocuments;
+  for (const file of candidates) {
+    const fileViolations = checkFile(rootDir, file, resolved);
+    if (fileViolations.length > 0) {
+      violations.push(...fileViolations);
+      rejected.push(file.path);
+    } else {
+      projectedDocuments += file.documents;
+    }
+  }
+
+  if (projectedDocuments > resolved.maxStoreDocuments) {
+    violations.push({
+      path: storeName || 'new store',
+      rule: 'documents',
+      message: `${projectedDocuments} documents after this upload exceeds the ${resolved.maxStoreDocuments}-document store limit; narrow include, split the project into several stores or raise max_store_documents`,
+    });
+  }
+  return { checkedFiles: candidates.length, projectedDocuments, violations, rejected };
+}
+
+export function hasStoreViolation(report: PreflightReport): boolean {
+  return report.violations.some(violation => violation.rule === 'documents');
+}
+
+export function formatPreflightReport(report: PreflightReport): string {
+  if (report.violations.length === 0) {
+    return `Preflight: ${report.checkedFiles} files within limits`;
+  }
+  const lines = report.violations.slice(0, MAX_LISTED_VIOLATIONS).map(violation => `- ${violation.path}: ${violation.message}`);
+  if (report.violations.length > MAX_LISTED_VIOLATIONS) {
+    lines.push(`- ... and ${report.violations.length - MAX_LISTED_VIOLATIONS} more`);
+  }
+  return `Preflight: ${report.violations.length} problem(s) in ${report.checkedFiles} files\n${lines.join('\n')}`;
+}
@@ -0,0 +1,233 @@
+// progress.ts - Terminal progress bars for uploads and end-of-run ingestion reports
+import { format } from 'util';
+import { DedupMode } from './dedup.js';
+import { IngestFileResult } from './ci-report.js';
+import { ErrorCode } from './errors.js';
+
+// Types
+export type FileState = 'queued' | 'uploading' | 'indexing' | 'done' | 'cached' | 'failed';
+
+export type ReportFormat = 'table' | 'json';
+
+export interface IngestFailure {
+  path: string;  // Relative path
+  reason: string;
+  code?: ErrorCode;  // Absent for files skipped before upload
+  retries: number;
+}
+
+export interface IngestDuplicate {
+  path: string;  // Relative path
+  of: string;  // Display name of the copy that is kept
+  exact: boolean;
+  action: DedupMode;
+}
+
+export interface IngestReport {
+  store: string;
+  totalFiles: number;
+  successCount: number;
+  cachedCount: number;
+  errorCount: number;
+  duplicateCount: number;
+  uploadedBytes: number;
+  retries: number;
+  durationMs: number;
+  failures: IngestFailure[];
+  duplicates: IngestDuplicate[];
+  remaining?: string[];  // Not uploaded because the run was cancelled
+  files?: IngestFileResult[];  // Per-file status and duration
+  stopped?: string;  // Why the failure policy cancelled the remaining files
+}
+
+interface FileProgress {
+  path: string;
+  size: number;
+  state: FileState;
+  retries: number;
+}
+
+export const REPORT_FORMATS: ReportFormat[] = ['table', 'json'];
+
+// Files in flight shown below the overall bar; the rest are summarized in one line
+const MAX_FILE_LINES = 8;
+const BAR_WIDTH = 30;
+const RENDER_INTERVAL_MS = 100;
+
+// Fields of the summaries a report is made from that hold live errors and upload results
+const BATCH_FIELDS = ['succeeded', 'failed'];
+
+const STATE_ICONS: { [state in FileState]: string } = {
+  queued: '·',
+  uploading: '⬆',
+  indexing: '⚙',
+  done: '✔',
+  cached: '✔',
+  failed: '✖',
+};
+
+export function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  if (bytes < 1024 * 1024 * 1024) return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  return `${(bytes / (1024 * 1024 * 1024)).toFixed(2)} GB`;
+}
+
+const DUPLICATE_ACTIONS: { [action in DedupMode]: string } = {
+  skip: 'skipped, duplicate of',
+  alias: 'linked as alias of',
+  replace: 'replaced',
+};
+
+function formatDuration(ms: number): string {
+  const seconds = Math.round(ms / 1000);
+  return seconds < 60 ? `${seconds}s` : `${Math.floor(seconds / 60)}m${String(seconds % 60).padStart(2, '0')}s`;
+}
+
+// Keeps the end of paths, which is the part that tells files apart
+function truncate(text: string, width: number): string {
+  if (text.length <= width) return text;
+  return width > 1 ? `…${text.slice(text.length - width + 1)}` : text.slice(0, width);
+}
+
+// Multi-line progress display redrawn in place: one bar for overall bytes and one
+// line per file in flight. While it is active, console.error output is printed
+// above the bars instead of breaking them.
+export class ProgressBars {
+  private readonly files = new Map<string, FileProgress>();
+  private readonly startedAt = Date.now();
+  private totalBytes = 0;
+  private doneBytes = 0;
+  private renderedLines = 0;
+  private timer?: NodeJS.Timeout;
+  private originalConsoleError?: typeof console.error;
+
+  constructor(private readonly stream: NodeJS.WriteStream = process.stderr) {}
+
+  // Bars need cursor movement, so they are only drawn on an interactive terminal
+  static supported(stream: NodeJS.WriteStream = process.stderr): boolean {
+    return Boolean(stream.isTTY) && !process.env.CI && process.env.TERM !== 'dumb';
+  }
+
+  start(files: { path: string; size: number }[]): void {
+    for (const file of files) {
+      this.files.set(file.path, { ...file, state: 'queued', retries: 0 });
+      this.totalBytes += file.size;
+    }
+    this.originalConsoleError = console.error;
+    console.error = (...args: unknown[]) => this.println(format(...args));
+    this.render();
+  }
+
+  update(filePath: string, state: FileState): void {
+    const file = this.files.get(filePath);
+    if (!file) return;
+    if ((state === 'done' || state === 'cached' || state === 'failed') && !this.isFinished(file)) {
+      this.doneBytes += file.size;
+    }
+    file.state = state;
+    this.scheduleRender();
+  }
+
+  retry(filePath: string): void {
+    const file = this.files.get(filePath);
+    if (!file) return;
+    file.retries++;
+    this.scheduleRender();
+  }
+
+  finish(): void {
+    if (this.timer) clearTimeout(this.timer);
+    this.timer = undefined;
+    this.render();
+    this.renderedLines = 0;
+    if (this.originalConsoleError) console.error = this.originalConsoleError;
+    this.originalConsoleError = undefined;
+  }
+
+  println(line: string): void {
+    this.stream.write(`${this.clearSequence()}${line}\n`);
+    this.renderedLines = 0;
+    this.render();
+  }
+
+  private isFinished(file: FileProgress): boolean {
+    return file.state === 'done' || file.state === 'cached' || file.state === 'failed';
+  }
+
+  private scheduleRender(): void {
+    if (this.timer) return;
+    this.timer = setTimeout(() => {
+      this.timer = undefined;
+      this.render();
+    }, RENDER_INTERVAL_MS);
+    this.timer.unref();
+  }
+
+  private clearSequence(): string {
+    return this.renderedLines > 0 ? `\x1b[${this.renderedLines}F\x1b[J` : '';
+  }
+
+  private render(): void {
+    const width = this.stream.columns || 80;
+    const files = [...this.files.values()];
+    const finished = files.filter(file => this.isFinished(file)).length;
+    const failed = files.filter(file => file.state === 'failed').length;
+    const active = files.filter(file => file.state === 'uploading' || file.state === 'indexing');
+
+    const ratio = this.totalBytes > 0 ? this.doneBytes / this.totalBytes : files.length > 0 ? finished / files.length : 1;
+    const filled = Math.round(ratio * BAR_WIDTH);
+    const elapsed = Date.now() - this.startedAt;
+    const eta = ratio > 0 && ratio < 1 ? ` · ETA ${formatDuration((elapsed / ratio) * (1 - ratio))}` : '';
+    const header = `[${'█'.repeat(filled)}${'░'.repeat(BAR_WIDTH - filled)}] ${Math.floor(ratio * 100)}% ` +
+      `${formatBytes(this.doneBytes)}/${formatBytes(this.totalBytes)} · ${finished}/${files.length} files` +
+      `${failed > 0 ? ` · ${failed} failed` : ''} · ${formatDuration(elapsed)}${eta}`;
+    const lines = [header.slice(0, width)];
+    for (const file of active.slice(0, MAX_FILE_LINES)) {
+      const detail = `${file.state} ${formatBytes(file.size)}${file.retries > 0 ? `, retry ${file.retries}` : ''}`;
+      lines.push(`  ${STATE_ICONS[file.state]} ${truncate(file.path, Math.max(10, width - detail.length - 8))} (${detail})`);
+    }
+    if (active.length > MAX_FILE_LINES) {
+      lines.push(`  … ${active.length - MAX_FILE_LINES} more in flight`);
+    }
+
+    this.stream.write(`${this.clearSequence()}${lines.join('\n')}\n`);
+    this.renderedLines = lines.length;
+  }
+}
+
+export function formatIngestReport(report: IngestReport, reportFormat: ReportFormat = 'table'): string {
+  if (reportFormat === 'json') {
+    return JSON.stringify(report, (key, value) => BATCH_FIELDS.includes(key) ? undefined : value, 2);
+  }
+
+  const lines = [
+    `📊 Ingestion report for ${report.store}: ${report.totalFiles} files in ${(report.durationMs / 1000).toFixed(1)}s`,
+    `   Succeeded: ${report.successCount} (${report.cachedCount} already indexed)`,
+    `   Failed:    ${report.errorCount}`,
+    `   Deduped:   ${report.duplicateCount}`,
+    `   Uploaded:  ${formatBytes(report.uploadedBytes)}`,
+    `   Retries:   ${report.retries}`,
+  ];
+  if (report.remaining && report.remaining.length > 0) {
+    lines.splice(3, 0, `   Cancelled: ${report.remaining.length} (${report.stopped ? `stopped by ${report.stopped}; ` : ''}left for the next run)`);
+  }
+  if (report.failures.length > 0) {
+    const reasons = report.failures.map(failure => failure.code ? `${failure.code}: ${failure.reason}` : failure.reason);
+    const pathWidth = Math.min(60, Math.max(4, ...report.failures.map(failure => failure.path.length)));
+    const reasonWidth = Math.min(60, Math.max(6, ...reasons.map(reason => reason.length)));
+    const row = (file: string, reason: string, retries: string) =>
+      `   ${truncate(file, pathWidth).padEnd(pathWidth)}  ${truncate(reason, reasonWidth).padEnd(reasonWidth)}  ${retries}`;
+    lines.push('', row('File', 'Reason', 'Retries'), row('-'.repeat(pathWidth), '-'.repeat(reasonWidth), '-------'));
+    for (const [index, failure] of report.failures.entries()) {
+      lines.push(row(failure.path, reasons[index], String(failure.retries)));
+    }
+  }
+  if (report.duplicates.length > 0) {
+    lines.push('');
+    for (const duplicate of report.duplicates) {
+      lines.push(`   ${duplicate.path}: ${DUPLICATE_ACTIONS[duplicate.action]} ${duplicate.of} (${duplicate.exact ? 'exact copy' : 'near-duplicate'})`);
+    }
+  }
+  return lines.join('\n');
+}
@@ -0,0 +1,146 @@
+// prompts.ts - Stored system instructions and answer-style presets, per profile and per store
+import * as fs from 'fs';
+import * as path from 'path';
+import { ConfigError, InvalidInputError } from './errors.js';
+import { AnswerLanguage, answerLanguageInstruction, parseAnswerLanguage } from './answer-language.js';
+import { TomlTable, TomlValue, expectTable, isTable } from './toml.js';
+
+// Types
+export type CitationFormat = 'footnotes' | 'inline' | 'none';
+
+export interface PromptPreset {
+  system?: string;  // System instruction; system_file is read into it
+  tone?: string;  // e.g. "concise and formal"
+  language?: string;  // Language of the answers, e.g. "Japanese"; a code such as "ja" is read as its name
+  citationFormat?: CitationFormat;
+}
+
+export interface PromptRule extends PromptPreset {
+  store: string;  // Store resource name
+}
+
+export interface PromptConfig extends PromptPreset {
+  stores: PromptRule[];  // [[prompt.stores]]; the values of a store replace the ones above
+  translateSnippets?: boolean;  // Also translate the cited chunks to the answer language
+}
+
+// footnotes: the numbered citations the tools print below the answer (default);
+// inline: the model also names the source file after each statement; none: it names none
+export const CITATION_FORMATS: CitationFormat[] = ['footnotes', 'inline', 'none'];
+
+export const DEFAULT_PROMPT_CONFIG: PromptConfig = { stores: [] };
+
+const CITATION_INSTRUCTIONS: { [format in CitationFormat]?: string } = {
+  inline: 'After each statement, name the file it comes from in square brackets, e.g. [guide.md].',
+  none: 'Do not mention file names or sources in the answer.',
+};
+
+function readFile(filePath: string, context: string): string {
+  try {
+    return fs.readFileSync(filePath, 'utf8').trim();
+  } catch (error) {
+    throw new ConfigError(`${context}: cannot read ${filePath}: ${(error as Error).message}`);
+  }
+}
+
+// system_file is resolved against the config file's directory and wins over system
+function parsePreset(table: TomlTable, context: string, baseDir: string): PromptPreset {
+  const preset: PromptPreset = {};
+  for (const key of ['system', 'system_file', 'tone', 'language', 'citation_format']) {
+    const value = table[key];
+    if (value !== undefined && (typeof value !== 'string' || !value.trim())) {
+      throw new ConfigError(`${context}.${key} must be a non-empty string`);
+    }
+  }
+  if (typeof table.system === 'string') preset.system = table.system.trim();
+  if (typeof table.system_file === 'string') {
+    preset.system = readFile(path.resolve(baseDir, table.system_file), `${context}.system_file`);
+  }
+  if (typeof table.tone === 'string') preset.tone = table.tone.trim();
+  if (typeof table.language === 'string') {
+    try {
+      preset.language = parseAnswerLanguage(table.language).name;
+    } catch (error) {
+      throw new ConfigError(`${context}.language: ${(error as Error).message}`);
+    }
+  }
+  if (typeof table.citation_format === 'string') {
+    if (!CITATION_FORMATS.includes(table.citation_format as CitationFormat)) {
+      throw new ConfigError(`${context}.citation_format must be one of: ${CITATION_FORMATS.join(', ')}`);
+    }
+    preset.citationFormat = table.citation_format as CitationFormat;
+  }
+  return preset;
+}
+
+export function parsePromptConfig(value: TomlValue | undefined, baseDir: string): PromptConfig {
+  if (value === undefined) return { ...DEFAULT_PROMPT_CONFIG, stores: [] };
+  expectTable(value, 'prompt');
+
+  const stores: PromptRule[] = [];
+  const rawStores = value.stores ?? [];
+  if (!Array.isArray(rawStores)) throw new ConfigError('prompt.stores must be [[prompt.stores]] entries');
+  for (const [index, rule] of rawStores.entries()) {
+    if (!isTable(rule) || typeof rule.store !== 'string' || !rule.store) {
+      throw new ConfigError(`prompt.stores[${index}]: store must be a non-empty string`);
+    }
+    stores.push({ store: rule.store, ...parsePreset(rule, `prompt.stores[${index}]`, baseDir) });
+  }
+  if (value.translate_snippets !== undefined && typeof value.translate_snippets !== 'boolean') {
+    throw new ConfigError('prompt.translate_snippets must be true or false');
+  }
+  return { ...parsePreset(value, 'prompt', baseDir), stores, translateSnippets: value.translate_snippets };
+}
+
+// The settings of the first searched store with an entry, over the profile's own
+export function promptPreset(config: PromptConfig, storeNames: string[]): PromptPreset {
+  const rule = storeNames.map(storeName => config.stores.find(entry => entry.store === storeName)).find(entry => entry);
+  return {
+    system: rule?.system ?? config.system,
+    tone: rule?.tone ?? config.tone,
+    language: rule?.language ?? config.language,
+    citationFormat: rule?.citationFormat ?? config.citationFormat,
+  };
+}
+
+export function presetInstruction(preset: PromptPreset): string | undefined {
+  const lines = [
+    preset.system,
+    preset.tone ? `Answer in a ${preset.tone} tone.` : undefined,
+    preset.language ? answerLanguageInstruction({ name: preset.language }) : undefined,
+    preset.citationFormat ? CITATION_INSTRUCTIONS[preset.citationFormat] : undefined,
+  ].filter(line => line);
+  return lines.length > 0 ? lines.join('\n\n') : undefined;
+}
+
+// The system instruction of a query: --system replaces the stored one, and the tone,
+// language and citation format of the preset still apply
+export function resolveSystemInstruction(config: PromptConfig, storeNames: string[], system?: string): string | undefined {
+  const preset = promptPreset(config, storeNames);
+  return presetInstruction(system !== undefined ? { ...preset, system } : preset);
+}
+
+// --answer-lang: the language applies to every store, over the ones of [[prompt.stores]]
+export function withAnswerLanguage(config: PromptConfig, language: AnswerLanguage): PromptConfig {
+  return {
+    ...config,
+    language: language.name,
+    stores: config.stores.map(({ language: _language, ...rule }) => rule),
+  };
+}
+
+// The language answers over these stores are requested in, if any
+export function answerLanguageOf(config: PromptConfig, storeNames: string[]): AnswerLanguage | undefined {
+  const language = promptPreset(config, storeNames).language;
+  return language ? parseAnswerLanguage(language) : undefined;
+}
+
+// --system takes the instruction itself, or @<file> to read it from a file
+export function readSystemOption(value: string): string {
+  if (!value.startsWith('@')) return value;
+  try {
+    return fs.readFileSync(value.slice(1), 'utf8').trim();
+  } catch (error) {
+    throw new InvalidInputError(`--system: cannot read ${value.slice(1)}: ${(error as Error).message}`);
+  }
+}
@@ -0,0 +1,260 @@
+// provenance.ts - Answer provenance as a graph of question, answer segments, chunks and documents
+import * as fs from 'fs';
+import * as path from 'path';
+import { GroundingMetadata } from '@google/genai';
+import { JsonSchema } from './schema.js';
+import { findChunk, lineOf } from './cite-context.js';
+
+// Types
+export type ProvenanceNodeKind = 'question' | 'answer' | 'segment' | 'chunk' | 'document';
+
+// question -answered_by-> answer -contains-> segment -cites-> chunk -part_of-> document
+export type ProvenanceEdgeKind = 'answered_by' | 'contains' | 'cites' | 'part_of';
+
+export interface ProvenanceNode {
+  id: string;  // "question", "answer", "segment:1", "chunk:1", "document:<title>"
+  kind: ProvenanceNodeKind;
+  text?: string;  // question, answer, segment and chunk
+  model?: string;  // answer
+  confidence?: number;  // answer: aggregate score, see answerConfidence
+  start?: number;  // segment: character range in the answer, end exclusive
+  end?: number;
+  startByte?: number;  // segment: UTF-8 byte range, as the API reports it
+  endByte?: number;
+  footnote?: number;  // chunk: its [n] marker in the answer
+  cited?: boolean;  // chunk: false for chunks retrieved but cited by no segment
+  pages?: [number, number];  // chunk: first and last page, Vertex AI only
+  sourceStart?: number;  // chunk: character range in sourcePath, when the file was found under sourceRoot
+  sourceEnd?: number;
+  startLine?: number;  // chunk: 1-based lines of that range
+  endLine?: number;
+  title?: string;  // document: display name, e.g. "src/main.rs#fn main"
+  uri?: string;  // document
+  sourcePath?: string;  // document: path it was uploaded from, the title without its "#part"
+  part?: string;  // document: symbol or rows of a file uploaded in parts
+}
+
+export interface ProvenanceEdge {
+  from: string;
+  to: string;
+  kind: ProvenanceEdgeKind;
+  confidence?: number;  // cites: grounding confidence of the segment for this chunk
+}
+
+export interface ProvenanceGraph {
+  schema: typeof PROVENANCE_SCHEMA_ID;
+  generatedAt: string;  // ISO 8601
+  stores: string[];
+  nodes: ProvenanceNode[];
+  edges: ProvenanceEdge[];
+}
+
+export interface ProvenanceOptions {
+  model?: string;
+  stores?: string[];
+  confidence?: number;
+  sourceRoot?: string;  // Directory the documents were ingested from, to locate chunks in their files
+}
+
+export const PROVENANCE_SCHEMA_ID = 'gemini-fs/provenance/v1';
+
+const NODE_KINDS: ProvenanceNodeKind[] = ['question', 'answer', 'segment', 'chunk', 'document'];
+const EDGE_KINDS: ProvenanceEdgeKind[] = ['answered_by', 'contains', 'cites', 'part_of'];
+const DOT_EXTENSIONS = ['.dot', '.gv'];
+const DOT_LABEL_LENGTH = 60;
+
+// The JSON Schema of ProvenanceGraph, for validating exports in downstream tooling
+export const PROVENANCE_JSON_SCHEMA: JsonSchema = {
+  type: 'object',
+  required: ['schema', 'generatedAt', 'stores', 'nodes', 'edges'],
+  properties: {
+    schema: { const: PROVENANCE_SCHEMA_ID },
+    generatedAt: { type: 'string' },
+    stores: { type: 'array', items: { type: 'string' } },
+    nodes: {
+      type: 'array',
+      items: {
+        type: 'object',
+        required: ['id', 'kind'],
+        properties: {
+          id: { type: 'string' },
+          kind: { enum: NODE_KINDS },
+          text: { type: 'string' },
+          model: { type: 'string' },
+          confidence: { type: 'number', minimum: 0, maximum: 1 },
+          start: { type: 'integer', minimum: 0 },
+          end: { type: 'integer', minimum: 0 },
+          startByte: { type: 'integer', minimum: 0 },
+          endByte: { type: 'integer', minimum: 0 },
+          footnote: { type: 'integer', minimum: 1 },
+          cited: { type: 'boolean' },
+          pages: { type: 'array', items: { type: 'integer', minimum: 1 }, minItems: 2, maxItems: 2 },
+          sourceStart: { type: 'integer', minimum: 0 },
+          sourceEnd: { type: 'integer', minimum: 0 },
+          startLine: { type: 'integer', minimum: 1 },
+          endLine: { type: 'integer', minimum: 1 },
+          title: { type: 'string' },
+          uri: { type: 'string' },
+          sourcePath: { type: 'string' },
+          part: { type: 'string' },
+        },
+      },
+    },
+    edges: {
+      type: 'array',
+      items: {
+        type: 'object',
+        required: ['from', 'to', 'kind'],
+        properties: {
+          from: { type: 'string' },
+          to: { type: 'string' },
+          kind: { enum: EDGE_KINDS },
+          confidence: { type: 'number', minimum: 0, maximum: 1 },
+        },
+      },
+    },
+  },
+};
+
+// Segment indices are UTF-8 byte offsets
+function toCharOffset(encoded: Buffer, byteOffset: number): number {
+  return encoded.subarray(0, byteOffset).toString('utf8').length;
+}
+
+// Where the chunk text sits in its source file, see findChunk
+function locateChunk(sourceRoot: string, sourcePath: string, chunkText: string): Partial<ProvenanceNode> {
+  const filePath = path.resolve(sourceRoot, sourcePath);
+  const relative = path.relative(sourceRoot, filePath);
+  if (!chunkText || relative.startsWith('..') || path.isAbsolute(relative)) return {};
+  let content: string;
+  try {
+    content = fs.readFileSync(filePath, 'utf8');
+  } catch (error) {
+    return {};
+  }
+  const found = findChunk(content, chunkText);
+  if (!found) return {};
+  const { start, length } = found;
+  return { sourceStart: start, sourceEnd: start + length, startLine: lineOf(content, start), endLine: lineOf(content, start + length) };
+}
+
+// Every grounding chunk is a node, cited or not; segments are the answer ranges of the
+// grounding supports, merged when several supports cover the same range
+export function buildProvenanceGraph(
+  question: string,
+  answer: string,
+  metadata: GroundingMetadata | undefined,
+  options: ProvenanceOptions = {}
+): ProvenanceGraph {
+  const nodes: ProvenanceNode[] = [
+    { id: 'question', kind: 'question', text: question },
+    { id: 'answer', kind: 'answer', text: answer, model: options.model, confidence: options.confidence },
+  ];
+  const edges: ProvenanceEdge[] = [{ from: 'question', to: 'answer', kind: 'answered_by' }];
+  const documents = new Set<string>();
+  const chunkIds = new Map<number, string>();
+  const chunkNodes = new Map<string, ProvenanceNode>();
+
+  (metadata?.groundingChunks || []).forEach((chunk, i) => {
+    const context = chunk.retrievedContext;
+    if (!context?.text) return;
+    const id = `chunk:${chunkIds.size + 1}`;
+    const title = context.title || context.uri || 'Unknown';
+    const [sourcePath, ...part] = title.split('#');
+    const pageSpan = context.ragChunk?.pageSpan;
+    const pages: [number, number] | undefined = pageSpan?.firstPage ? [pageSpan.firstPage, pageSpan.lastPage || pageSpan.firstPage] : undefined;
+    const node: ProvenanceNode = {
+      id,
+      kind: 'chunk',
+      text: context.text,
+      footnote: chunkIds.size + 1,
+      cited: false,
+      ...(pages ? { pages } : {}),
+      ...(options.sourceRoot ? locateChunk(options.sourceRoot, sourcePath, context.text) : {}),
+    };
+    chunkIds.set(i, id);
+    chunkNodes.set(id, node);
+    nodes.push(node);
+
+    const documentId = `document:${title}`;
+    if (!documents.has(documentId)) {
+      documents.add(documentId);
+      nodes.push({
+        id: documentId,
+        kind: 'document',
+        title,
+        uri: context.uri,
+        sourcePath,
+        ...(part.length > 0 ? { part: part.join('#') } : {}),
+      });
+    }
+    edges.push({ from: id, to: documentId, kind: 'part_of' });
+  });
+
+  const encoded = Buffer.from(answer, 'utf8');
+  const segments = new Map<string, string>();
+  for (const support of metadata?.groundingSupports || []) {
+    const startByte = support.segment?.startIndex || 0;
+    const endByte = support.segment?.endIndex;
+    if (endByte === undefined) continue;
+    const range = `${startByte}-${endByte}`;
+    const id = segments.get(range) || `segment:${segments.size + 1}`;
+    if (!segments.has(range)) {
+      segments.set(range, id);
+      const start = toCharOffset(encoded, startByte);
+      const end = toCharOffset(encoded, endByte);
+      nodes.push({ id, kind: 'segment', text: support.segment?.text || answer.slice(start, end), start, end, startByte, endByte });
+      edges.push({ from: 'answer', to: id, kind: 'contains' });
+    }
+    (support.groundingChunkIndices || []).forEach((chunkIndex, j) => {
+      const chunkId = chunkIds.get(chunkIndex);
+      const chunk = chunkId ? chunkNodes.get(chunkId) : undefined;
+      if (!chunkId || !chunk) return;
+      chunk.cited = true;
+      edges.push({ from: id, to: chunkId, kind: 'cites', confidence: support.confidenceScores?.[j] });
+    });
+  }
+
+  return { schema: PROVENANCE_SCHEMA_ID, generatedAt: new Date().toISOString(), stores: options.stores || [], nodes, edges };
+}
+
+function dotString(text: string): string {
+  return `"${text.replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\r?\n/g, '\\n')}"`;
+}
+
+function dotLabel(node: ProvenanceNode): string {
+  const text = node.kind === 'document' ? node.title || node.id : (node.text || '').replace(/\s+/g, ' ').trim();
+  const short = text.length > DOT_LABEL_LENGTH ? `${text.slice(0, DOT_LABEL_LENGTH - 1)}…` : text;
+  const prefix = node.kind === 'chunk' ? `[${node.footnote}] ` : node.kind === 'segment' ? `${node.start}-${node.end}: ` : '';
+  return `${prefix}${short}`;
+}
+
+const DOT_SHAPES: { [kind in ProvenanceNodeKind]: string } = {
+  question: 'diamond',
+  answer: 'box',
+  segment: 'box',
+  chunk: 'note',
+  document: 'folder',
+};
+
+// GraphViz rendering, e.g. dot -Tsvg graph.dot > graph.svg
+export function provenanceToDot(graph: ProvenanceGraph): string {
+  const lines = ['digraph provenance {', '  rankdir=LR;', '  node [fontname="Helvetica", fontsize=10];'];
+  for (const node of graph.nodes) {
+    const style = node.kind === 'chunk' && !node.cited ? ', style=dashed' : '';
+    lines.push(`  ${dotString(node.id)} [shape=${DOT_SHAPES[node.kind]}, label=${dotString(dotLabel(node))}${style}];`);
+  }
+  for (const edge of graph.edges) {
+    const label = edge.confidence !== undefined ? `${edge.kind} ${edge.confidence.toFixed(2)}` : edge.kind;
+    lines.push(`  ${dotString(edge.from)} -> ${dotString(edge.to)} [label=${dotString(label)}];`);
+  }
+  lines.push('}');
+  return `${lines.join('\n')}\n`;
+}
+
+// JSON, or DOT for a .dot or .gv file; returns the format written
+export function writeProvenance(filePath: string, graph: ProvenanceGraph): 'json' | 'dot' {
+  const dot = DOT_EXTENSIONS.includes(path.extname(filePath).toLowerCase());
+  fs.writeFileSync(filePath, dot ? provenanceToDot(graph) : `${JSON.stringify(graph, null, 2)}\n`);
+  return dot ? 'dot' : 'json';
+}
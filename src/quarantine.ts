@@ -0,0 +1,96 @@
+// quarantine.ts - Files whose upload or indexing failed, kept with the error until a retry uploads them
+import * as fs from 'fs';
+import * as path from 'path';
+import { IngestFailure } from './progress.js';
+import { DocumentMetadata } from './metadata.js';
+import { writeFileAtomic } from './shutdown.js';
+import { withFileLock } from './lock.js';
+import { ErrorCode } from './errors.js';
+
+// Types
+export interface QuarantinedFile {
+  storeName: string;
+  rootDir: string;  // Absolute path of the directory the file was ingested from
+  path: string;  // Relative path under rootDir
+  reason: string;  // Error of the last attempt
+  code?: ErrorCode;  // Absent for files skipped before upload
+  retries: number;  // Retries of the last attempt
+  attempts: number;  // Ingestions that failed so far
+  firstFailedAt: string;  // ISO 8601
+  lastFailedAt: string;
+  metadata?: DocumentMetadata;  // Metadata the ingestion gave every file, given again by the retry
+}
+
+export const QUARANTINE_FILE = '.gemini-quarantine.json';
+
+function keyOf(storeName: string, rootDir: string, relativePath: string): string {
+  return `${storeName}\n${path.resolve(rootDir)}\n${relativePath}`;
+}
+
+// Kept in a JSON file next to the index cache; every ingestion with the quarantine adds
+// its failures and drops the files it uploaded, so the list only holds files still failing.
+// Each change is read and written under the file's lock.
+export class Quarantine {
+  constructor(private readonly filePath: string) {}
+
+  list(storeName?: string): QuarantinedFile[] {
+    let files: QuarantinedFile[];
+    try {
+      files = (JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as { files: QuarantinedFile[] }).files || [];
+    } catch (error) {
+      return [];
+    }
+    return files.filter(file => !storeName || file.storeName === storeName);
+  }
+
+  // Cancelled files are not failures: resuming the ingestion uploads them
+  record(storeName: string, rootDir: string, failures: IngestFailure[], uploaded: string[] = [], metadata?: DocumentMetadata): void {
+    const failed = failures.filter(failure => failure.code !== 'CANCELLED');
+    if (failed.length === 0 && uploaded.length === 0) return;
+    withFileLock(this.filePath, () => {
+      const files = new Map(this.list().map(file => [keyOf(file.storeName, file.rootDir, file.path), file]));
+      for (const relativePath of uploaded) files.delete(keyOf(storeName, rootDir, relativePath));
+      const now = new Date().toISOString();
+      for (const failure of failed) {
+        const key = keyOf(storeName, rootDir, failure.path);
+        const previous = files.get(key);
+        files.set(key, {
+          storeName,
+          rootDir: path.resolve(rootDir),
+          path: failure.path,
+          reason: failure.reason,
+          ...(failure.code ? { code: failure.code } : {}),
+          retries: failure.retries,
+          attempts: (previous?.attempts || 0) + 1,
+          firstFailedAt: previous?.firstFailedAt || now,
+          lastFailedAt: now,
+          ...(metadata && Object.keys(metadata).length > 0 ? { metadata } : previous?.metadata ? { metadata: previous.metadata } : {}),
+        });
+      }
+      this.save([...files.values()]);
+    });
+  }
+
+  remove(files: QuarantinedFile[]): void {
+    const removed = new Set(files.map(file => keyOf(file.storeName, file.rootDir, file.path)));
+    withFileLock(this.filePath, () => this.save(this.list().filter(file => !removed.has(keyOf(file.storeName, file.rootDir, file.path)))));
+  }
+
+  // Returns how many files were dropped
+  clear(storeName?: string): number {
+    return withFileLock(this.filePath, () => {
+      const files = this.list();
+      const kept = files.filter(file => storeName && file.storeName !== storeName);
+      this.save(kept);
+      return files.length - kept.length;
+    });
+  }
+
+  private save(files: QuarantinedFile[]): void {
+    if (files.length === 0) {
+      fs.rmSync(this.filePath, { force: true });
+      return;
+    }
+    writeFileAtomic(this.filePath, JSON.stringify({ version: 1, files }, null, 2));
+  }
+}
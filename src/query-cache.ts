@@ -0,0 +1,156 @@
+// query-cache.ts - Cached answers for repeated queries against unchanged stores
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+import { GroundingMetadata } from '@google/genai';
+import { Citation, extractCitations } from './citations.js';
+import { TokenUsage } from './cost.js';
+import { StoreInfo } from './store.js';
+import { PendingChanges, withFileLock } from './lock.js';
+import { writeFileAtomic } from './shutdown.js';
+
+// Types
+export interface QueryCacheKey {
+  stores: string[];
+  revisions: string[];  // storeRevision of each store, in the same order
+  question: string;
+  model: string;
+  params: { [name: string]: string | number | boolean | undefined };  // Filter, mode and anything else that changes the answer
+}
+
+export interface CachedAnswer {
+  answer: string;
+  citations?: Citation[];  // Only kept when they cannot be rebuilt from groundingMetadata
+  groundingMetadata?: GroundingMetadata;
+  usage?: TokenUsage;  // Tokens spent by the original query
+  cachedAt: string;
+}
+
+interface QueryCacheEntry extends CachedAnswer {
+  expiresAt: number;
+}
+
+interface QueryCacheData {
+  version: number;
+  entries: { [key: string]: QueryCacheEntry };
+}
+
+export const DEFAULT_QUERY_CACHE_TTL_SECONDS = 3600;
+
+// Oldest entries are evicted first once the cache holds this many answers
+const MAX_ENTRIES = 500;
+
+// Case and whitespace do not change the answer
+export function normalizeQuestion(question: string): string {
+  return question.trim().replace(/\s+/g, ' ').toLowerCase();
+}
+
+// Changes whenever documents are added, removed or finish indexing
+export function storeRevision(store: StoreInfo): string {
+  return [store.updateTime || '', store.activeDocuments, store.pendingDocuments, store.failedDocuments, store.sizeBytes].join('|');
+}
+
+export class QueryCache {
+  private data: QueryCacheData;
+  private readonly changes = new PendingChanges<QueryCacheEntry>();
+  private cleared = false;  // Drop what other runs cached too on the next save
+
+  constructor(
+    private readonly filePath: string,
+    private readonly ttlSeconds: number = DEFAULT_QUERY_CACHE_TTL_SECONDS
+  ) {
+    this.data = this.load();
+  }
+
+  static key(key: QueryCacheKey): string {
+    const params = Object.entries(key.params)
+      .filter(([, value]) => value !== undefined)
+      .sort(([a], [b]) => a.localeCompare(b));
+    return createHash('sha256')
+      .update(JSON.stringify([key.stores, key.revisions, normalizeQuestion(key.question), key.model, params]))
+      .digest('hex');
+  }
+
+  get(key: string): CachedAnswer | undefined {
+    const entry = this.data.entries[key];
+    if (!entry) return undefined;
+    if (entry.expiresAt <= Date.now()) {
+      delete this.data.entries[key];
+      this.changes.delete(key);
+      this.save();
+      return undefined;
+    }
+    return {
+      answer: entry.answer,
+      citations: entry.citations,
+      groundingMetadata: entry.groundingMetadata,
+      usage: entry.usage,
+      cachedAt: entry.cachedAt,
+    };
+  }
+
+  set(key: string, answer: Omit<CachedAnswer, 'cachedAt'>): void {
+    this.data.entries[key] = { ...answer, cachedAt: new Date().toISOString(), expiresAt: Date.now() + this.ttlSeconds * 1000 };
+    this.changes.set(key, this.data.entries[key]);
+    this.evict();
+    this.save();
+  }
+
+  // Text of the chunks cited by the unexpired answers, with the file each came from
+  chunks(): { fileName: string; text: string }[] {
+    const now = Date.now();
+    const chunks: { fileName: string; text: string }[] = [];
+    for (const entry of Object.values(this.data.entries)) {
+      if (entry.expiresAt <= now) continue;
+      const citations = entry.citations || extractCitations(entry.groundingMetadata, entry.answer);
+      for (const citation of citations) {
+        if (citation.chunkText) chunks.push({ fileName: citation.fileName, text: citation.chunkText });
+      }
+    }
+    return chunks;
+  }
+
+  // Returns the number of entries removed
+  clear(): number {
+    const count = Object.keys(this.data.entries).length;
+    this.data.entries = {};
+    this.changes.clear();
+    this.cleared = true;
+    this.save();
+    return count;
+  }
+
+  private evict(): void {
+    const now = Date.now();
+    for (const [key, entry] of Object.entries(this.data.entries)) {
+      if (entry.expiresAt <= now) delete this.data.entries[key];
+    }
+    const keys = Object.keys(this.data.entries);
+    if (keys.length <= MAX_ENTRIES) return;
+    keys
+      .sort((a, b) => this.data.entries[a].expiresAt - this.data.entries[b].expiresAt)
+      .slice(0, keys.length - MAX_ENTRIES)
+      .forEach(key => delete this.data.entries[key]);
+  }
+
+  private load(): QueryCacheData {
+    try {
+      return JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as QueryCacheData;
+    } catch (error) {
+      return { version: 1, entries: {} };
+    }
+  }
+
+  // Written under the cache's lock, on top of the answers other runs cached since it
+  // was loaded; eviction runs again over the merged entries
+  private save(): void {
+    withFileLock(this.filePath, () => {
+      const current = this.cleared ? { version: 1, entries: {} } : this.load();
+      this.changes.applyTo(current.entries);
+      this.changes.clear();
+      this.cleared = false;
+      this.data = current;
+      this.evict();
+      writeFileAtomic(this.filePath, JSON.stringify(this.data, null, 2));
+    });
+  }
+}
@@ -0,0 +1,283 @@
+// quota.ts - Daily request and token budgets, with pauses instead of failures at quota boundaries
+import * as fs from 'fs';
+import { CancelledError, ConfigError, DailyBudgetExceededError, InvalidInputError, toFileSearchError } from './errors.js';
+import { retryAfterMs } from './retry.js';
+import { writeFileAtomic } from './shutdown.js';
+import { withFileLockAsync } from './lock.js';
+import { TomlValue, expectTable } from './toml.js';
+
+// Types
+export type QuotaExhaustedAction = 'wait' | 'fail';
+
+export interface QuotaConfig {
+  requestsPerDay?: number;
+  tokensPerDay?: number;  // Indexing tokens of uploads plus prompt and response tokens of queries
+  onExhausted: QuotaExhaustedAction;  // wait: pause until the daily reset; fail: raise DailyBudgetExceededError
+  timeZone: string;  // Where the day starts; Gemini API quotas reset at midnight Pacific time
+}
+
+export interface DailyUsage {
+  day: string;  // YYYY-MM-DD in the configured time zone
+  requests: number;
+  tokens: number;
+}
+
+export interface QuotaSchedulerOptions {
+  signal?: AbortSignal;  // Ends a pause early with a CancelledError
+  maxRateLimitPauseMs?: number;  // Longest pause after a 429 that outlived the retries (default: 15 minutes)
+}
+
+interface QuotaFile {
+  version: number;
+  days: { [day: string]: { requests: number; tokens: number } };
+}
+
+export const QUOTA_EXHAUSTED_ACTIONS: QuotaExhaustedAction[] = ['wait', 'fail'];
+
+export const DEFAULT_QUOTA_CONFIG: QuotaConfig = { onExhausted: 'wait', timeZone: 'America/Los_Angeles' };
+
+export const QUOTA_FILE = '.gemini-quota.json';
+
+// Reserved for the retrieved passages and the answer on top of a query's question
+export const QUERY_RESERVE_TOKENS = 4000;
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+const KEPT_DAYS = 7;
+const FIRST_RATE_LIMIT_PAUSE_MS = 60_000;
+const DEFAULT_MAX_RATE_LIMIT_PAUSE_MS = 15 * 60_000;
+
+// The pause after a 429 that outlived the retries: the Retry-After of the response,
+// else a minute doubling with each pause in a row
+export function rateLimitPauseMs(error: unknown, pauses: number, maxPauseMs: number = DEFAULT_MAX_RATE_LIMIT_PAUSE_MS): number {
+  return Math.min(maxPauseMs, retryAfterMs(error) ?? FIRST_RATE_LIMIT_PAUSE_MS * 2 ** pauses);
+}
+
+export function parseQuotaConfig(value: TomlValue | undefined): QuotaConfig {
+  if (value === undefined) return { ...DEFAULT_QUOTA_CONFIG };
+  expectTable(value, 'quota');
+
+  const positive = (key: string): number | undefined => {
+    const item = value[key];
+    if (item === undefined) return undefined;
+    if (typeof item !== 'number' || !Number.isInteger(item) || item < 1) throw new ConfigError(`quota.${key} must be a positive integer`);
+    return item;
+  };
+  const onExhausted = value.on_exhausted ?? DEFAULT_QUOTA_CONFIG.onExhausted;
+  if (!QUOTA_EXHAUSTED_ACTIONS.includes(onExhausted as QuotaExhaustedAction)) {
+    throw new ConfigError(`quota.on_exhausted must be one of: ${QUOTA_EXHAUSTED_ACTIONS.join(', ')}`);
+  }
+  const timeZone = value.time_zone ?? DEFAULT_QUOTA_CONFIG.timeZone;
+  if (typeof timeZone !== 'string' || !isTimeZone(timeZone)) {
+    throw new ConfigError(`quota.time_zone must be an IANA time zone such as "UTC" or "America/Los_Angeles"`);
+  }
+  return {
+    requestsPerDay: positive('requests_per_day'),
+    tokensPerDay: positive('tokens_per_day'),
+    onExhausted: onExhausted as QuotaExhaustedAction,
+    timeZone,
+  };
+}
+
+// Flag or environment values of the daily budgets on top of the [quota] table
+export function overrideQuotaConfig(config: QuotaConfig, overrides: { requestsPerDay?: string; tokensPerDay?: string }): QuotaConfig {
+  const positive = (name: string, raw: string | undefined): number | undefined => {
+    if (raw === undefined) return undefined;
+    const value = Number(raw);
+    if (!Number.isInteger(value) || value < 1) throw new InvalidInputError(`${name} must be a positive integer, got "${raw}"`);
+    return value;
+  };
+  return {
+    ...config,
+    requestsPerDay: positive('--requests-per-day', overrides.requestsPerDay) ?? config.requestsPerDay,
+    tokensPerDay: positive('--tokens-per-day', overrides.tokensPerDay) ?? config.tokensPerDay,
+  };
+}
+
+function isTimeZone(timeZone: string): boolean {
+  try {
+    new Intl.DateTimeFormat('en-US', { timeZone });
+    return true;
+  } catch (error) {
+    return false;
+  }
+}
+
+function clock(date: Date, timeZone: string): { day: string; msIntoDay: number } {
+  const parts = Object.fromEntries(new Intl.DateTimeFormat('en-US', {
+    timeZone,
+    hourCycle: 'h23',
+    year: 'numeric',
+    month: '2-digit',
+    day: '2-digit',
+    hour: '2-digit',
+    minute: '2-digit',
+    second: '2-digit',
+  }).formatToParts(date).map(part => [part.type, part.value]));
+  return {
+    day: `${parts.year}-${parts.month}-${parts.day}`,
+    msIntoDay: ((Number(parts.hour) * 60 + Number(parts.minute)) * 60 + Number(parts.second)) * 1000 + date.getMilliseconds(),
+  };
+}
+
+function sleep(ms: number, signal?: AbortSignal): Promise<void> {
+  return new Promise((resolve, reject) => {
+    if (signal?.aborted) {
+      reject(new CancelledError('Cancelled while waiting for quota'));
+      return;
+    }
+    const onAbort = () => {
+      clearTimeout(timer);
+      reject(new CancelledError('Cancelled while waiting for quota'));
+    };
+    const timer = setTimeout(() => {
+      signal?.removeEventListener('abort', onAbort);
+      resolve();
+    }, ms);
+    signal?.addEventListener('abort', onAbort, { once: true });
+  });
+}
+
+// Counts the requests and tokens spent per day in a local file and holds calls back
+// while a daily budget is used up. Calls in flight reserve their estimated tokens, so
+// concurrent uploads cannot overshoot the budget together. Requests that fail still count.
+export class QuotaScheduler {
+  private data: QuotaFile = { version: 1, days: {} };
+  private unsaved: QuotaFile['days'] = {};  // Counted here since the last save
+  private reservedRequests = 0;
+  private reservedTokens = 0;
+  private readonly waiters: (() => void)[] = [];
+
+  constructor(
+    readonly config: QuotaConfig,
+    private readonly filePath?: string,  // Usage is kept in memory only when omitted
+    private readonly options: QuotaSchedulerOptions = {}
+  ) {
+    if (filePath) this.data = QuotaScheduler.read(filePath);
+  }
+
+  private static read(filePath: string): QuotaFile {
+    try {
+      const data = JSON.parse(fs.readFileSync(filePath, 'utf8')) as QuotaFile;
+      if (data.version === 1 && typeof data.days === 'object' && data.days !== null) return data;
+    } catch (error) {
+      // Missing or unreadable file: start counting from zero
+    }
+    return { version: 1, days: {} };
+  }
+
+  get enabled(): boolean {
+    return this.config.requestsPerDay !== undefined || this.config.tokensPerDay !== undefined;
+  }
+
+  usage(now: Date = new Date()): DailyUsage {
+    const { day } = clock(now, this.config.timeZone);
+    const used = this.data.days[day] || { requests: 0, tokens: 0 };
+    return { day, requests: used.requests, tokens: used.tokens };
+  }
+
+  // Runs task once the budgets allow it. tokens is the estimate reserved while it runs;
+  // actualTokens, when given, replaces the estimate once the task has finished.
+  async run<T>(label: string, tokens: number, task: () => Promise<T>, actualTokens?: (result: T) => number | undefined): Promise<T> {
+    for (let pauses = 0; ; pauses++) {
+      await this.reserve(label, tokens);
+      let result: T;
+      try {
+        result = await task();
+      } catch (error) {
+        await this.settle(tokens, 0);
+        const err = toFileSearchError(error);
+        if (err.code !== 'QUOTA_EXCEEDED') throw error;
+        // The retries gave up on a 429; wait for the window to pass instead of failing the run
+        const pauseMs = rateLimitPauseMs(err.cause, pauses, this.options.maxRateLimitPauseMs);
+        console.error(`⏸️  Rate limited on ${label}; pausing ${Math.round(pauseMs / 1000)}s before trying again`);
+        await sleep(pauseMs, this.options.signal);
+        continue;
+      }
+      await this.settle(tokens, actualTokens?.(result) ?? tokens);
+      return result;
+    }
+  }
+
+  private exceeded(tokens: number): string | undefined {
+    const used = this.usage();
+    const { requestsPerDay, tokensPerDay } = this.config;
+    if (requestsPerDay !== undefined && used.requests + this.reservedRequests + 1 > requestsPerDay) {
+      return `${requestsPerDay} requests`;
+    }
+    if (tokensPerDay !== undefined && used.tokens + this.reservedTokens + tokens > tokensPerDay) {
+      return `${tokensPerDay} tokens`;
+    }
+    return undefined;
+  }
+
+  private async reserve(label: string, tokens: number): Promise<void> {
+    const { tokensPerDay } = this.config;
+    if (tokensPerDay !== undefined && tokens > tokensPerDay) {
+      throw new DailyBudgetExceededError(`${label} needs an estimated ${tokens} tokens, more than the daily budget of ${tokensPerDay}`);
+    }
+    for (let budget = this.exceeded(tokens); budget; budget = this.exceeded(tokens)) {
+      if (this.reservedRequests > 0) {
+        // Calls in flight may free part of their reservation; check again when one finishes
+        await new Promise<void>(resolve => this.waiters.push(resolve));
+        continue;
+      }
+      if (this.config.onExhausted === 'fail') {
+        throw new DailyBudgetExceededError(`Daily budget of ${budget} used up (${this.usage().day}); ${label} was not started`);
+      }
+      const now = new Date();
+      const { msIntoDay } = clock(now, this.config.timeZone);
+      const waitMs = Math.max(1000, DAY_MS - msIntoDay);
+      console.error(`⏸️  Daily budget of ${budget} used up; ${label} resumes at ${new Date(now.getTime() + waitMs).toISOString()}`);
+      await sleep(waitMs, this.options.signal);
+    }
+    this.reservedRequests++;
+    this.reservedTokens += tokens;
+  }
+
+  private async settle(reservedTokens: number, tokens: number): Promise<void> {
+    this.reservedRequests--;
+    this.reservedTokens -= reservedTokens;
+    const { day } = this.usage();
+    for (const days of this.filePath ? [this.data.days, this.unsaved] : [this.data.days]) {
+      const used = days[day] || (days[day] = { requests: 0, tokens: 0 });
+      used.requests++;
+      used.tokens += tokens;
+    }
+    this.waiters.splice(0).forEach(resolve => resolve());
+    await this.save();
+  }
+
+  // Adds what was counted since the last save to the file's counts under its lock, so
+  // runs sharing a quota file (the MCP server and a terminal) count each other's usage.
+  // The lock is waited for asynchronously: the daemon and the servers settle calls for
+  // every command and request, and must not stall them while another run writes.
+  private async save(): Promise<void> {
+    const filePath = this.filePath;
+    if (!filePath) return;
+    try {
+      await withFileLockAsync(filePath, () => {
+        const data = QuotaScheduler.read(filePath);
+        for (const [day, { requests, tokens }] of Object.entries(this.unsaved)) {
+          const used = data.days[day] || (data.days[day] = { requests: 0, tokens: 0 });
+          used.requests += requests;
+          used.tokens += tokens;
+        }
+        const days = Object.keys(data.days).sort();
+        for (const day of days.slice(0, Math.max(0, days.length - KEPT_DAYS))) delete data.days[day];
+        writeFileAtomic(filePath, JSON.stringify(data, null, 2));
+        this.data = data;
+        this.unsaved = {};
+      });
+    } catch (error) {
+      const err = error as Error;
+      console.error(`⚠️  Could not write ${this.filePath}: ${err.message}`);
+    }
+  }
+}
+
+export function formatQuotaUsage(scheduler: QuotaScheduler): string {
+  const used = scheduler.usage();
+  const { requestsPerDay, tokensPerDay } = scheduler.config;
+  const of = (value: number, budget?: number) => (budget !== undefined ? `${value}/${budget}` : String(value));
+  return `Quota ${used.day}: ${of(used.requests, requestsPerDay)} requests, ${of(used.tokens, tokensPerDay)} tokens`;
+}
@@ -0,0 +1,216 @@
+// redact.ts - Secret and PII scanning of document text before it leaves the machine
+import * as fs from 'fs';
+import { ConfigError } from './errors.js';
+import { DocumentMetadata } from './metadata.js';
+import { TomlTable, parseToml } from './toml.js';
+
+// Types
+export type RedactionPolicy = 'off' | 'block' | 'mask' | 'tag';
+
+export type RedactionKind = 'secret' | 'pii';
+
+export interface RedactionRule {
+  name: string;
+  kind: RedactionKind;
+  pattern: RegExp;  // A named group "value" limits masking to that part, e.g. the value of "password = ..."
+  mask?: string;  // Replacement text (default: "[REDACTED:<name>]")
+  validate?: (match: string) => boolean;  // Drops matches that only look like one, e.g. card numbers failing the Luhn check
+  allow?: RegExp[];  // Matches of this rule that are left alone, on top of the allowlist of every rule
+}
+
+export interface RedactionFinding {
+  rule: string;
+  kind: RedactionKind;
+  line: number;  // 1-based
+}
+
+export interface RedactionResult {
+  text: string;  // Masked with the mask policy, otherwise the text as given
+  findings: RedactionFinding[];
+}
+
+export const REDACTION_POLICIES: RedactionPolicy[] = ['off', 'block', 'mask', 'tag'];
+
+// Matches of any rule are skipped when they also match one of these, e.g. documentation placeholders
+const DEFAULT_ALLOW = [/@example\.(?:com|org|net)\b/i, /^(?:x+|\*+|<[^>]*>|\$\{[^}]*\}|\{\{[^}]*\}\})$/i];
+
+// Code that reads the secret from elsewhere, e.g. "apiKey: config.apiKey" or "password = getenv(...)"
+const CODE_REFERENCES = [/^[A-Za-z_$][\w$]*(?:\.[A-Za-z_$][\w$]*)+$/, /^[\w$.]+\(/];
+
+function luhn(digits: string): boolean {
+  const numbers = digits.replace(/\D/g, '');
+  if (numbers.length < 13 || numbers.length > 19) return false;
+  let sum = 0;
+  for (let i = 0; i < numbers.length; i++) {
+    let digit = Number(numbers[numbers.length - 1 - i]);
+    if (i % 2 === 1) digit = digit * 2 > 9 ? digit * 2 - 9 : digit * 2;
+    sum += digit;
+  }
+  return sum % 10 === 0;
+}
+
+export const BUILTIN_RULES: RedactionRule[] = [
+  { name: 'private-key', kind: 'secret', pattern: /-----BEGIN (?:[A-Z]+ )*PRIVATE KEY-----[\s\S]*?-----END (?:[A-Z]+ )*PRIVATE KEY-----/ },
+  { name: 'google-api-key', kind: 'secret', pattern: /\bAIza[\w-]{35}\b/ },
+  { name: 'aws-access-key', kind: 'secret', pattern: /\b(?:AKIA|ASIA)[A-Z0-9]{16}\b/ },
+  { name: 'github-token', kind: 'secret', pattern: /\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_\w{60,})\b/ },
+  { name: 'slack-token', kind: 'secret', pattern: /\bxox[abposr]-[A-Za-z0-9-]{10,}\b/ },
+  { name: 'jwt', kind: 'secret', pattern: /\beyJ[\w-]{8,}\.eyJ[\w-]{8,}\.[\w-]{8,}/ },
+  { name: 'bearer-token', kind: 'secret', pattern: /\bBearer\s+(?<value>[\w.~+/-]{20,}=*)/ },
+  {
+    name: 'secret-assignment',
+    kind: 'secret',
+    pattern: /\b(?:api[_-]?key|secret(?:[_-]?key)?|client[_-]?secret|password|passwd|access[_-]?token|auth[_-]?token)["']?\s*[:=]\s*["']?(?<value>[^\s"',;]{8,})/i,
+    allow: CODE_REFERENCES,
+  },
+  { name: 'email', kind: 'pii', pattern: /\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b/ },
+  { name: 'credit-card', kind: 'pii', pattern: /\b(?:\d[ -]?){12,18}\d\b/, validate: luhn },
+  { name: 'us-ssn', kind: 'pii', pattern: /\b(?!000|666|9\d\d)\d{3}-(?!00)\d{2}-(?!0000)\d{4}\b/ },
+];
+
+export function isRedactionPolicy(value: string): value is RedactionPolicy {
+  return REDACTION_POLICIES.includes(value as RedactionPolicy);
+}
+
+function lineOf(text: string, offset: number): number {
+  let line = 1;
+  for (let i = text.indexOf('\n'); i !== -1 && i < offset; i = text.indexOf('\n', i + 1)) line++;
+  return line;
+}
+
+function globalPattern(pattern: RegExp): RegExp {
+  return new RegExp(pattern.source, pattern.flags.includes('g') ? pattern.flags : `${pattern.flags}g`);
+}
+
+function toRegExp(source: unknown, flags: string, where: string): RegExp {
+  if (typeof source !== 'string' || !source) throw new ConfigError(`${where}: pattern must be a non-empty string`);
+  try {
+    return new RegExp(source, flags);
+  } catch (error) {
+    throw new ConfigError(`${where}: invalid pattern: ${(error as Error).message}`);
+  }
+}
+
+// A rule file adds rules, allowlist patterns and turns off built-in rules:
+//
+//   disable = ["email"]
+//   allow = ["@corp\\.example$"]
+//
+//   [[rule]]
+//   name = "employee-id"
+//   pattern = "\\bEMP-\\d{6}\\b"
+//   kind = "pii"            # or "secret" (default)
+//   ignore_case = false
+//   mask = "[EMPLOYEE]"
+//   allow = ["^EMP-000000$"]
+export function parseRuleFile(table: TomlTable, source: string): { rules: RedactionRule[]; allow: RegExp[]; disable: string[] } {
+  const strings = (key: string): string[] => {
+    const value = table[key];
+    if (value === undefined) return [];
+    if (!Array.isArray(value) || !value.every(item => typeof item === 'string')) {
+      throw new ConfigError(`${source}: ${key} must be an array of strings`);
+    }
+    return value as string[];
+  };
+  const entries = table.rule === undefined ? [] : table.rule;
+  if (!Array.isArray(entries)) throw new ConfigError(`${source}: rule must be an array of tables ([[rule]])`);
+  const rules = entries.map((entry, i): RedactionRule => {
+    const where = `${source}: rule ${i + 1}`;
+    if (typeof entry !== 'object' || Array.isArray(entry)) throw new ConfigError(`${where} must be a table`);
+    const rule = entry as TomlTable;
+    if (typeof rule.name !== 'string' || !rule.name) throw new ConfigError(`${where}: name must be a non-empty string`);
+    const kind = rule.kind ?? 'secret';
+    if (kind !== 'secret' && kind !== 'pii') throw new ConfigError(`${where}: kind must be "secret" or "pii"`);
+    if (rule.mask !== undefined && typeof rule.mask !== 'string') throw new ConfigError(`${where}: mask must be a string`);
+    const allow = rule.allow === undefined ? [] : rule.allow;
+    if (!Array.isArray(allow) || !allow.every(item => typeof item === 'string')) throw new ConfigError(`${where}: allow must be an array of strings`);
+    return {
+      name: rule.name,
+      kind,
+      pattern: toRegExp(rule.pattern, rule.ignore_case === true ? 'i' : '', where),
+      ...(rule.mask !== undefined ? { mask: rule.mask } : {}),
+      ...(allow.length > 0 ? { allow: allow.map(pattern => toRegExp(pattern, '', `${where}: allow`)) } : {}),
+    };
+  });
+  return { rules, allow: strings('allow').map(pattern => toRegExp(pattern, '', `${source}: allow`)), disable: strings('disable') };
+}
+
+// "email ×2, aws-access-key"
+export function describeFindings(findings: RedactionFinding[]): string {
+  const counts = new Map<string, number>();
+  for (const finding of findings) counts.set(finding.rule, (counts.get(finding.rule) || 0) + 1);
+  return [...counts].map(([rule, count]) => (count > 1 ? `${rule} ×${count}` : rule)).join(', ');
+}
+
+// What the tag policy attaches to a document, so queries can filter on it
+export function redactionMetadata(findings: RedactionFinding[]): DocumentMetadata {
+  return {
+    redaction_rules: [...new Set(findings.map(finding => finding.rule))],
+    redaction_kinds: [...new Set(findings.map(finding => finding.kind))],
+    redaction_matches: findings.length,
+  };
+}
+
+// Runs the rules over document text. The ingester consults the policy: block stops the
+// file, mask uploads the text returned by apply, tag uploads it as is with
+// redactionMetadata.
+export class Redactor {
+  private readonly rules: RedactionRule[];
+  private readonly allow: RegExp[];
+
+  constructor(readonly policy: Exclude<RedactionPolicy, 'off'>, rules: RedactionRule[] = BUILTIN_RULES, allow: RegExp[] = DEFAULT_ALLOW) {
+    this.rules = rules.map(rule => ({ ...rule, pattern: globalPattern(rule.pattern) }));
+    this.allow = allow;
+  }
+
+  // The built-in rules with the rule files on top; undefined when the policy is off
+  static load(policy: RedactionPolicy | undefined, ruleFiles: string[] = []): Redactor | undefined {
+    if (!policy || policy === 'off') return undefined;
+    if (!isRedactionPolicy(policy)) throw new ConfigError(`redaction policy must be one of: ${REDACTION_POLICIES.join(', ')} (got "${policy}")`);
+    let rules = [...BUILTIN_RULES];
+    const allow = [...DEFAULT_ALLOW];
+    for (const file of ruleFiles) {
+      let table: TomlTable;
+      try {
+        table = parseToml(fs.readFileSync(file, 'utf8'));
+      } catch (error) {
+        if (error instanceof ConfigError) throw error;
+        throw new ConfigError(`Cannot read redaction rules ${file}: ${(error as Error).message}`);
+      }
+      const parsed = parseRuleFile(table, file);
+      rules = [...rules.filter(rule => !parsed.disable.includes(rule.name) && !parsed.rules.some(added => added.name === rule.name)), ...parsed.rules];
+      allow.push(...parsed.allow);
+    }
+    return new Redactor(policy, rules, allow);
+  }
+
+  // Findings in text order, with lines of the text as given; the text is masked only
+  // with the mask policy
+  apply(text: string): RedactionResult {
+    const findings: (RedactionFinding & { offset: number })[] = [];
+    for (const rule of this.rules) {
+      this.each(text, rule, (value, offset) => {
+        findings.push({ rule: rule.name, kind: rule.kind, line: lineOf(text, offset), offset });
+        return value;
+      });
+    }
+    findings.sort((a, b) => a.offset - b.offset);
+    const masked = this.policy === 'mask' && findings.length > 0
+      ? this.rules.reduce((result, rule) => this.each(result, rule, () => rule.mask ?? `[REDACTED:${rule.name}]`), text)
+      : text;
+    return { text: masked, findings: findings.map(({ rule, kind, line }) => ({ rule, kind, line })) };
+  }
+
+  // Replaces the matches of a rule, or their "value" group, that pass its check and the allowlist
+  private each(text: string, rule: RedactionRule, replace: (value: string, offset: number) => string): string {
+    return text.replace(rule.pattern, (match: string, ...args: unknown[]) => {
+      const groups = typeof args[args.length - 1] === 'object' ? args[args.length - 1] as { value?: string } | undefined : undefined;
+      const offset = args[args.length - (groups ? 3 : 2)] as number;
+      const value = groups?.value ?? match;
+      if (rule.validate && !rule.validate(value)) return match;
+      if ([...this.allow, ...(rule.allow || [])].some(pattern => pattern.test(value))) return match;
+      const at = match.lastIndexOf(value);
+      return match.slice(0, at) + replace(value, offset + at) + match.slice(at + value.length);
+    });
+  }
+}
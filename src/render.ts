@@ -0,0 +1,196 @@
+// render.ts - Markdown answers rendered for the terminal: headings, highlighted code, tables and citation superscripts
+
+// Types
+export interface RenderOptions {
+  width?: number;  // Columns of horizontal rules (default: 80)
+}
+
+interface Table {
+  header: string[];
+  align: ('left' | 'right' | 'center')[];
+  rows: string[][];
+}
+
+const ANSI = {
+  reset: '\x1b[0m',
+  bold: '\x1b[1m',
+  dim: '\x1b[2m',
+  italic: '\x1b[3m',
+  underline: '\x1b[4m',
+  green: '\x1b[32m',
+  yellow: '\x1b[33m',
+  magenta: '\x1b[35m',
+  cyan: '\x1b[36m',
+  gray: '\x1b[90m',
+};
+
+const SUPERSCRIPTS = '⁰¹²³⁴⁵⁶⁷⁸⁹';
+
+// Keywords by the fence's language; other languages get strings, numbers and comments only
+const KEYWORDS: { [language: string]: string[] } = {
+  js: ['async', 'await', 'break', 'case', 'catch', 'class', 'const', 'continue', 'default', 'delete', 'else', 'export', 'extends', 'false', 'finally', 'for', 'from', 'function', 'if', 'import', 'in', 'instanceof', 'interface', 'let', 'new', 'null', 'of', 'return', 'static', 'switch', 'this', 'throw', 'true', 'try', 'type', 'typeof', 'undefined', 'var', 'void', 'while', 'yield'],
+  python: ['and', 'as', 'assert', 'async', 'await', 'break', 'class', 'continue', 'def', 'del', 'elif', 'else', 'except', 'False', 'finally', 'for', 'from', 'global', 'if', 'import', 'in', 'is', 'lambda', 'None', 'not', 'or', 'pass', 'raise', 'return', 'self', 'True', 'try', 'while', 'with', 'yield'],
+  rust: ['as', 'async', 'await', 'break', 'const', 'continue', 'crate', 'dyn', 'else', 'enum', 'false', 'fn', 'for', 'if', 'impl', 'in', 'let', 'loop', 'match', 'mod', 'move', 'mut', 'pub', 'ref', 'return', 'Self', 'self', 'static', 'struct', 'super', 'trait', 'true', 'type', 'unsafe', 'use', 'where', 'while'],
+  go: ['break', 'case', 'chan', 'const', 'continue', 'default', 'defer', 'else', 'false', 'for', 'func', 'go', 'if', 'import', 'interface', 'map', 'nil', 'package', 'range', 'return', 'select', 'struct', 'switch', 'true', 'type', 'var'],
+  c: ['auto', 'bool', 'break', 'case', 'char', 'class', 'const', 'continue', 'default', 'do', 'double', 'else', 'enum', 'extern', 'false', 'final', 'float', 'for', 'if', 'import', 'int', 'long', 'namespace', 'new', 'null', 'nullptr', 'package', 'private', 'protected', 'public', 'return', 'short', 'static', 'struct', 'switch', 'this', 'throw', 'true', 'try', 'typedef', 'void', 'while'],
+  sh: ['case', 'do', 'done', 'elif', 'else', 'esac', 'export', 'fi', 'for', 'function', 'if', 'in', 'local', 'return', 'then', 'while'],
+  sql: ['and', 'as', 'by', 'create', 'delete', 'from', 'group', 'having', 'insert', 'into', 'join', 'left', 'limit', 'not', 'null', 'on', 'or', 'order', 'select', 'set', 'table', 'update', 'values', 'where'],
+};
+
+const LANGUAGE_ALIASES: { [alias: string]: string } = {
+  javascript: 'js', typescript: 'js', ts: 'js', tsx: 'js', jsx: 'js', json: 'js',
+  py: 'python', rs: 'rust', golang: 'go',
+  cpp: 'c', 'c++': 'c', h: 'c', java: 'c', kotlin: 'c', kt: 'c', cs: 'c', csharp: 'c', swift: 'c',
+  bash: 'sh', shell: 'sh', zsh: 'sh', console: 'sh',
+};
+
+// "#" starts a comment in these, "//" in the rest
+const HASH_COMMENTS = new Set(['python', 'sh', 'yaml', 'yml', 'toml', 'ruby', 'rb']);
+
+// Escape codes need an interactive terminal; NO_COLOR and TERM=dumb turn rendering off
+export function supportsRendering(stream: NodeJS.WriteStream = process.stdout): boolean {
+  return Boolean(stream.isTTY) && !process.env.NO_COLOR && process.env.TERM !== 'dumb';
+}
+
+function style(code: string, text: string): string {
+  return `${code}${text}${ANSI.reset}`;
+}
+
+function stripAnsi(text: string): string {
+  return text.replace(/\x1b\[[0-9;]*m/g, '');
+}
+
+// Columns the text takes: East Asian wide characters and emoji count twice
+export function displayWidth(text: string): number {
+  let width = 0;
+  for (const char of stripAnsi(text)) {
+    const code = char.codePointAt(0) || 0;
+    if (code < 0x20 || (code >= 0x300 && code <= 0x36f) || code === 0x200d || (code >= 0xfe00 && code <= 0xfe0f)) continue;
+    const wide = (code >= 0x1100 && code <= 0x115f) || (code >= 0x2e80 && code <= 0xa4cf) || (code >= 0xac00 && code <= 0xd7a3)
+      || (code >= 0xf900 && code <= 0xfaff) || (code >= 0xfe30 && code <= 0xfe4f) || (code >= 0xff00 && code <= 0xff60)
+      || (code >= 0xffe0 && code <= 0xffe6) || (code >= 0x1f300 && code <= 0x1faff) || (code >= 0x20000 && code <= 0x3fffd);
+    width += wide ? 2 : 1;
+  }
+  return width;
+}
+
+function superscript(number: string): string {
+  return [...number].map(digit => SUPERSCRIPTS[Number(digit)]).join('');
+}
+
+// Footnote markers "[1][3]" become superscripts "¹˒³"; links keep their text, underlined,
+// and show the URL after it
+function renderInline(text: string): string {
+  return text.split(/(`[^`]+`)/).map((part, i) => {
+    if (i % 2 === 1) return style(ANSI.cyan, part.slice(1, -1));
+    return part
+      .replace(/((?:\[\d+\])+)(?!\()/g, markers => style(ANSI.yellow, (markers.match(/\d+/g) || []).map(superscript).join('˒')))
+      .replace(/\[([^\]]+)\]\(([^)\s]+)\)/g, (_link, label: string, url: string) => `${style(ANSI.underline, label)}${style(ANSI.gray, ` (${url})`)}`)
+      .replace(/\*\*([^*]+)\*\*|__([^_]+)__/g, (_bold, a: string | undefined, b: string | undefined) => style(ANSI.bold, a ?? b ?? ''))
+      .replace(/(^|[^\w*])\*([^*\s][^*]*)\*(?!\*)|(^|\W)_([^_\s][^_]*)_(?!\w)/g, (_italic, p1: string | undefined, a: string | undefined, p2: string | undefined, b: string | undefined) =>
+        `${p1 ?? p2 ?? ''}${style(ANSI.italic, a ?? b ?? '')}`)
+      .replace(/~~([^~]+)~~/g, (_strike, struck: string) => style(ANSI.dim, struck));
+  }).join('');
+}
+
+// One line at a time: strings, comments to the end of the line, numbers and keywords
+export function highlightCode(code: string, language: string = ''): string {
+  const name = LANGUAGE_ALIASES[language.toLowerCase()] || language.toLowerCase();
+  const keywords = new Set(KEYWORDS[name] || []);
+  const caseless = name === 'sql';
+  const comment = HASH_COMMENTS.has(name) ? '#' : name === 'sql' ? '--' : '//';
+  const token = new RegExp(`(${comment.replace(/[/]/g, '\\/')}.*$)|("(?:[^"\\\\]|\\\\.)*"|'(?:[^'\\\\]|\\\\.)*'|\`[^\`]*\`)|(\\b\\d+(?:\\.\\d+)?\\b)|([A-Za-z_][A-Za-z0-9_]*)`, 'g');
+  return code.split('\n').map(line => line.replace(token, (match, lineComment?: string, quoted?: string, number?: string, word?: string) => {
+    if (lineComment) return style(ANSI.gray, lineComment);
+    if (quoted) return style(ANSI.green, quoted);
+    if (number) return style(ANSI.yellow, number);
+    if (word && keywords.has(caseless ? word.toLowerCase() : word)) return style(ANSI.magenta, word);
+    return match;
+  })).join('\n');
+}
+
+function splitRow(line: string): string[] {
+  return line.trim().replace(/^\|/, '').replace(/\|$/, '').split(/(?<!\\)\|/).map(cell => cell.trim().replace(/\\\|/g, '|'));
+}
+
+function isSeparatorRow(line: string | undefined): boolean {
+  return !!line && /^\s*\|?\s*:?-{3,}:?\s*(\|\s*:?-{3,}:?\s*)*\|?\s*$/.test(line);
+}
+
+function pad(text: string, width: number, align: 'left' | 'right' | 'center'): string {
+  const space = Math.max(0, width - displayWidth(text));
+  if (align === 'right') return ' '.repeat(space) + text;
+  if (align === 'center') return ' '.repeat(Math.floor(space / 2)) + text + ' '.repeat(Math.ceil(space / 2));
+  return text + ' '.repeat(space);
+}
+
+// Box-drawn with aligned columns; a table wider than the terminal is left to wrap
+function renderTable(table: Table): string {
+  const header = table.header.map(renderInline);
+  const rows = table.rows.map(row => header.map((_cell, i) => renderInline(row[i] || '')));
+  const widths = header.map((cell, i) => Math.max(displayWidth(cell), ...rows.map(row => displayWidth(row[i]))));
+  const rule = (left: string, middle: string, right: string) => style(ANSI.gray, `${left}${widths.map(width => '─'.repeat(width + 2)).join(middle)}${right}`);
+  const line = (cells: string[], bold: boolean) => {
+    const bar = style(ANSI.gray, '│');
+    return `${bar}${cells.map((cell, i) => ` ${pad(bold ? style(ANSI.bold, cell) : cell, widths[i], table.align[i] || 'left')} `).join(bar)}${bar}`;
+  };
+  return [rule('┌', '┬', '┐'), line(header, true), rule('├', '┼', '┤'), ...rows.map(row => line(row, false)), rule('└', '┴', '┘')].join('\n');
+}
+
+export function renderMarkdown(markdown: string, options: RenderOptions = {}): string {
+  const width = Math.max(20, options.width || 80);
+  const lines = markdown.replace(/\r\n?/g, '\n').split('\n');
+  const output: string[] = [];
+  for (let i = 0; i < lines.length; i++) {
+    const line = lines[i];
+
+    const fence = line.match(/^\s*(```+|~~~+)\s*([\w+#.-]*)/);
+    if (fence) {
+      const code: string[] = [];
+      for (i++; i < lines.length && !lines[i].trim().startsWith(fence[1]); i++) code.push(lines[i]);
+      const bar = style(ANSI.gray, '│ ');
+      if (fence[2]) output.push(style(ANSI.gray, `╭─ ${fence[2]}`));
+      output.push(...highlightCode(code.join('\n'), fence[2]).split('\n').map(codeLine => `${bar}${codeLine}`));
+      continue;
+    }
+
+    if (line.includes('|') && isSeparatorRow(lines[i + 1])) {
+      const align = splitRow(lines[i + 1]).map(cell => (cell.endsWith(':') ? (cell.startsWith(':') ? 'center' : 'right') : 'left') as Table['align'][number]);
+      const table: Table = { header: splitRow(line), align, rows: [] };
+      for (i += 2; i < lines.length && lines[i].includes('|') && lines[i].trim(); i++) table.rows.push(splitRow(lines[i]));
+      i--;
+      output.push(renderTable(table));
+      continue;
+    }
+
+    const heading = line.match(/^(#{1,6})\s+(.*?)\s*#*\s*$/);
+    if (heading) {
+      const text = renderInline(heading[2]);
+      if (heading[1].length === 1) output.push(style(ANSI.bold + ANSI.underline + ANSI.magenta, stripAnsi(text)));
+      else if (heading[1].length === 2) output.push(style(ANSI.bold + ANSI.cyan, stripAnsi(text)));
+      else output.push(style(ANSI.bold, stripAnsi(text)));
+      continue;
+    }
+
+    if (/^\s*([-*_])(\s*\1){2,}\s*$/.test(line)) {
+      output.push(style(ANSI.gray, '─'.repeat(width)));
+      continue;
+    }
+
+    const quote = line.match(/^\s*>\s?(.*)$/);
+    if (quote) {
+      output.push(`${style(ANSI.gray, '│ ')}${style(ANSI.italic, renderInline(quote[1]))}`);
+      continue;
+    }
+
+    const item = line.match(/^(\s*)([-*+]|\d+[.)])\s+(?:\[([ xX])\]\s+)?(.*)$/);
+    if (item) {
+      const bullet = /\d/.test(item[2]) ? item[2] : item[3] !== undefined ? (item[3] === ' ' ? '☐' : '☑') : '•';
+      output.push(`${item[1]}${style(ANSI.cyan, bullet)} ${renderInline(item[4])}`);
+      continue;
+    }
+
+    output.push(renderInline(line));
+  }
+  return output.join('\n');
+}
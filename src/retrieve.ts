@@ -0,0 +1,83 @@
+// retrieve.ts - Retrieval without an answer: the ranked chunks a query matches and their documents
+import { GroundingChunkRetrievedContext, GroundingMetadata } from '@google/genai';
+import { RagContext } from './models.js';
+
+// Types
+export interface RetrieveOptions {
+  topK?: number;  // Chunks to return (default: 10)
+  metadataFilter?: string;  // AIP-160 filter, see parseFilterExpression
+  aclLabels?: string[];  // Only search documents carrying one of these access labels
+  model?: string;  // Gemini API only: the model that runs the file search tool
+}
+
+export interface RetrievedChunk {
+  rank: number;  // 1-based, in the order retrieved
+  text: string;
+  fileName: string;  // Display name of the source document
+  uri?: string;
+  store?: string;  // Store of the document, when the backend reports it or only one was searched
+  score?: number;  // Vertex AI: distance or similarity of the vector search; Gemini API: confidence, when reported
+}
+
+export const DEFAULT_TOP_K = 10;
+
+// The Gemini API has no retrieval-only call: the model is asked to search and not to
+// answer, and only the grounding of its reply is kept
+export const RETRIEVE_ONLY_INSTRUCTION = [
+  'Search the documents for the user message with the file search tool, using it as the search query.',
+  'Do not answer, summarize or comment on the results; reply with the single word OK.',
+].join(' ');
+
+// Chunks are told apart by their document and text; the model may search more than once
+// and retrieve the same chunk again
+export function chunksFromGrounding(metadata: GroundingMetadata | undefined, storeNames: string[], topK: number = DEFAULT_TOP_K): RetrievedChunk[] {
+  const confidences = new Map<number, number>();
+  for (const support of metadata?.groundingSupports || []) {
+    (support.groundingChunkIndices || []).forEach((chunkIndex, j) => {
+      const confidence = support.confidenceScores?.[j];
+      if (confidence !== undefined && confidence > (confidences.get(chunkIndex) ?? -1)) confidences.set(chunkIndex, confidence);
+    });
+  }
+  const seen = new Set<string>();
+  const chunks: RetrievedChunk[] = [];
+  (metadata?.groundingChunks || []).forEach((chunk, i) => {
+    const context = chunk.retrievedContext as (GroundingChunkRetrievedContext & { fileSearchStore?: string }) | undefined;
+    if (!context?.text) return;
+    const fileName = context.title || context.uri || 'Unknown';
+    const key = `${fileName}\n${context.text}`;
+    if (seen.has(key)) return;
+    seen.add(key);
+    chunks.push({
+      rank: chunks.length + 1,
+      text: context.text,
+      fileName,
+      uri: context.uri,
+      store: context.fileSearchStore || (storeNames.length === 1 ? storeNames[0] : undefined),
+      score: confidences.get(i),
+    });
+  });
+  return chunks.slice(0, topK);
+}
+
+export function chunksFromContexts(contexts: RagContext[], storeNames: string[], topK: number = DEFAULT_TOP_K): RetrievedChunk[] {
+  return contexts
+    .filter(context => context.text)
+    .slice(0, topK)
+    .map((context, i) => ({
+      rank: i + 1,
+      text: context.text || '',
+      fileName: context.sourceDisplayName || context.sourceUri || 'Unknown',
+      uri: context.sourceUri,
+      store: storeNames.length === 1 ? storeNames[0] : undefined,
+      score: context.score,
+    }));
+}
+
+export function formatRetrievedChunks(chunks: RetrievedChunk[]): string {
+  if (chunks.length === 0) return 'No chunks retrieved.';
+  return chunks.map(chunk => {
+    const details = [chunk.store, chunk.score !== undefined ? `score ${chunk.score.toFixed(3)}` : undefined].filter(detail => detail);
+    const text = chunk.text.split('\n').map(line => `    ${line}`).join('\n');
+    return `[${chunk.rank}] ${chunk.fileName}${details.length > 0 ? ` (${details.join(', ')})` : ''}\n${text}`;
+  }).join('\n\n');
+}
@@ -0,0 +1,103 @@
+// retry.ts - Retries with exponential backoff and jitter for transient API errors
+import { HttpStatusError, isNetworkError, statusOf, toFileSearchError } from './errors.js';
+import { telemetry } from './telemetry.js';
+
+// Types
+export interface RetryOptions {
+  maxAttempts?: number;  // Including the first attempt
+  baseDelayMs?: number;
+  maxDelayMs?: number;
+  jitter?: number;  // Fraction of the delay randomized away, 0-1
+  onRetry?: (attempt: number, error: unknown, delayMs: number) => void;  // Called before each retry
+  failover?: (error: unknown, startedAt: number) => boolean;  // True to repeat the attempt at once, e.g. with another API key
+}
+
+const RETRYABLE_STATUS = new Set([408, 429, 500, 502, 503, 504]);
+
+export const DEFAULT_RETRY_OPTIONS: Required<Omit<RetryOptions, 'onRetry' | 'failover'>> = {
+  maxAttempts: 5,
+  baseDelayMs: 1000,
+  maxDelayMs: 60_000,
+  jitter: 0.5,
+};
+
+export function isRetryable(error: unknown): boolean {
+  const status = statusOf(error);
+  if (status !== undefined) {
+    return RETRYABLE_STATUS.has(status);
+  }
+  return isNetworkError(error);
+}
+
+// Parses a Retry-After header (seconds or HTTP date) or the retryDelay
+// of a google.rpc.RetryInfo detail embedded in the error message
+export function retryAfterMs(error: unknown): number | undefined {
+  if (error instanceof HttpStatusError && error.retryAfter) {
+    const seconds = Number(error.retryAfter);
+    if (Number.isFinite(seconds)) return seconds * 1000;
+    const date = Date.parse(error.retryAfter);
+    if (!Number.isNaN(date)) return Math.max(0, date - Date.now());
+  }
+  if (error instanceof Error) {
+    const match = /"retryDelay"\s*:\s*"(\d+(?:\.\d+)?)s"/.exec(error.message);
+    if (match) return Number(match[1]) * 1000;
+  }
+  return undefined;
+}
+
+export function backoffDelay(attempt: number, options: typeof DEFAULT_RETRY_OPTIONS): number {
+  const exponential = Math.min(options.maxDelayMs, options.baseDelayMs * 2 ** (attempt - 1));
+  return Math.round(exponential * (1 - options.jitter * Math.random()));
+}
+
+// Calls fn until it succeeds, the error is not transient, or attempts run out.
+// fn receives the 1-based attempt number so callers can resume partial work.
+// The final failure is raised as a FileSearchError.
+export async function withRetry<T>(
+  label: string,
+  fn: (attempt: number) => Promise<T>,
+  options: RetryOptions = {}
+): Promise<T> {
+  const settings: typeof DEFAULT_RETRY_OPTIONS = {
+    maxAttempts: options.maxAttempts ?? DEFAULT_RETRY_OPTIONS.maxAttempts,
+    baseDelayMs: options.baseDelayMs ?? DEFAULT_RETRY_OPTIONS.baseDelayMs,
+    maxDelayMs: options.maxDelayMs ?? DEFAULT_RETRY_OPTIONS.maxDelayMs,
+    jitter: options.jitter ?? DEFAULT_RETRY_OPTIONS.jitter,
+  };
+
+  // "upload of main.rs" is reported as operation "upload" to keep metric cardinality low
+  const operation = label.replace(/ of .*$/, '');
+  const startedAt = Date.now();
+  return telemetry.trace(label, { operation }, async span => {
+    try {
+      for (let attempt = 1; ; attempt++) {
+        const attemptSpan = telemetry.startSpan(`${operation} attempt`, { attempt });
+        const attemptStartedAt = Date.now();
+        try {
+          const result = await fn(attempt);
+          attemptSpan.end();
+          span.setAttribute('attempts', attempt);
+          return result;
+        } catch (error) {
+          const status = statusOf(error);
+          attemptSpan.setAttribute('http.status_code', status).end(error);
+          if (attempt < settings.maxAttempts && options.failover?.(error, attemptStartedAt)) {
+            telemetry.retries.add(1, { operation });
+            continue;
+          }
+          if (attempt >= settings.maxAttempts || !isRetryable(error)) {
+            span.setAttribute('attempts', attempt);
+            throw toFileSearchError(error);
+          }
+          const delay = retryAfterMs(error) ?? backoffDelay(attempt, settings);
+          telemetry.retries.add(1, { operation });
+          options.onRetry?.(attempt + 1, error, delay);
+          console.error(`🔁 Retrying ${label} in ${(delay / 1000).toFixed(1)}s (attempt ${attempt + 1}/${settings.maxAttempts}${status ? `, HTTP ${status}` : ''})`);
+          await new Promise(resolve => setTimeout(resolve, delay));
+        }
+      }
+    } finally {
+      telemetry.requestDuration.record(Date.now() - startedAt, { operation });
+    }
+  });
+}
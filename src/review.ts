@@ -0,0 +1,499 @@
+// review.ts - Review comments on a unified diff, grounded in the project's File Search store
+import { GroundingMetadata } from '@google/genai';
+import { FileSearchClient, QueryOptions } from './client.js';
+import { Citation, extractCitations, formatFootnotes } from './citations.js';
+import { chunkCode } from './code-chunker.js';
+import { TokenUsage, formatUsage, toTokenUsage } from './cost.js';
+import { InvalidInputError } from './errors.js';
+import { Semaphore } from './pipeline.js';
+import { TemplateVars, renderTemplate } from './templates.js';
+
+// Types
+export type DiffLineType = 'add' | 'delete' | 'context';
+
+export interface DiffLine {
+  type: DiffLineType;
+  text: string;
+  oldLine?: number;  // Unset for added lines
+  newLine?: number;  // Unset for deleted lines
+}
+
+export interface DiffHunk {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  section: string;  // Text after the second @@, usually the enclosing function
+  lines: DiffLine[];
+}
+
+export interface DiffFile {
+  path: string;  // New path; the old one for deleted files
+  oldPath?: string;  // Set for renames
+  status: 'added' | 'deleted' | 'modified' | 'renamed';
+  binary: boolean;
+  hunks: DiffHunk[];
+}
+
+export interface ReviewChunk {
+  file: DiffFile;
+  hunks: DiffHunk[];
+  symbols: string[];  // Declarations the hunks touch
+}
+
+export type ReviewSeverity = 'error' | 'warning' | 'note';
+
+export interface ReviewComment {
+  file: string;
+  line: number;  // Line of the new file
+  endLine?: number;
+  severity: ReviewSeverity;
+  comment: string;
+  citations: number[];  // Footnote numbers of the ReviewReport citations supporting the comment
+}
+
+export interface ReviewReport {
+  comments: ReviewComment[];
+  citations: Citation[];
+  files: number;  // Files reviewed
+  chunks: number;
+  skipped: string[];  // Files not reviewed, with the reason
+  failures: { file: string; error: string }[];  // Chunks whose query failed
+  stores: string[];
+  model?: string;
+  usage?: TokenUsage;
+}
+
+export interface ReviewOptions extends QueryOptions {
+  template?: string;  // Prompt template source, see DEFAULT_REVIEW_TEMPLATE for its variables
+  vars?: TemplateVars;  // Extra template variables, e.g. focus
+  maxChunkLines?: number;  // Diff lines sent per query; larger hunks are sent alone (default: 400)
+  concurrency?: number;  // Queries in flight at once (default: 3)
+  contentOf?: (file: DiffFile) => string | undefined;  // New content of a file, to find the declarations a hunk touches
+}
+
+export type ReviewFormat = 'text' | 'markdown' | 'json' | 'sarif';
+
+export const REVIEW_FORMATS: ReviewFormat[] = ['text', 'markdown', 'json', 'sarif'];
+
+export const REVIEW_SEVERITIES: ReviewSeverity[] = ['error', 'warning', 'note'];
+
+const DEFAULT_MAX_CHUNK_LINES = 400;
+
+// Variables: file, status, symbols, diff, plus the ones passed in options.vars
+export const DEFAULT_REVIEW_TEMPLATE = `You are reviewing a change to {{file}}{{#if symbols}} in {{symbols}}{{/if}}.
+Use the other documents of the project to check the change against how this code is called, configured, tested and documented, and point out where the change breaks or contradicts them.
+{{#if focus}}Focus on: {{focus}}{{/if}}
+
+Write one review comment per line, in the form
+L<line> [error|warning|note] <comment>
+where <line> is a line number from the left column of the diff (L<start>-<end> for a range). Use error for bugs, warning for risky or inconsistent code and note for suggestions. Only comment on changed lines, and name the documents a comment relies on. If the change needs no comments, answer NONE.
+
+{{diff}}`;
+
+const COMMENT_LINE = /^\s*(?:[-*]\s+)?\**L(\d+)(?:\s*-\s*L?(\d+))?\**\s*\[(error|warning|note)\]\**\s*:?\s*(.*)$/i;
+
+// "a/src/lib.rs\t2024-01-01 ..." to "src/lib.rs"; /dev/null to undefined
+function pathOf(value: string): string | undefined {
+  let name = value.replace(/\t.*$/, '').trim();
+  if (name.startsWith('"') && name.endsWith('"')) name = JSON.parse(name) as string;
+  if (name === '/dev/null') return undefined;
+  return name.replace(/^[ab]\//, '');
+}
+
+// Reads the output of git diff, diff -u and most patch files. Hunk bodies are consumed
+// by their line counts, so removed lines starting with "--" are not read as headers.
+export function parseUnifiedDiff(text: string): DiffFile[] {
+  const files: DiffFile[] = [];
+  let file: DiffFile | undefined;
+  let hunk: DiffHunk | undefined;
+  let oldLine = 0;
+  let newLine = 0;
+  let oldRemaining = 0;
+  let newRemaining = 0;
+  let awaitingPaths = false;  // After a "diff --git" line, whose ---/+++ lines belong to the same file
+
+  const startFile = (oldPath?: string, newPath?: string): DiffFile => {
+    const started: DiffFile = { path: newPath || oldPath || '', oldPath, status: 'modified', binary: false, hunks: [] };
+    files.push(started);
+    hunk = undefined;
+    awaitingPaths = false;
+    return started;
+  };
+
+  for (const line of text.split(/\r?\n/)) {
+    if (hunk && (oldRemaining > 0 || newRemaining > 0)) {
+      if (line.startsWith('+') && newRemaining > 0) {
+        hunk.lines.push({ type: 'add', text: line.slice(1), newLine: newLine++ });
+        newRemaining--;
+        continue;
+      }
+      if (line.startsWith('-') && oldRemaining > 0) {
+        hunk.lines.push({ type: 'delete', text: line.slice(1), oldLine: oldLine++ });
+        oldRemaining--;
+        continue;
+      }
+      if ((line.startsWith(' ') || line === '') && oldRemaining > 0 && newRemaining > 0) {
+        hunk.lines.push({ type: 'context', text: line.slice(1), oldLine: oldLine++, newLine: newLine++ });
+        oldRemaining--;
+        newRemaining--;
+        continue;
+      }
+    }
+    if (line.startsWith('\\')) continue;  // "\ No newline at end of file"
+
+    const gitHeader = /^diff --git (?:"?a\/)?(.+?)"? (?:"?b\/)?(.+?)"?$/.exec(line);
+    if (gitHeader) {
+      file = startFile(gitHeader[1], gitHeader[2]);
+      awaitingPaths = true;
+      continue;
+    }
+    const hunkHeader = /^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@ ?(.*)$/.exec(line);
+    if (hunkHeader) {
+      if (!file) file = startFile();
+      awaitingPaths = false;
+      oldLine = Number(hunkHeader[1]);
+      newLine = Number(hunkHeader[3]);
+      oldRemaining = hunkHeader[2] !== undefined ? Number(hunkHeader[2]) : 1;
+      newRemaining = hunkHeader[4] !== undefined ? Number(hunkHeader[4]) : 1;
+      hunk = { oldStart: oldLine, oldLines: oldRemaining, newStart: newLine, newLines: newRemaining, section: hunkHeader[5].trim(), lines: [] };
+      file.hunks.push(hunk);
+      continue;
+    }
+    if (line.startsWith('--- ')) {
+      // Without a "diff --git" line, each "---" starts the next file
+      if (!file || !awaitingPaths) file = startFile();
+      const oldPath = pathOf(line.slice(4));
+      if (oldPath) file.oldPath = oldPath;
+      else file.status = 'added';
+      awaitingPaths = true;
+      continue;
+    }
+    if (line.startsWith('+++ ') && file) {
+      const newPath = pathOf(line.slice(4));
+      if (newPath) file.path = newPath;
+      else if (file.oldPath) {
+        file.path = file.oldPath;
+        file.status = 'deleted';
+      }
+      continue;
+    }
+    if (!file) continue;
+    if (line.startsWith('new file mode')) file.status = 'added';
+    else if (line.startsWith('deleted file mode')) file.status = 'deleted';
+    else if (line.startsWith('rename from ')) file.oldPath = line.slice('rename from '.length);
+    else if (line.startsWith('rename to ')) file.path = line.slice('rename to '.length);
+    else if (/^Binary files .* differ$/.test(line) || line === 'GIT binary patch') {
+      file.binary = true;
+      awaitingPaths = false;
+    }
+  }
+
+  for (const parsed of files) {
+    if (parsed.oldPath && parsed.oldPath !== parsed.path && parsed.status === 'modified') parsed.status = 'renamed';
+    if (parsed.status !== 'renamed') parsed.oldPath = undefined;
+  }
+  return files;
+}
+
+// Declarations overlapping the new lines of the hunks; the @@ section text when the
+// file content is not available or not a supported language
+function touchedSymbols(file: DiffFile, hunks: DiffHunk[], content: string | undefined): string[] {
+  const declarations = content ? chunkCode(file.path, content).filter(chunk => chunk.symbol !== '(module)') : [];
+  const symbols = new Set<string>();
+  for (const hunk of hunks) {
+    const end = hunk.newStart + Math.max(hunk.newLines, 1) - 1;
+    const overlapping = declarations.filter(chunk => chunk.startLine <= end && chunk.endLine >= hunk.newStart);
+    if (overlapping.length > 0) overlapping.forEach(chunk => symbols.add(chunk.symbol));
+    else if (hunk.section) symbols.add(hunk.section);
+  }
+  return [...symbols];
+}
+
+// Consecutive hunks of a file are sent together up to maxChunkLines diff lines.
+// Deleted and binary files have no new lines to comment on and are left out.
+export function chunkDiff(files: DiffFile[], options: Pick<ReviewOptions, 'maxChunkLines' | 'contentOf'> = {}): { chunks: ReviewChunk[]; skipped: string[] } {
+  const maxLines = options.maxChunkLines || DEFAULT_MAX_CHUNK_LINES;
+  const chunks: ReviewChunk[] = [];
+  const skipped: string[] = [];
+  for (const file of files) {
+    if (file.binary) {
+      skipped.push(`${file.path} (binary)`);
+      continue;
+    }
+    if (file.status === 'deleted') {
+      skipped.push(`${file.path} (deleted)`);
+      continue;
+    }
+    if (file.hunks.length === 0) {
+      skipped.push(`${file.path} (no changed lines)`);
+      continue;
+    }
+    const content = options.contentOf?.(file);
+    let group: DiffHunk[] = [];
+    let lines = 0;
+    const flush = () => {
+      if (group.length > 0) chunks.push({ file, hunks: group, symbols: touchedSymbols(file, group, content) });
+      group = [];
+      lines = 0;
+    };
+    for (const hunk of file.hunks) {
+      if (group.length > 0 && lines + hunk.lines.length > maxLines) flush();
+      group.push(hunk);
+      lines += hunk.lines.length;
+    }
+    flush();
+  }
+  return { chunks, skipped };
+}
+
+// Hunks with the new line number in front of every line the review can comment on
+export function formatNumberedDiff(hunks: DiffHunk[]): string {
+  const width = String(Math.max(...hunks.map(hunk => hunk.newStart + hunk.newLines))).length;
+  return hunks.map(hunk => {
+    const header = `@@ -${hunk.oldStart},${hunk.oldLines} +${hunk.newStart},${hunk.newLines} @@${hunk.section ? ` ${hunk.section}` : ''}`;
+    const body = hunk.lines.map(line => {
+      const number = line.newLine !== undefined ? String(line.newLine).padStart(width) : ' '.repeat(width);
+      const marker = line.type === 'add' ? '+' : line.type === 'delete' ? '-' : ' ';
+      return `${number} ${marker}${line.text}`;
+    });
+    return [header, ...body].join('\n');
+  }).join('\n');
+}
+
+function reviewPrompt(chunk: ReviewChunk, options: ReviewOptions): string {
+  const diff = formatNumberedDiff(chunk.hunks);
+  const fence = diff.includes('```') ? '~~~~' : '```';
+  return renderTemplate(options.template || DEFAULT_REVIEW_TEMPLATE, {
+    ...options.vars,
+    file: chunk.file.oldPath ? `${chunk.file.path} (renamed from ${chunk.file.oldPath})` : chunk.file.path,
+    status: chunk.file.status,
+    symbols: chunk.symbols.join(', '),
+    diff: `${fence}diff\n${diff}\n${fence}`,
+  });
+}
+
+// The chunk lines a comment may point at; others move to the closest of them
+function nearestLine(line: number, lines: number[]): number {
+  return lines.reduce((best, candidate) => Math.abs(candidate - line) < Math.abs(best - line) ? candidate : best, lines[0]);
+}
+
+// Comments of one answer with the local footnote numbers of the supports that fall
+// into each comment's text
+function parseComments(
+  chunk: ReviewChunk,
+  answer: string,
+  metadata: GroundingMetadata | undefined
+): { comments: (ReviewComment & { start: number; end: number })[]; supported: boolean } {
+  const lines = chunk.hunks.flatMap(hunk => hunk.lines.filter(line => line.type !== 'delete').map(line => line.newLine as number));
+  const comments: (ReviewComment & { start: number; end: number })[] = [];
+  let offset = 0;
+  for (const text of answer.split('\n')) {
+    const match = COMMENT_LINE.exec(text);
+    const previous = comments[comments.length - 1];
+    if (match) {
+      const line = lines.length > 0 ? nearestLine(Number(match[1]), lines) : Number(match[1]);
+      const endLine = match[2] !== undefined && Number(match[2]) > line ? Number(match[2]) : undefined;
+      comments.push({
+        file: chunk.file.path,
+        line,
+        endLine,
+        severity: match[3].toLowerCase() as ReviewSeverity,
+        comment: match[4].trim(),
+        citations: [],
+        start: offset,
+        end: offset + text.length,
+      });
+    } else if (previous && text.trim() && !/^NONE\.?$/i.test(text.trim())) {
+      // Continuation of a comment that spans several lines
+      previous.comment += `\n${text.trim()}`;
+      previous.end = offset + text.length;
+    }
+    offset += text.length + 1;
+  }
+
+  if (!metadata) return { comments, supported: false };
+  const numbers = new Map<number, number>();
+  (metadata.groundingChunks || []).forEach((groundingChunk, i) => {
+    if (groundingChunk.retrievedContext?.text) numbers.set(i, numbers.size + 1);
+  });
+  const encoded = Buffer.from(answer, 'utf8');
+  let supported = false;
+  for (const support of metadata.groundingSupports || []) {
+    const start = encoded.subarray(0, support.segment?.startIndex || 0).toString('utf8').length;
+    const comment = comments.find(candidate => start >= candidate.start && start <= candidate.end);
+    if (!comment) continue;
+    for (const chunkIndex of support.groundingChunkIndices || []) {
+      const number = numbers.get(chunkIndex);
+      if (number !== undefined && !comment.citations.includes(number)) {
+        comment.citations.push(number);
+        supported = true;
+      }
+    }
+  }
+  return { comments, supported };
+}
+
+// Reviews the changed files of a unified diff: the hunks of each file are sent in chunks,
+// with the declarations they touch, through the review template, and the store supplies
+// the callers, configuration and documentation to check them against. Comments are
+// keyed to lines of the new files; when the model does not ground single comments, each
+// comment of a chunk cites everything retrieved for that chunk.
+export async function reviewDiff(
+  client: FileSearchClient,
+  storeNames: string[],
+  diff: string,
+  options: ReviewOptions = {}
+): Promise<ReviewReport> {
+  if (storeNames.length === 0) throw new InvalidInputError('At least one store is required');
+  const files = parseUnifiedDiff(diff);
+  if (files.length === 0) throw new InvalidInputError('The input is not a unified diff');
+  const { chunks, skipped } = chunkDiff(files, options);
+  // Renders every prompt up front, so template errors surface before any query
+  const prompts = chunks.map(chunk => reviewPrompt(chunk, options));
+
+  const semaphore = new Semaphore(options.concurrency || 3);
+  const responses = await Promise.all(prompts.map(async prompt => {
+    try {
+      return { response: await semaphore.run(() => client.query(storeNames, prompt, options)) };
+    } catch (error) {
+      return { error: error as Error };
+    }
+  }));
+  if (responses.length > 0 && responses.every(r => r.error)) throw responses[0].error;
+
+  const report: ReviewReport = {
+    comments: [],
+    citations: [],
+    files: new Set(chunks.map(chunk => chunk.file.path)).size,
+    chunks: chunks.length,
+    skipped,
+    failures: [],
+    stores: storeNames,
+    model: options.model || client.model,
+  };
+  responses.forEach(({ response, error }, i) => {
+    const chunk = chunks[i];
+    if (!response) {
+      report.failures.push({ file: chunk.file.path, error: error?.message || 'Unknown error' });
+      return;
+    }
+    const usage = toTokenUsage(response.usageMetadata);
+    if (usage) {
+      report.usage = {
+        promptTokens: (report.usage?.promptTokens || 0) + usage.promptTokens,
+        responseTokens: (report.usage?.responseTokens || 0) + usage.responseTokens,
+        totalTokens: (report.usage?.totalTokens || 0) + usage.totalTokens,
+      };
+    }
+    const answer = response.text || '';
+    const metadata = response.candidates?.[0]?.groundingMetadata;
+    const { comments, supported } = parseComments(chunk, answer, metadata);
+    if (comments.length === 0) return;
+
+    // Numbered across all chunks; offsets are dropped since they point into the single answers
+    const local = extractCitations(metadata, answer);
+    const offset = report.citations.length;
+    report.citations.push(...local.map(citation => ({ ...citation, index: offset + citation.index, startIndex: undefined, endIndex: undefined })));
+    for (const { start, end, ...comment } of comments) {
+      const numbers = supported ? comment.citations : local.map(citation => citation.index);
+      report.comments.push({ ...comment, citations: numbers.map(number => offset + number).sort((a, b) => a - b) });
+    }
+  });
+  return report;
+}
+
+function formatLines(comment: ReviewComment): string {
+  return comment.endLine ? `${comment.line}-${comment.endLine}` : String(comment.line);
+}
+
+function footnotes(comment: ReviewComment): string {
+  return comment.citations.map(number => `[${number}]`).join('');
+}
+
+function formatSummary(report: ReviewReport): string {
+  const counts = REVIEW_SEVERITIES.map(severity => `${report.comments.filter(c => c.severity === severity).length} ${severity}`).join(', ');
+  let summary = `${report.comments.length} comments (${counts}) on ${report.files} files in ${report.chunks} ${report.chunks === 1 ? 'query' : 'queries'}`;
+  if (report.usage) summary += `; ${formatUsage(report.model || '', report.usage)}`;
+  return summary;
+}
+
+// Compiler-style "file:line: severity: comment" lines, which editors and CI logs link to
+export function formatReviewText(report: ReviewReport): string {
+  let text = report.comments.map(c => `${c.file}:${c.line}: ${c.severity}: ${c.comment.replace(/\n/g, ' ')} ${footnotes(c)}`.trimEnd()).join('\n');
+  if (report.citations.length > 0) {
+    text += '\n\nSources:\n' + report.citations.map(c => `  [${c.index}] ${c.fileName}${c.store ? ` (${c.store})` : ''}`).join('\n');
+  }
+  for (const failure of report.failures) text += `\n\n⚠️  ${failure.file}: review failed: ${failure.error}`;
+  return `${text}\n\n${formatSummary(report)}`.trimStart();
+}
+
+export function formatReviewMarkdown(report: ReviewReport): string {
+  let markdown = '# Review\n\n';
+  const files = [...new Set(report.comments.map(c => c.file))];
+  if (files.length === 0) markdown += 'No comments.\n\n';
+  for (const file of files) {
+    markdown += `## ${file}\n\n`;
+    for (const comment of report.comments.filter(c => c.file === file)) {
+      markdown += `- **L${formatLines(comment)}** _${comment.severity}_: ${comment.comment.replace(/\n/g, '\n  ')} ${footnotes(comment)}`.trimEnd() + '\n';
+    }
+    markdown += '\n';
+  }
+  if (report.citations.length > 0) markdown += `## Citations\n\n${formatFootnotes(report.citations)}\n\n`;
+  if (report.skipped.length > 0) markdown += `_Not reviewed: ${report.skipped.join(', ')}_\n\n`;
+  for (const failure of report.failures) markdown += `⚠️ ${failure.file}: review failed: ${failure.error}\n\n`;
+  return `${markdown}_${formatSummary(report)}_\n`;
+}
+
+// SARIF 2.1.0: one result per comment, located at its lines, with the cited documents as related locations
+export function formatReviewSarif(report: ReviewReport): string {
+  const results = report.comments.map(comment => ({
+    ruleId: `review-${comment.severity}`,
+    level: comment.severity,
+    message: { text: comment.comment },
+    locations: [{
+      physicalLocation: {
+        artifactLocation: { uri: comment.file },
+        region: { startLine: comment.line, endLine: comment.endLine || comment.line },
+      },
+    }],
+    relatedLocations: comment.citations.map(number => {
+      const citation = report.citations[number - 1];
+      return {
+        id: number,
+        message: { text: citation.chunkText.slice(0, 200) },
+        physicalLocation: { artifactLocation: { uri: citation.fileName } },
+      };
+    }),
+  }));
+  return JSON.stringify({
+    $schema: 'https://json.schemastore.org/sarif-2.1.0.json',
+    version: '2.1.0',
+    runs: [{
+      tool: {
+        driver: {
+          name: 'gemini-file-search-mcp-server',
+          version: '1.0.0',
+          rules: REVIEW_SEVERITIES.map(severity => ({ id: `review-${severity}`, shortDescription: { text: `Review comment (${severity})` } })),
+        },
+      },
+      results,
+      properties: { stores: report.stores, model: report.model, usage: report.usage, skipped: report.skipped, failures: report.failures },
+    }],
+  }, null, 2);
+}
+
+export function formatReview(report: ReviewReport, format: ReviewFormat = 'markdown'): string {
+  switch (format) {
+    case 'text':
+      return formatReviewText(report);
+    case 'json':
+      return JSON.stringify(report, null, 2);
+    case 'sarif':
+      return formatReviewSarif(report);
+    case 'markdown':
+      return formatReviewMarkdown(report);
+    default:
+      throw new InvalidInputError(`Unknown review format: ${format as string}`);
+  }
+}
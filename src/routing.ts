@@ -0,0 +1,160 @@
+// routing.ts - Picks the stores relevant to a question before it is searched
+import { FileSearchClient } from './client.js';
+import { ConfigError } from './errors.js';
+import { TomlValue, expectTable, isTable } from './toml.js';
+
+// Types
+export type RoutingMode = 'off' | 'keywords' | 'model' | 'auto';
+
+export interface RoutingRule {
+  target: string;  // Store name, or project ID or name
+  keywords: string[];
+}
+
+export interface RoutingConfig {
+  mode: RoutingMode;
+  maxStores: number;  // Most stores a question is routed to
+  model?: string;  // Classifier model; default: the client's model
+  rules: RoutingRule[];
+}
+
+export interface RouteTarget {
+  storeName: string;
+  label: string;
+  description?: string;  // Shown to the classifier
+  aliases?: string[];  // Project ID and name, matched against rule targets
+}
+
+export interface RouteDecision<T extends RouteTarget = RouteTarget> {
+  targets: T[];
+  method: 'keywords' | 'model' | 'all';
+  reason: string;
+}
+
+// off: search every store; keywords: config rules only; model: classifier call only;
+// auto: rules first, the classifier when no rule matches
+export const ROUTING_MODES: RoutingMode[] = ['off', 'keywords', 'model', 'auto'];
+
+export const DEFAULT_MAX_ROUTED_STORES = 2;
+
+export const DEFAULT_ROUTING_CONFIG: RoutingConfig = { mode: 'off', maxStores: DEFAULT_MAX_ROUTED_STORES, rules: [] };
+
+const CLASSIFIER_INSTRUCTION = [
+  'You route questions to knowledge bases.',
+  'Given a numbered list of knowledge bases and a question, reply with only a JSON array of the numbers of the knowledge bases most likely to contain the answer, most relevant first, e.g. [2, 1].',
+  'Reply [] when none of them is relevant.',
+].join(' ');
+
+export function parseRoutingConfig(value: TomlValue | undefined): RoutingConfig {
+  if (value === undefined) return { ...DEFAULT_ROUTING_CONFIG };
+  expectTable(value, 'routing');
+
+  const mode = value.mode ?? (value.rules !== undefined ? 'auto' : 'off');
+  if (!ROUTING_MODES.includes(mode as RoutingMode)) {
+    throw new ConfigError(`routing.mode must be one of: ${ROUTING_MODES.join(', ')}`);
+  }
+  const maxStores = value.max_stores ?? DEFAULT_MAX_ROUTED_STORES;
+  if (typeof maxStores !== 'number' || !Number.isInteger(maxStores) || maxStores < 1) {
+    throw new ConfigError('routing.max_stores must be a positive integer');
+  }
+  if (value.model !== undefined && (typeof value.model !== 'string' || !value.model)) {
+    throw new ConfigError('routing.model must be a non-empty string');
+  }
+
+  const rules: RoutingRule[] = [];
+  const rawRules = value.rules ?? [];
+  if (!Array.isArray(rawRules)) throw new ConfigError('routing.rules must be [[routing.rules]] entries');
+  for (const [index, rule] of rawRules.entries()) {
+    const target = isTable(rule) ? rule.store ?? rule.project : undefined;
+    const keywords = isTable(rule) ? rule.keywords : undefined;
+    if (typeof target !== 'string' || !target) {
+      throw new ConfigError(`routing.rules[${index}]: store (or project) must be a non-empty string`);
+    }
+    if (!Array.isArray(keywords) || keywords.length === 0 || !keywords.every(keyword => typeof keyword === 'string' && keyword)) {
+      throw new ConfigError(`routing.rules[${index}]: keywords must be a non-empty list of strings`);
+    }
+    rules.push({ target, keywords: keywords as string[] });
+  }
+  return { mode: mode as RoutingMode, maxStores, model: value.model as string | undefined, rules };
+}
+
+function escapeRegExp(text: string): string {
+  return text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
+
+// Whole words for ASCII keywords ("api" does not match "capital"); substrings otherwise,
+// since scripts such as Japanese do not separate words with spaces
+function containsKeyword(question: string, keyword: string): boolean {
+  const needle = keyword.toLowerCase();
+  if (!/^[\x20-\x7e]+$/.test(needle)) return question.includes(needle);
+  return new RegExp(`(^|[^a-z0-9_])${escapeRegExp(needle)}($|[^a-z0-9_])`).test(question);
+}
+
+function ruleMatches(rule: RoutingRule, target: RouteTarget): boolean {
+  return rule.target === target.storeName || rule.target === target.label || (target.aliases || []).includes(rule.target);
+}
+
+// Stores ranked by the number of their keywords found in the question
+export function routeByKeywords<T extends RouteTarget>(question: string, targets: T[], config: RoutingConfig): RouteDecision<T> | undefined {
+  const lower = question.toLowerCase();
+  const scored = targets.map(target => {
+    const matched = config.rules
+      .filter(rule => ruleMatches(rule, target))
+      .flatMap(rule => rule.keywords.filter(keyword => containsKeyword(lower, keyword)));
+    return { target, matched };
+  }).filter(entry => entry.matched.length > 0);
+  if (scored.length === 0) return undefined;
+
+  scored.sort((a, b) => b.matched.length - a.matched.length);
+  const picked = scored.slice(0, config.maxStores);
+  return {
+    targets: picked.map(entry => entry.target),
+    method: 'keywords',
+    reason: picked.map(entry => `${entry.target.label} (${entry.matched.join(', ')})`).join('; '),
+  };
+}
+
+export async function routeByModel<T extends RouteTarget>(
+  client: FileSearchClient,
+  question: string,
+  targets: T[],
+  config: RoutingConfig
+): Promise<RouteDecision<T> | undefined> {
+  const list = targets.map((target, i) => `${i + 1}. ${target.label}${target.description ? `: ${target.description}` : ''}`).join('\n');
+  const reply = await client.generate(`Knowledge bases:\n${list}\n\nQuestion: ${question}`, {
+    model: config.model,
+    systemInstruction: CLASSIFIER_INSTRUCTION,
+  });
+  const indices = (reply.match(/\[[^\]]*\]/)?.[0].match(/\d+/g) || []).map(Number);
+  const picked = [...new Set(indices)]
+    .filter(index => index >= 1 && index <= targets.length)
+    .slice(0, config.maxStores)
+    .map(index => targets[index - 1]);
+  if (picked.length === 0) return undefined;
+  return { targets: picked, method: 'model', reason: `classifier picked ${picked.map(target => target.label).join(', ')}` };
+}
+
+// Narrows targets to the stores relevant to the question. Falls back to every target
+// when routing is off, nothing matches or the classifier call fails, so routing can
+// only make a search cheaper, never leave it without stores.
+export async function routeQuestion<T extends RouteTarget>(
+  client: FileSearchClient,
+  question: string,
+  targets: T[],
+  config: RoutingConfig
+): Promise<RouteDecision<T>> {
+  const all: RouteDecision<T> = { targets, method: 'all', reason: 'no route matched' };
+  if (config.mode === 'off' || targets.length <= 1) return { ...all, reason: 'routing off' };
+
+  if (config.mode === 'keywords' || config.mode === 'auto') {
+    const decision = routeByKeywords(question, targets, config);
+    if (decision || config.mode === 'keywords') return decision || all;
+  }
+  try {
+    return await routeByModel(client, question, targets, config) || all;
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Routing classifier failed, searching every store: ${err.message}`);
+    return { ...all, reason: 'classifier failed' };
+  }
+}
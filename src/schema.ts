@@ -0,0 +1,148 @@
+// schema.ts - JSON Schema validation of structured answers
+import * as fs from 'fs';
+import { InvalidInputError, SchemaValidationError } from './errors.js';
+
+// Types
+export type JsonSchemaType = 'object' | 'array' | 'string' | 'number' | 'integer' | 'boolean' | 'null';
+
+// The subset of JSON Schema that Gemini response schemas use
+export interface JsonSchema {
+  type?: JsonSchemaType | JsonSchemaType[];
+  description?: string;
+  properties?: { [name: string]: JsonSchema };
+  required?: string[];
+  additionalProperties?: boolean | JsonSchema;
+  items?: JsonSchema;
+  enum?: unknown[];
+  const?: unknown;
+  minimum?: number;
+  maximum?: number;
+  exclusiveMinimum?: number;
+  exclusiveMaximum?: number;
+  minLength?: number;
+  maxLength?: number;
+  pattern?: string;
+  minItems?: number;
+  maxItems?: number;
+  anyOf?: JsonSchema[];
+  oneOf?: JsonSchema[];
+  allOf?: JsonSchema[];
+  $ref?: string;  // Local references only: "#/$defs/<name>" or "#/definitions/<name>"
+  $defs?: { [name: string]: JsonSchema };
+  definitions?: { [name: string]: JsonSchema };
+}
+
+export function loadJsonSchema(filePath: string): JsonSchema {
+  let schema: unknown;
+  try {
+    schema = JSON.parse(fs.readFileSync(filePath, 'utf8'));
+  } catch (error) {
+    throw new InvalidInputError(`Could not read JSON schema ${filePath}: ${(error as Error).message}`, { cause: error });
+  }
+  if (typeof schema !== 'object' || schema === null || Array.isArray(schema)) {
+    throw new InvalidInputError(`JSON schema ${filePath} must be an object`);
+  }
+  return schema as JsonSchema;
+}
+
+function typeOf(value: unknown): JsonSchemaType {
+  if (value === null) return 'null';
+  if (Array.isArray(value)) return 'array';
+  if (typeof value === 'number') return Number.isInteger(value) ? 'integer' : 'number';
+  return typeof value as JsonSchemaType;
+}
+
+function resolveRef(root: JsonSchema, ref: string): JsonSchema {
+  const match = /^#\/(\$defs|definitions)\/(.+)$/.exec(ref);
+  const target = match ? root[match[1] as '$defs' | 'definitions']?.[match[2]] : undefined;
+  if (!target) throw new InvalidInputError(`Unresolved schema reference: ${ref}`);
+  return target;
+}
+
+function check(value: unknown, schema: JsonSchema, root: JsonSchema, at: string, errors: string[]): void {
+  if (schema.$ref) {
+    check(value, resolveRef(root, schema.$ref), root, at, errors);
+    return;
+  }
+
+  const actual = typeOf(value);
+  if (schema.type) {
+    const allowed = Array.isArray(schema.type) ? schema.type : [schema.type];
+    if (!allowed.includes(actual) && !(actual === 'integer' && allowed.includes('number'))) {
+      errors.push(`${at}: expected ${allowed.join(' or ')}, got ${actual}`);
+      return;
+    }
+  }
+  if (schema.enum && !schema.enum.some(option => JSON.stringify(option) === JSON.stringify(value))) {
+    errors.push(`${at}: must be one of ${schema.enum.map(option => JSON.stringify(option)).join(', ')}`);
+  }
+  if (schema.const !== undefined && JSON.stringify(schema.const) !== JSON.stringify(value)) {
+    errors.push(`${at}: must be ${JSON.stringify(schema.const)}`);
+  }
+
+  if (typeof value === 'number') {
+    if (schema.minimum !== undefined && value < schema.minimum) errors.push(`${at}: must be >= ${schema.minimum}`);
+    if (schema.maximum !== undefined && value > schema.maximum) errors.push(`${at}: must be <= ${schema.maximum}`);
+    if (schema.exclusiveMinimum !== undefined && value <= schema.exclusiveMinimum) errors.push(`${at}: must be > ${schema.exclusiveMinimum}`);
+    if (schema.exclusiveMaximum !== undefined && value >= schema.exclusiveMaximum) errors.push(`${at}: must be < ${schema.exclusiveMaximum}`);
+  }
+  if (typeof value === 'string') {
+    if (schema.minLength !== undefined && value.length < schema.minLength) errors.push(`${at}: must be at least ${schema.minLength} characters`);
+    if (schema.maxLength !== undefined && value.length > schema.maxLength) errors.push(`${at}: must be at most ${schema.maxLength} characters`);
+    if (schema.pattern !== undefined && !new RegExp(schema.pattern, 'u').test(value)) errors.push(`${at}: must match ${schema.pattern}`);
+  }
+  if (Array.isArray(value)) {
+    if (schema.minItems !== undefined && value.length < schema.minItems) errors.push(`${at}: must have at least ${schema.minItems} items`);
+    if (schema.maxItems !== undefined && value.length > schema.maxItems) errors.push(`${at}: must have at most ${schema.maxItems} items`);
+    const items = schema.items;
+    if (items) value.forEach((item, i) => check(item, items, root, `${at}[${i}]`, errors));
+  }
+  if (actual === 'object') {
+    const object = value as { [key: string]: unknown };
+    for (const name of schema.required || []) {
+      if (!(name in object)) errors.push(`${at}: missing required property "${name}"`);
+    }
+    for (const [name, item] of Object.entries(object)) {
+      const property = schema.properties?.[name];
+      if (property) {
+        check(item, property, root, `${at}.${name}`, errors);
+      } else if (schema.additionalProperties === false) {
+        errors.push(`${at}: unexpected property "${name}"`);
+      } else if (typeof schema.additionalProperties === 'object') {
+        check(item, schema.additionalProperties, root, `${at}.${name}`, errors);
+      }
+    }
+  }
+
+  for (const sub of schema.allOf || []) check(value, sub, root, at, errors);
+  const matching = (options: JsonSchema[]) => options.filter(option => {
+    const optionErrors: string[] = [];
+    check(value, option, root, at, optionErrors);
+    return optionErrors.length === 0;
+  }).length;
+  if (schema.anyOf && matching(schema.anyOf) === 0) errors.push(`${at}: does not match any allowed schema`);
+  if (schema.oneOf && matching(schema.oneOf) !== 1) errors.push(`${at}: must match exactly one allowed schema`);
+}
+
+// Returns every violation as "<path>: <message>", with "$" for the root; empty when the value is valid
+export function validateJsonSchema(value: unknown, schema: JsonSchema): string[] {
+  const errors: string[] = [];
+  check(value, schema, schema, '$', errors);
+  return errors;
+}
+
+// Parses a structured answer and checks it against the schema it was requested with.
+// T is not checked by the compiler; keep it in line with the schema.
+export function parseStructuredAnswer<T = unknown>(text: string, schema: JsonSchema): T {
+  // Some models wrap JSON in a Markdown code fence despite the response MIME type
+  const json = text.trim().replace(/^```(?:json)?\s*\n([\s\S]*?)\n?```$/, '$1');
+  let value: unknown;
+  try {
+    value = JSON.parse(json);
+  } catch (error) {
+    throw new SchemaValidationError([`$: not valid JSON (${(error as Error).message})`]);
+  }
+  const errors = validateJsonSchema(value, schema);
+  if (errors.length > 0) throw new SchemaValidationError(errors);
+  return value as T;
+}
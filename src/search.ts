@@ -0,0 +1,256 @@
+// search.ts - Metadata filter expressions for File Search queries
+//
+// Translates a compact expression such as
+//   lang=rust AND (team=backend OR team=infra) AND NOT stage=draft
+// into the AIP-160 syntax expected by FileSearch.metadataFilter:
+//   lang = "rust" AND (team = "backend" OR team = "infra") AND NOT stage = "draft"
+// The same expressions select documents locally by their custom metadata.
+
+import { InvalidInputError } from './errors.js';
+import { DocumentMetadata, MetadataValue } from './metadata.js';
+
+// Types
+type Token =
+  | { kind: 'word'; value: string }
+  | { kind: 'string'; value: string }
+  | { kind: 'op'; value: string }
+  | { kind: 'lparen' }
+  | { kind: 'rparen' };
+
+type FilterNode =
+  | { kind: 'or' | 'and'; parts: FilterNode[] }
+  | { kind: 'not'; operand: FilterNode }
+  | { kind: 'group'; inner: FilterNode }
+  | { kind: 'compare'; key: string; op: string; literal: string; value: string | number };
+
+const COMPARISON_OPERATORS = ['<=', '>=', '!=', '=', '<', '>', ':'];
+
+export class FilterSyntaxError extends InvalidInputError {
+  constructor(message: string, readonly position: number) {
+    super(`Invalid filter at position ${position}: ${message}`);
+    this.name = 'FilterSyntaxError';
+  }
+}
+
+function tokenize(expression: string): { tokens: Token[]; positions: number[] } {
+  const tokens: Token[] = [];
+  const positions: number[] = [];
+  let i = 0;
+
+  while (i < expression.length) {
+    const c = expression[i];
+    if (/\s/.test(c)) {
+      i++;
+      continue;
+    }
+
+    positions.push(i);
+    if (c === '(') {
+      tokens.push({ kind: 'lparen' });
+      i++;
+    } else if (c === ')') {
+      tokens.push({ kind: 'rparen' });
+      i++;
+    } else if (c === '"' || c === "'") {
+      let value = '';
+      let j = i + 1;
+      while (j < expression.length && expression[j] !== c) {
+        if (expression[j] === '\\' && j + 1 < expression.length) j++;
+        value += expression[j++];
+      }
+      if (j >= expression.length) {
+        throw new FilterSyntaxError('unterminated string', i);
+      }
+      tokens.push({ kind: 'string', value });
+      i = j + 1;
+    } else {
+      const op = COMPARISON_OPERATORS.find(candidate => expression.startsWith(candidate, i));
+      if (op) {
+        tokens.push({ kind: 'op', value: op });
+        i += op.length;
+      } else {
+        const match = /^[^\s()"'<>=!:]+/.exec(expression.slice(i));
+        if (!match) {
+          throw new FilterSyntaxError(`unexpected "${c}"`, i);
+        }
+        tokens.push({ kind: 'word', value: match[0] });
+        i += match[0].length;
+      }
+    }
+  }
+
+  return { tokens, positions };
+}
+
+function isKeyword(token: Token | undefined, keyword: string): boolean {
+  return token?.kind === 'word' && token.value.toUpperCase() === keyword;
+}
+
+function quote(value: string): string {
+  return `"${value.replace(/\\/g, '\\\\').replace(/"/g, '\\"')}"`;
+}
+
+class FilterParser {
+  private index = 0;
+
+  constructor(
+    private readonly tokens: Token[],
+    private readonly positions: number[],
+    private readonly length: number
+  ) {}
+
+  parse(): FilterNode {
+    const result = this.parseOr();
+    if (this.index < this.tokens.length) {
+      throw this.error('unexpected token');
+    }
+    return result;
+  }
+
+  private error(message: string): FilterSyntaxError {
+    return new FilterSyntaxError(message, this.positions[this.index] ?? this.length);
+  }
+
+  private parseOr(): FilterNode {
+    const parts = [this.parseAnd()];
+    while (isKeyword(this.tokens[this.index], 'OR')) {
+      this.index++;
+      parts.push(this.parseAnd());
+    }
+    return parts.length === 1 ? parts[0] : { kind: 'or', parts };
+  }
+
+  private parseAnd(): FilterNode {
+    const parts = [this.parseUnary()];
+    while (isKeyword(this.tokens[this.index], 'AND')) {
+      this.index++;
+      parts.push(this.parseUnary());
+    }
+    return parts.length === 1 ? parts[0] : { kind: 'and', parts };
+  }
+
+  private parseUnary(): FilterNode {
+    if (isKeyword(this.tokens[this.index], 'NOT')) {
+      this.index++;
+      return { kind: 'not', operand: this.parseUnary() };
+    }
+    return this.parsePrimary();
+  }
+
+  private parsePrimary(): FilterNode {
+    const token = this.tokens[this.index];
+    if (!token) {
+      throw this.error('unexpected end of expression');
+    }
+
+    if (token.kind === 'lparen') {
+      this.index++;
+      const inner = this.parseOr();
+      if (this.tokens[this.index]?.kind !== 'rparen') {
+        throw this.error('expected ")"');
+      }
+      this.index++;
+      return { kind: 'group', inner };
+    }
+
+    if (token.kind !== 'word' || ['AND', 'OR', 'NOT'].includes(token.value.toUpperCase())) {
+      throw this.error('expected a metadata key');
+    }
+    const key = token.value;
+    this.index++;
+
+    const op = this.tokens[this.index];
+    if (!op || op.kind !== 'op') {
+      throw this.error(`expected a comparison after "${key}"`);
+    }
+    this.index++;
+
+    const value = this.tokens[this.index];
+    if (!value || (value.kind !== 'word' && value.kind !== 'string')) {
+      throw this.error(`expected a value for "${key}"`);
+    }
+    this.index++;
+
+    // Bare numbers compare numerically; everything else is a string literal
+    const numeric = value.kind === 'word' && /^-?\d+(\.\d+)?$/.test(value.value);
+    return {
+      kind: 'compare',
+      key,
+      op: op.value,
+      literal: numeric ? value.value : quote(value.value),
+      value: numeric ? Number(value.value) : value.value,
+    };
+  }
+}
+
+function parse(expression: string): FilterNode {
+  const { tokens, positions } = tokenize(expression);
+  if (tokens.length === 0) {
+    throw new FilterSyntaxError('empty expression', 0);
+  }
+  return new FilterParser(tokens, positions, expression.length).parse();
+}
+
+function render(node: FilterNode): string {
+  switch (node.kind) {
+    case 'or':
+      return node.parts.map(render).join(' OR ');
+    case 'and':
+      return node.parts.map(render).join(' AND ');
+    case 'not':
+      return `NOT ${render(node.operand)}`;
+    case 'group':
+      return `(${render(node.inner)})`;
+    case 'compare':
+      return `${node.key} ${node.op} ${node.literal}`;
+  }
+}
+
+// Lists match when any element does; ":" matches list elements and substrings
+function compare(actual: MetadataValue, op: string, expected: string | number): boolean {
+  if (Array.isArray(actual)) return actual.some(item => compare(item, op, expected));
+  if (op === ':') return String(actual).includes(String(expected));
+  const order = typeof actual === 'number' && typeof expected === 'number'
+    ? actual - expected
+    : String(actual).localeCompare(String(expected));
+  switch (op) {
+    case '=': return order === 0;
+    case '!=': return order !== 0;
+    case '<': return order < 0;
+    case '<=': return order <= 0;
+    case '>': return order > 0;
+    default: return order >= 0;
+  }
+}
+
+function evaluate(node: FilterNode, metadata: DocumentMetadata): boolean {
+  switch (node.kind) {
+    case 'or':
+      return node.parts.some(part => evaluate(part, metadata));
+    case 'and':
+      return node.parts.every(part => evaluate(part, metadata));
+    case 'not':
+      return !evaluate(node.operand, metadata);
+    case 'group':
+      return evaluate(node.inner, metadata);
+    case 'compare': {
+      const actual = metadata[node.key] ?? metadata[node.key.replace(/^metadata\./, '')];
+      if (actual === undefined) return node.op === '!=';
+      // "!=" on a list holds when no element equals the value
+      if (node.op === '!=' && Array.isArray(actual)) return !compare(actual, '=', node.value);
+      return compare(actual, node.op, node.value);
+    }
+  }
+}
+
+// Returns the AIP-160 filter string for a metadata expression
+export function parseFilterExpression(expression: string): string {
+  return render(parse(expression));
+}
+
+// Returns a predicate over custom metadata for the same expressions, such as
+// "metadata.team=frontend"; keys may carry a "metadata." prefix
+export function metadataMatcher(expression: string): (metadata: DocumentMetadata) => boolean {
+  const node = parse(expression);
+  return metadata => evaluate(node, metadata);
+}
@@ -0,0 +1,235 @@
+// shards.ts - Corpora split across several stores by a hash of each document's path
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+import { FileSearchClient } from './client.js';
+import { CAPACITY_WARNING_RATIO, RECOMMENDED_STORE_BYTES } from './capacity.js';
+import { FileSearchError, InvalidInputError, NotFoundError, StoreNotFoundError, toFileSearchError } from './errors.js';
+import { DirectoryIngester, IngestProgress, IngestSummary, toDisplayName } from './ingest.js';
+import { Semaphore } from './pipeline.js';
+import { DEFAULT_MAX_STORE_DOCUMENTS } from './preflight.js';
+import { PendingChanges, withFileLock } from './lock.js';
+import { writeFileAtomic } from './shutdown.js';
+
+// Types
+export interface ShardSet {
+  corpus: string;
+  stores: string[];  // Store of shard i at index i
+  createdAt: string;
+}
+
+interface ShardMapData {
+  version: number;
+  corpora: { [corpus: string]: ShardSet };
+}
+
+export interface ShardCountOptions {
+  maxStoreDocuments?: number;  // Default: 10000
+  maxStoreBytes?: number;  // Stored size per shard, input plus embeddings (default: the recommended 20 GB)
+}
+
+export interface ShardUploadSummary {
+  storeName: string;
+  shard: number;
+  files: number;
+  summary: IngestSummary;
+}
+
+export const SHARD_MAP_FILE = '.gemini-shards.json';
+
+// Shard numbers are three digits
+export const MAX_SHARDS = 1000;
+
+export const DEFAULT_SHARD_CONCURRENCY = 4;
+
+// A store holds its input plus the embeddings, about three times the input
+const STORED_BYTES_PER_INPUT_BYTE = 3;
+
+// "mycorpus", 1 to "mycorpus-001"
+export function shardDisplayName(corpus: string, shard: number): string {
+  return `${corpus}-${String(shard).padStart(3, '0')}`;
+}
+
+// The shard of a document, from the SHA-256 of its display name: the same on every
+// machine and run, whatever order the files are found in
+export function shardOf(key: string, count: number): number {
+  const digest = createHash('sha256').update(key).digest();
+  return digest.readUIntBE(0, 6) % count;
+}
+
+export function partitionByShard<T>(items: T[], keyOf: (item: T) => string, count: number): T[][] {
+  const shards: T[][] = Array.from({ length: count }, () => []);
+  for (const item of items) shards[shardOf(keyOf(item), count)].push(item);
+  return shards;
+}
+
+// Shards for a corpus of this many files and input bytes, keeping every shard below
+// the share of the limits at which capacity warnings start
+export function shardsNeeded(files: number, inputBytes: number, options: ShardCountOptions = {}): number {
+  const maxDocuments = (options.maxStoreDocuments || DEFAULT_MAX_STORE_DOCUMENTS) * CAPACITY_WARNING_RATIO;
+  const maxBytes = (options.maxStoreBytes || RECOMMENDED_STORE_BYTES) * CAPACITY_WARNING_RATIO;
+  const count = Math.max(1, Math.ceil(files / maxDocuments), Math.ceil((inputBytes * STORED_BYTES_PER_INPUT_BYTE) / maxBytes));
+  if (count > MAX_SHARDS) {
+    throw new InvalidInputError(`The corpus needs ${count} shards, more than the ${MAX_SHARDS} supported`);
+  }
+  return count;
+}
+
+function validateCorpus(corpus: string): void {
+  if (!corpus || corpus.trim() !== corpus || corpus.includes('/')) {
+    throw new InvalidInputError(`Invalid corpus name "${corpus}": use a name without slashes or surrounding spaces`);
+  }
+}
+
+// Local record of which stores make up each sharded corpus. Queries name the corpus
+// and expand() turns it into its shard stores.
+export class ShardMap {
+  private data: ShardMapData;
+  private readonly changes = new PendingChanges<ShardSet>();
+
+  constructor(private readonly filePath: string) {
+    this.data = this.load();
+  }
+
+  get(corpus: string): ShardSet | undefined {
+    return this.data.corpora[corpus];
+  }
+
+  list(): ShardSet[] {
+    return Object.values(this.data.corpora);
+  }
+
+  record(set: ShardSet): void {
+    this.data.corpora[set.corpus] = set;
+    this.changes.set(set.corpus, set);
+    this.save();
+  }
+
+  remove(corpus: string): void {
+    if (this.data.corpora[corpus]) {
+      delete this.data.corpora[corpus];
+      this.changes.delete(corpus);
+      this.save();
+    }
+  }
+
+  // Corpus of a shard store, if it is one
+  corpusOf(storeName: string): string | undefined {
+    return this.list().find(set => set.stores.includes(storeName))?.corpus;
+  }
+
+  // Corpus names replaced by their shard stores; store names are kept, duplicates dropped
+  expand(names: string[]): string[] {
+    const stores = names.flatMap(name => this.data.corpora[name]?.stores || [name]);
+    return [...new Set(stores)];
+  }
+
+  private load(): ShardMapData {
+    try {
+      return JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as ShardMapData;
+    } catch (error) {
+      return { version: 1, corpora: {} };
+    }
+  }
+
+  // Merged under the map's lock with the corpora other runs recorded since it was loaded
+  private save(): void {
+    withFileLock(this.filePath, () => {
+      const current = this.load();
+      this.changes.applyTo(current.corpora);
+      this.changes.clear();
+      this.data = current;
+      writeFileAtomic(this.filePath, JSON.stringify(this.data, null, 2));
+    });
+  }
+}
+
+// Creates the shard stores concurrently and records them. Stores left over from an
+// interrupted run are found by display name and reused, so running it again completes
+// the set. An existing corpus is returned as it is; its shard count cannot change,
+// since that would move most documents to another shard.
+export async function createShards(
+  client: FileSearchClient,
+  map: ShardMap,
+  corpus: string,
+  count: number,
+  options: { concurrency?: number } = {}
+): Promise<ShardSet> {
+  validateCorpus(corpus);
+  if (!Number.isInteger(count) || count < 1 || count > MAX_SHARDS) {
+    throw new InvalidInputError(`Shard count must be an integer between 1 and ${MAX_SHARDS}, got ${count}`);
+  }
+  const existing = map.get(corpus);
+  if (existing) {
+    if (existing.stores.length !== count) {
+      throw new InvalidInputError(`Corpus "${corpus}" already has ${existing.stores.length} shards; delete it and upload again to use ${count}`);
+    }
+    return existing;
+  }
+
+  const byDisplayName = new Map((await client.stores.listStores()).map(store => [store.displayName, store.name]));
+  const stores: string[] = [];
+  const failures: FileSearchError[] = [];
+  const semaphore = new Semaphore(options.concurrency || DEFAULT_SHARD_CONCURRENCY);
+  await Promise.all(Array.from({ length: count }, (_, shard) => semaphore.run(async () => {
+    const displayName = shardDisplayName(corpus, shard);
+    const reused = byDisplayName.get(displayName);
+    try {
+      stores[shard] = reused || await client.createStore(displayName);
+    } catch (error) {
+      failures.push(toFileSearchError(error));
+    }
+  })));
+  if (failures.length > 0) {
+    const [first] = failures;
+    throw new FileSearchError(first.code, `${failures.length} of ${count} shard stores of "${corpus}" could not be created; run again to retry: ${first.message}`, { cause: first });
+  }
+
+  const set: ShardSet = { corpus, stores, createdAt: new Date().toISOString() };
+  map.record(set);
+  return set;
+}
+
+// Uploads each file to the shard of its display name, one shard after the other so the
+// ingester's concurrency and rate limit apply to the whole corpus
+export async function uploadSharded(
+  client: FileSearchClient,
+  ingester: DirectoryIngester,
+  set: ShardSet,
+  rootDir: string,
+  files: string[],
+  onProgress?: (shard: number, progress: IngestProgress) => void
+): Promise<ShardUploadSummary[]> {
+  const shards = partitionByShard(files, filePath => toDisplayName(rootDir, filePath), set.stores.length);
+  const summaries: ShardUploadSummary[] = [];
+  for (const [shard, shardFiles] of shards.entries()) {
+    if (shardFiles.length === 0) continue;
+    const storeName = set.stores[shard];
+    const summary = await ingester.upload(client, storeName, rootDir, shardFiles, progress => onProgress?.(shard, progress));
+    summaries.push({ storeName, shard, files: shardFiles.length, summary });
+    if (summary.remaining.length > 0) break;  // Cancelled
+  }
+  return summaries;
+}
+
+// Deletes the shard stores with their documents, then forgets the corpus
+export async function deleteShards(client: FileSearchClient, map: ShardMap, corpus: string, options: { concurrency?: number } = {}): Promise<ShardSet> {
+  const set = map.get(corpus);
+  if (!set) throw new NotFoundError(`sharded corpus ${corpus}`);
+  const semaphore = new Semaphore(options.concurrency || DEFAULT_SHARD_CONCURRENCY);
+  const failures: FileSearchError[] = [];
+  await Promise.all(set.stores.map(storeName => semaphore.run(async () => {
+    try {
+      await client.deleteStore(storeName);
+    } catch (error) {
+      const err = toFileSearchError(error, storeName);
+      if (!(err instanceof StoreNotFoundError)) failures.push(err);  // Deleted by an earlier run
+    }
+  })));
+  // The map is kept until every store is gone, so running it again finishes the job
+  if (failures.length > 0) {
+    const [first] = failures;
+    throw new FileSearchError(first.code, `${failures.length} of ${set.stores.length} shard stores of "${corpus}" could not be deleted; run again to retry: ${first.message}`, { cause: first });
+  }
+  map.remove(corpus);
+  return set;
+}
@@ -0,0 +1,113 @@
+// shutdown.ts - Graceful SIGINT/SIGTERM handling: drain in-flight work, flush state, then exit
+import * as fs from 'fs';
+import { CancelledError, EXIT_CODES } from './errors.js';
+
+// Types
+export type Flusher = () => void | Promise<void>;
+
+// The first signal stops new work from starting and waits for the running jobs, the
+// second aborts their in-flight requests, and a third exits without waiting for them.
+// Flushers (manifests, checkpoints, telemetry) run before the process exits.
+export class Shutdown {
+  private readonly drainController = new AbortController();
+  private readonly abortController = new AbortController();
+  private readonly running = new Set<Promise<unknown>>();
+  private readonly flushers = new Set<Flusher>();
+  private signals = 0;
+  private installed = false;
+  private exiting = false;
+  private interrupted = false;
+
+  // Aborted by the first signal: finish what is running, start nothing new
+  get signal(): AbortSignal {
+    return this.drainController.signal;
+  }
+
+  // Aborted by the second signal: cancel in-flight requests
+  get abortSignal(): AbortSignal {
+    return this.abortController.signal;
+  }
+
+  get requested(): boolean {
+    return this.signals > 0;
+  }
+
+  install(): this {
+    if (this.installed) return this;
+    this.installed = true;
+    for (const name of ['SIGINT', 'SIGTERM'] as const) {
+      process.on(name, () => this.handle(name));
+    }
+    return this;
+  }
+
+  // Keeps the process alive on the first signal until work settles
+  track<T>(work: Promise<T>): Promise<T> {
+    this.running.add(work);
+    const settle = () => {
+      this.running.delete(work);
+    };
+    work.then(settle, settle);
+    return work;
+  }
+
+  // Returns a function that unregisters the flusher
+  onFlush(flusher: Flusher): () => void {
+    this.flushers.add(flusher);
+    return () => {
+      this.flushers.delete(flusher);
+    };
+  }
+
+  private handle(name: NodeJS.Signals): void {
+    this.signals++;
+    if (this.signals === 1) {
+      this.interrupted = this.running.size > 0;
+      if (this.interrupted) {
+        console.error(`\n⏹️  ${name}: finishing ${this.running.size} running job(s) without starting new files (repeat to abort in-flight uploads)`);
+      }
+      this.drainController.abort(new CancelledError(`Interrupted by ${name}`));
+      void this.exitWhenIdle();
+    } else if (this.signals === 2) {
+      console.error('⏹️  Aborting in-flight uploads; interrupted files are resumed by the next run (repeat to quit now)');
+      this.abortController.abort(new CancelledError(`Aborted by ${name}`));
+    } else {
+      console.error('⏹️  Quitting without waiting for running jobs');
+      void this.exit();
+    }
+  }
+
+  private async exitWhenIdle(): Promise<void> {
+    // Work tracked after the first signal is awaited too
+    while (this.running.size > 0) {
+      await Promise.allSettled([...this.running]);
+    }
+    await this.exit();
+  }
+
+  private async exit(): Promise<void> {
+    const code = this.interrupted ? EXIT_CODES.CANCELLED : 0;
+    if (this.exiting) process.exit(code);
+    this.exiting = true;
+    for (const flusher of this.flushers) {
+      try {
+        await flusher();
+      } catch (error) {
+        const err = error as Error;
+        console.error(`⚠️  Flush on shutdown failed: ${err.message}`);
+      }
+    }
+    process.exit(code);
+  }
+}
+
+// Shared by every pipeline in the process, like telemetry
+export const shutdown = new Shutdown();
+
+// Writes through a temporary file and a rename, so a process killed mid-write
+// leaves either the old or the new content behind, never a truncated file
+export function writeFileAtomic(filePath: string, data: string): void {
+  const tempPath = `${filePath}.${process.pid}.tmp`;
+  fs.writeFileSync(tempPath, data);
+  fs.renameSync(tempPath, filePath);
+}
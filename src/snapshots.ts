@@ -0,0 +1,183 @@
+// snapshots.ts - Versions of the sync manifest, kept with the file content they were uploaded from
+import * as fs from 'fs';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { hashFile } from './cache.js';
+import { resolvePathOf } from './expand.js';
+import { writeFileAtomic } from './shutdown.js';
+import { InvalidInputError, NotFoundError } from './errors.js';
+import { ManifestEntry, SyncManifest } from './sync.js';
+import { syncStatePath } from './tenant.js';
+
+// Types
+export interface Snapshot {
+  version: number;
+  id: number;  // Increasing within the directory
+  storeName: string;
+  createdAt: string;  // ISO 8601
+  reason: string;  // What produced it: "upload", "sync" or "rollback to <id>"
+  files: { [relativePath: string]: ManifestEntry };
+}
+
+export interface SnapshotInfo {
+  id: number;
+  storeName: string;
+  createdAt: string;
+  reason: string;
+  fileCount: number;
+  documentCount: number;
+}
+
+export interface RollbackPlan {
+  snapshot: number;
+  restored: string[];  // Relative paths removed or changed since the snapshot, uploaded again with their content then
+  deleted: string[];  // Added since the snapshot; their documents are deleted
+  unchanged: string[];
+  missing: string[];  // Changed or removed, but their content at the snapshot was not kept; left as they are
+}
+
+export const SNAPSHOTS_DIR = '.gemini-sync-snapshots';
+export const DEFAULT_MAX_SNAPSHOTS = 20;
+
+const OBJECTS_DIR = 'objects';
+const SNAPSHOT_FILE = /^(\d+)\.json$/;
+
+function documentNamesOf(entry: ManifestEntry): string[] {
+  return entry.documentNames || (entry.documentName ? [entry.documentName] : []);
+}
+
+// Whether two manifests name the same content and documents for every file
+function sameFiles(a: Snapshot['files'], b: Snapshot['files']): boolean {
+  const keys = Object.keys(a);
+  if (keys.length !== Object.keys(b).length) return false;
+  return keys.every(key => b[key] && a[key].hash === b[key].hash && documentNamesOf(a[key]).join('\n') === documentNamesOf(b[key]).join('\n'));
+}
+
+// One JSON file per snapshot in the .gemini-sync-snapshots/ of the synced directory's state, and
+// the content of every file they name in objects/, by SHA-256, so unchanged files are
+// stored once. The oldest snapshots beyond maxSnapshots are removed with the content
+// only they referenced.
+export class SnapshotStore {
+  private constructor(private readonly dir: string, private readonly maxSnapshots: number) {}
+
+  static open(rootDir: string, maxSnapshots: number = DEFAULT_MAX_SNAPSHOTS): SnapshotStore {
+    return new SnapshotStore(syncStatePath(rootDir, SNAPSHOTS_DIR), maxSnapshots);
+  }
+
+  // Newest first
+  list(storeName?: string): SnapshotInfo[] {
+    return this.ids()
+      .reverse()
+      .map(id => this.read(id))
+      .filter((snapshot): snapshot is Snapshot => !!snapshot && (!storeName || snapshot.storeName === storeName))
+      .map(snapshot => ({
+        id: snapshot.id,
+        storeName: snapshot.storeName,
+        createdAt: snapshot.createdAt,
+        reason: snapshot.reason,
+        fileCount: Object.keys(snapshot.files).length,
+        documentCount: Object.values(snapshot.files).reduce((sum, entry) => sum + documentNamesOf(entry).length, 0),
+      }));
+  }
+
+  get(id: number): Snapshot {
+    const snapshot = this.read(id);
+    if (!snapshot) throw new NotFoundError(`snapshot ${id}`);
+    return snapshot;
+  }
+
+  // Where the content with this hash is kept, undefined when it was not
+  contentPath(hash: string): string | undefined {
+    const objectPath = path.join(this.dir, OBJECTS_DIR, hash);
+    return hash && fs.existsSync(objectPath) ? objectPath : undefined;
+  }
+
+  // Records the manifest as the next snapshot, unless it names the same files and
+  // documents as the latest one of the store. Files whose content changed since they
+  // were uploaded are recorded without content and cannot be restored.
+  create(rootDir: string, manifest: SyncManifest, reason: string): Snapshot | undefined {
+    const files = manifest.entries();
+    const ids = this.ids();
+    const latest = [...ids].reverse().map(id => this.read(id)).find(snapshot => snapshot?.storeName === manifest.storeName);
+    if (latest && sameFiles(latest.files, files)) return undefined;
+
+    const objectsDir = path.join(this.dir, OBJECTS_DIR);
+    fs.mkdirSync(objectsDir, { recursive: true });
+    for (const [relativePath, entry] of Object.entries(files)) {
+      if (!entry.hash || this.contentPath(entry.hash)) continue;
+      const copyPath = path.join(objectsDir, `.tmp-${randomUUID()}`);
+      try {
+        fs.copyFileSync(resolvePathOf(rootDir, relativePath), copyPath);
+        if (hashFile(copyPath) === entry.hash) fs.renameSync(copyPath, path.join(objectsDir, entry.hash));
+      } catch (error) {
+        // A file removed since the upload: recorded without content
+      } finally {
+        fs.rmSync(copyPath, { force: true });
+      }
+    }
+
+    const snapshot: Snapshot = {
+      version: 1,
+      id: (ids[ids.length - 1] || 0) + 1,
+      storeName: manifest.storeName,
+      createdAt: new Date().toISOString(),
+      reason,
+      files,
+    };
+    writeFileAtomic(path.join(this.dir, `${snapshot.id}.json`), JSON.stringify(snapshot, null, 2));
+    this.prune();
+    return snapshot;
+  }
+
+  private ids(): number[] {
+    let names: string[];
+    try {
+      names = fs.readdirSync(this.dir);
+    } catch (error) {
+      return [];
+    }
+    return names.map(name => SNAPSHOT_FILE.exec(name)).filter((match): match is RegExpExecArray => !!match)
+      .map(match => Number(match[1]))
+      .sort((a, b) => a - b);
+  }
+
+  private read(id: number): Snapshot | undefined {
+    try {
+      return JSON.parse(fs.readFileSync(path.join(this.dir, `${id}.json`), 'utf8')) as Snapshot;
+    } catch (error) {
+      return undefined;
+    }
+  }
+
+  private prune(): void {
+    const ids = this.ids();
+    if (ids.length <= this.maxSnapshots) return;
+    for (const id of ids.slice(0, ids.length - this.maxSnapshots)) fs.rmSync(path.join(this.dir, `${id}.json`), { force: true });
+    const referenced = new Set(this.ids().flatMap(id => Object.values(this.read(id)?.files || {}).map(entry => entry.hash)));
+    const objectsDir = path.join(this.dir, OBJECTS_DIR);
+    for (const name of fs.readdirSync(objectsDir)) {
+      if (!referenced.has(name)) fs.rmSync(path.join(objectsDir, name), { force: true });
+    }
+  }
+}
+
+// The delta from the manifest back to the snapshot: files added since are deleted,
+// files changed or removed since are uploaded again in the version of the snapshot
+export function planRollback(manifest: SyncManifest, snapshot: Snapshot, store: SnapshotStore): RollbackPlan {
+  if (snapshot.storeName !== manifest.storeName) {
+    throw new InvalidInputError(`Snapshot ${snapshot.id} is of store ${snapshot.storeName}, not ${manifest.storeName}`);
+  }
+  const current = manifest.entries();
+  const plan: RollbackPlan = { snapshot: snapshot.id, restored: [], deleted: [], unchanged: [], missing: [] };
+  for (const [relativePath, entry] of Object.entries(snapshot.files)) {
+    if (current[relativePath]?.hash === entry.hash) {
+      plan.unchanged.push(relativePath);
+    } else if (store.contentPath(entry.hash)) {
+      plan.restored.push(relativePath);
+    } else {
+      plan.missing.push(relativePath);
+    }
+  }
+  plan.deleted = Object.keys(current).filter(relativePath => !snapshot.files[relativePath]);
+  return plan;
+}
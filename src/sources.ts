@@ -0,0 +1,379 @@
+// sources.ts - Google Drive folders and Cloud Storage prefixes as sources to ingest, through the same filters and upload pipeline as a directory
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { GoogleAuth } from 'google-auth-library';
+import { Document } from '@google/genai';
+import { FileSearchClient, UploadResult } from './client.js';
+import { DirectoryIngester, IngestOptions, IngestSummary, isExcludedByDefault, matchesGlob, toDisplayName } from './ingest.js';
+import { DocumentMetadata } from './metadata.js';
+import { archiveMemberOf, isArchive, relativePathOf } from './expand.js';
+import { AccessDeniedError, HttpStatusError, InvalidInputError, NotFoundError, toFileSearchError } from './errors.js';
+import { RetryOptions, withRetry } from './retry.js';
+import { runPipeline } from './pipeline.js';
+import { IngestFailure } from './progress.js';
+import { IngestFileResult } from './ci-report.js';
+
+// Types
+export interface SourceObject {
+  id: string;  // Drive file ID, or Cloud Storage object name
+  path: string;  // Relative to the source root, with the extension of what fetch returns
+  size?: number;  // Bytes; unknown for Google Docs, Sheets and Slides until exported
+  modifiedTime?: string;  // ISO 8601
+  owner?: string;
+  url: string;  // Where the original is opened
+  mimeType: string;  // Of the original
+}
+
+// Where documents come from when they are not on the local disk
+export interface Source {
+  readonly uri: string;  // gdrive://<folder-id> or gs://<bucket>/<prefix>
+  list(): Promise<SourceObject[]>;
+  fetch(object: SourceObject): Promise<Buffer>;
+  metadata(object: SourceObject): DocumentMetadata;
+}
+
+// Headers authorizing a request to the source, e.g. an OAuth access token
+export type SourceAuth = (url: string) => Promise<Record<string, string>>;
+
+export interface SourceOptions {
+  auth?: SourceAuth;  // Without it only public buckets can be read
+  retry?: RetryOptions;  // Backoff for 429/5xx responses
+  timeoutMs?: number;  // Per request
+}
+
+export interface SourceIngestOptions extends Omit<IngestOptions, 'fileMetadata'> {
+  replace?: boolean;  // Delete earlier documents of the same file after the upload (default: true)
+}
+
+export interface SourceIngestSummary {
+  uri: string;
+  listed: number;  // Objects found in the source
+  filtered: number;  // Left out by the include and exclude patterns
+  fetchFailures: IngestFailure[];  // Objects that could not be downloaded
+  upload: IngestSummary;
+  replacedDocuments: number;  // Earlier documents of re-ingested files that were removed
+}
+
+export const DRIVE_SCOPE = 'https://www.googleapis.com/auth/drive.readonly';
+export const GCS_SCOPE = 'https://www.googleapis.com/auth/devstorage.read_only';
+
+const DRIVE_API = 'https://www.googleapis.com/drive/v3';
+const GCS_API = 'https://storage.googleapis.com/storage/v1';
+const DRIVE_FOLDER = 'application/vnd.google-apps.folder';
+const DEFAULT_TIMEOUT_MS = 60_000;
+const DEFAULT_CONCURRENCY = 5;
+
+// Google Docs, Sheets and Slides have no content of their own and are exported;
+// other Google Workspace files (forms, drawings, shortcuts) are left out
+const DRIVE_EXPORTS: { [mimeType: string]: { mimeType: string; extension: string } } = {
+  'application/vnd.google-apps.document': { mimeType: 'text/markdown', extension: '.md' },
+  'application/vnd.google-apps.spreadsheet': { mimeType: 'text/csv', extension: '.csv' },  // The first sheet
+  'application/vnd.google-apps.presentation': { mimeType: 'text/plain', extension: '.txt' },
+};
+
+// Access tokens of a service account key, or of Application Default Credentials, for scope
+export function googleSourceAuth(scope: string, keyFile?: string): SourceAuth {
+  const auth = new GoogleAuth({ scopes: [scope], keyFile });
+  return async url => Object.fromEntries(await auth.getRequestHeaders(url));
+}
+
+async function request(url: string, label: string, options: SourceOptions): Promise<Response> {
+  return withRetry(label, async () => {
+    const response = await fetch(url, {
+      headers: options.auth ? await options.auth(url) : {},
+      signal: AbortSignal.timeout(options.timeoutMs || DEFAULT_TIMEOUT_MS),
+    });
+    if (response.ok) return response;
+    const body = (await response.text()).slice(0, 500);
+    if (response.status === 401 || response.status === 403) {
+      throw new AccessDeniedError(`${label}: access denied (HTTP ${response.status}); the credentials need read access to it${body ? ` - ${body}` : ''}`);
+    }
+    if (response.status === 404) throw new NotFoundError(label);
+    throw new HttpStatusError(`${label} returned HTTP ${response.status}${body ? `: ${body}` : ''}`, response.status, response.headers.get('retry-after'));
+  }, options.retry);
+}
+
+async function requestJson<T>(url: string, label: string, options: SourceOptions): Promise<T> {
+  return (await request(url, label, options)).json() as Promise<T>;
+}
+
+async function requestBuffer(url: string, label: string, options: SourceOptions): Promise<Buffer> {
+  return Buffer.from(await (await request(url, label, options)).arrayBuffer());
+}
+
+// Names may hold characters no path can; a second file of the same name in a folder
+// gets its ID added, as Drive allows both
+function uniquePath(taken: Set<string>, dir: string, name: string, id: string): string {
+  const safe = name.replace(/[/\\]/g, '_').replace(/^\.+$/, '_') || '_';
+  let candidate = dir ? `${dir}/${safe}` : safe;
+  if (taken.has(candidate)) {
+    const extension = path.posix.extname(safe);
+    candidate = `${candidate.slice(0, candidate.length - extension.length)} (${id})${extension}`;
+  }
+  taken.add(candidate);
+  return candidate;
+}
+
+interface DriveFile {
+  id: string;
+  name: string;
+  mimeType: string;
+  size?: string;
+  modifiedTime?: string;
+  webViewLink?: string;
+  owners?: { emailAddress?: string }[];
+}
+
+// A folder and its subfolders, shared drives included. Accepts the folder's ID or its URL.
+export class DriveSource implements Source {
+  readonly folderId: string;
+  readonly uri: string;
+
+  constructor(folder: string, private readonly options: SourceOptions = {}) {
+    const id = /\/folders\/([\w-]+)/.exec(folder)?.[1] || folder.replace(/^gdrive:\/\//, '');
+    if (!/^[\w-]{10,}$/.test(id)) throw new InvalidInputError(`Not a Drive folder ID or URL: ${folder}`);
+    this.folderId = id;
+    this.uri = `gdrive://${id}`;
+  }
+
+  async list(): Promise<SourceObject[]> {
+    const objects: SourceObject[] = [];
+    const taken = new Set<string>();
+    const folders: { id: string; dir: string }[] = [{ id: this.folderId, dir: '' }];
+    const visited = new Set<string>();
+    while (folders.length > 0) {
+      const folder = folders.shift()!;
+      if (visited.has(folder.id)) continue;
+      visited.add(folder.id);
+      for (const file of await this.children(folder.id)) {
+        if (file.mimeType === DRIVE_FOLDER) {
+          folders.push({ id: file.id, dir: uniquePath(taken, folder.dir, file.name, file.id) });
+          continue;
+        }
+        const exported = DRIVE_EXPORTS[file.mimeType];
+        if (file.mimeType.startsWith('application/vnd.google-apps.') && !exported) {
+          console.error(`⚠️  Skipped ${folder.dir ? `${folder.dir}/` : ''}${file.name}: ${file.mimeType} cannot be exported`);
+          continue;
+        }
+        const name = exported && !file.name.toLowerCase().endsWith(exported.extension) ? `${file.name}${exported.extension}` : file.name;
+        objects.push({
+          id: file.id,
+          path: uniquePath(taken, folder.dir, name, file.id),
+          size: file.size !== undefined ? Number(file.size) : undefined,
+          modifiedTime: file.modifiedTime,
+          owner: file.owners?.[0]?.emailAddress,
+          url: file.webViewLink || `https://drive.google.com/file/d/${file.id}/view`,
+          mimeType: file.mimeType,
+        });
+      }
+    }
+    return objects;
+  }
+
+  private async children(folderId: string): Promise<DriveFile[]> {
+    const files: DriveFile[] = [];
+    let pageToken: string | undefined;
+    do {
+      const params = new URLSearchParams({
+        q: `'${folderId}' in parents and trashed = false`,
+        fields: 'nextPageToken,files(id,name,mimeType,size,modifiedTime,webViewLink,owners(emailAddress))',
+        pageSize: '1000',
+        orderBy: 'name',
+        supportsAllDrives: 'true',
+        includeItemsFromAllDrives: 'true',
+      });
+      if (pageToken) params.set('pageToken', pageToken);
+      const page = await requestJson<{ files?: DriveFile[]; nextPageToken?: string }>(
+        `${DRIVE_API}/files?${params}`, `Drive folder ${folderId}`, this.options);
+      files.push(...(page.files || []));
+      pageToken = page.nextPageToken;
+    } while (pageToken);
+    return files;
+  }
+
+  async fetch(object: SourceObject): Promise<Buffer> {
+    const exported = DRIVE_EXPORTS[object.mimeType];
+    const url = exported
+      ? `${DRIVE_API}/files/${object.id}/export?mimeType=${encodeURIComponent(exported.mimeType)}`
+      : `${DRIVE_API}/files/${object.id}?alt=media&supportsAllDrives=true`;
+    return requestBuffer(url, `Drive file ${object.path}`, this.options);
+  }
+
+  metadata(object: SourceObject): DocumentMetadata {
+    return {
+      source_url: object.url,
+      drive_id: object.id,
+      ...(object.modifiedTime ? { last_modified: object.modifiedTime } : {}),
+    };
+  }
+}
+
+interface GcsObject {
+  name: string;
+  size?: string;
+  updated?: string;
+  generation?: string;
+  contentType?: string;
+}
+
+// The objects of a bucket below a prefix. Paths are the names after the prefix's last "/":
+// gs://docs/manuals/ lists manuals/a.md as a.md, gs://docs/manuals as manuals/a.md.
+export class GcsSource implements Source {
+  readonly bucket: string;
+  readonly prefix: string;
+  readonly uri: string;
+  private readonly generations = new Map<string, string>();
+
+  constructor(uri: string, private readonly options: SourceOptions = {}) {
+    const match = /^gs:\/\/([a-z0-9][a-z0-9._-]{1,220}[a-z0-9])(?:\/(.*))?$/.exec(uri);
+    if (!match) throw new InvalidInputError(`Not a Cloud Storage URI (gs://<bucket>/<prefix>): ${uri}`);
+    this.bucket = match[1];
+    this.prefix = match[2] || '';
+    this.uri = `gs://${this.bucket}/${this.prefix}`;
+  }
+
+  async list(): Promise<SourceObject[]> {
+    const base = this.prefix.slice(0, this.prefix.lastIndexOf('/') + 1);
+    const objects: SourceObject[] = [];
+    let pageToken: string | undefined;
+    do {
+      const params = new URLSearchParams({ prefix: this.prefix, fields: 'nextPageToken,items(name,size,updated,generation,contentType)' });
+      if (pageToken) params.set('pageToken', pageToken);
+      const page = await requestJson<{ items?: GcsObject[]; nextPageToken?: string }>(
+        `${GCS_API}/b/${this.bucket}/o?${params}`, `gs://${this.bucket}`, this.options);
+      for (const item of page.items || []) {
+        // Placeholders of the console's folders
+        if (item.name.endsWith('/')) continue;
+        if (item.generation) this.generations.set(item.name, item.generation);
+        objects.push({
+          id: item.name,
+          path: item.name.slice(base.length),
+          size: item.size !== undefined ? Number(item.size) : undefined,
+          modifiedTime: item.updated,
+          url: `gs://${this.bucket}/${item.name}`,
+          mimeType: item.contentType || 'application/octet-stream',
+        });
+      }
+      pageToken = page.nextPageToken;
+    } while (pageToken);
+    return objects;
+  }
+
+  // The generation that was listed, so an object overwritten since is not mixed in
+  async fetch(object: SourceObject): Promise<Buffer> {
+    const generation = this.generations.get(object.id);
+    const url = `${GCS_API}/b/${this.bucket}/o/${encodeURIComponent(object.id)}?alt=media${generation ? `&generation=${generation}` : ''}`;
+    return requestBuffer(url, object.url, this.options);
+  }
+
+  metadata(object: SourceObject): DocumentMetadata {
+    const generation = this.generations.get(object.id);
+    return {
+      source_url: object.url,
+      ...(generation ? { generation } : {}),
+      ...(object.modifiedTime ? { last_modified: object.modifiedTime } : {}),
+    };
+  }
+}
+
+// gdrive://<folder-id> or a Drive folder URL, and gs://<bucket>/<prefix>
+export function parseSource(value: string, options: SourceOptions = {}): Source {
+  if (value.startsWith('gs://')) return new GcsSource(value, options);
+  return new DriveSource(value, options);
+}
+
+// A file's documents are "<path>", or "<path>#<symbol>" with code chunking;
+// an archive's are "<path>/<inner path>"
+function documentsOf(documents: Document[], relativePath: string): Document[] {
+  return documents.filter(doc => doc.displayName === relativePath || doc.displayName?.startsWith(`${relativePath}#`)
+    || (isArchive(relativePath) && doc.displayName?.startsWith(`${relativePath}/`)));
+}
+
+// Lists the source, downloads what passes the include and exclude patterns into a
+// temporary tree and uploads it with the ingester, so conversion, chunking, redaction
+// and the index cache work as for local files. The tree is removed afterwards.
+export async function ingestSource(
+  client: FileSearchClient,
+  storeName: string,
+  source: Source,
+  options: SourceIngestOptions = {}
+): Promise<SourceIngestSummary> {
+  const treeDir = path.join(os.tmpdir(), `gemini-fs-source-${randomUUID()}`);
+  try {
+    const listed = await source.list();
+    const include = options.include || [];
+    const exclude = options.exclude || [];
+    const wanted = listed.filter(object => !isExcludedByDefault(object.path)
+      && !exclude.some(glob => matchesGlob(object.path, glob))
+      && (include.length === 0 || include.some(glob => matchesGlob(object.path, glob)) || isArchive(object.path)));
+    console.error(`☁️  ${listed.length} files in ${source.uri}, downloading ${wanted.length}`);
+
+    const fetchFailures: IngestFailure[] = [];
+    const fetchResults: IngestFileResult[] = [];
+    const fetchStartedAt = Date.now();
+    const byPath = new Map<string, SourceObject>();
+    await runPipeline(wanted, async object => {
+      const objectStartedAt = Date.now();
+      try {
+        const filePath = path.join(treeDir, ...object.path.split('/'));
+        fs.mkdirSync(path.dirname(filePath), { recursive: true });
+        fs.writeFileSync(filePath, await source.fetch(object));
+        // The tree is new, so the file's mtime comes from the source
+        if (object.modifiedTime) fs.utimesSync(filePath, new Date(object.modifiedTime), new Date(object.modifiedTime));
+        byPath.set(object.path, object);
+      } catch (error) {
+        fetchFailures.push({ path: object.path, reason: (error as Error).message, retries: 0 });
+        const err = toFileSearchError(error);
+        fetchResults.push({ path: object.path, status: 'failed', durationMs: Date.now() - objectStartedAt, retries: 0, code: err.code, reason: `download failed: ${err.message}` });
+      }
+    }, { concurrency: options.concurrency || DEFAULT_CONCURRENCY, signal: options.signal });
+    for (const failure of fetchFailures) console.error(`❌ Not downloaded: ${failure.path} - ${failure.reason}`);
+    // Downloaded files are reported by the ingester with their upload
+    if (fetchResults.length > 0) {
+      options.report?.add({
+        name: `download ${source.uri}`,
+        store: storeName,
+        rootDir: treeDir,
+        startedAt: new Date(fetchStartedAt).toISOString(),
+        durationMs: Date.now() - fetchStartedAt,
+        files: fetchResults,
+      });
+    }
+
+    const toRelative = (filePath: string) => relativePathOf(treeDir, filePath);
+    // Files expanded from an archive carry the archive's source
+    const objectOf = (filePath: string) => byPath.get(archiveMemberOf(filePath)?.archive || toRelative(filePath));
+    const ingester = new DirectoryIngester({
+      ...options,
+      fileMetadata: (filePath): DocumentMetadata | undefined => {
+        const object = objectOf(filePath);
+        if (!object) return undefined;
+        return {
+          ...source.metadata(object),
+          ...(object.owner && options.fileAttributes !== false ? { owner: object.owner } : {}),
+        };
+      },
+    });
+
+    const files = fs.existsSync(treeDir) ? ingester.collect(treeDir) : [];
+    const existing = options.replace === false ? [] : await client.listDocuments(storeName);
+    let replacedDocuments = 0;
+    const replaced: Promise<void>[] = [];
+    const upload = await ingester.upload(client, storeName, treeDir, files, undefined, (filePath: string, result: UploadResult) => {
+      const kept = new Set(result.documentNames || (result.documentName ? [result.documentName] : []));
+      const stale = documentsOf(existing, toDisplayName(treeDir, filePath).split(path.sep).join('/'))
+        .filter(doc => doc.name && !kept.has(doc.name));
+      replacedDocuments += stale.length;
+      replaced.push(...stale.map(doc => client.deleteDocument(doc.name!).catch(error => {
+        console.error(`⚠️  Could not delete the earlier document ${doc.name}: ${(error as Error).message}`);
+      })));
+    });
+    await Promise.all(replaced);
+
+    return { uri: source.uri, listed: listed.length, filtered: listed.length - wanted.length, fetchFailures, upload, replacedDocuments };
+  } finally {
+    fs.rmSync(treeDir, { recursive: true, force: true });
+  }
+}
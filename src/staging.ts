@@ -0,0 +1,330 @@
+// staging.ts - Staging directories: uploads prepared offline and pushed to a store later
+import * as fs from 'fs';
+import * as path from 'path';
+import { FileSearchClient, UploadResult } from './client.js';
+import { ChunkingConfig } from './chunking.js';
+import { IndexCache, hashFile } from './cache.js';
+import { estimateTokens } from './cost.js';
+import { FileSearchError, InvalidInputError, toFileSearchError } from './errors.js';
+import { DirectoryIngester, IngestProgress, IngestSummary, UploadedFile, toDisplayName } from './ingest.js';
+import { DocumentMetadata } from './metadata.js';
+import { FailureBudget, FailurePolicy, TokenBucket, runPipeline } from './pipeline.js';
+import { IngestFailure, formatIngestReport } from './progress.js';
+import { writeFileAtomic } from './shutdown.js';
+import { canonicalJson } from './manifest.js';
+import { CiReportWriter, IngestFileResult } from './ci-report.js';
+
+// Types
+export interface StagedDocument {
+  file: string;  // Artifact under files/
+  displayName: string;
+  mimeType?: string;
+  metadata?: DocumentMetadata;
+  chunking?: ChunkingConfig;
+  size: number;
+  sha256: string;  // Checked before the push, so a damaged copy is never uploaded
+}
+
+export interface StagedFile {
+  path: string;  // Relative path in the source directory, "/"-separated
+  displayName: string;
+  hash?: string;  // Content hash of the source, the index cache key
+  documents: StagedDocument[];  // One per symbol with code chunking
+  estimatedTokens: number;
+  skipped?: string;  // Why the file is not pushed
+}
+
+// Written as canonical JSON without times or machine paths, so identical trees staged
+// with the same settings give byte-identical manifests that can be diffed in review
+export interface StageManifest {
+  version: number;
+  createdAt?: string;  // Only in manifests of earlier versions
+  sourceDir?: string;
+  files: StagedFile[];
+  documentCount: number;
+  totalBytes: number;
+  estimatedTokens: number;
+}
+
+// Files already pushed, by store, so an interrupted or failed push continues where it stopped
+interface PushState {
+  version: number;
+  stores: { [storeName: string]: { [relativePath: string]: string[] } };  // Document names
+}
+
+export interface PushOptions {
+  cache?: IndexCache;  // Skip files whose content is already indexed and record the pushed ones
+  concurrency?: number;  // Files in flight at once (default: 5)
+  requestsPerMinute?: number;
+  metadata?: DocumentMetadata;  // Merged over the staged metadata, e.g. a project's
+  signal?: AbortSignal;  // Stops starting new files; files in flight still finish
+  abortSignal?: AbortSignal;  // Aborts in-flight transfers
+  onProgress?: (progress: IngestProgress) => void;
+  onFilePushed?: (file: StagedFile, result: UploadResult) => void;
+  report?: CiReportWriter;  // Gets the per-file results of the push, see ci-report.ts
+  failurePolicy?: FailurePolicy;  // Whether failed files stop the push; the files left are pushed by the next one (default: continue)
+}
+
+export const STAGE_MANIFEST_FILE = 'manifest.json';
+export const PUSH_STATE_FILE = 'push-state.json';
+const ARTIFACTS_DIR = 'files';
+
+// Runs every local step of an upload over the files (filters were applied by collect):
+// MIME detection, chunking config, metadata, local extraction, notebook conversion,
+// transcoding and code chunking, and writes the resulting documents with a manifest
+// to stageDir. Nothing is sent; pushStaged uploads the directory later, from this
+// machine or one with API access.
+export function stageDirectory(
+  ingester: DirectoryIngester,
+  rootDir: string,
+  files: string[],
+  stageDir: string,
+  onFile?: (file: StagedFile) => void
+): StageManifest {
+  prepareStageDir(stageDir);
+  const artifactsDir = path.join(stageDir, ARTIFACTS_DIR);
+  fs.mkdirSync(artifactsDir, { recursive: true });
+
+  const staged: StagedFile[] = [];
+  let next = 0;
+  // A stage directory inside the source tree is not staged into itself
+  const inside = (filePath: string) => !path.relative(path.resolve(stageDir), path.resolve(filePath)).startsWith('..');
+  for (const filePath of files.filter(filePath => !inside(filePath))) {
+    const prepared = ingester.prepare(rootDir, filePath);
+    const file: StagedFile = {
+      path: prepared.path.split(path.sep).join('/'),
+      displayName: toDisplayName(rootDir, filePath),
+      documents: [],
+      estimatedTokens: 0,
+      skipped: prepared.skipped,
+    };
+    try {
+      if (!prepared.skipped) file.hash = hashFile(filePath);
+      for (const document of prepared.documents) {
+        const artifact = `${String(next++).padStart(6, '0')}${path.extname(document.uploadPath)}`;
+        const artifactPath = path.join(artifactsDir, artifact);
+        fs.copyFileSync(document.uploadPath, artifactPath);
+        if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+        const size = fs.statSync(artifactPath).size;
+        file.documents.push({
+          file: `${ARTIFACTS_DIR}/${artifact}`,
+          displayName: document.options.displayName || file.displayName,
+          mimeType: document.options.mimeType,
+          metadata: document.options.metadata,
+          chunking: document.options.chunking,
+          size,
+          sha256: hashFile(artifactPath),
+        });
+        file.estimatedTokens += estimateTokens(size);
+      }
+    } catch (error) {
+      for (const document of prepared.documents) {
+        if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+      }
+      file.documents = [];
+      file.skipped = `unreadable: ${(error as Error).message}`;
+    }
+    staged.push(file);
+    onFile?.(file);
+  }
+
+  const documents = staged.flatMap(file => file.documents);
+  const manifest: StageManifest = {
+    version: 1,
+    files: staged,
+    documentCount: documents.length,
+    totalBytes: documents.reduce((sum, document) => sum + document.size, 0),
+    estimatedTokens: staged.reduce((sum, file) => sum + file.estimatedTokens, 0),
+  };
+  writeFileAtomic(path.join(stageDir, STAGE_MANIFEST_FILE), canonicalJson(manifest));
+  return manifest;
+}
+
+// An existing stage directory is replaced; any other non-empty directory is refused
+function prepareStageDir(stageDir: string): void {
+  if (!fs.existsSync(stageDir)) return;
+  if (!fs.statSync(stageDir).isDirectory()) throw new InvalidInputError(`Not a directory: ${stageDir}`);
+  const entries = fs.readdirSync(stageDir);
+  if (entries.length === 0) return;
+  if (!entries.includes(STAGE_MANIFEST_FILE)) {
+    throw new InvalidInputError(`${stageDir} is not empty and holds no ${STAGE_MANIFEST_FILE}; choose an empty or new directory`);
+  }
+  fs.rmSync(path.join(stageDir, ARTIFACTS_DIR), { recursive: true, force: true });
+  fs.rmSync(path.join(stageDir, PUSH_STATE_FILE), { force: true });
+}
+
+export function readStageManifest(stageDir: string): StageManifest {
+  const manifestPath = path.join(stageDir, STAGE_MANIFEST_FILE);
+  let manifest: StageManifest;
+  try {
+    manifest = JSON.parse(fs.readFileSync(manifestPath, 'utf8')) as StageManifest;
+  } catch (error) {
+    throw new InvalidInputError(`Cannot read ${manifestPath}: ${(error as Error).message}`, { cause: error });
+  }
+  if (manifest.version !== 1 || !Array.isArray(manifest.files)) {
+    throw new InvalidInputError(`${manifestPath} is not a stage manifest of a supported version`);
+  }
+  return manifest;
+}
+
+function readPushState(stageDir: string): PushState {
+  try {
+    return JSON.parse(fs.readFileSync(path.join(stageDir, PUSH_STATE_FILE), 'utf8')) as PushState;
+  } catch (error) {
+    return { version: 1, stores: {} };
+  }
+}
+
+// Uploads the staged documents to the store. The documents of a file are uploaded in
+// turn and deleted again if one of them fails, so a file is either fully indexed or
+// not at all. Pushed files are remembered in push-state.json for the next push.
+export async function pushStaged(
+  client: FileSearchClient,
+  storeName: string,
+  stageDir: string,
+  options: PushOptions = {}
+): Promise<IngestSummary> {
+  const startedAt = Date.now();
+  const manifest = readStageManifest(stageDir);
+  const state = readPushState(stageDir);
+  const pushed = state.stores[storeName] || (state.stores[storeName] = {});
+  const files = manifest.files.filter(file => !file.skipped && file.documents.length > 0);
+  const pending = files.filter(file => !pushed[file.path]);
+  const totalFiles = pending.length;
+  const failures: IngestFailure[] = [];
+  const succeeded: UploadedFile[] = [];
+  const failed: IngestSummary['failed'] = [];
+  const finished = new Set<string>();
+  const fileResults = new Map<string, IngestFileResult>();
+  let successCount = 0;
+  let errorCount = 0;
+  let cachedCount = 0;
+  let uploadedBytes = 0;
+  let retries = 0;
+
+  const budget = new FailureBudget(options.failurePolicy, options.signal);
+
+  const remember = (file: StagedFile, documentNames: string[]) => {
+    pushed[file.path] = documentNames;
+    writeFileAtomic(path.join(stageDir, PUSH_STATE_FILE), canonicalJson(state));
+  };
+
+  const pushFile = async (file: StagedFile): Promise<void> => {
+    const fileStartedAt = Date.now();
+    const cachedEntry = file.hash ? options.cache?.get(storeName, file.hash) : undefined;
+    let documentNames: string[] = [];
+    let failure: IngestFailure | undefined;
+    let failureError: FileSearchError | undefined;
+    let fileRetries = 0;
+    if (cachedEntry) {
+      documentNames = cachedEntry.documentNames || (cachedEntry.documentName ? [cachedEntry.documentName] : []);
+      cachedCount++;
+    } else {
+      try {
+        for (const document of file.documents) {
+          const artifactPath = path.join(stageDir, document.file);
+          if (hashFile(artifactPath) !== document.sha256) {
+            throw new InvalidInputError(`${document.file} changed since it was staged; stage the directory again`);
+          }
+          const result = await client.uploadFile(storeName, artifactPath, {
+            displayName: document.displayName,
+            mimeType: document.mimeType,
+            metadata: options.metadata ? { ...document.metadata, ...options.metadata } : document.metadata,
+            chunking: document.chunking,
+            onRetry: () => { fileRetries++; },
+            signal: options.abortSignal,
+          });
+          if (result.documentName) documentNames.push(result.documentName);
+          uploadedBytes += document.size;
+        }
+      } catch (error) {
+        const err = toFileSearchError(error);
+        console.error(`❌ Upload error (${err.code}): ${file.path} - ${err.message}`);
+        for (const documentName of documentNames) {
+          await client.deleteDocument(documentName).catch(() => undefined);
+        }
+        failure = { path: file.path, reason: err.message, code: err.code, retries: fileRetries };
+        failureError = err;
+      }
+    }
+    retries += fileRetries;
+    fileResults.set(file.path, {
+      path: file.path,
+      status: failure ? (failure.code === 'CANCELLED' ? 'cancelled' : 'failed') : cachedEntry ? 'cached' : 'uploaded',
+      durationMs: Date.now() - fileStartedAt,
+      bytes: failure || cachedEntry ? undefined : file.documents.reduce((sum, document) => sum + document.size, 0),
+      retries: fileRetries,
+      code: failure?.code,
+      reason: failure?.reason,
+    });
+    if (failure?.code === 'CANCELLED') return;
+
+    finished.add(file.path);
+    if (failure) {
+      errorCount++;
+      failures.push(failure);
+      failed.push({ input: file.path, error: failureError || new FileSearchError('OPERATION_FAILED', failure.reason) });
+      if (budget.fail()) console.error(`🛑 Stopping (${budget.stopped}); files in flight still finish`);
+    } else {
+      successCount++;
+      remember(file, documentNames);
+      const result = { documentName: documentNames[0], documentNames };
+      succeeded.push({ path: file.path, result });
+      if (!cachedEntry && file.hash) {
+        options.cache?.record(storeName, file.hash, {
+          ...result,
+          displayName: file.displayName,
+          indexedAt: new Date().toISOString(),
+        });
+      }
+      if (!cachedEntry) console.error(`✅ Upload complete: ${file.path}${file.documents.length > 1 ? ` (${file.documents.length} documents)` : ''}`);
+      options.onFilePushed?.(file, result);
+    }
+    const done = successCount + errorCount;
+    const progress = Math.round((done / totalFiles) * 100);
+    options.onProgress?.({ totalFiles, successCount, errorCount, progress, lastFile: file.path });
+    console.error(`⏳ Progress: ${progress}% (${done}/${totalFiles} files)`);
+  };
+
+  if (pending.length < files.length) {
+    console.error(`⏭️  ${files.length - pending.length} files were pushed to ${storeName} before`);
+  }
+  try {
+    await runPipeline(pending, pushFile, {
+      concurrency: options.concurrency || 5,
+      rateLimiter: options.requestsPerMinute ? new TokenBucket(options.requestsPerMinute) : undefined,
+      signal: budget.signal,
+    });
+  } finally {
+    budget.dispose();
+  }
+
+  const summary: IngestSummary = {
+    totalFiles,
+    successCount,
+    errorCount,
+    cachedCount,
+    duplicateCount: 0,
+    succeeded,
+    failed,
+    failures,
+    duplicates: [],
+    remaining: pending.filter(file => !finished.has(file.path)).map(file => file.path),
+    uploadedBytes,
+    retries,
+    durationMs: Date.now() - startedAt,
+    files: pending.map(file => fileResults.get(file.path) || { path: file.path, status: 'cancelled', durationMs: 0, retries: 0 }),
+    ...(budget.stopped ? { stopped: budget.stopped } : {}),
+  };
+  options.report?.add({
+    name: `push ${storeName}`,
+    store: storeName,
+    rootDir: manifest.sourceDir || stageDir,
+    startedAt: new Date(startedAt).toISOString(),
+    durationMs: summary.durationMs,
+    files: summary.files,
+  });
+  console.error(formatIngestReport({ store: storeName, ...summary }));
+  return summary;
+}
+
@@ -0,0 +1,261 @@
+// stats.ts - Corpus statistics of a store: MIME types, sizes, metadata keys and top terms
+import * as path from 'path';
+import { FileSearchClient } from './client.js';
+import { DocumentInfo, toDocumentInfo } from './documents.js';
+import { QueryCache } from './query-cache.js';
+import { isStopword } from './language.js';
+import { formatBytes } from './progress.js';
+
+// Types
+export interface CorpusStatsOptions {
+  queryCache?: QueryCache;  // Cited chunk text to count terms in; no terms without it
+  topTerms?: number;  // Default: 20
+}
+
+export interface MimeTypeStats {
+  mimeType: string;  // "unknown" when the store does not report one
+  documents: number;
+  bytes: number;
+}
+
+export interface SizeBucket {
+  label: string;  // e.g. "10 KB-100 KB"
+  minBytes: number;
+  maxBytes?: number;  // Exclusive; absent for the last bucket
+  documents: number;
+}
+
+export interface MetadataKeyStats {
+  key: string;
+  documents: number;  // Documents with the key
+  values: number;  // Distinct values; list values count each element
+  topValues: { value: string; documents: number }[];
+}
+
+export interface TermStats {
+  term: string;
+  count: number;
+  files: number;  // Files whose cited chunks contain the term
+}
+
+export interface SuspectDocument {
+  name: string;
+  displayName: string;
+  reason: string;  // "lockfile", "binary" or "minified"
+  sizeBytes: number;
+}
+
+export interface CorpusStats {
+  storeName: string;
+  displayName: string;
+  documents: number;
+  sourceBytes: number;
+  mimeTypes: MimeTypeStats[];  // Most documents first
+  sizes: SizeBucket[];
+  metadataKeys: MetadataKeyStats[];
+  terms?: { chunks: number; files: number; top: TermStats[] };  // Only with cached chunk text of the store
+  suspects: SuspectDocument[];
+  warnings: string[];
+}
+
+export const DEFAULT_TOP_TERMS = 20;
+
+const KB = 1024;
+const MB = 1024 * KB;
+
+const SIZE_BUCKETS: [number, number | undefined][] = [
+  [0, KB],
+  [KB, 10 * KB],
+  [10 * KB, 100 * KB],
+  [100 * KB, MB],
+  [MB, 10 * MB],
+  [10 * MB, undefined],
+];
+
+// Values shown for each metadata key
+const TOP_VALUES = 3;
+
+// Dependency lockfiles: large, repetitive and never what a question is about
+const LOCKFILES = new Set([
+  'package-lock.json', 'npm-shrinkwrap.json', 'yarn.lock', 'pnpm-lock.yaml', 'bun.lockb', 'cargo.lock',
+  'poetry.lock', 'pipfile.lock', 'uv.lock', 'gemfile.lock', 'composer.lock', 'go.sum', 'flake.lock',
+  'podfile.lock', 'pubspec.lock', 'mix.lock', 'packages.lock.json', 'gradle.lockfile',
+]);
+
+const BINARY_MIME_TYPES = /^(application\/(octet-stream|zip|gzip|x-tar|x-7z-compressed|x-executable|x-sharedlib|wasm|java-archive)|image\/|audio\/|video\/|font\/)/;
+
+// Suspects above this share of the documents or bytes are reported as a warning
+const SUSPECT_WARNING_RATIO = 0.1;
+
+const MIN_TERM_LENGTH = 3;
+
+// Suspect documents listed in the Markdown report; JSON has all of them
+const LISTED_SUSPECTS = 20;
+
+function bucketLabel(minBytes: number, maxBytes: number | undefined): string {
+  const size = (bytes: number) => formatBytes(bytes).replace(/\.0 /, ' ');
+  return maxBytes === undefined ? `≥ ${size(minBytes)}` : `${size(minBytes)}-${size(maxBytes)}`;
+}
+
+function suspectReason(info: DocumentInfo): string | undefined {
+  // Display names of code chunks carry the symbol after "#"
+  const name = path.posix.basename(info.displayName.split('#')[0]).toLowerCase();
+  if (LOCKFILES.has(name)) return 'lockfile';
+  if (info.mimeType && BINARY_MIME_TYPES.test(info.mimeType)) return 'binary';
+  if (/\.min\.(js|css|mjs)$/.test(name)) return 'minified';
+  return undefined;
+}
+
+function metadataKeyStats(documents: DocumentInfo[]): MetadataKeyStats[] {
+  const keys = new Map<string, { documents: number; values: Map<string, number> }>();
+  for (const info of documents) {
+    for (const [key, value] of Object.entries(info.metadata)) {
+      let stats = keys.get(key);
+      if (!stats) {
+        stats = { documents: 0, values: new Map() };
+        keys.set(key, stats);
+      }
+      stats.documents++;
+      for (const element of new Set((Array.isArray(value) ? value : [value]).map(String))) {
+        stats.values.set(element, (stats.values.get(element) || 0) + 1);
+      }
+    }
+  }
+  return [...keys.entries()]
+    .map(([key, stats]) => ({
+      key,
+      documents: stats.documents,
+      values: stats.values.size,
+      topValues: [...stats.values.entries()]
+        .sort((a, b) => b[1] - a[1] || a[0].localeCompare(b[0]))
+        .slice(0, TOP_VALUES)
+        .map(([value, count]) => ({ value, documents: count })),
+    }))
+    .sort((a, b) => b.documents - a.documents || a.key.localeCompare(b.key));
+}
+
+// Terms of the cached chunks cited from the store's documents; the same chunk cited
+// by several answers is counted once
+function termStats(chunks: { fileName: string; text: string }[], displayNames: Set<string>, topTerms: number): CorpusStats['terms'] {
+  const seen = new Set<string>();
+  const counts = new Map<string, { count: number; files: Set<string> }>();
+  const files = new Set<string>();
+  for (const chunk of chunks) {
+    if (!displayNames.has(chunk.fileName)) continue;
+    const key = `${chunk.fileName}\n${chunk.text}`;
+    if (seen.has(key)) continue;
+    seen.add(key);
+    files.add(chunk.fileName);
+    for (const word of chunk.text.toLowerCase().match(/[\p{L}\p{N}_]+/gu) || []) {
+      if (word.length < MIN_TERM_LENGTH || /^\d+$/.test(word) || isStopword(word)) continue;
+      let term = counts.get(word);
+      if (!term) {
+        term = { count: 0, files: new Set() };
+        counts.set(word, term);
+      }
+      term.count++;
+      term.files.add(chunk.fileName);
+    }
+  }
+  if (seen.size === 0) return undefined;
+  const top = [...counts.entries()]
+    .sort((a, b) => b[1].count - a[1].count || a[0].localeCompare(b[0]))
+    .slice(0, topTerms)
+    .map(([term, stats]) => ({ term, count: stats.count, files: stats.files.size }));
+  return { chunks: seen.size, files: files.size, top };
+}
+
+// Listing the documents takes one request per page; terms only come from chunk text
+// already cached locally, since the API does not return the chunks of a document
+export async function corpusStats(client: FileSearchClient, storeName: string, options: CorpusStatsOptions = {}): Promise<CorpusStats> {
+  const store = await client.stores.getStore(storeName);
+  const documents = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document));
+
+  const mimeTypes = new Map<string, MimeTypeStats>();
+  const sizes: SizeBucket[] = SIZE_BUCKETS.map(([minBytes, maxBytes]) => ({ label: bucketLabel(minBytes, maxBytes), minBytes, maxBytes, documents: 0 }));
+  const suspects: SuspectDocument[] = [];
+  let sourceBytes = 0;
+  for (const info of documents) {
+    const mimeType = info.mimeType || 'unknown';
+    const stats = mimeTypes.get(mimeType) || { mimeType, documents: 0, bytes: 0 };
+    stats.documents++;
+    stats.bytes += info.sizeBytes;
+    mimeTypes.set(mimeType, stats);
+    const bucket = sizes.find(b => info.sizeBytes >= b.minBytes && (b.maxBytes === undefined || info.sizeBytes < b.maxBytes));
+    if (bucket) bucket.documents++;
+    sourceBytes += info.sizeBytes;
+    const reason = suspectReason(info);
+    if (reason) suspects.push({ name: info.name, displayName: info.displayName, reason, sizeBytes: info.sizeBytes });
+  }
+
+  const warnings: string[] = [];
+  const suspectBytes = suspects.reduce((sum, suspect) => sum + suspect.sizeBytes, 0);
+  if (suspects.length > 0 && (suspects.length >= documents.length * SUSPECT_WARNING_RATIO || suspectBytes >= sourceBytes * SUSPECT_WARNING_RATIO)) {
+    warnings.push(`${suspects.length} lockfiles, binaries or minified files hold ${formatBytes(suspectBytes)} of ${formatBytes(sourceBytes)}; consider ignoring them`);
+  }
+  const unknown = mimeTypes.get('unknown');
+  if (unknown) warnings.push(`${unknown.documents} documents have no MIME type`);
+
+  const displayNames = new Set(documents.map(info => info.displayName).filter(name => name));
+  return {
+    storeName: store.name,
+    displayName: store.displayName,
+    documents: documents.length,
+    sourceBytes,
+    mimeTypes: [...mimeTypes.values()].sort((a, b) => b.documents - a.documents || b.bytes - a.bytes),
+    sizes,
+    metadataKeys: metadataKeyStats(documents),
+    terms: options.queryCache ? termStats(options.queryCache.chunks(), displayNames, options.topTerms || DEFAULT_TOP_TERMS) : undefined,
+    suspects,
+    warnings,
+  };
+}
+
+function bar(count: number, max: number, width: number = 30): string {
+  return max > 0 ? '█'.repeat(Math.round((count / max) * width)) : '';
+}
+
+export function formatCorpusStatsMarkdown(stats: CorpusStats): string {
+  let markdown = `## ${stats.displayName || stats.storeName}\n- **Name**: ${stats.storeName}\n`;
+  markdown += `- **Documents**: ${stats.documents}, ${formatBytes(stats.sourceBytes)}\n\n`;
+
+  markdown += '### MIME types\n';
+  for (const mime of stats.mimeTypes) {
+    markdown += `- ${mime.mimeType}: ${mime.documents} documents, ${formatBytes(mime.bytes)}\n`;
+  }
+  if (stats.mimeTypes.length === 0) markdown += 'No documents.\n';
+
+  markdown += '\n### Sizes\n```\n';
+  const maxDocuments = Math.max(0, ...stats.sizes.map(bucket => bucket.documents));
+  const labelWidth = Math.max(...stats.sizes.map(bucket => bucket.label.length));
+  for (const bucket of stats.sizes) {
+    markdown += `${bucket.label.padEnd(labelWidth)}  ${String(bucket.documents).padStart(6)}  ${bar(bucket.documents, maxDocuments)}\n`;
+  }
+  markdown += '```\n';
+
+  if (stats.metadataKeys.length > 0) {
+    markdown += '\n### Metadata keys\n';
+    for (const key of stats.metadataKeys) {
+      const values = key.topValues.map(value => `${value.value} (${value.documents})`).join(', ');
+      markdown += `- **${key.key}**: ${key.documents} documents, ${key.values} distinct values${values ? `: ${values}` : ''}${key.values > key.topValues.length ? ', …' : ''}\n`;
+    }
+  }
+
+  markdown += '\n### Top terms\n';
+  if (stats.terms) {
+    markdown += `From ${stats.terms.chunks} cached chunks of ${stats.terms.files} files:\n`;
+    for (const term of stats.terms.top) markdown += `- ${term.term}: ${term.count} (${term.files} files)\n`;
+  } else {
+    markdown += 'No cached chunk text for this store; terms are counted in the chunks cited by cached answers.\n';
+  }
+
+  if (stats.suspects.length > 0) {
+    markdown += '\n### Suspect documents\n';
+    for (const suspect of stats.suspects.slice(0, LISTED_SUSPECTS)) {
+      markdown += `- ${suspect.displayName || suspect.name} (${suspect.reason}, ${formatBytes(suspect.sizeBytes)})\n`;
+    }
+    if (stats.suspects.length > LISTED_SUSPECTS) markdown += `- … and ${stats.suspects.length - LISTED_SUSPECTS} more\n`;
+  }
+  for (const warning of stats.warnings) markdown += `\n⚠️ ${warning}`;
+  return markdown + (stats.warnings.length > 0 ? '\n' : '');
+}
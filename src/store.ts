@@ -0,0 +1,201 @@
+// store.ts - File Search store lifecycle management
+import { GoogleGenAI, FileSearchStore, ImportFileResponse } from '@google/genai';
+import * as path from 'path';
+import { waitForOperation, PollOptions } from './operations.js';
+import { DocumentMetadata, toCustomMetadata } from './metadata.js';
+import { RetryOptions, withRetry } from './retry.js';
+import { InvalidInputError, toFileSearchError } from './errors.js';
+import { VertexRagTransport } from './vertex.js';
+import { PageOptions, Paginator } from './pagination.js';
+import { ChunkingConfig, toApiChunkingConfig } from './chunking.js';
+import { Notifier, indexingCompleted, indexingFailed } from './notify.js';
+import { TenantPolicy, crossTenantError, isTenantStore, tenantDisplayName, withoutTenant } from './tenant.js';
+
+// Types
+export interface StoreInfo {
+  name: string;
+  displayName: string;
+  createTime?: string;
+  updateTime?: string;
+  activeDocuments: number;
+  pendingDocuments: number;
+  failedDocuments: number;
+  sizeBytes: number;
+}
+
+export interface ImportFileOptions extends PollOptions {
+  displayName?: string;
+  mimeType?: string;
+  metadata?: DocumentMetadata;
+  chunking?: ChunkingConfig;
+}
+
+// With a tenant, its stores are shown by their display name within the tenant
+function toStoreInfo(store: FileSearchStore, tenant?: TenantPolicy): StoreInfo {
+  return {
+    name: store.name || '',
+    displayName: tenant ? withoutTenant(tenant.name, store.displayName || '') : store.displayName || '',
+    createTime: store.createTime,
+    updateTime: store.updateTime,
+    activeDocuments: Number(store.activeDocumentsCount || 0),
+    pendingDocuments: Number(store.pendingDocumentsCount || 0),
+    failedDocuments: Number(store.failedDocumentsCount || 0),
+    sizeBytes: Number(store.sizeBytes || 0),
+  };
+}
+
+export class StoreManager {
+  constructor(
+    private readonly sdk: GoogleGenAI | (() => GoogleGenAI),  // A function when the client can change, e.g. with several API keys
+    private readonly retry: RetryOptions = {},
+    private readonly vertex?: VertexRagTransport,  // RAG corpora take the place of stores
+    private readonly notifier?: Notifier,
+    private readonly operationTimeout?: number,  // Default wait for imports to be indexed
+    private readonly tenant?: TenantPolicy  // Creates, lists and reaches only the tenant's stores
+  ) {}
+
+  private readonly tenantStores = new Set<string>();  // Names of stores known to be the tenant's
+
+  private get ai(): GoogleGenAI {
+    return typeof this.sdk === 'function' ? this.sdk() : this.sdk;
+  }
+
+  async createStore(displayName: string): Promise<StoreInfo> {
+    const name = this.tenant ? tenantDisplayName(this.tenant.name, displayName) : displayName;
+    const request = this.vertex
+      ? this.vertex.createCorpus(name)
+      : this.ai.fileSearchStores.create({ config: { displayName: name } });
+    const store = await request.catch(error => {
+      throw toFileSearchError(error);
+    });
+    if (!store.name) {
+      throw new Error('Failed to create file search store');
+    }
+    this.tenantStores.add(store.name);
+    return toStoreInfo(store, this.tenant);
+  }
+
+  async listStores(): Promise<StoreInfo[]> {
+    return this.paginateStores().all();
+  }
+
+  paginateStores(options: PageOptions = {}): Paginator<StoreInfo> {
+    return new Paginator(async (pageToken, pageSize) => {
+      try {
+        const page = this.vertex
+          ? await this.vertex.listCorporaPage(pageToken, pageSize)
+          : await this.ai.fileSearchStores.list({ config: { pageToken, pageSize } }).then(pager => ({
+            items: pager.page,
+            nextPageToken: pager.params.config?.pageToken || undefined,
+          }));
+        const items = page.items.filter(store => !this.tenant || this.owns(store) || this.tenant.allowCrossTenant);
+        return { items: items.map(store => toStoreInfo(store, this.tenant)), nextPageToken: page.nextPageToken };
+      } catch (error) {
+        throw toFileSearchError(error);
+      }
+    }, options);
+  }
+
+  async getStore(name: string): Promise<StoreInfo> {
+    const request = this.vertex ? this.vertex.getCorpus(name) : this.ai.fileSearchStores.get({ name });
+    const store = await request.catch(error => {
+      throw toFileSearchError(error, name);
+    });
+    if (this.tenant && !this.owns(store) && !this.tenant.allowCrossTenant) {
+      throw crossTenantError(this.tenant.name, name, store.displayName || '');
+    }
+    return toStoreInfo(store, this.tenant);
+  }
+
+  // Records the stores of the tenant as they are seen, so each is looked up at most once
+  private owns(store: FileSearchStore): boolean {
+    if (!this.tenant || !isTenantStore(this.tenant.name, store.displayName || '')) return false;
+    if (store.name) this.tenantStores.add(store.name);
+    return true;
+  }
+
+  // Refuses stores of other tenants, or of none, unless cross-tenant access is allowed
+  async checkTenant(storeNames: string[]): Promise<void> {
+    if (!this.tenant || this.tenant.allowCrossTenant) return;
+    for (const name of storeNames) {
+      if (!this.tenantStores.has(name)) await this.getStore(name);
+    }
+  }
+
+  // Without force, deletion fails if the store still contains documents
+  async deleteStore(name: string, force: boolean = true): Promise<void> {
+    await this.checkTenant([name]);
+    const request = this.vertex
+      ? this.vertex.deleteCorpus(name, force)
+      : this.ai.fileSearchStores.delete({ name, config: { force } });
+    await request.catch(error => {
+      throw toFileSearchError(error, name);
+    });
+  }
+
+  // Imports a file that already exists in the Files API (e.g. "files/abc-123")
+  async importFile(storeName: string, fileName: string, options: ImportFileOptions = {}): Promise<ImportFileResponse | undefined> {
+    await this.checkTenant([storeName]);
+    return this.notifying(storeName, fileName, options.displayName, () => this.doImport(storeName, fileName, options));
+  }
+
+  // Uploads a local file to the Files API, then imports it into the store
+  async importLocalFile(storeName: string, filePath: string, options: ImportFileOptions = {}): Promise<ImportFileResponse | undefined> {
+    await this.checkTenant([storeName]);
+    return this.notifying(storeName, filePath, options.displayName, () => this.doImportLocal(storeName, filePath, options));
+  }
+
+  private async notifying(
+    storeName: string,
+    file: string,
+    displayName: string | undefined,
+    run: () => Promise<ImportFileResponse | undefined>
+  ): Promise<ImportFileResponse | undefined> {
+    try {
+      const response = await run();
+      await this.notifier?.notify(indexingCompleted(storeName, file, displayName, response?.documentName));
+      return response;
+    } catch (error) {
+      await this.notifier?.notify(indexingFailed(storeName, file, displayName, error));
+      throw error;
+    }
+  }
+
+  private async doImport(storeName: string, fileName: string, options: ImportFileOptions): Promise<ImportFileResponse | undefined> {
+    if (this.vertex) {
+      throw new InvalidInputError('Importing Files API files is not supported by the vertex backend; upload the local file instead');
+    }
+    const operation = await withRetry(`import of ${fileName}`, () => this.ai.fileSearchStores.importFile({
+      fileSearchStoreName: storeName,
+      fileName,
+      config: {
+        customMetadata: options.metadata ? toCustomMetadata(options.metadata) : undefined,
+        chunkingConfig: toApiChunkingConfig(options.chunking),
+      },
+    }), this.retry);
+    const done = await waitForOperation<ImportFileResponse>(this.ai, operation, { retry: this.retry, ...options, timeout: options.timeout ?? this.operationTimeout });
+    return done.response;
+  }
+
+  private async doImportLocal(storeName: string, filePath: string, options: ImportFileOptions): Promise<ImportFileResponse | undefined> {
+    const vertex = this.vertex;
+    if (vertex) {
+      const documentName = await withRetry(`upload of ${path.basename(filePath)}`, () => vertex.uploadFile(storeName, filePath, {
+        displayName: options.displayName,
+        chunking: options.chunking,
+      }), this.retry);
+      return { documentName };
+    }
+    const file = await withRetry(`upload of ${path.basename(filePath)}`, () => this.ai.files.upload({
+      file: filePath,
+      config: {
+        displayName: options.displayName || path.basename(filePath),
+        mimeType: options.mimeType,
+      },
+    }), this.retry);
+    if (!file.name) {
+      throw new Error('Files API did not return a file name');
+    }
+    return this.doImport(storeName, file.name, options);
+  }
+}
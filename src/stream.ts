@@ -0,0 +1,67 @@
+// stream.ts - Incremental query events from a streaming generateContent call
+import { GenerateContentResponse, GroundingChunkRetrievedContext, GroundingMetadata } from '@google/genai';
+import { TokenUsage, toTokenUsage } from './cost.js';
+import { BlockedAnswer, blockedAnswer } from './generation.js';
+
+// Types
+export interface TextEvent {
+  type: 'text';
+  text: string;
+}
+
+export interface CitationEvent {
+  type: 'citation';
+  index: number;
+  fileName: string;
+  snippet: string;
+}
+
+export interface DoneEvent {
+  type: 'done';
+  text: string;  // Full answer text
+  totalTokens?: number;
+  usage?: TokenUsage;
+  groundingMetadata?: GroundingMetadata;  // Last grounding metadata received, see extractCitations
+  blocked?: BlockedAnswer;  // Set when the safety filters stopped the question or the reply
+}
+
+export type QueryEvent = TextEvent | CitationEvent | DoneEvent;
+
+// Converts raw response chunks into text deltas and citation events.
+// Grounding chunks are repeated across stream chunks, so each citation is emitted once.
+export async function* toQueryEvents(chunks: AsyncGenerator<GenerateContentResponse>): AsyncGenerator<QueryEvent> {
+  const seen = new Set<string>();
+  let fullText = '';
+  let usage: TokenUsage | undefined;
+  let groundingMetadata: GroundingMetadata | undefined;
+  let blocked: BlockedAnswer | undefined;
+
+  for await (const chunk of chunks) {
+    const text = chunk.text;
+    if (text) {
+      fullText += text;
+      yield { type: 'text', text };
+    }
+
+    const metadata = chunk.candidates?.[0]?.groundingMetadata;
+    if (metadata?.groundingChunks?.length) {
+      groundingMetadata = metadata;
+    }
+    const groundingChunks = metadata?.groundingChunks || [];
+    for (const groundingChunk of groundingChunks) {
+      const retrievedContext: GroundingChunkRetrievedContext = groundingChunk.retrievedContext || {};
+      const fileName = retrievedContext.title || retrievedContext.uri || 'Unknown';
+      const snippet = retrievedContext.text || '';
+      const key = `${fileName}\n${snippet}`;
+      if (!snippet || seen.has(key)) continue;
+      seen.add(key);
+      yield { type: 'citation', index: seen.size, fileName, snippet };
+    }
+
+    // Usage is cumulative; the last chunk carries the totals
+    usage = toTokenUsage(chunk.usageMetadata) || usage;
+    blocked = blockedAnswer(chunk, fullText) || blocked;
+  }
+
+  yield { type: 'done', text: fullText, totalTokens: usage?.totalTokens, usage, groundingMetadata, ...(blocked ? { blocked } : {}) };
+}
@@ -0,0 +1,102 @@
+// summaries.ts - One-page summaries of large files, uploaded as companion documents
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { FileSearchClient, UploadFileOptions, UploadResult } from './client.js';
+import { canExtract, extractText } from './extract.js';
+import { decodeText, detectEncoding, isTextMimeType } from './encoding.js';
+
+// Types
+export interface SummaryOptions {
+  minBytes?: number;  // Files at least this large get a summary (default: 200 KB)
+  model?: string;  // Default: the client's model
+  redact?: (text: string) => string;  // Applied to the file's text before the model sees it
+}
+
+export const SUMMARY_KIND = 'summary';
+export const DEFAULT_SUMMARY_MIN_BYTES = 200 * 1024;
+
+// Roughly 250k tokens; the rest of a longer file is left out of the summary input
+const MAX_SUMMARY_INPUT_CHARS = 1_000_000;
+
+const SUMMARY_INSTRUCTION = [
+  'You write summaries that are indexed next to the documents they describe, so that search finds the right document.',
+  'Write a one-page summary (at most about 500 words) of the document in Markdown.',
+  'Start with one sentence on what the document is, then cover its main topics, key terms, names, APIs and conclusions.',
+  'Use short sections or bullet points. Only state what the document says; do not add commentary.',
+].join(' ');
+
+export function summaryDisplayName(displayName: string): string {
+  return `${displayName}#summary`;
+}
+
+export function needsSummary(size: number, options: SummaryOptions): boolean {
+  return size >= (options.minBytes ?? DEFAULT_SUMMARY_MIN_BYTES);
+}
+
+// PDF, DOCX and XLSX through local extraction, text types as they are
+export function canSummarize(filePath: string, mimeType: string): boolean {
+  return canExtract(filePath) || isTextMimeType(mimeType);
+}
+
+// The text to summarize; undefined for binary files and failed extractions
+export function readSummarySource(filePath: string, mimeType: string): string | undefined {
+  if (!canSummarize(filePath, mimeType)) return undefined;
+  if (canExtract(filePath)) {
+    try {
+      return extractText(filePath).text;
+    } catch (error) {
+      return undefined;
+    }
+  }
+  const data = fs.readFileSync(filePath);
+  return detectEncoding(data) ? decodeText(data).text : undefined;
+}
+
+export async function summarize(client: FileSearchClient, displayName: string, text: string, options: SummaryOptions = {}): Promise<string> {
+  const truncated = text.length > MAX_SUMMARY_INPUT_CHARS;
+  const prompt = `Document: ${displayName}${truncated ? ' (beginning only, the document is longer)' : ''}\n\n${text.slice(0, MAX_SUMMARY_INPUT_CHARS)}`;
+  const summary = (await client.generate(prompt, { model: options.model, systemInstruction: SUMMARY_INSTRUCTION })).trim();
+  if (!summary) throw new Error('The model returned an empty summary');
+  return `# Summary of ${displayName}\n\n${summary}\n`;
+}
+
+// Summarizes the file and uploads the summary as "<displayName>#summary" with kind=summary,
+// then returns result with the summary among its documents, so it is replaced and
+// deleted together with the file. A failed summary is logged and leaves result as is.
+export async function uploadSummary(
+  client: FileSearchClient,
+  storeName: string,
+  filePath: string,
+  displayName: string,
+  result: UploadResult,
+  uploadOptions: UploadFileOptions,
+  options: SummaryOptions = {}
+): Promise<UploadResult> {
+  const summaryPath = path.join(os.tmpdir(), `gemini-summary-${randomUUID()}.md`);
+  try {
+    const source = readSummarySource(filePath, uploadOptions.mimeType || '');
+    const text = source !== undefined && options.redact ? options.redact(source) : source;
+    if (!text?.trim()) return result;
+    fs.writeFileSync(summaryPath, await summarize(client, displayName, text, options));
+    const summary = await client.uploadFile(storeName, summaryPath, {
+      ...uploadOptions,
+      resume: false,
+      mimeType: 'text/markdown',
+      chunking: undefined,
+      displayName: summaryDisplayName(displayName),
+      metadata: { ...uploadOptions.metadata, kind: SUMMARY_KIND, summary_of: displayName },
+    });
+    if (!summary.documentName) return result;
+    console.error(`📝 Summary uploaded: ${displayName}`);
+    const documentNames = result.documentNames || (result.documentName ? [result.documentName] : []);
+    return { documentName: result.documentName || summary.documentName, documentNames: [...documentNames, summary.documentName] };
+  } catch (error) {
+    const err = error as Error;
+    console.error(`⚠️  Summary failed, the file is indexed without one: ${displayName} - ${err.message}`);
+    return result;
+  } finally {
+    fs.rmSync(summaryPath, { force: true });
+  }
+}
@@ -0,0 +1,55 @@
+// sync.test.ts - Sync planning and manifest records for files deleted between the walk and their use
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { SyncManifest, planSync } from './sync.js';
+
+const STORE = 'fileSearchStores/handbook-4f2k9x1q';
+
+// A synced directory with its state in a state home of its own
+function syncedDir(t: TestContext): string {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'sync-test-'));
+  const previous = process.env.GEMINI_FS_HOME;
+  process.env.GEMINI_FS_HOME = path.join(dir, 'home');
+  t.after(() => {
+    if (previous === undefined) delete process.env.GEMINI_FS_HOME;
+    else process.env.GEMINI_FS_HOME = previous;
+    fs.rmSync(dir, { recursive: true, force: true });
+  });
+  const rootDir = path.join(dir, 'docs');
+  fs.mkdirSync(rootDir);
+  return rootDir;
+}
+
+test('a recorded file deleted after the walk is planned as removed', t => {
+  const rootDir = syncedDir(t);
+  const kept = path.join(rootDir, 'kept.md');
+  const gone = path.join(rootDir, 'gone.md');
+  fs.writeFileSync(kept, '# Kept\n');
+  fs.writeFileSync(gone, '# Gone\n');
+  const manifest = SyncManifest.load(rootDir, STORE);
+  manifest.record(rootDir, kept, { documentName: `${STORE}/documents/kept` });
+  manifest.record(rootDir, gone, { documentName: `${STORE}/documents/gone` });
+
+  // The walk still listed gone.md
+  fs.rmSync(gone);
+  assert.deepEqual(planSync(rootDir, [kept, gone], manifest), { added: [], changed: [], removed: ['gone.md'], unchanged: ['kept.md'] });
+});
+
+test('a file deleted after its upload is not recorded, and the others still are', t => {
+  const rootDir = syncedDir(t);
+  const gone = path.join(rootDir, 'gone.md');
+  const kept = path.join(rootDir, 'kept.md');
+  fs.writeFileSync(kept, '# Kept\n');
+  const manifest = SyncManifest.load(rootDir, STORE);
+
+  const warnings: string[] = [];
+  t.mock.method(console, 'error', (message: string) => warnings.push(message));
+  manifest.record(rootDir, gone, { documentName: `${STORE}/documents/gone` });
+  manifest.record(rootDir, kept, { documentName: `${STORE}/documents/kept` });
+  assert.deepEqual(manifest.keys(), ['kept.md']);
+  assert.deepEqual(warnings, ['⚠️  Not recorded in the sync manifest, deleted since it was uploaded: gone.md']);
+  assert.deepEqual(SyncManifest.load(rootDir, STORE).keys(), ['kept.md']);
+});
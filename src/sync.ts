@@ -0,0 +1,727 @@
+// sync.ts - Incremental sync between a local directory and a File Search store
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { FileSearchClient, UploadResult } from './client.js';
+import { DirectoryIngester, IngestProgress, toDisplayName } from './ingest.js';
+import { BatchResult, FailureBudget } from './pipeline.js';
+import { hashFile } from './cache.js';
+import { IngestDuplicate, IngestFailure } from './progress.js';
+import { writeFileAtomic } from './shutdown.js';
+import { PendingChanges, withFileLock, withFileLockAsync } from './lock.js';
+import { canonicalJson } from './manifest.js';
+import { relativePathOf, resolvePathOf } from './expand.js';
+import { JournalEntry, SyncJournal } from './journal.js';
+import { toFileSearchError } from './errors.js';
+import { RollbackPlan, SnapshotStore, planRollback } from './snapshots.js';
+import { IngestFileResult } from './ci-report.js';
+import { syncStatePath } from './tenant.js';
+
+// Types
+export interface ManifestEntry {
+  hash: string;  // SHA-256 of the file, also in the sha256 metadata of its documents
+  size: number;
+  mtimeMs: number;
+  documentName?: string;
+  documentNames?: string[];  // Set when the file was uploaded as one document per symbol or with a summary
+  uploadedAt: string;
+}
+
+interface ManifestData {
+  version: number;
+  storeName: string;
+  files: { [relativePath: string]: ManifestEntry };
+}
+
+export interface SyncPlan {
+  added: string[];  // Relative paths
+  changed: string[];
+  removed: string[];
+  unchanged: string[];
+}
+
+// succeeded holds the relative paths uploaded or removed, failed the paths whose upload
+// or delete failed with its error
+export interface SyncSummary extends BatchResult<string> {
+  uploaded: number;
+  deleted: number;
+  unchanged: number;
+  errorCount: number;
+  failures: IngestFailure[];  // Relative paths with the reason
+  duplicates: IngestDuplicate[];
+  remaining: string[];  // Not uploaded because the sync was cancelled; the next sync picks them up
+  retries: number;
+  stopped?: string;  // Why the ingester's failure policy cancelled the remaining files
+}
+
+export interface RollbackSummary extends RollbackPlan {
+  errorCount: number;
+  failures: IngestFailure[];
+  created?: number;  // Snapshot recording the store after the rollback
+}
+
+export interface ReconcileSummary {
+  replayed: number;  // Deletes sent again
+  adopted: number;  // Uploads that had finished, recorded without uploading again
+  discarded: number;  // Documents of unfinished or outdated uploads, deleted so the next sync uploads the file afresh
+}
+
+export const MANIFEST_FILE = '.gemini-sync.json';
+
+// Allowed difference between the local clock and the API's document creation times
+const CLOCK_SKEW_MS = 5 * 60 * 1000;
+
+function toRelativeKey(rootDir: string, filePath: string): string {
+  return relativePathOf(rootDir, filePath);
+}
+
+export class SyncManifest {
+  private readonly changes = new PendingChanges<ManifestEntry>();
+
+  private constructor(
+    private readonly manifestPath: string,
+    private readonly data: ManifestData
+  ) {}
+
+  // Loads the manifest for rootDir, starting fresh if it belongs to a different store
+  static load(rootDir: string, storeName: string): SyncManifest {
+    const manifestPath = syncStatePath(rootDir, MANIFEST_FILE);
+    const data = SyncManifest.read(manifestPath);
+    if (data?.storeName === storeName) {
+      return new SyncManifest(manifestPath, data);
+    }
+    return new SyncManifest(manifestPath, { version: 1, storeName, files: {} });
+  }
+
+  private static read(manifestPath: string): ManifestData | undefined {
+    try {
+      return JSON.parse(fs.readFileSync(manifestPath, 'utf8')) as ManifestData;
+    } catch (error) {
+      return undefined;  // Missing or unreadable manifest: treat every file as new
+    }
+  }
+
+  get size(): number {
+    return Object.keys(this.data.files).length;
+  }
+
+  get storeName(): string {
+    return this.data.storeName;
+  }
+
+  get(relativePath: string): ManifestEntry | undefined {
+    return this.data.files[relativePath];
+  }
+
+  keys(): string[] {
+    return Object.keys(this.data.files);
+  }
+
+  entries(): { [relativePath: string]: ManifestEntry } {
+    return { ...this.data.files };
+  }
+
+  // A file deleted since it was uploaded is not recorded, with a warning
+  record(rootDir: string, filePath: string, result: UploadResult): void {
+    const key = toRelativeKey(rootDir, filePath);
+    let stats: fs.Stats;
+    let hash: string;
+    try {
+      stats = fs.statSync(filePath);
+      hash = hashFile(filePath);
+    } catch (error) {
+      if (!isMissing(error)) throw error;
+      console.error(`⚠️  Not recorded in the sync manifest, deleted since it was uploaded: ${key}`);
+      return;
+    }
+    this.set(key, {
+      hash,
+      size: stats.size,
+      mtimeMs: stats.mtimeMs,
+      documentName: result.documentName,
+      documentNames: result.documentNames,
+      uploadedAt: new Date().toISOString(),
+    });
+    this.save();
+  }
+
+  touch(relativePath: string, mtimeMs: number): void {
+    const entry = this.data.files[relativePath];
+    if (entry) this.set(relativePath, { ...entry, mtimeMs });
+  }
+
+  // Detaches a document deleted outside of sync and returns the file it belonged to
+  forgetDocument(documentName: string): string | undefined {
+    for (const [key, entry] of Object.entries(this.data.files)) {
+      const names = entry.documentNames || (entry.documentName ? [entry.documentName] : []);
+      if (!names.includes(documentName)) continue;
+      this.detachDocument(key, documentName);
+      return key;
+    }
+    return undefined;
+  }
+
+  // Drops one document of a file, and the file once none is left. A file split into
+  // several documents is marked changed so the next sync replaces the rest.
+  detachDocument(relativePath: string, documentName: string): void {
+    const entry = this.data.files[relativePath];
+    if (!entry) return;
+    const names = entry.documentNames || (entry.documentName ? [entry.documentName] : []);
+    const remaining = names.filter(name => name !== documentName);
+    if (remaining.length === names.length) return;
+    if (remaining.length === 0) {
+      this.delete(relativePath);
+    } else {
+      this.set(relativePath, { ...entry, hash: '', mtimeMs: 0, documentName: remaining[0], documentNames: remaining });
+    }
+    this.save();
+  }
+
+  documentNames(): Set<string> {
+    return new Set(Object.values(this.data.files).flatMap(entry => entry.documentNames || (entry.documentName ? [entry.documentName] : [])));
+  }
+
+  // True when a file other than relativePath still points at the document, e.g. a linked duplicate
+  isShared(documentName: string, relativePath: string): boolean {
+    return Object.entries(this.data.files).some(([key, entry]) =>
+      key !== relativePath && (entry.documentName === documentName || entry.documentNames?.includes(documentName)));
+  }
+
+  remove(relativePath: string): void {
+    this.delete(relativePath);
+    this.save();
+  }
+
+  // Written under the manifest's lock, on top of what other runs wrote since it was
+  // loaded, so a watcher and a manual sync keep each other's entries
+  save(): void {
+    withFileLock(this.manifestPath, () => this.merge());
+  }
+
+  // save for the end of a sync, without blocking a server while another run holds the lock
+  async saveAsync(): Promise<void> {
+    await withFileLockAsync(this.manifestPath, () => this.merge());
+  }
+
+  private merge(): void {
+    const current = SyncManifest.read(this.manifestPath);
+    if (current?.storeName === this.data.storeName) {
+      this.changes.applyTo(current.files);
+      this.data.files = current.files;
+    }
+    this.changes.clear();
+    // Uploads finish in any order; sorted keys keep the file stable for diffs
+    writeFileAtomic(this.manifestPath, canonicalJson(this.data));
+  }
+
+  private set(relativePath: string, entry: ManifestEntry): void {
+    this.data.files[relativePath] = entry;
+    this.changes.set(relativePath, entry);
+  }
+
+  private delete(relativePath: string): void {
+    delete this.data.files[relativePath];
+    this.changes.delete(relativePath);
+  }
+}
+
+function isMissing(error: unknown): boolean {
+  return (error as NodeJS.ErrnoException).code === 'ENOENT';
+}
+
+// Compares the local files against the manifest. Files whose size and mtime
+// are unchanged are not re-hashed; one deleted since the walk counts as removed.
+export function planSync(rootDir: string, files: string[], manifest: SyncManifest): SyncPlan {
+  const plan: SyncPlan = { added: [], changed: [], removed: [], unchanged: [] };
+  const seen = new Set<string>();
+
+  for (const filePath of files) {
+    const key = toRelativeKey(rootDir, filePath);
+    seen.add(key);
+    const entry = manifest.get(key);
+    if (!entry) {
+      plan.added.push(key);
+      continue;
+    }
+
+    try {
+      const stats = fs.statSync(filePath);
+      if (entry.size === stats.size && entry.mtimeMs === stats.mtimeMs) {
+        plan.unchanged.push(key);
+      } else if (entry.size === stats.size && entry.hash === hashFile(filePath)) {
+        // Touched but identical content
+        manifest.touch(key, stats.mtimeMs);
+        plan.unchanged.push(key);
+      } else {
+        plan.changed.push(key);
+      }
+    } catch (error) {
+      if (!isMissing(error)) throw error;
+      seen.delete(key);
+    }
+  }
+
+  plan.removed = manifest.keys().filter(key => !seen.has(key));
+  return plan;
+}
+
+// Settles the journal entries a crashed sync left behind, before the next sync plans:
+// deletes are sent again, and for uploads the store is searched for the documents
+// of the file. Complete uploads of unchanged files are recorded in the manifest;
+// partial or outdated ones are deleted so the plan uploads the file again. An upload
+// that replaced an earlier version deletes that version once the new one is recorded,
+// and keeps it otherwise.
+export async function reconcileSync(
+  client: FileSearchClient,
+  storeName: string,
+  rootDir: string,
+  manifest: SyncManifest,
+  ingester: DirectoryIngester
+): Promise<ReconcileSummary> {
+  const journal = SyncJournal.open(rootDir);
+  try {
+    const pending = journal.pending(storeName);
+    const summary: ReconcileSummary = { replayed: 0, adopted: 0, discarded: 0 };
+    if (pending.length === 0) {
+      journal.compact();
+      return summary;
+    }
+    console.error(`🩹 Reconciling ${pending.length} unfinished sync operation(s) from the journal`);
+
+    for (const entry of pending.filter(entry => entry.op === 'delete')) {
+      if (!entry.documentName) {
+        journal.failed(entry.id, 'no document');
+        continue;
+      }
+      await deleteTracked(client, storeName, entry.path, entry.documentName, manifest, ingester);
+      journal.done(entry.id);
+      summary.replayed++;
+    }
+
+    const uploads = pending.filter(entry => entry.op === 'upload');
+    if (uploads.length > 0) {
+      const documents = await client.listDocuments(storeName);
+      for (const entry of uploads) {
+        // Recorded before the sync stopped; only the earlier version is left to delete
+        if (entry.replaces && isRecorded(manifest, entry)) {
+          summary.replayed += await deleteReplaced(client, storeName, entry, manifest, ingester);
+          journal.done(entry.id);
+          continue;
+        }
+        // Documents named after the file, created after the upload began and not yet in the manifest
+        const known = manifest.documentNames();
+        const created = documents.filter(document =>
+          document.name && !known.has(document.name) && isDocumentOf(document.displayName, entry)
+          && (!document.createTime || Date.parse(document.createTime) >= Date.parse(entry.startedAt) - CLOCK_SKEW_MS));
+        if (created.length === 0) {
+          journal.failed(entry.id, 'not uploaded before the sync stopped');
+          continue;
+        }
+        const filePath = resolvePathOf(rootDir, entry.path);
+        if (isComplete(created, entry, rootDir, filePath, ingester)) {
+          const documentNames = created.map(document => document.name as string);
+          manifest.record(rootDir, filePath, { documentName: documentNames[0], documentNames });
+          ingester.cache?.record(storeName, entry.hash as string, {
+            documentName: documentNames[0],
+            documentNames,
+            displayName: entry.displayName as string,
+            indexedAt: new Date().toISOString(),
+          });
+          console.error(`✅ Recovered upload: ${entry.path}`);
+          summary.adopted++;
+          summary.replayed += await deleteReplaced(client, storeName, entry, manifest, ingester);
+        } else {
+          for (const document of created) {
+            await client.deleteDocument(document.name as string).catch(error => {
+              if (toFileSearchError(error).code !== 'NOT_FOUND') throw error;
+            });
+          }
+          console.error(`🗑️  Discarded unfinished upload: ${entry.path}`);
+          summary.discarded += created.length;
+        }
+        journal.done(entry.id);
+      }
+    }
+    journal.compact();
+    return summary;
+  } finally {
+    journal.close();
+  }
+}
+
+// The manifest holds documents of the file other than the ones the upload replaces
+function isRecorded(manifest: SyncManifest, entry: JournalEntry): boolean {
+  const recorded = manifest.get(entry.path);
+  const names = recorded?.documentNames || (recorded?.documentName ? [recorded.documentName] : []);
+  return names.length > 0 && !names.some(name => entry.replaces?.includes(name));
+}
+
+// Deletes the earlier version an upload replaced, except documents the file or another
+// file still points at; returns how many were deleted
+async function deleteReplaced(
+  client: FileSearchClient,
+  storeName: string,
+  entry: JournalEntry,
+  manifest: SyncManifest,
+  ingester: DirectoryIngester
+): Promise<number> {
+  const recorded = manifest.get(entry.path);
+  const current = new Set(recorded?.documentNames || (recorded?.documentName ? [recorded.documentName] : []));
+  const stale = (entry.replaces || []).filter(name => !current.has(name) && !manifest.isShared(name, entry.path));
+  for (const documentName of stale) {
+    await deleteTracked(client, storeName, entry.path, documentName, manifest, ingester);
+  }
+  return stale.length;
+}
+
+function isDocumentOf(displayName: string | undefined, entry: JournalEntry): boolean {
+  return !!displayName && !!entry.displayName && (displayName === entry.displayName || displayName.startsWith(`${entry.displayName}#`));
+}
+
+// The file is unchanged and every document it is uploaded as exists, summary aside
+function isComplete(created: { displayName?: string }[], entry: JournalEntry, rootDir: string, filePath: string, ingester: DirectoryIngester): boolean {
+  let hash: string;
+  try {
+    hash = hashFile(filePath);
+  } catch (error) {
+    return false;
+  }
+  if (!entry.hash || hash !== entry.hash) return false;
+  const prepared = ingester.prepare(rootDir, filePath);
+  for (const document of prepared.documents) {
+    if (document.temporary) fs.rmSync(document.uploadPath, { force: true });
+  }
+  const documents = created.filter(document => !document.displayName?.endsWith('#summary'));
+  return !prepared.skipped && documents.length === prepared.documents.length;
+}
+
+// Deletes one document of a file and detaches it from the manifest; a document that is
+// already gone counts as deleted, as after a crash between the two
+async function deleteTracked(
+  client: FileSearchClient,
+  storeName: string,
+  relativePath: string,
+  documentName: string,
+  manifest: SyncManifest,
+  ingester: DirectoryIngester
+): Promise<void> {
+  await client.deleteDocument(documentName).catch(error => {
+    if (toFileSearchError(error).code !== 'NOT_FOUND') throw error;
+  });
+  ingester.cache?.forgetDocument(storeName, documentName);
+  manifest.detachDocument(relativePath, documentName);
+}
+
+// Deletes the documents of a file that no other file shares, each journaled ahead of it
+async function deleteJournaled(
+  client: FileSearchClient,
+  storeName: string,
+  relativePath: string,
+  documentNames: string[],
+  manifest: SyncManifest,
+  ingester: DirectoryIngester,
+  journal: SyncJournal
+): Promise<void> {
+  for (const documentName of documentNames) {
+    if (manifest.isShared(documentName, relativePath)) continue;
+    const id = journal.begin('delete', storeName, relativePath, { documentName });
+    try {
+      await deleteTracked(client, storeName, relativePath, documentName, manifest, ingester);
+    } catch (error) {
+      journal.failed(id, (error as Error).message);
+      throw error;
+    }
+    journal.done(id);
+  }
+}
+
+// Snapshots are a convenience: failing to record one does not fail the sync
+export function recordSnapshot(rootDir: string, manifest: SyncManifest, reason: string): number | undefined {
+  try {
+    const snapshot = SnapshotStore.open(rootDir).create(rootDir, manifest, reason);
+    if (snapshot) console.error(`📸 Snapshot ${snapshot.id}: ${Object.keys(snapshot.files).length} file(s)`);
+    return snapshot?.id;
+  } catch (error) {
+    console.error(`⚠️  Could not record a sync snapshot: ${(error as Error).message}`);
+    return undefined;
+  }
+}
+
+// Every upload and delete is journaled ahead of it, see reconcileSync for the recovery.
+// Changed files are uploaded before the documents of their earlier version are deleted,
+// so a failed or interrupted upload leaves the store with the old version, not none.
+export async function applySync(
+  client: FileSearchClient,
+  storeName: string,
+  rootDir: string,
+  plan: SyncPlan,
+  manifest: SyncManifest,
+  ingester: DirectoryIngester,
+  onProgress?: (progress: IngestProgress) => void
+): Promise<SyncSummary> {
+  const journal = SyncJournal.open(rootDir);
+  try {
+    let deleted = 0;
+    const failedDeletes: IngestFailure[] = [];
+    const succeeded: string[] = [];
+    const failed: SyncSummary['failed'] = [];
+    const deleteResults: IngestFileResult[] = [];
+    const deletesStartedAt = Date.now();
+    // Failed deletes count against the failure policy before any upload starts
+    const budget = new FailureBudget(ingester.failurePolicy);
+    const deletes = plan.removed;
+    let undone: string[] = [];
+
+    // Documents to delete once the new version of each changed file is recorded
+    const previous = new Map<string, string[]>();
+    for (const key of plan.changed) {
+      const entry = manifest.get(key);
+      const documentNames = entry?.documentNames || (entry?.documentName ? [entry.documentName] : []);
+      if (documentNames.length > 0) previous.set(key, documentNames);
+    }
+
+    // Remove documents for deleted files
+    for (const [index, key] of deletes.entries()) {
+      const entry = manifest.get(key);
+      const documentNames = entry?.documentNames || (entry?.documentName ? [entry.documentName] : []);
+      const fileStartedAt = Date.now();
+      try {
+        await deleteJournaled(client, storeName, key, documentNames, manifest, ingester, journal);
+        manifest.remove(key);
+        deleted++;
+        succeeded.push(key.split(path.sep).join('/'));
+        deleteResults.push({ path: key, status: 'deleted', durationMs: Date.now() - fileStartedAt, retries: 0 });
+        console.error(`🗑️  Removed: ${key}`);
+      } catch (error) {
+        const err = toFileSearchError(error);
+        console.error(`❌ Delete error: ${key} - ${err.message}`);
+        failedDeletes.push({ path: key, reason: `delete failed: ${err.message}`, code: err.code, retries: 0 });
+        failed.push({ input: key.split(path.sep).join('/'), error: err });
+        deleteResults.push({
+          path: key,
+          status: 'failed',
+          durationMs: Date.now() - fileStartedAt,
+          retries: 0,
+          code: err.code,
+          reason: `delete failed: ${err.message}`,
+        });
+        if (budget.fail()) {
+          undone = deletes.slice(index + 1);
+          break;
+        }
+      }
+    }
+    // The uploads are reported by the ingester as a suite of their own
+    if (deleteResults.length > 0) {
+      ingester.report?.add({
+        name: `sync ${storeName} (deletes)`,
+        store: storeName,
+        rootDir,
+        startedAt: new Date(deletesStartedAt).toISOString(),
+        durationMs: Date.now() - deletesStartedAt,
+        files: deleteResults,
+      });
+    }
+
+    // Nothing is uploaded; what was planned is left for the next sync, which plans it again
+    if (budget.stopped) {
+      await manifest.saveAsync();
+      journal.compact();
+      return {
+        uploaded: 0,
+        deleted,
+        unchanged: plan.unchanged.length,
+        succeeded,
+        failed,
+        errorCount: failedDeletes.length,
+        failures: failedDeletes,
+        duplicates: [],
+        remaining: [...undone, ...plan.added, ...plan.changed].map(key => key.split(path.sep).join('/')),
+        retries: 0,
+        stopped: budget.stopped,
+      };
+    }
+
+    // Failed and cancelled uploads stay begun: a document may exist even so, e.g. when
+    // indexing timed out, and the next sync looks for it
+    const toUpload = [...plan.added, ...plan.changed].map(key => resolvePathOf(rootDir, key));
+    const uploads = new Map<string, number>();
+    for (const filePath of toUpload) {
+      let hash: string | undefined;
+      try {
+        hash = hashFile(filePath);
+      } catch (error) {
+        // Reported by the upload
+      }
+      const key = relativePathOf(rootDir, filePath);
+      const displayName = toDisplayName(rootDir, filePath);
+      const replaces = previous.get(key);
+      uploads.set(filePath, journal.begin('upload', storeName, key, { displayName, hash, ...(replaces ? { replaces } : {}) }));
+    }
+    const replaced = new Map<string, number>();  // Journal entry of each changed file uploaded, by relative path
+    const summary = await ingester.upload(
+      client,
+      storeName,
+      rootDir,
+      toUpload,
+      onProgress,
+      (filePath, result) => {
+        manifest.record(rootDir, filePath, result);
+        const key = relativePathOf(rootDir, filePath);
+        const id = uploads.get(filePath);
+        uploads.delete(filePath);
+        // Settled once the earlier version is deleted as well
+        if (id !== undefined && previous.has(key)) replaced.set(key, id);
+        else if (id !== undefined) journal.done(id);
+      }
+    );
+    await manifest.saveAsync();
+    // Duplicates that were skipped created no document; a changed file that became one
+    // has its earlier version deleted, the content is in the store under the other copy
+    const retired: string[] = [];
+    for (const duplicate of summary.duplicates.filter(duplicate => duplicate.action === 'skip')) {
+      const filePath = resolvePathOf(rootDir, duplicate.path);
+      const id = uploads.get(filePath);
+      if (id !== undefined) journal.failed(id, `duplicate of ${duplicate.of}`);
+      if (previous.has(duplicate.path)) retired.push(duplicate.path);
+    }
+
+    for (const key of [...replaced.keys(), ...retired]) {
+      const recorded = manifest.get(key);
+      const current = new Set(replaced.has(key) ? recorded?.documentNames || (recorded?.documentName ? [recorded.documentName] : []) : []);
+      try {
+        await deleteJournaled(client, storeName, key, (previous.get(key) || []).filter(name => !current.has(name)), manifest, ingester, journal);
+        const id = replaced.get(key);
+        if (id !== undefined) journal.done(id);
+        else manifest.remove(key);
+      } catch (error) {
+        // Left for the next sync: the upload stays begun, or a retired file is still changed
+        const err = toFileSearchError(error);
+        console.error(`❌ Delete error: ${key} - ${err.message}`);
+        failedDeletes.push({ path: key, reason: `earlier version not deleted: ${err.message}`, code: err.code, retries: 0 });
+        failed.push({ input: key, error: err });
+      }
+    }
+    journal.compact();
+
+    const failures = [
+      ...failedDeletes,
+      ...summary.failures.map(failure => ({ ...failure, path: failure.path.split(path.sep).join('/') })),
+    ];
+    if (failures.length === 0 && summary.remaining.length === 0) recordSnapshot(rootDir, manifest, 'sync');
+    return {
+      uploaded: summary.successCount,
+      deleted,
+      unchanged: plan.unchanged.length,
+      succeeded: [...succeeded, ...summary.succeeded.map(file => file.path.split(path.sep).join('/')).filter(key => !failed.some(failure => failure.input === key))],
+      failed: [...failed, ...summary.failed.map(failure => ({ ...failure, input: failure.input.split(path.sep).join('/') }))],
+      errorCount: failures.length,
+      failures,
+      duplicates: summary.duplicates,
+      remaining: summary.remaining.map(relativePath => relativePath.split(path.sep).join('/')),
+      retries: summary.retries,
+      ...(summary.stopped ? { stopped: summary.stopped } : {}),
+    };
+  } finally {
+    journal.close();
+  }
+}
+
+// Brings the store back to a snapshot: files changed or removed since are uploaded again
+// from the content the snapshot kept, then the documents of their later versions and of
+// files added since are deleted. Local files are left alone, so the next sync uploads
+// them again where they still differ from the snapshot.
+export async function rollbackSync(
+  client: FileSearchClient,
+  storeName: string,
+  rootDir: string,
+  manifest: SyncManifest,
+  ingester: DirectoryIngester,
+  snapshotId: number,
+  onProgress?: (progress: IngestProgress) => void
+): Promise<RollbackSummary> {
+  const store = SnapshotStore.open(rootDir);
+  const snapshot = store.get(snapshotId);
+  const plan = planRollback(manifest, snapshot, store);
+  const journal = SyncJournal.open(rootDir);
+  try {
+    const failures: IngestFailure[] = [];
+
+    // Documents to delete once the snapshot's versions are in the store
+    const previous = new Map<string, string[]>();
+    const restoring = new Map<string, number>();  // Journal entry of each restored file, by relative path
+    for (const key of [...plan.restored, ...plan.deleted]) {
+      const entry = manifest.get(key);
+      previous.set(key, entry?.documentNames || (entry?.documentName ? [entry.documentName] : []));
+    }
+
+    // The snapshot's content under its relative paths, so documents get their names back
+    const stagingDir = fs.mkdtempSync(path.join(os.tmpdir(), 'gemini-rollback-'));
+    try {
+      const toUpload = plan.restored.map(key => {
+        const filePath = resolvePathOf(stagingDir, key);
+        fs.mkdirSync(path.dirname(filePath), { recursive: true });
+        fs.copyFileSync(store.contentPath(snapshot.files[key].hash) as string, filePath);
+        return filePath;
+      });
+      const uploads = new Map<string, number>();
+      for (const filePath of toUpload) {
+        const key = relativePathOf(stagingDir, filePath);
+        const replaces = previous.get(key);
+        uploads.set(filePath, journal.begin('upload', storeName, key, {
+          displayName: toDisplayName(stagingDir, filePath),
+          hash: snapshot.files[key].hash,
+          ...(replaces && replaces.length > 0 ? { replaces } : {}),
+        }));
+      }
+      const summary = await ingester.upload(
+        client,
+        storeName,
+        stagingDir,
+        toUpload,
+        onProgress,
+        (filePath, result) => {
+          manifest.record(stagingDir, filePath, result);
+          const key = relativePathOf(stagingDir, filePath);
+          const id = uploads.get(filePath);
+          uploads.delete(filePath);
+          // Settled once the later version is deleted as well
+          if (id !== undefined && previous.get(key)?.length) restoring.set(key, id);
+          else if (id !== undefined) journal.done(id);
+        }
+      );
+      await manifest.saveAsync();
+      failures.push(...summary.failures.map(failure => ({ ...failure, path: failure.path.split(path.sep).join('/') })));
+      failures.push(...summary.remaining.map(relativePath => ({ path: relativePath.split(path.sep).join('/'), reason: 'cancelled', retries: 0 })));
+    } finally {
+      fs.rmSync(stagingDir, { recursive: true, force: true });
+    }
+
+    // A file whose restore failed keeps its current documents
+    const failed = new Set(failures.map(failure => failure.path));
+    for (const [key, documentNames] of previous) {
+      if (failed.has(key)) continue;
+      try {
+        await deleteJournaled(client, storeName, key, documentNames, manifest, ingester, journal);
+        if (plan.deleted.includes(key)) {
+          manifest.remove(key);
+          console.error(`🗑️  Removed: ${key}`);
+        } else {
+          const id = restoring.get(key);
+          if (id !== undefined) journal.done(id);
+          console.error(`⏪ Restored: ${key}`);
+        }
+      } catch (error) {
+        const err = error as Error;
+        console.error(`❌ Delete error: ${key} - ${err.message}`);
+        failures.push({ path: key, reason: `delete failed: ${err.message}`, retries: 0 });
+      }
+    }
+    journal.compact();
+
+    const created = failures.length === 0 ? recordSnapshot(rootDir, manifest, `rollback to ${snapshot.id}`) : undefined;
+    return { ...plan, errorCount: failures.length, failures, ...(created !== undefined ? { created } : {}) };
+  } finally {
+    journal.close();
+  }
+}
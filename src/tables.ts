@@ -0,0 +1,134 @@
+// tables.ts - Splits CSV and TSV files into documents of a few rows each
+import * as path from 'path';
+import { DocumentMetadata, MetadataValue } from './metadata.js';
+
+// Types
+export interface RowChunkingOptions {
+  rowsPerDocument?: number;  // Default: 1
+  metadataColumns?: string[];  // Columns stored as metadata (default: the first 10)
+}
+
+export interface RowChunk {
+  name: string;  // "rows-2-11", or "row-2" for single rows
+  startLine: number;  // 1-based line of the first row, the header being line 1
+  endLine: number;
+  text: string;
+  values: DocumentMetadata;  // Column values, keyed by the column name made a metadata key
+}
+
+const DELIMITERS: { [extension: string]: string } = {
+  '.csv': ',',
+  '.tsv': '\t',
+  '.tab': '\t',
+};
+
+const DEFAULT_METADATA_COLUMNS = 10;
+const MAX_VALUE_LENGTH = 256;
+
+export function isTable(filePath: string): boolean {
+  return path.extname(filePath).toLowerCase() in DELIMITERS;
+}
+
+// RFC 4180: fields may be quoted, quotes inside doubled, and quoted fields may span
+// lines. Returns each record with the line it starts on.
+export function parseDelimited(text: string, delimiter: string): { line: number; fields: string[] }[] {
+  const records: { line: number; fields: string[] }[] = [];
+  let fields: string[] = [];
+  let field = '';
+  let quoted = false;
+  let line = 1;
+  let recordLine = 1;
+  const endRecord = () => {
+    fields.push(field);
+    if (fields.length > 1 || fields[0] !== '') records.push({ line: recordLine, fields });
+    fields = [];
+    field = '';
+  };
+  for (let i = 0; i < text.length; i++) {
+    const char = text[i];
+    if (quoted) {
+      if (char === '"' && text[i + 1] === '"') {
+        field += '"';
+        i++;
+      } else if (char === '"') {
+        quoted = false;
+      } else {
+        if (char === '\n') line++;
+        field += char;
+      }
+    } else if (char === '"' && field === '') {
+      quoted = true;
+    } else if (char === delimiter) {
+      fields.push(field);
+      field = '';
+    } else if (char === '\n' || char === '\r') {
+      if (char === '\r' && text[i + 1] === '\n') i++;
+      endRecord();
+      recordLine = ++line;
+    } else {
+      field += char;
+    }
+  }
+  if (field !== '' || fields.length > 0) endRecord();
+  return records;
+}
+
+// One document per group of rows, each row written as "column: value" lines under the
+// header so the passage makes sense on its own. A table without data rows gives none.
+export function chunkTable(filePath: string, text: string, options: RowChunkingOptions = {}): RowChunk[] {
+  const delimiter = DELIMITERS[path.extname(filePath).toLowerCase()];
+  if (!delimiter) return [];
+  const [header, ...rows] = parseDelimited(text, delimiter);
+  if (!header || rows.length === 0) return [];
+
+  const columns = header.fields.map((column, i) => column.trim() || `column_${i + 1}`);
+  const metadataColumns = options.metadataColumns
+    ? columns.filter(column => options.metadataColumns?.includes(column))
+    : columns.slice(0, DEFAULT_METADATA_COLUMNS);
+  const rowsPerDocument = Math.max(1, Math.floor(options.rowsPerDocument || 1));
+  const fileName = path.basename(filePath);
+
+  const chunks: RowChunk[] = [];
+  for (let start = 0; start < rows.length; start += rowsPerDocument) {
+    const group = rows.slice(start, start + rowsPerDocument);
+    const startLine = group[0].line;
+    const endLine = group[group.length - 1].line;
+    const lines = [`${fileName}, ${group.length === 1 ? `row ${startLine}` : `rows ${startLine}-${endLine}`}`, `Columns: ${columns.join(', ')}`];
+    for (const row of group) {
+      lines.push('', ...(group.length > 1 ? [`Row ${row.line}:`] : []));
+      columns.forEach((column, i) => {
+        const value = row.fields[i]?.trim();
+        if (value) lines.push(`${column}: ${value}`);
+      });
+    }
+    chunks.push({
+      name: group.length === 1 ? `row-${startLine}` : `rows-${startLine}-${endLine}`,
+      startLine,
+      endLine,
+      text: `${lines.join('\n')}\n`,
+      values: columnValues(columns, metadataColumns, group.map(row => row.fields), rowsPerDocument === 1),
+    });
+  }
+  return chunks;
+}
+
+// Single rows keep numbers as numbers; groups of rows get the distinct values of each
+// column as a string list, which the ":" (has) operator matches, even when short of rows
+function columnValues(columns: string[], metadataColumns: string[], rows: string[][], single: boolean): DocumentMetadata {
+  const values: DocumentMetadata = {};
+  for (const column of metadataColumns) {
+    const index = columns.indexOf(column);
+    const cells = [...new Set(rows.map(row => (row[index] || '').trim().slice(0, MAX_VALUE_LENGTH)).filter(Boolean))];
+    if (cells.length === 0) continue;
+    let value: MetadataValue = cells;
+    if (single) value = /^-?\d+(\.\d+)?$/.test(cells[0]) ? Number(cells[0]) : cells[0];
+    values[metadataKey(column)] = value;
+  }
+  return values;
+}
+
+// "Unit Price (USD)" -> "unit_price_usd"
+export function metadataKey(column: string): string {
+  const key = column.toLowerCase().replace(/[^a-z0-9_.-]+/g, '_').replace(/^_+|_+$/g, '');
+  return /^[a-z_]/.test(key) ? key : `_${key}`;
+}
@@ -0,0 +1,140 @@
+// tar.ts - Minimal ustar reader and writer for gzip-compressed archives
+import * as fs from 'fs';
+import * as zlib from 'zlib';
+
+// Types
+export interface TarEntry {
+  name: string;  // Relative path with "/" separators
+  data: Buffer;
+}
+
+const BLOCK_SIZE = 512;
+
+function writeString(header: Buffer, value: string, offset: number, length: number): void {
+  header.write(value, offset, Math.min(Buffer.byteLength(value), length), 'utf8');
+}
+
+function writeOctal(header: Buffer, value: number, offset: number, length: number): void {
+  writeString(header, value.toString(8).padStart(length - 1, '0') + '\0', offset, length);
+}
+
+function readString(header: Buffer, offset: number, length: number): string {
+  const field = header.subarray(offset, offset + length);
+  const end = field.indexOf(0);
+  return field.subarray(0, end === -1 ? length : end).toString('utf8');
+}
+
+// PAX extended header records, "<length> <key>=<value>\n" with length counting the whole record
+function parsePax(data: Buffer): Map<string, string> {
+  const records = new Map<string, string>();
+  let offset = 0;
+  while (offset < data.length) {
+    const space = data.indexOf(0x20, offset);
+    const length = space === -1 ? NaN : parseInt(data.toString('utf8', offset, space), 10);
+    if (!(length > 0) || offset + length > data.length) break;
+    const record = data.toString('utf8', space + 1, offset + length - 1);
+    const equals = record.indexOf('=');
+    if (equals > 0) records.set(record.slice(0, equals), record.slice(equals + 1));
+    offset += length;
+  }
+  return records;
+}
+
+// Names longer than 100 bytes are split at a "/" into the 155-byte prefix field
+function splitName(name: string): { prefix: string; name: string } {
+  if (Buffer.byteLength(name) <= 100) return { prefix: '', name };
+  for (let i = name.lastIndexOf('/'); i > 0; i = name.lastIndexOf('/', i - 1)) {
+    const prefix = name.slice(0, i);
+    const rest = name.slice(i + 1);
+    if (Buffer.byteLength(prefix) <= 155 && Buffer.byteLength(rest) <= 100) return { prefix, name: rest };
+  }
+  throw new Error(`Path too long for a tar archive: ${name}`);
+}
+
+function header(entry: TarEntry, mtime: number): Buffer {
+  const block = Buffer.alloc(BLOCK_SIZE);
+  const { prefix, name } = splitName(entry.name);
+  writeString(block, name, 0, 100);
+  writeOctal(block, 0o644, 100, 8);
+  writeOctal(block, 0, 108, 8);
+  writeOctal(block, 0, 116, 8);
+  writeOctal(block, entry.data.length, 124, 12);
+  writeOctal(block, mtime, 136, 12);
+  block.fill(' ', 148, 156);  // Checksum is computed with its own field set to spaces
+  block.write('0', 156);
+  block.write('ustar\0', 257);
+  block.write('00', 263);
+  writeString(block, prefix, 345, 155);
+
+  let checksum = 0;
+  for (const byte of block) checksum += byte;
+  writeString(block, checksum.toString(8).padStart(6, '0') + '\0 ', 148, 8);
+  return block;
+}
+
+export function writeTarGz(filePath: string, entries: TarEntry[]): void {
+  const mtime = Math.floor(Date.now() / 1000);
+  const blocks: Buffer[] = [];
+  for (const entry of entries) {
+    blocks.push(header(entry, mtime), entry.data);
+    const padding = (BLOCK_SIZE - (entry.data.length % BLOCK_SIZE)) % BLOCK_SIZE;
+    if (padding > 0) blocks.push(Buffer.alloc(padding));
+  }
+  // Two zero blocks mark the end of the archive
+  blocks.push(Buffer.alloc(BLOCK_SIZE * 2));
+  fs.writeFileSync(filePath, zlib.gzipSync(Buffer.concat(blocks)));
+}
+
+// Returns regular files only; directories and links are skipped. GNU long names ("L") and
+// PAX path and size records ("x" for the next entry, "g" for all that follow) are applied.
+// The archive is decompressed to at most maxBytes, past which it is rejected.
+export function readTarGz(filePath: string, maxBytes?: number): TarEntry[] {
+  let data: Buffer;
+  try {
+    data = zlib.gunzipSync(fs.readFileSync(filePath), maxBytes !== undefined ? { maxOutputLength: maxBytes } : {});
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === 'ERR_BUFFER_TOO_LARGE') throw new Error(`${filePath} expands to more than ${maxBytes} bytes`);
+    throw error;
+  }
+  const entries: TarEntry[] = [];
+  const globalPax = new Map<string, string>();
+  let pax = new Map<string, string>();
+  let longName: string | undefined;
+  let offset = 0;
+  while (offset + BLOCK_SIZE <= data.length) {
+    const block = data.subarray(offset, offset + BLOCK_SIZE);
+    if (block.every(byte => byte === 0)) break;
+
+    const name = readString(block, 0, 100);
+    const prefix = readString(block, 345, 155);
+    const type = String.fromCharCode(block[156]);
+    const extended = type === 'L' || type === 'x' || type === 'g';
+    // A PAX size replaces the octal field, which stops at 8GB
+    const paxSize = extended ? undefined : pax.get('size') ?? globalPax.get('size');
+    const size = paxSize !== undefined ? parseInt(paxSize, 10) : parseInt(readString(block, 124, 12).trim() || '0', 8);
+    if (Number.isNaN(size)) {
+      throw new Error(`Corrupt tar header at offset ${offset}`);
+    }
+
+    const start = offset + BLOCK_SIZE;
+    const body = data.subarray(start, start + size);
+    if (type === 'L') {
+      // The name is the entry's data, NUL-terminated
+      longName = readString(body, 0, body.length);
+    } else if (type === 'x') {
+      pax = parsePax(body);
+    } else if (type === 'g') {
+      for (const [key, value] of parsePax(body)) globalPax.set(key, value);
+    } else {
+      if (type === '0' || type === '\0') {
+        const entryName = pax.get('path') ?? longName ?? globalPax.get('path') ?? (prefix ? `${prefix}/${name}` : name);
+        entries.push({ name: entryName, data: body });
+      }
+      // Extended headers describe only the entry that follows them
+      pax = new Map();
+      longName = undefined;
+    }
+    offset = start + Math.ceil(size / BLOCK_SIZE) * BLOCK_SIZE;
+  }
+  return entries;
+}
@@ -0,0 +1,309 @@
+// telemetry.ts - Tracing spans and metrics with optional OTLP/HTTP export
+import { AsyncLocalStorage } from 'async_hooks';
+import { randomBytes } from 'crypto';
+import { TokenUsage } from './cost.js';
+
+// Types
+export type AttributeValue = string | number | boolean;
+export type Attributes = { [key: string]: AttributeValue | undefined };
+
+export interface TelemetryOptions {
+  endpoint?: string;  // OTLP/HTTP base URL, e.g. http://localhost:4318; export is off without it
+  serviceName?: string;
+  headers?: { [name: string]: string };
+  exportIntervalMs?: number;
+}
+
+interface SpanData {
+  traceId: string;
+  spanId: string;
+  parentSpanId?: string;
+  name: string;
+  startTimeUnixNano: string;
+  endTimeUnixNano?: string;
+  attributes: Attributes;
+  error?: string;
+}
+
+interface HistogramPoint {
+  attributes: Attributes;
+  count: number;
+  sum: number;
+  bucketCounts: number[];
+}
+
+const SCOPE = { name: 'gemini-file-search', version: '1.0.0' };
+
+// Milliseconds for latencies; bounds shared by every histogram
+const HISTOGRAM_BOUNDS = [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10_000, 30_000, 60_000, 120_000];
+
+// Spans kept in memory when export falls behind; older ones are dropped
+const MAX_PENDING_SPANS = 2048;
+
+function nowNano(): string {
+  return (BigInt(Date.now()) * 1_000_000n).toString();
+}
+
+function attributeKey(attributes: Attributes): string {
+  return JSON.stringify(Object.entries(attributes).filter(([, value]) => value !== undefined).sort(([a], [b]) => a.localeCompare(b)));
+}
+
+function toOtlpAttributes(attributes: Attributes): { key: string; value: { [type: string]: unknown } }[] {
+  return Object.entries(attributes)
+    .filter((entry): entry is [string, AttributeValue] => entry[1] !== undefined)
+    .map(([key, value]) => ({
+      key,
+      value: typeof value === 'string' ? { stringValue: value }
+        : typeof value === 'boolean' ? { boolValue: value }
+        : Number.isInteger(value) ? { intValue: String(value) }
+        : { doubleValue: value },
+    }));
+}
+
+// Parses OTEL_EXPORTER_OTLP_HEADERS ("key1=value1,key2=value2")
+export function parseOtlpHeaders(value: string | undefined): { [name: string]: string } {
+  const headers: { [name: string]: string } = {};
+  for (const pair of (value || '').split(',')) {
+    const index = pair.indexOf('=');
+    if (index > 0) headers[pair.slice(0, index).trim()] = decodeURIComponent(pair.slice(index + 1).trim());
+  }
+  return headers;
+}
+
+export class Span {
+  private ended = false;
+
+  constructor(readonly data: SpanData, private readonly onEnd: (span: SpanData) => void) {}
+
+  setAttribute(key: string, value: AttributeValue | undefined): this {
+    this.data.attributes[key] = value;
+    return this;
+  }
+
+  // Ends the span once; an error marks its status as failed
+  end(error?: unknown): void {
+    if (this.ended) return;
+    this.ended = true;
+    this.data.endTimeUnixNano = nowNano();
+    if (error !== undefined) {
+      this.data.error = error instanceof Error ? error.message : String(error);
+    }
+    this.onEnd(this.data);
+  }
+}
+
+export class Counter {
+  private readonly points = new Map<string, { attributes: Attributes; value: number }>();
+
+  constructor(readonly name: string, readonly unit: string, readonly description: string) {}
+
+  add(value: number, attributes: Attributes = {}): void {
+    const key = attributeKey(attributes);
+    const point = this.points.get(key) || { attributes, value: 0 };
+    point.value += value;
+    this.points.set(key, point);
+  }
+
+  snapshot(): { attributes: Attributes; value: number }[] {
+    return [...this.points.values()];
+  }
+}
+
+export class Histogram {
+  private readonly points = new Map<string, HistogramPoint>();
+
+  constructor(readonly name: string, readonly unit: string, readonly description: string) {}
+
+  record(value: number, attributes: Attributes = {}): void {
+    const key = attributeKey(attributes);
+    const point = this.points.get(key) || { attributes, count: 0, sum: 0, bucketCounts: new Array(HISTOGRAM_BOUNDS.length + 1).fill(0) };
+    point.count++;
+    point.sum += value;
+    const bucket = HISTOGRAM_BOUNDS.findIndex(bound => value <= bound);
+    point.bucketCounts[bucket === -1 ? HISTOGRAM_BOUNDS.length : bucket]++;
+    this.points.set(key, point);
+  }
+
+  snapshot(): HistogramPoint[] {
+    return [...this.points.values()];
+  }
+}
+
+// Spans nest through async calls: a span started inside trace() becomes a child of it.
+// Metrics are cumulative since process start and exported with every flush.
+export class Telemetry {
+  private readonly context = new AsyncLocalStorage<SpanData>();
+  private readonly pending: SpanData[] = [];
+  private readonly startTimeUnixNano = nowNano();
+  private options: TelemetryOptions = {};
+  private timer?: NodeJS.Timeout;
+
+  readonly requestDuration = new Histogram('gemini_fs.request.duration', 'ms', 'Latency of API requests, including retries');
+  readonly retries = new Counter('gemini_fs.request.retries', '{retry}', 'Retried API request attempts');
+  readonly uploadedBytes = new Counter('gemini_fs.upload.bytes', 'By', 'Bytes uploaded to stores');
+  readonly uploadedFiles = new Counter('gemini_fs.upload.files', '{file}', 'Files uploaded, by result');
+  readonly tokens = new Counter('gemini_fs.tokens', '{token}', 'Tokens used by queries, by kind (prompt, response)');
+
+  get enabled(): boolean {
+    return Boolean(this.options.endpoint);
+  }
+
+  configure(options: TelemetryOptions): void {
+    this.options = options;
+    if (this.timer) clearInterval(this.timer);
+    this.timer = undefined;
+    if (!options.endpoint) return;
+    this.timer = setInterval(() => void this.flush(), options.exportIntervalMs || 10_000);
+    this.timer.unref();
+  }
+
+  startSpan(name: string, attributes: Attributes = {}): Span {
+    const parent = this.context.getStore();
+    return new Span({
+      traceId: parent?.traceId || randomBytes(16).toString('hex'),
+      spanId: randomBytes(8).toString('hex'),
+      parentSpanId: parent?.spanId,
+      name,
+      startTimeUnixNano: nowNano(),
+      attributes: { ...attributes },
+    }, span => this.enqueue(span));
+  }
+
+  // Runs fn inside a span that ends when fn settles
+  async trace<T>(name: string, attributes: Attributes, fn: (span: Span) => Promise<T>): Promise<T> {
+    const span = this.startSpan(name, attributes);
+    try {
+      const result = await this.context.run(span.data, () => fn(span));
+      span.end();
+      return result;
+    } catch (error) {
+      span.end(error);
+      throw error;
+    }
+  }
+
+  recordTokens(model: string, usage: TokenUsage | undefined, span?: Span): void {
+    if (!usage) return;
+    span?.setAttribute('tokens.prompt', usage.promptTokens).setAttribute('tokens.response', usage.responseTokens);
+    this.tokens.add(usage.promptTokens, { kind: 'prompt', model });
+    this.tokens.add(usage.responseTokens, { kind: 'response', model });
+  }
+
+  private enqueue(span: SpanData): void {
+    if (!this.enabled) return;
+    this.pending.push(span);
+    if (this.pending.length > MAX_PENDING_SPANS) this.pending.shift();
+  }
+
+  private resource(): { attributes: ReturnType<typeof toOtlpAttributes> } {
+    return { attributes: toOtlpAttributes({ 'service.name': this.options.serviceName || 'gemini-file-search' }) };
+  }
+
+  private metrics(): unknown[] {
+    const timeUnixNano = nowNano();
+    const sums = [this.retries, this.uploadedBytes, this.uploadedFiles, this.tokens].map(counter => ({
+      name: counter.name,
+      unit: counter.unit,
+      description: counter.description,
+      sum: {
+        aggregationTemporality: 2,  // Cumulative
+        isMonotonic: true,
+        dataPoints: counter.snapshot().map(point => ({
+          attributes: toOtlpAttributes(point.attributes),
+          startTimeUnixNano: this.startTimeUnixNano,
+          timeUnixNano,
+          asInt: String(Math.round(point.value)),
+        })),
+      },
+    }));
+    const histograms = [this.requestDuration].map(histogram => ({
+      name: histogram.name,
+      unit: histogram.unit,
+      description: histogram.description,
+      histogram: {
+        aggregationTemporality: 2,
+        dataPoints: histogram.snapshot().map(point => ({
+          attributes: toOtlpAttributes(point.attributes),
+          startTimeUnixNano: this.startTimeUnixNano,
+          timeUnixNano,
+          count: String(point.count),
+          sum: point.sum,
+          bucketCounts: point.bucketCounts.map(String),
+          explicitBounds: HISTOGRAM_BOUNDS,
+        })),
+      },
+    }));
+    return [...sums, ...histograms].filter(metric => ('sum' in metric ? metric.sum.dataPoints : metric.histogram.dataPoints).length > 0);
+  }
+
+  private async post(signal: 'traces' | 'metrics', body: unknown): Promise<void> {
+    const url = `${(this.options.endpoint || '').replace(/\/$/, '')}/v1/${signal}`;
+    const response = await fetch(url, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json', ...this.options.headers },
+      body: JSON.stringify(body),
+    });
+    if (!response.ok) {
+      throw new Error(`HTTP ${response.status}`);
+    }
+  }
+
+  // Export failures are logged and never fail the operation being observed
+  async flush(): Promise<void> {
+    if (!this.enabled) return;
+    const spans = this.pending.splice(0, this.pending.length);
+    try {
+      if (spans.length > 0) {
+        await this.post('traces', {
+          resourceSpans: [{
+            resource: this.resource(),
+            scopeSpans: [{
+              scope: SCOPE,
+              spans: spans.map(span => ({
+                traceId: span.traceId,
+                spanId: span.spanId,
+                parentSpanId: span.parentSpanId,
+                name: span.name,
+                kind: 1,  // Internal
+                startTimeUnixNano: span.startTimeUnixNano,
+                endTimeUnixNano: span.endTimeUnixNano,
+                attributes: toOtlpAttributes(span.attributes),
+                status: span.error ? { code: 2, message: span.error } : { code: 1 },
+              })),
+            }],
+          }],
+        });
+      }
+      const metrics = this.metrics();
+      if (metrics.length > 0) {
+        await this.post('metrics', {
+          resourceMetrics: [{ resource: this.resource(), scopeMetrics: [{ scope: SCOPE, metrics }] }],
+        });
+      }
+    } catch (error) {
+      const err = error as Error;
+      console.error(`⚠️  Telemetry export to ${this.options.endpoint} failed: ${err.message}`);
+    }
+  }
+
+  async shutdown(): Promise<void> {
+    if (this.timer) clearInterval(this.timer);
+    this.timer = undefined;
+    await this.flush();
+  }
+}
+
+// Shared by the client, the pipelines and the entry points
+export const telemetry = new Telemetry();
+
+// Standard OTEL_* variables apply; the endpoint argument (flag or config) wins
+export function configureTelemetry(endpoint?: string, env: NodeJS.ProcessEnv = process.env): void {
+  telemetry.configure({
+    endpoint: endpoint || env.OTEL_EXPORTER_OTLP_ENDPOINT,
+    serviceName: env.OTEL_SERVICE_NAME,
+    headers: parseOtlpHeaders(env.OTEL_EXPORTER_OTLP_HEADERS),
+  });
+  if (telemetry.enabled) {
+    console.error(`📡 Exporting traces and metrics to ${endpoint || env.OTEL_EXPORTER_OTLP_ENDPOINT}`);
+  }
+}
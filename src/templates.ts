@@ -0,0 +1,129 @@
+// templates.ts - Prompt templates with Handlebars-style variable substitution
+//
+// Supports {{name}}, {{name | default "text"}}, {{#if name}}...{{else}}...{{/if}},
+// {{#unless name}}...{{/unless}} and {{! comments }}. Variables are strings;
+// using one that is not set (and has no default) is an error.
+
+import * as fs from 'fs';
+import { InvalidInputError } from './errors.js';
+
+// Types
+export type TemplateVars = { [name: string]: string };
+
+export type TemplateNode =
+  | { type: 'text'; text: string }
+  | { type: 'var'; name: string; fallback?: string }
+  | { type: 'if'; name: string; negate: boolean; then: TemplateNode[]; otherwise: TemplateNode[] };
+
+export class TemplateError extends InvalidInputError {
+  constructor(message: string, readonly line?: number) {
+    super(line !== undefined ? `Template error on line ${line}: ${message}` : `Template error: ${message}`);
+    this.name = 'TemplateError';
+  }
+}
+
+const NAME = /^[A-Za-z_][\w.-]*$/;
+const VARIABLE = /^([A-Za-z_][\w.-]*)(?:\s*\|\s*default\s+"((?:[^"\\]|\\.)*)")?$/;
+
+function lineAt(source: string, index: number): number {
+  return source.slice(0, index).split('\n').length;
+}
+
+export function parseTemplate(source: string): TemplateNode[] {
+  const root: TemplateNode[] = [];
+  // Open blocks, innermost last; nodes are appended to the branch being filled
+  const stack: { node: Extract<TemplateNode, { type: 'if' }>; inElse: boolean; line: number }[] = [];
+  const target = (): TemplateNode[] => {
+    const open = stack[stack.length - 1];
+    if (!open) return root;
+    return open.inElse ? open.node.otherwise : open.node.then;
+  };
+
+  const tag = /\{\{(.*?)\}\}/gs;
+  let last = 0;
+  for (let match = tag.exec(source); match; match = tag.exec(source)) {
+    if (match.index > last) target().push({ type: 'text', text: source.slice(last, match.index) });
+    last = tag.lastIndex;
+    const body = match[1].trim();
+    const line = lineAt(source, match.index);
+
+    if (body.startsWith('!')) continue;
+    const block = /^#(if|unless)\s+(\S+)$/.exec(body);
+    if (block) {
+      if (!NAME.test(block[2])) throw new TemplateError(`invalid variable name "${block[2]}"`, line);
+      const node: Extract<TemplateNode, { type: 'if' }> = { type: 'if', name: block[2], negate: block[1] === 'unless', then: [], otherwise: [] };
+      target().push(node);
+      stack.push({ node, inElse: false, line });
+    } else if (body === 'else') {
+      const open = stack[stack.length - 1];
+      if (!open || open.inElse) throw new TemplateError('{{else}} outside of {{#if}}', line);
+      open.inElse = true;
+    } else if (body === '/if' || body === '/unless') {
+      const open = stack.pop();
+      if (!open || (open.node.negate ? '/unless' : '/if') !== body) throw new TemplateError(`unexpected {{${body}}}`, line);
+    } else {
+      const variable = VARIABLE.exec(body);
+      if (!variable) throw new TemplateError(`cannot parse {{${body}}}`, line);
+      target().push({ type: 'var', name: variable[1], fallback: variable[2]?.replace(/\\(.)/g, '$1') });
+    }
+  }
+  if (last < source.length) target().push({ type: 'text', text: source.slice(last) });
+
+  const unclosed = stack.pop();
+  if (unclosed) throw new TemplateError(`{{#${unclosed.node.negate ? 'unless' : 'if'} ${unclosed.node.name}}} is never closed`, unclosed.line);
+  return root;
+}
+
+function renderNodes(nodes: TemplateNode[], vars: TemplateVars): string {
+  return nodes.map(node => {
+    if (node.type === 'text') return node.text;
+    if (node.type === 'var') return vars[node.name] ?? node.fallback ?? '';
+    const truthy = Boolean(vars[node.name]);
+    return renderNodes(truthy !== node.negate ? node.then : node.otherwise, vars);
+  }).join('');
+}
+
+// Only variables outside skipped {{#if}} branches must be set
+function missingVariables(nodes: TemplateNode[], vars: TemplateVars): string[] {
+  const missing: string[] = [];
+  for (const node of nodes) {
+    if (node.type === 'var' && node.fallback === undefined && vars[node.name] === undefined) missing.push(node.name);
+    if (node.type === 'if') {
+      missing.push(...missingVariables(Boolean(vars[node.name]) !== node.negate ? node.then : node.otherwise, vars));
+    }
+  }
+  return [...new Set(missing)];
+}
+
+export function renderTemplate(source: string, vars: TemplateVars): string {
+  const nodes = parseTemplate(source);
+  const missing = missingVariables(nodes, vars);
+  if (missing.length > 0) {
+    throw new TemplateError(`missing variable${missing.length > 1 ? 's' : ''}: ${missing.join(', ')}`);
+  }
+  return renderNodes(nodes, vars).trim();
+}
+
+export function renderTemplateFile(filePath: string, vars: TemplateVars): string {
+  let source: string;
+  try {
+    source = fs.readFileSync(filePath, 'utf8');
+  } catch (error) {
+    throw new InvalidInputError(`Cannot read template ${filePath}: ${(error as Error).message}`, { cause: error });
+  }
+  return renderTemplate(source, vars);
+}
+
+// Parses ["file=src/lib.rs", "audience=new hires"]; values may contain "="
+export function parseVarPairs(pairs: string[]): TemplateVars {
+  const vars: TemplateVars = {};
+  for (const pair of pairs) {
+    const index = pair.indexOf('=');
+    const name = index > 0 ? pair.slice(0, index).trim() : '';
+    if (!NAME.test(name)) {
+      throw new InvalidInputError(`Invalid template variable "${pair}", expected name=value`);
+    }
+    vars[name] = pair.slice(index + 1);
+  }
+  return vars;
+}
@@ -0,0 +1,64 @@
+// tenant.test.ts - Where local state files live, and what happens to the ones earlier versions left elsewhere
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import { spawnSync } from 'child_process';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { fileURLToPath } from 'url';
+import { statePath } from './tenant.js';
+
+const BIN_DIR = path.join(path.dirname(fileURLToPath(import.meta.url)), 'bin');
+
+function tempDir(t: TestContext): string {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'tenant-test-'));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  return dir;
+}
+
+// GEMINI_FS_HOME for the rest of the test
+function useStateHome(t: TestContext, home: string): void {
+  const previous = process.env.GEMINI_FS_HOME;
+  process.env.GEMINI_FS_HOME = home;
+  t.after(() => {
+    if (previous === undefined) delete process.env.GEMINI_FS_HOME;
+    else process.env.GEMINI_FS_HOME = previous;
+  });
+}
+
+test('a state file left by an earlier version is copied to the state home and the original kept', t => {
+  const dir = tempDir(t);
+  const home = path.join(dir, 'home');
+  useStateHome(t, home);
+  const legacy = path.join(dir, 'package', '.gemini-index-cache.json');
+  fs.mkdirSync(path.dirname(legacy));
+  fs.writeFileSync(legacy, '{"entries":{}}');
+
+  const file = statePath('.gemini-index-cache.json', undefined, [legacy]);
+  assert.equal(file, path.join(home, '.gemini-index-cache.json'));
+  assert.equal(fs.readFileSync(file, 'utf8'), '{"entries":{}}');
+  assert.equal(fs.readFileSync(legacy, 'utf8'), '{"entries":{}}');
+
+  // Once there, the state home's copy wins over the original
+  fs.writeFileSync(legacy, '{"entries":{"a":1}}');
+  assert.equal(fs.readFileSync(statePath('.gemini-index-cache.json', undefined, [legacy]), 'utf8'), '{"entries":{}}');
+});
+
+test('a command-line tool run in a directory holding an unrelated projects.json leaves it in place', t => {
+  const dir = tempDir(t);
+  const home = path.join(dir, 'home');
+  const project = path.join(dir, 'project');
+  fs.mkdirSync(project);
+  fs.writeFileSync(path.join(project, 'projects.json'), '{"members":["someone else\'s"]}');
+
+  for (const args of [['completions-cli.js', '--help'], ['history-cli.js', 'list', '--json']]) {
+    const run = spawnSync(process.execPath, [path.join(BIN_DIR, args[0]), ...args.slice(1)], {
+      cwd: project,
+      env: { ...process.env, GEMINI_FS_HOME: home, GEMINI_API_KEY: 'unused' },
+      encoding: 'utf8',
+    });
+    assert.doesNotMatch(run.stderr, /projects\.json/, `${args.join(' ')}: ${run.stderr}`);
+  }
+  assert.equal(fs.readFileSync(path.join(project, 'projects.json'), 'utf8'), '{"members":["someone else\'s"]}');
+  assert.ok(!fs.existsSync(path.join(home, 'projects.json')));
+});
@@ -0,0 +1,124 @@
+// tenant.ts - Tenant namespaces: prefixed store names, local state per tenant and cross-tenant guards
+import * as fs from 'fs';
+import { createHash } from 'crypto';
+import * as os from 'os';
+import * as path from 'path';
+import { AccessDeniedError, InvalidInputError } from './errors.js';
+
+// Types
+export interface TenantPolicy {
+  name: string;
+  allowCrossTenant?: boolean;  // Reach stores of other tenants, or of none, instead of refusing them
+}
+
+// The stores of a tenant are created as "<tenant>/<display name>"
+export const TENANT_SEPARATOR = '/';
+
+const TENANT_PATTERN = /^[A-Za-z0-9][A-Za-z0-9_-]{0,62}$/;
+
+export function parseTenantName(name: string): string {
+  const trimmed = name.trim();
+  if (!TENANT_PATTERN.test(trimmed)) {
+    throw new InvalidInputError(`Invalid tenant "${name}": use up to 63 letters, digits, _ and -, starting with a letter or digit`);
+  }
+  return trimmed;
+}
+
+// The tenant given with --tenant or GEMINI_FS_TENANT, validated
+export function resolveTenant(name: string | undefined): string | undefined {
+  return name ? parseTenantName(name) : undefined;
+}
+
+export function tenantPolicy(name: string | undefined, allowCrossTenant: boolean = false): TenantPolicy | undefined {
+  return name ? { name, allowCrossTenant } : undefined;
+}
+
+// Root of the local state directories: GEMINI_FS_HOME, else ~/.gemini-fs where an
+// earlier version created it, else $XDG_STATE_HOME/gemini-fs (~/.local/state/gemini-fs,
+// or %LOCALAPPDATA%\gemini-fs on Windows)
+export function stateHome(env: NodeJS.ProcessEnv = process.env): string {
+  if (env.GEMINI_FS_HOME) return env.GEMINI_FS_HOME;
+  const legacy = path.join(os.homedir(), '.gemini-fs');
+  if (fs.existsSync(legacy)) return legacy;
+  const stateDir = process.platform === 'win32'
+    ? env.LOCALAPPDATA || path.join(os.homedir(), 'AppData', 'Local')
+    : env.XDG_STATE_HOME || path.join(os.homedir(), '.local', 'state');
+  return path.join(stateDir, 'gemini-fs');
+}
+
+// Root of the per-tenant state directories
+export function tenantHome(): string {
+  return stateHome();
+}
+
+export function tenantDir(tenant: string): string {
+  return path.join(tenantHome(), tenant);
+}
+
+// Where a local state file (index cache, projects, history, ...) lives: in the
+// tenant's directory under the same name, or at its default path without a tenant
+export function tenantStatePath(defaultPath: string, tenant: string | undefined): string {
+  if (!tenant) return defaultPath;
+  const dir = tenantDir(tenant);
+  fs.mkdirSync(dir, { recursive: true });
+  return path.join(dir, path.basename(defaultPath));
+}
+
+// Where a local state file (index cache, projects, quota, history, ...) lives: in the state
+// home, or in the tenant's directory under it. Earlier versions kept the files elsewhere; the
+// first of legacyPaths that exists is copied over when the target does not exist yet.
+export function statePath(name: string, tenant: string | undefined, legacyPaths: string[] = []): string {
+  return stateIn(tenant ? tenantDir(tenant) : stateHome(), name, legacyPaths);
+}
+
+// Where the sync manifest, journal, snapshots and ingest checkpoint of a synced directory
+// live: <state home>/sync/<the first 16 hex digits of the SHA-256 of its absolute path>/.
+// Earlier versions kept them in the directory itself, from where they are copied over.
+export function syncStatePath(rootDir: string, name: string): string {
+  const resolved = path.resolve(rootDir);
+  const key = createHash('sha256').update(resolved).digest('hex').slice(0, 16);
+  return stateIn(path.join(stateHome(), 'sync', key), name, [path.join(resolved, name)]);
+}
+
+function stateIn(dir: string, name: string, legacyPaths: string[]): string {
+  fs.mkdirSync(dir, { recursive: true });
+  const target = path.join(dir, name);
+  if (fs.existsSync(target)) return target;
+  const legacy = legacyPaths.find(candidate => path.resolve(candidate) !== target && fs.existsSync(candidate));
+  return legacy ? migrateState(legacy, target) : target;
+}
+
+// Copies a state file or directory to where it lives now, and returns the path to use: the
+// old one while a run of an earlier version holds its lock, or when it can't be copied. The
+// original is left in place, so nothing is lost should it not have been ours after all.
+function migrateState(from: string, to: string): string {
+  if (fs.existsSync(`${from}.lock`)) return from;
+  try {
+    fs.cpSync(from, to, { recursive: true, errorOnExist: true, force: false });
+  } catch (error) {
+    fs.rmSync(to, { recursive: true, force: true });
+    console.error(`⚠️  Could not copy ${from} to ${to}, still using it: ${(error as Error).message}`);
+    return from;
+  }
+  console.error(`📦 Copied ${from} to ${to}; the copy is used from now on, and ${from} can be removed`);
+  return to;
+}
+
+export function tenantDisplayName(tenant: string, displayName: string): string {
+  return isTenantStore(tenant, displayName) ? displayName : `${tenant}${TENANT_SEPARATOR}${displayName}`;
+}
+
+export function isTenantStore(tenant: string, displayName: string): boolean {
+  return displayName.startsWith(`${tenant}${TENANT_SEPARATOR}`);
+}
+
+// The display name within the tenant; names of other tenants' stores are kept whole
+export function withoutTenant(tenant: string, displayName: string): string {
+  return isTenantStore(tenant, displayName) ? displayName.slice(tenant.length + TENANT_SEPARATOR.length) : displayName;
+}
+
+export function crossTenantError(tenant: string, storeName: string, displayName: string): AccessDeniedError {
+  return new AccessDeniedError(
+    `${storeName}${displayName ? ` ("${displayName}")` : ''} does not belong to tenant "${tenant}"; pass --allow-cross-tenant to use it`
+  );
+}
@@ -0,0 +1,140 @@
+// testing.test.ts - FileSearchClient against the mock server, and a record/replay round trip of its requests
+import { TestContext, test } from 'node:test';
+import assert from 'node:assert/strict';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { FileSearchClient, FileSearchClientBuilder } from './client.js';
+import { extractCitations } from './citations.js';
+import { toDocumentInfo } from './documents.js';
+import { NotFoundError, QuotaExceededError } from './errors.js';
+import { REDACTED } from './log.js';
+import { MockGeminiServer, RecordedInteraction, loadFixtures, recordHttp, replayHttp } from './testing.js';
+
+const API_KEY = 'test-api-key-7f3a9c21';
+const BEARER = 'ya29.test-access-token-5d8e';
+
+function tempDir(t: TestContext): string {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'testing-test-'));
+  t.after(() => fs.rmSync(dir, { recursive: true, force: true }));
+  return dir;
+}
+
+async function startServer(t: TestContext, server: MockGeminiServer = new MockGeminiServer()): Promise<MockGeminiServer> {
+  await server.start();
+  t.after(() => server.stop());
+  return server;
+}
+
+// No daemon and no upload sessions in the state home: the test owns everything it touches
+function clientBuilder(baseUrl: string, dir: string): FileSearchClientBuilder {
+  return FileSearchClient.builder()
+    .apiKey(API_KEY)
+    .baseUrl(baseUrl)
+    .uploadSessionsFile(path.join(dir, 'sessions.json'))
+    .daemon(false);
+}
+
+// Creates a store, uploads one file and asks about it; the same calls are made again on replay
+async function session(client: FileSearchClient, dir: string): Promise<{ storeName: string; documentName?: string; displayNames: string[]; answer: string }> {
+  const storeName = await client.createStore('Handbook');
+  const filePath = path.join(dir, 'limits.txt');
+  fs.writeFileSync(filePath, 'Files up to 100 MB can be uploaded to a File Search store.\n');
+  const { documentName } = await client.uploadFile(storeName, filePath, { metadata: { team: 'platform' } });
+  const displayNames = (await client.listDocuments(storeName)).map(document => document.displayName || '');
+  const answer = (await client.query([storeName], 'How large can a file be?')).text || '';
+  return { storeName, documentName, displayNames, answer };
+}
+
+test('FileSearchClient uploads to, lists and queries the mock server', async t => {
+  const dir = tempDir(t);
+  const server = await startServer(t, new MockGeminiServer({ answer: (question, documents) => `${documents.length} documents for: ${question}` }));
+  const client = clientBuilder(server.baseUrl, dir).build();
+
+  const storeName = await client.createStore('Handbook');
+  const filePath = path.join(dir, 'limits.txt');
+  fs.writeFileSync(filePath, 'Files up to 100 MB can be uploaded to a File Search store.\n');
+  const { documentName } = await client.uploadFile(storeName, filePath, { metadata: { team: 'platform', revision: 7 } });
+  assert.deepEqual(server.documents(storeName).map(document => document.name), [documentName]);
+
+  const [info] = (await client.listDocuments(storeName)).map(document => toDocumentInfo(document));
+  assert.equal(info.displayName, 'limits.txt');
+  assert.equal(info.state, 'ACTIVE');
+  assert.deepEqual(info.metadata, { team: 'platform', revision: 7 });
+
+  const response = await client.query([storeName], 'How large can a file be?');
+  assert.equal(response.text, '1 documents for: How large can a file be?');
+  const citations = extractCitations(response.candidates?.[0]?.groundingMetadata, response.text);
+  assert.deepEqual(citations.map(citation => [citation.fileName, citation.chunkText]), [
+    ['limits.txt', 'Files up to 100 MB can be uploaded to a File Search store.\n'],
+  ]);
+  assert.ok(server.requests.every(request => request.headers['x-goog-api-key'] === API_KEY));
+});
+
+test('a custom route answers before the built-in ones, and a 429 ends as a quota error once the retries run out', async t => {
+  const dir = tempDir(t);
+  const server = await startServer(t);
+  const storeName = server.addStore('Handbook');
+  server.on('POST', /:generateContent$/, { status: 429, body: { error: { code: 429, message: 'Resource has been exhausted', status: 'RESOURCE_EXHAUSTED' } } });
+  const client = clientBuilder(server.baseUrl, dir).retry({ maxAttempts: 2, baseDelayMs: 1, jitter: 0 }).build();
+
+  await assert.rejects(client.query([storeName], 'How large can a file be?'), QuotaExceededError);
+  assert.equal(server.requests.filter(request => request.path.endsWith(':generateContent')).length, 2);
+});
+
+test('recorded interactions replay without the server, with credentials redacted from the fixtures', async t => {
+  const dir = tempDir(t);
+  const fixtures = path.join(dir, 'fixtures');
+  const server = await startServer(t);
+  const baseUrl = server.baseUrl;
+  const client = () => clientBuilder(baseUrl, dir).api({ headers: { Authorization: `Bearer ${BEARER}` } }).build();
+
+  const original = globalThis.fetch;
+  const stopRecording = recordHttp(fixtures, { secrets: [API_KEY] });
+  t.after(stopRecording);
+  const recorded = await session(client(), dir);
+  stopRecording();
+  assert.equal(globalThis.fetch, original);
+  await server.stop();
+
+  const interactions: RecordedInteraction[] = loadFixtures(fixtures);
+  const paths = interactions.map(interaction => new URL(interaction.request.url).pathname.replace(recorded.storeName, '<store>').replace(/^\/mock-upload\/.+/, '/mock-upload/<session>'));
+  assert.deepEqual(interactions.map((interaction, i) => `${interaction.request.method} ${paths[i]}`), [
+    'POST /v1beta/fileSearchStores',
+    'POST /upload/v1beta/<store>:uploadToFileSearchStore',
+    'POST /mock-upload/<session>',
+    'GET /v1beta/<store>/documents',
+    'POST /v1beta/models/gemini-2.5-flash:generateContent',
+  ]);
+  for (const interaction of interactions) {
+    assert.equal(interaction.request.headers['x-goog-api-key'], REDACTED);
+    assert.equal(interaction.request.headers.authorization, REDACTED);
+  }
+  for (const name of fs.readdirSync(fixtures)) {
+    const text = fs.readFileSync(path.join(fixtures, name), 'utf8');
+    assert.ok(!text.includes(API_KEY) && !text.includes(BEARER), `${name} holds a credential`);
+  }
+
+  const stopReplay = replayHttp(fixtures, { secrets: [API_KEY] });
+  t.after(stopReplay);
+  assert.deepEqual(await session(client(), dir), recorded);
+  assert.deepEqual(recorded.displayNames, ['limits.txt']);
+  // Each recording answers once
+  await assert.rejects(fetch(`${baseUrl}/v1beta/fileSearchStores`, { method: 'POST', body: '{}' }), NotFoundError);
+});
+
+test('uploading a file that does not exist is a not-found error, and sends nothing', async t => {
+  const dir = tempDir(t);
+  const server = await startServer(t);
+  const storeName = server.addStore('Handbook');
+  const client = clientBuilder(server.baseUrl, dir).build();
+  const missing = path.join(dir, 'missing.txt');
+
+  await assert.rejects(client.uploadFile(storeName, missing), (error: unknown) => {
+    assert.ok(error instanceof NotFoundError);
+    assert.equal(error.resource, missing);
+    assert.equal((error.cause as NodeJS.ErrnoException).code, 'ENOENT');
+    return true;
+  });
+  assert.deepEqual(server.requests.filter(request => request.method === 'POST'), []);
+});
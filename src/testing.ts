@@ -0,0 +1,435 @@
+// testing.ts - Mock Gemini API server and record/replay of HTTP interactions
+import * as fs from 'fs';
+import * as http from 'http';
+import * as path from 'path';
+import { AddressInfo } from 'net';
+import { createHash, randomUUID } from 'crypto';
+import { InvalidInputError, NotFoundError } from './errors.js';
+import { REDACTED, defaultSecrets, redactSecrets } from './log.js';
+
+// Types
+export interface MockRequest {
+  method: string;
+  path: string;  // Without the query string
+  query: URLSearchParams;
+  headers: http.IncomingHttpHeaders;
+  body: Buffer;
+}
+
+export interface MockReply {
+  status?: number;  // Default: 200
+  headers?: { [name: string]: string };
+  body?: unknown;  // Objects are sent as JSON, strings and buffers as they are
+}
+
+export type MockHandler = (request: MockRequest) => MockReply | Promise<MockReply>;
+
+export interface MockDocument {
+  name: string;
+  displayName: string;
+  mimeType: string;
+  text: string;  // Returned as the retrieved context of generateContent
+  customMetadata?: unknown[];
+  createTime: string;
+}
+
+interface MockStore {
+  name: string;
+  displayName: string;
+  createTime: string;
+  documents: Map<string, MockDocument>;
+}
+
+export interface MockServerOptions {
+  port?: number;  // Default: a free port
+  // Text of every generated answer; default: "Mock answer to: <question>"
+  answer?: (question: string, documents: MockDocument[]) => string;
+}
+
+export interface RecordedInteraction {
+  request: { method: string; url: string; headers: { [name: string]: string }; body?: string; bodySize?: number; bodySha256?: string };
+  response: { status: number; headers: { [name: string]: string }; body: string; bodyEncoding?: 'base64' };
+}
+
+export interface RecorderOptions {
+  secrets?: string[];  // Redacted wherever they occur; default: GEMINI_API_KEY and GOOGLE_API_KEY
+}
+
+const SECRET_HEADERS = ['x-goog-api-key', 'authorization', 'cookie', 'proxy-authorization'];
+const DROPPED_HEADERS = ['set-cookie', 'content-length', 'content-encoding', 'transfer-encoding', 'connection', 'keep-alive', 'date'];
+// Larger request bodies (uploaded files) are recorded by size and hash only
+const MAX_RECORDED_BODY = 64 * 1024;
+
+function toJson(value: unknown): string {
+  return JSON.stringify(value);
+}
+
+// An in-memory stand-in for the File Search endpoints of the Gemini API: stores,
+// documents, uploads (simple and resumable), operations, generateContent with
+// grounding metadata and embeddings. Point a client at it with baseUrl or GEMINI_BASE_URL.
+export class MockGeminiServer {
+  readonly requests: MockRequest[] = [];
+  private readonly stores = new Map<string, MockStore>();
+  private readonly operations = new Map<string, unknown>();
+  private readonly sessions = new Map<string, { storeName: string; displayName?: string; mimeType: string; customMetadata?: unknown[]; received: Buffer[] }>();
+  private readonly routes: { method: string; pattern: RegExp; handler: MockHandler }[] = [];
+  private server?: http.Server;
+  private base = '';
+
+  constructor(private readonly options: MockServerOptions = {}) {}
+
+  get baseUrl(): string {
+    if (!this.server) throw new InvalidInputError('The mock server is not started');
+    return this.base;
+  }
+
+  // Custom routes are tried before the built-in ones, newest first
+  on(method: string, pattern: string | RegExp, handler: MockHandler | MockReply): this {
+    const regex = typeof pattern === 'string' ? new RegExp(`^${pattern.replace(/[.*+?^${}()|[\]\\]/g, '\\$&')}$`) : pattern;
+    this.routes.unshift({ method: method.toUpperCase(), pattern: regex, handler: typeof handler === 'function' ? handler : () => handler });
+    return this;
+  }
+
+  addStore(displayName: string): string {
+    const name = `fileSearchStores/${displayName.toLowerCase().replace(/[^a-z0-9]+/g, '-').slice(0, 30)}-${randomUUID().slice(0, 8)}`;
+    this.stores.set(name, { name, displayName, createTime: new Date().toISOString(), documents: new Map() });
+    return name;
+  }
+
+  addDocument(storeName: string, displayName: string, text: string, mimeType: string = 'text/plain', customMetadata?: unknown[]): string {
+    const store = this.stores.get(storeName);
+    if (!store) throw new NotFoundError(`Mock store not found: ${storeName}`);
+    const name = `${storeName}/documents/${randomUUID().replace(/-/g, '').slice(0, 16)}`;
+    store.documents.set(name, { name, displayName, mimeType, text, customMetadata, createTime: new Date().toISOString() });
+    return name;
+  }
+
+  documents(storeName: string): MockDocument[] {
+    return [...(this.stores.get(storeName)?.documents.values() || [])];
+  }
+
+  async start(): Promise<string> {
+    this.server = http.createServer((req, res) => {
+      const chunks: Buffer[] = [];
+      req.on('data', chunk => chunks.push(chunk as Buffer));
+      req.on('end', () => {
+        const url = new URL(req.url || '/', 'http://localhost');
+        const request: MockRequest = { method: req.method || 'GET', path: url.pathname, query: url.searchParams, headers: req.headers, body: Buffer.concat(chunks) };
+        this.requests.push(request);
+        this.handle(request).then(reply => this.send(res, reply), error => {
+          const err = error as Error & { status?: number };
+          this.send(res, { status: err.status || 500, body: { error: { code: err.status || 500, message: err.message } } });
+        });
+      });
+    });
+    await new Promise<void>(resolve => this.server!.listen(this.options.port || 0, '127.0.0.1', resolve));
+    this.base = `http://127.0.0.1:${(this.server.address() as AddressInfo).port}`;
+    return this.base;
+  }
+
+  async stop(): Promise<void> {
+    const server = this.server;
+    this.server = undefined;
+    if (server) await new Promise<void>(resolve => server.close(() => resolve()));
+  }
+
+  private send(res: http.ServerResponse, reply: MockReply): void {
+    const body = reply.body === undefined ? '' : typeof reply.body === 'string' || Buffer.isBuffer(reply.body) ? reply.body : toJson(reply.body);
+    res.writeHead(reply.status || 200, {
+      ...(typeof reply.body === 'object' && !Buffer.isBuffer(reply.body) ? { 'Content-Type': 'application/json' } : {}),
+      ...reply.headers,
+    });
+    res.end(body);
+  }
+
+  private fail(status: number, message: string): never {
+    throw Object.assign(new Error(message), { status });
+  }
+
+  private json(request: MockRequest): { [key: string]: unknown } {
+    try {
+      return request.body.length > 0 ? JSON.parse(request.body.toString('utf8')) : {};
+    } catch (error) {
+      return this.fail(400, 'Invalid JSON body');
+    }
+  }
+
+  private store(name: string): MockStore {
+    return this.stores.get(name) || this.fail(404, `Requested entity was not found: ${name}`);
+  }
+
+  private toApiDocument(document: MockDocument): { [key: string]: unknown } {
+    return {
+      name: document.name,
+      displayName: document.displayName,
+      mimeType: document.mimeType,
+      sizeBytes: String(Buffer.byteLength(document.text)),
+      state: 'STATE_ACTIVE',
+      customMetadata: document.customMetadata,
+      createTime: document.createTime,
+      updateTime: document.createTime,
+    };
+  }
+
+  private toApiStore(store: MockStore): { [key: string]: unknown } {
+    const size = [...store.documents.values()].reduce((sum, doc) => sum + Buffer.byteLength(doc.text), 0);
+    return {
+      name: store.name,
+      displayName: store.displayName,
+      createTime: store.createTime,
+      updateTime: store.createTime,
+      activeDocumentsCount: String(store.documents.size),
+      pendingDocumentsCount: '0',
+      failedDocumentsCount: '0',
+      sizeBytes: String(size),
+    };
+  }
+
+  // Indexing is instant: the operation is done when it is returned
+  private finishUpload(storeName: string, displayName: string, mimeType: string, content: Buffer, customMetadata?: unknown[]): MockReply {
+    const documentName = this.addDocument(storeName, displayName, content.toString('utf8'), mimeType, customMetadata);
+    const operation = {
+      name: `${storeName}/upload/operations/${randomUUID().slice(0, 12)}`,
+      done: true,
+      response: { '@type': 'type.googleapis.com/google.ai.generativelanguage.v1main.UploadToFileSearchStoreResponse', parent: storeName, documentName },
+    };
+    this.operations.set(operation.name, operation);
+    return { headers: { 'x-goog-upload-status': 'final' }, body: operation };
+  }
+
+  private generate(model: string, request: MockRequest): { [key: string]: unknown } {
+    const body = this.json(request) as {
+      contents?: { parts?: { text?: string }[] }[];
+      tools?: { fileSearch?: { fileSearchStoreNames?: string[] } }[];
+    };
+    const question = (body.contents || []).flatMap(content => content.parts || []).map(part => part.text || '').join('\n');
+    const storeNames = (body.tools || []).flatMap(tool => tool.fileSearch?.fileSearchStoreNames || []);
+    const documents = storeNames.flatMap(storeName => this.documents(storeName));
+    const answer = this.options.answer ? this.options.answer(question, documents) : `Mock answer to: ${question}`;
+    const chunks = documents.slice(0, 3).map(doc => ({
+      retrievedContext: { title: doc.displayName, text: doc.text.slice(0, 500), fileSearchStore: doc.name.split('/documents/')[0] },
+    }));
+    const tokens = Math.ceil(question.length / 4);
+    return {
+      candidates: [{
+        content: { role: 'model', parts: [{ text: answer }] },
+        finishReason: 'STOP',
+        groundingMetadata: chunks.length > 0 ? {
+          groundingChunks: chunks,
+          groundingSupports: [{ segment: { startIndex: 0, endIndex: answer.length, text: answer }, groundingChunkIndices: chunks.map((_, i) => i), confidenceScores: chunks.map(() => 0.9) }],
+        } : undefined,
+      }],
+      usageMetadata: { promptTokenCount: tokens, candidatesTokenCount: Math.ceil(answer.length / 4), totalTokenCount: tokens + Math.ceil(answer.length / 4) },
+      modelVersion: model,
+    };
+  }
+
+  // Vectors derived from the text hash, so equal texts get equal embeddings
+  private embedding(text: string): { values: number[] } {
+    const hash = createHash('sha256').update(text).digest();
+    return { values: [...hash.subarray(0, 16)].map(byte => byte / 255 - 0.5) };
+  }
+
+  private async handle(request: MockRequest): Promise<MockReply> {
+    for (const route of this.routes) {
+      if (route.method === request.method && route.pattern.test(request.path)) return route.handler(request);
+    }
+    const { method } = request;
+    const apiPath = request.path.replace(/^\/(upload\/)?v1(beta)?\//, '');
+    const upload = request.path.startsWith('/upload/');
+    let match: RegExpExecArray | null;
+
+    if (request.path.startsWith('/mock-upload/')) {
+      const session = this.sessions.get(request.path.slice('/mock-upload/'.length)) || this.fail(404, 'Upload session not found');
+      const command = String(request.headers['x-goog-upload-command'] || '');
+      if (command === 'query') {
+        return { headers: { 'x-goog-upload-status': 'active', 'x-goog-upload-size-received': String(Buffer.concat(session.received).length) } };
+      }
+      session.received.push(request.body);
+      if (!command.includes('finalize')) return { headers: { 'x-goog-upload-status': 'active' } };
+      this.sessions.delete(request.path.slice('/mock-upload/'.length));
+      return this.finishUpload(session.storeName, session.displayName || 'upload', session.mimeType, Buffer.concat(session.received), session.customMetadata);
+    }
+    if (upload && method === 'POST' && (match = /^(fileSearchStores\/[^/:]+):uploadToFileSearchStore$/.exec(apiPath))) {
+      const storeName = this.store(match[1]).name;
+      const config = this.json(request) as { displayName?: string; customMetadata?: unknown[] };
+      const id = randomUUID();
+      this.sessions.set(id, {
+        storeName,
+        displayName: config.displayName || String(request.headers['x-goog-upload-file-name'] || '') || undefined,
+        mimeType: String(request.headers['x-goog-upload-header-content-type'] || 'application/octet-stream'),
+        customMetadata: config.customMetadata,
+        received: [],
+      });
+      return { headers: { 'x-goog-upload-url': `${this.base}/mock-upload/${id}`, 'x-goog-upload-status': 'active' } };
+    }
+
+    if (apiPath === 'fileSearchStores') {
+      if (method === 'POST') {
+        const name = this.addStore(String(this.json(request).displayName || request.query.get('displayName') || 'store'));
+        return { body: this.toApiStore(this.store(name)) };
+      }
+      if (method === 'GET') return { body: { fileSearchStores: [...this.stores.values()].map(store => this.toApiStore(store)) } };
+    }
+    if ((match = /^(fileSearchStores\/[^/:]+)$/.exec(apiPath))) {
+      const store = this.store(match[1]);
+      if (method === 'GET') return { body: this.toApiStore(store) };
+      if (method === 'DELETE') {
+        if (store.documents.size > 0 && request.query.get('force') !== 'true') this.fail(400, 'Store is not empty; use force');
+        this.stores.delete(store.name);
+        return { body: {} };
+      }
+    }
+    if ((match = /^(fileSearchStores\/[^/:]+)\/documents$/.exec(apiPath)) && method === 'GET') {
+      return { body: { documents: this.documents(this.store(match[1]).name).map(doc => this.toApiDocument(doc)) } };
+    }
+    if ((match = /^(fileSearchStores\/[^/:]+)\/documents\/[^/:]+$/.exec(apiPath))) {
+      const store = this.store(match[1]);
+      const document = store.documents.get(apiPath) || this.fail(404, `Requested entity was not found: ${apiPath}`);
+      if (method === 'GET') return { body: this.toApiDocument(document) };
+      if (method === 'DELETE') {
+        store.documents.delete(apiPath);
+        return { body: {} };
+      }
+    }
+    if ((match = /^(fileSearchStores\/[^/:]+):importFile$/.exec(apiPath)) && method === 'POST') {
+      const body = this.json(request) as { fileName?: string; customMetadata?: unknown[] };
+      return this.finishUpload(this.store(match[1]).name, body.fileName || 'imported', 'text/plain', Buffer.from(''), body.customMetadata);
+    }
+    if (/\/operations\//.test(apiPath) && method === 'GET') {
+      return { body: this.operations.get(apiPath) || this.fail(404, `Operation not found: ${apiPath}`) };
+    }
+    if ((match = /^models\/([^/:]+):(generateContent|streamGenerateContent)$/.exec(apiPath)) && method === 'POST') {
+      const response = this.generate(match[1], request);
+      if (match[2] === 'generateContent') return { body: response };
+      return { headers: { 'Content-Type': 'text/event-stream' }, body: `data: ${toJson(response)}\r\n\r\n` };
+    }
+    if ((match = /^models\/([^/:]+):countTokens$/.exec(apiPath)) && method === 'POST') {
+      return { body: { totalTokens: Math.ceil(request.body.length / 4) } };
+    }
+    if ((match = /^models\/([^/:]+):(embedContent|batchEmbedContents)$/.exec(apiPath)) && method === 'POST') {
+      const body = this.json(request) as { content?: { parts?: { text?: string }[] }; requests?: { content?: { parts?: { text?: string }[] } }[] };
+      const textOf = (content?: { parts?: { text?: string }[] }) => (content?.parts || []).map(part => part.text || '').join('');
+      if (match[2] === 'embedContent') return { body: { embedding: this.embedding(textOf(body.content)) } };
+      return { body: { embeddings: (body.requests || []).map(item => this.embedding(textOf(item.content))) } };
+    }
+    return this.fail(404, `The mock server does not implement ${method} ${request.path}`);
+  }
+}
+
+function plainHeaders(headers: RequestInit['headers'] | Headers, secrets: string[]): { [name: string]: string } {
+  const result: { [name: string]: string } = {};
+  new Headers(headers).forEach((value, name) => {
+    if (DROPPED_HEADERS.includes(name)) return;
+    result[name] = SECRET_HEADERS.includes(name) ? REDACTED : redactSecrets(value, secrets);
+  });
+  return result;
+}
+
+function requestUrl(input: string | URL | Request): string {
+  return typeof input === 'string' ? input : input instanceof URL ? input.toString() : input.url;
+}
+
+function requestMethod(input: string | URL | Request, init?: RequestInit): string {
+  return (init?.method || (input instanceof Request ? input.method : 'GET')).toUpperCase();
+}
+
+function describeBody(body: RequestInit['body'], secrets: string[]): Pick<RecordedInteraction['request'], 'body' | 'bodySize' | 'bodySha256'> {
+  if (body === undefined || body === null) return {};
+  if (typeof body === 'string') {
+    return body.length <= MAX_RECORDED_BODY ? { body: redactSecrets(body, secrets) } : { bodySize: body.length, bodySha256: createHash('sha256').update(body).digest('hex') };
+  }
+  if (body instanceof Uint8Array || body instanceof ArrayBuffer) {
+    const bytes = Buffer.from(body instanceof ArrayBuffer ? new Uint8Array(body) : body);
+    return { bodySize: bytes.length, bodySha256: createHash('sha256').update(bytes).digest('hex') };
+  }
+  return { body: `[${body.constructor?.name || 'stream'}]` };
+}
+
+function isTextual(contentType: string): boolean {
+  return !contentType || /^text\/|json|xml|event-stream|javascript/.test(contentType);
+}
+
+function fixtureName(index: number, method: string, url: string): string {
+  const slug = new URL(url).pathname.replace(/^\/+/, '').replace(/[^\w.-]+/g, '_').slice(-80);
+  return `${String(index).padStart(4, '0')}-${method}-${slug}.json`;
+}
+
+// Replaces the global fetch, which @google/genai and the REST transports use, with one that
+// also writes every interaction to dir. Returns a function restoring the previous fetch.
+export function recordHttp(dir: string, options: RecorderOptions = {}): () => void {
+  const secrets = options.secrets || defaultSecrets();
+  const original = globalThis.fetch;
+  fs.mkdirSync(dir, { recursive: true });
+  let index = fs.readdirSync(dir).filter(name => name.endsWith('.json')).length;
+
+  globalThis.fetch = async (input: string | URL | Request, init?: RequestInit): Promise<Response> => {
+    const response = await original(input, init);
+    const method = requestMethod(input, init);
+    const url = redactSecrets(requestUrl(input), secrets);
+    const bytes = Buffer.from(await response.arrayBuffer());
+    const contentType = response.headers.get('content-type') || '';
+    const textual = isTextual(contentType);
+    const interaction: RecordedInteraction = {
+      request: { method, url, headers: plainHeaders(init?.headers, secrets), ...describeBody(init?.body, secrets) },
+      response: {
+        status: response.status,
+        headers: plainHeaders(response.headers, secrets),
+        body: textual ? redactSecrets(bytes.toString('utf8'), secrets) : bytes.toString('base64'),
+        ...(textual ? {} : { bodyEncoding: 'base64' as const }),
+      },
+    };
+    index += 1;
+    fs.writeFileSync(path.join(dir, fixtureName(index, method, url)), JSON.stringify(interaction, null, 2) + '\n');
+    return new Response(response.status === 204 || response.status === 304 ? null : bytes, {
+      status: response.status,
+      statusText: response.statusText,
+      headers: response.headers,
+    });
+  };
+  return () => {
+    globalThis.fetch = original;
+  };
+}
+
+export function loadFixtures(dir: string): RecordedInteraction[] {
+  if (!fs.existsSync(dir)) throw new NotFoundError(`Fixture directory not found: ${dir}`);
+  return fs.readdirSync(dir)
+    .filter(name => name.endsWith('.json'))
+    .sort()
+    .map(name => JSON.parse(fs.readFileSync(path.join(dir, name), 'utf8')) as RecordedInteraction);
+}
+
+// Answers fetch calls from the fixtures recordHttp wrote, without touching the network.
+// Each interaction is used once, in recorded order among those with the same method and URL;
+// a request with no recording left fails instead of going out.
+export function replayHttp(dir: string, options: RecorderOptions = {}): () => void {
+  const secrets = options.secrets || defaultSecrets();
+  const original = globalThis.fetch;
+  const pending = loadFixtures(dir);
+
+  globalThis.fetch = async (input: string | URL | Request, init?: RequestInit): Promise<Response> => {
+    const method = requestMethod(input, init);
+    const url = redactSecrets(requestUrl(input), secrets);
+    const index = pending.findIndex(interaction => interaction.request.method === method && interaction.request.url === url);
+    if (index === -1) throw new NotFoundError(`No recorded interaction left for ${method} ${url} in ${dir}`);
+    const [{ response }] = pending.splice(index, 1);
+    const body = response.bodyEncoding === 'base64' ? Buffer.from(response.body, 'base64') : response.body;
+    return new Response(response.status === 204 || response.status === 304 ? null : body, { status: response.status, headers: response.headers });
+  };
+  return () => {
+    globalThis.fetch = original;
+  };
+}
+
+// --record <dir> or --replay <dir> of the command-line tools
+export function installHttpFixtures(record: string | undefined, replay: string | undefined): void {
+  if (record && replay) throw new InvalidInputError('--record and --replay cannot be used together');
+  if (record) {
+    recordHttp(path.resolve(record));
+    console.error(`⏺️  Recording HTTP interactions to ${record}`);
+  } else if (replay) {
+    replayHttp(path.resolve(replay));
+    console.error(`▶️  Replaying HTTP interactions from ${replay}`);
+  }
+}
@@ -0,0 +1,99 @@
+// timeouts.ts - Separate deadlines for connecting, uploading, generating and waiting for indexing
+import { TomlValue, expectTable } from './toml.js';
+import { ConfigError, FileSearchError, RequestTimeoutError } from './errors.js';
+
+// Types
+export type TimeoutKind = 'connect' | 'upload' | 'query' | 'operation';
+
+export interface Timeouts {
+  connect?: number;  // Milliseconds to open the TCP and TLS connection of a request
+  upload?: number;  // Milliseconds per request carrying file bytes
+  query?: number;  // Milliseconds per generation request, streamed answers until their last chunk
+  operation?: number;  // Milliseconds to wait for indexing (default: 10 minutes)
+}
+
+export const TIMEOUT_KINDS: TimeoutKind[] = ['connect', 'upload', 'query', 'operation'];
+
+const DURATION_UNITS_MS: { [unit: string]: number } = { ms: 1, s: 1000, m: 60_000, h: 3_600_000 };
+
+// An aborted fetch, also once withRetry has raised it as a CancelledError
+function isAbort(error: unknown): boolean {
+  if (error instanceof FileSearchError) return error.code === 'CANCELLED';
+  return error instanceof Error && (error.name === 'AbortError' || error.name === 'TimeoutError');
+}
+
+// "500ms", "45s", "2m" or "1h"; a bare number is seconds
+export function parseDuration(value: string | number, name: string): number {
+  if (typeof value === 'number') {
+    if (!Number.isFinite(value) || value <= 0) throw new ConfigError(`${name} must be a positive duration`);
+    return value * 1000;
+  }
+  const match = /^(\d+(?:\.\d+)?)\s*(ms|s|m|h)?$/.exec(value.trim());
+  if (!match || Number(match[1]) <= 0) {
+    throw new ConfigError(`${name} must be a positive duration such as "500ms", "45s", "2m" or "1h", got "${value}"`);
+  }
+  return Number(match[1]) * DURATION_UNITS_MS[match[2] || 's'];
+}
+
+export function parseTimeoutsConfig(value: TomlValue | undefined): Timeouts {
+  if (value === undefined) return {};
+  expectTable(value, 'timeouts');
+
+  const timeouts: Timeouts = {};
+  for (const [key, item] of Object.entries(value)) {
+    if (!TIMEOUT_KINDS.includes(key as TimeoutKind)) {
+      throw new ConfigError(`Unknown timeout "${key}" (expected one of: ${TIMEOUT_KINDS.join(', ')})`);
+    }
+    if (typeof item !== 'string' && typeof item !== 'number') {
+      throw new ConfigError(`timeouts.${key} must be a duration such as "60s"`);
+    }
+    timeouts[key as TimeoutKind] = parseDuration(item, `timeouts.${key}`);
+  }
+  return timeouts;
+}
+
+// A signal that also aborts once timeoutMs has passed, for requests that outlive one call
+// such as streamed answers. An abort by the deadline is raised as a RequestTimeoutError,
+// so it is not mistaken for a cancellation; so is one by a client whose own timeout ran
+// out at the same time.
+export interface Deadline {
+  signal?: AbortSignal;
+  error(error: unknown): unknown;  // What to raise for a failure of the request
+  clear(): void;
+}
+
+export function startDeadline(kind: TimeoutKind, timeoutMs: number | undefined, signal?: AbortSignal): Deadline {
+  if (!timeoutMs) return { signal, error: error => error, clear: () => {} };
+
+  const controller = new AbortController();
+  const startedAt = Date.now();
+  const timer = setTimeout(() => controller.abort(), timeoutMs);
+  const onAbort = () => controller.abort(signal?.reason);
+  signal?.addEventListener('abort', onAbort, { once: true });
+  return {
+    signal: controller.signal,
+    error: error => isAbort(error) && !signal?.aborted && Date.now() - startedAt >= timeoutMs
+      ? new RequestTimeoutError(kind, timeoutMs, { cause: error })
+      : error,
+    clear: () => {
+      clearTimeout(timer);
+      signal?.removeEventListener('abort', onAbort);
+    },
+  };
+}
+
+export async function withTimeout<T>(
+  kind: TimeoutKind,
+  timeoutMs: number | undefined,
+  task: (signal?: AbortSignal) => Promise<T>,
+  signal?: AbortSignal
+): Promise<T> {
+  const deadline = startDeadline(kind, timeoutMs, signal);
+  try {
+    return await task(deadline.signal);
+  } catch (error) {
+    throw deadline.error(error);
+  } finally {
+    deadline.clear();
+  }
+}
@@ -0,0 +1,101 @@
+// toml.test.ts - The TOML parser on the values of the specification, and the errors of malformed ones
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { ConfigError } from './errors.js';
+import { parseToml } from './toml.js';
+
+function refuses(text: string, message: RegExp): void {
+  assert.throws(() => parseToml(text), (error: unknown) => {
+    assert.ok(error instanceof ConfigError, `${text}: ${String(error)}`);
+    assert.match(error.message, message);
+    return true;
+  });
+}
+
+test('tables, arrays of tables, dotted and quoted keys', () => {
+  assert.deepEqual(parseToml([
+    'name = "handbook"',
+    'site."google.com" = 1',
+    '[chunking]',
+    'max_tokens_per_chunk = 300  # tokens',
+    '[[rules]]',
+    'pattern = \'*.md\'',
+    '[[rules]]',
+    'pattern = \'*.rs\'',
+  ].join('\n')), {
+    name: 'handbook',
+    site: { 'google.com': 1 },
+    chunking: { max_tokens_per_chunk: 300 },
+    rules: [{ pattern: '*.md' }, { pattern: '*.rs' }],
+  });
+});
+
+test('numbers: decimal, hex, octal and binary integers, floats, underscores, inf and nan', () => {
+  const table = parseToml('a = +42\nb = 1_000\nc = 0xff\nd = 0o17\ne = 0b101\nf = -3.25\ng = 6.5e-3\nh = -inf\ni = nan\n');
+  assert.deepEqual({ ...table, i: undefined }, { a: 42, b: 1000, c: 255, d: 15, e: 5, f: -3.25, g: 0.0065, h: -Infinity, i: undefined });
+  assert.ok(Number.isNaN(table.i));
+});
+
+test('malformed numbers and other bare words are refused instead of read as strings', () => {
+  for (const value of ['1e', '.5', '0x', '5.', '1.2.3', 'yes', '2024-13']) {
+    refuses(`a = ${value}\n`, new RegExp(`line 1: invalid value "${value.replace(/\./g, '\\.')}"`));
+  }
+});
+
+test('dates and times are kept as written, with a T or a space before the time', () => {
+  assert.deepEqual(parseToml([
+    'odt = 1979-05-27T07:32:00Z',
+    'offset = 1979-05-27T00:32:00.999-07:00',
+    'local = 1979-05-27 07:32:00',
+    'spaced = 1979-05-27 07:32:00+09:00  # comment',
+    'date = 1979-05-27',
+    'time = 07:32:00.5',
+    'list = [1979-05-27 07:32:00, 1979-05-28]',
+  ].join('\n')), {
+    odt: '1979-05-27T07:32:00Z',
+    offset: '1979-05-27T00:32:00.999-07:00',
+    local: '1979-05-27 07:32:00',
+    spaced: '1979-05-27 07:32:00+09:00',
+    date: '1979-05-27',
+    time: '07:32:00.5',
+    list: ['1979-05-27 07:32:00', '1979-05-28'],
+  });
+});
+
+test('basic strings: escapes, and \\u and \\U needing their count of hex digits of a scalar value', () => {
+  assert.deepEqual(parseToml('a = "tab\\there \\"quoted\\" \\u00e9 \\U0001F600"\n'), { a: 'tab\there "quoted" é 😀' });
+  for (const escape of ['\\u00g1', '\\u12', '\\UZZZZZZZZ', '\\uD800', '\\U00110000']) {
+    refuses(`a = "${escape}"\n`, /line 1: invalid escape/);
+  }
+  refuses('a = "\\q"\n', /invalid escape "\\q"/);
+  refuses('a = "open\n', /unterminated string/);
+});
+
+test('multiline basic strings: the first newline is dropped, a line-ending backslash joins lines', () => {
+  const text = [
+    'joined = """',
+    'The quick \\',
+    '',
+    '    brown fox \\   ',
+    '  jumps."""',
+    'kept = """',
+    'one',
+    'two\\n"""',
+    'after = 1',
+  ].join('\n');
+  assert.deepEqual(parseToml(text), { joined: 'The quick brown fox jumps.', kept: 'one\ntwo\n', after: 1 });
+  // Line numbers still count the joined lines
+  refuses(`${text}\nbad = 1e\n`, /line 10: invalid value "1e"/);
+});
+
+test('literal strings take backslashes as they are', () => {
+  assert.deepEqual(parseToml("path = 'C:\\Users\\nodejs'\nregex = '''\n\\d{2} \\\n'''\n"), { path: 'C:\\Users\\nodejs', regex: '\\d{2} \\\n' });
+});
+
+test('keys named after Object.prototype members are own properties', () => {
+  const table = parseToml('__proto__ = 1\n[constructor]\nx = 2\n');
+  assert.ok(Object.hasOwn(table, '__proto__'));
+  assert.equal(table.__proto__, 1);
+  assert.deepEqual(table.constructor, { x: 2 });
+  assert.equal(Object.getPrototypeOf({}).polluted, undefined);
+});
@@ -0,0 +1,347 @@
+// toml.ts - Minimal TOML parser for configuration files
+//
+// Supports tables ([a.b]), arrays of tables ([[a]]), bare/quoted/dotted keys,
+// basic, literal and multiline strings, numbers (with inf and nan), booleans, arrays and
+// inline tables. Dates and times are kept as strings; any other bare value is an error.
+
+import { ConfigError } from './errors.js';
+
+const NUMBER = /^[+-]?(\d+(\.\d+)?([eE][+-]?\d+)?)$|^(0x[0-9a-fA-F]+|0o[0-7]+|0b[01]+)$/;
+// Offset and local date-times (with "T" or a space), local dates and local times
+const DATE = /^\d{4}-\d{2}-\d{2}$/;
+const TIME = /^\d{2}:\d{2}(:\d{2}(\.\d+)?)?$/;
+const DATE_TIME = /^\d{4}-\d{2}-\d{2}[Tt ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?([Zz]|[+-]\d{2}:\d{2})?$/;
+
+export type TomlValue = string | number | boolean | TomlValue[] | TomlTable;
+export interface TomlTable {
+  [key: string]: TomlValue;
+}
+
+// Keys like "__proto__" are read and written as own properties, so a file can't reach
+// Object.prototype through them
+function own(table: TomlTable, key: string): TomlValue | undefined {
+  return Object.hasOwn(table, key) ? table[key] : undefined;
+}
+
+function define(table: TomlTable, key: string, value: TomlValue): void {
+  Object.defineProperty(table, key, { value, enumerable: true, writable: true, configurable: true });
+}
+
+class TomlParser {
+  private pos = 0;
+  private line = 1;
+
+  constructor(private readonly text: string) {}
+
+  parse(): TomlTable {
+    const root: TomlTable = {};
+    let current = root;
+
+    while (this.skipWhitespaceAndComments()) {
+      if (this.peek() === '[') {
+        const isArray = this.text.startsWith('[[', this.pos);
+        this.pos += isArray ? 2 : 1;
+        const keys = this.parseKey();
+        this.skipInlineWhitespace();
+        this.expect(isArray ? ']]' : ']');
+        current = isArray ? this.appendTableArray(root, keys) : this.resolveTable(root, keys);
+      } else {
+        const keys = this.parseKey();
+        this.skipInlineWhitespace();
+        this.expect('=');
+        this.skipInlineWhitespace();
+        this.assign(current, keys, this.parseValue());
+      }
+      this.expectLineEnd();
+    }
+    return root;
+  }
+
+  private error(message: string): ConfigError {
+    return new ConfigError(`TOML parse error at line ${this.line}: ${message}`);
+  }
+
+  private peek(): string {
+    return this.text[this.pos];
+  }
+
+  private expect(token: string): void {
+    if (!this.text.startsWith(token, this.pos)) {
+      throw this.error(`expected "${token}"`);
+    }
+    this.pos += token.length;
+  }
+
+  private skipInlineWhitespace(): void {
+    while (this.peek() === ' ' || this.peek() === '\t') this.pos++;
+  }
+
+  // Skips blank lines and comments; returns false at end of input
+  private skipWhitespaceAndComments(): boolean {
+    while (this.pos < this.text.length) {
+      const c = this.peek();
+      if (c === ' ' || c === '\t' || c === '\r') {
+        this.pos++;
+      } else if (c === '\n') {
+        this.pos++;
+        this.line++;
+      } else if (c === '#') {
+        while (this.pos < this.text.length && this.peek() !== '\n') this.pos++;
+      } else {
+        return true;
+      }
+    }
+    return false;
+  }
+
+  private expectLineEnd(): void {
+    this.skipInlineWhitespace();
+    if (this.peek() === '#') {
+      while (this.pos < this.text.length && this.peek() !== '\n') this.pos++;
+    }
+    if (this.peek() === '\r') this.pos++;
+    if (this.pos < this.text.length && this.peek() !== '\n') {
+      throw this.error(`unexpected "${this.peek()}"`);
+    }
+  }
+
+  private parseKey(): string[] {
+    const keys: string[] = [];
+    for (;;) {
+      this.skipInlineWhitespace();
+      const c = this.peek();
+      if (c === '"') {
+        keys.push(this.parseBasicString());
+      } else if (c === "'") {
+        keys.push(this.parseLiteralString());
+      } else {
+        const match = /^[A-Za-z0-9_-]+/.exec(this.text.slice(this.pos));
+        if (!match) throw this.error('invalid key');
+        keys.push(match[0]);
+        this.pos += match[0].length;
+      }
+      this.skipInlineWhitespace();
+      if (this.peek() !== '.') return keys;
+      this.pos++;
+    }
+  }
+
+  private parseValue(): TomlValue {
+    const c = this.peek();
+    if (this.text.startsWith('"""', this.pos)) return this.parseMultilineString();
+    if (this.text.startsWith("'''", this.pos)) return this.parseMultilineLiteralString();
+    if (c === '"') return this.parseBasicString();
+    if (c === "'") return this.parseLiteralString();
+    if (c === '[') return this.parseArray();
+    if (c === '{') return this.parseInlineTable();
+    if (this.text.startsWith('true', this.pos)) {
+      this.pos += 4;
+      return true;
+    }
+    if (this.text.startsWith('false', this.pos)) {
+      this.pos += 5;
+      return false;
+    }
+
+    const match = /^[^\s,\]}#]+/.exec(this.text.slice(this.pos));
+    if (!match) throw this.error('missing value');
+    this.pos += match[0].length;
+    let value = match[0];
+    // A local date-time may separate the date from the time with a space
+    const time = DATE.test(value) ? /^ \d{2}:[^\s,\]}#]+/.exec(this.text.slice(this.pos)) : null;
+    if (time) {
+      value += time[0];
+      this.pos += time[0].length;
+    }
+    const raw = value.replace(/_/g, '');
+    if (/^[+-]?inf$/.test(raw)) return raw.startsWith('-') ? -Infinity : Infinity;
+    if (/^[+-]?nan$/.test(raw)) return NaN;
+    if (NUMBER.test(raw)) return Number(raw.replace(/^\+/, ''));
+    // Dates and times are returned verbatim
+    if (DATE.test(value) || TIME.test(value) || DATE_TIME.test(value)) return value;
+    throw this.error(`invalid value "${value}"`);
+  }
+
+  private parseBasicString(): string {
+    this.expect('"');
+    let result = '';
+    while (this.peek() !== '"') {
+      if (this.pos >= this.text.length || this.peek() === '\n') throw this.error('unterminated string');
+      if (this.peek() === '\\') {
+        result += this.parseEscape();
+      } else {
+        result += this.text[this.pos++];
+      }
+    }
+    this.pos++;
+    return result;
+  }
+
+  // A newline right after the opening quotes is dropped, and a backslash at the end of a
+  // line drops the newline and the whitespace after it
+  private parseMultilineString(): string {
+    this.expect('"""');
+    if (this.text.startsWith('\r\n', this.pos)) this.pos++;
+    if (this.peek() === '\n') {
+      this.pos++;
+      this.line++;
+    }
+    let result = '';
+    while (!this.text.startsWith('"""', this.pos)) {
+      if (this.pos >= this.text.length) throw this.error('unterminated string');
+      const lineEnd = /^\\[ \t]*\r?\n[ \t\r\n]*/.exec(this.text.slice(this.pos));
+      if (lineEnd) {
+        this.line += lineEnd[0].split('\n').length - 1;
+        this.pos += lineEnd[0].length;
+      } else if (this.peek() === '\\') {
+        result += this.parseEscape();
+      } else {
+        if (this.peek() === '\n') this.line++;
+        result += this.text[this.pos++];
+      }
+    }
+    this.pos += 3;
+    return result;
+  }
+
+  private parseEscape(): string {
+    this.pos++;
+    const c = this.text[this.pos++];
+    const escapes: { [key: string]: string } = { n: '\n', t: '\t', r: '\r', '"': '"', '\\': '\\', b: '\b', f: '\f' };
+    if (escapes[c] !== undefined) return escapes[c];
+    if (c === 'u' || c === 'U') {
+      const length = c === 'u' ? 4 : 8;
+      const digits = this.text.slice(this.pos, this.pos + length);
+      const code = parseInt(digits, 16);
+      // Unicode scalar values only: no surrogates, nothing past U+10FFFF
+      if (!new RegExp(`^[0-9a-fA-F]{${length}}$`).test(digits) || code > 0x10ffff || (code >= 0xd800 && code <= 0xdfff)) {
+        throw this.error(`invalid escape "\\${c}${digits}": needs ${length} hex digits of a Unicode scalar value`);
+      }
+      this.pos += length;
+      return String.fromCodePoint(code);
+    }
+    throw this.error(`invalid escape "\\${c}"`);
+  }
+
+  private parseLiteralString(): string {
+    this.expect("'");
+    const end = this.text.indexOf("'", this.pos);
+    if (end === -1) throw this.error('unterminated string');
+    const result = this.text.slice(this.pos, end);
+    this.pos = end + 1;
+    return result;
+  }
+
+  // No escapes; a newline right after the opening quotes is dropped
+  private parseMultilineLiteralString(): string {
+    this.expect("'''");
+    if (this.text.startsWith('\r\n', this.pos)) this.pos++;
+    if (this.peek() === '\n') {
+      this.pos++;
+      this.line++;
+    }
+    let end = this.text.indexOf("'''", this.pos);
+    if (end === -1) throw this.error('unterminated string');
+    // Up to two quotes may end the content, as in ''''quoted'''''
+    for (let extra = 0; extra < 2 && this.text[end + 3] === "'"; extra++) end++;
+    const result = this.text.slice(this.pos, end);
+    this.line += result.split('\n').length - 1;
+    this.pos = end + 3;
+    return result;
+  }
+
+  private parseArray(): TomlValue[] {
+    this.expect('[');
+    const values: TomlValue[] = [];
+    for (;;) {
+      this.skipWhitespaceAndComments();
+      if (this.peek() === ']') break;
+      values.push(this.parseValue());
+      this.skipWhitespaceAndComments();
+      if (this.peek() === ',') {
+        this.pos++;
+      } else if (this.peek() !== ']') {
+        throw this.error('expected "," or "]" in array');
+      }
+    }
+    this.pos++;
+    return values;
+  }
+
+  private parseInlineTable(): TomlTable {
+    this.expect('{');
+    const table: TomlTable = {};
+    this.skipInlineWhitespace();
+    if (this.peek() === '}') {
+      this.pos++;
+      return table;
+    }
+    for (;;) {
+      const keys = this.parseKey();
+      this.expect('=');
+      this.skipInlineWhitespace();
+      this.assign(table, keys, this.parseValue());
+      this.skipInlineWhitespace();
+      if (this.peek() === '}') break;
+      this.expect(',');
+    }
+    this.pos++;
+    return table;
+  }
+
+  private resolveTable(table: TomlTable, keys: string[]): TomlTable {
+    let current = table;
+    for (const key of keys) {
+      let next = own(current, key);
+      if (next === undefined) {
+        next = {};
+        define(current, key, next);
+      }
+      if (Array.isArray(next)) {
+        next = next[next.length - 1];
+      }
+      if (typeof next !== 'object' || Array.isArray(next)) {
+        throw this.error(`key "${key}" is not a table`);
+      }
+      current = next;
+    }
+    return current;
+  }
+
+  private appendTableArray(root: TomlTable, keys: string[]): TomlTable {
+    const parent = this.resolveTable(root, keys.slice(0, -1));
+    const key = keys[keys.length - 1];
+    const existing = own(parent, key);
+    const entry: TomlTable = {};
+    if (existing === undefined) {
+      define(parent, key, [entry]);
+    } else if (Array.isArray(existing)) {
+      existing.push(entry);
+    } else {
+      throw this.error(`key "${key}" is not an array of tables`);
+    }
+    return entry;
+  }
+
+  private assign(table: TomlTable, keys: string[], value: TomlValue): void {
+    const target = this.resolveTable(table, keys.slice(0, -1));
+    const key = keys[keys.length - 1];
+    if (own(target, key) !== undefined) {
+      throw this.error(`duplicate key "${key}"`);
+    }
+    define(target, key, value);
+  }
+}
+
+export function parseToml(text: string): TomlTable {
+  return new TomlParser(text).parse();
+}
+
+export function isTable(value: TomlValue | undefined): value is TomlTable {
+  return typeof value === 'object' && !Array.isArray(value);
+}
+
+// For the settings sections, e.g. expectTable(value, 'quota') for a [quota] that is not a table
+export function expectTable(value: TomlValue | undefined, name: string): asserts value is TomlTable {
+  if (!isTable(value)) throw new ConfigError(`${name} must be a table`);
+}
@@ -0,0 +1,296 @@
+// transforms.ts - Named document transforms applied to the text of matching files before upload
+import * as path from 'path';
+import { ConfigError, InvalidInputError } from './errors.js';
+import { DocumentMetadata, MetadataValue } from './metadata.js';
+import { TomlTable, TomlValue, isTable, parseToml } from './toml.js';
+
+// Types
+export interface DocumentContext {
+  filePath: string;  // The file on disk, for transforms that depend on its extension
+  relativePath: string;
+  firstLine: number;  // 1-based line of the file the text starts at; later for a piece of a split file
+}
+
+export interface TransformedText {
+  text: string;
+  metadata?: DocumentMetadata;  // Added to the document's metadata
+}
+
+export interface DocumentTransform {
+  readonly name: string;  // Named in the apply list of a [[transforms]] rule
+  readonly description: string;
+  apply(text: string, context: DocumentContext): TransformedText;
+}
+
+export interface TransformRule {
+  pattern: string;  // Glob matched as --include globs are
+  transforms: DocumentTransform[];
+}
+
+interface CommentSyntax {
+  line: string[];
+  block?: [string, string];
+  quotes: string[];
+  hashAfterSpace?: boolean;  // "#" starts a comment only at the start of a line or after whitespace
+}
+
+const C_LIKE: CommentSyntax = { line: ['//'], block: ['/*', '*/'], quotes: ['"', "'", '`'] };
+const RUST: CommentSyntax = { line: ['//'], block: ['/*', '*/'], quotes: ['"'] };  // ' also starts lifetimes
+const CSS: CommentSyntax = { line: [], block: ['/*', '*/'], quotes: ['"', "'"] };
+const HASH: CommentSyntax = { line: ['#'], quotes: ['"', "'"], hashAfterSpace: true };
+const SQL: CommentSyntax = { line: ['--'], block: ['/*', '*/'], quotes: ["'", '"'] };
+const MARKUP: CommentSyntax = { line: [], block: ['<!--', '-->'], quotes: [] };
+
+const COMMENT_SYNTAX: { [extension: string]: CommentSyntax } = {
+  '.js': C_LIKE, '.jsx': C_LIKE, '.mjs': C_LIKE, '.cjs': C_LIKE, '.ts': C_LIKE, '.tsx': C_LIKE,
+  '.java': C_LIKE, '.kt': C_LIKE, '.kts': C_LIKE, '.scala': C_LIKE, '.groovy': C_LIKE,
+  '.c': C_LIKE, '.h': C_LIKE, '.cc': C_LIKE, '.cpp': C_LIKE, '.hpp': C_LIKE, '.cs': C_LIKE,
+  '.go': C_LIKE, '.swift': C_LIKE, '.dart': C_LIKE, '.php': C_LIKE, '.proto': C_LIKE,
+  '.rs': RUST,
+  '.css': CSS, '.scss': CSS, '.less': CSS,
+  '.py': HASH, '.rb': HASH, '.pl': HASH, '.r': HASH, '.sh': HASH, '.bash': HASH, '.zsh': HASH,
+  '.yaml': HASH, '.yml': HASH, '.toml': HASH, '.conf': HASH, '.cfg': HASH, '.tf': HASH,
+  '.sql': SQL,
+  '.html': MARKUP, '.htm': MARKUP, '.xml': MARKUP, '.svg': MARKUP, '.vue': MARKUP, '.md': MARKUP, '.markdown': MARKUP,
+};
+
+// Indentation carries meaning, so minify keeps it
+const INDENT_SENSITIVE = new Set(['.py', '.yaml', '.yml', '.coffee', '.pug', '.haml', '.nim']);
+
+// Blank lines separate paragraphs, so minify keeps one
+const PROSE_EXTENSIONS = new Set(['.md', '.markdown', '.mdx', '.txt', '.rst', '.adoc', '.org']);
+
+const FRONT_MATTER = /^\uFEFF?(---|\+\+\+)[ \t]*\r?\n(?:([\s\S]*?)\r?\n)?\1[ \t]*(?:\r?\n|$)/;
+
+// Front matter keys become metadata keys; characters metadata keys cannot hold become "_"
+function metadataKey(name: string): string {
+  return name.replace(/[^A-Za-z0-9_.-]/g, '_').replace(/^(?=[^A-Za-z_])/, '_');
+}
+
+function unquote(value: string): string {
+  const quoted = /^(["'])([\s\S]*)\1$/.exec(value);
+  return quoted ? quoted[2] : value;
+}
+
+function yamlValue(raw: string): MetadataValue {
+  if (/^\[.*\]$/.test(raw)) {
+    return raw.slice(1, -1).split(',').map(item => unquote(item.trim())).filter(Boolean);
+  }
+  if (/^["']/.test(raw)) return unquote(raw);
+  const value = raw.replace(/\s+#.*$/, '');
+  return /^-?\d+(?:\.\d+)?$/.test(value) ? Number(value) : value;
+}
+
+// The flat part of YAML front matter: scalars, [a, b] lists and "- item" lists. Nested
+// mappings are left out, since metadata values cannot hold them.
+function parseYamlFrontMatter(body: string): DocumentMetadata {
+  const metadata: DocumentMetadata = {};
+  let listKey: string | undefined;
+  for (const line of body.split(/\r?\n/)) {
+    if (!line.trim() || line.trimStart().startsWith('#')) continue;
+    const item = /^\s*-\s+(.*)$/.exec(line);
+    if (item && listKey) {
+      (metadata[listKey] as string[]).push(unquote(item[1].trim()));
+      continue;
+    }
+    listKey = undefined;
+    const pair = /^([^\s:#-][^:]*):(?:\s+(.*))?$/.exec(line);
+    if (!pair) continue;
+    const key = metadataKey(pair[1].trim());
+    const raw = (pair[2] || '').trim();
+    if (raw) {
+      metadata[key] = yamlValue(raw);
+    } else {
+      metadata[key] = [];
+      listKey = key;
+    }
+  }
+  for (const [key, value] of Object.entries(metadata)) {
+    if (Array.isArray(value) && value.length === 0) delete metadata[key];
+  }
+  return metadata;
+}
+
+function tomlMetadataValue(value: TomlValue): MetadataValue | undefined {
+  if (typeof value === 'string' || typeof value === 'number') return value;
+  if (typeof value === 'boolean') return String(value);
+  if (Array.isArray(value) && value.every(item => typeof item === 'string' || typeof item === 'number')) return value.map(String);
+  return undefined;
+}
+
+function parseTomlFrontMatter(body: string): DocumentMetadata {
+  let table: TomlTable;
+  try {
+    table = parseToml(body);
+  } catch (error) {
+    return {};
+  }
+  const metadata: DocumentMetadata = {};
+  for (const [key, value] of Object.entries(table)) {
+    const converted = tomlMetadataValue(value);
+    if (converted !== undefined) metadata[metadataKey(key)] = converted;
+  }
+  return metadata;
+}
+
+function syntaxOf(filePath: string): CommentSyntax | undefined {
+  return COMMENT_SYNTAX[path.extname(filePath).toLowerCase()];
+}
+
+// Removes the comments of the syntax, outside string literals. Newlines inside block
+// comments are kept, so every line stays where it was in the file.
+function stripComments(text: string, syntax: CommentSyntax): string {
+  let result = '';
+  let i = 0;
+  while (i < text.length) {
+    const c = text[i];
+    if (syntax.quotes.includes(c)) {
+      let j = i + 1;
+      while (j < text.length && text[j] !== c && (c === '`' || text[j] !== '\n')) j += text[j] === '\\' ? 2 : 1;
+      result += text.slice(i, j + 1);
+      i = j + 1;
+      continue;
+    }
+    if (syntax.block && text.startsWith(syntax.block[0], i)) {
+      const end = text.indexOf(syntax.block[1], i + syntax.block[0].length);
+      const comment = text.slice(i, end === -1 ? text.length : end + syntax.block[1].length);
+      result += comment.replace(/[^\n]/g, '');
+      i += comment.length;
+      continue;
+    }
+    const line = syntax.line.find(marker => text.startsWith(marker, i));
+    const shebang = i === 0 && text.startsWith('#!');
+    if (line && !shebang && !(syntax.hashAfterSpace && i > 0 && !/\s/.test(text[i - 1]))) {
+      const end = text.indexOf('\n', i);
+      i = end === -1 ? text.length : end;
+      continue;
+    }
+    result += c;
+    i++;
+  }
+  return result.replace(/[ \t]+$/gm, '');
+}
+
+// YAML (---) or TOML (+++) front matter at the top of the text, moved into metadata
+export class FrontMatterTransform implements DocumentTransform {
+  readonly name = 'front-matter';
+  readonly description = 'Move YAML or TOML front matter into document metadata';
+
+  apply(text: string): TransformedText {
+    const match = FRONT_MATTER.exec(text);
+    if (!match) return { text };
+    const body = match[2] || '';
+    const metadata = match[1] === '---' ? parseYamlFrontMatter(body) : parseTomlFrontMatter(body);
+    return { text: text.slice(match[0].length), metadata };
+  }
+}
+
+// Files in a language without a known comment syntax are left as they are
+export class StripCommentsTransform implements DocumentTransform {
+  readonly name = 'strip-comments';
+  readonly description = 'Remove code comments, keeping line positions';
+
+  apply(text: string, context: DocumentContext): TransformedText {
+    const syntax = syntaxOf(context.filePath);
+    return { text: syntax ? stripComments(text, syntax) : text };
+  }
+}
+
+// "  12 | " before each line, so answers can cite line numbers
+export class LineNumbersTransform implements DocumentTransform {
+  readonly name = 'line-numbers';
+  readonly description = 'Prefix each line with its line number in the file';
+
+  apply(text: string, context: DocumentContext): TransformedText {
+    const lines = text.split('\n');
+    if (lines[lines.length - 1] === '') lines.pop();
+    const width = String(context.firstLine + lines.length - 1).length;
+    return {
+      text: lines.map((line, i) => `${String(context.firstLine + i).padStart(width)} | ${line}`).join('\n') + '\n',
+    };
+  }
+}
+
+// Fewer bytes and tokens: JSON without formatting; code without blank lines and, where
+// indentation carries no meaning, without it; prose with single blank lines
+export class MinifyTransform implements DocumentTransform {
+  readonly name = 'minify';
+  readonly description = 'Remove insignificant whitespace';
+
+  apply(text: string, context: DocumentContext): TransformedText {
+    const extension = path.extname(context.filePath).toLowerCase();
+    if (extension === '.json') {
+      try {
+        return { text: JSON.stringify(JSON.parse(text)) };
+      } catch (error) {
+        // Not valid JSON; reduced like other code
+      }
+    }
+    const trimmed = text.replace(/\r\n/g, '\n').replace(/[ \t]+$/gm, '');
+    if (PROSE_EXTENSIONS.has(extension) || !(extension in COMMENT_SYNTAX || extension === '.json')) {
+      return { text: trimmed.replace(/\n{3,}/g, '\n\n').trim() + '\n' };
+    }
+    const lines = trimmed.split('\n').filter(line => line !== '');
+    return { text: (INDENT_SENSITIVE.has(extension) ? lines : lines.map(line => line.trimStart())).join('\n') + '\n' };
+  }
+}
+
+const documentTransforms = new Map<string, DocumentTransform>();
+
+// Adds a transform, or replaces the one registered under the same name
+export function registerDocumentTransform(transform: DocumentTransform): void {
+  documentTransforms.set(transform.name, transform);
+}
+
+registerDocumentTransform(new FrontMatterTransform());
+registerDocumentTransform(new StripCommentsTransform());
+registerDocumentTransform(new LineNumbersTransform());
+registerDocumentTransform(new MinifyTransform());
+
+export function listDocumentTransforms(): DocumentTransform[] {
+  return [...documentTransforms.values()];
+}
+
+export function resolveDocumentTransforms(names: string[]): DocumentTransform[] {
+  return names.map(name => {
+    const transform = documentTransforms.get(name);
+    if (!transform) {
+      throw new InvalidInputError(`Unknown document transform "${name}" (expected one of: ${[...documentTransforms.keys()].join(', ')})`);
+    }
+    return transform;
+  });
+}
+
+export function parseTransformRules(value: TomlValue | undefined): TransformRule[] {
+  if (value === undefined) return [];
+  if (!Array.isArray(value)) throw new ConfigError('transforms must be [[transforms]] entries');
+
+  return value.map((rule, index) => {
+    const pattern = isTable(rule) ? rule.pattern : undefined;
+    const names = isTable(rule) ? rule.apply : undefined;
+    if (typeof pattern !== 'string' || !pattern) {
+      throw new ConfigError(`transforms[${index}]: pattern must be a non-empty glob`);
+    }
+    if (!Array.isArray(names) || names.length === 0 || !names.every(name => typeof name === 'string' && name)) {
+      throw new ConfigError(`transforms[${index}]: apply must be a non-empty list of transform names`);
+    }
+    try {
+      return { pattern, transforms: resolveDocumentTransforms(names as string[]) };
+    } catch (error) {
+      throw new ConfigError(`transforms[${index}]: ${(error as Error).message}`);
+    }
+  });
+}
+
+// Runs the chain in order; metadata of later transforms wins, and the names applied are
+// recorded as "transforms" metadata
+export function applyDocumentTransforms(chain: DocumentTransform[], text: string, context: DocumentContext): Required<TransformedText> {
+  let result = text;
+  let metadata: DocumentMetadata = {};
+  for (const transform of chain) {
+    const transformed = transform.apply(result, context);
+    result = transformed.text;
+    metadata = { ...metadata, ...transformed.metadata };
+  }
+  return { text: result, metadata: { ...metadata, transforms: chain.map(transform => transform.name) } };
+}
@@ -0,0 +1,756 @@
+// tui.ts - Terminal UI with panes for stores, fuzzy-filtered documents, and a query box with streaming answers and citations
+import * as fs from 'fs';
+import * as path from 'path';
+import { spawn, spawnSync } from 'child_process';
+import { FileSearchClient, QueryOptions } from './client.js';
+import { StoreInfo } from './store.js';
+import { DocumentInfo, formatDocumentMarkdown, groupDocumentParts, toDocumentInfo } from './documents.js';
+import { ChunkingPolicy } from './chunking.js';
+import { Citation, extractCitations, formatCitationFile } from './citations.js';
+import { addCitationContext, formatCitationContext, formatContextLocation } from './cite-context.js';
+import { PromptConfig, resolveSystemInstruction } from './prompts.js';
+import { DoneEvent } from './stream.js';
+import { formatUsage } from './cost.js';
+import { formatBlockedAnswer } from './generation.js';
+import { displayWidth } from './render.js';
+import { InvalidInputError, toFileSearchError } from './errors.js';
+
+// Types
+export type TuiPane = 'stores' | 'documents' | 'query' | 'answer' | 'citations';
+
+export interface TuiOptions {
+  stores?: string[];  // Listed first and marked for the first question
+  query?: QueryOptions;  // Model, generation and grounding settings of every question
+  prompt?: PromptConfig;  // Stored instruction and answer style of the marked stores
+  system?: string;  // Replaces the stored instruction
+  chunking?: ChunkingPolicy;  // For the estimated chunks of the document details
+  sourceRoots: string[];  // Where cited and selected files are looked up, in order
+  contextLines?: number;  // Lines shown around a cited chunk (default: 3)
+  input?: NodeJS.ReadStream;
+  output?: NodeJS.WriteStream;
+}
+
+export interface Key {
+  name: string;  // "char" for text, "click", "wheelup" and "wheeldown" for the mouse, else e.g. "enter" or "ctrl-c"
+  char?: string;
+  x?: number;  // 1-based cell of a mouse event
+  y?: number;
+}
+
+export interface FuzzyMatch {
+  score: number;
+  positions: number[];  // Indices of the matched characters in the text
+}
+
+interface Rect {
+  x: number;  // 1-based cell of the top left corner, borders included
+  y: number;
+  width: number;
+  height: number;
+}
+
+const DEFAULT_CONTEXT_LINES = 3;
+
+const PANES: TuiPane[] = ['stores', 'documents', 'query', 'answer', 'citations'];
+
+const ANSI = {
+  reset: '\x1b[0m',
+  bold: '\x1b[1m',
+  inverse: '\x1b[7m',
+  red: '\x1b[31m',
+  yellow: '\x1b[33m',
+  cyan: '\x1b[36m',
+  gray: '\x1b[90m',
+};
+
+// Alternate screen, hidden cursor and SGR mouse reports, so clicks arrive with their cell
+const ENTER_SCREEN = '\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h';
+const LEAVE_SCREEN = '\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l';
+
+const SEQUENCES: { [sequence: string]: string } = {
+  '\x1b[A': 'up', '\x1b[B': 'down', '\x1b[C': 'right', '\x1b[D': 'left',
+  '\x1bOA': 'up', '\x1bOB': 'down', '\x1bOC': 'right', '\x1bOD': 'left',
+  '\x1b[5~': 'pageup', '\x1b[6~': 'pagedown',
+  '\x1b[H': 'home', '\x1b[F': 'end', '\x1b[1~': 'home', '\x1b[4~': 'end', '\x1bOH': 'home', '\x1bOF': 'end',
+  '\x1b[Z': 'backtab', '\x1b[3~': 'delete',
+};
+
+const CONTROLS: { [char: string]: string } = {
+  '\r': 'enter', '\n': 'enter', '\t': 'tab', '\x7f': 'backspace', '\b': 'backspace',
+  '\x03': 'ctrl-c', '\x0c': 'ctrl-l', '\x0f': 'ctrl-o', '\x12': 'ctrl-r', '\x15': 'ctrl-u',
+};
+
+const HINTS: { [pane in TuiPane]: string } = {
+  stores: '↑↓ select  Space mark  type to filter documents  Tab next pane  Ctrl-C quit',
+  documents: 'type to filter  ↑↓ select  Enter details  Ctrl-O open  Esc clear',
+  query: 'Enter ask  ↑ last question  Esc clear  Tab next pane',
+  answer: '↑↓ PgUp PgDn scroll  Tab next pane',
+  citations: '↑↓ select  Enter or click to open  Tab next pane',
+};
+
+// The keys in a chunk of raw terminal input; unknown escape sequences are dropped
+export function parseKeys(data: string): Key[] {
+  const keys: Key[] = [];
+  let i = 0;
+  while (i < data.length) {
+    const mouse = /^\x1b\[<(\d+);(\d+);(\d+)([Mm])/.exec(data.slice(i));
+    if (mouse) {
+      const button = Number(mouse[1]);
+      const name = button === 0 ? 'click' : button === 64 ? 'wheelup' : button === 65 ? 'wheeldown' : undefined;
+      if (name && mouse[4] === 'M') keys.push({ name, x: Number(mouse[2]), y: Number(mouse[3]) });
+      i += mouse[0].length;
+      continue;
+    }
+    const sequence = Object.keys(SEQUENCES).find(candidate => data.startsWith(candidate, i));
+    if (sequence) {
+      keys.push({ name: SEQUENCES[sequence] });
+      i += sequence.length;
+      continue;
+    }
+    if (data[i] === '\x1b') {
+      const unknown = /^\x1b\[[0-9;?]*[~A-Za-z]/.exec(data.slice(i));
+      if (!unknown) keys.push({ name: 'escape' });
+      i += unknown ? unknown[0].length : 1;
+      continue;
+    }
+    const char = String.fromCodePoint(data.codePointAt(i) || 0);
+    i += char.length;
+    if (CONTROLS[char]) keys.push({ name: CONTROLS[char] });
+    else if (char >= ' ') keys.push({ name: 'char', char });
+  }
+  return keys;
+}
+
+// The characters of the query in order, ignoring case and spaces; runs of consecutive
+// characters and matches at the start of a path segment or word score higher
+export function fuzzyMatch(query: string, text: string): FuzzyMatch | undefined {
+  const needle = query.toLowerCase().replace(/\s+/g, '');
+  if (!needle) return { score: 0, positions: [] };
+  const haystack = text.toLowerCase();
+  const positions: number[] = [];
+  let score = 0;
+  let from = 0;
+  for (const char of needle) {
+    const found = haystack.indexOf(char, from);
+    if (found === -1) return undefined;
+    const previous = positions[positions.length - 1];
+    if (previous !== undefined) score += found === previous + 1 ? 5 : -Math.min(found - previous - 1, 10) / 2;
+    if (found === 0 || /[\/\\_\-. ]/.test(text[found - 1])) score += 3;
+    score += 1;
+    positions.push(found);
+    from = found + char.length;
+  }
+  // Among equal matches, shorter names first
+  return { score: score - text.length / 100, positions };
+}
+
+export function filterDocuments(documents: DocumentInfo[], query: string): { document: DocumentInfo; match: FuzzyMatch }[] {
+  const entries: { document: DocumentInfo; match: FuzzyMatch }[] = [];
+  for (const document of documents) {
+    const match = fuzzyMatch(query, document.displayName || document.name);
+    if (match) entries.push({ document, match });
+  }
+  return entries.sort((a, b) => b.match.score - a.match.score
+    || (a.document.displayName || a.document.name).localeCompare(b.document.displayName || b.document.name));
+}
+
+// Cut or padded to exactly `width` columns; escape codes are kept and control characters dropped
+export function fitText(text: string, width: number): string {
+  let fitted = '';
+  let used = 0;
+  for (const part of text.split(/(\x1b\[[0-9;]*m)/)) {
+    if (part.startsWith('\x1b[')) {
+      fitted += part;
+      continue;
+    }
+    for (const char of part) {
+      if (char < ' ') continue;
+      const charWidth = displayWidth(char);
+      if (used + charWidth > width) return `${fitted}${ANSI.reset}${' '.repeat(width - used)}`;
+      fitted += char;
+      used += charWidth;
+    }
+  }
+  return `${fitted}${' '.repeat(width - used)}`;
+}
+
+// Lines of at most `width` columns, broken at spaces where there are any
+export function wrapText(text: string, width: number): string[] {
+  const lines: string[] = [];
+  for (const paragraph of text.replace(/\t/g, '  ').split(/\r?\n/)) {
+    let line = '';
+    for (const word of paragraph.split(/(?<= )/)) {
+      if (displayWidth(line + word.trimEnd()) <= width) {
+        line += word;
+        continue;
+      }
+      if (line) lines.push(line.trimEnd());
+      line = '';
+      for (const char of word) {
+        if (displayWidth(line + char) > width) {
+          lines.push(line);
+          line = '';
+        }
+        line += char;
+      }
+    }
+    lines.push(line.trimEnd());
+  }
+  return lines;
+}
+
+// The end of the text in `width` columns, for input longer than its box
+function tailText(text: string, width: number): string {
+  if (displayWidth(text) <= width) return text;
+  const chars = [...text];
+  let tail = '';
+  while (chars.length > 0 && displayWidth(`…${chars[chars.length - 1]}${tail}`) <= width) tail = `${chars.pop()}${tail}`;
+  return `…${tail}`;
+}
+
+function highlight(text: string, positions: number[]): string {
+  const matched = new Set(positions);
+  let highlighted = '';
+  let index = 0;
+  for (const char of text) {
+    highlighted += matched.has(index) ? `${ANSI.bold}${ANSI.yellow}${char}${ANSI.reset}` : char;
+    index += char.length;
+  }
+  return highlighted;
+}
+
+function box(title: string, body: string[], rect: Rect, focused: boolean): string[] {
+  const color = focused ? `${ANSI.bold}${ANSI.cyan}` : ANSI.gray;
+  const label = fitText(` ${title} `, Math.max(0, rect.width - 4)).trimEnd();
+  const inner = rect.width - 2;
+  const lines = [`${color}┌─${ANSI.reset}${focused ? ANSI.bold : ''}${label}${ANSI.reset} ${color}${'─'.repeat(Math.max(0, rect.width - 4 - displayWidth(label)))}┐${ANSI.reset}`];
+  for (let i = 0; i < rect.height - 2; i++) {
+    lines.push(`${color}│${ANSI.reset}${fitText(body[i] ?? '', inner)}${ANSI.reset}${color}│${ANSI.reset}`);
+  }
+  lines.push(`${color}└${'─'.repeat(inner)}┘${ANSI.reset}`);
+  return lines;
+}
+
+// Selected rows are shown plain, as the styles of the text would end the inverse
+function listRow(text: string, plain: string, width: number, selected: boolean, focused: boolean): string {
+  if (!selected) return text;
+  return `${focused ? ANSI.inverse : ANSI.bold}${fitText(plain, width)}${ANSI.reset}`;
+}
+
+function gray(text: string): string {
+  return `${ANSI.gray}${text}${ANSI.reset}`;
+}
+
+// Opens the file at the line in $VISUAL or $EDITOR, which take over the terminal until
+// they exit, else with the desktop's default application. Returns what to report.
+function openFile(file: string, line: number, suspend: () => void, resume: () => void): string {
+  const editor = (process.env.VISUAL || process.env.EDITOR || '').trim();
+  if (editor) {
+    const [command, ...args] = editor.split(/\s+/).filter(arg => arg);
+    const target = /^(code|code-insiders|codium|cursor)(\.cmd)?$/.test(path.basename(command)) ? ['--goto', `${file}:${line}`] : [`+${line}`, file];
+    suspend();
+    const result = spawnSync(command, [...args, ...target], { stdio: 'inherit' });
+    resume();
+    return result.error ? `Could not run ${command}: ${result.error.message}` : `Opened ${file}:${line} in ${command}`;
+  }
+  const [command, ...args] = process.platform === 'darwin' ? ['open', file]
+    : process.platform === 'win32' ? ['cmd', '/c', 'start', '', file]
+    : ['xdg-open', file];
+  const child = spawn(command, args, { detached: true, stdio: 'ignore' });
+  child.on('error', () => undefined);  // Without a desktop the file just does not open
+  child.unref();
+  return `Opened ${file} with ${command}; set EDITOR to open it at line ${line}`;
+}
+
+// Stores on the left with the fuzzy-filtered documents of the highlighted one; the
+// question goes to the marked stores, else the highlighted one, and streams into the
+// answer pane, with its citations below, which open in the editor at the cited lines
+export class TuiApp {
+  private readonly input: NodeJS.ReadStream;
+  private readonly output: NodeJS.WriteStream;
+  private stores: StoreInfo[] = [];
+  private storesLoaded = false;
+  private storeIndex = 0;
+  private readonly marked: Set<string>;
+  private readonly documents = new Map<string, DocumentInfo[] | 'loading' | Error>();
+  private filtered?: { documents: DocumentInfo[]; filter: string; entries: { document: DocumentInfo; match: FuzzyMatch }[] };
+  private documentFilter = '';
+  private documentIndex = 0;
+  private question = '';
+  private readonly asked: string[] = [];
+  private answerTitle = 'Answer';
+  private answer = '';
+  private answerScroll?: number;  // Undefined follows the end of the answer
+  private answerMaxScroll = 0;
+  private citations: Citation[] = [];
+  private citationIndex = 0;
+  private focus: TuiPane = 'stores';
+  private status = '';
+  private streaming?: { cancelled: boolean };
+  private layout = new Map<TuiPane, Rect>();
+  private readonly offsets: Partial<Record<TuiPane, number>> = {};
+  private done?: () => void;
+  private running = false;
+
+  constructor(private readonly client: FileSearchClient, private readonly options: TuiOptions) {
+    this.input = options.input || process.stdin;
+    this.output = options.output || process.stdout;
+    this.marked = new Set(options.stores || []);
+  }
+
+  // Resolves when the user quits; the terminal is restored however the UI ends
+  async run(): Promise<void> {
+    if (!this.input.isTTY || !this.output.isTTY) {
+      throw new InvalidInputError('The TUI needs an interactive terminal; use npm run chat or npm run query when piping');
+    }
+    const onData = (data: Buffer) => this.handle(parseKeys(data.toString('utf8')));
+    const onResize = () => this.render();
+    const restore = () => this.suspend();
+    this.input.setRawMode(true);
+    this.input.resume();
+    this.input.on('data', onData);
+    this.output.on('resize', onResize);
+    process.on('exit', restore);
+    this.output.write(ENTER_SCREEN);
+    this.running = true;
+    try {
+      const finished = new Promise<void>(resolve => {
+        this.done = resolve;
+      });
+      this.loadStores();
+      this.render();
+      await finished;
+    } finally {
+      this.running = false;
+      this.input.off('data', onData);
+      this.output.off('resize', onResize);
+      process.off('exit', restore);
+      this.suspend();
+      this.input.pause();
+    }
+  }
+
+  private suspend(): void {
+    this.output.write(LEAVE_SCREEN);
+    this.input.setRawMode(false);
+  }
+
+  private resume(): void {
+    this.input.setRawMode(true);
+    this.output.write(`${ENTER_SCREEN}\x1b[2J`);
+  }
+
+  // Stores given on the command line first, in their order, then the others by name
+  private async loadStores(): Promise<void> {
+    this.status = 'Listing stores…';
+    this.render();
+    try {
+      const listed = await this.client.stores.listStores();
+      const given = await Promise.all((this.options.stores || []).map(name => listed.find(store => store.name === name) || this.client.stores.getStore(name)));
+      const others = listed.filter(store => !given.some(first => first.name === store.name))
+        .sort((a, b) => (a.displayName || a.name).localeCompare(b.displayName || b.name));
+      this.stores = [...given, ...others];
+      this.storeIndex = Math.min(this.storeIndex, Math.max(0, this.stores.length - 1));
+      this.status = `${this.stores.length} stores`;
+      if (this.store) this.loadDocuments(this.store.name);
+    } catch (error) {
+      const err = toFileSearchError(error);
+      this.status = `❌ [${err.code}] ${err.message}`;
+    }
+    this.storesLoaded = true;
+    this.render();
+  }
+
+  private async loadDocuments(storeName: string, reload: boolean = false): Promise<void> {
+    if (this.documents.has(storeName) && !reload) return;
+    this.documents.set(storeName, 'loading');
+    this.render();
+    try {
+      const documents = (await this.client.listDocuments(storeName)).map(document => toDocumentInfo(document, this.options.chunking));
+      this.documents.set(storeName, groupDocumentParts(documents));
+    } catch (error) {
+      this.documents.set(storeName, toFileSearchError(error));
+    }
+    this.render();
+  }
+
+  private get store(): StoreInfo | undefined {
+    return this.stores[this.storeIndex];
+  }
+
+  private targetStores(): StoreInfo[] {
+    const marked = this.stores.filter(store => this.marked.has(store.name));
+    return marked.length > 0 ? marked : this.store ? [this.store] : [];
+  }
+
+  private visibleDocuments(): { document: DocumentInfo; match: FuzzyMatch }[] {
+    const documents = this.store ? this.documents.get(this.store.name) : undefined;
+    if (!Array.isArray(documents)) return [];
+    if (this.filtered?.documents !== documents || this.filtered.filter !== this.documentFilter) {
+      this.filtered = { documents, filter: this.documentFilter, entries: filterDocuments(documents, this.documentFilter) };
+    }
+    return this.filtered.entries;
+  }
+
+  private selectStore(index: number): void {
+    const clamped = Math.max(0, Math.min(index, this.stores.length - 1));
+    if (clamped === this.storeIndex || !this.stores[clamped]) return;
+    this.storeIndex = clamped;
+    this.documentIndex = 0;
+    this.loadDocuments(this.stores[clamped].name);
+  }
+
+  private handle(keys: Key[]): void {
+    for (const key of keys) this.handleKey(key);
+    this.render();
+  }
+
+  private handleKey(key: Key): void {
+    switch (key.name) {
+      case 'ctrl-c':
+        // Stops the answer first; a second Ctrl-C quits
+        if (this.streaming) this.streaming.cancelled = true;
+        else this.done?.();
+        return;
+      case 'tab':
+      case 'backtab':
+        this.focus = PANES[(PANES.indexOf(this.focus) + (key.name === 'tab' ? 1 : PANES.length - 1)) % PANES.length];
+        return;
+      case 'ctrl-l':
+        this.output.write('\x1b[2J');
+        return;
+      case 'ctrl-r':
+        if (this.focus === 'stores') this.loadStores();
+        else if (this.store) this.loadDocuments(this.store.name, true);
+        return;
+      case 'click':
+        this.click(key.x || 0, key.y || 0);
+        return;
+      case 'wheelup':
+      case 'wheeldown': {
+        const pane = this.paneAt(key.x || 0, key.y || 0);
+        if (pane) this.move(pane, key.name === 'wheelup' ? -3 : 3);
+        return;
+      }
+    }
+    if (key.name === 'escape' && this.streaming) {
+      this.streaming.cancelled = true;
+      return;
+    }
+
+    switch (this.focus) {
+      case 'stores':
+        if (key.name === 'enter') this.focus = 'documents';
+        else if (key.char === ' ') this.toggleMark();
+        else if (key.name === 'char') {
+          // Typing starts filtering the documents of the store
+          this.focus = 'documents';
+          this.handleKey(key);
+        } else this.navigate('stores', key);
+        return;
+      case 'documents':
+        if (key.name === 'char') this.setFilter(this.documentFilter + key.char);
+        else if (key.name === 'backspace') this.setFilter([...this.documentFilter].slice(0, -1).join(''));
+        else if (key.name === 'escape' || key.name === 'ctrl-u') this.setFilter('');
+        else if (key.name === 'enter') this.showDocument();
+        else if (key.name === 'ctrl-o') this.openDocument();
+        else this.navigate('documents', key);
+        return;
+      case 'query':
+        if (key.name === 'char') this.question += key.char;
+        else if (key.name === 'backspace') this.question = [...this.question].slice(0, -1).join('');
+        else if (key.name === 'escape' || key.name === 'ctrl-u') this.question = '';
+        else if (key.name === 'up' && this.asked.length > 0) this.question = this.asked[this.asked.length - 1];
+        else if (key.name === 'enter') this.ask();
+        return;
+      case 'answer':
+        this.navigate('answer', key);
+        return;
+      case 'citations':
+        if (key.name === 'enter' || key.name === 'ctrl-o') this.openCitation();
+        else this.navigate('citations', key);
+        return;
+    }
+  }
+
+  private navigate(pane: TuiPane, key: Key): void {
+    const page = Math.max(1, (this.layout.get(pane)?.height || 10) - 3);
+    const steps: { [name: string]: number } = { up: -1, down: 1, pageup: -page, pagedown: page, home: -Infinity, end: Infinity };
+    if (steps[key.name] !== undefined) this.move(pane, steps[key.name]);
+  }
+
+  private move(pane: TuiPane, delta: number): void {
+    const clamp = (value: number, count: number) => Math.max(0, Math.min(value, count - 1));
+    if (pane === 'stores') {
+      this.selectStore(clamp(this.storeIndex + delta, this.stores.length));
+    } else if (pane === 'documents') {
+      this.documentIndex = clamp(this.documentIndex + delta, this.visibleDocuments().length);
+    } else if (pane === 'citations') {
+      this.citationIndex = clamp(this.citationIndex + delta, this.citations.length);
+    } else if (pane === 'answer') {
+      const scroll = Math.max(0, Math.min((this.answerScroll ?? this.answerMaxScroll) + delta, this.answerMaxScroll));
+      this.answerScroll = scroll >= this.answerMaxScroll ? undefined : scroll;
+    }
+  }
+
+  private toggleMark(): void {
+    const store = this.store;
+    if (!store) return;
+    if (this.marked.has(store.name)) this.marked.delete(store.name);
+    else this.marked.add(store.name);
+  }
+
+  private setFilter(filter: string): void {
+    this.documentFilter = filter;
+    this.documentIndex = 0;
+  }
+
+  private paneAt(x: number, y: number): TuiPane | undefined {
+    for (const [pane, rect] of this.layout) {
+      if (x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height) return pane;
+    }
+    return undefined;
+  }
+
+  // Focuses the pane and selects the row under the pointer; a citation also opens
+  private click(x: number, y: number): void {
+    const pane = this.paneAt(x, y);
+    const rect = pane ? this.layout.get(pane) : undefined;
+    if (!pane || !rect) return;
+    this.focus = pane;
+    // The documents pane has its filter on the first row
+    const row = y - rect.y - 1 - (pane === 'documents' ? 1 : 0);
+    if (row < 0) return;
+    const index = (this.offsets[pane] || 0) + row;
+    if (pane === 'stores' && index < this.stores.length) {
+      this.selectStore(index);
+    } else if (pane === 'documents' && index < this.visibleDocuments().length) {
+      this.documentIndex = index;
+    } else if (pane === 'citations' && index < this.citations.length) {
+      this.citationIndex = index;
+      this.openCitation();
+    }
+  }
+
+  private showDocument(): void {
+    const entry = this.visibleDocuments()[this.documentIndex];
+    if (!entry) return;
+    const local = this.localFile(entry.document);
+    this.answerTitle = entry.document.displayName || entry.document.name;
+    this.answer = `${formatDocumentMarkdown(entry.document)}${local ? `\nLocal file: ${local} (Ctrl-O opens it)\n` : ''}`;
+    this.answerScroll = 0;
+    this.focus = 'answer';
+  }
+
+  // The file a document was ingested from, under the first source root that has it
+  private localFile(document: DocumentInfo): string | undefined {
+    const relativePath = (document.displayName || '').split('#')[0];
+    if (!relativePath) return undefined;
+    for (const root of this.options.sourceRoots) {
+      const file = path.resolve(root, relativePath);
+      const relative = path.relative(path.resolve(root), file);
+      if (!relative.startsWith('..') && !path.isAbsolute(relative) && fs.existsSync(file)) return file;
+    }
+    return undefined;
+  }
+
+  private openDocument(): void {
+    const entry = this.visibleDocuments()[this.documentIndex];
+    if (!entry) return;
+    const local = this.localFile(entry.document);
+    this.status = local
+      ? openFile(local, 1, () => this.suspend(), () => this.resume())
+      : `${entry.document.displayName} is not under ${this.options.sourceRoots.join(', ')}; pass --source-root`;
+  }
+
+  private openCitation(): void {
+    const citation = this.citations[this.citationIndex];
+    if (!citation) return;
+    this.status = citation.context
+      ? openFile(citation.context.file, citation.context.startLine, () => this.suspend(), () => this.resume())
+      : `${citation.fileName} is not under ${this.options.sourceRoots.join(', ')}; pass --source-root`;
+  }
+
+  private async ask(): Promise<void> {
+    const question = this.question.trim();
+    const stores = this.targetStores();
+    if (!question || this.streaming) return;
+    if (stores.length === 0) {
+      this.status = 'No store to ask';
+      return;
+    }
+    const streaming = { cancelled: false };
+    this.streaming = streaming;
+    this.asked.push(question);
+    this.question = '';
+    this.answerTitle = question;
+    this.answer = '';
+    this.answerScroll = undefined;
+    this.citations = [];
+    this.citationIndex = 0;
+    this.status = `Asking ${stores.map(store => store.displayName || store.name).join(', ')}… (Esc stops)`;
+    this.render();
+
+    const storeNames = stores.map(store => store.name);
+    const options: QueryOptions = {
+      ...this.options.query,
+      systemInstruction: this.options.prompt ? resolveSystemInstruction(this.options.prompt, storeNames, this.options.system) : this.options.system,
+    };
+    const model = options.model || this.client.model;
+    const startedAt = Date.now();
+    try {
+      let done: DoneEvent | undefined;
+      for await (const event of this.client.queryStream(storeNames, question, options)) {
+        if (streaming.cancelled) break;
+        if (event.type === 'text') {
+          this.answer += event.text;
+          this.render();
+        } else if (event.type === 'done') {
+          done = event;
+        }
+      }
+      if (streaming.cancelled) {
+        this.status = 'Stopped';
+      } else if (done?.blocked) {
+        this.answer = `⛔ ${formatBlockedAnswer(done.blocked)}`;
+        this.status = 'Blocked';
+      } else {
+        this.citations = addCitationContext(extractCitations(done?.groundingMetadata, this.answer), {
+          sourceRoots: this.options.sourceRoots,
+          lines: this.options.contextLines ?? DEFAULT_CONTEXT_LINES,
+        });
+        const usage = done?.usage ? `, ${formatUsage(model, done.usage)}` : '';
+        this.status = `${((Date.now() - startedAt) / 1000).toFixed(1)}s, ${this.citations.length} citations${usage}`;
+      }
+    } catch (error) {
+      const err = toFileSearchError(error);
+      this.status = `❌ [${err.code}] ${err.message}`;
+    } finally {
+      this.streaming = undefined;
+    }
+    this.render();
+  }
+
+  // Scrolls a list just enough to keep the selected row in view
+  private scrolled(pane: TuiPane, selected: number, count: number, height: number): number {
+    let offset = this.offsets[pane] || 0;
+    if (selected < offset) offset = selected;
+    if (selected >= offset + height) offset = selected - height + 1;
+    offset = Math.max(0, Math.min(offset, count - height));
+    this.offsets[pane] = offset;
+    return offset;
+  }
+
+  private render(): void {
+    if (!this.running) return;
+    const width = Math.max(this.output.columns || 80, 40);
+    const height = Math.max(this.output.rows || 24, 12);
+    const leftWidth = Math.max(24, Math.floor(width * 0.35));
+    const rightWidth = width - leftWidth;
+    const bodyHeight = height - 1;  // The last row is the status bar
+    const storesHeight = Math.max(5, Math.floor(bodyHeight * 0.35));
+    const queryHeight = 3;
+    const citationsHeight = Math.max(4, Math.min(10, Math.floor(bodyHeight * 0.3)));
+    const rects: Record<TuiPane, Rect> = {
+      stores: { x: 1, y: 1, width: leftWidth, height: storesHeight },
+      documents: { x: 1, y: 1 + storesHeight, width: leftWidth, height: bodyHeight - storesHeight },
+      query: { x: leftWidth + 1, y: 1, width: rightWidth, height: queryHeight },
+      answer: { x: leftWidth + 1, y: 1 + queryHeight, width: rightWidth, height: bodyHeight - queryHeight - citationsHeight },
+      citations: { x: leftWidth + 1, y: 1 + bodyHeight - citationsHeight, width: rightWidth, height: citationsHeight },
+    };
+    this.layout = new Map(PANES.map(pane => [pane, rects[pane]] as [TuiPane, Rect]));
+
+    const left = [...this.renderStores(rects.stores), ...this.renderDocuments(rects.documents)];
+    const right = [...this.renderQuery(rects.query), ...this.renderAnswer(rects.answer), ...this.renderCitations(rects.citations)];
+    const hint = HINTS[this.focus];
+    const statusWidth = Math.max(0, width - displayWidth(hint) - 3);
+    const statusBar = `${ANSI.inverse}${fitText(` ${this.status}`, statusWidth)}${ANSI.inverse} ${fitText(hint, width - statusWidth - 2)} ${ANSI.reset}`;
+    const rows = [...left.map((line, i) => `${line}${right[i] ?? ''}`), statusBar];
+    this.output.write(rows.map((row, i) => `\x1b[${i + 1};1H${row}`).join(''));
+  }
+
+  private renderStores(rect: Rect): string[] {
+    const inner = rect.width - 2;
+    const rows = rect.height - 2;
+    const focused = this.focus === 'stores';
+    const offset = this.scrolled('stores', this.storeIndex, this.stores.length, rows);
+    const lines = this.stores.slice(offset, offset + rows).map((store, i) => {
+      const count = String(store.activeDocuments);
+      const name = `${this.marked.has(store.name) ? '●' : ' '} ${store.displayName || store.name}`;
+      const plain = `${fitText(name, Math.max(0, inner - count.length - 1))} ${count}`;
+      return listRow(`${fitText(name, Math.max(0, inner - count.length - 1))} ${gray(count)}`, plain, inner, offset + i === this.storeIndex, focused);
+    });
+    if (this.stores.length === 0) lines.push(gray(this.storesLoaded ? 'No stores; create one with npm run ingest' : 'Loading…'));
+    return box(`Stores (${this.stores.length})`, lines, rect, focused);
+  }
+
+  private renderDocuments(rect: Rect): string[] {
+    const inner = rect.width - 2;
+    const rows = rect.height - 3;
+    const focused = this.focus === 'documents';
+    const documents = this.store ? this.documents.get(this.store.name) : undefined;
+    const cursor = focused ? `${ANSI.inverse} ${ANSI.reset}` : '';
+    const lines = [`${ANSI.cyan}/${ANSI.reset} ${tailText(this.documentFilter, inner - 3)}${cursor}`];
+    let title = 'Documents';
+    if (documents === 'loading') {
+      lines.push(gray('Loading…'));
+    } else if (documents instanceof Error) {
+      lines.push(`${ANSI.red}${documents.message}${ANSI.reset}`);
+    } else if (!documents) {
+      lines.push(gray('Select a store'));
+    } else {
+      const entries = this.visibleDocuments();
+      title = `Documents (${this.documentFilter ? `${entries.length}/` : ''}${documents.length})`;
+      this.documentIndex = Math.max(0, Math.min(this.documentIndex, entries.length - 1));
+      const offset = this.scrolled('documents', this.documentIndex, entries.length, rows);
+      lines.push(...entries.slice(offset, offset + rows).map(({ document, match }, i) => {
+        const name = document.displayName || document.name;
+        const state = document.state !== 'ACTIVE' ? ` ${document.state}` : '';
+        return listRow(`${highlight(name, match.positions)}${state ? `${ANSI.yellow}${state}${ANSI.reset}` : ''}`, `${name}${state}`, inner, offset + i === this.documentIndex, focused);
+      }));
+      if (entries.length === 0) lines.push(gray(documents.length > 0 ? 'No match' : 'No documents'));
+    }
+    return box(title, lines, rect, focused);
+  }
+
+  private renderQuery(rect: Rect): string[] {
+    const inner = rect.width - 2;
+    const focused = this.focus === 'query';
+    const targets = this.targetStores().map(store => store.displayName || store.name).join(', ');
+    const cursor = focused ? `${ANSI.inverse} ${ANSI.reset}` : '';
+    const placeholder = !this.question && !focused ? gray('Tab here and type a question') : '';
+    return box(`Ask ${targets || '(no store)'}`, [`${ANSI.cyan}>${ANSI.reset} ${tailText(this.question, inner - 3)}${cursor}${placeholder}`], rect, focused);
+  }
+
+  private renderAnswer(rect: Rect): string[] {
+    const inner = rect.width - 2;
+    const rows = rect.height - 2;
+    // With the citations focused, the pane shows the highlighted one's chunk
+    const preview = this.focus === 'citations' ? this.citations[this.citationIndex] : undefined;
+    if (preview) {
+      const location = preview.context ? formatContextLocation(preview.context) : preview.fileName;
+      const lines = preview.context
+        ? [`${preview.fileName}${formatCitationFile(preview)}`, '', ...formatCitationContext(preview.context).split('\n')]
+        : [...wrapText(`${preview.fileName}${formatCitationFile(preview)}`, inner), '', ...wrapText(preview.chunkText, inner), '', gray('Not found locally; pass --source-root to show and open the file')];
+      return box(`[${preview.index}] ${location}`, lines, rect, false);
+    }
+    const lines = this.answer ? wrapText(this.answer, inner) : [gray('Answers stream here; Enter on a document shows its details')];
+    this.answerMaxScroll = Math.max(0, lines.length - rows);
+    const scroll = Math.min(this.answerScroll ?? this.answerMaxScroll, this.answerMaxScroll);
+    const position = lines.length > rows ? ` ${scroll + rows}/${lines.length}` : '';
+    return box(`${this.answerTitle}${position}`, lines.slice(scroll, scroll + rows), rect, this.focus === 'answer');
+  }
+
+  private renderCitations(rect: Rect): string[] {
+    const inner = rect.width - 2;
+    const rows = rect.height - 2;
+    const focused = this.focus === 'citations';
+    const offset = this.scrolled('citations', this.citationIndex, this.citations.length, rows);
+    const lines = this.citations.slice(offset, offset + rows).map((citation, i) => {
+      const location = citation.context ? `  ${formatContextLocation(citation.context)}` : '';
+      return listRow(`${ANSI.cyan}[${citation.index}]${ANSI.reset} ${citation.fileName}${gray(location)}`, `[${citation.index}] ${citation.fileName}${location}`, inner, offset + i === this.citationIndex, focused);
+    });
+    if (this.citations.length === 0) lines.push(gray(this.streaming ? 'Waiting for the answer…' : 'Citations of the answer'));
+    return box(`Citations (${this.citations.length})`, lines, rect, focused);
+  }
+}
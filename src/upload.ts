@@ -0,0 +1,230 @@
+// upload.ts - Resumable chunked uploads with sessions persisted to disk
+import { ChunkingConfig, CustomMetadata, UploadToFileSearchStoreOperation } from '@google/genai';
+import * as fs from 'fs';
+import * as path from 'path';
+import { HttpStatusError } from './errors.js';
+import { withFileLock } from './lock.js';
+import { writeFileAtomic } from './shutdown.js';
+import { AuthProvider } from './auth.js';
+import { ApiOptions, DEFAULT_API_VERSION } from './backend.js';
+import { ResumableUploadStartRequest } from './models.js';
+import { withTimeout } from './timeouts.js';
+
+// Types
+interface UploadSession {
+  uploadUrl: string;
+  storeName: string;
+  filePath: string;
+  size: number;
+  mtimeMs: number;
+  createdAt: string;
+}
+
+interface UploadSessionsData {
+  sessions: { [key: string]: UploadSession };
+}
+
+export interface ResumableUploadOptions {
+  displayName?: string;
+  mimeType?: string;
+  resume?: boolean;
+  customMetadata?: CustomMetadata[];
+  chunkingConfig?: ChunkingConfig;
+  signal?: AbortSignal;
+  timeout?: number;  // Milliseconds per request: the start, a status query or a chunk
+}
+
+// Files above this size go through the resumable protocol
+export const RESUMABLE_THRESHOLD = 50 * 1024 * 1024;
+
+// Chunks must be multiples of 256 KiB except for the final one
+const CHUNK_SIZE = 8 * 1024 * 1024;
+const UPLOAD_BASE_URL = 'https://generativelanguage.googleapis.com';
+
+export class UploadSessionStore {
+  constructor(private readonly filePath: string) {}
+
+  get(key: string): UploadSession | undefined {
+    return this.load().sessions[key];
+  }
+
+  // Read and written under the file's lock, as concurrent uploads share the file
+  set(key: string, session: UploadSession): void {
+    withFileLock(this.filePath, () => {
+      const data = this.load();
+      data.sessions[key] = session;
+      this.save(data);
+    });
+  }
+
+  delete(key: string): void {
+    withFileLock(this.filePath, () => {
+      const data = this.load();
+      if (data.sessions[key]) {
+        delete data.sessions[key];
+        this.save(data);
+      }
+    });
+  }
+
+  private load(): UploadSessionsData {
+    try {
+      return JSON.parse(fs.readFileSync(this.filePath, 'utf8')) as UploadSessionsData;
+    } catch (error) {
+      return { sessions: {} };
+    }
+  }
+
+  private save(data: UploadSessionsData): void {
+    writeFileAtomic(this.filePath, JSON.stringify(data, null, 2));
+  }
+}
+
+export class ResumableUploader {
+  constructor(
+    private readonly auth: AuthProvider,
+    private readonly sessions: UploadSessionStore,
+    private readonly baseUrl: string = UPLOAD_BASE_URL,
+    private readonly api: ApiOptions = {}
+  ) {}
+
+  // Custom headers go first, so they never replace the credentials
+  private async requestHeaders(url: string): Promise<{ [name: string]: string }> {
+    return { ...this.api.headers, ...(await this.auth.requestHeaders(url)) };
+  }
+
+  async upload(storeName: string, filePath: string, options: ResumableUploadOptions = {}): Promise<UploadToFileSearchStoreOperation> {
+    const stats = fs.statSync(filePath);
+    const key = `${storeName}|${path.resolve(filePath)}`;
+    const mimeType = options.mimeType || 'application/octet-stream';
+
+    let uploadUrl: string | undefined;
+    let offset = 0;
+
+    // Continue a persisted session only if the file is unchanged since it was started
+    const existing = options.resume ? this.sessions.get(key) : undefined;
+    if (existing && existing.size === stats.size && existing.mtimeMs === stats.mtimeMs) {
+      const received = await this.queryOffset(existing.uploadUrl, options);
+      if (received !== undefined) {
+        uploadUrl = existing.uploadUrl;
+        offset = received;
+        console.error(`🔁 Resuming upload: ${path.basename(filePath)} at ${offset}/${stats.size} bytes`);
+      }
+    }
+
+    if (!uploadUrl) {
+      uploadUrl = await this.startSession(storeName, stats.size, mimeType, options);
+      this.sessions.set(key, {
+        uploadUrl,
+        storeName,
+        filePath: path.resolve(filePath),
+        size: stats.size,
+        mtimeMs: stats.mtimeMs,
+        createdAt: new Date().toISOString(),
+      });
+    }
+
+    const handle = await fs.promises.open(filePath, 'r');
+    try {
+      while (offset < stats.size) {
+        options.signal?.throwIfAborted();
+        const chunkSize = Math.min(CHUNK_SIZE, stats.size - offset);
+        const isLast = offset + chunkSize >= stats.size;
+        const buffer = Buffer.alloc(chunkSize);
+        const { bytesRead } = await handle.read(buffer, 0, chunkSize, offset);
+        if (bytesRead !== chunkSize) {
+          throw new Error(`Short read at offset ${offset}: expected ${chunkSize} bytes, got ${bytesRead}`);
+        }
+
+        const headers = {
+          ...(await this.requestHeaders(uploadUrl)),
+          'X-Goog-Upload-Command': isLast ? 'upload, finalize' : 'upload',
+          'X-Goog-Upload-Offset': String(offset),
+          'Content-Length': String(chunkSize),
+        };
+        const response = await withTimeout('upload', options.timeout, signal => fetch(uploadUrl!, {
+          method: 'POST',
+          headers,
+          body: buffer,
+          signal,
+        }), options.signal);
+
+        // The session stays on disk so a later run can pick up from the last acknowledged chunk
+        if (!response.ok) {
+          throw new HttpStatusError(
+            `Chunk upload failed at offset ${offset}: HTTP ${response.status} ${await response.text()}`,
+            response.status,
+            response.headers.get('retry-after')
+          );
+        }
+
+        offset += chunkSize;
+        const status = response.headers.get('x-goog-upload-status');
+        if (status === 'final') {
+          this.sessions.delete(key);
+          const operation = new UploadToFileSearchStoreOperation();
+          Object.assign(operation, await response.json());
+          return operation;
+        }
+        if (status !== 'active') {
+          throw new Error(`Upload session ended unexpectedly with status "${status}"`);
+        }
+      }
+    } finally {
+      await handle.close();
+    }
+
+    throw new Error('All content has been uploaded, but the upload status is not finalized');
+  }
+
+  private async startSession(storeName: string, size: number, mimeType: string, options: ResumableUploadOptions): Promise<string> {
+    const url = `${this.baseUrl.replace(/\/+$/, '')}/upload/${this.api.version || DEFAULT_API_VERSION}/${storeName}:uploadToFileSearchStore`;
+    const body: ResumableUploadStartRequest = {
+      displayName: options.displayName,
+      customMetadata: options.customMetadata,
+      chunkingConfig: options.chunkingConfig,
+    };
+    const headers = {
+      ...(await this.requestHeaders(url)),
+      'Content-Type': 'application/json',
+      'X-Goog-Upload-Protocol': 'resumable',
+      'X-Goog-Upload-Command': 'start',
+      'X-Goog-Upload-Header-Content-Length': String(size),
+      'X-Goog-Upload-Header-Content-Type': mimeType,
+    };
+    // Bounded like each chunk, so a stalled start fails instead of hanging the upload
+    const response = await withTimeout('upload', options.timeout, signal => fetch(url, {
+      method: 'POST',
+      headers,
+      body: JSON.stringify(body),
+      signal,
+    }), options.signal);
+
+    const uploadUrl = response.headers.get('x-goog-upload-url');
+    if (!response.ok || !uploadUrl) {
+      throw new HttpStatusError(
+        `Failed to start resumable upload: HTTP ${response.status} ${await response.text()}`,
+        response.status,
+        response.headers.get('retry-after')
+      );
+    }
+    return uploadUrl;
+  }
+
+  // Returns the number of bytes the server has already received, or undefined if the session is gone
+  private async queryOffset(uploadUrl: string, options: ResumableUploadOptions): Promise<number | undefined> {
+    try {
+      const headers = {
+        ...(await this.requestHeaders(uploadUrl)),
+        'X-Goog-Upload-Command': 'query',
+      };
+      const response = await withTimeout('upload', options.timeout, signal => fetch(uploadUrl, { method: 'POST', headers, signal }), options.signal);
+      if (!response.ok || response.headers.get('x-goog-upload-status') !== 'active') {
+        return undefined;
+      }
+      return Number(response.headers.get('x-goog-upload-size-received') || 0);
+    } catch (error) {
+      return undefined;
+    }
+  }
+}
@@ -0,0 +1,236 @@
+// vertex.ts - Vertex AI RAG Engine transport: RAG corpora in place of File Search stores
+import { Document, DocumentState, FileSearchStore } from '@google/genai';
+import * as fs from 'fs';
+import * as path from 'path';
+import { AuthProvider } from './auth.js';
+import { ApiOptions, BackendConfig, DEFAULT_VERTEX_API_VERSION, DEFAULT_VERTEX_LOCATION } from './backend.js';
+import { ChunkingConfig } from './chunking.js';
+import { HttpStatusError } from './errors.js';
+import {
+  CreateRagCorpusRequest,
+  ListRagCorporaResponse,
+  ListRagFilesResponse,
+  RagContext,
+  RagCorpus,
+  RagFile,
+  RagFileState,
+  UploadRagFileMetadata,
+  RetrieveContextsRequest,
+  RetrieveContextsResponse,
+  UploadRagFileResponse,
+  VertexOperation,
+} from './models.js';
+import { PollOptions, pollUntilDone } from './operations.js';
+import { Page } from './pagination.js';
+import { RetryOptions, withRetry } from './retry.js';
+
+// Types
+export interface VertexUploadOptions {
+  displayName?: string;
+  chunking?: ChunkingConfig;
+}
+
+const FILE_STATES: { [state in RagFileState]?: DocumentState } = {
+  ACTIVE: DocumentState.STATE_ACTIVE,
+  ERROR: DocumentState.STATE_FAILED,
+};
+
+function toDocument(file: RagFile): Document {
+  return {
+    name: file.name,
+    displayName: file.displayName,
+    state: FILE_STATES[file.fileStatus?.state || 'STATE_UNSPECIFIED'] || DocumentState.STATE_PENDING,
+    sizeBytes: file.sizeBytes,
+    createTime: file.createTime,
+    updateTime: file.updateTime,
+  };
+}
+
+// Resource names are "projects/<p>/locations/<l>/ragCorpora/<id>" and ".../ragFiles/<id>".
+// Requests carry the OAuth token of the auth provider; API keys are not accepted.
+export class VertexRagTransport {
+  private readonly host: string;
+  private readonly parent: string;
+
+  constructor(
+    backend: BackendConfig,
+    private readonly auth: AuthProvider,
+    private readonly retry: RetryOptions = {},
+    private readonly api: ApiOptions = {}
+  ) {
+    const location = backend.location || DEFAULT_VERTEX_LOCATION;
+    this.host = location === 'global' ? 'https://aiplatform.googleapis.com' : `https://${location}-aiplatform.googleapis.com`;
+    this.parent = `projects/${backend.project}/locations/${location}`;
+  }
+
+  private get version(): string {
+    return this.api.version || DEFAULT_VERTEX_API_VERSION;
+  }
+
+  private async request<T>(method: string, resource: string, body?: unknown, signal?: AbortSignal): Promise<T> {
+    const url = `${this.host}/${this.version}/${resource}`;
+    const response = await fetch(url, {
+      method,
+      headers: {
+        ...this.api.headers,
+        ...(await this.auth.requestHeaders(url)),
+        ...(body !== undefined ? { 'Content-Type': 'application/json' } : {}),
+      },
+      body: body !== undefined ? JSON.stringify(body) : undefined,
+      signal,
+    });
+    if (!response.ok) {
+      throw new HttpStatusError(
+        `Vertex AI ${method} ${resource} failed: HTTP ${response.status} ${await response.text()}`,
+        response.status,
+        response.headers.get('retry-after')
+      );
+    }
+    return (await response.json()) as T;
+  }
+
+  private async waitForOperation(operation: VertexOperation, options: PollOptions = {}): Promise<VertexOperation> {
+    return pollUntilDone(operation, pending => this.request<VertexOperation>('GET', pending.name), { retry: this.retry, ...options });
+  }
+
+  private async listAll<T, P extends { nextPageToken?: string }>(resource: string, items: (page: P) => T[] | undefined): Promise<T[]> {
+    const all: T[] = [];
+    let pageToken: string | undefined;
+    do {
+      const page = await this.listPage(resource, items, pageToken);
+      all.push(...page.items);
+      pageToken = page.nextPageToken;
+    } while (pageToken);
+    return all;
+  }
+
+  private async listPage<T, P extends { nextPageToken?: string }>(
+    resource: string,
+    items: (page: P) => T[] | undefined,
+    pageToken?: string,
+    pageSize?: number
+  ): Promise<Page<T>> {
+    const params = new URLSearchParams();
+    if (pageToken) params.set('pageToken', pageToken);
+    if (pageSize) params.set('pageSize', String(pageSize));
+    const query = params.toString() ? `?${params}` : '';
+    const page = await withRetry(`list ${resource.split('/').pop()}`, () => this.request<P>('GET', `${resource}${query}`), this.retry);
+    return { items: items(page) || [], nextPageToken: page.nextPageToken || undefined };
+  }
+
+  async createCorpus(displayName: string): Promise<FileSearchStore> {
+    const body: CreateRagCorpusRequest = { displayName };
+    const operation = await this.request<VertexOperation>('POST', `${this.parent}/ragCorpora`, body);
+    const done = await this.waitForOperation(operation);
+    return done.response as RagCorpus;
+  }
+
+  async listCorpora(): Promise<FileSearchStore[]> {
+    return this.listAll(`${this.parent}/ragCorpora`, (page: ListRagCorporaResponse) => page.ragCorpora);
+  }
+
+  async listCorporaPage(pageToken?: string, pageSize?: number): Promise<Page<FileSearchStore>> {
+    return this.listPage(`${this.parent}/ragCorpora`, (page: ListRagCorporaResponse) => page.ragCorpora, pageToken, pageSize);
+  }
+
+  // Corpora carry no document counts; they are computed from the file list
+  async getCorpus(name: string): Promise<FileSearchStore> {
+    const corpus = await withRetry('get corpus', () => this.request<RagCorpus>('GET', name), this.retry);
+    const files = await this.listFiles(name);
+    const count = (state: DocumentState) => String(files.filter(file => file.state === state).length);
+    return {
+      ...corpus,
+      activeDocumentsCount: count(DocumentState.STATE_ACTIVE),
+      pendingDocumentsCount: count(DocumentState.STATE_PENDING),
+      failedDocumentsCount: count(DocumentState.STATE_FAILED),
+      sizeBytes: String(files.reduce((sum, file) => sum + Number(file.sizeBytes || 0), 0)),
+    };
+  }
+
+  async deleteCorpus(name: string, force: boolean = true): Promise<void> {
+    const operation = await this.request<VertexOperation>('DELETE', `${name}?force=${force}`);
+    await this.waitForOperation(operation);
+  }
+
+  async listFiles(corpusName: string): Promise<Document[]> {
+    return (await this.listAll(`${corpusName}/ragFiles`, (page: ListRagFilesResponse) => page.ragFiles)).map(toDocument);
+  }
+
+  async listFilesPage(corpusName: string, pageToken?: string, pageSize?: number): Promise<Page<Document>> {
+    const page = await this.listPage(`${corpusName}/ragFiles`, (response: ListRagFilesResponse) => response.ragFiles, pageToken, pageSize);
+    return { items: page.items.map(toDocument), nextPageToken: page.nextPageToken };
+  }
+
+  async getFile(name: string): Promise<Document> {
+    return toDocument(await withRetry('get file', () => this.request<RagFile>('GET', name), this.retry));
+  }
+
+  async deleteFile(name: string): Promise<void> {
+    const operation = await this.request<VertexOperation>('DELETE', name);
+    await this.waitForOperation(operation);
+  }
+
+  // The contexts of the corpora closest to the text, ranked by the vector search alone
+  async retrieveContexts(
+    corpusNames: string[],
+    text: string,
+    options: { topK?: number; metadataFilter?: string; signal?: AbortSignal } = {}
+  ): Promise<RagContext[]> {
+    const body: RetrieveContextsRequest = {
+      vertexRagStore: { ragResources: corpusNames.map(ragCorpus => ({ ragCorpus })) },
+      query: {
+        text,
+        ragRetrievalConfig: {
+          topK: options.topK,
+          ...(options.metadataFilter ? { filter: { metadataFilter: options.metadataFilter } } : {}),
+        },
+      },
+    };
+    const response = await withRetry('retrieve contexts', () => this.request<RetrieveContextsResponse>('POST', `${this.parent}:retrieveContexts`, body, options.signal), this.retry);
+    return response.contexts?.contexts || [];
+  }
+
+  // Direct upload indexes the file synchronously; there is no operation to poll.
+  // Custom metadata is not supported by RAG Engine uploads.
+  async uploadFile(corpusName: string, filePath: string, options: VertexUploadOptions = {}): Promise<string | undefined> {
+    const metadata: UploadRagFileMetadata = {
+      rag_file: { display_name: options.displayName || path.basename(filePath) },
+    };
+    if (options.chunking?.maxTokensPerChunk !== undefined) {
+      metadata.upload_rag_file_config = {
+        rag_file_transformation_config: {
+          rag_file_chunking_config: {
+            fixed_length_chunking: {
+              chunk_size: options.chunking.maxTokensPerChunk,
+              chunk_overlap: options.chunking.maxOverlapTokens || 0,
+            },
+          },
+        },
+      };
+    }
+
+    const form = new FormData();
+    form.append('metadata', JSON.stringify(metadata));
+    form.append('file', new Blob([fs.readFileSync(filePath)]), path.basename(filePath));
+
+    const url = `${this.host}/upload/${this.version}/${corpusName}/ragFiles:upload`;
+    const response = await fetch(url, {
+      method: 'POST',
+      headers: {
+        ...this.api.headers,
+        ...(await this.auth.requestHeaders(url)),
+        'X-Goog-Upload-Protocol': 'multipart',
+      },
+      body: form,
+    });
+    const result = (await response.json().catch(() => ({}))) as UploadRagFileResponse;
+    if (!response.ok || result.error) {
+      throw new HttpStatusError(
+        `Vertex AI upload of ${path.basename(filePath)} failed: HTTP ${response.status} ${result.error?.message || ''}`.trim(),
+        response.ok ? 400 : response.status,
+        response.headers.get('retry-after')
+      );
+    }
+    return result.ragFile?.name;
+  }
+}
@@ -0,0 +1,145 @@
+// watch.ts - Continuous indexing: syncs a directory to its store as files change
+import * as fs from 'fs';
+import * as path from 'path';
+import { FileSearchClient } from './client.js';
+import { DirectoryIngester, isExcludedByDefault } from './ingest.js';
+import { SyncManifest, SyncSummary, planSync, applySync, reconcileSync } from './sync.js';
+
+// Types
+export interface WatchOptions {
+  debounceMs?: number;  // Quiet period after the last change before syncing (default: 2000)
+  canSync?: () => boolean;  // Checked before each sync, e.g. to wait for a running upload job
+  onSync?: (summary: SyncSummary, manifest: SyncManifest) => void | Promise<void>;
+}
+
+export interface WatchStatus {
+  rootDir: string;
+  storeName: string;
+  startedAt: string;
+  syncing: boolean;
+  pendingChanges: number;
+  syncCount: number;
+  lastSyncAt?: string;
+  lastSummary?: SyncSummary;
+  lastError?: string;
+}
+
+const DEFAULT_DEBOUNCE_MS = 2000;
+
+// Change events are collected until the directory has been quiet for debounceMs,
+// then a single incremental sync uploads new and changed files and removes deleted ones.
+// Syncs never overlap: changes that arrive during a sync trigger another one afterwards.
+export class DirectoryWatcher {
+  private watcher?: fs.FSWatcher;
+  private timer?: NodeJS.Timeout;
+  private running?: Promise<void>;
+  private rerun = false;
+  private readonly pending = new Set<string>();
+  private readonly debounceMs: number;
+  private readonly state: WatchStatus;
+
+  constructor(
+    private readonly client: FileSearchClient,
+    private readonly storeName: string,
+    private readonly rootDir: string,
+    private readonly ingester: DirectoryIngester,
+    private readonly options: WatchOptions = {}
+  ) {
+    this.debounceMs = options.debounceMs ?? DEFAULT_DEBOUNCE_MS;
+    this.state = {
+      rootDir,
+      storeName,
+      startedAt: new Date().toISOString(),
+      syncing: false,
+      pendingChanges: 0,
+      syncCount: 0,
+    };
+  }
+
+  get status(): WatchStatus {
+    return { ...this.state, pendingChanges: this.pending.size };
+  }
+
+  start(): void {
+    if (this.watcher) return;
+    this.watcher = fs.watch(this.rootDir, { recursive: true }, (_event, fileName) => this.onChange(fileName));
+    this.watcher.on('error', error => {
+      this.state.lastError = error.message;
+      console.error(`❌ Watch error: ${this.rootDir} - ${error.message}`);
+    });
+    console.error(`👀 Watching ${this.rootDir} (debounce ${this.debounceMs}ms)`);
+  }
+
+  // Stops watching and waits for a sync in progress to finish
+  async stop(): Promise<void> {
+    this.watcher?.close();
+    this.watcher = undefined;
+    clearTimeout(this.timer);
+    this.pending.clear();
+    this.rerun = false;
+    await this.running;
+    console.error(`🛑 Stopped watching ${this.rootDir}`);
+  }
+
+  private onChange(fileName: string | null): void {
+    if (fileName) {
+      const relativePath = fileName.split(path.sep).join('/');
+      // Also skips the sync manifest, which every sync rewrites
+      if (isExcludedByDefault(relativePath)) return;
+      this.pending.add(relativePath);
+    } else {
+      // Some platforms omit the file name; sync anyway
+      this.pending.add('');
+    }
+    clearTimeout(this.timer);
+    this.timer = setTimeout(() => this.flush(), this.debounceMs);
+  }
+
+  private flush(): void {
+    if (!this.watcher) return;
+    if (this.running || (this.options.canSync && !this.options.canSync())) {
+      this.rerun = true;
+      if (!this.running) {
+        this.timer = setTimeout(() => this.flush(), this.debounceMs);
+      }
+      return;
+    }
+
+    this.rerun = false;
+    this.running = this.sync().finally(() => {
+      this.running = undefined;
+      if (this.rerun || this.pending.size > 0) {
+        this.timer = setTimeout(() => this.flush(), this.debounceMs);
+      }
+    });
+  }
+
+  private async sync(): Promise<void> {
+    const changes = this.pending.size;
+    this.pending.clear();
+    this.state.syncing = true;
+    try {
+      const manifest = SyncManifest.load(this.rootDir, this.storeName);
+      await reconcileSync(this.client, this.storeName, this.rootDir, manifest, this.ingester);
+      const plan = planSync(this.rootDir, this.ingester.collect(this.rootDir), manifest);
+      if (plan.added.length + plan.changed.length + plan.removed.length === 0) {
+        await manifest.saveAsync();
+        return;
+      }
+
+      console.error(`🔁 ${changes} change(s) in ${this.rootDir}: ${plan.added.length} new, ${plan.changed.length} changed, ${plan.removed.length} removed`);
+      const summary = await applySync(this.client, this.storeName, this.rootDir, plan, manifest, this.ingester);
+      this.state.syncCount++;
+      this.state.lastSyncAt = new Date().toISOString();
+      this.state.lastSummary = summary;
+      this.state.lastError = undefined;
+      await this.options.onSync?.(summary, manifest);
+    } catch (error) {
+      const err = error as Error;
+      this.state.lastError = err.message;
+      console.error(`❌ Watch sync error: ${this.rootDir} - ${err.message}`);
+    } finally {
+      this.state.syncing = false;
+    }
+  }
+}
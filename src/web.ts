@@ -0,0 +1,212 @@
+// web.ts - Fetches web pages and sitemaps and uploads them to a store as Markdown documents
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { randomUUID } from 'crypto';
+import { FileSearchClient } from './client.js';
+import { DocumentMetadata } from './metadata.js';
+import { HttpStatusError, InvalidInputError, toFileSearchError } from './errors.js';
+import { RetryOptions, withRetry } from './retry.js';
+import { runPipeline } from './pipeline.js';
+import { IngestFailure } from './progress.js';
+import { PendingOperations } from './operations.js';
+import { CiReportWriter, IngestFileResult } from './ci-report.js';
+import { decodeEntities, extractReadable } from './html.js';
+
+// Types
+export interface WebPage {
+  url: string;  // Final URL after redirects
+  title?: string;
+  markdown: string;
+}
+
+export interface Sitemap {
+  urls: string[];  // Page URLs of a <urlset>
+  sitemaps: string[];  // Child sitemaps of a <sitemapindex>
+}
+
+export interface FetchOptions {
+  timeoutMs?: number;  // Per request
+  retry?: RetryOptions;  // Backoff for 429/5xx responses
+}
+
+export interface WebIngestOptions extends FetchOptions {
+  metadata?: DocumentMetadata;  // Added to every page besides source_url
+  concurrency?: number;
+  replace?: boolean;  // Delete earlier documents of the same page after the upload (default: true)
+  signal?: AbortSignal;  // No further page is started once aborted
+  wait?: boolean;  // false: pages are not waited on while they index (default: true)
+  pending?: PendingOperations;  // Where pages that did not wait are kept until a later wait confirms them
+  report?: CiReportWriter;  // Gets the result of every page, see ci-report.ts
+}
+
+export interface WebIngestSummary {
+  totalPages: number;
+  successCount: number;
+  errorCount: number;
+  failures: IngestFailure[];  // Page URLs with the reason
+  documents: { url: string; displayName: string; documentName?: string; operationName?: string }[];
+}
+
+const DEFAULT_TIMEOUT_MS = 30_000;
+const DEFAULT_CONCURRENCY = 3;
+const DEFAULT_MAX_PAGES = 500;
+const USER_AGENT = 'gemini-file-search-mcp-server';
+
+export function parseHttpUrl(value: string): URL {
+  let url: URL;
+  try {
+    url = new URL(value);
+  } catch (error) {
+    throw new InvalidInputError(`Invalid URL: ${value}`);
+  }
+  if (url.protocol !== 'http:' && url.protocol !== 'https:') {
+    throw new InvalidInputError(`Only http and https URLs are supported: ${value}`);
+  }
+  return url;
+}
+
+async function fetchText(url: string, accept: string, options: FetchOptions): Promise<{ text: string; contentType: string; url: string }> {
+  return withRetry(`fetch ${url}`, async () => {
+    const response = await fetch(url, {
+      headers: { 'User-Agent': USER_AGENT, Accept: accept },
+      redirect: 'follow',
+      signal: AbortSignal.timeout(options.timeoutMs || DEFAULT_TIMEOUT_MS),
+    });
+    if (!response.ok) {
+      throw new HttpStatusError(`${url} returned HTTP ${response.status}`, response.status, response.headers.get('retry-after'));
+    }
+    return {
+      text: await response.text(),
+      contentType: (response.headers.get('content-type') || '').split(';')[0].trim().toLowerCase(),
+      url: response.url || url,
+    };
+  }, options.retry);
+}
+
+// HTML is reduced to its main content; Markdown and plain text are kept as they are
+export async function fetchPage(url: string, options: FetchOptions = {}): Promise<WebPage> {
+  parseHttpUrl(url);
+  const response = await fetchText(url, 'text/html,application/xhtml+xml,text/markdown;q=0.9,text/plain;q=0.8', options);
+  const isHtml = response.contentType === 'text/html' || response.contentType === 'application/xhtml+xml'
+    || (!response.contentType && /^\s*(<!doctype html|<html)/i.test(response.text));
+  if (!isHtml) {
+    if (!response.contentType.startsWith('text/')) {
+      throw new InvalidInputError(`${response.url} is ${response.contentType || 'of unknown type'}, not a web page`);
+    }
+    return { url: response.url, markdown: response.text.trim() };
+  }
+  const readable = extractReadable(response.text, response.url);
+  if (!readable.markdown.replace(/^# .*$/m, '').trim()) {
+    throw new InvalidInputError(`No readable content found on ${response.url}`);
+  }
+  return { url: response.url, title: readable.title, markdown: readable.markdown };
+}
+
+export function parseSitemap(xml: string): Sitemap {
+  const locations = (block: string) => [...block.matchAll(/<loc>\s*(?:<!\[CDATA\[)?([\s\S]*?)(?:\]\]>)?\s*<\/loc>/gi)]
+    .map(match => decodeEntities(match[1].trim()))
+    .filter(Boolean);
+  if (/<sitemapindex[\s>]/i.test(xml)) {
+    return { urls: [], sitemaps: locations(xml) };
+  }
+  if (!/<urlset[\s>]/i.test(xml)) {
+    throw new InvalidInputError('Not a sitemap: expected <urlset> or <sitemapindex>');
+  }
+  return { urls: locations(xml), sitemaps: [] };
+}
+
+// Follows sitemap indexes breadth first and returns at most maxPages unique page URLs
+export async function collectSitemapUrls(url: string, options: FetchOptions & { maxPages?: number } = {}): Promise<string[]> {
+  const maxPages = options.maxPages || DEFAULT_MAX_PAGES;
+  const pending = [parseHttpUrl(url).toString()];
+  const visited = new Set<string>();
+  const pages = new Set<string>();
+  while (pending.length > 0 && pages.size < maxPages) {
+    const sitemapUrl = pending.shift()!;
+    if (visited.has(sitemapUrl)) continue;
+    visited.add(sitemapUrl);
+    const sitemap = parseSitemap((await fetchText(sitemapUrl, 'application/xml,text/xml', options)).text);
+    pending.push(...sitemap.sitemaps);
+    for (const page of sitemap.urls) {
+      if (pages.size >= maxPages) break;
+      pages.add(page);
+    }
+  }
+  if (pending.length > 0 || pages.size >= maxPages) {
+    console.error(`⚠️  Sitemap ${url} lists more than ${maxPages} pages; only the first ${maxPages} are ingested`);
+  }
+  return [...pages];
+}
+
+// Host and path, so the same page always gets the same display name
+export function pageDisplayName(url: string): string {
+  const parsed = new URL(url);
+  const pagePath = parsed.pathname.replace(/\/+$/, '');
+  return `${parsed.host}${pagePath}${parsed.search}`.replace(/[<>:"|?*\\]/g, '_');
+}
+
+export async function ingestUrls(
+  client: FileSearchClient,
+  storeName: string,
+  urls: string[],
+  options: WebIngestOptions = {}
+): Promise<WebIngestSummary> {
+  const existing = options.replace !== false ? await client.listDocuments(storeName) : [];
+  const failures: IngestFailure[] = [];
+  const documents: WebIngestSummary['documents'] = [];
+  const startedAt = Date.now();
+  const pageResults = new Map<string, IngestFileResult>();
+
+  await runPipeline(urls, async (url) => {
+    const tempFile = path.join(os.tmpdir(), `gemini-fs-web-${randomUUID()}.md`);
+    const pageStartedAt = Date.now();
+    try {
+      const page = await fetchPage(url, options);
+      const displayName = pageDisplayName(page.url);
+      fs.writeFileSync(tempFile, page.markdown + '\n');
+      const result = await client.uploadFile(storeName, tempFile, {
+        displayName,
+        mimeType: 'text/markdown',
+        metadata: { ...options.metadata, source_url: page.url },
+        signal: options.signal,
+        wait: options.wait,
+      });
+      if (result.operationName) {
+        options.pending?.add({ operation: result.operationName, kind: 'upload', storeName, displayName, createdAt: new Date().toISOString() });
+      }
+      // Only once the new copy is in, so a failed fetch or upload keeps the old one
+      for (const document of existing.filter(doc => doc.displayName === displayName && doc.name)) {
+        await client.deleteDocument(document.name!);
+      }
+      documents.push({ url: page.url, displayName, documentName: result.documentName, operationName: result.operationName });
+      pageResults.set(url, { path: url, status: 'uploaded', durationMs: Date.now() - pageStartedAt, bytes: Buffer.byteLength(page.markdown) + 1, retries: 0 });
+      console.error(`${result.operationName ? '⏳' : '✅'} ${page.url} → ${displayName}`);
+    } catch (error) {
+      const err = toFileSearchError(error);
+      failures.push({ path: url, reason: err.message, code: err.code, retries: 0 });
+      pageResults.set(url, { path: url, status: err.code === 'CANCELLED' ? 'cancelled' : 'failed', durationMs: Date.now() - pageStartedAt, retries: 0, code: err.code, reason: err.message });
+      console.error(`❌ ${url}: ${err.message}`);
+    } finally {
+      fs.rmSync(tempFile, { force: true });
+    }
+  }, { concurrency: options.concurrency || DEFAULT_CONCURRENCY, signal: options.signal });
+
+  // Pages are named by their URL; the root is only used to resolve file paths
+  options.report?.add({
+    name: `ingest ${storeName}`,
+    store: storeName,
+    rootDir: '.',
+    startedAt: new Date(startedAt).toISOString(),
+    durationMs: Date.now() - startedAt,
+    files: urls.map(url => pageResults.get(url) || { path: url, status: 'cancelled', durationMs: 0, retries: 0 }),
+  });
+
+  return {
+    totalPages: urls.length,
+    successCount: documents.length,
+    errorCount: failures.length,
+    failures,
+    documents,
+  };
+}
@@ -0,0 +1,96 @@
+// zip.ts - Minimal ZIP archive reader for stored and deflated entries
+import * as zlib from 'zlib';
+
+// Types
+export interface ZipEntry {
+  name: string;
+  size: number;  // Uncompressed size in bytes
+  compressedSize: number;
+  read(): Buffer;
+}
+
+const END_OF_CENTRAL_DIRECTORY = 0x06054b50;
+const CENTRAL_DIRECTORY_HEADER = 0x02014b50;
+const LOCAL_FILE_HEADER = 0x04034b50;
+
+const FLAG_ENCRYPTED = 0x1;
+const FLAG_UTF8 = 0x800;
+
+// Entries are inflated to at most their declared size, so a crafted entry can't expand
+// past what the archive's sizes were checked against
+function readEntry(data: Buffer, localOffset: number, entry: { name: string; size: number; compressedSize: number; method: number; flags: number }): Buffer {
+  if (entry.flags & FLAG_ENCRYPTED) {
+    throw new Error(`Encrypted ZIP entry: ${entry.name}`);
+  }
+  if (data.readUInt32LE(localOffset) !== LOCAL_FILE_HEADER) {
+    throw new Error(`Corrupt ZIP entry: ${entry.name}`);
+  }
+  // The local header repeats the name and may carry a different extra field
+  const start = localOffset + 30 + data.readUInt16LE(localOffset + 26) + data.readUInt16LE(localOffset + 28);
+  const body = data.subarray(start, start + entry.compressedSize);
+  const tooLarge = () => new Error(`ZIP entry larger than its declared ${entry.size} bytes: ${entry.name}`);
+  let contents: Buffer;
+  switch (entry.method) {
+    case 0:
+      contents = Buffer.from(body);
+      break;
+    case 8:
+      try {
+        // One byte over the declared size is enough to tell the entry lied
+        contents = zlib.inflateRawSync(body, { maxOutputLength: entry.size + 1 });
+      } catch (error) {
+        throw (error as NodeJS.ErrnoException).code === 'ERR_BUFFER_TOO_LARGE' ? tooLarge() : error;
+      }
+      break;
+    default:
+      throw new Error(`Unsupported ZIP compression method ${entry.method}: ${entry.name}`);
+  }
+  if (contents.length > entry.size) throw tooLarge();
+  return contents;
+}
+
+// Lists entries from the central directory; contents are inflated on read()
+export function readZip(data: Buffer): ZipEntry[] {
+  // The end record is 22 bytes plus a comment of up to 64KB
+  let end = -1;
+  for (let i = data.length - 22; i >= Math.max(0, data.length - 22 - 0xffff); i--) {
+    if (data.readUInt32LE(i) === END_OF_CENTRAL_DIRECTORY) {
+      end = i;
+      break;
+    }
+  }
+  if (end === -1) {
+    throw new Error('Not a ZIP archive');
+  }
+
+  const count = data.readUInt16LE(end + 10);
+  let offset = data.readUInt32LE(end + 16);
+  if (offset === 0xffffffff) {
+    throw new Error('ZIP64 archives are not supported');
+  }
+
+  const entries: ZipEntry[] = [];
+  for (let n = 0; n < count; n++) {
+    if (offset + 46 > data.length || data.readUInt32LE(offset) !== CENTRAL_DIRECTORY_HEADER) {
+      throw new Error('Corrupt ZIP central directory');
+    }
+    const flags = data.readUInt16LE(offset + 8);
+    const method = data.readUInt16LE(offset + 10);
+    const compressedSize = data.readUInt32LE(offset + 20);
+    const size = data.readUInt32LE(offset + 24);
+    const nameLength = data.readUInt16LE(offset + 28);
+    const extraLength = data.readUInt16LE(offset + 30);
+    const commentLength = data.readUInt16LE(offset + 32);
+    const localOffset = data.readUInt32LE(offset + 42);
+    const name = data.toString(flags & FLAG_UTF8 ? 'utf8' : 'latin1', offset + 46, offset + 46 + nameLength);
+
+    entries.push({
+      name,
+      size,
+      compressedSize,
+      read: () => readEntry(data, localOffset, { name, size, compressedSize, method, flags }),
+    });
+    offset += 46 + nameLength + extraLength + commentLength;
+  }
+  return entries;
+}